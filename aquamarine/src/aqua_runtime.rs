@@ -24,7 +24,9 @@ use avm_server::{
 };
 use fluence_keypair::KeyPair;
 use libp2p::PeerId;
+use marine::MarineError;
 use marine_wasmtime_backend::WasmtimeWasmBackend;
+use peer_metrics::InterpretationFailureReason;
 use tracing::Level;
 
 use crate::config::VmConfig;
@@ -60,6 +62,9 @@ pub trait AquaRuntime: Sized + Send + 'static {
 
     /// Return current size of memory. Use only for diagnostics purposes.
     fn memory_stats(&self) -> AVMMemoryStats;
+
+    /// Classify an interpretation error for the `interpretation_failures` metric breakdown.
+    fn failure_reason(error: &Self::Error) -> InterpretationFailureReason;
 }
 
 #[async_trait]
@@ -164,6 +169,23 @@ impl AquaRuntime for AVMRunner<WasmtimeWasmBackend> {
     fn memory_stats(&self) -> AVMMemoryStats {
         self.memory_stats()
     }
+
+    fn failure_reason(error: &Self::Error) -> InterpretationFailureReason {
+        match error {
+            RunnerError::MarineError(MarineError::HighProbabilityOOM { .. }) => {
+                InterpretationFailureReason::ResourceExhausted
+            }
+            RunnerError::MarineError(MarineError::EngineError(_)) => {
+                InterpretationFailureReason::WasmTrap
+            }
+            RunnerError::MarineError(_) => InterpretationFailureReason::Other,
+            RunnerError::InterpreterResultDeError(_)
+            | RunnerError::IncorrectInterpreterResult(_) => {
+                InterpretationFailureReason::ScriptError
+            }
+            _ => InterpretationFailureReason::Other,
+        }
+    }
 }
 
 pub fn parse_outcome(
@@ -191,3 +213,41 @@ fn parse_peer_id(s: &str) -> Result<PeerId, FieldError> {
         err: err.to_string(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use avm_server::avm_runner::AVMRunner;
+
+    type AVM = AVMRunner<WasmtimeWasmBackend>;
+
+    #[test]
+    fn interpreter_result_errors_are_script_errors() {
+        let error = RunnerError::InterpreterResultDeError("malformed result".to_string());
+        assert_eq!(
+            AVM::failure_reason(&error),
+            InterpretationFailureReason::ScriptError
+        );
+
+        let error = RunnerError::IncorrectInterpreterResult(vec![]);
+        assert_eq!(
+            AVM::failure_reason(&error),
+            InterpretationFailureReason::ScriptError
+        );
+    }
+
+    #[test]
+    fn unclassified_errors_fall_back_to_other() {
+        let error = RunnerError::Aux("something odd".to_string());
+        assert_eq!(
+            AVM::failure_reason(&error),
+            InterpretationFailureReason::Other
+        );
+
+        let error = RunnerError::MarineError(MarineError::InvalidConfig("bad".to_string()));
+        assert_eq!(
+            AVM::failure_reason(&error),
+            InterpretationFailureReason::Other
+        );
+    }
+}
@@ -29,7 +29,7 @@ use health::HealthCheckRegistry;
 use particle_execution::{ParticleFunctionStatic, ServiceFunction};
 use particle_protocol::ExtendedParticle;
 use particle_services::{PeerScope, WasmBackendConfig};
-use peer_metrics::{ParticleExecutorMetrics, VmPoolMetrics};
+use peer_metrics::{ParticleDataStoreMetrics, ParticleExecutorMetrics, VmPoolMetrics};
 use workers::{Event, KeyStorage, PeerScopes, Receiver, Workers};
 
 use crate::command::Command;
@@ -48,6 +48,9 @@ pub struct AquamarineBackend<RT: AquaRuntime, F> {
     plumber: Plumber<RT, F>,
     out: EffectsChannel,
     data_store: Arc<ParticleDataStore>,
+    /// How long a data file must stay untouched before the periodic
+    /// cleanup removes it
+    cleanup_grace_period: Duration,
 }
 
 impl<RT: AquaRuntime, F: ParticleFunctionStatic> AquamarineBackend<RT, F> {
@@ -61,6 +64,7 @@ impl<RT: AquaRuntime, F: ParticleFunctionStatic> AquamarineBackend<RT, F> {
         out: EffectsChannel,
         plumber_metrics: Option<ParticleExecutorMetrics>,
         vm_pool_metrics: Option<VmPoolMetrics>,
+        data_store_metrics: Option<ParticleDataStoreMetrics>,
         health_registry: Option<&mut HealthCheckRegistry>,
         workers: Arc<Workers>,
         key_storage: Arc<KeyStorage>,
@@ -71,11 +75,14 @@ impl<RT: AquaRuntime, F: ParticleFunctionStatic> AquamarineBackend<RT, F> {
         let (outlet, inlet) = mpsc::channel(100);
         let sender = AquamarineApi::new(outlet, config.execution_timeout);
 
+        let cleanup_grace_period = data_store_config.cleanup_grace_period;
         let data_store = ParticleDataStore::new(
             data_store_config.particles_dir,
             data_store_config.particles_vault_dir,
             data_store_config.particles_anomaly_dir,
-        );
+        )
+        .with_worker_quota(data_store_config.worker_quota)
+        .with_metrics(data_store_metrics);
         let data_store: Arc<ParticleDataStore> = Arc::new(data_store);
         let avm_wasm_backend = WasmtimeWasmBackend::new(avm_wasm_backend_config.into())?;
 
@@ -103,11 +110,18 @@ impl<RT: AquaRuntime, F: ParticleFunctionStatic> AquamarineBackend<RT, F> {
             plumber,
             out,
             data_store,
+            cleanup_grace_period,
         };
 
         Ok((this, sender))
     }
 
+    /// The shared particle data store, e.g. for exposing its disk usage
+    /// through builtins
+    pub fn data_store(&self) -> Arc<ParticleDataStore> {
+        self.data_store.clone()
+    }
+
     pub fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<()> {
         let mut wake = self.process_worker_events();
 
@@ -180,6 +194,7 @@ impl<RT: AquaRuntime, F: ParticleFunctionStatic> AquamarineBackend<RT, F> {
 
     pub fn start(mut self) -> JoinHandle<()> {
         let data_store = self.data_store.clone();
+        let cleanup_grace_period = self.cleanup_grace_period;
         let mut stream = futures::stream::poll_fn(move |cx| self.poll(cx).map(|_| Some(()))).fuse();
         let result = tokio::task::Builder::new()
             .name("Aquamarine")
@@ -189,6 +204,7 @@ impl<RT: AquaRuntime, F: ParticleFunctionStatic> AquamarineBackend<RT, F> {
                         .initialize()
                         .await
                         .expect("Could not initialize data store");
+                    data_store.spawn_cleanup_task(cleanup_grace_period);
                     loop {
                         stream.next().await;
                     }
@@ -75,6 +75,10 @@ impl VmPoolConfig {
     }
 }
 
+/// Particle data files untouched for a day are expired: comfortably above
+/// any sane particle TTL, including periodically re-executed spells
+const DEFAULT_CLEANUP_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Debug, Clone)]
 pub struct DataStoreConfig {
     /// Dir for the interpreter to persist particle data
@@ -85,6 +89,12 @@ pub struct DataStoreConfig {
     pub particles_vault_dir: PathBuf,
     /// Dir to store particles data of AquaVM performance anomalies
     pub particles_anomaly_dir: PathBuf,
+    /// Particle data files untouched for this long are removed by the
+    /// periodic cleanup; must comfortably exceed the maximum particle TTL
+    pub cleanup_grace_period: Duration,
+    /// Cap on the total size of particle data files per worker scope;
+    /// writes beyond it are rejected. `None` means no cap
+    pub worker_quota: Option<u64>,
 }
 
 impl DataStoreConfig {
@@ -94,6 +104,8 @@ impl DataStoreConfig {
             particles_dir: config_utils::particles_dir(&base_dir),
             particles_vault_dir: config_utils::particles_vault_dir(&base_dir),
             particles_anomaly_dir: config_utils::particles_anomaly_dir(&base_dir),
+            cleanup_grace_period: DEFAULT_CLEANUP_GRACE_PERIOD,
+            worker_quota: None,
         }
     }
 }
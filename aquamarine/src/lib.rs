@@ -53,6 +53,6 @@ pub use crate::particle_effects::{InterpretationStats, ParticleEffects, RemoteRo
 pub type AVMRunner = avm_server::avm_runner::AVMRunner<WasmtimeWasmBackend>;
 pub use error::AquamarineApiError;
 pub use marine_wasmtime_backend::WasmtimeWasmBackend;
-pub use particle_data_store::{DataStoreError, ParticleDataStore};
+pub use particle_data_store::{CleanupStats, DataStoreError, ParticleDataStore};
 pub use particle_services::WasmBackendConfig;
 pub use plumber::Plumber;
@@ -15,8 +15,10 @@
  */
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use avm_server::avm_runner::RawAVMOutcome;
@@ -24,11 +26,13 @@ use avm_server::{AnomalyData, CallResults, ParticleParameters};
 use fluence_libp2p::PeerId;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use parking_lot::Mutex;
 use thiserror::Error;
 use tracing::instrument;
 
 use now_millis::now_ms;
 use particle_execution::{ParticleVault, VaultError};
+use peer_metrics::ParticleDataStoreMetrics;
 
 type Result<T> = std::result::Result<T, DataStoreError>;
 
@@ -37,6 +41,13 @@ pub struct ParticleDataStore {
     pub particle_data_store: PathBuf,
     pub vault: ParticleVault,
     pub anomaly_data_store: PathBuf,
+    /// Bytes of particle data on disk per worker scope, kept in sync
+    /// with every write and delete
+    usage: Arc<Mutex<HashMap<String, u64>>>,
+    /// Cap on particle data bytes per worker scope; writes beyond it
+    /// are rejected with [DataStoreError::QuotaExceeded]
+    worker_quota: Option<u64>,
+    metrics: Option<ParticleDataStoreMetrics>,
 }
 
 impl ParticleDataStore {
@@ -49,9 +60,24 @@ impl ParticleDataStore {
             particle_data_store,
             vault: ParticleVault::new(vault_dir),
             anomaly_data_store,
+            usage: <_>::default(),
+            worker_quota: None,
+            metrics: None,
         }
     }
 
+    /// Caps particle data bytes per worker scope; writes beyond the cap
+    /// fail with [DataStoreError::QuotaExceeded]
+    pub fn with_worker_quota(mut self, quota: Option<u64>) -> Self {
+        self.worker_quota = quota;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Option<ParticleDataStoreMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     pub fn data_file(&self, particle_id: &str, current_peer_id: &str, signature: &[u8]) -> PathBuf {
         let key = store_key_from_components(particle_id, current_peer_id, signature);
         self.particle_data_store.join(key)
@@ -86,9 +112,69 @@ impl ParticleDataStore {
 
         self.vault.initialize().await?;
 
+        self.rebuild_usage().await?;
+
+        Ok(())
+    }
+
+    /// Rebuilds the per-worker usage accounting from the files on disk,
+    /// so quotas and gauges survive a restart
+    async fn rebuild_usage(&self) -> Result<()> {
+        let mut usage: HashMap<String, u64> = HashMap::new();
+        let mut entries = tokio::fs::read_dir(&self.particle_data_store)
+            .await
+            .map_err(|err| DataStoreError::ListDataStore(err, self.particle_data_store.clone()))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|err| DataStoreError::ListDataStore(err, self.particle_data_store.clone()))?
+        {
+            let file_name = entry.file_name();
+            let Some(worker) = worker_scope_from_store_key(&file_name.to_string_lossy()) else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            *usage.entry(worker).or_default() += metadata.len();
+        }
+
+        let total = usage.values().sum();
+        if let Some(metrics) = self.metrics.as_ref() {
+            for (worker, bytes) in &usage {
+                metrics.observe_usage(worker, *bytes, total);
+            }
+        }
+        *self.usage.lock() = usage;
+
         Ok(())
     }
 
+    /// Current particle data usage: bytes per worker scope and the total
+    pub fn usage(&self) -> (HashMap<String, u64>, u64) {
+        let usage = self.usage.lock().clone();
+        let total = usage.values().sum();
+        (usage, total)
+    }
+
+    /// Applies a write (`old_bytes` -> `new_bytes`) or a delete
+    /// (`new_bytes` = 0) to the usage accounting and the gauges
+    fn record_usage_change(&self, worker: &str, old_bytes: u64, new_bytes: u64) {
+        let mut usage = self.usage.lock();
+        let entry = usage.entry(worker.to_string()).or_default();
+        *entry = entry.saturating_sub(old_bytes) + new_bytes;
+        let worker_bytes = *entry;
+        if worker_bytes == 0 {
+            usage.remove(worker);
+        }
+        let total = usage.values().sum();
+        drop(usage);
+
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.observe_usage(worker, worker_bytes, total);
+        }
+    }
+
     #[instrument(level = tracing::Level::INFO, skip_all)]
     pub async fn store_data(
         &self,
@@ -99,9 +185,32 @@ impl ParticleDataStore {
     ) -> Result<()> {
         tracing::trace!(target: "particle_reap", particle_id = particle_id, "Storing data for particle");
         let data_path = self.data_file(particle_id, current_peer_id, signature);
+        // size of the file being overwritten, if any: an overwrite only
+        // counts the difference against the quota
+        let old_size = tokio::fs::metadata(&data_path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if let Some(quota) = self.worker_quota {
+            let used = self
+                .usage
+                .lock()
+                .get(current_peer_id)
+                .copied()
+                .unwrap_or(0);
+            if used.saturating_sub(old_size) + data.len() as u64 > quota {
+                return Err(DataStoreError::QuotaExceeded {
+                    worker: current_peer_id.to_string(),
+                    used,
+                    attempted: data.len() as u64,
+                    quota,
+                });
+            }
+        }
         tokio::fs::write(&data_path, data)
             .await
             .map_err(|err| DataStoreError::StoreData(err, data_path))?;
+        self.record_usage_change(current_peer_id, old_size, data.len() as u64);
 
         Ok(())
     }
@@ -158,9 +267,17 @@ impl ParticleDataStore {
         particle_token: &str,
     ) -> Result<()> {
         tracing::debug!(target: "particle_reap", particle_id = particle_id, "Cleaning up particle data for particle");
-        let path = self.data_file(particle_id, &current_peer_id.to_base58(), signature);
+        let worker_scope = current_peer_id.to_base58();
+        let path = self.data_file(particle_id, &worker_scope, signature);
+        let size = tokio::fs::metadata(&path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
         match tokio::fs::remove_file(&path).await {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.record_usage_change(&worker_scope, size, 0);
+                Ok(())
+            }
             // ignore NotFound
             Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
             Err(err) => Err(DataStoreError::CleanupData(err)),
@@ -173,6 +290,84 @@ impl ParticleDataStore {
         Ok(())
     }
 
+    /// Removes data files untouched for longer than `grace_period`.
+    ///
+    /// The grace period must comfortably exceed the maximum particle TTL:
+    /// a particle that is still live keeps its file fresh with every write
+    /// (and a particle being executed has just been written), so only data
+    /// of long-expired particles is old enough to be touched here
+    pub async fn cleanup_expired(&self, grace_period: Duration) -> Result<CleanupStats> {
+        let mut stats = CleanupStats::default();
+        let mut entries = tokio::fs::read_dir(&self.particle_data_store)
+            .await
+            .map_err(|err| DataStoreError::ListDataStore(err, self.particle_data_store.clone()))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|err| DataStoreError::ListDataStore(err, self.particle_data_store.clone()))?
+        {
+            let file_name = entry.file_name();
+            let Some(worker) = worker_scope_from_store_key(&file_name.to_string_lossy()) else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let expired = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > grace_period);
+            if !expired {
+                continue;
+            }
+            match tokio::fs::remove_file(entry.path()).await {
+                Ok(()) => {
+                    stats.removed_files += 1;
+                    stats.freed_bytes += metadata.len();
+                    self.record_usage_change(&worker, metadata.len(), 0);
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => tracing::warn!(
+                    "Failed to remove expired particle data file {:?}: {}",
+                    entry.path(),
+                    err
+                ),
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Spawns a background task that periodically removes data files of
+    /// long-expired particles, see [ParticleDataStore::cleanup_expired].
+    /// Runs every half of `grace_period`, so an expired file lives at most
+    /// 1.5x the grace period past its last write
+    pub fn spawn_cleanup_task(self: &Arc<Self>, grace_period: Duration) {
+        let data_store = self.clone();
+        tokio::task::Builder::new()
+            .name("ParticleDataStoreCleanup")
+            .spawn(async move {
+                let period = (grace_period / 2).max(Duration::from_secs(1));
+                let mut interval = tokio::time::interval(period);
+                // the first tick resolves immediately; nothing is expired yet
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    match data_store.cleanup_expired(grace_period).await {
+                        Ok(stats) if stats.removed_files > 0 => log::info!(
+                            "Particle data cleanup removed {} expired files, freed {} bytes",
+                            stats.removed_files,
+                            stats.freed_bytes
+                        ),
+                        Ok(_) => {}
+                        Err(err) => log::warn!("Particle data cleanup failed: {err}"),
+                    }
+                }
+            })
+            .expect("Could not spawn task");
+    }
+
     fn detect_mem_limits_anomaly(&self, memory_delta: usize, outcome: &RawAVMOutcome) -> bool {
         memory_delta > MEMORY_DELTA_BYTES_THRESHOLD
             || outcome.soft_limits_triggering.are_limits_exceeded()
@@ -277,6 +472,25 @@ pub enum DataStoreError {
     SerializeAnomaly(#[source] serde_json::error::Error),
     #[error("error reading data from {1:?}")]
     ReadData(#[source] std::io::Error, PathBuf),
+    #[error("error listing particle data files in {1:?}")]
+    ListDataStore(#[source] std::io::Error, PathBuf),
+    #[error(
+        "worker {worker} exceeded its particle data quota: \
+         {used} bytes stored + {attempted} bytes to write > {quota} bytes"
+    )]
+    QuotaExceeded {
+        worker: String,
+        used: u64,
+        attempted: u64,
+        quota: u64,
+    },
+}
+
+/// Result of one [ParticleDataStore::cleanup_expired] pass
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CleanupStats {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
 }
 
 fn store_key_from_components(particle_id: &str, current_peer_id: &str, signature: &[u8]) -> String {
@@ -290,6 +504,17 @@ fn format_signature(signature: &[u8]) -> String {
     bs58::encode(signature).into_string()
 }
 
+/// Extracts the worker scope (`current_peer_id`) back out of a store key
+/// produced by [store_key_from_components]. The peer id is base58 and thus
+/// free of `-` and `_`, so searching from the end is unambiguous even when
+/// the particle id itself contains the separators
+fn worker_scope_from_store_key(key: &str) -> Option<String> {
+    let key = key.strip_prefix("particle_")?;
+    let sig_idx = key.rfind("-sig_")?;
+    let peer_idx = key[..sig_idx].rfind("-peer_")?;
+    Some(key[peer_idx + "-peer_".len()..sig_idx].to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::ParticleDataStore;
@@ -510,4 +735,136 @@ mod tests {
         assert!(!data_file_path.exists());
         assert!(!vault_path.exists())
     }
+
+    #[tokio::test]
+    async fn cleanup_expired_removes_only_long_expired_files() {
+        use peer_metrics::{ParticleDataStoreMetrics, WorkerScopeLabel};
+        use prometheus_client::registry::Registry;
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut registry = Registry::default();
+        let metrics = ParticleDataStoreMetrics::new(&mut registry);
+        let store = ParticleDataStore::new(
+            temp_dir.path().join("particle_data_store"),
+            temp_dir.path().join("vault"),
+            temp_dir.path().join("anomaly_data_store"),
+        )
+        .with_metrics(Some(metrics.clone()));
+        store.initialize().await.expect("Failed to initialize");
+
+        let worker = "test_peer";
+        let signature: &[u8] = &[0];
+        store
+            .store_data(b"expired_data", "expired_particle", worker, signature)
+            .await
+            .expect("Failed to store data");
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        store
+            .store_data(b"live_data", "live_particle", worker, signature)
+            .await
+            .expect("Failed to store data");
+
+        let stats = store
+            .cleanup_expired(Duration::from_millis(200))
+            .await
+            .expect("Failed to clean up expired data");
+
+        assert_eq!(stats.removed_files, 1);
+        assert_eq!(stats.freed_bytes, b"expired_data".len() as u64);
+        assert!(!store
+            .data_file("expired_particle", worker, signature)
+            .exists());
+        assert!(store.data_file("live_particle", worker, signature).exists());
+
+        let live_len = b"live_data".len() as u64;
+        let (workers, total) = store.usage();
+        assert_eq!(total, live_len);
+        assert_eq!(workers.get(worker), Some(&live_len));
+        assert_eq!(metrics.total_bytes.get(), live_len as i64);
+        let worker_gauge = metrics.worker_bytes.get_or_create(&WorkerScopeLabel {
+            worker: worker.to_string(),
+        });
+        assert_eq!(worker_gauge.get(), live_len as i64);
+    }
+
+    #[tokio::test]
+    async fn writes_beyond_the_worker_quota_are_rejected() {
+        use crate::DataStoreError;
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let store = ParticleDataStore::new(
+            temp_dir.path().join("particle_data_store"),
+            temp_dir.path().join("vault"),
+            temp_dir.path().join("anomaly_data_store"),
+        )
+        .with_worker_quota(Some(10));
+        store.initialize().await.expect("Failed to initialize");
+
+        let signature: &[u8] = &[0];
+        store
+            .store_data(b"123456", "particle_1", "worker1", signature)
+            .await
+            .expect("Failed to store data");
+        // overwriting the same particle counts only the size difference
+        store
+            .store_data(b"12345678", "particle_1", "worker1", signature)
+            .await
+            .expect("Failed to overwrite data");
+
+        let result = store
+            .store_data(b"123456", "particle_2", "worker1", signature)
+            .await;
+        match result {
+            Err(DataStoreError::QuotaExceeded {
+                worker,
+                used,
+                attempted,
+                quota,
+            }) => {
+                assert_eq!(worker, "worker1");
+                assert_eq!(used, 8);
+                assert_eq!(attempted, 6);
+                assert_eq!(quota, 10);
+            }
+            other => panic!("expected QuotaExceeded, got {other:?}"),
+        }
+
+        // each worker scope has its own budget
+        store
+            .store_data(b"123456", "particle_2", "worker2", signature)
+            .await
+            .expect("Failed to store data for another worker");
+
+        let (workers, total) = store.usage();
+        assert_eq!(total, 14);
+        assert_eq!(workers.get("worker1"), Some(&8));
+        assert_eq!(workers.get("worker2"), Some(&6));
+    }
+
+    #[tokio::test]
+    async fn initialize_rebuilds_usage_from_disk() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let store = ParticleDataStore::new(
+            temp_dir.path().join("particle_data_store"),
+            temp_dir.path().join("vault"),
+            temp_dir.path().join("anomaly_data_store"),
+        );
+        store.initialize().await.expect("Failed to initialize");
+        store
+            .store_data(b"some_data", "test_particle", "test_peer", &[0])
+            .await
+            .expect("Failed to store data");
+
+        // a fresh store over the same directory picks the accounting back up
+        let store = ParticleDataStore::new(
+            temp_dir.path().join("particle_data_store"),
+            temp_dir.path().join("vault"),
+            temp_dir.path().join("anomaly_data_store"),
+        );
+        store.initialize().await.expect("Failed to initialize");
+
+        let (workers, total) = store.usage();
+        assert_eq!(total, b"some_data".len() as u64);
+        assert_eq!(workers.get("test_peer"), Some(&(b"some_data".len() as u64)));
+    }
 }
@@ -20,6 +20,7 @@ use libp2p::PeerId;
 
 use avm_server::CallRequests;
 use particle_protocol::ExtendedParticle;
+use peer_metrics::InterpretationFailureReason;
 use types::peer_scope::PeerScope;
 
 #[derive(Clone, Debug)]
@@ -50,15 +51,18 @@ pub struct InterpretationStats {
     pub memory_delta: usize,
     pub new_data_len: Option<usize>,
     pub success: bool,
+    /// Why the interpretation failed; `None` when `success` is true
+    pub failure_reason: Option<InterpretationFailureReason>,
 }
 
 impl InterpretationStats {
-    pub fn failed() -> Self {
+    pub fn failed(reason: InterpretationFailureReason) -> Self {
         Self {
             interpretation_time: Duration::default(),
             memory_delta: 0,
             new_data_len: None,
             success: false,
+            failure_reason: Some(reason),
         }
     }
 }
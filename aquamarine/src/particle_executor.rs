@@ -28,9 +28,11 @@ use tracing::instrument;
 use fluence_libp2p::PeerId;
 use particle_protocol::Particle;
 
+use peer_metrics::InterpretationFailureReason;
+
 use crate::spawner::SpawnFunctions;
 use crate::spawner::Spawner;
-use crate::{AquaRuntime, InterpretationStats, ParticleDataStore, ParticleEffects};
+use crate::{AquaRuntime, DataStoreError, InterpretationStats, ParticleDataStore, ParticleEffects};
 
 pub(super) type AVMRes<RT> = FutResult<Option<RT>, ParticleEffects, InterpretationStats>;
 
@@ -110,7 +112,7 @@ impl<RT: AquaRuntime> ParticleExecutor for RT {
             FutResult {
                 runtime: Some(self),
                 effects: ParticleEffects::empty(),
-                stats: InterpretationStats::failed(),
+                stats: InterpretationStats::failed(InterpretationFailureReason::Other),
             }
         }
     }
@@ -146,12 +148,15 @@ async fn execute_with_prev_data<RT: AquaRuntime>(
             process_avm_result(data_store, current_peer_id, prev_data_len, avm_result).await
         }
         Err(err) => {
-            if err.is_cancelled() {
+            let reason = if err.is_cancelled() {
                 tracing::warn!(particle_id, "Particle task was cancelled");
+                // the call task is cancelled when it outlives its deadline
+                InterpretationFailureReason::Timeout
             } else {
                 tracing::error!(particle_id, "Particle task panic");
-            }
-            let stats = InterpretationStats::failed();
+                InterpretationFailureReason::Other
+            };
+            let stats = InterpretationStats::failed(reason);
             let effects = ParticleEffects::empty();
             FutResult {
                 // We loose an AVM instance here
@@ -221,10 +226,16 @@ where
                     "Could not save particle result: {}",
                     err
                 );
+                let reason = match &err {
+                    DataStoreError::QuotaExceeded { .. } => {
+                        InterpretationFailureReason::DataQuotaExceeded
+                    }
+                    _ => InterpretationFailureReason::Other,
+                };
                 return FutResult {
                     runtime: Some(avm_result.vm),
                     effects: ParticleEffects::empty(),
-                    stats: InterpretationStats::failed(),
+                    stats: InterpretationStats::failed(reason),
                 };
             }
         }
@@ -283,11 +294,16 @@ async fn avm_call<'a, RT: AquaRuntime>(
             let interpretation_time = now.elapsed();
             let new_data_len = avm_outcome.as_ref().map(|e| e.data.len()).ok();
             let memory_delta = memory_size_after - memory_size_before;
+            let failure_reason = avm_outcome
+                .as_ref()
+                .err()
+                .map(|err| RT::failure_reason(err));
             let stats = InterpretationStats {
                 memory_delta,
                 interpretation_time,
                 new_data_len,
                 success: avm_outcome.is_ok(),
+                failure_reason,
             };
             AVMCallResult {
                 avm_outcome,
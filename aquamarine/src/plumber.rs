@@ -39,7 +39,10 @@ use mock_time::now_ms;
 use particle_execution::{ParticleFunctionStatic, ParticleParams, ServiceFunction};
 use particle_protocol::ExtendedParticle;
 use particle_services::PeerScope;
-use peer_metrics::{ParticleExecutorMetrics, WorkerLabel, WorkerType};
+use peer_metrics::{
+    InterpretationFailureLabel, InterpretationFailureReason, ParticleExecutorMetrics, WorkerLabel,
+    WorkerType,
+};
 /// Get current time from OS
 #[cfg(not(test))]
 use real_time::now_ms;
@@ -486,13 +489,18 @@ impl<RT: AquaRuntime, F: ParticleFunctionStatic> Plumber<RT, F> {
                 if stat.success {
                     m.interpretation_successes.get_or_create(&label).inc();
                 } else {
-                    m.interpretation_failures.get_or_create(&label).inc();
+                    let reason = stat
+                        .failure_reason
+                        .unwrap_or(InterpretationFailureReason::Other);
+                    let failure_label = InterpretationFailureLabel::new(&label, reason);
+                    m.interpretation_failures
+                        .get_or_create(&failure_label)
+                        .inc();
                 }
 
                 let interpretation_time = stat.interpretation_time.as_secs_f64();
                 m.interpretation_time_sec
-                    .get_or_create(&label)
-                    .observe(interpretation_time);
+                    .observe(|family| family.get_or_create(&label).observe(interpretation_time));
             }
             m.total_actors_mailbox
                 .get_or_create(&label)
@@ -660,6 +668,10 @@ mod tests {
     use fluence_keypair::KeyPair;
     use fluence_libp2p::RandomPeerId;
     use futures::task::noop_waker_ref;
+    use peer_metrics::{
+        InterpretationFailureLabel, InterpretationFailureReason, ParticleExecutorMetrics,
+        WorkerLabel, WorkerType,
+    };
     use workers::{DummyCoreManager, KeyStorage, PeerScopes, Workers};
 
     use particle_args::Args;
@@ -753,6 +765,10 @@ mod tests {
                 allocation_rejects: None,
             }
         }
+
+        fn failure_reason(_error: &Self::Error) -> InterpretationFailureReason {
+            InterpretationFailureReason::Other
+        }
     }
 
     async fn plumber() -> Plumber<VMMock, Arc<MockF>> {
@@ -898,6 +914,95 @@ mod tests {
         }
         assert_eq!(plumber.host_actors.len(), 0);
     }
+
+    /// Pool exhaustion is nearly impossible to trigger deterministically,
+    /// so it is injected through a chaos fault rule
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn injected_pool_exhaustion_makes_get_vm_fail() {
+        use std::time::Duration;
+
+        let avm_wasm_config: WasmtimeConfig = WasmBackendConfig::default().into();
+        let avm_wasm_backend =
+            WasmtimeWasmBackend::new(avm_wasm_config).expect("Could not create wasm backend");
+        let mut vm_pool: VmPool<VMMock> = VmPool::new(1, (), None, None, avm_wasm_backend);
+
+        // wait until the single VM is created in the background
+        let (id, vm) = loop {
+            vm_pool.poll(&mut context());
+            if let Some(vm) = vm_pool.get_vm() {
+                break vm;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        vm_pool.put_vm(id, vm);
+
+        chaos::install(
+            "vm_pool::get_vm",
+            chaos::FaultRule::FailFor(Duration::from_secs(5)),
+        );
+        assert!(
+            vm_pool.get_vm().is_none(),
+            "the pool must report exhausted while the fault rule is active"
+        );
+
+        chaos::clear("vm_pool::get_vm");
+        assert!(vm_pool.get_vm().is_some());
+    }
+
+    #[test]
+    fn interpretation_failures_are_counted_per_reason() {
+        use prometheus_client::registry::Registry;
+
+        let mut registry = Registry::default();
+        let toggles = peer_metrics::MetricsToggles::default();
+        let metrics = ParticleExecutorMetrics::new(&mut registry, &toggles);
+        let label = WorkerLabel::new(WorkerType::Host, RandomPeerId::random().to_string());
+
+        let reasons = [
+            InterpretationFailureReason::ScriptError,
+            InterpretationFailureReason::ResourceExhausted,
+            InterpretationFailureReason::WasmTrap,
+            InterpretationFailureReason::Timeout,
+            InterpretationFailureReason::DataQuotaExceeded,
+            InterpretationFailureReason::Other,
+        ];
+        for reason in reasons {
+            let failure_label = InterpretationFailureLabel::new(&label, reason);
+            metrics
+                .interpretation_failures
+                .get_or_create(&failure_label)
+                .inc();
+        }
+        let timeout_label =
+            InterpretationFailureLabel::new(&label, InterpretationFailureReason::Timeout);
+        metrics
+            .interpretation_failures
+            .get_or_create(&timeout_label)
+            .inc();
+
+        assert_eq!(
+            metrics
+                .interpretation_failures
+                .get_or_create(&timeout_label)
+                .get(),
+            2
+        );
+        for reason in reasons {
+            if reason == InterpretationFailureReason::Timeout {
+                continue;
+            }
+            let failure_label = InterpretationFailureLabel::new(&label, reason);
+            assert_eq!(
+                metrics
+                    .interpretation_failures
+                    .get_or_create(&failure_label)
+                    .get(),
+                1,
+                "counter for {reason:?} must be incremented independently"
+            );
+        }
+    }
 }
 
 /// Code taken from https://blog.iany.me/2019/03/how-to-mock-time-in-rust-tests-and-cargo-gotchas-we-met/
@@ -97,6 +97,14 @@ impl<RT: AquaRuntime> VmPool<RT> {
 
     /// Takes VM from pool
     pub fn get_vm(&mut self) -> Option<(usize, RT)> {
+        #[cfg(feature = "chaos")]
+        if chaos::should_fail("vm_pool::get_vm") {
+            self.meter(|m| {
+                m.get_vm.inc();
+                m.no_free_vm.inc();
+            });
+            return None;
+        }
         let runtimes = self.runtimes.iter_mut();
         let vm = runtimes
             .enumerate()
@@ -14,17 +14,21 @@
  * limitations under the License.
  */
 
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
 use libp2p::{core::Multiaddr, PeerId};
+use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use particle_protocol::ExtendedParticle;
-use particle_protocol::{Contact, SendStatus};
+use particle_protocol::{Contact, ProtocolConfig, SendStatus};
+use peer_metrics::ParticleType;
 
 use crate::connection_pool::LifecycleEvent;
+use crate::link_stats::{LinkStat, SharedLinkStats};
 use crate::ConnectionPoolT;
 
 // marked `pub` to be available in benchmarks
@@ -39,6 +43,11 @@ pub enum Command {
         particle: ExtendedParticle,
         out: oneshot::Sender<SendStatus>,
     },
+    SendBuffered {
+        to: Contact,
+        particle: ExtendedParticle,
+        out: oneshot::Sender<SendStatus>,
+    },
     Dial {
         addr: Multiaddr,
         out: oneshot::Sender<Option<Contact>>,
@@ -55,6 +64,12 @@ pub enum Command {
         peer_id: PeerId,
         out: oneshot::Sender<Option<Contact>>,
     },
+    GetMyContact {
+        out: oneshot::Sender<Option<Contact>>,
+    },
+    GetAllContacts {
+        out: oneshot::Sender<Vec<Contact>>,
+    },
 
     CountConnections {
         out: oneshot::Sender<usize>,
@@ -62,13 +77,142 @@ pub enum Command {
     LifecycleEvents {
         out: mpsc::UnboundedSender<LifecycleEvent>,
     },
+    WaitForConnection {
+        peer_id: PeerId,
+        out: oneshot::Sender<Contact>,
+    },
+    WaitForDisconnect {
+        peer_id: PeerId,
+        out: oneshot::Sender<()>,
+    },
+    GetPeerHistory {
+        peer_id: PeerId,
+        out: oneshot::Sender<Vec<(Instant, LifecycleEvent)>>,
+    },
+    UpdateProtocolConfig {
+        config: ProtocolConfig,
+        out: oneshot::Sender<()>,
+    },
+    ParticleCounts {
+        out: oneshot::Sender<HashMap<ParticleType, u64>>,
+    },
+    SetPeerRateLimit {
+        peer_id: PeerId,
+        bytes_per_sec: Option<u64>,
+        out: oneshot::Sender<()>,
+    },
+    Snapshot {
+        out: oneshot::Sender<Snapshot>,
+    },
+    PeerStats {
+        peer_id: PeerId,
+        out: oneshot::Sender<Option<PeerStats>>,
+    },
+}
+
+impl Command {
+    /// Command kind used as a label in command queue metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Command::Connect { .. } => "connect",
+            Command::Send { .. } => "send",
+            Command::SendBuffered { .. } => "send_buffered",
+            Command::Dial { .. } => "dial",
+            Command::Disconnect { .. } => "disconnect",
+            Command::IsConnected { .. } => "is_connected",
+            Command::GetContact { .. } => "get_contact",
+            Command::GetMyContact { .. } => "get_my_contact",
+            Command::GetAllContacts { .. } => "get_all_contacts",
+            Command::CountConnections { .. } => "count_connections",
+            Command::LifecycleEvents { .. } => "lifecycle_events",
+            Command::WaitForConnection { .. } => "wait_for_connection",
+            Command::WaitForDisconnect { .. } => "wait_for_disconnect",
+            Command::GetPeerHistory { .. } => "get_peer_history",
+            Command::UpdateProtocolConfig { .. } => "update_protocol_config",
+            Command::ParticleCounts { .. } => "particle_counts",
+            Command::SetPeerRateLimit { .. } => "set_peer_rate_limit",
+            Command::Snapshot { .. } => "snapshot",
+            Command::PeerStats { .. } => "peer_stats",
+        }
+    }
+}
+
+/// Keep-alive class a peer is assigned to when its connection is established,
+/// driving how aggressively the connection is retained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerClass {
+    /// Bootstrap/relay nodes from config; their connections are retained
+    /// aggressively and re-dialed on an unexpected close
+    Relay,
+    /// Worker and management peers; exempt from client session limits
+    /// but not re-dialed
+    Worker,
+    /// Everyone else; idle connections are closed through the
+    /// client idle-timeout path
+    Client,
+}
+
+/// Per-peer view returned by [ConnectionPoolApi::peer_stats]
+#[derive(Debug, Clone)]
+pub struct PeerStats {
+    /// Keep-alive class the peer was assigned on connection
+    pub class: PeerClass,
+    /// Whether the peer currently has live connections
+    pub connected: bool,
+}
+
+/// Point-in-time view of the connection pool state, captured atomically
+/// inside the behaviour; see [ConnectionPoolApi::snapshot]
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Connected peers along with their connected addresses only,
+    /// without Identify-discovered ones
+    pub connected: Vec<Contact>,
+    /// Addresses with a dial still in flight
+    pub dialing: Vec<Multiaddr>,
+    /// Particles waiting to be forwarded to execution
+    pub queue_depth: usize,
+    pub metrics: PoolStats,
+}
+
+/// Counters captured alongside a [Snapshot]
+#[derive(Debug, Clone, Default)]
+pub struct PoolStats {
+    /// Cumulative inbound particle counts per [ParticleType]
+    pub particle_counts: HashMap<ParticleType, u64>,
+    /// Self-addressed sends parked until the execution queue drains
+    pub buffered_sends: usize,
+    /// Sends currently delayed by a per-peer rate limit
+    pub throttled_sends: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum WaitError {
+    #[error("timed out after {after:?} waiting for peer {peer_id}")]
+    Timeout { peer_id: PeerId, after: Duration },
+    #[error("command wasn't enqueued to the connection pool in time: the command queue was full")]
+    CommandQueueTimedOut,
+    #[error("connection pool was stopped while waiting for peer {peer_id}")]
+    PoolStopped { peer_id: PeerId },
+}
+
+/// [Command] paired with the moment it was pushed to the command channel.
+/// Used to measure how long commands wait in the queue before execution.
+#[derive(Debug)]
+pub struct EnqueuedCommand {
+    pub command: Command,
+    pub enqueued: Instant,
 }
 
 #[derive(Clone, Debug)]
 pub struct ConnectionPoolApi {
     // TODO: marked as `pub` to be available in benchmarks
-    pub outlet: mpsc::UnboundedSender<Command>,
+    pub outlet: mpsc::Sender<EnqueuedCommand>,
     pub send_timeout: Duration,
+    /// How long to wait for a free slot in the command channel before giving up
+    pub enqueue_timeout: Duration,
+    /// Per-link send latency accounting, shared with the behaviour
+    pub link_stats: SharedLinkStats,
 }
 
 impl ConnectionPoolApi {
@@ -77,11 +221,177 @@ impl ConnectionPoolApi {
         R: Default + Send + Sync + 'static,
         F: FnOnce(oneshot::Sender<R>) -> Command,
     {
+        self.execute_or(R::default, cmd)
+    }
+
+    /// Enqueues a command to the bounded command channel, waiting for capacity
+    /// at most `enqueue_timeout`. On timeout resolves to `on_timeout()` instead
+    /// of blocking forever on a stalled behaviour.
+    fn execute_or<R, F, T>(&self, on_timeout: T, cmd: F) -> BoxFuture<'static, R>
+    where
+        R: Default + Send + Sync + 'static,
+        T: FnOnce() -> R + Send + 'static,
+        F: FnOnce(oneshot::Sender<R>) -> Command,
+    {
+        let (out, inlet) = oneshot::channel();
+        let command = EnqueuedCommand {
+            command: cmd(out),
+            enqueued: Instant::now(),
+        };
+        let outlet = self.outlet.clone();
+        let enqueue_timeout = self.enqueue_timeout;
+        async move {
+            match tokio::time::timeout(enqueue_timeout, outlet.send(command)).await {
+                // command channel was full for the whole `enqueue_timeout`
+                Err(..) => on_timeout(),
+                // behaviour is dead; fall back to default as if the response channel was dropped
+                Ok(Err(..)) => R::default(),
+                Ok(Ok(())) => inlet.await.unwrap_or_default(),
+            }
+        }
+        .boxed()
+    }
+
+    /// Resolves with the peer's [Contact] as soon as the peer is connected
+    /// (immediately if it already is), or with [WaitError::Timeout] after `timeout`.
+    /// Saves callers from subscribing to `lifecycle_events` and filtering by peer id.
+    pub async fn wait_for_connection(
+        &self,
+        peer_id: PeerId,
+        timeout: Duration,
+    ) -> Result<Contact, WaitError> {
+        let (out, inlet) = oneshot::channel();
+        let command = EnqueuedCommand {
+            command: Command::WaitForConnection { peer_id, out },
+            enqueued: Instant::now(),
+        };
+        match tokio::time::timeout(self.enqueue_timeout, self.outlet.send(command)).await {
+            // command channel was full for the whole `enqueue_timeout`
+            Err(..) => Err(WaitError::CommandQueueTimedOut),
+            // behaviour is dead, the connection will never be reported
+            Ok(Err(..)) => Err(WaitError::PoolStopped { peer_id }),
+            Ok(Ok(())) => match tokio::time::timeout(timeout, inlet).await {
+                Err(..) => Err(WaitError::Timeout {
+                    peer_id,
+                    after: timeout,
+                }),
+                // behaviour dropped the sender without resolving it
+                Ok(Err(..)) => Err(WaitError::PoolStopped { peer_id }),
+                Ok(Ok(contact)) => Ok(contact),
+            },
+        }
+    }
+
+    /// Resolves once the peer has no live connections left (immediately if it
+    /// isn't connected at call time), or with [WaitError::Timeout] after `timeout`.
+    /// The check and the subscription happen in one command inside the behaviour,
+    /// so a disconnect can't slip between them
+    pub async fn wait_disconnected(
+        &self,
+        peer_id: PeerId,
+        timeout: Duration,
+    ) -> Result<(), WaitError> {
         let (out, inlet) = oneshot::channel();
-        if self.outlet.send(cmd(out)).is_err() {
-            return futures::future::ready(R::default()).boxed();
+        let command = EnqueuedCommand {
+            command: Command::WaitForDisconnect { peer_id, out },
+            enqueued: Instant::now(),
+        };
+        match tokio::time::timeout(self.enqueue_timeout, self.outlet.send(command)).await {
+            // command channel was full for the whole `enqueue_timeout`
+            Err(..) => Err(WaitError::CommandQueueTimedOut),
+            // behaviour is dead, the disconnect will never be reported
+            Ok(Err(..)) => Err(WaitError::PoolStopped { peer_id }),
+            Ok(Ok(())) => match tokio::time::timeout(timeout, inlet).await {
+                Err(..) => Err(WaitError::Timeout {
+                    peer_id,
+                    after: timeout,
+                }),
+                // behaviour dropped the sender without resolving it
+                Ok(Err(..)) => Err(WaitError::PoolStopped { peer_id }),
+                Ok(Ok(())) => Ok(()),
+            },
         }
-        inlet.map(|r| r.unwrap_or_default()).boxed()
+    }
+
+    /// Returns the top `count` links by p95 send latency, slowest first
+    pub fn slow_links(&self, count: usize) -> Vec<LinkStat> {
+        self.link_stats.lock().slow_links(count)
+    }
+
+    /// Returns the last lifecycle events recorded for `peer_id`, oldest first,
+    /// along with the moment each event happened
+    pub async fn get_peer_history(&self, peer_id: PeerId) -> Vec<(Instant, LifecycleEvent)> {
+        self.execute(|out| Command::GetPeerHistory { peer_id, out })
+            .await
+    }
+
+    /// Like [ConnectionPoolT::send], but a particle addressed to the local
+    /// node is never dropped on a full execution queue: the send is parked
+    /// and resolves `Ok` once the particle is actually enqueued after the
+    /// queue drains. There is deliberately no timeout on the parked phase,
+    /// so local senders get natural backpressure instead of a failure
+    pub fn send_buffered(
+        &self,
+        to: Contact,
+        particle: ExtendedParticle,
+    ) -> BoxFuture<'static, SendStatus> {
+        self.execute_or(
+            || SendStatus::CommandQueueTimedOut,
+            |out| Command::SendBuffered { to, particle, out },
+        )
+    }
+
+    /// Returns the local node's contact: its peer id along with the
+    /// externally-visible addresses confirmed by the swarm.
+    /// `None` only if the pool has stopped
+    pub async fn get_my_contact(&self) -> Option<Contact> {
+        self.execute(|out| Command::GetMyContact { out }).await
+    }
+
+    /// Returns contacts of all currently connected peers.
+    /// Empty only if nothing is connected or the pool has stopped
+    pub async fn get_all_contacts(&self) -> Vec<Contact> {
+        self.execute(|out| Command::GetAllContacts { out }).await
+    }
+
+    /// Swaps the protocol config used for connections established from now on.
+    /// Existing connections and in-flight handlers keep the config
+    /// they were created with.
+    pub async fn update_protocol_config(&self, config: ProtocolConfig) {
+        self.execute(|out| Command::UpdateProtocolConfig { config, out })
+            .await
+    }
+
+    /// Returns cumulative counts of inbound particles per [ParticleType]
+    /// since the pool started. Empty only if the pool has stopped
+    pub async fn particle_counts(&self) -> HashMap<ParticleType, u64> {
+        self.execute(|out| Command::ParticleCounts { out }).await
+    }
+
+    /// Captures a point-in-time view of the pool: connected contacts,
+    /// in-flight dials, queue depth and counters, all read in one go inside
+    /// the behaviour so they are consistent with each other.
+    /// Default (empty) only if the pool has stopped
+    pub async fn snapshot(&self) -> Snapshot {
+        self.execute(|out| Command::Snapshot { out }).await
+    }
+
+    /// Returns the keep-alive class and connectivity of `peer_id`.
+    /// `None` if the peer has never connected or its class was forgotten
+    /// after it disconnected (client classes are not retained)
+    pub async fn peer_stats(&self, peer_id: PeerId) -> Option<PeerStats> {
+        self.execute(|out| Command::PeerStats { peer_id, out }).await
+    }
+
+    /// Caps outbound particle traffic to `peer_id` at `bytes_per_sec`;
+    /// sends above the cap are delayed, not dropped. `None` removes the cap
+    pub async fn set_peer_rate_limit(&self, peer_id: PeerId, bytes_per_sec: Option<u64>) {
+        self.execute(|out| Command::SetPeerRateLimit {
+            peer_id,
+            bytes_per_sec,
+            out,
+        })
+        .await
     }
 }
 
@@ -112,7 +422,20 @@ impl ConnectionPoolT for ConnectionPoolApi {
     }
 
     fn send(&self, to: Contact, particle: ExtendedParticle) -> BoxFuture<'static, SendStatus> {
-        let fut = self.execute(|out| Command::Send { to, particle, out });
+        #[cfg(feature = "chaos")]
+        let chaos_point = format!("connection-pool::send::{}", to.peer_id);
+        let fut = self.execute_or(
+            || SendStatus::CommandQueueTimedOut,
+            |out| Command::Send { to, particle, out },
+        );
+        #[cfg(feature = "chaos")]
+        let fut = async move {
+            if let Some(delay) = chaos::delay(&chaos_point) {
+                tokio::time::sleep(delay).await;
+            }
+            fut.await
+        }
+        .boxed();
         // timeout on send is required because libp2p can silently drop outbound events
         let timeout = self.send_timeout;
         tokio::time::timeout(self.send_timeout, fut)
@@ -137,8 +460,11 @@ impl ConnectionPoolT for ConnectionPoolApi {
 
     fn lifecycle_events(&self) -> BoxStream<'static, LifecycleEvent> {
         let (out, inlet) = mpsc::unbounded_channel();
-        let cmd = Command::LifecycleEvents { out };
-        if self.outlet.send(cmd).is_err() {
+        let cmd = EnqueuedCommand {
+            command: Command::LifecycleEvents { out },
+            enqueued: Instant::now(),
+        };
+        if self.outlet.try_send(cmd).is_err() {
             return futures::stream::empty().boxed();
         };
 
@@ -14,7 +14,9 @@
  * limitations under the License.
  */
 
-use futures::{Sink, StreamExt};
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, Sink, StreamExt};
 use libp2p::core::Endpoint;
 use libp2p::swarm::dial_opts::DialOpts;
 use libp2p::swarm::CloseConnection::All;
@@ -23,43 +25,168 @@ use libp2p::swarm::{
     THandlerOutEvent, ToSwarm,
 };
 use libp2p::{
-    core::{ConnectedPoint, Multiaddr},
+    core::{multiaddr::Protocol, ConnectedPoint, Multiaddr},
     swarm::{NetworkBehaviour, NotifyHandler, OneShotHandler},
     PeerId,
 };
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     task::{Context, Poll, Waker},
 };
 use tokio::sync::{mpsc, oneshot};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::sync::PollSender;
 
+use crate::api::EnqueuedCommand;
 use crate::connection_pool::LifecycleEvent;
-use crate::{Command, ConnectionPoolApi};
-use fluence_libp2p::remote_multiaddr;
+use crate::link_stats::{LinkStatsRegistry, SharedLinkStats, MAX_TRACKED_LINKS};
+use crate::{Command, ConnectionPoolApi, PeerClass, PeerStats, PoolStats, Snapshot};
+use fluence_libp2p::{remote_multiaddr, LogRateLimit, LogRateLimiter};
 use particle_protocol::{
-    CompletionChannel, Contact, ExtendedParticle, HandlerMessage, ProtocolConfig, SendStatus,
+    AirVersionPolicy, CompletionChannel, Contact, ExtendedParticle, HandlerMessage, Particle,
+    ProtocolConfig, SendStatus,
 };
-use peer_metrics::ConnectionPoolMetrics;
+use peer_metrics::{ConnectionPoolMetrics, LinkLabel, ParticleDirection, ParticleType};
+use semver::Version;
 
 // type SwarmEventType = generate_swarm_event_type!(ConnectionPoolBehaviour);
 
 // TODO: replace with generate_swarm_event_type
 type SwarmEventType = ToSwarm<(), HandlerMessage>;
 
+/// How many lifecycle events are kept per peer in [PeerHistory]
+const PEER_HISTORY_LIMIT: usize = 50;
+
+/// How many Identify-discovered addresses are retained per peer; the most
+/// recently observed ones win. Keeps peers that rotate ephemeral ports
+/// (NATed clients) from growing the set without bound
+const MAX_DISCOVERED_ADDRESSES: usize = 8;
+
+/// Discovered addresses not re-observed via Identify within this period
+/// are dropped on the next update
+const DISCOVERED_ADDRESS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Pause before a send is retried after a failed substream upgrade, giving
+/// a transient condition (e.g. TCP RST mid-handshake) a chance to clear
+const UPGRADE_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// A send dispatched to the handler later: after the retry delay of a failed
+/// substream upgrade, or once a rate-limited peer's token bucket refills.
+/// Carries the target peer, the particle, the caller's outlet and the
+/// upgrade retries left
+type PendingSend = (PeerId, Particle, oneshot::Sender<SendStatus>, u32);
+
+/// Token bucket limiting outbound bytes to a single peer. Tokens refill at
+/// `bytes_per_sec` up to one second of burst; a send may drive the balance
+/// negative, and the next sends then wait for the refill to cover the debt,
+/// which spaces them out at the configured rate
+#[derive(Debug)]
+struct TokenBucket {
+    /// Refill rate; clamped to at least 1 to keep delays finite
+    bytes_per_sec: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Self {
+            bytes_per_sec,
+            tokens: bytes_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Withdraws `bytes` and returns how long the send must be delayed
+    /// for the refill to cover them; `None` means it can go out right away
+    fn withdraw(&mut self, bytes: usize) -> Option<Duration> {
+        let rate = self.bytes_per_sec as f64;
+        let now = Instant::now();
+        let refilled = now.duration_since(self.last_refill).as_secs_f64() * rate;
+        self.tokens = (self.tokens + refilled).min(rate);
+        self.last_refill = now;
+        self.tokens -= bytes as f64;
+        (self.tokens < 0.0).then(|| Duration::from_secs_f64(-self.tokens / rate))
+    }
+}
+
+/// Last [PEER_HISTORY_LIMIT] lifecycle events of a single peer,
+/// kept for debugging unstable connections
+#[derive(Debug, Default)]
+struct PeerHistory {
+    events: VecDeque<(Instant, LifecycleEvent)>,
+}
+
+impl PeerHistory {
+    fn push(&mut self, event: LifecycleEvent) {
+        if self.events.len() == PEER_HISTORY_LIMIT {
+            self.events.pop_front();
+        }
+        self.events.push_back((Instant::now(), event));
+    }
+}
+
+/// Limits applied to sessions of client peers: peers whose connection
+/// addresses are not in the configured relay/bootstrap set. Relay peers
+/// are never limited
+#[derive(Debug, Clone, Default)]
+pub struct ClientSessionConfig {
+    /// Relay/bootstrap addresses; peers connected through them are exempt
+    pub relay_addresses: HashSet<Multiaddr>,
+    /// Close a client connection with no particle activity for this long.
+    /// `None` keeps idle connections until the transport gives up
+    pub idle_timeout: Option<Duration>,
+    /// Maximum number of concurrent client sessions; exceeding it evicts
+    /// the idlest session. `None` means no cap
+    pub max_sessions: Option<usize>,
+}
+
+/// Per-peer-class keep-alive policy. Peers are classified on connection:
+/// [PeerClass::Relay] for bootstrap/relay addresses from config,
+/// [PeerClass::Worker] for the configured worker/management peer ids,
+/// [PeerClass::Client] for everyone else. Relay connections are retained
+/// aggressively (re-dialed on an unexpected close), client connections go
+/// through the idle-timeout path of [ClientSessionConfig]
+#[derive(Debug, Clone, Default)]
+pub struct KeepAliveConfig {
+    /// Peer ids classified as [PeerClass::Worker]: workers and the
+    /// management peer
+    pub worker_peer_ids: HashSet<PeerId>,
+    /// Re-dial relay-class peers whose last connection closed without a
+    /// [Command::Disconnect] request
+    pub redial_relays: bool,
+}
+
+/// Where queued local particles are persisted on graceful shutdown
+/// and reloaded from on the next start
+#[derive(Debug, Clone)]
+pub struct QueueSpillConfig {
+    /// File the queued local particles are serialized to
+    pub path: PathBuf,
+    /// Maximum size of the spill file in bytes; particles that don't fit
+    /// are dropped and counted in the `spilled_particles_dropped` metric
+    pub max_size: usize,
+}
+
 #[derive(Debug, Default)]
 /// [Peer] is the representation of [Contact] extended with precise connectivity information
 struct Peer {
     /// Current peer has active connections with that list of addresses
     connected: HashSet<Multiaddr>,
-    /// Addresses gathered via Identify protocol, but not connected
-    discovered: HashSet<Multiaddr>,
+    /// Addresses gathered via Identify protocol, but not connected,
+    /// each with the moment it was last observed
+    discovered: HashMap<Multiaddr, Instant>,
     /// Dialed but not yet connected addresses
     dialing: HashSet<Multiaddr>,
     /// Channels to notify when any dial succeeds or peer is already connected
     dial_promises: Vec<oneshot::Sender<bool>>,
+    /// AIR interpreter version advertised via Identify; `None` until the peer is identified
+    air_version: Option<Version>,
     // TODO: this layout of `dialing` and `dial_promises` doesn't allow to check specific addresses for reachability
     //       if check reachability for specific maddrs is ever required, one would need to maintain the following info:
     //       reachability_promises: HashMap<Multiaddr, Vec<oneshot::Sender<bool>>
@@ -69,7 +196,7 @@ impl Peer {
     pub fn addresses(&self) -> impl Iterator<Item = &Multiaddr> {
         self.connected
             .iter()
-            .chain(&self.discovered)
+            .chain(self.discovered.keys())
             .chain(&self.dialing)
             .collect::<HashSet<_>>()
             .into_iter()
@@ -81,6 +208,7 @@ impl Peer {
             discovered: Default::default(),
             dialing: Default::default(),
             dial_promises: vec![],
+            air_version: None,
         }
     }
 
@@ -93,6 +221,7 @@ impl Peer {
             discovered: Default::default(),
             dialing: addresses.into_iter().collect(),
             dial_promises: vec![outlet],
+            air_version: None,
         }
     }
 }
@@ -100,33 +229,117 @@ impl Peer {
 pub struct ConnectionPoolBehaviour {
     peer_id: PeerId,
 
-    commands: UnboundedReceiverStream<Command>,
+    commands: ReceiverStream<EnqueuedCommand>,
 
     outlet: PollSender<ExtendedParticle>,
     subscribers: Vec<mpsc::UnboundedSender<LifecycleEvent>>,
 
-    queue: VecDeque<ExtendedParticle>,
+    /// Particles waiting to be forwarded to execution, each with the moment it was enqueued
+    queue: VecDeque<(Instant, ExtendedParticle)>,
+    /// Soft cap on `queue`: buffered self-sends park while the queue length
+    /// is at or above it. Matches the particle outlet buffer size
+    queue_capacity: usize,
+    /// Self-addressed sends parked until the queue drains below `queue_capacity`;
+    /// resolved `Ok` only once the particle is actually enqueued
+    buffered_sends: VecDeque<(ExtendedParticle, oneshot::Sender<SendStatus>)>,
     contacts: HashMap<PeerId, Peer>,
     dialing: HashMap<Multiaddr, Vec<oneshot::Sender<Option<Contact>>>>,
+    /// When each in-flight dial was initiated, to measure dial latency.
+    /// Inbound connections have no entry here and aren't observed
+    dial_started: HashMap<Multiaddr, Instant>,
+    /// Channels to resolve when a specific peer connects
+    connection_waiters: HashMap<PeerId, Vec<oneshot::Sender<Contact>>>,
+    /// Channels to resolve when a specific peer disconnects
+    disconnect_waiters: HashMap<PeerId, Vec<oneshot::Sender<()>>>,
+    /// Recent lifecycle events per peer
+    peer_history: HashMap<PeerId, PeerHistory>,
+    /// Externally-visible addresses of the local node confirmed by the swarm;
+    /// advertised through the local contact
+    external_addresses: HashSet<Multiaddr>,
 
     events: VecDeque<SwarmEventType>,
     waker: Option<Waker>,
     pub(super) protocol_config: ProtocolConfig,
 
     metrics: Option<ConnectionPoolMetrics>,
+    /// Whether the closed particle outlet was already reported to logs
+    outlet_closed_logged: bool,
+    /// Cumulative counts of inbound particles per [ParticleType]; unlike the
+    /// metrics they are queryable through [Command::ParticleCounts]
+    particle_counts: HashMap<ParticleType, u64>,
+    /// Deduplicates warn logs a flapping peer produces thousands of times
+    log_limiter: LogRateLimiter,
+
+    /// Per-link send latency accounting, shared with [ConnectionPoolApi]
+    link_stats: SharedLinkStats,
+    /// Links whose p95 send latency exceeds this threshold are reported
+    /// through the `slow_links` metric
+    slow_link_threshold: Duration,
+    /// Range of remote AIR interpreter versions particles are exchanged with;
+    /// particles from and to peers outside the range are refused
+    version_policy: AirVersionPolicy,
+
+    /// Limits for client (non-relay) peer sessions
+    client_sessions: ClientSessionConfig,
+    /// Per-peer-class keep-alive policy
+    keep_alive: KeepAliveConfig,
+    /// Keep-alive class assigned to each peer on connection. Client entries
+    /// are forgotten on disconnect; relay/worker entries are kept (their
+    /// number is bounded by config) so keep-alive decisions survive reconnects
+    peer_class: HashMap<PeerId, PeerClass>,
+    /// Peers whose connections are being closed on request, so the close
+    /// doesn't count as unexpected and trigger a relay re-dial
+    deliberate_disconnects: HashSet<PeerId>,
+    /// Last particle activity per client peer; relay peers are not tracked.
+    /// Uses the tokio clock so idle checks and the check interval agree
+    client_activity: HashMap<PeerId, tokio::time::Instant>,
+    /// Ticks while an idle timeout is configured, triggering idle checks
+    idle_check: Option<tokio::time::Interval>,
+    /// Where queued local particles are spilled on graceful shutdown;
+    /// `None` disables the spill entirely
+    queue_spill: Option<QueueSpillConfig>,
+    /// Completion watchers of sends that may still be retried; each resolves
+    /// to a retry order after [UPGRADE_RETRY_DELAY], or to nothing when the
+    /// send completed (successfully or not) without needing a retry
+    upgrade_retries: FuturesUnordered<BoxFuture<'static, Option<PendingSend>>>,
+    /// Outbound rate limits per peer, set via [Command::SetPeerRateLimit]
+    peer_rate_limits: HashMap<PeerId, TokenBucket>,
+    /// Sends delayed by a peer's rate limit, each resolving once its share
+    /// of the token bucket has refilled
+    throttled_sends: FuturesUnordered<BoxFuture<'static, PendingSend>>,
 }
 
 impl ConnectionPoolBehaviour {
-    fn execute(&mut self, cmd: Command) {
-        match cmd {
+    fn execute(&mut self, cmd: EnqueuedCommand) {
+        let kind = cmd.command.kind();
+        let delay = cmd.enqueued.elapsed();
+        self.meter(|m| m.command_executed(kind, delay));
+        match cmd.command {
             Command::Dial { addr, out } => self.dial(addr, out),
             Command::Connect { contact, out } => self.connect(contact, out),
             Command::Disconnect { peer_id, out } => self.disconnect(peer_id, out),
             Command::IsConnected { peer_id, out } => self.is_connected(peer_id, out),
             Command::GetContact { peer_id, out } => self.get_contact(peer_id, out),
+            Command::GetMyContact { out } => self.get_my_contact(out),
+            Command::GetAllContacts { out } => self.get_all_contacts(out),
             Command::Send { to, particle, out } => self.send(to, particle, out),
+            Command::SendBuffered { to, particle, out } => self.send_buffered(to, particle, out),
             Command::CountConnections { out } => self.count_connections(out),
             Command::LifecycleEvents { out } => self.add_subscriber(out),
+            Command::WaitForConnection { peer_id, out } => self.wait_for_connection(peer_id, out),
+            Command::WaitForDisconnect { peer_id, out } => self.wait_for_disconnect(peer_id, out),
+            Command::GetPeerHistory { peer_id, out } => self.get_peer_history(peer_id, out),
+            Command::UpdateProtocolConfig { config, out } => {
+                self.update_protocol_config(config, out)
+            }
+            Command::ParticleCounts { out } => self.get_particle_counts(out),
+            Command::SetPeerRateLimit {
+                peer_id,
+                bytes_per_sec,
+                out,
+            } => self.set_peer_rate_limit(peer_id, bytes_per_sec, out),
+            Command::Snapshot { out } => self.snapshot(out),
+            Command::PeerStats { peer_id, out } => self.get_peer_stats(peer_id, out),
         }
     }
 
@@ -135,6 +348,9 @@ impl ConnectionPoolBehaviour {
     pub fn dial(&mut self, address: Multiaddr, out: oneshot::Sender<Option<Contact>>) {
         // TODO: return Contact immediately if that address is already connected
         self.dialing.entry(address.clone()).or_default().push(out);
+        self.dial_started
+            .entry(address.clone())
+            .or_insert_with(Instant::now);
 
         self.push_event(ToSwarm::Dial {
             opts: DialOpts::unknown_peer_id().address(address).build(),
@@ -180,6 +396,11 @@ impl ConnectionPoolBehaviour {
         };
 
         if !addresses.is_empty() {
+            for maddr in &addresses {
+                self.dial_started
+                    .entry(maddr.clone())
+                    .or_insert_with(Instant::now);
+            }
             self.push_event(ToSwarm::Dial {
                 opts: DialOpts::peer_id(new_contact.peer_id)
                     .addresses(addresses)
@@ -189,6 +410,11 @@ impl ConnectionPoolBehaviour {
     }
 
     pub fn disconnect(&mut self, peer_id: PeerId, outlet: oneshot::Sender<bool>) {
+        // a requested close is not an unexpected one: don't re-dial the peer
+        // even if it is relay-class
+        if self.contacts.contains_key(&peer_id) {
+            self.deliberate_disconnects.insert(peer_id);
+        }
         self.push_event(ToSwarm::CloseConnection {
             peer_id,
             connection: All,
@@ -208,12 +434,29 @@ impl ConnectionPoolBehaviour {
         outlet.send(contact).ok();
     }
 
+    /// Returns the local node's contact with the confirmed external addresses
+    pub fn get_my_contact(&self, outlet: oneshot::Sender<Option<Contact>>) {
+        outlet.send(Some(self.my_contact())).ok();
+    }
+
+    /// Returns contacts of all currently connected peers
+    pub fn get_all_contacts(&self, outlet: oneshot::Sender<Vec<Contact>>) {
+        let contacts = self
+            .contacts
+            .iter()
+            .map(|(peer_id, contact)| {
+                Contact::new(*peer_id, contact.addresses().cloned().collect())
+            })
+            .collect();
+        outlet.send(contacts).ok();
+    }
+
     /// Sends a particle to a connected contact. Returns whether sending succeeded or not
     /// Result is sent to channel inside `upgrade_outbound` in ProtocolHandler
     pub fn send(
         &mut self,
         to: Contact,
-        particle: ExtendedParticle,
+        mut particle: ExtendedParticle,
         outlet: oneshot::Sender<SendStatus>,
     ) {
         let span =
@@ -221,9 +464,30 @@ impl ConnectionPoolBehaviour {
         let _guard = span.enter();
         if to.peer_id == self.peer_id {
             // If particle is sent to the current node, process it locally
-            self.queue.push_back(particle);
+            self.queue.push_back((Instant::now(), particle));
             outlet.send(SendStatus::Ok).ok();
             self.wake();
+        } else if particle.particle.hop_count >= self.protocol_config.max_hops {
+            tracing::warn!(
+                target: "blocked",
+                particle_id = particle.particle.id,
+                "Won't forward particle to {}: hop count {} reached the limit of {}, looks like a routing loop",
+                to.peer_id,
+                particle.particle.hop_count,
+                self.protocol_config.max_hops
+            );
+            self.meter(|m| m.hop_limit_exceeded.inc());
+            outlet.send(SendStatus::HopLimitExceeded).ok();
+        } else if let Some(air_version) = self.incompatible_air_version(&to.peer_id) {
+            tracing::warn!(
+                target: "blocked",
+                particle_id = particle.particle.id,
+                "Won't send particle to {}: AIR interpreter version {} is outside the supported range",
+                to.peer_id,
+                air_version
+            );
+            self.meter(|m| m.count_version_mismatch(ParticleDirection::Outbound));
+            outlet.send(SendStatus::VersionMismatch).ok();
         } else if self.contacts.contains_key(&to.peer_id) {
             tracing::debug!(
                 target: "network",
@@ -232,15 +496,24 @@ impl ConnectionPoolBehaviour {
                 self.peer_id,
                 to.peer_id
             );
-            // Send particle to remote peer
-            self.push_event(ToSwarm::NotifyHandler {
-                peer_id: to.peer_id,
-                handler: NotifyHandler::Any,
-                event: HandlerMessage::OutParticle(
-                    particle.particle,
-                    CompletionChannel::Oneshot(outlet),
-                ),
+            self.meter(|m| {
+                m.outgoing_particle(&particle.particle.id, particle.particle.data.len() as f64)
             });
+            self.touch_client_activity(&to.peer_id);
+            // every forward to a remote peer counts as a hop
+            particle.particle.hop_count += 1;
+            // leave a trace so the next node can detect an immediate bounce-back
+            particle.particle.push_path_trace(self.peer_id);
+            // Send particle to remote peer
+            let outlet = self.watch_link_latency(to.peer_id, outlet);
+            let retries = self.protocol_config.max_upgrade_retries;
+            let size = particle.particle.data.len();
+            match self.throttle(&to.peer_id, size) {
+                Some(delay) => {
+                    self.delay_send(to.peer_id, particle.particle, outlet, retries, delay)
+                }
+                None => self.send_to_handler(to.peer_id, particle.particle, outlet, retries),
+            }
         } else {
             tracing::warn!(
                 particle_id = particle.particle.id,
@@ -251,61 +524,685 @@ impl ConnectionPoolBehaviour {
         }
     }
 
+    /// Like [ConnectionPoolBehaviour::send], but a self-addressed particle
+    /// hitting a full execution queue is parked instead of being enqueued
+    /// right away; the outlet resolves `Ok` only once the queue drains below
+    /// [ConnectionPoolBehaviour::queue_capacity] and the particle is enqueued.
+    /// Sends to remote peers behave exactly like [ConnectionPoolBehaviour::send]
+    pub fn send_buffered(
+        &mut self,
+        to: Contact,
+        particle: ExtendedParticle,
+        outlet: oneshot::Sender<SendStatus>,
+    ) {
+        if to.peer_id == self.peer_id && self.queue.len() >= self.queue_capacity {
+            tracing::debug!(
+                particle_id = particle.particle.id,
+                "Parking self-addressed particle: queue is at capacity ({})",
+                self.queue_capacity
+            );
+            self.buffered_sends.push_back((particle, outlet));
+            self.wake();
+        } else {
+            self.send(to, particle, outlet);
+        }
+    }
+
+    /// Interposes a channel between the protocol handler and `outlet` to measure
+    /// how long the send took, from `NotifyHandler` dispatch to completion.
+    /// Successful sends are recorded in [LinkStatsRegistry]; links whose p95
+    /// exceeds `slow_link_threshold` are exposed through the `slow_links` gauge.
+    fn watch_link_latency(
+        &self,
+        remote: PeerId,
+        outlet: oneshot::Sender<SendStatus>,
+    ) -> oneshot::Sender<SendStatus> {
+        let (watched_outlet, watched_inlet) = oneshot::channel();
+        let started = Instant::now();
+        let link_stats = self.link_stats.clone();
+        let metrics = self.metrics.clone();
+        let threshold = self.slow_link_threshold;
+        tokio::spawn(async move {
+            // if the handler dropped the completion channel, drop `outlet` as well
+            // so the caller observes the same error
+            if let Ok(status) = watched_inlet.await {
+                if matches!(status, SendStatus::Ok) {
+                    let (p95, evicted) = link_stats.lock().observe(remote, started.elapsed());
+                    if let Some(metrics) = metrics.as_ref() {
+                        let label = LinkLabel {
+                            remote_peer: remote.to_base58(),
+                        };
+                        if p95 >= threshold {
+                            metrics
+                                .slow_links
+                                .get_or_create(&label)
+                                .set(p95.as_millis() as i64);
+                        } else {
+                            metrics.slow_links.remove(&label);
+                        }
+                        if let Some(evicted) = evicted {
+                            metrics.slow_links.remove(&LinkLabel {
+                                remote_peer: evicted.to_base58(),
+                            });
+                        }
+                    }
+                }
+                outlet.send(status).ok();
+            }
+        });
+        watched_outlet
+    }
+
+    /// Dispatches the particle to the peer's protocol handler. With retries
+    /// left, the completion is watched: a failed substream upgrade schedules
+    /// the same particle for another attempt after [UPGRADE_RETRY_DELAY]
+    /// instead of failing the caller right away. With no retries left the
+    /// caller's outlet is handed to the handler directly, so disabled
+    /// retries (the default) add no cloning or channel hops
+    fn send_to_handler(
+        &mut self,
+        to: PeerId,
+        particle: Particle,
+        outlet: oneshot::Sender<SendStatus>,
+        retries_left: u32,
+    ) {
+        let channel = if retries_left == 0 {
+            CompletionChannel::Oneshot(outlet)
+        } else {
+            let (watched_outlet, watched_inlet) = oneshot::channel();
+            let retry_particle = particle.clone();
+            self.upgrade_retries.push(
+                async move {
+                    match watched_inlet.await {
+                        // a failed upgrade surfaces either as a protocol error
+                        // or as the handler dropping the channel; both look
+                        // transient enough to retry
+                        Ok(SendStatus::ProtocolError(_)) | Err(_) => {
+                            tokio::time::sleep(UPGRADE_RETRY_DELAY).await;
+                            Some((to, retry_particle, outlet, retries_left - 1))
+                        }
+                        Ok(status) => {
+                            outlet.send(status).ok();
+                            None
+                        }
+                    }
+                }
+                .boxed(),
+            );
+            CompletionChannel::Oneshot(watched_outlet)
+        };
+        self.push_event(ToSwarm::NotifyHandler {
+            peer_id: to,
+            handler: NotifyHandler::Any,
+            event: HandlerMessage::OutParticle(particle, channel),
+        });
+    }
+
+    /// Charges `bytes` against the peer's rate limit, if any, returning
+    /// how long the send has to wait for the token bucket to cover them
+    fn throttle(&mut self, peer_id: &PeerId, bytes: usize) -> Option<Duration> {
+        self.peer_rate_limits.get_mut(peer_id)?.withdraw(bytes)
+    }
+
+    /// Parks a rate-limited send until `delay` elapses; it is then
+    /// dispatched from [ConnectionPoolBehaviour::poll]
+    fn delay_send(
+        &mut self,
+        to: PeerId,
+        particle: Particle,
+        outlet: oneshot::Sender<SendStatus>,
+        retries_left: u32,
+        delay: Duration,
+    ) {
+        tracing::debug!(
+            target: "network",
+            particle_id = particle.id,
+            "Delaying send to rate-limited peer {} by {:?}",
+            to,
+            delay
+        );
+        self.throttled_sends.push(
+            async move {
+                tokio::time::sleep(delay).await;
+                (to, particle, outlet, retries_left)
+            }
+            .boxed(),
+        );
+        self.wake();
+    }
+
     /// Returns number of connected contacts
     pub fn count_connections(&mut self, outlet: oneshot::Sender<usize>) {
         outlet.send(self.contacts.len()).ok();
     }
 
+    /// Captures a point-in-time view of the pool state for diagnostics.
+    /// Everything is read synchronously right here, so the parts of the
+    /// snapshot are consistent with each other
+    pub fn snapshot(&self, outlet: oneshot::Sender<Snapshot>) {
+        let connected = self
+            .contacts
+            .iter()
+            .map(|(peer_id, peer)| {
+                Contact::new(*peer_id, peer.connected.iter().cloned().collect())
+            })
+            .collect();
+        let snapshot = Snapshot {
+            connected,
+            dialing: self.dialing.keys().cloned().collect(),
+            queue_depth: self.queue.len(),
+            metrics: PoolStats {
+                particle_counts: self.particle_counts.clone(),
+                buffered_sends: self.buffered_sends.len(),
+                throttled_sends: self.throttled_sends.len(),
+            },
+        };
+        outlet.send(snapshot).ok();
+    }
+
     /// Subscribes given channel for all `LifecycleEvent`s
     pub fn add_subscriber(&mut self, outlet: mpsc::UnboundedSender<LifecycleEvent>) {
         self.subscribers.push(outlet);
     }
 
+    /// Resolves `out` with the peer's contact as soon as the peer is connected.
+    /// If the peer is already connected, resolves immediately.
+    pub fn wait_for_connection(&mut self, peer_id: PeerId, out: oneshot::Sender<Contact>) {
+        if let Some(contact) = self.get_contact_impl(peer_id) {
+            out.send(contact).ok();
+            return;
+        }
+        let waiters = self.connection_waiters.entry(peer_id).or_default();
+        // drop waiters whose callers have given up already
+        waiters.retain(|waiter| !waiter.is_closed());
+        waiters.push(out);
+    }
+
+    /// Resolves `out` as soon as the peer has no live connections left.
+    /// If the peer isn't connected, resolves immediately.
+    pub fn wait_for_disconnect(&mut self, peer_id: PeerId, out: oneshot::Sender<()>) {
+        if !self.contacts.contains_key(&peer_id) {
+            out.send(()).ok();
+            return;
+        }
+        let waiters = self.disconnect_waiters.entry(peer_id).or_default();
+        // drop waiters whose callers have given up already
+        waiters.retain(|waiter| !waiter.is_closed());
+        waiters.push(out);
+    }
+
+    /// Returns a copy of the last lifecycle events recorded for `peer_id`, oldest first
+    pub fn get_peer_history(
+        &self,
+        peer_id: PeerId,
+        outlet: oneshot::Sender<Vec<(Instant, LifecycleEvent)>>,
+    ) {
+        let history = self
+            .peer_history
+            .get(&peer_id)
+            .map(|history| history.events.iter().cloned().collect())
+            .unwrap_or_default();
+        outlet.send(history).ok();
+    }
+
+    /// Returns a copy of cumulative inbound particle counts per [ParticleType]
+    pub fn get_particle_counts(&self, outlet: oneshot::Sender<HashMap<ParticleType, u64>>) {
+        outlet.send(self.particle_counts.clone()).ok();
+    }
+
+    /// Returns the peer's keep-alive class and connectivity; `None` for peers
+    /// that never connected or whose (client) class was forgotten on disconnect
+    pub fn get_peer_stats(&self, peer_id: PeerId, outlet: oneshot::Sender<Option<PeerStats>>) {
+        let stats = self.peer_class.get(&peer_id).map(|class| PeerStats {
+            class: *class,
+            connected: self.contacts.contains_key(&peer_id),
+        });
+        outlet.send(stats).ok();
+    }
+
+    /// Records addresses gathered via Identify, with hygiene so the set stays
+    /// small and dialable: loopback/link-local addresses of remote peers are
+    /// ignored (unless the peer itself is connected from such an address),
+    /// re-observed addresses only get their timestamp refreshed, addresses
+    /// not re-observed within [DISCOVERED_ADDRESS_TTL] are dropped, and at
+    /// most [MAX_DISCOVERED_ADDRESSES] most recently observed are retained
     pub fn add_discovered_addresses(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
-        self.contacts
-            .entry(peer_id)
-            .or_default()
-            .discovered
-            .extend(addresses);
+        let peer = self.contacts.entry(peer_id).or_default();
+        // a peer connected from a loopback/link-local address runs on this
+        // host or link, so such addresses are dialable for it
+        let peer_is_local = peer.connected.iter().any(is_local_maddr);
+
+        let now = Instant::now();
+        for maddr in addresses {
+            if !peer_is_local && is_local_maddr(&maddr) {
+                continue;
+            }
+            // an already connected address has nothing to gain from discovery
+            if peer.connected.contains(&maddr) {
+                continue;
+            }
+            // a repeated identical update only refreshes the timestamps
+            peer.discovered.insert(maddr, now);
+        }
+
+        peer.discovered
+            .retain(|_, observed| now.duration_since(*observed) < DISCOVERED_ADDRESS_TTL);
+        while peer.discovered.len() > MAX_DISCOVERED_ADDRESSES {
+            let oldest = peer
+                .discovered
+                .iter()
+                .min_by_key(|(_, observed)| **observed)
+                .map(|(maddr, _)| maddr.clone());
+            match oldest {
+                Some(oldest) => peer.discovered.remove(&oldest),
+                None => break,
+            };
+        }
+
+        let count = peer.discovered.len();
+        self.meter(|m| m.discovered_addresses_per_peer.observe(count as f64));
+    }
+
+    /// Swaps the protocol config used to create handlers for connections
+    /// established from now on. Existing connections and in-flight handlers
+    /// keep the config they were created with.
+    pub fn update_protocol_config(&mut self, config: ProtocolConfig, outlet: oneshot::Sender<()>) {
+        self.protocol_config = config;
+        outlet.send(()).ok();
+    }
+
+    /// Caps outbound particle traffic to `peer_id` at `bytes_per_sec`;
+    /// sends above the cap are delayed until the peer's token bucket
+    /// refills. `None` removes the cap; already delayed sends still go out
+    /// after their original delay
+    pub fn set_peer_rate_limit(
+        &mut self,
+        peer_id: PeerId,
+        bytes_per_sec: Option<u64>,
+        outlet: oneshot::Sender<()>,
+    ) {
+        match bytes_per_sec {
+            Some(rate) => {
+                self.peer_rate_limits.insert(peer_id, TokenBucket::new(rate));
+            }
+            None => {
+                self.peer_rate_limits.remove(&peer_id);
+            }
+        }
+        outlet.send(()).ok();
+    }
+
+    /// Records the AIR interpreter version the peer advertised via Identify
+    pub fn set_air_version(&mut self, peer_id: PeerId, air_version: Option<Version>) {
+        self.contacts.entry(peer_id).or_default().air_version = air_version;
+    }
+
+    /// Returns the peer's advertised AIR version if it is outside the supported range.
+    /// `None` means the peer is compatible or hasn't advertised a version
+    fn incompatible_air_version(&self, peer_id: &PeerId) -> Option<&Version> {
+        let version = self.contacts.get(peer_id)?.air_version.as_ref()?;
+        (!self.version_policy.allows(version)).then_some(version)
     }
 
     fn meter<U, F: Fn(&ConnectionPoolMetrics) -> U>(&self, f: F) {
         self.metrics.as_ref().map(f);
     }
+
+    /// Assigns the peer its keep-alive class based on the address it connected
+    /// through and the configured worker/management peer ids. A peer seen on a
+    /// relay address keeps the relay class even if it later connects from
+    /// another address; other classes never override an earlier assignment
+    fn classify_peer(&mut self, peer_id: PeerId, maddr: &Multiaddr) {
+        let class = if self.client_sessions.relay_addresses.contains(maddr) {
+            PeerClass::Relay
+        } else if self.keep_alive.worker_peer_ids.contains(&peer_id) {
+            PeerClass::Worker
+        } else {
+            PeerClass::Client
+        };
+        let assigned = self.peer_class.entry(peer_id).or_insert(class);
+        if class == PeerClass::Relay {
+            *assigned = PeerClass::Relay;
+        }
+    }
+
+    /// Re-dials a relay-class peer whose last connection just closed, unless
+    /// the close was requested through [Command::Disconnect]. A bootstrap
+    /// supervisor re-dialing the same peer in parallel is harmless: libp2p's
+    /// default dial condition skips peers already connected or being dialed
+    fn maybe_redial_relay(&mut self, peer_id: &PeerId, maddr: &Multiaddr) {
+        if self.deliberate_disconnects.remove(peer_id) {
+            return;
+        }
+        if !self.keep_alive.redial_relays
+            || self.peer_class.get(peer_id) != Some(&PeerClass::Relay)
+        {
+            return;
+        }
+        // gather addresses before the contact is removed; fall back to the
+        // address the closed connection used
+        let mut addresses: Vec<Multiaddr> = self
+            .contacts
+            .get(peer_id)
+            .map(|peer| peer.addresses().cloned().collect())
+            .unwrap_or_default();
+        if addresses.is_empty() {
+            addresses.push(maddr.clone());
+        }
+        log::info!(
+            target: "network",
+            "Relay {} disconnected unexpectedly, re-dialing",
+            peer_id
+        );
+        for maddr in &addresses {
+            self.dial_started
+                .entry(maddr.clone())
+                .or_insert_with(Instant::now);
+        }
+        self.push_event(ToSwarm::Dial {
+            opts: DialOpts::peer_id(*peer_id).addresses(addresses).build(),
+        });
+    }
+
+    /// Starts tracking activity of a newly connected client peer and enforces
+    /// the session cap by evicting the idlest client session. Connections
+    /// through a relay/bootstrap address are exempt from both limits
+    fn register_client_session(&mut self, peer_id: PeerId, maddr: &Multiaddr) {
+        if self.client_sessions.relay_addresses.contains(maddr) {
+            // the peer turned out to be a relay: make sure it's never limited
+            self.client_activity.remove(&peer_id);
+            return;
+        }
+        if self.client_activity.contains_key(&peer_id) {
+            self.touch_client_activity(&peer_id);
+            return;
+        }
+        if let Some(max_sessions) = self.client_sessions.max_sessions {
+            if self.client_activity.len() >= max_sessions {
+                let idlest = self
+                    .client_activity
+                    .iter()
+                    .min_by_key(|(_, last_activity)| *last_activity)
+                    .map(|(peer_id, _)| *peer_id);
+                if let Some(idlest) = idlest {
+                    log::info!(
+                        target: "network",
+                        "Client session limit of {} reached, evicting the idlest client {}",
+                        max_sessions,
+                        idlest
+                    );
+                    self.client_activity.remove(&idlest);
+                    self.meter(|m| m.client_sessions_evicted.inc());
+                    self.push_event(ToSwarm::CloseConnection {
+                        peer_id: idlest,
+                        connection: All,
+                    });
+                }
+            }
+        }
+        self.client_activity
+            .insert(peer_id, tokio::time::Instant::now());
+    }
+
+    /// Refreshes the peer's last-activity timestamp if it is a tracked client
+    fn touch_client_activity(&mut self, peer_id: &PeerId) {
+        if let Some(last_activity) = self.client_activity.get_mut(peer_id) {
+            *last_activity = tokio::time::Instant::now();
+        }
+    }
+
+    /// Closes client connections with no particle activity for longer than
+    /// the configured idle timeout
+    fn close_idle_clients(&mut self) {
+        let Some(idle_timeout) = self.client_sessions.idle_timeout else {
+            return;
+        };
+        let idle: Vec<PeerId> = self
+            .client_activity
+            .iter()
+            .filter(|(_, last_activity)| last_activity.elapsed() >= idle_timeout)
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+        for peer_id in idle {
+            log::info!(
+                target: "network",
+                "Closing client connection to {}: no activity for {:?}",
+                peer_id,
+                idle_timeout
+            );
+            self.client_activity.remove(&peer_id);
+            self.meter(|m| m.client_sessions_idle_closed.inc());
+            self.push_event(ToSwarm::CloseConnection {
+                peer_id,
+                connection: All,
+            });
+        }
+    }
+
+    /// Drops all queued particles: with the outlet closed they can never reach execution.
+    /// The error is logged only once, the drops are accounted in metrics.
+    fn on_outlet_closed(&mut self) {
+        if !self.outlet_closed_logged {
+            self.outlet_closed_logged = true;
+            log::error!("Particle outlet closed");
+        }
+        let dropped = self.queue.len() as u64;
+        self.queue.clear();
+        if dropped > 0 {
+            self.meter(|m| m.particles_dropped_outlet_closed.inc_by(dropped));
+        }
+    }
 }
 
 impl ConnectionPoolBehaviour {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         buffer: usize,
+        command_buffer: usize,
         protocol_config: ProtocolConfig,
         peer_id: PeerId,
         metrics: Option<ConnectionPoolMetrics>,
+        slow_link_threshold: Duration,
+        version_policy: AirVersionPolicy,
+        client_sessions: ClientSessionConfig,
+        queue_spill: Option<QueueSpillConfig>,
     ) -> (Self, mpsc::Receiver<ExtendedParticle>, ConnectionPoolApi) {
         let (outlet, inlet) = mpsc::channel(buffer);
         let outlet = PollSender::new(outlet);
-        let (command_outlet, command_inlet) = mpsc::unbounded_channel();
+        let (command_outlet, command_inlet) = mpsc::channel(command_buffer);
+        let link_stats: SharedLinkStats = Arc::new(parking_lot::Mutex::new(
+            LinkStatsRegistry::new(MAX_TRACKED_LINKS),
+        ));
         let api = ConnectionPoolApi {
             outlet: command_outlet,
             send_timeout: protocol_config.upgrade_timeout * 2,
+            enqueue_timeout: protocol_config.upgrade_timeout,
+            link_stats: link_stats.clone(),
         };
 
-        let this = Self {
+        // check for idle clients twice per timeout so a silent connection
+        // is closed no later than 1.5x the configured timeout
+        let idle_check = client_sessions
+            .idle_timeout
+            .map(|timeout| tokio::time::interval(timeout / 2));
+
+        let mut this = Self {
             peer_id,
             outlet,
-            commands: UnboundedReceiverStream::new(command_inlet),
+            commands: ReceiverStream::new(command_inlet),
             subscribers: <_>::default(),
             queue: <_>::default(),
+            queue_capacity: buffer,
+            buffered_sends: <_>::default(),
             contacts: <_>::default(),
             dialing: <_>::default(),
+            dial_started: <_>::default(),
+            connection_waiters: <_>::default(),
+            disconnect_waiters: <_>::default(),
+            peer_history: <_>::default(),
+            external_addresses: <_>::default(),
             events: <_>::default(),
             waker: None,
             protocol_config,
             metrics,
+            outlet_closed_logged: false,
+            particle_counts: <_>::default(),
+            log_limiter: <_>::default(),
+            link_stats,
+            slow_link_threshold,
+            version_policy,
+            client_sessions,
+            keep_alive: <_>::default(),
+            peer_class: <_>::default(),
+            deliberate_disconnects: <_>::default(),
+            client_activity: <_>::default(),
+            idle_check,
+            queue_spill,
+            upgrade_retries: <_>::default(),
+            peer_rate_limits: <_>::default(),
+            throttled_sends: <_>::default(),
         };
+        this.reload_spilled_queue();
 
         (this, inlet, api)
     }
 
+    /// Replaces the default (disabled) per-peer-class keep-alive policy
+    pub fn with_keep_alive(mut self, config: KeepAliveConfig) -> Self {
+        self.keep_alive = config;
+        self
+    }
+
+    /// Re-enqueues particles spilled on the previous graceful shutdown,
+    /// dropping the ones whose TTL ran out in the meantime. The spill file
+    /// is removed right away so particles are never replayed twice
+    fn reload_spilled_queue(&mut self) {
+        let Some(spill) = self.queue_spill.clone() else {
+            return;
+        };
+        if !spill.path.exists() {
+            return;
+        }
+        let bytes = match std::fs::read(&spill.path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                log::warn!("Failed to read particle spill file {:?}: {}", spill.path, err);
+                return;
+            }
+        };
+        if let Err(err) = std::fs::remove_file(&spill.path) {
+            log::warn!(
+                "Failed to remove particle spill file {:?}: {}",
+                spill.path,
+                err
+            );
+        }
+        let particles: Vec<Particle> = match serde_json::from_slice(&bytes) {
+            Ok(particles) => particles,
+            Err(err) => {
+                log::warn!(
+                    "Failed to deserialize particle spill file {:?}: {}",
+                    spill.path,
+                    err
+                );
+                return;
+            }
+        };
+        let mut expired: u64 = 0;
+        for particle in particles {
+            if particle.is_expired() {
+                expired += 1;
+                continue;
+            }
+            let root_span = tracing::info_span!("Particle", particle_id = particle.id);
+            self.queue
+                .push_back((Instant::now(), ExtendedParticle::new(particle, root_span)));
+        }
+        if expired > 0 {
+            self.meter(|m| m.spilled_particles_expired.inc_by(expired));
+        }
+        if !self.queue.is_empty() {
+            log::info!(
+                "Reloaded {} spilled particles from {:?}",
+                self.queue.len(),
+                spill.path
+            );
+        }
+    }
+
+    /// Persists locally originated particles still waiting in the queue so
+    /// the next start can replay them. Local means initiated by this node or
+    /// one of `local_peers` (worker peer ids); remote and expired particles
+    /// are never persisted. Particles exceeding the spill file size cap are
+    /// dropped and counted. Returns the number of particles written
+    pub fn spill_queue(&self, local_peers: &HashSet<PeerId>) -> usize {
+        let Some(spill) = self.queue_spill.as_ref() else {
+            return 0;
+        };
+        let mut spilled: Vec<&Particle> = Vec::new();
+        // account for the JSON array brackets and per-element separators
+        let mut size = 2;
+        let mut dropped: u64 = 0;
+        let mut expired: u64 = 0;
+        for (_, particle) in &self.queue {
+            let particle = &particle.particle;
+            let local = particle.init_peer_id == self.peer_id
+                || local_peers.contains(&particle.init_peer_id);
+            if !local {
+                continue;
+            }
+            if particle.is_expired() {
+                expired += 1;
+                continue;
+            }
+            match serde_json::to_vec(particle) {
+                Ok(bytes) if size + bytes.len() + 1 <= spill.max_size => {
+                    size += bytes.len() + 1;
+                    spilled.push(particle);
+                }
+                Ok(_) => dropped += 1,
+                Err(err) => {
+                    log::warn!("Failed to serialize particle {}: {}", particle.id, err);
+                    dropped += 1;
+                }
+            }
+        }
+        if dropped > 0 {
+            self.meter(|m| m.spilled_particles_dropped.inc_by(dropped));
+        }
+        if expired > 0 {
+            self.meter(|m| m.spilled_particles_expired.inc_by(expired));
+        }
+        if spilled.is_empty() {
+            return 0;
+        }
+        let bytes = match serde_json::to_vec(&spilled) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                log::warn!("Failed to serialize the particle spill: {}", err);
+                return 0;
+            }
+        };
+        match std::fs::write(&spill.path, bytes) {
+            Ok(()) => {
+                log::info!(
+                    "Spilled {} local particles to {:?}",
+                    spilled.len(),
+                    spill.path
+                );
+                spilled.len()
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to write particle spill file {:?}: {}",
+                    spill.path,
+                    err
+                );
+                0
+            }
+        }
+    }
+
     fn wake(&self) {
         if let Some(waker) = &self.waker {
             waker.wake_by_ref();
@@ -313,6 +1210,12 @@ impl ConnectionPoolBehaviour {
     }
 
     fn add_connected_address(&mut self, peer_id: PeerId, maddr: Multiaddr) {
+        // if we dialed that address, observe how long the dial took;
+        // inbound connections have no recorded dial start and are skipped
+        if let Some(started) = self.dial_started.remove(&maddr) {
+            self.meter(|m| m.dial_latency_sec.observe(started.elapsed().as_secs_f64()));
+        }
+
         // notify these waiting for a peer to be connected
         match self.contacts.entry(peer_id) {
             Entry::Occupied(mut entry) => {
@@ -340,10 +1243,30 @@ impl ConnectionPoolBehaviour {
                 out.send(contact.clone()).ok();
             }
         }
+
+        // notify these waiting for this exact peer to be connected
+        if let Some(waiters) = self.connection_waiters.remove(&peer_id) {
+            // the contact was just inserted above, so it is always there
+            if let Some(contact) = self.get_contact_impl(peer_id) {
+                for out in waiters {
+                    out.send(contact.clone()).ok();
+                }
+            }
+        }
         self.meter(|m| m.connected_peers.set(self.contacts.len() as i64));
     }
 
     fn lifecycle_event(&mut self, event: LifecycleEvent) {
+        let peer_id = match &event {
+            LifecycleEvent::Connected(contact) | LifecycleEvent::Disconnected(contact) => {
+                contact.peer_id
+            }
+        };
+        self.peer_history
+            .entry(peer_id)
+            .or_default()
+            .push(event.clone());
+
         self.subscribers.retain(|out| {
             let ok = out.send(event.clone());
             ok.is_ok()
@@ -356,6 +1279,12 @@ impl ConnectionPoolBehaviour {
     }
 
     fn remove_contact(&mut self, peer_id: &PeerId, reason: &str) {
+        self.client_activity.remove(peer_id);
+        // client classes are forgotten so the map doesn't grow without bound;
+        // relay/worker classes are kept for keep-alive decisions on reconnect
+        if self.peer_class.get(peer_id) == Some(&PeerClass::Client) {
+            self.peer_class.remove(peer_id);
+        }
         if let Some(contact) = self.contacts.remove(peer_id) {
             log::debug!("Contact {} was removed: {}", peer_id, reason);
             self.lifecycle_event(LifecycleEvent::Disconnected(Contact::new(
@@ -363,6 +1292,13 @@ impl ConnectionPoolBehaviour {
                 contact.addresses().cloned().collect(),
             )));
 
+            // notify these waiting for this exact peer to be disconnected
+            if let Some(waiters) = self.disconnect_waiters.remove(peer_id) {
+                for out in waiters {
+                    out.send(()).ok();
+                }
+            }
+
             for out in contact.dial_promises {
                 // if dial was in progress, notify waiters
                 out.send(false).ok();
@@ -372,12 +1308,36 @@ impl ConnectionPoolBehaviour {
     }
 
     fn get_contact_impl(&self, peer_id: PeerId) -> Option<Contact> {
+        if peer_id == self.peer_id {
+            return Some(self.my_contact());
+        }
         self.contacts.get(&peer_id).map(|c| Contact {
             peer_id,
             addresses: c.addresses().cloned().collect(),
         })
     }
 
+    /// The local node's contact: its peer id with the external addresses
+    /// confirmed by the swarm so far
+    fn my_contact(&self) -> Contact {
+        Contact::new(
+            self.peer_id,
+            self.external_addresses.iter().cloned().collect(),
+        )
+    }
+
+    /// Remembers an externally-visible address of the local node so that
+    /// other nodes can discover it from the local contact record
+    fn on_new_external_addr(&mut self, addr: &Multiaddr) {
+        log::debug!(
+            target: "network",
+            "{}: external address confirmed: {}",
+            self.peer_id,
+            addr
+        );
+        self.external_addresses.insert(addr.clone());
+    }
+
     fn on_connection_closed(
         &mut self,
         peer_id: &PeerId,
@@ -386,6 +1346,7 @@ impl ConnectionPoolBehaviour {
     ) {
         let multiaddr = remote_multiaddr(cp);
         if remaining_established == 0 {
+            self.maybe_redial_relay(peer_id, multiaddr);
             self.remove_contact(peer_id, "disconnected");
             log::debug!(
                 target: "network",
@@ -425,11 +1386,18 @@ impl ConnectionPoolBehaviour {
             return;
         }
 
-        log::warn!(
-            "Error dialing peer {}: {:?}",
-            peer_id.map_or("unknown".to_string(), |id| id.to_string()),
-            error
-        );
+        let peer = peer_id.map_or("unknown".to_string(), |id| id.to_string());
+        match self.log_limiter.observe("dial_failure", &peer) {
+            LogRateLimit::Emit => log::warn!("Error dialing peer {}: {:?}", peer, error),
+            LogRateLimit::Summary { repeats, window } => log::warn!(
+                "Error dialing peer {}: {:?} (repeated {} times in the last {:?})",
+                peer,
+                error,
+                repeats,
+                window
+            ),
+            LogRateLimit::Suppress => {}
+        }
         match error {
             DialError::WrongPeerId { endpoint, .. } => {
                 let addr = match endpoint {
@@ -463,6 +1431,9 @@ impl ConnectionPoolBehaviour {
     }
 
     fn cleanup_address(&mut self, peer_id: Option<&PeerId>, addr: &Multiaddr) {
+        // the dial failed, so there's no latency to observe
+        self.dial_started.remove(addr);
+
         // Notify those who waits for address dial
         if let Some(outs) = self.dialing.remove(addr) {
             for out in outs {
@@ -493,6 +1464,17 @@ impl ConnectionPoolBehaviour {
     }
 }
 
+/// Whether the multiaddr points to a loopback or link-local IP;
+/// such addresses are not dialable from other hosts
+fn is_local_maddr(maddr: &Multiaddr) -> bool {
+    maddr.iter().any(|protocol| match protocol {
+        Protocol::Ip4(ip) => ip.is_loopback() || ip.is_link_local(),
+        // fe80::/10 is the IPv6 link-local range
+        Protocol::Ip6(ip) => ip.is_loopback() || (ip.segments()[0] & 0xffc0) == 0xfe80,
+        _ => false,
+    })
+}
+
 impl NetworkBehaviour for ConnectionPoolBehaviour {
     type ConnectionHandler = OneShotHandler<ProtocolConfig, HandlerMessage, HandlerMessage>;
     type ToSwarm = ();
@@ -522,6 +1504,8 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
         );
 
         self.add_connected_address(peer_id, remote_addr.clone());
+        self.classify_peer(peer_id, remote_addr);
+        self.register_client_session(peer_id, remote_addr);
 
         self.lifecycle_event(LifecycleEvent::Connected(Contact::new(
             peer_id,
@@ -566,6 +1550,8 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
         );
 
         self.add_connected_address(peer_id, addr.clone());
+        self.classify_peer(peer_id, addr);
+        self.register_client_session(peer_id, addr);
 
         self.lifecycle_event(LifecycleEvent::Connected(Contact::new(
             peer_id,
@@ -578,7 +1564,19 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
         match event {
             FromSwarm::ConnectionEstablished(event) => {
                 for addr in event.failed_addresses {
-                    log::warn!("failed to connect to {} {}", addr, event.peer_id);
+                    match self.log_limiter.observe("connect_failure", &addr.to_string()) {
+                        LogRateLimit::Emit => {
+                            log::warn!("failed to connect to {} {}", addr, event.peer_id)
+                        }
+                        LogRateLimit::Summary { repeats, window } => log::warn!(
+                            "failed to connect to {} {} (repeated {} times in the last {:?})",
+                            addr,
+                            event.peer_id,
+                            repeats,
+                            window
+                        ),
+                        LogRateLimit::Suppress => {}
+                    }
                     self.cleanup_address(Some(&event.peer_id), addr)
                 }
             }
@@ -603,8 +1601,12 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
             FromSwarm::ListenerClosed(_) => {}
 
             FromSwarm::NewExternalAddrCandidate(_) => {}
-            FromSwarm::ExternalAddrConfirmed(_) => {}
-            FromSwarm::ExternalAddrExpired(_) => {}
+            FromSwarm::ExternalAddrConfirmed(event) => {
+                self.on_new_external_addr(event.addr);
+            }
+            FromSwarm::ExternalAddrExpired(event) => {
+                self.external_addresses.remove(event.addr);
+            }
             e => {
                 tracing::warn!("Unexpected event {:?}", e);
                 #[cfg(test)]
@@ -621,6 +1623,18 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
     ) {
         match event {
             Ok(HandlerMessage::InParticle(particle)) => {
+                if let Some(air_version) = self.incompatible_air_version(&from) {
+                    tracing::warn!(
+                        target: "blocked",
+                        particle_id = particle.id,
+                        "Rejecting particle from {}: AIR interpreter version {} is outside the supported range",
+                        from,
+                        air_version
+                    );
+                    self.meter(|m| m.count_version_mismatch(ParticleDirection::Inbound));
+                    return;
+                }
+                self.touch_client_activity(&from);
                 tracing::info!(target: "network", particle_id = particle.id,"{}: received particle from {}; queue {}", self.peer_id, from, self.queue.len());
                 let root_span = tracing::info_span!("Particle", particle_id = particle.id);
 
@@ -631,8 +1645,10 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
                         particle.data.len() as f64,
                     )
                 });
+                let particle_type = ParticleType::from_particle(&particle.id);
+                *self.particle_counts.entry(particle_type).or_default() += 1;
                 self.queue
-                    .push_back(ExtendedParticle::new(particle, root_span));
+                    .push_back((Instant::now(), ExtendedParticle::new(particle, root_span)));
                 self.wake();
             }
             Ok(HandlerMessage::Upgrade) => {}
@@ -644,14 +1660,19 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<SwarmEventType> {
         self.waker = Some(cx.waker().clone());
 
+        // cloned to be usable while `self.outlet` is mutably borrowed below
+        let metrics = self.metrics.clone();
         loop {
             // Check backpressure on the outlet
             let mut outlet = Pin::new(&mut self.outlet);
             match outlet.as_mut().poll_ready(cx) {
                 Poll::Ready(Ok(_)) => {
                     // channel is ready to consume more particles, so send them
-                    if let Some(particle) = self.queue.pop_front() {
+                    if let Some((enqueued, particle)) = self.queue.pop_front() {
                         let particle_id = particle.particle.id.clone();
+                        if let Some(m) = metrics.as_ref() {
+                            m.queue_wait_sec.observe(enqueued.elapsed().as_secs_f64());
+                        }
 
                         if let Err(err) = outlet.start_send(particle) {
                             tracing::error!(
@@ -679,22 +1700,79 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
                         log::trace!(target: "network", "Connection pool outlet is pending; queue {}", len);
                     }
                     if self.outlet.is_closed() {
-                        log::error!("Particle outlet closed");
+                        self.on_outlet_closed();
                     }
                     break;
                 }
                 Poll::Ready(Err(err)) => {
                     log::warn!("ConnectionPool particle inlet has been dropped: {}", err);
+                    self.on_outlet_closed();
                     break;
                 }
             }
         }
 
+        // unpark buffered self-sends once the queue has capacity again
+        while self.queue.len() < self.queue_capacity {
+            let Some((particle, outlet)) = self.buffered_sends.pop_front() else {
+                break;
+            };
+            tracing::debug!(
+                particle_id = particle.particle.id,
+                "Queue drained, enqueueing parked self-addressed particle"
+            );
+            self.queue.push_back((Instant::now(), particle));
+            outlet.send(SendStatus::Ok).ok();
+        }
+
         self.meter(|m| m.particle_queue_size.set(self.queue.len() as i64));
         while let Poll::Ready(Some(cmd)) = self.commands.poll_next_unpin(cx) {
             self.execute(cmd)
         }
 
+        // sends whose substream upgrade failed come back here after
+        // [UPGRADE_RETRY_DELAY] and go out for another attempt
+        while let Poll::Ready(Some(retry)) = self.upgrade_retries.poll_next_unpin(cx) {
+            if let Some((to, particle, outlet, retries_left)) = retry {
+                // the peer may have disconnected while the retry was pending
+                if !self.contacts.contains_key(&to) {
+                    outlet.send(SendStatus::NotConnected).ok();
+                    continue;
+                }
+                tracing::debug!(
+                    target: "network",
+                    particle_id = particle.id,
+                    "Retrying send to {} after a failed substream upgrade; {} retries left",
+                    to,
+                    retries_left
+                );
+                self.meter(|m| m.upgrade_retries.inc());
+                self.send_to_handler(to, particle, outlet, retries_left);
+            }
+        }
+
+        // rate-limited sends go out once their delay has elapsed
+        while let Poll::Ready(Some((to, particle, outlet, retries_left))) =
+            self.throttled_sends.poll_next_unpin(cx)
+        {
+            // the peer may have disconnected while the send was delayed
+            if !self.contacts.contains_key(&to) {
+                outlet.send(SendStatus::NotConnected).ok();
+                continue;
+            }
+            self.send_to_handler(to, particle, outlet, retries_left);
+        }
+
+        if let Some(idle_check) = self.idle_check.as_mut() {
+            let mut ticked = false;
+            while idle_check.poll_tick(cx).is_ready() {
+                ticked = true;
+            }
+            if ticked {
+                self.close_idle_clients();
+            }
+        }
+
         if let Some(event) = self.events.pop_front() {
             return Poll::Ready(event);
         }
@@ -702,3 +1780,1872 @@ impl NetworkBehaviour for ConnectionPoolBehaviour {
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use particle_protocol::Particle;
+    use prometheus_client::registry::Registry;
+
+    #[tokio::test]
+    async fn count_dropped_particles_when_outlet_is_closed() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, inlet, _api) = ConnectionPoolBehaviour::new(
+            1,
+            128,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+        // close the outlet: every queued particle is now undeliverable
+        drop(inlet);
+
+        for _ in 0..3 {
+            behaviour.queue.push_back((
+                Instant::now(),
+                ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+            ));
+        }
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+
+        assert_eq!(metrics.particles_dropped_outlet_closed.get(), 3);
+        assert!(behaviour.queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn outbound_particle_is_counted_and_its_size_observed() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let remote = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour.add_connected_address(remote, maddr);
+
+        let particle = Particle {
+            data: vec![0; 500],
+            ..Particle::default()
+        };
+        let (out, _status) = oneshot::channel();
+        behaviour.send(
+            Contact::new(remote, vec![]),
+            ExtendedParticle::new(particle, tracing::Span::none()),
+            out,
+        );
+
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded.contains("connection_pool_sent_particles_total{particle_type=\"Common\"} 1"),
+            "no sent particle counted in {encoded}"
+        );
+        assert!(
+            encoded
+                .contains("connection_pool_message_sizes_sent_count{particle_type=\"Common\"} 1"),
+            "no outbound size observation in {encoded}"
+        );
+        assert!(
+            encoded
+                .contains("connection_pool_message_sizes_sent_sum{particle_type=\"Common\"} 500"),
+            "outbound size must equal the particle data length in {encoded}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn commands_time_out_when_behaviour_is_stalled() {
+        use crate::ConnectionPoolT;
+
+        let (_behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            1,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        // the behaviour is never polled, so the first command occupies
+        // the only slot in the command channel forever
+        tokio::spawn(api.is_connected(PeerId::random()));
+        tokio::task::yield_now().await;
+
+        let sent = api
+            .send(
+                Contact::new(PeerId::random(), vec![]),
+                ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+            )
+            .await;
+        assert!(
+            matches!(sent, SendStatus::CommandQueueTimedOut),
+            "expected CommandQueueTimedOut, got {sent:?}"
+        );
+    }
+
+    /// Covers the send-timeout accounting path in [ConnectionPoolApi::send]:
+    /// a chaos-injected delay longer than `send_timeout` must surface as
+    /// [SendStatus::TimedOut] carrying the configured timeout, while the same
+    /// send resolves `Ok` without the rule
+    #[cfg(feature = "chaos")]
+    #[tokio::test(start_paused = true)]
+    async fn injected_send_delay_is_accounted_as_timeout() {
+        use crate::ConnectionPoolT;
+
+        let peer_id = PeerId::random();
+        let send_timeout = Duration::from_secs(1);
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            peer_id,
+            None,
+            send_timeout,
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let send = |api: &ConnectionPoolApi| {
+            api.send(
+                Contact::new(peer_id, vec![]),
+                ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+            )
+        };
+
+        // without a fault rule a self-send resolves `Ok`
+        // as soon as the behaviour picks up the command
+        let sent = tokio::spawn(send(&api));
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        let sent = sent.await.unwrap();
+        assert!(matches!(sent, SendStatus::Ok), "expected Ok, got {sent:?}");
+
+        let point = format!("connection-pool::send::{peer_id}");
+        chaos::install(&point, chaos::FaultRule::Delay(Duration::from_secs(2)));
+        let sent = send(&api).await;
+        chaos::clear(&point);
+        assert!(
+            matches!(sent, SendStatus::TimedOut { after, .. } if after == send_timeout),
+            "expected TimedOut after {send_timeout:?}, got {sent:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn buffered_self_send_resolves_once_the_queue_drains() {
+        let peer_id = PeerId::random();
+        let (mut behaviour, mut inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            peer_id,
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        // fill the queue past its capacity of 1
+        for _ in 0..2 {
+            behaviour.queue.push_back((
+                Instant::now(),
+                ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+            ));
+        }
+
+        let buffered = Particle {
+            id: "buffered".to_string(),
+            ..Particle::default()
+        };
+        let send = {
+            let api = api.clone();
+            tokio::spawn(async move {
+                api.send_buffered(
+                    Contact::new(peer_id, vec![]),
+                    ExtendedParticle::new(buffered, tracing::Span::none()),
+                )
+                .await
+            })
+        };
+        // let the command reach the behaviour: the queue is full,
+        // so the send is parked instead of resolving
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        tokio::task::yield_now().await;
+        assert_eq!(behaviour.buffered_sends.len(), 1);
+        assert!(!send.is_finished(), "parked send must not resolve yet");
+
+        // drain the queue; the parked send is enqueued and resolves Ok
+        let mut delivered = vec![];
+        for _ in 0..10 {
+            while let Ok(particle) = inlet.try_recv() {
+                delivered.push(particle.particle.id);
+            }
+            let _ = behaviour.poll(&mut cx);
+            tokio::task::yield_now().await;
+            if send.is_finished() {
+                break;
+            }
+        }
+        let status = send.await.unwrap();
+        assert!(matches!(status, SendStatus::Ok), "send failed: {status:?}");
+        assert!(behaviour.buffered_sends.is_empty());
+
+        // the buffered particle itself reaches execution
+        for _ in 0..10 {
+            let _ = behaviour.poll(&mut cx);
+            while let Ok(particle) = inlet.try_recv() {
+                delivered.push(particle.particle.id);
+            }
+            if delivered.iter().any(|id| id == "buffered") {
+                break;
+            }
+        }
+        assert!(
+            delivered.iter().any(|id| id == "buffered"),
+            "buffered particle must be delivered, got {delivered:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_connection_resolves_on_connect() {
+        use std::time::Duration;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer_id = PeerId::random();
+        let wait = {
+            let api = api.clone();
+            tokio::spawn(async move {
+                api.wait_for_connection(peer_id, Duration::from_secs(5))
+                    .await
+            })
+        };
+        // let the waiter enqueue its command, then register it in the behaviour
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        assert_eq!(behaviour.connection_waiters.len(), 1);
+
+        // report an established connection the way the swarm does
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                peer_id,
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+
+        let contact = wait.await.unwrap().expect("waiter must be resolved");
+        assert_eq!(contact.peer_id, peer_id);
+        assert_eq!(contact.addresses, vec![maddr]);
+        assert!(behaviour.connection_waiters.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_connection_times_out() {
+        use crate::api::WaitError;
+        use std::time::Duration;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer_id = PeerId::random();
+        let timeout = Duration::from_millis(100);
+        let wait = {
+            let api = api.clone();
+            tokio::spawn(async move { api.wait_for_connection(peer_id, timeout).await })
+        };
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+
+        // the peer never connects
+        let result = wait.await.unwrap();
+        assert!(
+            matches!(result, Err(WaitError::Timeout { .. })),
+            "expected Timeout, got {result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_disconnected_resolves_on_disconnect() {
+        use std::time::Duration;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer_id = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                peer_id,
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+
+        let wait = {
+            let api = api.clone();
+            tokio::spawn(
+                async move { api.wait_disconnected(peer_id, Duration::from_secs(5)).await },
+            )
+        };
+        // let the waiter enqueue its command, then register it in the behaviour
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        assert_eq!(behaviour.disconnect_waiters.len(), 1);
+
+        behaviour.remove_contact(&peer_id, "test disconnect");
+
+        wait.await.unwrap().expect("waiter must be resolved");
+        assert!(behaviour.disconnect_waiters.is_empty());
+
+        // a peer that isn't connected resolves immediately
+        let wait = {
+            let api = api.clone();
+            tokio::spawn(
+                async move { api.wait_disconnected(peer_id, Duration::from_secs(5)).await },
+            )
+        };
+        tokio::task::yield_now().await;
+        let _ = behaviour.poll(&mut cx);
+        wait.await.unwrap().expect("must resolve immediately");
+        assert!(behaviour.disconnect_waiters.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_disconnected_times_out() {
+        use crate::api::WaitError;
+        use std::time::Duration;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer_id = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                peer_id,
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+
+        let timeout = Duration::from_millis(100);
+        let wait = {
+            let api = api.clone();
+            tokio::spawn(async move { api.wait_disconnected(peer_id, timeout).await })
+        };
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+
+        // the peer never disconnects
+        let result = wait.await.unwrap();
+        assert!(
+            matches!(result, Err(WaitError::Timeout { .. })),
+            "expected Timeout, got {result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn queue_wait_time_is_observed() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, mut inlet, _api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        // while the outlet is held (not consumed), the particle waits in the queue
+        behaviour.queue.push_back((
+            Instant::now(),
+            ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+        ));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        // release: the outlet has capacity, so polling forwards the particle
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        assert!(inlet.try_recv().is_ok(), "particle must reach the outlet");
+
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded.contains("queue_wait_sec_count 1"),
+            "no queue_wait_sec observation in {encoded}"
+        );
+        let sum: f64 = encoded
+            .lines()
+            .find(|line| line.contains("queue_wait_sec_sum"))
+            .and_then(|line| line.split_whitespace().last())
+            .expect("queue_wait_sec_sum must be encoded")
+            .parse()
+            .unwrap();
+        assert!(sum > 0.0, "queue wait time must be non-zero, got {sum}");
+    }
+
+    #[tokio::test]
+    async fn dial_latency_is_observed_for_outbound_connections_only() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        // an inbound connection has no dial start recorded, so nothing is observed
+        let inbound: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                PeerId::random(),
+                &inbound,
+                &inbound,
+            )
+            .unwrap();
+
+        // dial an address, let the dial take a moment, then report it connected
+        let dialed: Multiaddr = "/ip4/127.0.0.1/tcp/12346".parse().unwrap();
+        let (out, _contact) = oneshot::channel();
+        behaviour.dial(dialed.clone(), out);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        behaviour
+            .handle_established_outbound_connection(
+                ConnectionId::new_unchecked(1),
+                PeerId::random(),
+                &dialed,
+                Endpoint::Dialer,
+            )
+            .unwrap();
+
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded.contains("dial_latency_sec_count 1"),
+            "exactly one dial latency observation expected in {encoded}"
+        );
+        let sum: f64 = encoded
+            .lines()
+            .find(|line| line.contains("dial_latency_sec_sum"))
+            .and_then(|line| line.split_whitespace().last())
+            .expect("dial_latency_sec_sum must be encoded")
+            .parse()
+            .unwrap();
+        assert!(sum > 0.0, "dial latency must be non-zero, got {sum}");
+    }
+
+    #[tokio::test]
+    async fn peer_history_records_lifecycle_events() {
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer_id = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        // connect, disconnect and reconnect the peer a few times
+        for n in 0..3 {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer_id,
+                    &maddr,
+                    &maddr,
+                )
+                .unwrap();
+            if n < 2 {
+                behaviour.remove_contact(&peer_id, "test disconnect");
+            }
+        }
+
+        let get = {
+            let api = api.clone();
+            tokio::spawn(async move { api.get_peer_history(peer_id).await })
+        };
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        let history = get.await.unwrap();
+
+        let kinds: Vec<_> = history
+            .iter()
+            .map(|(_, event)| match event {
+                LifecycleEvent::Connected(contact) => {
+                    assert_eq!(contact.peer_id, peer_id);
+                    "connected"
+                }
+                LifecycleEvent::Disconnected(contact) => {
+                    assert_eq!(contact.peer_id, peer_id);
+                    "disconnected"
+                }
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "connected",
+                "disconnected",
+                "connected",
+                "disconnected",
+                "connected"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_link_tops_the_slow_links_report() {
+        use crate::ConnectionPoolT;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_millis(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let fast = PeerId::random();
+        let slow = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        for (n, peer) in [fast, slow].into_iter().enumerate() {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer,
+                    &maddr,
+                    &maddr,
+                )
+                .unwrap();
+        }
+
+        for (peer, delay) in [
+            (fast, Duration::from_millis(5)),
+            (slow, Duration::from_millis(60)),
+        ] {
+            let send = {
+                let api = api.clone();
+                tokio::spawn(async move {
+                    api.send(
+                        Contact::new(peer, vec![]),
+                        ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+                    )
+                    .await
+                })
+            };
+            // let the send command reach the behaviour, then dispatch it
+            tokio::task::yield_now().await;
+            let waker = futures::task::noop_waker();
+            let mut cx = Context::from_waker(&waker);
+            let outlet = loop {
+                match behaviour.poll(&mut cx) {
+                    Poll::Ready(ToSwarm::NotifyHandler {
+                        event: HandlerMessage::OutParticle(_, CompletionChannel::Oneshot(outlet)),
+                        ..
+                    }) => break outlet,
+                    Poll::Ready(_) => continue,
+                    Poll::Pending => panic!("send wasn't dispatched to the handler"),
+                }
+            };
+            // inject per-link delay before the handler reports completion
+            tokio::time::sleep(delay).await;
+            outlet.send(SendStatus::Ok).unwrap();
+            let status = send.await.unwrap();
+            assert!(matches!(status, SendStatus::Ok), "send failed: {status:?}");
+        }
+
+        let report = api.slow_links(10);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].peer_id, slow.to_base58());
+        assert_eq!(report[1].peer_id, fast.to_base58());
+        assert!(
+            report[0].p95_ms >= 60,
+            "implausible p95 for the slow link: {}ms",
+            report[0].p95_ms
+        );
+        assert_eq!(report[0].samples, 1);
+    }
+
+    #[tokio::test]
+    async fn command_queue_delay_is_observed() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let (out, count) = oneshot::channel();
+        api.outlet
+            .send(EnqueuedCommand {
+                command: Command::CountConnections { out },
+                enqueued: std::time::Instant::now(),
+            })
+            .await
+            .unwrap();
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        assert_eq!(count.await.unwrap(), 0);
+
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded.contains("command_queue_delay_count{kind=\"count_connections\"} 1"),
+            "no command_queue_delay observation in {encoded}"
+        );
+    }
+
+    #[tokio::test]
+    async fn updated_protocol_config_applies_to_new_connections() {
+        use libp2p::swarm::ConnectionHandler;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let updated = ProtocolConfig::new(Duration::from_secs(42), Duration::from_secs(43));
+        let update = {
+            let api = api.clone();
+            let updated = updated.clone();
+            tokio::spawn(async move { api.update_protocol_config(updated).await })
+        };
+        // let the command reach the behaviour, then dispatch it
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        update.await.unwrap();
+
+        // a connection established after the update uses the new config
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        let handler = behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                PeerId::random(),
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+        assert_eq!(
+            handler.listen_protocol().timeout(),
+            &updated.upgrade_timeout
+        );
+    }
+
+    #[tokio::test]
+    async fn confirmed_external_addresses_are_advertised_in_the_local_contact() {
+        use crate::ConnectionPoolT;
+        use libp2p::swarm::{ExternalAddrConfirmed, ExternalAddrExpired};
+
+        let peer_id = PeerId::random();
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            ProtocolConfig::default(),
+            peer_id,
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let addr1: Multiaddr = "/ip4/1.2.3.4/tcp/7777".parse().unwrap();
+        let addr2: Multiaddr = "/ip4/1.2.3.4/tcp/7778".parse().unwrap();
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
+            addr: &addr1,
+        }));
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
+            addr: &addr2,
+        }));
+
+        let get = {
+            let api = api.clone();
+            tokio::spawn(async move { api.get_my_contact().await })
+        };
+        // let the command reach the behaviour, then dispatch it
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        let contact = get.await.unwrap().expect("local contact must be resolved");
+        assert_eq!(contact.peer_id, peer_id);
+        let addresses: HashSet<_> = contact.addresses.into_iter().collect();
+        assert_eq!(addresses, [addr1.clone(), addr2.clone()].into());
+
+        // an expired address is no longer advertised; `get_contact` on the
+        // local peer id returns the same record
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrExpired(ExternalAddrExpired {
+            addr: &addr2,
+        }));
+        let get = {
+            let api = api.clone();
+            tokio::spawn(async move { api.get_contact(peer_id).await })
+        };
+        tokio::task::yield_now().await;
+        let _ = behaviour.poll(&mut cx);
+        let contact = get.await.unwrap().expect("local contact must be known");
+        assert_eq!(contact.addresses, vec![addr1]);
+    }
+
+    #[tokio::test]
+    async fn incompatible_air_version_is_refused_both_ways() {
+        use crate::ConnectionPoolT;
+
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let policy = AirVersionPolicy {
+            min_air_version: Some("0.50.0".parse().unwrap()),
+            max_air_version: None,
+        };
+        let (mut behaviour, mut inlet, api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            policy,
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let ancient = PeerId::random();
+        let modern = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        for (n, peer) in [ancient, modern].into_iter().enumerate() {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer,
+                    &maddr,
+                    &maddr,
+                )
+                .unwrap();
+        }
+        behaviour.set_air_version(ancient, Some("0.33.0".parse().unwrap()));
+        behaviour.set_air_version(modern, Some("0.55.0".parse().unwrap()));
+
+        // inbound: the ancient peer's particle is dropped before it reaches the queue
+        behaviour.on_connection_handler_event(
+            ancient,
+            ConnectionId::new_unchecked(0),
+            Ok(HandlerMessage::InParticle(Particle::default())),
+        );
+        assert!(behaviour.queue.is_empty());
+        // while the modern peer's particle flows through
+        behaviour.on_connection_handler_event(
+            modern,
+            ConnectionId::new_unchecked(1),
+            Ok(HandlerMessage::InParticle(Particle::default())),
+        );
+        assert_eq!(behaviour.queue.len(), 1);
+
+        // outbound: the forward to the ancient peer is skipped
+        let send = {
+            let api = api.clone();
+            tokio::spawn(async move {
+                api.send(
+                    Contact::new(ancient, vec![]),
+                    ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+                )
+                .await
+            })
+        };
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        let status = send.await.unwrap();
+        assert!(
+            matches!(status, SendStatus::VersionMismatch),
+            "expected VersionMismatch, got {status:?}"
+        );
+        assert!(inlet.try_recv().is_ok(), "modern particle must be queued");
+
+        // both refusals are counted, split by direction
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded.contains("version_mismatches_total{direction=\"Inbound\"} 1"),
+            "no inbound version mismatch in {encoded}"
+        );
+        assert!(
+            encoded.contains("version_mismatches_total{direction=\"Outbound\"} 1"),
+            "no outbound version mismatch in {encoded}"
+        );
+    }
+
+    /// Polls the behaviour and returns peers of all `CloseConnection` events produced so far
+    fn drain_closed_connections(behaviour: &mut ConnectionPoolBehaviour) -> Vec<PeerId> {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut closed = vec![];
+        while let Poll::Ready(event) = behaviour.poll(&mut cx) {
+            if let ToSwarm::CloseConnection { peer_id, .. } = event {
+                closed.push(peer_id);
+            }
+        }
+        closed
+    }
+
+    /// A particle initiated by `init_peer_id` that stays alive for `ttl` ms
+    fn particle_from(init_peer_id: PeerId, id: &str, ttl: u32) -> Particle {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        Particle {
+            id: id.to_string(),
+            init_peer_id,
+            timestamp,
+            ttl,
+            ..Particle::default()
+        }
+    }
+
+    fn spilling_behaviour(
+        peer_id: PeerId,
+        spill: QueueSpillConfig,
+        metrics: Option<ConnectionPoolMetrics>,
+    ) -> (
+        ConnectionPoolBehaviour,
+        mpsc::Receiver<ExtendedParticle>,
+        ConnectionPoolApi,
+    ) {
+        ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            peer_id,
+            metrics,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            Some(spill),
+        )
+    }
+
+    #[tokio::test]
+    async fn local_particles_survive_a_restart_through_the_spill_file() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let data_dir = tempfile::tempdir().unwrap();
+        let spill = QueueSpillConfig {
+            path: data_dir.path().join("particles_spill.json"),
+            max_size: 1024 * 1024,
+        };
+        let host = PeerId::random();
+        let worker = PeerId::random();
+
+        let (mut behaviour, _inlet, _api) =
+            spilling_behaviour(host, spill.clone(), Some(metrics.clone()));
+        for particle in [
+            particle_from(host, "spell_host", 60_000),
+            particle_from(worker, "spell_worker", 60_000),
+            // a remote particle must not be persisted
+            particle_from(PeerId::random(), "remote", 60_000),
+            // an expired local one must be dropped and counted
+            Particle {
+                id: "expired".to_string(),
+                init_peer_id: host,
+                timestamp: 0,
+                ttl: 0,
+                ..Particle::default()
+            },
+        ] {
+            behaviour
+                .queue
+                .push_back((Instant::now(), ExtendedParticle::new(particle, tracing::Span::none())));
+        }
+
+        // graceful shutdown: only the two live local particles are persisted
+        let spilled = behaviour.spill_queue(&[worker].into());
+        assert_eq!(spilled, 2);
+        assert_eq!(metrics.spilled_particles_expired.get(), 1);
+        drop(behaviour);
+
+        // restart with the same data dir: the particles are re-enqueued
+        // and reach execution
+        let (mut behaviour, mut inlet, _api) = spilling_behaviour(host, spill.clone(), None);
+        assert_eq!(behaviour.queue.len(), 2);
+        assert!(
+            !spill.path.exists(),
+            "spill file must be removed after reload"
+        );
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+        let ids: Vec<_> = std::iter::from_fn(|| inlet.try_recv().ok())
+            .map(|particle| particle.particle.id)
+            .collect();
+        assert_eq!(ids, vec!["spell_host", "spell_worker"]);
+    }
+
+    #[tokio::test]
+    async fn spill_respects_the_size_cap_and_counts_drops() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let data_dir = tempfile::tempdir().unwrap();
+        let spill = QueueSpillConfig {
+            path: data_dir.path().join("particles_spill.json"),
+            max_size: 512,
+        };
+        let host = PeerId::random();
+
+        let (mut behaviour, _inlet, _api) =
+            spilling_behaviour(host, spill.clone(), Some(metrics.clone()));
+        let small = particle_from(host, "small", 60_000);
+        let huge = Particle {
+            data: vec![0; 10_000],
+            ..particle_from(host, "huge", 60_000)
+        };
+        for particle in [small, huge] {
+            behaviour
+                .queue
+                .push_back((Instant::now(), ExtendedParticle::new(particle, tracing::Span::none())));
+        }
+
+        let spilled = behaviour.spill_queue(&HashSet::new());
+        assert_eq!(spilled, 1, "only the small particle fits under the cap");
+        assert_eq!(metrics.spilled_particles_dropped.get(), 1);
+
+        let (behaviour, _inlet, _api) = spilling_behaviour(host, spill, None);
+        assert_eq!(behaviour.queue.len(), 1);
+        assert_eq!(behaviour.queue[0].1.particle.id, "small");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_client_is_closed_while_active_client_and_relay_persist() {
+        let relay_maddr: Multiaddr = "/ip4/10.0.0.1/tcp/7777".parse().unwrap();
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig {
+                relay_addresses: [relay_maddr.clone()].into(),
+                idle_timeout: Some(Duration::from_secs(1)),
+                max_sessions: None,
+            },
+            None,
+        );
+
+        let silent = PeerId::random();
+        let active = PeerId::random();
+        let relay = PeerId::random();
+        for (n, (peer, maddr)) in [
+            (silent, "/ip4/127.0.0.1/tcp/12345".parse().unwrap()),
+            (active, "/ip4/127.0.0.1/tcp/12346".parse().unwrap()),
+            (relay, relay_maddr),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer,
+                    &maddr,
+                    &maddr,
+                )
+                .unwrap();
+        }
+        assert!(drain_closed_connections(&mut behaviour).is_empty());
+
+        // keep the active client alive with a particle just before its deadline
+        tokio::time::advance(Duration::from_millis(600)).await;
+        behaviour.on_connection_handler_event(
+            active,
+            ConnectionId::new_unchecked(1),
+            Ok(HandlerMessage::InParticle(Particle::default())),
+        );
+
+        // by now the silent client is past the timeout, the active one is not
+        tokio::time::advance(Duration::from_millis(600)).await;
+        let closed = drain_closed_connections(&mut behaviour);
+        assert_eq!(closed, vec![silent], "only the silent client must be closed");
+        assert_eq!(metrics.client_sessions_idle_closed.get(), 1);
+
+        // the relay stays untouched no matter how long it is silent
+        tokio::time::advance(Duration::from_secs(60)).await;
+        let closed = drain_closed_connections(&mut behaviour);
+        assert!(
+            !closed.contains(&relay),
+            "relay connection must never be closed as idle"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exceeding_session_cap_evicts_the_idlest_client() {
+        let relay_maddr: Multiaddr = "/ip4/10.0.0.1/tcp/7777".parse().unwrap();
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig {
+                relay_addresses: [relay_maddr.clone()].into(),
+                idle_timeout: None,
+                max_sessions: Some(2),
+            },
+            None,
+        );
+
+        let connect = |behaviour: &mut ConnectionPoolBehaviour, n, peer, maddr: &Multiaddr| {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer,
+                    maddr,
+                    maddr,
+                )
+                .unwrap();
+        };
+
+        // the relay doesn't count toward the cap
+        let relay = PeerId::random();
+        connect(&mut behaviour, 0, relay, &relay_maddr);
+
+        let client1 = PeerId::random();
+        let client2 = PeerId::random();
+        connect(
+            &mut behaviour,
+            1,
+            client1,
+            &"/ip4/127.0.0.1/tcp/12345".parse().unwrap(),
+        );
+        tokio::time::advance(Duration::from_millis(100)).await;
+        connect(
+            &mut behaviour,
+            2,
+            client2,
+            &"/ip4/127.0.0.1/tcp/12346".parse().unwrap(),
+        );
+        assert!(drain_closed_connections(&mut behaviour).is_empty());
+
+        // a particle from client1 makes client2 the idlest one
+        tokio::time::advance(Duration::from_millis(100)).await;
+        behaviour.on_connection_handler_event(
+            client1,
+            ConnectionId::new_unchecked(1),
+            Ok(HandlerMessage::InParticle(Particle::default())),
+        );
+
+        // the third client exceeds the cap: client2 is evicted, the relay is not
+        let client3 = PeerId::random();
+        connect(
+            &mut behaviour,
+            3,
+            client3,
+            &"/ip4/127.0.0.1/tcp/12347".parse().unwrap(),
+        );
+        let closed = drain_closed_connections(&mut behaviour);
+        assert_eq!(closed, vec![client2], "the idlest client must be evicted");
+        assert_eq!(metrics.client_sessions_evicted.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn discovered_addresses_are_capped_and_prefer_the_newest() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            Some(metrics),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        // a NATed client rotating its ephemeral port on every Identify
+        let peer_id = PeerId::random();
+        let maddr = |port: usize| -> Multiaddr {
+            format!("/ip4/1.2.3.4/tcp/{}", 10000 + port).parse().unwrap()
+        };
+        let updates = 100;
+        for port in 0..updates {
+            behaviour.add_discovered_addresses(peer_id, vec![maddr(port)]);
+        }
+
+        let discovered = &behaviour.contacts[&peer_id].discovered;
+        assert_eq!(discovered.len(), MAX_DISCOVERED_ADDRESSES);
+        for port in updates - MAX_DISCOVERED_ADDRESSES..updates {
+            assert!(
+                discovered.contains_key(&maddr(port)),
+                "the most recently observed address {} must be retained",
+                maddr(port)
+            );
+        }
+
+        // every update observed the per-peer set size
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded.contains(&format!("discovered_addresses_per_peer_count {updates}")),
+            "no per-peer address count observations in {encoded}"
+        );
+    }
+
+    #[tokio::test]
+    async fn loopback_addresses_of_remote_peers_are_not_retained() {
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let loopback: Multiaddr = "/ip4/127.0.0.1/tcp/7777".parse().unwrap();
+        let link_local: Multiaddr = "/ip6/fe80::1/tcp/7777".parse().unwrap();
+        let global: Multiaddr = "/ip4/1.2.3.4/tcp/7777".parse().unwrap();
+
+        // a remote peer advertising loopback/link-local addresses: those
+        // are not dialable from here and must be dropped
+        let remote = PeerId::random();
+        behaviour.add_connected_address(remote, global.clone());
+        behaviour.add_discovered_addresses(
+            remote,
+            vec![loopback.clone(), link_local, "/ip4/5.6.7.8/tcp/7777".parse().unwrap()],
+        );
+        let discovered = &behaviour.contacts[&remote].discovered;
+        assert_eq!(discovered.len(), 1);
+        assert!(discovered.contains_key(&"/ip4/5.6.7.8/tcp/7777".parse().unwrap()));
+
+        // a peer connected over loopback runs on this host,
+        // so its loopback addresses stay usable
+        let local = PeerId::random();
+        behaviour.add_connected_address(local, "/ip4/127.0.0.1/tcp/12345".parse().unwrap());
+        behaviour.add_discovered_addresses(local, vec![loopback.clone()]);
+        assert!(behaviour.contacts[&local].discovered.contains_key(&loopback));
+    }
+
+    #[tokio::test]
+    async fn inbound_particles_are_counted_per_type() {
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer = PeerId::random();
+        for (n, id) in ["spell_1_0", "spell_2_0", "ordinary-particle"]
+            .into_iter()
+            .enumerate()
+        {
+            behaviour.on_connection_handler_event(
+                peer,
+                ConnectionId::new_unchecked(n),
+                Ok(HandlerMessage::InParticle(Particle {
+                    id: id.to_string(),
+                    ..Particle::default()
+                })),
+            );
+        }
+
+        let counts = {
+            let api = api.clone();
+            tokio::spawn(async move { api.particle_counts().await })
+        };
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let _ = behaviour.poll(&mut cx);
+
+        let counts = counts.await.unwrap();
+        assert_eq!(counts.get(&ParticleType::Spell), Some(&2));
+        assert_eq!(counts.get(&ParticleType::Common), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn hop_count_grows_along_a_chain_and_cuts_routing_loops() {
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+
+        let config = ProtocolConfig {
+            max_hops: 3,
+            ..ProtocolConfig::default()
+        };
+        let next_peer = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+
+        // a chain of three forwarding nodes, each connected to the next peer
+        let mut nodes = vec![];
+        for _ in 0..3 {
+            let (mut behaviour, inlet, _api) = ConnectionPoolBehaviour::new(
+                8,
+                8,
+                config.clone(),
+                PeerId::random(),
+                Some(metrics.clone()),
+                Duration::from_secs(1),
+                AirVersionPolicy::default(),
+                ClientSessionConfig::default(),
+                None,
+            );
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(0),
+                    next_peer,
+                    &maddr,
+                    &maddr,
+                )
+                .unwrap();
+            nodes.push((behaviour, inlet));
+        }
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut particle = Particle::default();
+        for (i, (node, _inlet)) in nodes.iter_mut().enumerate() {
+            let (out, _status) = oneshot::channel();
+            node.send(
+                Contact::new(next_peer, vec![]),
+                ExtendedParticle::new(particle, tracing::Span::none()),
+                out,
+            );
+            particle = loop {
+                match node.poll(&mut cx) {
+                    Poll::Ready(ToSwarm::NotifyHandler {
+                        event: HandlerMessage::OutParticle(particle, _),
+                        ..
+                    }) => break particle,
+                    Poll::Ready(_) => continue,
+                    Poll::Pending => panic!("particle wasn't forwarded"),
+                }
+            };
+            assert_eq!(particle.hop_count, i as u32 + 1);
+            // every forwarding node leaves itself in the path trace
+            assert_eq!(particle.prev_relay(), Some(node.peer_id));
+        }
+        // each node of the chain added one hop
+        assert_eq!(particle.hop_count, 3);
+        assert_eq!(metrics.hop_limit_exceeded.get(), 0);
+
+        // one more forward exceeds max_hops: the loop is cut
+        let (node, _inlet) = &mut nodes[0];
+        let (out, status) = oneshot::channel();
+        node.send(
+            Contact::new(next_peer, vec![]),
+            ExtendedParticle::new(particle, tracing::Span::none()),
+            out,
+        );
+        let status = status.await.unwrap();
+        assert!(
+            matches!(status, SendStatus::HopLimitExceeded),
+            "expected HopLimitExceeded, got {status:?}"
+        );
+        assert_eq!(metrics.hop_limit_exceeded.get(), 1);
+    }
+
+    /// Polls the behaviour until it hands a particle to the handler,
+    /// sleeping between attempts so pending retry delays can elapse
+    async fn next_out_particle(
+        behaviour: &mut ConnectionPoolBehaviour,
+    ) -> (Particle, oneshot::Sender<SendStatus>) {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..100 {
+            match behaviour.poll(&mut cx) {
+                Poll::Ready(ToSwarm::NotifyHandler {
+                    event:
+                        HandlerMessage::OutParticle(particle, CompletionChannel::Oneshot(outlet)),
+                    ..
+                }) => return (particle, outlet),
+                Poll::Ready(_) => continue,
+                Poll::Pending => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+        panic!("no particle was dispatched to the handler");
+    }
+
+    #[tokio::test]
+    async fn failed_upgrade_is_retried_and_the_caller_sees_success() {
+        use crate::ConnectionPoolT;
+
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let config = ProtocolConfig {
+            max_upgrade_retries: 1,
+            ..ProtocolConfig::default()
+        };
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            config,
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                peer,
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+
+        let send = tokio::spawn(async move {
+            api.send(
+                Contact::new(peer, vec![]),
+                ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+            )
+            .await
+        });
+        // let the send command reach the behaviour
+        tokio::task::yield_now().await;
+
+        // the first attempt fails its substream upgrade
+        let (first, outlet) = next_out_particle(&mut behaviour).await;
+        outlet
+            .send(SendStatus::ProtocolError("connection reset".to_string()))
+            .unwrap();
+
+        // after [UPGRADE_RETRY_DELAY] the same particle goes out again;
+        // the retry is not another hop
+        let (second, outlet) = next_out_particle(&mut behaviour).await;
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.hop_count, first.hop_count);
+        outlet.send(SendStatus::Ok).unwrap();
+
+        let status = send.await.unwrap();
+        assert!(matches!(status, SendStatus::Ok), "send failed: {status:?}");
+        assert_eq!(metrics.upgrade_retries.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_surface_the_upgrade_failure() {
+        use crate::ConnectionPoolT;
+
+        let mut registry = Registry::default();
+        let metrics = ConnectionPoolMetrics::new(&mut registry);
+        let config = ProtocolConfig {
+            max_upgrade_retries: 1,
+            ..ProtocolConfig::default()
+        };
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            1,
+            8,
+            config,
+            PeerId::random(),
+            Some(metrics.clone()),
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                peer,
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+
+        let send = tokio::spawn(async move {
+            api.send(
+                Contact::new(peer, vec![]),
+                ExtendedParticle::new(Particle::default(), tracing::Span::none()),
+            )
+            .await
+        });
+        tokio::task::yield_now().await;
+
+        // both the first attempt and its single retry fail
+        for _ in 0..2 {
+            let (_particle, outlet) = next_out_particle(&mut behaviour).await;
+            outlet
+                .send(SendStatus::ProtocolError("connection reset".to_string()))
+                .unwrap();
+        }
+
+        let status = send.await.unwrap();
+        assert!(
+            matches!(status, SendStatus::ProtocolError(_)),
+            "expected ProtocolError, got {status:?}"
+        );
+        assert_eq!(metrics.upgrade_retries.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn peer_rate_limit_spaces_out_sends() {
+        let (mut behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let peer = PeerId::random();
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(0),
+                peer,
+                &maddr,
+                &maddr,
+            )
+            .unwrap();
+
+        // 1000 bytes/sec with 200-byte particles: the initial one-second
+        // burst covers five sends, each following one waits 200ms more
+        let (limit_set, _) = oneshot::channel();
+        behaviour.set_peer_rate_limit(peer, Some(1000), limit_set);
+        let particle = Particle {
+            data: vec![0; 200],
+            ..Particle::default()
+        };
+
+        let started = Instant::now();
+        for _ in 0..7 {
+            let (out, _status) = oneshot::channel();
+            behaviour.send(
+                Contact::new(peer, vec![]),
+                ExtendedParticle::new(particle.clone(), tracing::Span::none()),
+                out,
+            );
+        }
+        let mut dispatched_at = vec![];
+        for _ in 0..7 {
+            let (_particle, outlet) = next_out_particle(&mut behaviour).await;
+            dispatched_at.push(started.elapsed());
+            outlet.send(SendStatus::Ok).unwrap();
+        }
+
+        assert!(
+            dispatched_at[4] < Duration::from_millis(100),
+            "sends within the burst were delayed: {:?}",
+            dispatched_at[4]
+        );
+        assert!(
+            dispatched_at[5] >= Duration::from_millis(150),
+            "6th send wasn't delayed: {:?}",
+            dispatched_at[5]
+        );
+        assert!(
+            dispatched_at[6] >= Duration::from_millis(350),
+            "7th send wasn't delayed further: {:?}",
+            dispatched_at[6]
+        );
+
+        // removing the limit makes sends immediate again
+        let (limit_removed, _) = oneshot::channel();
+        behaviour.set_peer_rate_limit(peer, None, limit_removed);
+        let unlimited = Instant::now();
+        let (out, _status) = oneshot::channel();
+        behaviour.send(
+            Contact::new(peer, vec![]),
+            ExtendedParticle::new(particle, tracing::Span::none()),
+            out,
+        );
+        let (_particle, outlet) = next_out_particle(&mut behaviour).await;
+        assert!(
+            unlimited.elapsed() < Duration::from_millis(100),
+            "send after limit removal was delayed: {:?}",
+            unlimited.elapsed()
+        );
+        outlet.send(SendStatus::Ok).unwrap();
+    }
+
+    /// Lets an already issued API call reach the behaviour, executes it
+    /// and drains pending swarm events
+    async fn drive(behaviour: &mut ConnectionPoolBehaviour) {
+        tokio::task::yield_now().await;
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        while behaviour.poll(&mut cx).is_ready() {}
+    }
+
+    #[tokio::test]
+    async fn snapshot_captures_connections_and_dials() {
+        use crate::ConnectionPoolT;
+
+        let (mut behaviour, _inlet, api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig::default(),
+            None,
+        );
+
+        let maddr: Multiaddr = "/ip4/127.0.0.1/tcp/12345".parse().unwrap();
+        let peers = [PeerId::random(), PeerId::random()];
+        for (n, peer) in peers.into_iter().enumerate() {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer,
+                    &maddr,
+                    &maddr,
+                )
+                .unwrap();
+        }
+        let dial_maddr: Multiaddr = "/ip4/127.0.0.1/tcp/23456".parse().unwrap();
+        let (dial_out, _dial_inlet) = oneshot::channel();
+        behaviour.dial(dial_maddr.clone(), dial_out);
+
+        let snapshot = {
+            let api = api.clone();
+            tokio::spawn(async move { api.snapshot().await })
+        };
+        drive(&mut behaviour).await;
+        let snapshot = snapshot.await.unwrap();
+
+        let connections = {
+            let api = api.clone();
+            tokio::spawn(async move { api.count_connections().await })
+        };
+        drive(&mut behaviour).await;
+        let connections = connections.await.unwrap();
+
+        assert_eq!(snapshot.connected.len(), connections);
+        assert_eq!(snapshot.dialing, vec![dial_maddr.clone()]);
+        assert_eq!(snapshot.queue_depth, 0);
+        assert!(snapshot.metrics.particle_counts.is_empty());
+        assert_eq!(snapshot.metrics.buffered_sends, 0);
+        assert_eq!(snapshot.metrics.throttled_sends, 0);
+
+        // once the dial completes, it no longer shows up as in flight
+        behaviour.add_connected_address(PeerId::random(), dial_maddr);
+        let snapshot = {
+            let api = api.clone();
+            tokio::spawn(async move { api.snapshot().await })
+        };
+        drive(&mut behaviour).await;
+        let snapshot = snapshot.await.unwrap();
+
+        assert!(snapshot.dialing.is_empty());
+        assert_eq!(snapshot.connected.len(), 3);
+    }
+
+    /// Creates a behaviour where `relay_maddr` is a configured relay address
+    /// and relay re-dialing is enabled
+    fn keep_alive_behaviour(
+        relay_maddr: &Multiaddr,
+        worker_peer_ids: HashSet<PeerId>,
+    ) -> ConnectionPoolBehaviour {
+        let (behaviour, _inlet, _api) = ConnectionPoolBehaviour::new(
+            8,
+            8,
+            ProtocolConfig::default(),
+            PeerId::random(),
+            None,
+            Duration::from_secs(1),
+            AirVersionPolicy::default(),
+            ClientSessionConfig {
+                relay_addresses: std::iter::once(relay_maddr.clone()).collect(),
+                ..ClientSessionConfig::default()
+            },
+            None,
+        );
+        behaviour.with_keep_alive(KeepAliveConfig {
+            worker_peer_ids,
+            redial_relays: true,
+        })
+    }
+
+    /// Peer ids of the Dial events currently pending in the behaviour
+    fn pending_dials(behaviour: &ConnectionPoolBehaviour) -> Vec<Option<PeerId>> {
+        behaviour
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                ToSwarm::Dial { opts } => Some(opts.get_peer_id()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn relay_class_peer_is_redialed_on_unexpected_close() {
+        let relay_maddr: Multiaddr = "/ip4/127.0.0.1/tcp/7777".parse().unwrap();
+        let client_maddr: Multiaddr = "/ip4/127.0.0.1/tcp/8888".parse().unwrap();
+        let mut behaviour = keep_alive_behaviour(&relay_maddr, HashSet::new());
+
+        let relay_peer = PeerId::random();
+        let client_peer = PeerId::random();
+        behaviour
+            .handle_established_outbound_connection(
+                ConnectionId::new_unchecked(0),
+                relay_peer,
+                &relay_maddr,
+                Endpoint::Dialer,
+            )
+            .unwrap();
+        behaviour
+            .handle_established_inbound_connection(
+                ConnectionId::new_unchecked(1),
+                client_peer,
+                &client_maddr,
+                &client_maddr,
+            )
+            .unwrap();
+
+        // force-close both connections without a disconnect request
+        behaviour.on_connection_closed(
+            &relay_peer,
+            &ConnectedPoint::Dialer {
+                address: relay_maddr.clone(),
+                role_override: Endpoint::Dialer,
+            },
+            0,
+        );
+        behaviour.on_connection_closed(
+            &client_peer,
+            &ConnectedPoint::Listener {
+                local_addr: client_maddr.clone(),
+                send_back_addr: client_maddr,
+            },
+            0,
+        );
+
+        assert_eq!(
+            pending_dials(&behaviour),
+            vec![Some(relay_peer)],
+            "only the relay-class peer must be re-dialed"
+        );
+    }
+
+    #[tokio::test]
+    async fn deliberate_relay_disconnect_is_not_redialed() {
+        let relay_maddr: Multiaddr = "/ip4/127.0.0.1/tcp/7777".parse().unwrap();
+        let mut behaviour = keep_alive_behaviour(&relay_maddr, HashSet::new());
+
+        let relay_peer = PeerId::random();
+        behaviour
+            .handle_established_outbound_connection(
+                ConnectionId::new_unchecked(0),
+                relay_peer,
+                &relay_maddr,
+                Endpoint::Dialer,
+            )
+            .unwrap();
+
+        let (out, _disconnected) = oneshot::channel();
+        behaviour.disconnect(relay_peer, out);
+        behaviour.on_connection_closed(
+            &relay_peer,
+            &ConnectedPoint::Dialer {
+                address: relay_maddr,
+                role_override: Endpoint::Dialer,
+            },
+            0,
+        );
+
+        assert!(
+            pending_dials(&behaviour).is_empty(),
+            "a requested disconnect must not trigger a re-dial"
+        );
+    }
+
+    #[tokio::test]
+    async fn peer_stats_reports_keep_alive_class() {
+        let relay_maddr: Multiaddr = "/ip4/127.0.0.1/tcp/7777".parse().unwrap();
+        let client_maddr: Multiaddr = "/ip4/127.0.0.1/tcp/8888".parse().unwrap();
+        let worker_peer = PeerId::random();
+        let mut behaviour =
+            keep_alive_behaviour(&relay_maddr, std::iter::once(worker_peer).collect());
+
+        let relay_peer = PeerId::random();
+        let client_peer = PeerId::random();
+        for (n, (peer, maddr)) in [
+            (relay_peer, &relay_maddr),
+            (worker_peer, &client_maddr),
+            (client_peer, &client_maddr),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            behaviour
+                .handle_established_inbound_connection(
+                    ConnectionId::new_unchecked(n),
+                    peer,
+                    maddr,
+                    maddr,
+                )
+                .unwrap();
+        }
+
+        let class_of = |behaviour: &ConnectionPoolBehaviour, peer_id| {
+            let (out, inlet) = oneshot::channel();
+            behaviour.get_peer_stats(peer_id, out);
+            inlet.try_recv().unwrap().map(|stats| stats.class)
+        };
+
+        assert_eq!(class_of(&behaviour, relay_peer), Some(PeerClass::Relay));
+        assert_eq!(class_of(&behaviour, worker_peer), Some(PeerClass::Worker));
+        assert_eq!(class_of(&behaviour, client_peer), Some(PeerClass::Client));
+        assert_eq!(class_of(&behaviour, PeerId::random()), None);
+
+        // a disconnected client's class is forgotten
+        behaviour.on_connection_closed(
+            &client_peer,
+            &ConnectedPoint::Listener {
+                local_addr: client_maddr.clone(),
+                send_back_addr: client_maddr,
+            },
+            0,
+        );
+        assert_eq!(class_of(&behaviour, client_peer), None);
+    }
+}
@@ -30,7 +30,17 @@
 pub use api::ConnectionPoolApi;
 // to be available in benchmarks
 pub use api::Command;
+pub use api::EnqueuedCommand;
+pub use api::WaitError;
+pub use api::PeerClass;
+pub use api::PeerStats;
+pub use api::PoolStats;
+pub use api::Snapshot;
+pub use behaviour::ClientSessionConfig;
 pub use behaviour::ConnectionPoolBehaviour;
+pub use behaviour::KeepAliveConfig;
+pub use behaviour::QueueSpillConfig;
+pub use link_stats::LinkStat;
 
 pub use crate::connection_pool::ConnectionPoolT;
 pub use crate::connection_pool::LifecycleEvent;
@@ -38,3 +48,4 @@ pub use crate::connection_pool::LifecycleEvent;
 mod api;
 mod behaviour;
 mod connection_pool;
+mod link_stats;
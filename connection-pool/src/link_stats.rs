@@ -0,0 +1,194 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use libp2p::PeerId;
+use serde::Serialize;
+
+/// How many completion time samples are kept per link
+const MAX_SAMPLES: usize = 128;
+/// How many links are tracked before the least recently used one is evicted
+pub const MAX_TRACKED_LINKS: usize = 512;
+
+pub type SharedLinkStats = Arc<parking_lot::Mutex<LinkStatsRegistry>>;
+
+/// Send latency report for a single (local peer → remote peer) link
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct LinkStat {
+    pub peer_id: String,
+    /// 95th percentile of send completion time in milliseconds,
+    /// computed over the last [MAX_SAMPLES] sends
+    pub p95_ms: u64,
+    /// Total number of completed sends observed on this link
+    pub samples: u64,
+}
+
+/// Rolling reservoir of send completion times for a single link
+#[derive(Debug, Default)]
+struct LinkLatency {
+    /// Last [MAX_SAMPLES] send completion times
+    samples: VecDeque<Duration>,
+    /// Total number of observations, including evicted ones
+    total: u64,
+}
+
+impl LinkLatency {
+    fn observe(&mut self, latency: Duration) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+        self.total += 1;
+    }
+
+    fn p95(&self) -> Duration {
+        debug_assert!(!self.samples.is_empty());
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        // index of the 95th percentile, rounding up so that with few samples
+        // the worst observation is reported
+        let idx = (sorted.len() * 95).div_ceil(100).saturating_sub(1);
+        sorted[idx]
+    }
+}
+
+/// Per-link send latency accounting for the connection pool.
+/// Memory is bounded: at most `capacity` links are tracked, the least
+/// recently observed one is evicted when a new link shows up.
+#[derive(Debug)]
+pub struct LinkStatsRegistry {
+    links: HashMap<PeerId, LinkLatency>,
+    /// LRU order of tracked links: least recently observed first
+    order: VecDeque<PeerId>,
+    capacity: usize,
+}
+
+impl LinkStatsRegistry {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            links: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Records a send completion time for the link to `peer_id`.
+    /// Returns the link's current p95 and the peer evicted to make room, if any.
+    pub fn observe(&mut self, peer_id: PeerId, latency: Duration) -> (Duration, Option<PeerId>) {
+        let mut evicted = None;
+        if !self.links.contains_key(&peer_id) && self.links.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.links.remove(&oldest);
+                evicted = Some(oldest);
+            }
+        }
+        // refresh the link's position in the LRU order
+        self.order.retain(|id| id != &peer_id);
+        self.order.push_back(peer_id);
+
+        let link = self.links.entry(peer_id).or_default();
+        link.observe(latency);
+        (link.p95(), evicted)
+    }
+
+    /// Returns the top `count` links by p95 send latency, slowest first
+    pub fn slow_links(&self, count: usize) -> Vec<LinkStat> {
+        let mut report: Vec<_> = self
+            .links
+            .iter()
+            .map(|(peer_id, link)| (link.p95(), peer_id, link.total))
+            .collect();
+        report.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        report
+            .into_iter()
+            .take(count)
+            .map(|(p95, peer_id, samples)| LinkStat {
+                peer_id: peer_id.to_base58(),
+                p95_ms: p95.as_millis() as u64,
+                samples,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_link_tops_the_report() {
+        let mut registry = LinkStatsRegistry::new(MAX_TRACKED_LINKS);
+        let fast = PeerId::random();
+        let slow = PeerId::random();
+
+        for _ in 0..20 {
+            registry.observe(fast, Duration::from_millis(5));
+            registry.observe(slow, Duration::from_millis(80));
+        }
+        // a single spike on the fast link must not dominate its p95
+        registry.observe(fast, Duration::from_millis(500));
+
+        let report = registry.slow_links(10);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].peer_id, slow.to_base58());
+        assert_eq!(report[0].p95_ms, 80);
+        assert_eq!(report[0].samples, 20);
+        assert_eq!(report[1].peer_id, fast.to_base58());
+        assert_eq!(report[1].p95_ms, 5);
+        assert_eq!(report[1].samples, 21);
+    }
+
+    #[test]
+    fn least_recently_used_link_is_evicted() {
+        let mut registry = LinkStatsRegistry::new(2);
+        let first = PeerId::random();
+        let second = PeerId::random();
+        let third = PeerId::random();
+
+        registry.observe(first, Duration::from_millis(1));
+        registry.observe(second, Duration::from_millis(2));
+        // touch `first` so that `second` becomes the LRU link
+        registry.observe(first, Duration::from_millis(1));
+
+        let (_, evicted) = registry.observe(third, Duration::from_millis(3));
+        assert_eq!(evicted, Some(second));
+
+        let report = registry.slow_links(10);
+        assert_eq!(report.len(), 2);
+        assert!(report.iter().all(|link| link.peer_id != second.to_base58()));
+    }
+
+    #[test]
+    fn p95_over_rolling_window() {
+        let mut registry = LinkStatsRegistry::new(1);
+        let peer = PeerId::random();
+
+        // fill the whole window with slow samples, then displace them with fast ones
+        for _ in 0..MAX_SAMPLES {
+            registry.observe(peer, Duration::from_millis(100));
+        }
+        for _ in 0..MAX_SAMPLES {
+            registry.observe(peer, Duration::from_millis(1));
+        }
+
+        let report = registry.slow_links(1);
+        assert_eq!(report[0].p95_ms, 1);
+        assert_eq!(report[0].samples, (MAX_SAMPLES * 2) as u64);
+    }
+}
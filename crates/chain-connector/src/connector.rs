@@ -22,6 +22,7 @@ use std::collections::HashMap;
 use std::ops::Div;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 
 use ccp_shared::proof::CCProof;
 use ccp_shared::types::{Difficulty, GlobalNonce, CUID};
@@ -29,10 +30,11 @@ use clarity::{Transaction, Uint256};
 use eyre::eyre;
 use futures::FutureExt;
 use jsonrpsee::core::async_trait;
-use jsonrpsee::core::client::{BatchResponse, ClientT};
+use jsonrpsee::core::client::{BatchResponse, ClientT, Error as RPCError};
 use jsonrpsee::core::params::{ArrayParams, BatchRequestBuilder};
 use jsonrpsee::http_client::HttpClientBuilder;
 use jsonrpsee::rpc_params;
+use serde::de::DeserializeOwned;
 use serde_json::Value as JValue;
 use serde_json::{json, Value};
 use tokio::sync::Mutex;
@@ -45,9 +47,11 @@ use hex_utils::decode_hex;
 use particle_args::{Args, JError};
 use particle_builtins::{wrap, CustomService};
 use particle_execution::{ParticleParams, ServiceFunction};
+use peer_metrics::ChainRpcMetrics;
 use server_config::ChainConfig;
 use types::DealId;
 
+use crate::endpoints::{RpcEndpoints, PROBE_PERIOD};
 use crate::error::{process_response, ConnectorError};
 use crate::Offer::{ComputePeer, ComputeUnit};
 
@@ -89,7 +93,10 @@ pub trait ChainConnector: Send + Sync {
 }
 
 pub struct HttpChainConnector {
-    client: Arc<jsonrpsee::http_client::HttpClient>,
+    /// A client per configured RPC endpoint, in the same order as
+    /// [RpcEndpoints::urls]; requests go to the currently healthy one
+    clients: Vec<Arc<jsonrpsee::http_client::HttpClient>>,
+    endpoints: Arc<RpcEndpoints>,
     config: ChainConfig,
     tx_nonce_mutex: Arc<Mutex<()>>,
     host_id: PeerId,
@@ -109,26 +116,169 @@ impl HttpChainConnector {
     pub fn new(
         config: ChainConfig,
         host_id: PeerId,
+        metrics: Option<ChainRpcMetrics>,
     ) -> eyre::Result<(Arc<Self>, HashMap<String, CustomService>)> {
         tracing::info!(target: "chain-connector","Connecting to chain via {}", config.http_endpoint);
 
+        let urls: Vec<String> = std::iter::once(config.http_endpoint.clone())
+            .chain(config.http_backup_endpoints.iter().cloned())
+            .collect();
+        let clients = urls
+            .iter()
+            .map(|url| Ok(Arc::new(HttpClientBuilder::default().build(url)?)))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let endpoints = Arc::new(RpcEndpoints::new(urls, metrics));
+
         let connector = Arc::new(Self {
-            client: Arc::new(HttpClientBuilder::default().build(&config.http_endpoint)?),
+            clients,
+            endpoints,
             config,
             tx_nonce_mutex: Arc::new(Default::default()),
             host_id,
         });
 
+        if connector.clients.len() > 1 {
+            Self::start_probing(connector.clone());
+        }
+
         let builtins = Self::make_connector_builtins(connector.clone());
         Ok((connector, builtins))
     }
 
+    /// Periodically probes endpoints that were taken out of rotation,
+    /// so traffic can fail back to a recovered one
+    fn start_probing(connector: Arc<Self>) {
+        tokio::task::Builder::new()
+            .name("chain-rpc-probe")
+            .spawn(async move {
+                let mut timer = tokio::time::interval(PROBE_PERIOD);
+                loop {
+                    timer.tick().await;
+                    connector.probe_failed_endpoints().await;
+                }
+            })
+            .expect("Could not spawn chain-rpc-probe task");
+    }
+
+    pub(crate) async fn probe_failed_endpoints(&self) {
+        for (index, url) in self.endpoints.probe_candidates() {
+            let result: Result<String, RPCError> = self.clients[index]
+                .request("eth_blockNumber", rpc_params![])
+                .await;
+            match result {
+                Ok(_) => {
+                    tracing::info!(target: "chain-connector", "Probe of chain RPC endpoint {url} succeeded");
+                    self.endpoints.mark_healthy(index);
+                }
+                Err(err) => {
+                    tracing::debug!(target: "chain-connector", "Probe of chain RPC endpoint {url} failed: {err}");
+                    self.endpoints.extend_ban(index);
+                }
+            }
+        }
+    }
+
+    /// Whether the error means the endpoint itself misbehaved, as opposed to
+    /// a proper RPC-level reply (e.g. a reverted call) that any endpoint
+    /// would have returned as well
+    fn is_endpoint_error(err: &RPCError) -> bool {
+        !matches!(err, RPCError::Call(_))
+    }
+
+    /// Sends the request through the currently selected endpoint, accounting
+    /// latency and errors per endpoint. When a transport-level failure takes
+    /// the endpoint out of rotation, the request is retried on the next one
+    async fn rpc_request<R>(&self, method: &str, params: ArrayParams) -> Result<R, RPCError>
+    where
+        R: DeserializeOwned,
+    {
+        let mut attempts = self.clients.len();
+        loop {
+            let (index, _) = self.endpoints.current();
+            let started = Instant::now();
+            let result: Result<R, RPCError> =
+                self.clients[index].request(method, params.clone()).await;
+            match result {
+                Ok(value) => {
+                    self.endpoints.observe_success(index, started.elapsed());
+                    return Ok(value);
+                }
+                Err(err) if Self::is_endpoint_error(&err) => {
+                    self.endpoints.observe_error(index);
+                    attempts -= 1;
+                    // retry right away only if the failure switched us to another endpoint
+                    if attempts == 0 || self.endpoints.current().0 == index {
+                        return Err(err);
+                    }
+                    tracing::warn!(
+                        target: "chain-connector",
+                        "Retrying the request on {} after a transport error: {err}",
+                        self.endpoints.current().1
+                    );
+                }
+                Err(err) => {
+                    // the endpoint answered properly, the error is the caller's to handle
+                    self.endpoints.observe_success(index, started.elapsed());
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// The batch counterpart of [Self::rpc_request]
+    async fn rpc_batch_request<R>(
+        &self,
+        batch: BatchRequestBuilder<'static>,
+    ) -> Result<BatchResponse<'static, R>, RPCError>
+    where
+        R: DeserializeOwned + std::fmt::Debug + 'static,
+    {
+        let mut attempts = self.clients.len();
+        loop {
+            let (index, _) = self.endpoints.current();
+            let started = Instant::now();
+            let result = self.clients[index].batch_request(batch.clone()).await;
+            match result {
+                Ok(response) => {
+                    self.endpoints.observe_success(index, started.elapsed());
+                    return Ok(response);
+                }
+                Err(err) if Self::is_endpoint_error(&err) => {
+                    self.endpoints.observe_error(index);
+                    attempts -= 1;
+                    if attempts == 0 || self.endpoints.current().0 == index {
+                        return Err(err);
+                    }
+                    tracing::warn!(
+                        target: "chain-connector",
+                        "Retrying the batch request on {} after a transport error: {err}",
+                        self.endpoints.current().1
+                    );
+                }
+                Err(err) => {
+                    self.endpoints.observe_success(index, started.elapsed());
+                    return Err(err);
+                }
+            }
+        }
+    }
+
     fn make_connector_builtins(connector: Arc<Self>) -> HashMap<String, CustomService> {
         let mut builtins = HashMap::new();
         builtins.insert(
             "connector".to_string(),
             CustomService::new(
-                vec![("send_tx", Self::make_send_tx_closure(connector.clone()))],
+                vec![
+                    ("send_tx", Self::make_send_tx_closure(connector.clone())),
+                    (
+                        "rpc_endpoints",
+                        Self::make_rpc_endpoints_closure(connector.clone()),
+                    ),
+                    (
+                        "switch_rpc_endpoint",
+                        Self::make_switch_rpc_endpoint_closure(connector.clone()),
+                    ),
+                ],
                 None,
             ),
         );
@@ -142,6 +292,44 @@ impl HttpChainConnector {
         }))
     }
 
+    fn make_rpc_endpoints_closure(connector: Arc<Self>) -> ServiceFunction {
+        ServiceFunction::Immut(Box::new(move |_args, _params| {
+            let connector = connector.clone();
+            async move { wrap(connector.rpc_endpoints_builtin()) }.boxed()
+        }))
+    }
+
+    fn make_switch_rpc_endpoint_closure(connector: Arc<Self>) -> ServiceFunction {
+        ServiceFunction::Immut(Box::new(move |args, params| {
+            let connector = connector.clone();
+            async move { wrap(connector.switch_rpc_endpoint_builtin(args, params)) }.boxed()
+        }))
+    }
+
+    /// Reports per-endpoint health, so operators can see where chain
+    /// requests go and why
+    fn rpc_endpoints_builtin(&self) -> Result<JValue, JError> {
+        serde_json::to_value(self.endpoints.status())
+            .map_err(|err| JError::new(format!("Failed to serialize endpoint status: {err}")))
+    }
+
+    fn switch_rpc_endpoint_builtin(
+        &self,
+        args: Args,
+        params: ParticleParams,
+    ) -> Result<JValue, JError> {
+        if params.init_peer_id != self.host_id {
+            return Err(JError::new("Only the root worker can switch RPC endpoints"));
+        }
+
+        let mut args = args.function_args.into_iter();
+        let url: String = Args::next("url", &mut args)?;
+        self.endpoints
+            .force_switch(&url)
+            .map_err(|err| JError::new(format!("Failed to switch RPC endpoint: {err}")))?;
+        Ok(json!(url))
+    }
+
     async fn send_tx_builtin(&self, args: Args, params: ParticleParams) -> Result<JValue, JError> {
         if params.init_peer_id != self.host_id {
             return Err(JError::new("Only the root worker can send transactions"));
@@ -163,8 +351,7 @@ impl HttpChainConnector {
         }
 
         let block: Value = process_response(
-            self.client
-                .request("eth_getBlockByNumber", rpc_params!["pending", false])
+            self.rpc_request("eth_getBlockByNumber", rpc_params!["pending", false])
                 .await,
         )?;
 
@@ -184,8 +371,7 @@ impl HttpChainConnector {
     async fn get_tx_nonce(&self) -> Result<U256, ConnectorError> {
         let address = self.config.wallet_key.to_address().to_string();
         let resp: String = process_response(
-            self.client
-                .request("eth_getTransactionCount", rpc_params![address, "pending"])
+            self.rpc_request("eth_getTransactionCount", rpc_params![address, "pending"])
                 .await,
         )?;
 
@@ -199,8 +385,7 @@ impl HttpChainConnector {
         }
 
         let resp: String = process_response(
-            self.client
-                .request("eth_maxPriorityFeePerGas", rpc_params![])
+            self.rpc_request("eth_maxPriorityFeePerGas", rpc_params![])
                 .await,
         )?;
         let max_priority_fee_per_gas =
@@ -210,16 +395,15 @@ impl HttpChainConnector {
 
     async fn estimate_gas_limit(&self, data: &[u8], to: &str) -> Result<U256, ConnectorError> {
         let resp: String = process_response(
-            self.client
-                .request(
-                    "eth_estimateGas",
-                    rpc_params![json!({
-                        "from": self.config.wallet_key.to_address().to_string(),
-                        "to": to,
-                        "data": format!("0x{}", hex::encode(data)),
-                    })],
-                )
-                .await,
+            self.rpc_request(
+                "eth_estimateGas",
+                rpc_params![json!({
+                    "from": self.config.wallet_key.to_address().to_string(),
+                    "to": to,
+                    "data": format!("0x{}", hex::encode(data)),
+                })],
+            )
+            .await,
         )?;
         let limit = U256::from_str(&resp).map_err(|err| InvalidU256(resp, err.to_string()))?;
         Ok(limit)
@@ -266,8 +450,7 @@ impl HttpChainConnector {
         );
 
         let resp: String = process_response(
-            self.client
-                .request("eth_sendRawTransaction", rpc_params![format!("0x{}", tx)])
+            self.rpc_request("eth_sendRawTransaction", rpc_params![format!("0x{}", tx)])
                 .await,
         )?;
         Ok(resp)
@@ -338,18 +521,17 @@ impl ChainConnector for HttpChainConnector {
         .abi_encode()
         .encode_hex();
         let resp: String = process_response(
-            self.client
-                .request(
-                    "eth_call",
-                    rpc_params![
-                        json!({
-                            "data": data,
-                            "to": self.config.market_contract_address,
-                        }),
-                        "latest"
-                    ],
-                )
-                .await,
+            self.rpc_request(
+                "eth_call",
+                rpc_params![
+                    json!({
+                        "data": data,
+                        "to": self.config.market_contract_address,
+                    }),
+                    "latest"
+                ],
+            )
+            .await,
         )?;
         let compute_peer = <ComputePeer as SolType>::abi_decode(&decode_hex(&resp)?, true)?;
         Ok(CommitmentId::new(compute_peer.commitmentId.0))
@@ -367,7 +549,7 @@ impl ChainConnector for HttpChainConnector {
         batch.insert("eth_call", self.max_proofs_per_epoch_params())?;
 
         tracing::debug!("Sending batch request: {batch:?}");
-        let resp: BatchResponse<String> = self.client.batch_request(batch).await?;
+        let resp: BatchResponse<String> = self.rpc_batch_request(batch).await?;
         tracing::debug!("Got response for batch request: {resp:?}");
         let mut results = resp
             .into_ok()
@@ -430,18 +612,17 @@ impl ChainConnector for HttpChainConnector {
         .encode_hex();
 
         let resp: String = process_response(
-            self.client
-                .request(
-                    "eth_call",
-                    rpc_params![
-                        json!({
-                            "data": data,
-                            "to": self.config.market_contract_address,
-                        }),
-                        "latest"
-                    ],
-                )
-                .await,
+            self.rpc_request(
+                "eth_call",
+                rpc_params![
+                    json!({
+                        "data": data,
+                        "to": self.config.market_contract_address,
+                    }),
+                    "latest"
+                ],
+            )
+            .await,
         )?;
         let bytes = decode_hex(&resp)?;
         let compute_units = <Array<ComputeUnit> as SolType>::abi_decode(&bytes, true)?;
@@ -460,18 +641,17 @@ impl ChainConnector for HttpChainConnector {
         .encode_hex();
 
         let resp: String = process_response(
-            self.client
-                .request(
-                    "eth_call",
-                    rpc_params![
-                        json!({
-                            "data": data,
-                            "to": self.config.cc_contract_address,
-                        }),
-                        "latest"
-                    ],
-                )
-                .await,
+            self.rpc_request(
+                "eth_call",
+                rpc_params![
+                    json!({
+                        "data": data,
+                        "to": self.config.cc_contract_address,
+                    }),
+                    "latest"
+                ],
+            )
+            .await,
         )?;
         Ok(<CCStatus as SolType>::abi_decode(
             &decode_hex(&resp)?,
@@ -481,8 +661,7 @@ impl ChainConnector for HttpChainConnector {
 
     async fn get_global_nonce(&self) -> Result<GlobalNonce, ConnectorError> {
         let resp: String = process_response(
-            self.client
-                .request("eth_call", self.global_nonce_params())
+            self.rpc_request("eth_call", self.global_nonce_params())
                 .await,
         )?;
 
@@ -520,7 +699,7 @@ impl ChainConnector for HttpChainConnector {
             )?;
         }
 
-        let resp: BatchResponse<String> = self.client.batch_request(batch).await?;
+        let resp: BatchResponse<String> = self.rpc_batch_request(batch).await?;
         let mut statuses = vec![];
 
         for status in resp.into_iter() {
@@ -579,7 +758,7 @@ impl ChainConnector for HttpChainConnector {
         for tx_hash in tx_hashes {
             batch.insert("eth_getTransactionReceipt", rpc_params![tx_hash])?;
         }
-        let resp: BatchResponse<Value> = self.client.batch_request(batch).await?;
+        let resp: BatchResponse<Value> = self.rpc_batch_request(batch).await?;
         let mut receipts = vec![];
         for receipt in resp.into_iter() {
             let receipt = receipt.map_err(|e| ConnectorError::RpcError(e.to_owned().into()));
@@ -607,6 +786,8 @@ mod tests {
 
     use chain_data::peer_id_from_hex;
     use hex_utils::decode_hex;
+    use peer_metrics::ChainRpcMetrics;
+    use prometheus_client::registry::Registry;
 
     use crate::{
         is_commitment_not_active, CCStatus, ChainConnector, CommitmentId, ConnectorError,
@@ -614,9 +795,18 @@ mod tests {
     };
 
     fn get_connector(url: &str) -> Arc<HttpChainConnector> {
+        get_connector_with(url, vec![], None)
+    }
+
+    fn get_connector_with(
+        url: &str,
+        http_backup_endpoints: Vec<String>,
+        metrics: Option<ChainRpcMetrics>,
+    ) -> Arc<HttpChainConnector> {
         let (connector, _) = HttpChainConnector::new(
             server_config::ChainConfig {
                 http_endpoint: url.to_string(),
+                http_backup_endpoints,
                 cc_contract_address: "0x0E62f5cfA5189CA34E79CCB03829C064405790aD".to_string(),
                 core_contract_address: "0x2f5224b7Cb8bd98d9Ef61c247F4741758E8E873d".to_string(),
                 market_contract_address: "0x1dC1eB8fc8dBc35be6fE75ceba05C7D410a2e721".to_string(),
@@ -630,6 +820,7 @@ mod tests {
             },
             peer_id_from_hex("0x6497db93b32e4cdd979ada46a23249f444da1efb186cd74b9666bd03f710028b")
                 .unwrap(),
+            metrics,
         )
         .unwrap();
 
@@ -946,4 +1137,144 @@ mod tests {
             "0x55bfec4a4400ca0b09e075e2b517041cd78b10021c51726cb73bcba52213fa05"
         );
     }
+
+    fn compute_units_response() -> String {
+        let data = "0x000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000025d204dcc21f59c2a2098a277e48879207f614583e066654ad6736d36815ebb9e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000450e2f2a5bdb528895e9005f67e70fe213b9b822122e96fd85d2238cae55b6f900000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+        format!("{{\"jsonrpc\":\"2.0\",\"result\":\"{data}\",\"id\":0}}")
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_over_to_backup_endpoint() {
+        let mut primary = mockito::Server::new();
+        let mut backup = mockito::Server::new();
+        let primary_mock = primary
+            .mock("POST", "/")
+            // three transport errors take the primary out of rotation
+            .expect(3)
+            .with_status(500)
+            .create();
+        let backup_mock = backup
+            .mock("POST", "/")
+            .expect(1)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(compute_units_response())
+            .create();
+
+        let connector = get_connector_with(&primary.url(), vec![backup.url()], None);
+
+        // the first two errors keep the primary in rotation
+        assert!(connector.get_compute_units().await.is_err());
+        assert!(connector.get_compute_units().await.is_err());
+        // the third error switches to the backup and the request is retried there
+        let units = connector.get_compute_units().await.unwrap();
+
+        primary_mock.assert();
+        backup_mock.assert();
+        assert_eq!(units.len(), 2);
+        assert_eq!(connector.endpoints.current().1, backup.url());
+    }
+
+    #[tokio::test]
+    async fn test_failover_metrics_are_attributed_per_endpoint() {
+        let mut registry = Registry::default();
+        let metrics = ChainRpcMetrics::new(&mut registry);
+
+        let mut primary = mockito::Server::new();
+        let mut backup = mockito::Server::new();
+        primary
+            .mock("POST", "/")
+            .expect(3)
+            .with_status(500)
+            .create();
+        backup
+            .mock("POST", "/")
+            .expect(1)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(compute_units_response())
+            .create();
+
+        let connector = get_connector_with(&primary.url(), vec![backup.url()], Some(metrics));
+
+        assert!(connector.get_compute_units().await.is_err());
+        assert!(connector.get_compute_units().await.is_err());
+        connector.get_compute_units().await.unwrap();
+
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+
+        let primary_url = primary.url();
+        let backup_url = backup.url();
+        assert!(
+            encoded.contains(&format!(
+                "chain_rpc_requests_total{{endpoint=\"{primary_url}\"}} 3"
+            )),
+            "primary requests are counted: {encoded}"
+        );
+        assert!(
+            encoded.contains(&format!(
+                "chain_rpc_request_errors_total{{endpoint=\"{primary_url}\"}} 3"
+            )),
+            "primary errors are counted: {encoded}"
+        );
+        assert!(
+            encoded.contains(&format!(
+                "chain_rpc_requests_total{{endpoint=\"{backup_url}\"}} 1"
+            )),
+            "the retried request is attributed to the backup: {encoded}"
+        );
+        assert!(
+            encoded.contains("chain_rpc_failovers_total 1"),
+            "the switch is counted: {encoded}"
+        );
+        assert!(
+            encoded.contains(&format!(
+                "chain_rpc_endpoint_active{{endpoint=\"{backup_url}\"}} 1"
+            )),
+            "the backup is marked active: {encoded}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_fails_back_to_recovered_primary() {
+        let mut primary = mockito::Server::new();
+        let mut backup = mockito::Server::new();
+        let failing_mock = primary
+            .mock("POST", "/")
+            .expect(3)
+            .with_status(500)
+            .create();
+        backup
+            .mock("POST", "/")
+            .expect(1)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(compute_units_response())
+            .create();
+
+        let connector = get_connector_with(&primary.url(), vec![backup.url()], None);
+
+        assert!(connector.get_compute_units().await.is_err());
+        assert!(connector.get_compute_units().await.is_err());
+        connector.get_compute_units().await.unwrap();
+        assert_eq!(connector.endpoints.current().1, backup.url());
+        failing_mock.assert();
+
+        // the primary recovers; a successful probe puts it back into rotation
+        primary.reset();
+        let recovered_mock = primary
+            .mock("POST", "/")
+            .expect(1)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","result":"0x1","id":0}"#)
+            .create();
+
+        connector.endpoints.expire_bans();
+        connector.probe_failed_endpoints().await;
+
+        recovered_mock.assert();
+        assert_eq!(connector.endpoints.current().1, primary.url());
+    }
 }
@@ -0,0 +1,384 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use peer_metrics::ChainRpcMetrics;
+
+/// After this many consecutive transport errors the endpoint is considered
+/// unhealthy and requests fail over to the next healthy one
+const MAX_CONSECUTIVE_ERRORS: u32 = 3;
+/// An endpoint whose average request latency exceeds this threshold is
+/// considered unhealthy even if its requests succeed
+const LATENCY_THRESHOLD: Duration = Duration::from_secs(5);
+/// How much a single observation shifts the average latency
+const LATENCY_SMOOTHING: f64 = 0.2;
+/// How long an unhealthy endpoint stays out of rotation before it is probed again
+pub const PROBE_PERIOD: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    requests: u64,
+    errors: u64,
+    consecutive_errors: u32,
+    // exponential moving average of request latency, in seconds
+    avg_latency_sec: Option<f64>,
+    // while set, the endpoint is out of rotation; after the deadline it is
+    // a candidate for probing, and only a successful probe puts it back
+    banned_until: Option<Instant>,
+}
+
+impl EndpointState {
+    fn healthy(&self) -> bool {
+        self.banned_until.is_none()
+    }
+
+    /// A coarse indicator for operators: 1.0 for an endpoint with no recent
+    /// errors, decreasing with consecutive errors, 0.0 while out of rotation
+    fn health_score(&self) -> f64 {
+        if self.healthy() {
+            1.0 / (1.0 + self.consecutive_errors as f64)
+        } else {
+            0.0
+        }
+    }
+}
+
+struct State {
+    endpoints: Vec<EndpointState>,
+    current: usize,
+}
+
+/// Current state of a single RPC endpoint, as reported by the management builtin
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    /// Whether requests are currently routed to this endpoint
+    pub active: bool,
+    pub healthy: bool,
+    pub health_score: f64,
+    pub requests: u64,
+    pub errors: u64,
+    pub avg_latency_ms: Option<u64>,
+}
+
+/// A set of interchangeable chain RPC endpoints with per-endpoint health
+/// accounting. Requests go to the first healthy endpoint in config order;
+/// an endpoint that accumulates errors or becomes slow is taken out of
+/// rotation until a probe succeeds on it again
+pub struct RpcEndpoints {
+    urls: Vec<String>,
+    probe_cooldown: Duration,
+    metrics: Option<ChainRpcMetrics>,
+    state: Mutex<State>,
+}
+
+impl RpcEndpoints {
+    pub fn new(urls: Vec<String>, metrics: Option<ChainRpcMetrics>) -> Self {
+        Self::with_probe_cooldown(urls, metrics, PROBE_PERIOD)
+    }
+
+    pub(crate) fn with_probe_cooldown(
+        urls: Vec<String>,
+        metrics: Option<ChainRpcMetrics>,
+        probe_cooldown: Duration,
+    ) -> Self {
+        debug_assert!(!urls.is_empty(), "at least one RPC endpoint is required");
+        let endpoints = urls.iter().map(|_| EndpointState::default()).collect();
+        let this = Self {
+            urls,
+            probe_cooldown,
+            metrics,
+            state: Mutex::new(State {
+                endpoints,
+                current: 0,
+            }),
+        };
+        for (index, url) in this.urls.iter().enumerate() {
+            this.meter(|m| m.set_active_endpoint(url, index == 0));
+        }
+        this
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Index and URL of the endpoint requests are currently routed to
+    pub fn current(&self) -> (usize, &str) {
+        let current = self.state.lock().current;
+        (current, &self.urls[current])
+    }
+
+    /// Records a successful request. A success resets the error streak, but a
+    /// consistently slow endpoint is failed over all the same
+    pub fn observe_success(&self, index: usize, latency: Duration) {
+        self.meter(|m| m.observe_request(&self.urls[index], latency));
+        let mut state = self.state.lock();
+        let endpoint = &mut state.endpoints[index];
+        endpoint.requests += 1;
+        endpoint.consecutive_errors = 0;
+        let avg = match endpoint.avg_latency_sec {
+            Some(avg) => avg + LATENCY_SMOOTHING * (latency.as_secs_f64() - avg),
+            None => latency.as_secs_f64(),
+        };
+        endpoint.avg_latency_sec = Some(avg);
+        if index == state.current && avg > LATENCY_THRESHOLD.as_secs_f64() {
+            self.ban_and_switch(&mut state, index, "average latency is over the threshold");
+        }
+    }
+
+    /// Records a transport-level failure; enough of them in a row take the
+    /// endpoint out of rotation
+    pub fn observe_error(&self, index: usize) {
+        self.meter(|m| m.observe_request_error(&self.urls[index]));
+        let mut state = self.state.lock();
+        let endpoint = &mut state.endpoints[index];
+        endpoint.requests += 1;
+        endpoint.errors += 1;
+        endpoint.consecutive_errors += 1;
+        if index == state.current && endpoint.consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+            self.ban_and_switch(&mut state, index, "too many consecutive errors");
+        }
+    }
+
+    fn ban_and_switch(&self, state: &mut State, index: usize, reason: &str) {
+        state.endpoints[index].banned_until = Some(Instant::now() + self.probe_cooldown);
+        let next = Self::select(state);
+        if next != state.current {
+            tracing::warn!(
+                target: "chain-connector",
+                "Chain RPC endpoint {} is unhealthy ({reason}), failing over to {}",
+                self.urls[index],
+                self.urls[next],
+            );
+            state.current = next;
+            self.meter(|m| {
+                m.observe_failover();
+                m.set_active_endpoint(&self.urls[index], false);
+                m.set_active_endpoint(&self.urls[next], true);
+            });
+        } else {
+            tracing::warn!(
+                target: "chain-connector",
+                "Chain RPC endpoint {} is unhealthy ({reason}), but there is no healthy endpoint to fail over to",
+                self.urls[index],
+            );
+        }
+    }
+
+    /// The first healthy endpoint in config order, so traffic prefers the
+    /// primary; the current one if none are healthy
+    fn select(state: &State) -> usize {
+        state
+            .endpoints
+            .iter()
+            .position(EndpointState::healthy)
+            .unwrap_or(state.current)
+    }
+
+    /// Out-of-rotation endpoints whose cooldown has expired, ready to be probed
+    pub fn probe_candidates(&self) -> Vec<(usize, String)> {
+        let now = Instant::now();
+        let state = self.state.lock();
+        state
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, endpoint)| matches!(endpoint.banned_until, Some(deadline) if deadline <= now))
+            .map(|(index, _)| (index, self.urls[index].clone()))
+            .collect()
+    }
+
+    /// Puts the endpoint back into rotation after a successful probe; traffic
+    /// returns to it if it precedes the current endpoint in config order
+    pub fn mark_healthy(&self, index: usize) {
+        let mut state = self.state.lock();
+        state.endpoints[index].banned_until = None;
+        state.endpoints[index].consecutive_errors = 0;
+        let next = Self::select(&state);
+        if next != state.current {
+            tracing::info!(
+                target: "chain-connector",
+                "Chain RPC endpoint {} is healthy again, failing back from {}",
+                self.urls[next],
+                self.urls[state.current],
+            );
+            let previous = state.current;
+            state.current = next;
+            self.meter(|m| {
+                m.set_active_endpoint(&self.urls[previous], false);
+                m.set_active_endpoint(&self.urls[next], true);
+            });
+        }
+    }
+
+    /// Keeps the endpoint out of rotation for another cooldown after a failed probe
+    pub fn extend_ban(&self, index: usize) {
+        let mut state = self.state.lock();
+        state.endpoints[index].banned_until = Some(Instant::now() + self.probe_cooldown);
+    }
+
+    /// Routes requests to the given endpoint regardless of its health history.
+    /// Returns an error if the URL is not in the configured set
+    pub fn force_switch(&self, url: &str) -> Result<(), String> {
+        let index = self
+            .urls
+            .iter()
+            .position(|candidate| candidate == url)
+            .ok_or_else(|| format!("unknown RPC endpoint '{url}'"))?;
+        let mut state = self.state.lock();
+        state.endpoints[index].banned_until = None;
+        state.endpoints[index].consecutive_errors = 0;
+        if index != state.current {
+            tracing::info!(
+                target: "chain-connector",
+                "Chain RPC endpoint switched to {} by request",
+                self.urls[index],
+            );
+            let previous = state.current;
+            state.current = index;
+            self.meter(|m| {
+                m.set_active_endpoint(&self.urls[previous], false);
+                m.set_active_endpoint(&self.urls[index], true);
+            });
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let state = self.state.lock();
+        self.urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| {
+                let endpoint = &state.endpoints[index];
+                EndpointStatus {
+                    url: url.clone(),
+                    active: index == state.current,
+                    healthy: endpoint.healthy(),
+                    health_score: endpoint.health_score(),
+                    requests: endpoint.requests,
+                    errors: endpoint.errors,
+                    avg_latency_ms: endpoint
+                        .avg_latency_sec
+                        .map(|avg| (avg * 1000.0).round() as u64),
+                }
+            })
+            .collect()
+    }
+
+    /// Moves every ban deadline to now, so banned endpoints become probe
+    /// candidates without waiting out the cooldown
+    #[cfg(test)]
+    pub(crate) fn expire_bans(&self) {
+        let now = Instant::now();
+        for endpoint in self.state.lock().endpoints.iter_mut() {
+            if endpoint.banned_until.is_some() {
+                endpoint.banned_until = Some(now);
+            }
+        }
+    }
+
+    fn meter<F: Fn(&ChainRpcMetrics)>(&self, f: F) {
+        if let Some(metrics) = self.metrics.as_ref() {
+            f(metrics);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(urls: &[&str]) -> RpcEndpoints {
+        RpcEndpoints::with_probe_cooldown(
+            urls.iter().map(|url| url.to_string()).collect(),
+            None,
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn fails_over_after_consecutive_errors() {
+        let endpoints = endpoints(&["http://primary", "http://backup"]);
+        assert_eq!(endpoints.current().1, "http://primary");
+
+        // a success in between resets the error streak
+        endpoints.observe_error(0);
+        endpoints.observe_error(0);
+        endpoints.observe_success(0, Duration::from_millis(10));
+        endpoints.observe_error(0);
+        endpoints.observe_error(0);
+        assert_eq!(endpoints.current().1, "http://primary");
+
+        endpoints.observe_error(0);
+        assert_eq!(endpoints.current().1, "http://backup");
+    }
+
+    #[test]
+    fn successful_probe_fails_back_to_primary() {
+        let endpoints = endpoints(&["http://primary", "http://backup"]);
+        for _ in 0..MAX_CONSECUTIVE_ERRORS {
+            endpoints.observe_error(0);
+        }
+        assert_eq!(endpoints.current().1, "http://backup");
+
+        // the cooldown is zero, so the failed endpoint is a candidate right away
+        let candidates = endpoints.probe_candidates();
+        assert_eq!(candidates, vec![(0, "http://primary".to_string())]);
+
+        endpoints.mark_healthy(0);
+        assert_eq!(endpoints.current().1, "http://primary");
+        assert!(endpoints.probe_candidates().is_empty());
+    }
+
+    #[test]
+    fn slow_endpoint_is_failed_over() {
+        let endpoints = endpoints(&["http://primary", "http://backup"]);
+        // a single slow response sets the average above the threshold
+        endpoints.observe_success(0, LATENCY_THRESHOLD * 2);
+        assert_eq!(endpoints.current().1, "http://backup");
+    }
+
+    #[test]
+    fn force_switch_overrides_selection() {
+        let endpoints = endpoints(&["http://primary", "http://backup"]);
+        endpoints.force_switch("http://backup").unwrap();
+        assert_eq!(endpoints.current().1, "http://backup");
+        assert!(endpoints.force_switch("http://unknown").is_err());
+
+        let status = endpoints.status();
+        assert!(!status[0].active);
+        assert!(status[1].active);
+        assert!(status.iter().all(|endpoint| endpoint.healthy));
+    }
+
+    #[test]
+    fn last_endpoint_is_kept_when_all_are_unhealthy() {
+        let endpoints = endpoints(&["http://primary", "http://backup"]);
+        for index in [0, 1] {
+            for _ in 0..MAX_CONSECUTIVE_ERRORS {
+                endpoints.observe_error(index);
+            }
+        }
+        // nowhere to go: requests keep flowing to the last selected endpoint
+        assert_eq!(endpoints.current().1, "http://backup");
+    }
+}
@@ -18,11 +18,13 @@
 #![feature(result_flattening)]
 
 mod connector;
+mod endpoints;
 mod error;
 mod function;
 
 pub use connector::CCInitParams;
 pub use connector::ChainConnector;
 pub use connector::HttpChainConnector;
+pub use endpoints::EndpointStatus;
 pub use error::ConnectorError;
 pub use function::*;
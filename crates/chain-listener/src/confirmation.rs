@@ -0,0 +1,138 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+
+use alloy_primitives::BlockNumber;
+
+/// Buffers chain events until the head is `depth` blocks past the block that
+/// emitted them, so a reorg within the confirmation window can discard them
+/// before the node acts
+pub struct ConfirmationBuffer<E> {
+    depth: u64,
+    // events keyed by the block that emitted them
+    buffered: BTreeMap<BlockNumber, Vec<E>>,
+    // the highest block whose events were handed out for processing
+    last_processed: Option<BlockNumber>,
+}
+
+/// What [ConfirmationBuffer::discard] did with the reorged-out event
+#[derive(Debug, PartialEq, Eq)]
+pub enum Discarded {
+    /// The block was still within the confirmation window;
+    /// this many buffered events matched and were dropped
+    Buffered(usize),
+    /// The block was already processed: the reorg is deeper than the
+    /// confirmation window and the node may have acted on stale events
+    AlreadyProcessed,
+}
+
+impl<E> ConfirmationBuffer<E> {
+    pub fn new(depth: u64, last_processed: Option<BlockNumber>) -> Self {
+        Self {
+            depth,
+            buffered: BTreeMap::new(),
+            last_processed,
+        }
+    }
+
+    /// Buffers an event of `block` until the block is confirmed
+    pub fn push(&mut self, block: BlockNumber, event: E) {
+        self.buffered.entry(block).or_default().push(event);
+    }
+
+    /// Drops the buffered events of `block` that `matches`; reports
+    /// [Discarded::AlreadyProcessed] when the block is past the confirmation
+    /// window and its events can no longer be taken back
+    pub fn discard(&mut self, block: BlockNumber, matches: impl FnMut(&E) -> bool) -> Discarded {
+        if self.last_processed >= Some(block) {
+            return Discarded::AlreadyProcessed;
+        }
+
+        match self.buffered.get_mut(&block) {
+            Some(events) => {
+                let discarded = events.extract_if(matches).count();
+                if events.is_empty() {
+                    self.buffered.remove(&block);
+                }
+                Discarded::Buffered(discarded)
+            }
+            None => Discarded::Buffered(0),
+        }
+    }
+
+    /// Returns the events of every block the `head` is at least `depth`
+    /// blocks past, oldest block first
+    pub fn confirm(&mut self, head: BlockNumber) -> Vec<E> {
+        let confirmed_up_to = head.saturating_sub(self.depth);
+        let still_buffered = self.buffered.split_off(&(confirmed_up_to + 1));
+        let confirmed = std::mem::replace(&mut self.buffered, still_buffered);
+        if Some(confirmed_up_to) > self.last_processed {
+            self.last_processed = Some(confirmed_up_to);
+        }
+
+        confirmed.into_values().flatten().collect()
+    }
+
+    /// The highest block whose events were handed out for processing
+    pub fn last_processed(&self) -> Option<BlockNumber> {
+        self.last_processed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_confirmed_only_after_depth_blocks() {
+        let mut buffer = ConfirmationBuffer::new(3, None);
+        buffer.push(100, "a");
+        assert!(buffer.confirm(101).is_empty());
+        assert!(buffer.confirm(102).is_empty());
+        assert_eq!(buffer.confirm(103), vec!["a"]);
+        assert_eq!(buffer.last_processed(), Some(100));
+    }
+
+    #[test]
+    fn reorg_within_the_window_swaps_events() {
+        let mut buffer = ConfirmationBuffer::new(3, None);
+        buffer.push(100, "stale");
+        buffer.push(101, "kept");
+        let discarded = buffer.discard(100, |e| *e == "stale");
+        assert_eq!(discarded, Discarded::Buffered(1));
+
+        buffer.push(100, "canonical");
+        assert_eq!(buffer.confirm(104), vec!["canonical", "kept"]);
+    }
+
+    #[test]
+    fn reorg_past_processed_blocks_is_reported() {
+        let mut buffer: ConfirmationBuffer<&str> = ConfirmationBuffer::new(3, Some(100));
+        assert_eq!(buffer.discard(95, |_| true), Discarded::AlreadyProcessed);
+        // a block that never buffered anything is a no-op
+        assert_eq!(buffer.discard(101, |_| true), Discarded::Buffered(0));
+    }
+
+    #[test]
+    fn last_processed_never_moves_backwards() {
+        let mut buffer: ConfirmationBuffer<&str> = ConfirmationBuffer::new(0, Some(100));
+        buffer.confirm(90);
+        assert_eq!(buffer.last_processed(), Some(100));
+        buffer.confirm(105);
+        assert_eq!(buffer.last_processed(), Some(105));
+    }
+}
@@ -24,6 +24,7 @@ extern crate core;
 
 pub use listener::ChainListener;
 
+mod confirmation;
 mod event;
 mod listener;
 
@@ -60,6 +60,7 @@ use peer_metrics::ChainListenerMetrics;
 use server_config::{ChainConfig, ChainListenerConfig};
 use types::DealId;
 
+use crate::confirmation::{ConfirmationBuffer, Discarded};
 use crate::event::cc_activated::CommitmentActivated;
 use crate::event::{ComputeUnitMatched, UnitActivated, UnitDeactivated};
 use crate::persistence;
@@ -73,6 +74,10 @@ pub struct ChainListener {
     chain_connector: Arc<dyn ChainConnector>,
     // To subscribe to chain events
     ws_client: WsClient,
+    // All configured websocket endpoints, the primary one first
+    ws_endpoints: Vec<String>,
+    // Index of the endpoint `ws_client` is connected to
+    current_ws: usize,
 
     ccp_client: Option<CCPRpcHttpClient>,
 
@@ -100,6 +105,11 @@ pub struct ChainListener {
     // the compute units that are in deals and not in commitment
     active_deals: BTreeMap<DealId, CUID>,
 
+    // unit events buffered until the head is `confirmation_depth` blocks past them
+    confirmations: ConfirmationBuffer<BufferedEvent>,
+    // the last processed block that was persisted, to skip redundant writes
+    last_persisted_block: Option<BlockNumber>,
+
     /// Resets every epoch
     last_submitted_proof_id: ProofIdx,
     pending_proof_txs: Vec<(String, CUID)>,
@@ -143,9 +153,16 @@ impl ChainListener {
             tracing::warn!(target: "chain-listener", "CCP client is not set, will submit mocked proofs");
         }
 
+        let ws_endpoints = std::iter::once(listener_config.ws_endpoint.clone())
+            .chain(listener_config.ws_backup_endpoints.iter().cloned())
+            .collect();
+        let confirmation_depth = listener_config.confirmation_depth;
+
         Self {
             chain_connector,
             ws_client,
+            ws_endpoints,
+            current_ws: 0,
             listener_config,
             config: chain_config,
             host_id,
@@ -170,6 +187,8 @@ impl ChainListener {
             commitment_activated: None,
             unit_matched: None,
             active_deals: BTreeMap::new(),
+            confirmations: ConfirmationBuffer::new(confirmation_depth, None),
+            last_persisted_block: None,
             metrics,
         }
     }
@@ -198,6 +217,10 @@ impl ChainListener {
                     exit(1);
                 }
 
+                if let Err(err) = self.load_last_processed_block().await {
+                    tracing::warn!(target: "chain-listener", "Failed to load persisted last processed block: {err}");
+                }
+
                 tracing::info!(target: "chain-listener", "Subscribing to chain events");
                 if let Err(err) = self.refresh_subscriptions().await {
                     tracing::error!(target: "chain-listener", "Failed to subscribe to chain events: {err}; Stopping...");
@@ -497,6 +520,43 @@ impl ChainListener {
         Ok(ws_client)
     }
 
+    /// Reconnects the websocket client, rotating over the configured
+    /// endpoints starting with the current one; on a full unsuccessful pass
+    /// sleeps with a doubling delay and starts over
+    async fn reconnect_ws_client(&mut self) -> WsClient {
+        let mut delay = Duration::from_secs(1);
+        loop {
+            for attempt in 0..self.ws_endpoints.len() {
+                let index = (self.current_ws + attempt) % self.ws_endpoints.len();
+                let ws_endpoint = &self.ws_endpoints[index];
+                match WsClientBuilder::default().build(ws_endpoint).await {
+                    Ok(client) => {
+                        if index != self.current_ws {
+                            tracing::warn!(
+                                target: "chain-listener",
+                                "Switched to websocket endpoint {ws_endpoint}"
+                            );
+                        }
+                        tracing::info!(
+                            target: "chain-listener",
+                            "Successfully connected to websocket endpoint: {ws_endpoint}"
+                        );
+                        self.current_ws = index;
+                        return client;
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            target: "chain-listener",
+                            "Error connecting to websocket endpoint {ws_endpoint}, error: {err}"
+                        );
+                    }
+                }
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(Duration::from_secs(30));
+        }
+    }
+
     async fn subscribe_unit_events(
         &mut self,
         commitment_id: &CommitmentId,
@@ -515,8 +575,7 @@ impl ChainListener {
 
     async fn refresh_subscriptions(&mut self) -> Result<(), client::Error> {
         if !self.ws_client.is_connected() {
-            self.ws_client =
-                ChainListener::create_ws_client(&self.listener_config.ws_endpoint).await?;
+            self.ws_client = self.reconnect_ws_client().await;
         }
 
         // loop because subscriptions can fail and require reconnection, we can't proceed without them
@@ -539,9 +598,7 @@ impl ChainListener {
                 Err(err) => match err {
                     client::Error::RestartNeeded(_) => {
                         tracing::warn!(target: "chain-listener", "Failed to refresh subscriptions: {err}; Restart client...");
-                        self.ws_client =
-                            ChainListener::create_ws_client(&self.listener_config.ws_endpoint)
-                                .await?;
+                        self.ws_client = self.reconnect_ws_client().await;
                     }
                     _ => {
                         tracing::error!(target: "chain-listener", "Failed to refresh subscriptions: {err}; Retrying...");
@@ -669,6 +726,12 @@ impl ChainListener {
         let (block_timestamp, block_number) = Self::parse_block_header(header?)?;
         self.observe(|m| m.observe_new_block(block_number));
 
+        let confirmed = self.confirmations.confirm(block_number);
+        for event in confirmed {
+            self.handle_confirmed_event(event).await?;
+        }
+        self.persist_last_processed_block().await;
+
         // `epoch_number = 1 + (block_timestamp - init_timestamp) / epoch_duration`
         let epoch_number =
             U256::from(1) + (block_timestamp - self.init_timestamp) / self.epoch_duration;
@@ -755,6 +818,81 @@ impl ChainListener {
         Ok(())
     }
 
+    /// Either processes the event right away (`confirmation_depth` is 0),
+    /// buffers it until its block is confirmed, or — for a log removed by a
+    /// reorg — discards the buffered copy. Returns `true` when the event was
+    /// consumed by the buffer and must not be processed now
+    fn buffer_or_discard(&mut self, kind: UnitEventKind, log: &Log) -> eyre::Result<bool> {
+        let block = Self::parse_block_number(&log.block_number)?;
+        if log.removed {
+            let discarded = self.confirmations.discard(block, |e| {
+                e.kind == kind && e.log.data == log.data && e.log.topics == log.topics
+            });
+            if discarded == Discarded::AlreadyProcessed {
+                tracing::error!(target: "chain-listener",
+                    "Chain reorg removed a {kind:?} event of already processed block {block}; \
+                     the node may have acted on state that is no longer canonical"
+                );
+                self.observe(|m| m.observe_deep_reorg());
+            }
+            return Ok(true);
+        }
+
+        if self.listener_config.confirmation_depth == 0 {
+            return Ok(false);
+        }
+
+        self.confirmations.push(
+            block,
+            BufferedEvent {
+                kind,
+                log: log.clone(),
+            },
+        );
+        Ok(true)
+    }
+
+    async fn handle_confirmed_event(&mut self, event: BufferedEvent) -> eyre::Result<()> {
+        match event.kind {
+            UnitEventKind::UnitActivated => self.handle_unit_activated(event.log).await,
+            UnitEventKind::UnitDeactivated => self.handle_unit_deactivated(event.log).await,
+            UnitEventKind::ComputeUnitMatched => self.handle_unit_matched(event.log),
+        }
+    }
+
+    async fn persist_last_processed_block(&mut self) {
+        if self.listener_config.confirmation_depth == 0 {
+            return;
+        }
+        let Some(block) = self.confirmations.last_processed() else {
+            return;
+        };
+        if self.last_persisted_block == Some(block) {
+            return;
+        }
+
+        match persistence::persist_last_processed_block(&self.persisted_proof_id_dir, block).await {
+            Ok(()) => self.last_persisted_block = Some(block),
+            Err(err) => {
+                tracing::warn!(target: "chain-listener", "Failed to persist last processed block {block}: {err}; Ignoring..")
+            }
+        }
+    }
+
+    async fn load_last_processed_block(&mut self) -> eyre::Result<()> {
+        let persisted =
+            persistence::load_persisted_last_processed_block(&self.persisted_proof_id_dir).await?;
+
+        if let Some(block) = persisted {
+            tracing::info!(target: "chain-listener", "Loaded persisted last processed block {block}");
+            self.confirmations =
+                ConfirmationBuffer::new(self.listener_config.confirmation_depth, Some(block));
+            self.last_persisted_block = Some(block);
+        }
+
+        Ok(())
+    }
+
     async fn process_unit_activated(
         &mut self,
         event: Option<Result<JsonValue, client::Error>>,
@@ -766,6 +904,14 @@ impl ChainListener {
             err
         })?;
 
+        if self.buffer_or_discard(UnitEventKind::UnitActivated, &log)? {
+            return Ok(());
+        }
+
+        self.handle_unit_activated(log).await
+    }
+
+    async fn handle_unit_activated(&mut self, log: Log) -> eyre::Result<()> {
         let unit_event = parse_log::<UnitActivated>(log)?;
         tracing::info!(target: "chain-listener",
             "Received UnitActivated event for unit: {}, startEpoch: {}",
@@ -796,6 +942,15 @@ impl ChainListener {
             tracing::error!(target: "chain-listener", "Failed to parse UnitDeactivated event: {err}, data: {event}");
             err
         })?;
+
+        if self.buffer_or_discard(UnitEventKind::UnitDeactivated, &log)? {
+            return Ok(());
+        }
+
+        self.handle_unit_deactivated(log).await
+    }
+
+    async fn handle_unit_deactivated(&mut self, log: Log) -> eyre::Result<()> {
         let unit_event = parse_log::<UnitDeactivated>(log)?;
         let unit_id = CUID::new(unit_event.unitId.0);
         tracing::info!(target: "chain-listener",
@@ -817,6 +972,15 @@ impl ChainListener {
             tracing::error!(target: "chain-listener", "Failed to parse DealMatched event: {err}, data: {event}");
             err
         })?;
+
+        if self.buffer_or_discard(UnitEventKind::ComputeUnitMatched, &log)? {
+            return Ok(());
+        }
+
+        self.handle_unit_matched(log)
+    }
+
+    fn handle_unit_matched(&mut self, log: Log) -> eyre::Result<()> {
         let deal_event = parse_log::<ComputeUnitMatched>(log)?;
         tracing::info!(target: "chain-listener",
             "Received DealMatched event for deal: {}",
@@ -1024,6 +1188,8 @@ impl ChainListener {
                     finished_cores,
                 })
             }
+            // acquire_worker_core produces no other errors
+            Err(err) => Err(err.into()),
         }
     }
 
@@ -1357,6 +1523,20 @@ impl ChainListener {
     }
 }
 
+/// A unit event held back until its block is `confirmation_depth` blocks
+/// behind the chain head
+struct BufferedEvent {
+    kind: UnitEventKind,
+    log: Log,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UnitEventKind {
+    UnitActivated,
+    UnitDeactivated,
+    ComputeUnitMatched,
+}
+
 struct CUGroups {
     /// Already started units involved in CC and not having less than MIN_PROOFS_PER_EPOCH proofs in the current epoch
     pub priority_units: Vec<CUID>,
@@ -31,10 +31,19 @@ pub struct PersistedProofId {
     pub epoch: U256,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct PersistedLastProcessedBlock {
+    pub block_number: u64,
+}
+
 pub(crate) fn proof_id_filename() -> String {
     "proof_id.toml".to_string()
 }
 
+pub(crate) fn last_processed_block_filename() -> String {
+    "last_processed_block.toml".to_string()
+}
+
 pub(crate) async fn persist_proof_id(
     proof_id_dir: &Path,
     proof_id: ProofIdx,
@@ -51,6 +60,37 @@ pub(crate) async fn persist_proof_id(
         .context(format!("error writing proof id to {}", path.display()))
 }
 
+pub(crate) async fn persist_last_processed_block(
+    dir: &Path,
+    block_number: u64,
+) -> eyre::Result<()> {
+    let path = dir.join(last_processed_block_filename());
+    let bytes = toml_edit::ser::to_vec(&PersistedLastProcessedBlock { block_number })
+        .map_err(|err| eyre::eyre!("Last processed block serialization failed {err}"))?;
+    tokio::fs::write(&path, bytes).await.context(format!(
+        "error writing last processed block to {}",
+        path.display()
+    ))
+}
+
+pub(crate) async fn load_persisted_last_processed_block(dir: &Path) -> eyre::Result<Option<u64>> {
+    let path = dir.join(last_processed_block_filename());
+    if path.exists() {
+        let bytes = tokio::fs::read(&path).await.context(format!(
+            "error reading last processed block from {}",
+            path.display()
+        ))?;
+        let persisted: PersistedLastProcessedBlock =
+            toml_edit::de::from_slice(&bytes).context(format!(
+                "error deserializing last processed block from {}",
+                path.display()
+            ))?;
+        Ok(Some(persisted.block_number))
+    } else {
+        Ok(None)
+    }
+}
+
 pub(crate) async fn load_persisted_proof_id(
     proof_id_dir: &Path,
 ) -> eyre::Result<Option<PersistedProofId>> {
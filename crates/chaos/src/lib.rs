@@ -0,0 +1,157 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Failure injection for integration tests.
+//!
+//! Subsystems place named injection points on failure paths that are nearly
+//! impossible to trigger deterministically from the outside (persistence IO
+//! errors, pool exhaustion, network delays). Tests install [`FaultRule`]s at
+//! those points at runtime. The whole machinery is compiled only under the
+//! `chaos` feature of the host crates, so release builds carry no trace of it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+
+/// What an injection point should do when it is hit
+#[derive(Debug, Clone)]
+pub enum FaultRule {
+    /// Fail the next `n` hits, then behave normally again
+    FailNext(usize),
+    /// Fail every hit for the duration, counted from installation
+    FailFor(Duration),
+    /// Delay every hit by the duration; the injection point applies the delay
+    Delay(Duration),
+}
+
+enum InstalledRule {
+    FailNext(AtomicUsize),
+    FailUntil(Instant),
+    Delay(Duration),
+}
+
+static RULES: Lazy<RwLock<HashMap<String, InstalledRule>>> = Lazy::new(<_>::default);
+
+/// Installs `rule` at the injection point `point`, replacing any previous rule
+pub fn install(point: &str, rule: FaultRule) {
+    let rule = match rule {
+        FaultRule::FailNext(n) => InstalledRule::FailNext(AtomicUsize::new(n)),
+        FaultRule::FailFor(duration) => InstalledRule::FailUntil(Instant::now() + duration),
+        FaultRule::Delay(duration) => InstalledRule::Delay(duration),
+    };
+    RULES.write().insert(point.to_string(), rule);
+}
+
+/// Removes the rule at `point`; returns `false` if none was installed
+pub fn clear(point: &str) -> bool {
+    RULES.write().remove(point).is_some()
+}
+
+/// Removes every installed rule
+pub fn clear_all() {
+    RULES.write().clear();
+}
+
+/// Points with an installed rule and a short description of each rule
+pub fn list() -> Vec<(String, String)> {
+    RULES
+        .read()
+        .iter()
+        .map(|(point, rule)| {
+            let description = match rule {
+                InstalledRule::FailNext(n) => {
+                    format!("fail next {} hits", n.load(Ordering::Relaxed))
+                }
+                InstalledRule::FailUntil(deadline) => {
+                    format!(
+                        "fail for {:?} more",
+                        deadline.saturating_duration_since(Instant::now())
+                    )
+                }
+                InstalledRule::Delay(duration) => format!("delay by {duration:?}"),
+            };
+            (point.clone(), description)
+        })
+        .collect()
+}
+
+/// Whether the hit at `point` must fail. A `FailNext` rule is spent by one
+/// for every hit that it fails
+pub fn should_fail(point: &str) -> bool {
+    match RULES.read().get(point) {
+        Some(InstalledRule::FailNext(n)) => n
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok(),
+        Some(InstalledRule::FailUntil(deadline)) => Instant::now() < *deadline,
+        _ => false,
+    }
+}
+
+/// The delay the injection point at `point` must apply, if any
+pub fn delay(point: &str) -> Option<Duration> {
+    match RULES.read().get(point) {
+        Some(InstalledRule::Delay(duration)) => Some(*duration),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the rule registry is global, so every test uses its own point names
+
+    #[test]
+    fn fail_next_rule_is_spent_by_failed_hits() {
+        install("test::fail_next", FaultRule::FailNext(2));
+        assert!(should_fail("test::fail_next"));
+        assert!(should_fail("test::fail_next"));
+        assert!(!should_fail("test::fail_next"), "the rule must be spent");
+        assert!(!should_fail("test::never_installed"));
+    }
+
+    #[test]
+    fn fail_for_rule_expires() {
+        install("test::fail_for", FaultRule::FailFor(Duration::from_secs(5)));
+        assert!(should_fail("test::fail_for"));
+
+        install("test::fail_for", FaultRule::FailFor(Duration::ZERO));
+        assert!(!should_fail("test::fail_for"), "the rule must have expired");
+    }
+
+    #[test]
+    fn delay_rule_is_reported_until_cleared() {
+        let duration = Duration::from_secs(2);
+        install("test::delay", FaultRule::Delay(duration));
+        assert_eq!(delay("test::delay"), Some(duration));
+        assert_eq!(delay("test::delay"), Some(duration));
+        assert!(!should_fail("test::delay"), "a delay is not a failure");
+
+        assert!(clear("test::delay"));
+        assert_eq!(delay("test::delay"), None);
+        assert!(!clear("test::delay"));
+    }
+
+    #[test]
+    fn installed_rules_are_listed() {
+        install("test::listed", FaultRule::Delay(Duration::from_secs(1)));
+        let listed = list();
+        assert!(listed.iter().any(|(point, _)| point == "test::listed"));
+    }
+}
@@ -41,6 +41,10 @@ pub fn particles_anomaly_dir(base_dir: &Path) -> PathBuf {
     particles_dir(base_dir).join("anomalies")
 }
 
+pub fn wasm_cache_dir(base_dir: &Path) -> PathBuf {
+    base_dir.join("wasm_cache")
+}
+
 pub fn blueprint_dir(base_dir: &Path) -> PathBuf {
     base_dir.join("blueprint")
 }
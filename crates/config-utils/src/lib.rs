@@ -34,4 +34,5 @@ pub use config::particles_dir;
 pub use config::particles_vault_dir;
 pub use config::services_dir;
 pub use config::to_peer_id;
+pub use config::wasm_cache_dir;
 pub use config::workdir;
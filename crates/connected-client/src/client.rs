@@ -26,7 +26,7 @@ use tokio::sync::mpsc::error::SendError;
 use tokio::sync::{mpsc, oneshot};
 use tokio::{select, task, task::JoinHandle};
 
-use fluence_libp2p::{build_transport, Transport};
+use fluence_libp2p::{build_transport, Transport, TransportConfig};
 use particle_protocol::{Particle, ProtocolConfig};
 
 use crate::api::ParticleApi;
@@ -110,7 +110,11 @@ impl Client {
                 FluenceClientBehaviour::new(protocol_config, public_key.into(), reconnect_enabled);
 
             let kp = self.key_pair.clone().into();
-            let transport = build_transport(transport, &kp, transport_timeout);
+            let transport_config = TransportConfig {
+                socket_timeout: transport_timeout,
+                ..<_>::default()
+            };
+            let transport = build_transport(transport, &kp, &transport_config);
             SwarmBuilder::with_existing_identity(kp)
                 .with_tokio()
                 .with_other_transport(|_| transport)?
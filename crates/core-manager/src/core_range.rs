@@ -15,6 +15,7 @@
  */
 
 use ccp_shared::types::PhysicalCoreId;
+use cpu_utils::{CPUTopology, CPUTopologyError};
 use nonempty::NonEmpty;
 use range_set_blaze::RangeSetBlaze;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -31,6 +32,38 @@ impl CoreRange {
 
         self.0.is_subset(&range)
     }
+
+    /// Iterates the range as physical core ids, in ascending order,
+    /// so the `usize` to core id conversion lives in one place
+    pub fn iter_physical(&self) -> impl Iterator<Item = PhysicalCoreId> + '_ {
+        self.0
+            .iter()
+            .map(|core_id| PhysicalCoreId::new(core_id as u32))
+    }
+
+    /// Whether the range contains the physical core id
+    pub fn contains_physical(&self, core_id: PhysicalCoreId) -> bool {
+        self.0.contains(<u32>::from(core_id) as usize)
+    }
+
+    /// Checks that every core id in the range exists on this machine.
+    /// Intended for configuration-load time, before a core manager is
+    /// constructed: it names the first offending core id instead of the
+    /// generic error the manager construction would fail with
+    pub fn validate_against_topology(
+        &self,
+        topology: &CPUTopology,
+    ) -> Result<(), CoreRangeError> {
+        let physical_cores = topology
+            .physical_cores()
+            .map_err(|err| CoreRangeError::CollectCoresData { err })?;
+        let existing: RangeSetBlaze<usize> =
+            RangeSetBlaze::from_iter(physical_cores.into_iter().map(<usize>::from));
+        match self.0.iter().find(|core_id| !existing.contains(*core_id)) {
+            None => Ok(()),
+            Some(core_id) => Err(CoreRangeError::CoreNotFound(core_id as u32)),
+        }
+    }
 }
 
 impl Debug for CoreRange {
@@ -146,6 +179,14 @@ impl<'de> Deserialize<'de> for CoreRange {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum CoreRangeError {
+    #[error("Core {0} from the range doesn't exist on this machine")]
+    CoreNotFound(u32),
+    #[error("Failed to collect cores data from OS {err:?}")]
+    CollectCoresData { err: CPUTopologyError },
+}
+
 #[derive(Debug, Error, PartialEq)]
 pub enum ParseError {
     #[error("Range can't be an empty")]
@@ -156,7 +197,8 @@ pub enum ParseError {
 
 #[cfg(test)]
 mod tests {
-    use crate::core_range::{CoreRange, ParseError};
+    use crate::core_range::{CoreRange, CoreRangeError, ParseError};
+    use cpu_utils::CPUTopology;
 
     #[test]
     fn range_parsing_test() {
@@ -290,6 +332,52 @@ mod tests {
         assert_eq!(format!("{}", core_range_1), "0-2,5,7-9");
     }
 
+    #[test]
+    fn validate_against_topology_accepts_existing_cores() {
+        let topology = CPUTopology::new().unwrap();
+        let cores: Vec<usize> = topology
+            .physical_cores()
+            .unwrap()
+            .into_iter()
+            .map(<usize>::from)
+            .collect();
+        let range = CoreRange::try_from(cores.as_slice()).unwrap();
+        assert!(range.validate_against_topology(&topology).is_ok());
+    }
+
+    #[test]
+    fn validate_against_topology_reports_offending_core() {
+        let topology = CPUTopology::new().unwrap();
+        // an id right above the biggest existing one can't be on this machine
+        let missing = topology
+            .physical_cores()
+            .unwrap()
+            .into_iter()
+            .map(<usize>::from)
+            .max()
+            .unwrap()
+            + 1;
+        let range = CoreRange::try_from(&[missing][..]).unwrap();
+        let result = range.validate_against_topology(&topology);
+        assert!(
+            matches!(result, Err(CoreRangeError::CoreNotFound(id)) if id == missing as u32),
+            "expected CoreNotFound({missing}), got {result:?}"
+        );
+    }
+
+    #[test]
+    fn iter_physical_yields_core_ids() {
+        use ccp_shared::types::PhysicalCoreId;
+
+        let core_range: CoreRange = "0-2,5".parse().unwrap();
+        let actual: Vec<PhysicalCoreId> = core_range.iter_physical().collect();
+        let expected: Vec<PhysicalCoreId> = [0u32, 1, 2, 5].map(PhysicalCoreId::new).to_vec();
+        assert_eq!(actual, expected);
+
+        assert!(core_range.contains_physical(PhysicalCoreId::new(5)));
+        assert!(!core_range.contains_physical(PhysicalCoreId::new(4)));
+    }
+
     #[test]
     fn range_is_inclusive() {
         let core_range_1: CoreRange = "1-3".parse().unwrap();
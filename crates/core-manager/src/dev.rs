@@ -29,7 +29,10 @@ use crate::manager::CoreManagerFunctions;
 use crate::persistence::{
     PersistenceTask, PersistentCoreManagerFunctions, PersistentCoreManagerState,
 };
-use crate::types::{AcquireRequest, Assignment, Cores, WorkType};
+use crate::types::{
+    pinning_script, AcquireRequest, Assignment, CoreEvent, Cores, WorkType,
+    EVENTS_CHANNEL_CAPACITY,
+};
 use crate::{CoreRange, Map, MultiMap};
 
 /// `DevCoreManager` is a CPU core manager that provides a more flexible approach to
@@ -39,20 +42,29 @@ use crate::{CoreRange, Map, MultiMap};
 /// making it suitable for scenarios where workload priorities may vary and strict allocation
 /// policies are not necessary.
 pub struct DevCoreManager {
+    // name of this manager instance; attached to its log lines to tell
+    // instances apart when a process runs more than one manager
+    name: String,
     // path to the persistent state
     file_path: PathBuf,
     // inner state
     state: RwLock<CoreManagerState>,
-    // persistent task notification channel
-    sender: tokio::sync::mpsc::Sender<()>,
+    // assignment change events; the persistence task is one of the subscribers
+    events: tokio::sync::broadcast::Sender<CoreEvent>,
 }
 
 impl DevCoreManager {
-    /// Loads the state from `file_name` if exists. If not creates a new empty state
+    /// Loads the state from `file_name` if exists. If not creates a new empty state.
+    /// When the persisted state doesn't match `system_cpu_count` or `core_range`,
+    /// the state is rebuilt from scratch, or, with `strict` set, loading fails with
+    /// [LoadingError::ConfigChanged] so an unintended config change doesn't silently
+    /// drop the persisted core mapping
     pub fn from_path(
+        name: String,
         file_path: PathBuf,
         system_cpu_count: usize,
         core_range: CoreRange,
+        strict: bool,
     ) -> Result<(Self, PersistenceTask), LoadingError> {
         let exists = file_path.exists();
         if exists {
@@ -72,11 +84,30 @@ impl DevCoreManager {
                 && persistent_state.system_cores.len() == system_cpu_count
             {
                 let state: CoreManagerState = persistent_state.into();
-                Ok(Self::make_instance_with_task(file_path, state))
+                Ok(Self::make_instance_with_task(name, file_path, state))
             } else {
-                tracing::warn!(target: "core-manager", "The initial config has been changed. Ignoring persisted core mapping");
+                let persisted_system_cores = persistent_state.system_cores.len();
+                if strict {
+                    return Err(LoadingError::ConfigChanged {
+                        config_system_cores: system_cpu_count,
+                        persisted_system_cores,
+                        config_core_range: format!("{config_range:?}"),
+                        persisted_core_range: format!("{loaded_range:?}"),
+                    });
+                }
+                let added = &config_range - &loaded_range;
+                let removed = &loaded_range - &config_range;
+                tracing::warn!(
+                    target: "core-manager",
+                    name = %name,
+                    "The initial config has been changed: \
+                     system cores {persisted_system_cores} -> {system_cpu_count}, \
+                     core range {loaded_range:?} -> {config_range:?} \
+                     (added {added:?}, removed {removed:?}). \
+                     Ignoring persisted core mapping"
+                );
                 let (core_manager, task) =
-                    Self::new(file_path.clone(), system_cpu_count, core_range)
+                    Self::new(name, file_path.clone(), system_cpu_count, core_range)
                         .map_err(|err| LoadingError::CreateCoreManager { err })?;
                 core_manager
                     .persist()
@@ -84,9 +115,10 @@ impl DevCoreManager {
                 Ok((core_manager, task))
             }
         } else {
-            tracing::debug!(target: "core-manager", "No persisted core mapping was not found. Creating a new one");
-            let (core_manager, task) = Self::new(file_path.clone(), system_cpu_count, core_range)
-                .map_err(|err| LoadingError::CreateCoreManager { err })?;
+            tracing::debug!(target: "core-manager", name = %name, "No persisted core mapping was not found. Creating a new one");
+            let (core_manager, task) =
+                Self::new(name, file_path.clone(), system_cpu_count, core_range)
+                    .map_err(|err| LoadingError::CreateCoreManager { err })?;
             core_manager
                 .persist()
                 .map_err(|err| LoadingError::PersistError { err })?;
@@ -96,6 +128,7 @@ impl DevCoreManager {
 
     /// Creates an empty core manager with only system cores assigned
     fn new(
+        name: String,
         file_name: PathBuf,
         system_cpu_count: usize,
         core_range: CoreRange,
@@ -131,10 +164,7 @@ impl DevCoreManager {
         let mut available_cores: BTreeSet<PhysicalCoreId> = BTreeSet::new();
 
         for physical_core_id in physical_cores {
-            if core_range
-                .0
-                .contains(<PhysicalCoreId as Into<u32>>::into(physical_core_id) as usize)
-            {
+            if core_range.contains_physical(physical_core_id) {
                 let logical_cores = topology
                     .logical_cores_for_physical(physical_core_id)
                     .map_err(|err| CreateError::CollectCoresData { err })?;
@@ -173,28 +203,99 @@ impl DevCoreManager {
             work_type_mapping: type_mapping,
         };
 
-        let result = Self::make_instance_with_task(file_name, inner_state);
+        let result = Self::make_instance_with_task(name, file_name, inner_state);
 
         Ok(result)
     }
 
+    /// Validates `new` and atomically replaces the current state with it, persisting
+    /// the result. Intended for persistence format migrations: tooling loads an
+    /// old-format file, transforms it and installs the transformed state here.
+    ///
+    /// The swap is rejected if the new state references cores absent from its own
+    /// cores mapping, or would strand currently running units (units with assigned
+    /// cores that are missing from the new state).
+    pub fn replace_state(&self, new: PersistentCoreManagerState) -> Result<(), LoadingError> {
+        let known_cores: BTreeSet<PhysicalCoreId> = new
+            .cores_mapping
+            .iter()
+            .map(|(core_id, _)| *core_id)
+            .collect();
+        let mut unknown_cores: BTreeSet<PhysicalCoreId> = BTreeSet::new();
+        let referenced_cores = new
+            .system_cores
+            .iter()
+            .chain(new.available_cores.iter())
+            .chain(new.unit_id_mapping.iter().map(|(core_id, _)| core_id));
+        for core_id in referenced_cores {
+            if !known_cores.contains(core_id) {
+                unknown_cores.insert(*core_id);
+            }
+        }
+        if !unknown_cores.is_empty() {
+            return Err(LoadingError::UnknownCores {
+                cores: unknown_cores.into_iter().collect(),
+            });
+        }
+
+        let new_units: BTreeSet<CUID> = new
+            .unit_id_mapping
+            .iter()
+            .map(|(_, unit_id)| *unit_id)
+            .collect();
+
+        let new_system_cores: BTreeSet<PhysicalCoreId> =
+            new.system_cores.iter().copied().collect();
+
+        let mut lock = self.state.write();
+        let mut stranded: Vec<CUID> = lock
+            .unit_id_core_mapping
+            .keys()
+            .copied()
+            .filter(|unit_id| !new_units.contains(unit_id))
+            .collect();
+        if !stranded.is_empty() {
+            stranded.sort();
+            return Err(LoadingError::StrandedUnits { units: stranded });
+        }
+        let system_cores_changed = lock.system_cores != new_system_cores;
+        *lock = new.into();
+        drop(lock);
+
+        if system_cores_changed {
+            let _ = self.events.send(CoreEvent::SystemCoresChanged);
+        }
+
+        self.persist()
+            .map_err(|err| LoadingError::PersistError { err })?;
+        Ok(())
+    }
+
     fn make_instance_with_task(
+        name: String,
         file_name: PathBuf,
         state: CoreManagerState,
     ) -> (Self, PersistenceTask) {
-        // This channel is used to notify a persistent task about changes.
-        // It has a size of 1 because we need only the fact that this change happen
-        let (sender, receiver) = tokio::sync::mpsc::channel(1);
+        // This channel notifies subscribers (including the persistence task)
+        // about assignment changes. The capacity is small on purpose: laggards
+        // lose the oldest events and can re-read the full state from the manager
+        let (events, receiver) = tokio::sync::broadcast::channel(EVENTS_CHANNEL_CAPACITY);
 
         (
             Self {
+                name: name.clone(),
                 file_path: file_name,
-                sender,
+                events,
                 state: RwLock::new(state),
             },
-            PersistenceTask::new(receiver),
+            PersistenceTask::new(name, receiver),
         )
     }
+
+    /// Name of this manager instance, attached to every log line it emits
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 struct CoreManagerState {
@@ -228,6 +329,8 @@ impl From<&CoreManagerState> for PersistentCoreManagerState {
                 .iter()
                 .map(|(k, v)| ((*k), v.clone()))
                 .collect(),
+            // the dev manager doesn't support preemption, so it doesn't track the order
+            acquire_order: Vec::new(),
         }
     }
 }
@@ -254,6 +357,24 @@ impl CoreManagerFunctions for DevCoreManager {
         &self,
         assign_request: AcquireRequest,
     ) -> Result<Assignment, AcquireError> {
+        // tie core allocation into distributed traces: the span carries the
+        // request shape, the closing event reports what was actually assigned
+        let span = tracing::info_span!(
+            target: "core-manager",
+            "acquire_worker_core",
+            name = %self.name,
+            unit_count = assign_request.unit_ids.len(),
+            work_type = %assign_request.worker_type,
+        );
+        let _guard = span.enter();
+
+        // a duplicated unit id would be mapped once and silently no-op afterwards,
+        // so reject the request before touching the state
+        let duplicate_unit_ids = assign_request.duplicate_unit_ids();
+        if !duplicate_unit_ids.is_empty() {
+            return Err(AcquireError::DuplicateUnitIds(duplicate_unit_ids));
+        }
+
         let mut lock = self.state.write();
         let mut result_physical_core_ids = BTreeSet::new();
         let mut result_logical_core_ids = BTreeSet::new();
@@ -261,8 +382,10 @@ impl CoreManagerFunctions for DevCoreManager {
             assign_request.unit_ids.len(),
             FxBuildHasher::default(),
         );
+        let mut assignments: Vec<(CUID, PhysicalCoreId)> =
+            Vec::with_capacity(assign_request.unit_ids.len());
         let worker_unit_type = assign_request.worker_type;
-        for unit_id in assign_request.unit_ids {
+        for unit_id in assign_request.unit_ids.iter().copied() {
             let physical_core_id = lock.unit_id_core_mapping.get(&unit_id).cloned();
             let physical_core_id = match physical_core_id {
                 None => {
@@ -285,6 +408,7 @@ impl CoreManagerFunctions for DevCoreManager {
                 }
             };
             result_physical_core_ids.insert(physical_core_id);
+            assignments.push((unit_id, physical_core_id));
 
             // SAFETY: The physical core always has corresponding logical ids,
             // unit_id_core_mapping can't have a wrong physical_core_id
@@ -307,19 +431,32 @@ impl CoreManagerFunctions for DevCoreManager {
             );
         }
 
-        // We are trying to notify a persistence task that the state has been changed.
-        // We don't care if the channel is full, it means the current state will be stored with the previous event
-        let _ = self.sender.try_send(());
+        drop(lock);
+
+        // Notify subscribers (including the persistence task) about the change.
+        // We don't care if there are no subscribers left to receive the events
+        let _ = self.events.send(CoreEvent::Acquired {
+            assignments,
+            work_type: worker_unit_type,
+        });
+
+        tracing::info!(
+            target: "core-manager",
+            physical_cores = result_physical_core_ids.len(),
+            "cores acquired"
+        );
 
         Ok(Assignment {
             physical_core_ids: result_physical_core_ids,
             logical_core_ids: result_logical_core_ids,
             cuid_cores,
+            preempted_cuids: Vec::new(),
         })
     }
 
     fn release(&self, unit_ids: &[CUID]) {
         let mut lock = self.state.write();
+        let mut released: Vec<CUID> = Vec::with_capacity(unit_ids.len());
         for unit_id in unit_ids {
             if let Some(physical_core_id) = lock.unit_id_core_mapping.remove(unit_id) {
                 let mapping = lock.core_unit_id_mapping.get_vec_mut(&physical_core_id);
@@ -331,8 +468,13 @@ impl CoreManagerFunctions for DevCoreManager {
                     }
                 }
                 lock.work_type_mapping.remove(unit_id);
+                released.push(*unit_id);
             }
         }
+        drop(lock);
+        if !released.is_empty() {
+            let _ = self.events.send(CoreEvent::Released { cuids: released });
+        }
     }
 
     fn get_system_cpu_assignment(&self) -> Assignment {
@@ -354,8 +496,43 @@ impl CoreManagerFunctions for DevCoreManager {
             physical_core_ids: lock.system_cores.clone(),
             logical_core_ids,
             cuid_cores: Map::with_hasher(FxBuildHasher::default()),
+            preempted_cuids: Vec::new(),
         }
     }
+
+    fn export_pinning_script(&self, unit_id: &CUID) -> Option<String> {
+        let lock = self.state.read();
+        let physical_core_id = *lock.unit_id_core_mapping.get(unit_id)?;
+        let logical_core_ids = lock.cores_mapping.get_vec(&physical_core_id)?;
+        Some(pinning_script(logical_core_ids))
+    }
+
+    fn work_type_of(&self, unit_id: &CUID) -> Option<WorkType> {
+        let lock = self.state.read();
+        lock.work_type_mapping.get(unit_id).cloned()
+    }
+
+    fn reassign_work_type(&self, unit_id: &CUID, new: WorkType) -> Result<(), AcquireError> {
+        let mut lock = self.state.write();
+        let physical_core_id = match lock.unit_id_core_mapping.get(unit_id) {
+            Some(core_id) => *core_id,
+            None => return Err(AcquireError::UnitNotAssigned { unit_id: *unit_id }),
+        };
+        lock.work_type_mapping.insert(*unit_id, new.clone());
+        drop(lock);
+
+        // the cores stay as they were; the event only signals that the state
+        // has changed and has to be persisted
+        let _ = self.events.send(CoreEvent::Acquired {
+            assignments: vec![(*unit_id, physical_core_id)],
+            work_type: new,
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<CoreEvent> {
+        self.events.subscribe()
+    }
 }
 
 impl PersistentCoreManagerFunctions for DevCoreManager {
@@ -376,8 +553,12 @@ mod tests {
     use rand::RngCore;
     use std::str::FromStr;
 
+    use std::ops::Deref;
+
+    use crate::errors::{AcquireError, LoadingError};
     use crate::manager::CoreManagerFunctions;
-    use crate::types::{AcquireRequest, WorkType};
+    use crate::persistence::PersistentCoreManagerState;
+    use crate::types::{AcquireRequest, CoreEvent, WorkType};
     use crate::{CoreRange, DevCoreManager, StrictCoreManager};
 
     fn cores_exists() -> bool {
@@ -390,9 +571,11 @@ mod tests {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
 
             let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 2,
                 CoreRange::default(),
+                false,
             )
             .unwrap();
             let init_id_1 = <CUID>::from_hex(
@@ -405,22 +588,19 @@ mod tests {
             .unwrap();
             let unit_ids = vec![init_id_1, init_id_2];
             let assignment_1 = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             let assignment_2 = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::Deal,
-                })
+                .acquire_worker_core(AcquireRequest::new(unit_ids.clone(), WorkType::Deal))
                 .unwrap();
             let assignment_3 = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             assert_eq!(assignment_1, assignment_2);
             assert_eq!(assignment_1, assignment_3);
@@ -433,9 +613,11 @@ mod tests {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
             let system_cpu_count = 2;
             let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 system_cpu_count,
                 CoreRange::default(),
+                false,
             )
             .unwrap();
             let before_lock = manager.state.read();
@@ -462,10 +644,10 @@ mod tests {
             .unwrap();
             let unit_ids = vec![init_id_1, init_id_2];
             let assignment = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             assert_eq!(assignment.physical_core_ids.len(), 2);
 
@@ -502,9 +684,11 @@ mod tests {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
             let system_cpu_count = 2;
             let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 system_cpu_count,
                 CoreRange::default(),
+                false,
             )
             .unwrap();
             let before_lock = manager.state.read();
@@ -534,10 +718,7 @@ mod tests {
 
                 let unit_ids = vec![init_id_1, init_id_2];
                 let assignment = manager
-                    .acquire_worker_core(AcquireRequest {
-                        unit_ids: unit_ids.clone(),
-                        worker_type: WorkType::Deal,
-                    })
+                    .acquire_worker_core(AcquireRequest::new(unit_ids.clone(), WorkType::Deal))
                     .unwrap();
                 assert_eq!(assignment.physical_core_ids.len(), 2);
             }
@@ -561,6 +742,271 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_duplicate_unit_ids_rejected() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+
+            let before_available = manager.state.read().available_cores.clone();
+
+            let result = manager.acquire_worker_core(AcquireRequest::new(
+                vec![init_id_1, init_id_2, init_id_1],
+                WorkType::Deal,
+            ));
+            assert!(
+                matches!(result, Err(AcquireError::DuplicateUnitIds(ref unit_ids)) if unit_ids == &vec![init_id_1]),
+                "expected DuplicateUnitIds, got {result:?}"
+            );
+
+            // the request is rejected before any state is touched
+            let state = manager.state.read();
+            assert_eq!(state.available_cores, before_available);
+            assert_eq!(state.core_unit_id_mapping.len(), 0);
+            assert_eq!(state.unit_id_core_mapping.len(), 0);
+            assert_eq!(state.work_type_mapping.len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_replace_state() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let assignment = manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+            let current_core = *assignment.physical_core_ids.first().unwrap();
+
+            // build a new state from the current one, pinning the running unit
+            // to a different core
+            let lock = manager.state.read();
+            let mut new_state: PersistentCoreManagerState = lock.deref().into();
+            drop(lock);
+            let new_core = *new_state
+                .available_cores
+                .iter()
+                .find(|core_id| **core_id != current_core)
+                .expect("no spare core to migrate to");
+            new_state.unit_id_mapping = vec![(new_core, init_id_1)];
+
+            manager.replace_state(new_state).unwrap();
+
+            // subsequent acquires must reflect the swapped-in state
+            let assignment = manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+            assert_eq!(
+                assignment.physical_core_ids.iter().collect::<Vec<_>>(),
+                vec![&new_core]
+            );
+        }
+    }
+
+    #[test]
+    fn test_events_on_assignment_changes() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+            let mut events = manager.subscribe();
+
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let assignment = manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+            manager.release(&[init_id_1]);
+
+            // swap a system core with a free one: subscribers must be told
+            // that the system set has changed
+            let lock = manager.state.read();
+            let mut new_state: PersistentCoreManagerState = lock.deref().into();
+            drop(lock);
+            let moved = new_state.system_cores.pop().unwrap();
+            let replacement = new_state.available_cores.pop().unwrap();
+            new_state.system_cores.push(replacement);
+            new_state.available_cores.push(moved);
+            manager.replace_state(new_state).unwrap();
+
+            let assigned_core = *assignment.physical_core_ids.first().unwrap();
+            assert_eq!(
+                events.try_recv().unwrap(),
+                CoreEvent::Acquired {
+                    assignments: vec![(init_id_1, assigned_core)],
+                    work_type: WorkType::Deal,
+                }
+            );
+            assert_eq!(
+                events.try_recv().unwrap(),
+                CoreEvent::Released {
+                    cuids: vec![init_id_1]
+                }
+            );
+            assert_eq!(events.try_recv().unwrap(), CoreEvent::SystemCoresChanged);
+            assert!(events.try_recv().is_err(), "no more events are expected");
+        }
+    }
+
+    #[test]
+    fn test_replace_state_rejects_stranding_units() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+
+            // the new state forgets about the running unit
+            let lock = manager.state.read();
+            let mut new_state: PersistentCoreManagerState = lock.deref().into();
+            drop(lock);
+            new_state.unit_id_mapping = vec![];
+
+            let result = manager.replace_state(new_state);
+            assert!(
+                matches!(result, Err(LoadingError::StrandedUnits { ref units }) if units == &vec![init_id_1]),
+                "expected StrandedUnits, got {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_replace_state_rejects_unknown_cores() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+
+            let lock = manager.state.read();
+            let mut new_state: PersistentCoreManagerState = lock.deref().into();
+            drop(lock);
+            // reference a core the new state's cores mapping knows nothing about
+            new_state
+                .available_cores
+                .push(cpu_utils::PhysicalCoreId::new(16384));
+
+            let result = manager.replace_state(new_state);
+            assert!(
+                matches!(result, Err(LoadingError::UnknownCores { .. })),
+                "expected UnknownCores, got {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_changed_config_resets_state_by_default() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let path = temp_dir.path().join("test.toml");
+
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                path.clone(),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+            drop(manager);
+
+            // the system core count has changed: the persisted state is rebuilt
+            let (manager, _task) =
+                DevCoreManager::from_path("test".to_string(), path, 1, CoreRange::default(), false)
+                    .unwrap();
+            let lock = manager.state.read();
+            assert_eq!(lock.system_cores.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_changed_config_fails_loudly_in_strict_mode() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let path = temp_dir.path().join("test.toml");
+
+            let (manager, _task) = DevCoreManager::from_path(
+                "test".to_string(),
+                path.clone(),
+                2,
+                CoreRange::default(),
+                false,
+            )
+            .unwrap();
+            drop(manager);
+
+            let err =
+                DevCoreManager::from_path("test".to_string(), path, 1, CoreRange::default(), true)
+                    .err()
+                .expect("loading must fail in strict mode");
+            assert!(
+                matches!(
+                    err,
+                    LoadingError::ConfigChanged {
+                        config_system_cores: 1,
+                        persisted_system_cores: 2,
+                        ..
+                    }
+                ),
+                "expected ConfigChanged, got {err:?}"
+            );
+        }
+    }
+
     #[test]
     fn test_wrong_range() {
         if cores_exists() {
@@ -568,7 +1014,12 @@ mod tests {
 
             let range = CoreRange::from_str("0-16384").unwrap();
 
-            let result = StrictCoreManager::from_path(temp_dir.path().join("test.toml"), 2, range);
+            let result = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                range,
+            );
 
             assert!(result.is_err());
             assert_eq!(
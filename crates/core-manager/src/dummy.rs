@@ -16,7 +16,7 @@
 
 use crate::errors::AcquireError;
 use crate::manager::CoreManagerFunctions;
-use crate::types::{AcquireRequest, Assignment};
+use crate::types::{AcquireRequest, Assignment, CoreEvent, WorkType, EVENTS_CHANNEL_CAPACITY};
 use crate::Map;
 use async_trait::async_trait;
 use ccp_shared::types::{LogicalCoreId, PhysicalCoreId, CUID};
@@ -24,8 +24,18 @@ use fxhash::FxBuildHasher;
 use rand::prelude::IteratorRandom;
 use std::collections::BTreeSet;
 
-#[derive(Default)]
-pub struct DummyCoreManager {}
+pub struct DummyCoreManager {
+    // no real assignments happen here, but subscribers still get synthetic
+    // events so the observers behave uniformly across manager kinds
+    events: tokio::sync::broadcast::Sender<CoreEvent>,
+}
+
+impl Default for DummyCoreManager {
+    fn default() -> Self {
+        let (events, _) = tokio::sync::broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+        Self { events }
+    }
+}
 
 impl DummyCoreManager {
     fn all_cores(&self) -> Assignment {
@@ -39,6 +49,7 @@ impl DummyCoreManager {
             physical_core_ids,
             logical_core_ids,
             cuid_cores: Map::with_hasher(FxBuildHasher::default()),
+            preempted_cuids: Vec::new(),
         }
     }
 }
@@ -58,16 +69,54 @@ impl CoreManagerFunctions for DummyCoreManager {
                 .choose_multiple(&mut rand::thread_rng(), assign_request.unit_ids.len()),
         );
 
+        let _ = self.events.send(CoreEvent::Acquired {
+            // the dummy manager doesn't pin units to cores, so every unit is
+            // reported on the synthetic core 0
+            assignments: assign_request
+                .unit_ids
+                .into_iter()
+                .map(|unit_id| (unit_id, PhysicalCoreId::new(0)))
+                .collect(),
+            work_type: assign_request.worker_type,
+        });
+
         Ok(Assignment {
             physical_core_ids: BTreeSet::new(),
             logical_core_ids,
             cuid_cores: Map::with_hasher(FxBuildHasher::default()),
+            preempted_cuids: Vec::new(),
         })
     }
 
-    fn release(&self, _unit_ids: &[CUID]) {}
+    fn release(&self, unit_ids: &[CUID]) {
+        if !unit_ids.is_empty() {
+            let _ = self.events.send(CoreEvent::Released {
+                cuids: unit_ids.to_vec(),
+            });
+        }
+    }
 
     fn get_system_cpu_assignment(&self) -> Assignment {
         self.all_cores()
     }
+
+    fn export_pinning_script(&self, _unit_id: &CUID) -> Option<String> {
+        // the dummy manager doesn't track per-unit assignments, so there's
+        // nothing to pin an external process to
+        None
+    }
+
+    fn work_type_of(&self, _unit_id: &CUID) -> Option<WorkType> {
+        // no per-unit state is kept, so the work type is unknown
+        None
+    }
+
+    fn reassign_work_type(&self, unit_id: &CUID, _new: WorkType) -> Result<(), AcquireError> {
+        // no per-unit state is kept, so there is nothing to reassign
+        Err(AcquireError::UnitNotAssigned { unit_id: *unit_id })
+    }
+
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<CoreEvent> {
+        self.events.subscribe()
+    }
 }
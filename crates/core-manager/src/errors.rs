@@ -61,6 +61,17 @@ pub enum LoadingError {
         #[from]
         err: PersistError,
     },
+    #[error("Persisted core state doesn't match the config: system cores {persisted_system_cores} (config: {config_system_cores}), core range {persisted_core_range} (config: {config_core_range})")]
+    ConfigChanged {
+        config_system_cores: usize,
+        persisted_system_cores: usize,
+        config_core_range: String,
+        persisted_core_range: String,
+    },
+    #[error("Replacement state references cores absent from its cores mapping: {cores:?}")]
+    UnknownCores { cores: Vec<PhysicalCoreId> },
+    #[error("Replacement state would strand currently running units: {units:?}")]
+    StrandedUnits { units: Vec<CUID> },
 }
 
 #[derive(Debug, Error)]
@@ -77,6 +88,14 @@ pub enum PersistError {
     },
 }
 
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("Couldn't build an acquire request: unit ids are missing")]
+    MissingUnitIds,
+    #[error("Couldn't build an acquire request: worker type is missing")]
+    MissingWorkerType,
+}
+
 #[derive(Debug)]
 pub struct CurrentAssignment {
     data: Vec<(PhysicalCoreId, CUID)>,
@@ -115,4 +134,8 @@ pub enum AcquireError {
         available: usize,
         current_assignment: CurrentAssignment,
     },
+    #[error("Couldn't reassign work type: unit {unit_id} holds no cores")]
+    UnitNotAssigned { unit_id: CUID },
+    #[error("Couldn't assign cores: the request contains duplicate unit ids: {0:?}")]
+    DuplicateUnitIds(Vec<CUID>),
 }
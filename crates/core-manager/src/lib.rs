@@ -39,6 +39,9 @@ mod strict;
 
 pub use ccp_shared::types::CUID;
 pub use core_range::CoreRange;
+pub use core_range::CoreRangeError;
+pub use core_range::ParseError;
+pub use cpu_utils::CPUTopology;
 pub use cpu_utils::LogicalCoreId;
 pub use cpu_utils::PhysicalCoreId;
 pub use dev::DevCoreManager;
@@ -46,6 +49,7 @@ pub use dummy::DummyCoreManager;
 use fxhash::FxHasher;
 pub use manager::CoreManager;
 pub use manager::CoreManagerFunctions;
+pub use persistence::PersistentCoreManagerState;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 pub use strict::StrictCoreManager;
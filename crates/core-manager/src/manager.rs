@@ -21,7 +21,7 @@ use enum_dispatch::enum_dispatch;
 use crate::dummy::DummyCoreManager;
 use crate::errors::AcquireError;
 use crate::strict::StrictCoreManager;
-use crate::types::{AcquireRequest, Assignment};
+use crate::types::{AcquireRequest, Assignment, CoreEvent, WorkType};
 
 /// The `CoreManagerFunctions` trait defines operations for managing CPU cores.
 ///
@@ -62,7 +62,7 @@ use crate::types::{AcquireRequest, Assignment};
 /// let unit_ids = vec!["1".into(), "2".into()];
 ///
 /// // Acquire and release cores
-/// let assignment = core_manager.acquire_worker_core(AcquireRequest { unit_ids, worker_type: WorkType::CapacityCommitment }).unwrap();
+/// let assignment = core_manager.acquire_worker_core(AcquireRequest::new(unit_ids.clone(), WorkType::CapacityCommitment)).unwrap();
 ///
 /// // Retrieve system CPU assignment
 /// let system_assignment = core_manager.get_system_cpu_assignment();
@@ -82,6 +82,28 @@ pub trait CoreManagerFunctions {
     fn release(&self, unit_ids: &[CUID]);
 
     fn get_system_cpu_assignment(&self) -> Assignment;
+
+    /// Renders a shell command pinning an external process (e.g. a CCP proof
+    /// worker running in a separate binary) to the logical cores assigned to
+    /// `unit_id`, like `taskset --cpu-list 2,3 <command>`.
+    /// Returns `None` if the CUID has no cores assigned
+    fn export_pinning_script(&self, unit_id: &CUID) -> Option<String>;
+
+    /// Returns the [WorkType] the unit is currently acquired as, or `None`
+    /// for a unit that holds no cores. Lets schedulers check the current
+    /// type without re-acquiring the unit just to observe it
+    fn work_type_of(&self, unit_id: &CUID) -> Option<WorkType>;
+
+    /// Switches the [WorkType] of an already acquired unit in place, keeping
+    /// its core assignment intact. Cheaper and more explicit than re-running
+    /// [`CoreManagerFunctions::acquire_worker_core`] just to change the type.
+    /// Errors with [`AcquireError::UnitNotAssigned`] if the unit holds no cores
+    fn reassign_work_type(&self, unit_id: &CUID, new: WorkType) -> Result<(), AcquireError>;
+
+    /// Subscribes to [`CoreEvent`] notifications about assignment changes.
+    /// A subscriber that doesn't keep up loses the oldest pending events,
+    /// so the stream must be treated as a change signal, not as a replayable log
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<CoreEvent>;
 }
 
 #[enum_dispatch(CoreManagerFunctions)]
@@ -20,15 +20,16 @@ use std::path::Path;
 use std::sync::Arc;
 
 use ccp_shared::types::{LogicalCoreId, PhysicalCoreId, CUID};
-use futures::StreamExt;
 use hex_utils::serde_as::Hex;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
-use tokio::sync::mpsc::Receiver;
-use tokio_stream::wrappers::ReceiverStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::Notify;
 
 use crate::errors::PersistError;
-use crate::types::WorkType;
+use crate::types::{CoreEvent, WorkType};
 use crate::CoreManager;
 
 pub trait PersistentCoreManagerFunctions {
@@ -36,53 +37,123 @@ pub trait PersistentCoreManagerFunctions {
 }
 
 pub struct PersistenceTask {
-    receiver: Receiver<()>,
+    // name of the manager whose state this task persists; attached to its log lines
+    name: String,
+    receiver: broadcast::Receiver<CoreEvent>,
+    // asks the background task to drain pending events, write one last time and exit
+    stop: Arc<Notify>,
+    // how many forced flushes have been performed, exposed for tests and debugging
+    flush_count: AtomicU64,
 }
 
 impl PersistenceTask {
-    pub(crate) fn new(receiver: Receiver<()>) -> Self {
-        Self { receiver }
+    pub(crate) fn new(name: String, receiver: broadcast::Receiver<CoreEvent>) -> Self {
+        Self {
+            name,
+            receiver,
+            stop: Arc::new(Notify::new()),
+            flush_count: AtomicU64::new(0),
+        }
     }
 }
 
 impl PersistenceTask {
-    async fn process_events<Src>(stream: Src, core_manager: Arc<CoreManager>)
-    where
-        Src: futures::Stream<Item = ()> + Unpin + Send + Sync + 'static,
-    {
-        let core_manager = core_manager.clone();
-        // We are not interested in the content of the event
-        // We are waiting for the event to initiate the persistence process
-        stream.for_each(move |_| {
+    async fn process_events(
+        name: String,
+        mut receiver: broadcast::Receiver<CoreEvent>,
+        stop: Arc<Notify>,
+        core_manager: Arc<CoreManager>,
+    ) {
+        // We are not interested in the content of the events, only in the fact
+        // that the state has changed and has to be written out
+        loop {
+            let stopping = tokio::select! {
+                event = receiver.recv() => match event {
+                    // The state is written as a whole, so events lost by lagging
+                    // are covered by the write triggered by the latest one
+                    Ok(_) | Err(RecvError::Lagged(_)) => false,
+                    Err(RecvError::Closed) => break,
+                },
+                // on stop, fall through to one final write covering whatever
+                // is still pending in the channel, then exit
+                _ = stop.notified() => true,
+            };
+            // coalesce the events accumulated so far into a single write
+            loop {
+                match receiver.try_recv() {
+                    Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+                    Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+                }
+            }
             let core_manager = core_manager.clone();
-            async move {
-                tokio::task::spawn_blocking(move || {
-                    if let CoreManager::Persistent(manager) = core_manager.as_ref() {
-                        let result = manager.persist();
-                        match result {
-                            Ok(_) => {
-                                tracing::debug!(target: "core-manager", "Core state was persisted");
-                            }
-                            Err(err) => {
-                                tracing::warn!(target: "core-manager", "Failed to save core state {err}");
-                            }
+            let name = name.clone();
+            tokio::task::spawn_blocking(move || {
+                if let CoreManager::Persistent(manager) = core_manager.as_ref() {
+                    let result = manager.persist();
+                    match result {
+                        Ok(_) => {
+                            tracing::debug!(target: "core-manager", name = %name, "Core state was persisted");
+                        }
+                        Err(err) => {
+                            tracing::warn!(target: "core-manager", name = %name, "Failed to save core state {err}");
                         }
                     }
-                })
-                    .await
-                    .expect("Could not spawn persist task")
+                }
+            })
+            .await
+            .expect("Could not spawn persist task");
+
+            if stopping {
+                tracing::debug!(target: "core-manager", name = %name, "Core state persistence stopped after a final write");
+                break;
             }
-        }).await;
+        }
     }
 
-    pub async fn run(self, core_manager: Arc<CoreManager>) {
-        let stream = ReceiverStream::from(self.receiver);
-
+    pub async fn run(&self, core_manager: Arc<CoreManager>) {
+        // resubscribe() follows the events from this point on; the state at the
+        // moment of the manager creation is persisted by the manager itself
+        let receiver = self.receiver.resubscribe();
         tokio::task::Builder::new()
             .name("core-manager-persist")
-            .spawn(Self::process_events(stream, core_manager))
+            .spawn(Self::process_events(
+                self.name.clone(),
+                receiver,
+                self.stop.clone(),
+                core_manager,
+            ))
             .expect("Could not spawn persist task");
     }
+
+    /// Asks the background task to drain pending events, write the state one
+    /// last time and exit. Intended for graceful shutdown, where exiting
+    /// right away could lose the most recent change
+    pub fn stop(&self) {
+        // notify_one stores a permit, so a stop signaled while a write is
+        // in flight is not lost
+        self.stop.notify_one();
+    }
+
+    /// Persists the state right away, bypassing the event channel. Intended for
+    /// shutdown sequences where the write must complete before the process exits
+    pub async fn force_flush(&self, core_manager: Arc<CoreManager>) {
+        let name = self.name.clone();
+        tokio::task::spawn_blocking(move || {
+            if let CoreManager::Persistent(manager) = core_manager.as_ref() {
+                if let Err(err) = manager.persist() {
+                    tracing::warn!(target: "core-manager", name = %name, "Failed to save core state on forced flush {err}");
+                }
+            }
+        })
+        .await
+        .expect("Could not spawn persist task");
+        self.flush_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// How many forced flushes have been performed so far
+    pub fn flush_count(&self) -> u64 {
+        self.flush_count.load(Ordering::Relaxed)
+    }
 }
 
 #[serde_as]
@@ -95,10 +166,20 @@ pub struct PersistentCoreManagerState {
     pub unit_id_mapping: Vec<(PhysicalCoreId, CUID)>,
     #[serde_as(as = "Vec<(Hex, _)>")]
     pub work_type_mapping: Vec<(CUID, WorkType)>,
+    // order in which units were assigned, oldest first; used for preemption decisions
+    #[serde(default)]
+    #[serde_as(as = "Vec<Hex>")]
+    pub acquire_order: Vec<CUID>,
 }
 
 impl PersistentCoreManagerState {
     pub fn persist(&self, file_path: &Path) -> Result<(), PersistError> {
+        #[cfg(feature = "chaos")]
+        if chaos::should_fail("core-manager::persist") {
+            return Err(PersistError::IoError {
+                err: std::io::Error::other("chaos: injected persist failure"),
+            });
+        }
         let toml = toml::to_string_pretty(&self)
             .map_err(|err| PersistError::SerializationError { err })?;
         let mut file = File::create(file_path).map_err(|err| PersistError::IoError { err })?;
@@ -110,16 +191,22 @@ impl PersistentCoreManagerState {
 
 #[cfg(test)]
 mod tests {
+    use crate::manager::CoreManagerFunctions;
     use crate::persistence::PersistentCoreManagerState;
-    use crate::types::WorkType;
+    use crate::types::{AcquireRequest, WorkType};
+    use crate::{CoreManager, CoreRange, StrictCoreManager};
     use ccp_shared::types::{LogicalCoreId, PhysicalCoreId, CUID};
     use hex::FromHex;
+    use std::sync::Arc;
 
     #[test]
     fn test_serde() {
         let init_id_1 =
             <CUID>::from_hex("54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea")
                 .unwrap();
+        let init_id_2 =
+            <CUID>::from_hex("1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0")
+                .unwrap();
         let persistent_state = PersistentCoreManagerState {
             cores_mapping: vec![
                 (PhysicalCoreId::new(1), LogicalCoreId::new(1)),
@@ -134,14 +221,142 @@ mod tests {
             system_cores: vec![PhysicalCoreId::new(1)],
             available_cores: vec![PhysicalCoreId::new(2), PhysicalCoreId::new(3)],
             unit_id_mapping: vec![(PhysicalCoreId::new(4), init_id_1)],
-            work_type_mapping: vec![(init_id_1, WorkType::Deal)],
+            work_type_mapping: vec![
+                (init_id_1, WorkType::Deal),
+                (init_id_2, WorkType::Custom("Proof".to_string())),
+            ],
+            acquire_order: vec![init_id_1],
         };
         let actual = toml::to_string(&persistent_state).unwrap();
         let expected = "cores_mapping = [[1, 1], [1, 2], [2, 3], [2, 4], [3, 5], [3, 6], [4, 7], [4, 8]]\n\
         system_cores = [1]\n\
         available_cores = [2, 3]\n\
         unit_id_mapping = [[4, \"54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea\"]]\n\
-        work_type_mapping = [[\"54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea\", \"Deal\"]]\n";
-        assert_eq!(expected, actual)
+        work_type_mapping = [[\"54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea\", \"Deal\"], [\"1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0\", \"Proof\"]]\n\
+        acquire_order = [\"54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea\"]\n";
+        assert_eq!(expected, actual);
+
+        let deserialized: PersistentCoreManagerState = toml::from_str(&actual).unwrap();
+        // the custom work type must survive the TOML round trip
+        assert_eq!(
+            deserialized.work_type_mapping,
+            persistent_state.work_type_mapping
+        );
+    }
+
+    /// Drives the persist-failure warning path deterministically: an injected
+    /// IO error makes a flush fail and keep the file stale, the next flush
+    /// succeeds once the fault rule is spent
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn test_injected_persist_failure_keeps_file_stale() {
+        if num_cpus::get_physical() >= 4 {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let file_path = temp_dir.path().join("test.toml");
+            let (manager, task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                file_path.clone(),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let manager: Arc<CoreManager> = Arc::new(manager.into());
+
+            let init_id_hex = "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea";
+            let init_id_1 = <CUID>::from_hex(init_id_hex).unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+
+            chaos::install("core-manager::persist", chaos::FaultRule::FailNext(1));
+            task.force_flush(manager.clone()).await;
+            let persisted = std::fs::read_to_string(&file_path).unwrap();
+            assert!(
+                !persisted.contains(init_id_hex),
+                "the failed flush must leave the file stale"
+            );
+
+            // the rule is spent, the next flush writes the state out
+            task.force_flush(manager).await;
+            let persisted = std::fs::read_to_string(&file_path).unwrap();
+            assert!(persisted.contains(init_id_hex));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_drains_pending_events_and_writes_final_state() {
+        if num_cpus::get_physical() >= 4 {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let file_path = temp_dir.path().join("test.toml");
+            let (manager, task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                file_path.clone(),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let manager: Arc<CoreManager> = Arc::new(manager.into());
+            task.run(manager.clone()).await;
+
+            let init_id_hex = "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea";
+            let init_id_1 = <CUID>::from_hex(init_id_hex).unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+            task.stop();
+
+            // the queued change must reach the disk through the stopping task
+            // alone, without a forced flush
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+            loop {
+                let persisted = std::fs::read_to_string(&file_path).unwrap();
+                if persisted.contains(init_id_hex) {
+                    break;
+                }
+                assert!(
+                    std::time::Instant::now() < deadline,
+                    "the final write never happened: {persisted}"
+                );
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_force_flush_persists_current_state() {
+        if num_cpus::get_physical() >= 4 {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let file_path = temp_dir.path().join("test.toml");
+            let (manager, task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                file_path.clone(),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let manager: Arc<CoreManager> = Arc::new(manager.into());
+
+            let init_id_hex = "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea";
+            let init_id_1 = <CUID>::from_hex(init_id_hex).unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+
+            // the background task is not running, so only a forced flush
+            // can bring the file up to date
+            task.force_flush(manager.clone()).await;
+            assert_eq!(task.flush_count(), 1);
+            let persisted = std::fs::read_to_string(&file_path).unwrap();
+            assert!(persisted.contains(init_id_hex));
+
+            manager.release(&[init_id_1]);
+            task.force_flush(manager).await;
+            assert_eq!(task.flush_count(), 2);
+            let persisted = std::fs::read_to_string(&file_path).unwrap();
+            assert!(
+                !persisted.contains(init_id_hex),
+                "the released unit must be gone from the persisted state"
+            );
+        }
     }
 }
@@ -14,9 +14,11 @@
  * limitations under the License.
  */
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::ops::Deref;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use ccp_shared::types::{LogicalCoreId, PhysicalCoreId, CUID};
 use cpu_utils::CPUTopology;
@@ -29,24 +31,34 @@ use crate::manager::CoreManagerFunctions;
 use crate::persistence::{
     PersistenceTask, PersistentCoreManagerFunctions, PersistentCoreManagerState,
 };
-use crate::types::{AcquireRequest, Assignment, Cores, WorkType};
+use crate::types::{
+    pinning_script, AcquireRequest, Assignment, CoreEvent, Cores, WorkType,
+    EVENTS_CHANNEL_CAPACITY,
+};
 use crate::{BiMap, CoreRange, Map, MultiMap};
 
 /// `StrictCoreManager` is a CPU core manager responsible for allocating and releasing CPU cores
 /// based on workload requirements. It maintains the state of core allocations, persists
 /// the state to disk, and provides methods for acquiring and releasing cores.
 pub struct StrictCoreManager {
+    // name of this manager instance; attached to its log lines to tell
+    // instances apart when a process runs more than one manager
+    name: String,
     // path to the persistent state
     file_path: PathBuf,
     // inner state
     state: RwLock<CoreManagerState>,
-    // persistent task notification channel
-    sender: tokio::sync::mpsc::Sender<()>,
+    // mirrors `state.available_cores.len()` so hot-path code (e.g. admission
+    // controllers) can check core availability without taking the lock
+    available_count: Arc<AtomicUsize>,
+    // assignment change events; the persistence task is one of the subscribers
+    events: tokio::sync::broadcast::Sender<CoreEvent>,
 }
 
 impl StrictCoreManager {
     /// Loads the state from `file_name` if exists. If not creates a new empty state
     pub fn from_path(
+        name: String,
         file_path: PathBuf,
         system_cpu_count: usize,
         core_range: CoreRange,
@@ -69,11 +81,11 @@ impl StrictCoreManager {
                 && persistent_state.system_cores.len() == system_cpu_count
             {
                 let state: CoreManagerState = persistent_state.into();
-                Ok(Self::make_instance_with_task(file_path, state))
+                Ok(Self::make_instance_with_task(name, file_path, state))
             } else {
-                tracing::warn!(target: "core-manager", "The initial config has been changed. Ignoring persisted core mapping");
+                tracing::warn!(target: "core-manager", name = %name, "The initial config has been changed. Ignoring persisted core mapping");
                 let (core_manager, task) =
-                    Self::new(file_path.clone(), system_cpu_count, core_range)
+                    Self::new(name, file_path.clone(), system_cpu_count, core_range)
                         .map_err(|err| LoadingError::CreateCoreManager { err })?;
                 core_manager
                     .persist()
@@ -81,9 +93,10 @@ impl StrictCoreManager {
                 Ok((core_manager, task))
             }
         } else {
-            tracing::debug!(target: "core-manager", "No persisted core mapping was not found. Creating a new one.");
-            let (core_manager, task) = Self::new(file_path.clone(), system_cpu_count, core_range)
-                .map_err(|err| LoadingError::CreateCoreManager { err })?;
+            tracing::debug!(target: "core-manager", name = %name, "No persisted core mapping was not found. Creating a new one.");
+            let (core_manager, task) =
+                Self::new(name, file_path.clone(), system_cpu_count, core_range)
+                    .map_err(|err| LoadingError::CreateCoreManager { err })?;
             core_manager
                 .persist()
                 .map_err(|err| LoadingError::PersistError { err })?;
@@ -93,6 +106,7 @@ impl StrictCoreManager {
 
     /// Creates an empty core manager with only system cores assigned
     fn new(
+        name: String,
         file_name: PathBuf,
         system_cpu_count: usize,
         core_range: CoreRange,
@@ -128,10 +142,7 @@ impl StrictCoreManager {
         let mut available_cores: BTreeSet<PhysicalCoreId> = BTreeSet::new();
 
         for physical_core_id in physical_cores {
-            if core_range
-                .0
-                .contains(<PhysicalCoreId as Into<u32>>::into(physical_core_id) as usize)
-            {
+            if core_range.contains_physical(physical_core_id) {
                 let logical_cores = topology
                     .logical_cores_for_physical(physical_core_id)
                     .map_err(|err| CreateError::CollectCoresData { err })?;
@@ -167,30 +178,50 @@ impl StrictCoreManager {
             available_cores,
             unit_id_mapping,
             work_type_mapping: type_mapping,
+            acquire_order: VecDeque::new(),
         };
 
-        let result = Self::make_instance_with_task(file_name, inner_state);
+        let result = Self::make_instance_with_task(name, file_name, inner_state);
 
         Ok(result)
     }
 
     fn make_instance_with_task(
+        name: String,
         file_name: PathBuf,
         state: CoreManagerState,
     ) -> (Self, PersistenceTask) {
-        // This channel is used to notify a persistent task about changes.
-        // It has a size of 1 because we need only the fact that this change happen
-        let (sender, receiver) = tokio::sync::mpsc::channel(1);
+        // This channel notifies subscribers (including the persistence task)
+        // about assignment changes. The capacity is small on purpose: laggards
+        // lose the oldest events and can re-read the full state from the manager
+        let (events, receiver) = tokio::sync::broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+
+        let available_count = Arc::new(AtomicUsize::new(state.available_cores.len()));
 
         (
             Self {
+                name: name.clone(),
                 file_path: file_name,
-                sender,
+                events,
                 state: RwLock::new(state),
+                available_count,
             },
-            PersistenceTask::new(receiver),
+            PersistenceTask::new(name, receiver),
         )
     }
+
+    /// Name of this manager instance, attached to every log line it emits
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of free physical cores, kept in sync with the inner state.
+    /// Reading the atomic doesn't take the state lock, so it is safe to poll
+    /// from hot-path code; the value may be momentarily stale while an
+    /// acquire or release is in flight
+    pub fn available_count_atomic(&self) -> Arc<AtomicUsize> {
+        self.available_count.clone()
+    }
 }
 
 struct CoreManagerState {
@@ -204,6 +235,8 @@ struct CoreManagerState {
     unit_id_mapping: BiMap<PhysicalCoreId, CUID>,
     // mapping between unit id and workload type
     work_type_mapping: Map<CUID, WorkType>,
+    // order in which units were assigned, oldest first
+    acquire_order: VecDeque<CUID>,
 }
 
 impl From<&CoreManagerState> for PersistentCoreManagerState {
@@ -222,6 +255,7 @@ impl From<&CoreManagerState> for PersistentCoreManagerState {
                 .iter()
                 .map(|(k, v)| ((*k), v.clone()))
                 .collect(),
+            acquire_order: value.acquire_order.iter().cloned().collect(),
         }
     }
 }
@@ -234,6 +268,7 @@ impl From<PersistentCoreManagerState> for CoreManagerState {
             available_cores: value.available_cores.into_iter().collect(),
             unit_id_mapping: value.unit_id_mapping.into_iter().collect(),
             work_type_mapping: value.work_type_mapping.into_iter().collect(),
+            acquire_order: value.acquire_order.into_iter().collect(),
         }
     }
 }
@@ -243,6 +278,24 @@ impl CoreManagerFunctions for StrictCoreManager {
         &self,
         assign_request: AcquireRequest,
     ) -> Result<Assignment, AcquireError> {
+        // tie core allocation into distributed traces: the span carries the
+        // request shape, the closing event reports what was actually assigned
+        let span = tracing::info_span!(
+            target: "core-manager",
+            "acquire_worker_core",
+            name = %self.name,
+            unit_count = assign_request.unit_ids.len(),
+            work_type = %assign_request.worker_type,
+        );
+        let _guard = span.enter();
+
+        // a duplicated unit id would be mapped once and silently no-op afterwards,
+        // so reject the request before touching the state
+        let duplicate_unit_ids = assign_request.duplicate_unit_ids();
+        if !duplicate_unit_ids.is_empty() {
+            return Err(AcquireError::DuplicateUnitIds(duplicate_unit_ids));
+        }
+
         let mut lock = self.state.write();
         let mut cuid_cores: Map<CUID, Cores> = HashMap::with_capacity_and_hasher(
             assign_request.unit_ids.len(),
@@ -253,6 +306,7 @@ impl CoreManagerFunctions for StrictCoreManager {
         let mut result_logical_core_ids = BTreeSet::new();
 
         let worker_unit_type = assign_request.worker_type;
+        let mut preferred_cores = assign_request.preferred_cores;
         let available = lock.available_cores.len();
 
         let core_usage = assign_request
@@ -268,36 +322,91 @@ impl CoreManagerFunctions for StrictCoreManager {
 
         let required = core_usage.iter().filter(|(_, core)| core.is_none()).count();
 
+        let mut preempted_cuids: Vec<CUID> = Vec::new();
         if required > available {
-            let current_assignment: Vec<(PhysicalCoreId, CUID)> =
-                lock.unit_id_mapping.iter().map(|(k, v)| (*k, *v)).collect();
-            return Err(AcquireError::NotFoundAvailableCores {
-                required,
-                available,
-                current_assignment: CurrentAssignment::new(current_assignment),
-            });
+            // When preemption is enabled, displace CapacityCommitment units,
+            // least-recently-assigned first. Deal units and system cores are never displaced.
+            // Victims are selected before any state is modified, so the request either
+            // succeeds as a whole or leaves the state untouched.
+            if assign_request.preempt {
+                let requested: BTreeSet<CUID> =
+                    core_usage.iter().map(|(unit_id, _)| *unit_id).collect();
+                preempted_cuids = lock
+                    .acquire_order
+                    .iter()
+                    .filter(|unit_id| {
+                        !requested.contains(unit_id)
+                            && lock.work_type_mapping.get(unit_id)
+                                == Some(&WorkType::CapacityCommitment)
+                    })
+                    .take(required - available)
+                    .cloned()
+                    .collect();
+            }
+
+            if available + preempted_cuids.len() < required {
+                let current_assignment: Vec<(PhysicalCoreId, CUID)> =
+                    lock.unit_id_mapping.iter().map(|(k, v)| (*k, *v)).collect();
+                return Err(AcquireError::NotFoundAvailableCores {
+                    required,
+                    available,
+                    current_assignment: CurrentAssignment::new(current_assignment),
+                });
+            }
+
+            for unit_id in &preempted_cuids {
+                // SAFETY: victims are taken from acquire_order, so they are always mapped to a core
+                let (physical_core_id, _) = lock
+                    .unit_id_mapping
+                    .remove_by_right(unit_id)
+                    .expect("Unexpected state. Should not be empty never");
+                lock.available_cores.insert(physical_core_id);
+                lock.work_type_mapping.remove(unit_id);
+                let unit_id = *unit_id;
+                lock.acquire_order.retain(|id| *id != unit_id);
+            }
         }
 
+        let mut assignments: Vec<(CUID, PhysicalCoreId)> = Vec::with_capacity(core_usage.len());
+
         for (unit_id, physical_core_id) in core_usage {
             let physical_core_id = match physical_core_id {
                 None => {
-                    // SAFETY: this should never happen because we already checked the availability of cores
-                    let core_id = lock
-                        .available_cores
-                        .pop_last()
-                        .expect("Unexpected state. Should not be empty never");
+                    // a preferred core is honored only while it is still free;
+                    // otherwise fall back to the usual placement
+                    let preferred = preferred_cores
+                        .iter()
+                        .position(|core_id| lock.available_cores.contains(core_id));
+                    let core_id = match preferred {
+                        Some(index) => {
+                            let core_id = preferred_cores.remove(index);
+                            lock.available_cores.remove(&core_id);
+                            core_id
+                        }
+                        // SAFETY: this should never happen because we already
+                        // checked the availability of cores
+                        None => lock
+                            .available_cores
+                            .pop_last()
+                            .expect("Unexpected state. Should not be empty never"),
+                    };
                     lock.unit_id_mapping.insert(core_id, unit_id);
                     lock.work_type_mapping
                         .insert(unit_id, worker_unit_type.clone());
+                    lock.acquire_order.push_back(unit_id);
                     core_id
                 }
                 Some(core_id) => {
                     lock.work_type_mapping
                         .insert(unit_id, worker_unit_type.clone());
+                    // reassignment refreshes the unit's position in the preemption order
+                    lock.acquire_order.retain(|id| *id != unit_id);
+                    lock.acquire_order.push_back(unit_id);
                     core_id
                 }
             };
             result_physical_core_ids.insert(physical_core_id);
+            assignments.push((unit_id, physical_core_id));
 
             // SAFETY: The physical core always has corresponding logical ids,
             // unit_id_mapping can't have a wrong physical_core_id
@@ -320,25 +429,54 @@ impl CoreManagerFunctions for StrictCoreManager {
             );
         }
 
-        // We are trying to notify a persistence task that the state has been changed.
-        // We don't care if the channel is full, it means the current state will be stored with the previous event
-        let _ = self.sender.try_send(());
+        self.available_count
+            .store(lock.available_cores.len(), Ordering::Release);
+        drop(lock);
+
+        // Notify subscribers (including the persistence task) about the change.
+        // We don't care if there are no subscribers left to receive the events
+        if !preempted_cuids.is_empty() {
+            let _ = self.events.send(CoreEvent::Released {
+                cuids: preempted_cuids.clone(),
+            });
+        }
+        let _ = self.events.send(CoreEvent::Acquired {
+            assignments,
+            work_type: worker_unit_type,
+        });
+
+        tracing::info!(
+            target: "core-manager",
+            physical_cores = result_physical_core_ids.len(),
+            preempted = preempted_cuids.len(),
+            "cores acquired"
+        );
 
         Ok(Assignment {
             physical_core_ids: result_physical_core_ids,
             logical_core_ids: result_logical_core_ids,
             cuid_cores,
+            preempted_cuids,
         })
     }
 
     fn release(&self, unit_ids: &[CUID]) {
         let mut lock = self.state.write();
+        let mut released: Vec<CUID> = Vec::with_capacity(unit_ids.len());
         for unit_id in unit_ids {
             if let Some((physical_core_id, _)) = lock.unit_id_mapping.remove_by_right(unit_id) {
                 lock.available_cores.insert(physical_core_id);
                 lock.work_type_mapping.remove(unit_id);
+                lock.acquire_order.retain(|id| id != unit_id);
+                released.push(*unit_id);
             }
         }
+        self.available_count
+            .store(lock.available_cores.len(), Ordering::Release);
+        drop(lock);
+        if !released.is_empty() {
+            let _ = self.events.send(CoreEvent::Released { cuids: released });
+        }
     }
 
     fn get_system_cpu_assignment(&self) -> Assignment {
@@ -360,8 +498,43 @@ impl CoreManagerFunctions for StrictCoreManager {
             physical_core_ids: lock.system_cores.clone(),
             logical_core_ids,
             cuid_cores: Map::with_hasher(FxBuildHasher::default()),
+            preempted_cuids: Vec::new(),
         }
     }
+
+    fn export_pinning_script(&self, unit_id: &CUID) -> Option<String> {
+        let lock = self.state.read();
+        let physical_core_id = *lock.unit_id_mapping.get_by_right(unit_id)?;
+        let logical_core_ids = lock.cores_mapping.get_vec(&physical_core_id)?;
+        Some(pinning_script(logical_core_ids))
+    }
+
+    fn work_type_of(&self, unit_id: &CUID) -> Option<WorkType> {
+        let lock = self.state.read();
+        lock.work_type_mapping.get(unit_id).cloned()
+    }
+
+    fn reassign_work_type(&self, unit_id: &CUID, new: WorkType) -> Result<(), AcquireError> {
+        let mut lock = self.state.write();
+        let physical_core_id = match lock.unit_id_mapping.get_by_right(unit_id) {
+            Some(core_id) => *core_id,
+            None => return Err(AcquireError::UnitNotAssigned { unit_id: *unit_id }),
+        };
+        lock.work_type_mapping.insert(*unit_id, new.clone());
+        drop(lock);
+
+        // the cores stay as they were; the event only signals that the state
+        // has changed and has to be persisted
+        let _ = self.events.send(CoreEvent::Acquired {
+            assignments: vec![(*unit_id, physical_core_id)],
+            work_type: new,
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<CoreEvent> {
+        self.events.subscribe()
+    }
 }
 
 impl PersistentCoreManagerFunctions for StrictCoreManager {
@@ -387,8 +560,8 @@ mod tests {
     use crate::manager::CoreManagerFunctions;
     use crate::persistence::PersistentCoreManagerState;
     use crate::strict::StrictCoreManager;
-    use crate::types::{AcquireRequest, WorkType};
-    use crate::CoreRange;
+    use crate::types::{AcquireRequest, CoreEvent, WorkType};
+    use crate::{CoreManager, CoreRange};
 
     fn cores_exists() -> bool {
         num_cpus::get_physical() >= 4
@@ -400,6 +573,7 @@ mod tests {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
 
             let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 2,
                 CoreRange::default(),
@@ -415,22 +589,19 @@ mod tests {
             .unwrap();
             let unit_ids = vec![init_id_1, init_id_2];
             let assignment_1 = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             let assignment_2 = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::Deal,
-                })
+                .acquire_worker_core(AcquireRequest::new(unit_ids.clone(), WorkType::Deal))
                 .unwrap();
             let assignment_3 = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             assert_eq!(assignment_1, assignment_2);
             assert_eq!(
@@ -446,12 +617,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_work_type_of() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+
+            assert_eq!(manager.work_type_of(&init_id_1), Some(WorkType::Deal));
+            // a unit that was never acquired has no work type
+            assert_eq!(manager.work_type_of(&init_id_2), None);
+
+            manager.release(&[init_id_1]);
+            assert_eq!(manager.work_type_of(&init_id_1), None);
+        }
+    }
+
+    #[test]
+    fn test_reassign_work_type() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+            let assignment = manager
+                .acquire_worker_core(AcquireRequest::new(
+                    vec![init_id_1],
+                    WorkType::CapacityCommitment,
+                ))
+                .unwrap();
+            let core_before = *manager
+                .state
+                .read()
+                .unit_id_mapping
+                .get_by_right(&init_id_1)
+                .unwrap();
+
+            manager
+                .reassign_work_type(&init_id_1, WorkType::Deal)
+                .unwrap();
+            assert_eq!(manager.work_type_of(&init_id_1), Some(WorkType::Deal));
+
+            // the unit keeps the core it was assigned before
+            let core_after = *manager
+                .state
+                .read()
+                .unit_id_mapping
+                .get_by_right(&init_id_1)
+                .unwrap();
+            assert_eq!(core_before, core_after);
+            assert!(assignment.physical_core_ids.contains(&core_after));
+
+            // a unit that holds no cores can't be reassigned
+            let result = manager.reassign_work_type(&init_id_2, WorkType::Deal);
+            assert!(
+                matches!(result, Err(AcquireError::UnitNotAssigned { unit_id }) if unit_id == init_id_2),
+                "expected UnitNotAssigned, got {result:?}"
+            );
+        }
+    }
+
     #[test]
     fn test_acquire_and_release() {
         if cores_exists() {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
             let system_cpu_count = 2;
             let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 system_cpu_count,
                 CoreRange::default(),
@@ -481,10 +741,10 @@ mod tests {
             .unwrap();
             let unit_ids = vec![init_id_1, init_id_2];
             let assignment = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             assert_eq!(assignment.physical_core_ids.len(), 2);
             assert_eq!(assignment.cuid_cores.len(), 2);
@@ -518,6 +778,199 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_available_count_atomic_tracks_state() {
+        if cores_exists() {
+            use std::sync::atomic::Ordering;
+
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let system_cpu_count = 2;
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                system_cpu_count,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let available = manager.available_count_atomic();
+
+            let consistent = |manager: &StrictCoreManager| {
+                assert_eq!(
+                    available.load(Ordering::Acquire),
+                    manager.state.read().available_cores.len()
+                );
+            };
+
+            consistent(&manager);
+            assert_eq!(
+                available.load(Ordering::Acquire),
+                num_cpus::get_physical() - system_cpu_count
+            );
+
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(
+                    vec![init_id_1, init_id_2],
+                    WorkType::Deal,
+                ))
+                .unwrap();
+            consistent(&manager);
+
+            manager.release(&[init_id_1]);
+            consistent(&manager);
+
+            // reacquiring a still-assigned unit doesn't consume another core
+            manager
+                .acquire_worker_core(AcquireRequest::new(
+                    vec![init_id_2],
+                    WorkType::CapacityCommitment,
+                ))
+                .unwrap();
+            consistent(&manager);
+
+            manager.release(&[init_id_2]);
+            consistent(&manager);
+            assert_eq!(
+                available.load(Ordering::Acquire),
+                num_cpus::get_physical() - system_cpu_count
+            );
+        }
+    }
+
+    #[test]
+    fn test_available_count_atomic_under_concurrency() {
+        if cores_exists() {
+            use std::sync::atomic::Ordering;
+
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let system_cpu_count = 2;
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                system_cpu_count,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let available = manager.available_count_atomic();
+            let initial = available.load(Ordering::Acquire);
+
+            // two threads repeatedly acquire and release their own unit; with
+            // at least two free cores every acquire succeeds
+            std::thread::scope(|scope| {
+                for unit_id in random_unit_ids(2) {
+                    let manager = &manager;
+                    scope.spawn(move || {
+                        for _ in 0..100 {
+                            manager
+                                .acquire_worker_core(AcquireRequest::new(
+                                    vec![unit_id],
+                                    WorkType::Deal,
+                                ))
+                                .unwrap();
+                            manager.release(&[unit_id]);
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(available.load(Ordering::Acquire), initial);
+            assert_eq!(
+                available.load(Ordering::Acquire),
+                manager.state.read().available_cores.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_events_on_acquire_and_release() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let mut events = manager.subscribe();
+
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+            let unit_ids = vec![init_id_1, init_id_2];
+            let assignment = manager
+                .acquire_worker_core(AcquireRequest::new(unit_ids.clone(), WorkType::Deal))
+                .unwrap();
+            manager.release(&unit_ids);
+
+            let expected_assignments: Vec<_> = unit_ids
+                .iter()
+                .map(|unit_id| (*unit_id, assignment.cuid_cores[unit_id].physical_core_id))
+                .collect();
+            assert_eq!(
+                events.try_recv().unwrap(),
+                CoreEvent::Acquired {
+                    assignments: expected_assignments,
+                    work_type: WorkType::Deal,
+                }
+            );
+            assert_eq!(
+                events.try_recv().unwrap(),
+                CoreEvent::Released { cuids: unit_ids }
+            );
+            assert!(events.try_recv().is_err(), "no more events are expected");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_persistence_happens_via_events() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let file_path = temp_dir.path().join("test.toml");
+            let (manager, task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                file_path.clone(),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let manager: std::sync::Arc<CoreManager> = std::sync::Arc::new(manager.into());
+            task.run(manager.clone()).await;
+
+            let init_id_hex = "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea";
+            let init_id_1 = <CUID>::from_hex(init_id_hex).unwrap();
+            manager
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_1], WorkType::Deal))
+                .unwrap();
+
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+            loop {
+                let persisted = std::fs::read_to_string(&file_path)
+                    .expect("Failed to read the persisted state");
+                if persisted.contains(init_id_hex) {
+                    break;
+                }
+                assert!(
+                    std::time::Instant::now() < deadline,
+                    "the state was not persisted in time"
+                );
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+
     #[test]
     fn test_acquire_error_message() {
         if cores_exists() {
@@ -547,23 +1000,22 @@ mod tests {
                 available_cores: vec![PhysicalCoreId::new(2)],
                 unit_id_mapping: vec![(PhysicalCoreId::new(3), init_id_1)],
                 work_type_mapping: vec![(init_id_1, WorkType::Deal)],
+                acquire_order: vec![init_id_1],
             };
             let (manager, _task) = StrictCoreManager::make_instance_with_task(
+                "test".to_string(),
                 temp_dir.into_path(),
                 persistent_state.into(),
             );
 
             manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: vec![init_id_2],
-                    worker_type: WorkType::Deal,
-                })
+                .acquire_worker_core(AcquireRequest::new(vec![init_id_2], WorkType::Deal))
                 .unwrap();
 
-            let result = manager.acquire_worker_core(AcquireRequest {
-                unit_ids: vec![init_id_3],
-                worker_type: WorkType::Deal,
-            });
+            let result = manager.acquire_worker_core(AcquireRequest::new(
+                vec![init_id_3],
+                WorkType::Deal,
+            ));
 
             let expected = "Couldn't assign core: no free cores left. \
             Required: 1, available: 0, current assignment: [2 -> 1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0, \
@@ -572,6 +1024,42 @@ mod tests {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_export_pinning_script() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let init_id_1 =
+            <CUID>::from_hex("54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea")
+                .unwrap();
+        let init_id_2 =
+            <CUID>::from_hex("1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0")
+                .unwrap();
+        let persistent_state = PersistentCoreManagerState {
+            cores_mapping: vec![
+                (PhysicalCoreId::new(1), LogicalCoreId::new(1)),
+                (PhysicalCoreId::new(1), LogicalCoreId::new(2)),
+                (PhysicalCoreId::new(2), LogicalCoreId::new(3)),
+                (PhysicalCoreId::new(2), LogicalCoreId::new(4)),
+            ],
+            system_cores: vec![PhysicalCoreId::new(1)],
+            available_cores: vec![],
+            unit_id_mapping: vec![(PhysicalCoreId::new(2), init_id_1)],
+            work_type_mapping: vec![(init_id_1, WorkType::Deal)],
+            acquire_order: vec![init_id_1],
+        };
+        let (manager, _task) = StrictCoreManager::make_instance_with_task(
+            "test".to_string(),
+            temp_dir.into_path(),
+            persistent_state.into(),
+        );
+
+        let script = manager.export_pinning_script(&init_id_1);
+        assert_eq!(script.as_deref(), Some("taskset --cpu-list 3,4 <command>"));
+
+        let unassigned = manager.export_pinning_script(&init_id_2);
+        assert_eq!(unassigned, None, "unassigned CUIDs must yield no script");
+    }
+
     #[test]
     fn test_wrong_range() {
         if cores_exists() {
@@ -579,7 +1067,12 @@ mod tests {
 
             let range = CoreRange::from_str("0-16384").unwrap();
 
-            let result = StrictCoreManager::from_path(temp_dir.path().join("test.toml"), 2, range);
+            let result = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                range,
+            );
 
             assert!(result.is_err());
             assert_eq!(
@@ -595,6 +1088,7 @@ mod tests {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
             let system_cpu_count = 1;
             let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 system_cpu_count,
                 CoreRange::default(),
@@ -611,19 +1105,16 @@ mod tests {
                 .collect();
 
             let assignment = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             assert_eq!(assignment.physical_core_ids.len(), unit_ids_count);
             assert_eq!(assignment.cuid_cores.len(), unit_ids_count);
 
             let assignment = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::Deal,
-                })
+                .acquire_worker_core(AcquireRequest::new(unit_ids.clone(), WorkType::Deal))
                 .unwrap();
             assert_eq!(assignment.physical_core_ids.len(), unit_ids_count);
             assert_eq!(assignment.cuid_cores.len(), unit_ids_count);
@@ -636,6 +1127,7 @@ mod tests {
             let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
             let system_cpu_count = 1;
             let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
                 temp_dir.path().join("test.toml"),
                 system_cpu_count,
                 CoreRange::default(),
@@ -652,10 +1144,10 @@ mod tests {
                 .collect();
 
             let assignment = manager
-                .acquire_worker_core(AcquireRequest {
-                    unit_ids: unit_ids.clone(),
-                    worker_type: WorkType::CapacityCommitment,
-                })
+                .acquire_worker_core(AcquireRequest::new(
+                    unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
                 .unwrap();
             assert_eq!(assignment.physical_core_ids.len(), unit_ids_count);
             assert_eq!(assignment.cuid_cores.len(), unit_ids_count);
@@ -667,10 +1159,10 @@ mod tests {
                 })
                 .collect();
 
-            let result = manager.acquire_worker_core(AcquireRequest {
-                unit_ids: unit_ids.clone(),
-                worker_type: WorkType::Deal,
-            });
+            let result = manager.acquire_worker_core(AcquireRequest::new(
+                unit_ids.clone(),
+                WorkType::Deal,
+            ));
 
             assert!(result.is_err());
             if let Err(err) = result {
@@ -683,8 +1175,346 @@ mod tests {
                         assert_eq!(required, unit_ids_count);
                         assert_eq!(available, 0);
                     }
+                    other => panic!("unexpected error: {other}"),
                 }
             }
         }
     }
+
+    #[test]
+    fn test_duplicate_unit_ids_rejected() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+
+            let before_available = manager.state.read().available_cores.clone();
+
+            let result = manager.acquire_worker_core(AcquireRequest::new(
+                vec![init_id_1, init_id_2, init_id_1],
+                WorkType::Deal,
+            ));
+            assert!(
+                matches!(result, Err(AcquireError::DuplicateUnitIds(ref unit_ids)) if unit_ids == &vec![init_id_1]),
+                "expected DuplicateUnitIds, got {result:?}"
+            );
+
+            // the request is rejected before any state is touched
+            let state = manager.state.read();
+            assert_eq!(state.available_cores, before_available);
+            assert_eq!(state.unit_id_mapping.len(), 0);
+            assert_eq!(state.work_type_mapping.len(), 0);
+            assert!(state.acquire_order.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_preferred_core_honored() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                1,
+                CoreRange::from_str("0-3").unwrap(),
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+
+            // core 0 is taken by the system; a free preferred core wins over
+            // the default highest-core-first placement
+            let assignment_1 = manager
+                .acquire_worker_core(
+                    AcquireRequest::builder()
+                        .unit_ids(vec![init_id_1])
+                        .worker_type(WorkType::Deal)
+                        .preferred_cores(vec![PhysicalCoreId::new(1)])
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(
+                assignment_1.physical_core_ids,
+                BTreeSet::from([PhysicalCoreId::new(1)])
+            );
+
+            // an occupied preferred core falls back to the default placement
+            let assignment_2 = manager
+                .acquire_worker_core(
+                    AcquireRequest::builder()
+                        .unit_ids(vec![init_id_2])
+                        .worker_type(WorkType::Deal)
+                        .preferred_cores(vec![PhysicalCoreId::new(1)])
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(
+                assignment_2.physical_core_ids,
+                BTreeSet::from([PhysicalCoreId::new(3)])
+            );
+        }
+    }
+
+    fn random_unit_ids(count: usize) -> Vec<CUID> {
+        (0..count)
+            .map(|_| {
+                let mut rng = rand::thread_rng();
+                let bytes: [u8; 32] = rng.gen();
+                CUID::new(bytes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_deal_preempts_capacity_commitments() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let system_cpu_count = 1;
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                system_cpu_count,
+                CoreRange::default(),
+            )
+            .unwrap();
+
+            // fill all the cores with CapacityCommitment units
+            let cc_unit_ids = random_unit_ids(num_cpus::get_physical() - system_cpu_count);
+            manager
+                .acquire_worker_core(AcquireRequest::new(
+                    cc_unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
+                .unwrap();
+
+            let deal_unit_ids = random_unit_ids(2);
+            let assignment = manager
+                .acquire_worker_core(
+                    AcquireRequest::new(deal_unit_ids.clone(), WorkType::Deal).with_preemption(),
+                )
+                .unwrap();
+
+            // the least recently assigned CC units are displaced
+            assert_eq!(assignment.preempted_cuids, cc_unit_ids[..2].to_vec());
+            assert_eq!(assignment.cuid_cores.len(), 2);
+
+            let state = manager.state.read();
+            for unit_id in &assignment.preempted_cuids {
+                assert!(state.unit_id_mapping.get_by_right(unit_id).is_none());
+                assert!(state.work_type_mapping.get(unit_id).is_none());
+            }
+            for unit_id in &deal_unit_ids {
+                assert_eq!(state.work_type_mapping.get(unit_id), Some(&WorkType::Deal));
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_preemption_without_flag() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let system_cpu_count = 1;
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                system_cpu_count,
+                CoreRange::default(),
+            )
+            .unwrap();
+
+            let cc_unit_ids = random_unit_ids(num_cpus::get_physical() - system_cpu_count);
+            manager
+                .acquire_worker_core(AcquireRequest::new(
+                    cc_unit_ids.clone(),
+                    WorkType::CapacityCommitment,
+                ))
+                .unwrap();
+
+            let result = manager.acquire_worker_core(AcquireRequest::new(
+                random_unit_ids(2),
+                WorkType::Deal,
+            ));
+
+            assert!(result.is_err());
+            if let Err(err) = result {
+                match err {
+                    AcquireError::NotFoundAvailableCores {
+                        required,
+                        available,
+                        ..
+                    } => {
+                        assert_eq!(required, 2);
+                        assert_eq!(available, 0);
+                    }
+                    other => panic!("unexpected error: {other}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_preemption_never_displaces_deals() {
+        if cores_exists() {
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let system_cpu_count = 1;
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                system_cpu_count,
+                CoreRange::default(),
+            )
+            .unwrap();
+
+            // fill all the cores with Deal units
+            let deal_unit_ids = random_unit_ids(num_cpus::get_physical() - system_cpu_count);
+            manager
+                .acquire_worker_core(AcquireRequest::new(deal_unit_ids.clone(), WorkType::Deal))
+                .unwrap();
+
+            let result = manager.acquire_worker_core(
+                AcquireRequest::new(random_unit_ids(1), WorkType::Deal).with_preemption(),
+            );
+
+            assert!(result.is_err());
+
+            // the already assigned deals are left intact
+            let state = manager.state.read();
+            for unit_id in &deal_unit_ids {
+                assert!(state.unit_id_mapping.get_by_right(unit_id).is_some());
+            }
+        }
+    }
+
+    // collects everything the fmt subscriber writes out
+    #[derive(Clone, Default)]
+    struct Capture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Capture {
+        fn logs(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl std::io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            std::io::Write::write(&mut *self.0.lock().unwrap(), buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for Capture {
+        type Writer = Capture;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_log_lines_carry_the_manager_name() {
+        if cores_exists() {
+            let capture = Capture::default();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::DEBUG)
+                .with_ansi(false)
+                .with_writer(capture.clone())
+                .finish();
+
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            tracing::subscriber::with_default(subscriber, || {
+                // creating a manager without a persisted state emits a debug line
+                let (_manager, _task) = StrictCoreManager::from_path(
+                    "ccp".to_string(),
+                    temp_dir.path().join("test.toml"),
+                    2,
+                    CoreRange::default(),
+                )
+                .unwrap();
+            });
+
+            let logs = capture.logs();
+            assert!(
+                logs.contains("name=ccp"),
+                "log lines must carry the manager name, got: {logs}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_acquire_emits_span_with_request_fields() {
+        if cores_exists() {
+            let capture = Capture::default();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::INFO)
+                .with_ansi(false)
+                .with_writer(capture.clone())
+                .finish();
+
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let (manager, _task) = StrictCoreManager::from_path(
+                "test".to_string(),
+                temp_dir.path().join("test.toml"),
+                2,
+                CoreRange::default(),
+            )
+            .unwrap();
+            let init_id_1 = <CUID>::from_hex(
+                "54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea",
+            )
+            .unwrap();
+            let init_id_2 = <CUID>::from_hex(
+                "1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0",
+            )
+            .unwrap();
+
+            tracing::subscriber::with_default(subscriber, || {
+                manager
+                    .acquire_worker_core(AcquireRequest::new(
+                        vec![init_id_1, init_id_2],
+                        WorkType::Deal,
+                    ))
+                    .unwrap();
+            });
+
+            // the closing event is emitted inside the span, so one fmt line
+            // carries both the span fields and the resulting core count
+            let logs = capture.logs();
+            for expected in [
+                "acquire_worker_core",
+                "unit_count=2",
+                "work_type=Deal",
+                "physical_cores=2",
+                "cores acquired",
+            ] {
+                assert!(
+                    logs.contains(expected),
+                    "expected {expected:?} in the acquire trace, got: {logs}"
+                );
+            }
+        }
+    }
 }
@@ -14,33 +14,198 @@
  * limitations under the License.
  */
 
-use crate::Map;
+use crate::core_range::ParseError;
+use crate::errors::BuildError;
+use crate::{CoreRange, Map};
 use ccp_shared::types::CUID;
 use cpu_utils::pinning::pin_current_thread_to_cpuset;
 use cpu_utils::{LogicalCoreId, PhysicalCoreId};
-use serde::{Deserialize, Serialize};
+use range_set_blaze::RangeSetBlaze;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum WorkType {
     CapacityCommitment,
     Deal,
+    /// A user-defined work-type label for protocol extensions and custom
+    /// workloads; serialized as the bare inner string
+    Custom(String),
 }
 
+impl Display for WorkType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkType::CapacityCommitment => write!(f, "CapacityCommitment"),
+            WorkType::Deal => write!(f, "Deal"),
+            WorkType::Custom(label) => write!(f, "{label}"),
+        }
+    }
+}
+
+// Serialized as a plain string (`"Deal"`, `"MyWorkload"`), matching the format
+// the derived implementation used before `Custom` appeared, so old persisted
+// states keep loading
+impl Serialize for WorkType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for WorkType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        Ok(match str.as_str() {
+            "CapacityCommitment" => WorkType::CapacityCommitment,
+            "Deal" => WorkType::Deal,
+            _ => WorkType::Custom(str),
+        })
+    }
+}
+
+#[derive(Debug)]
 pub struct AcquireRequest {
     pub(crate) unit_ids: Vec<CUID>,
     pub(crate) worker_type: WorkType,
+    // when there are no free cores left, displace CapacityCommitment units
+    // instead of failing the request
+    pub(crate) preempt: bool,
+    // scheduling priority hint; not interpreted by the current managers
+    pub(crate) priority: u8,
+    // cores the caller would like the units placed on, best effort
+    pub(crate) preferred_cores: Vec<PhysicalCoreId>,
+}
+
+/// Builds an [AcquireRequest] field by field; see [AcquireRequest::builder].
+/// `unit_ids` and `worker_type` are required, everything else is optional
+#[derive(Default)]
+pub struct AcquireRequestBuilder {
+    unit_ids: Vec<CUID>,
+    worker_type: Option<WorkType>,
+    preempt: bool,
+    priority: u8,
+    preferred_cores: Vec<PhysicalCoreId>,
+}
+
+impl AcquireRequestBuilder {
+    pub fn unit_ids(mut self, unit_ids: Vec<CUID>) -> Self {
+        self.unit_ids = unit_ids;
+        self
+    }
+
+    pub fn worker_type(mut self, worker_type: WorkType) -> Self {
+        self.worker_type = Some(worker_type);
+        self
+    }
+
+    /// Scheduling priority hint carried with the request; higher wins.
+    /// Not interpreted by the current managers, recorded for callers that
+    /// layer their own admission policies on top
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Cores the caller would like the units placed on. Best effort: a
+    /// preferred core is used only if it is free, units beyond the preferred
+    /// set fall back to the manager's own placement
+    pub fn preferred_cores(mut self, preferred_cores: Vec<PhysicalCoreId>) -> Self {
+        self.preferred_cores = preferred_cores;
+        self
+    }
+
+    pub fn build(self) -> Result<AcquireRequest, BuildError> {
+        if self.unit_ids.is_empty() {
+            return Err(BuildError::MissingUnitIds);
+        }
+        let worker_type = self.worker_type.ok_or(BuildError::MissingWorkerType)?;
+        Ok(AcquireRequest {
+            unit_ids: self.unit_ids,
+            worker_type,
+            preempt: self.preempt,
+            priority: self.priority,
+            preferred_cores: self.preferred_cores,
+        })
+    }
 }
 
 impl AcquireRequest {
+    pub fn builder() -> AcquireRequestBuilder {
+        AcquireRequestBuilder::default()
+    }
+
     pub fn new(unit_ids: Vec<CUID>, worker_type: WorkType) -> Self {
-        Self {
-            unit_ids,
-            worker_type,
+        Self::builder()
+            .unit_ids(unit_ids)
+            .worker_type(worker_type.clone())
+            .build()
+            // an empty acquire is a valid no-op for the managers,
+            // so `new` stays infallible for existing call sites
+            .unwrap_or_else(|_| Self {
+                unit_ids: Vec::new(),
+                worker_type,
+                preempt: false,
+                priority: 0,
+                preferred_cores: Vec::new(),
+            })
+    }
+
+    /// The request's scheduling priority hint; see [AcquireRequestBuilder::priority]
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// Allows the manager to free cores held by `WorkType::CapacityCommitment` units
+    /// (least-recently-assigned first) when there are not enough free cores.
+    /// Displaced units are reported in [`Assignment::preempted_cuids`].
+    pub fn with_preemption(mut self) -> Self {
+        self.preempt = true;
+        self
+    }
+
+    /// Unit ids that appear more than once in the request, in the order of
+    /// their first occurrence; each duplicated id is reported once
+    pub(crate) fn duplicate_unit_ids(&self) -> Vec<CUID> {
+        let mut seen: BTreeSet<CUID> = BTreeSet::new();
+        let mut duplicates: Vec<CUID> = Vec::new();
+        for unit_id in &self.unit_ids {
+            if !seen.insert(*unit_id) && !duplicates.contains(unit_id) {
+                duplicates.push(*unit_id);
+            }
         }
+        duplicates
     }
 }
 
+/// A structured notification about an assignment change, delivered to
+/// [`crate::CoreManagerFunctions::subscribe`] subscribers
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum CoreEvent {
+    /// Cores were assigned (or reassigned) to the given compute units;
+    /// each unit is paired with the physical core it now holds, so subscribers
+    /// don't have to query the manager to learn the placement
+    Acquired {
+        assignments: Vec<(CUID, PhysicalCoreId)>,
+        work_type: WorkType,
+    },
+    /// The given compute units no longer hold any cores
+    Released { cuids: Vec<CUID> },
+    /// The set of cores reserved for the node itself has changed
+    SystemCoresChanged,
+}
+
+// How many events a slow subscriber can fall behind before it starts losing
+// the oldest ones
+pub(crate) const EVENTS_CHANNEL_CAPACITY: usize = 16;
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Cores {
     pub physical_core_id: PhysicalCoreId,
@@ -54,10 +219,192 @@ pub struct Assignment {
     // We don't need a cryptographically secure hash and it is better to use a fx hash here
     // to improve performance
     pub cuid_cores: Map<CUID, Cores>,
+    // CapacityCommitment units displaced to satisfy this request;
+    // the caller is responsible for notifying CCP/chain layers about them
+    pub preempted_cuids: Vec<CUID>,
 }
 
 impl Assignment {
     pub fn pin_current_thread(&self) {
         pin_current_thread_to_cpuset(self.logical_core_ids.iter().cloned());
     }
+
+    /// Folds `logical_core_ids` into the compact cpuset notation expected by
+    /// cgroups and systemd `AllowedCPUs`, e.g. "2-5,8".
+    /// Singleton ranges render as a bare core id
+    pub fn to_cpuset_string(&self) -> String {
+        let set: RangeSetBlaze<u32> = self
+            .logical_core_ids
+            .iter()
+            .map(|core_id| <LogicalCoreId as Into<u32>>::into(*core_id))
+            .collect();
+        set.ranges()
+            .map(|range| {
+                let start = range.start();
+                let end = range.end();
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{start}-{end}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Parses the cpuset notation produced by [`Assignment::to_cpuset_string`]
+    /// back into a set of logical core ids
+    pub fn logical_cores_from_cpuset(cpuset: &str) -> Result<BTreeSet<LogicalCoreId>, ParseError> {
+        // the notation is the same as the one of the config core range, so reuse its parser
+        let range = CoreRange::from_str(cpuset)?;
+        Ok(range
+            .0
+            .iter()
+            .map(|core_id| LogicalCoreId::from(core_id as u32))
+            .collect())
+    }
+}
+
+/// Formats a shell command that pins `<command>` to the given logical cores,
+/// for workloads running outside this process (e.g. external proof workers).
+pub(crate) fn pinning_script(logical_core_ids: &[LogicalCoreId]) -> String {
+    let core_list = logical_core_ids
+        .iter()
+        .map(|core_id| <LogicalCoreId as Into<u32>>::into(*core_id).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    format_pinning_script(&core_list)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn format_pinning_script(core_list: &str) -> String {
+    format!("taskset --cpu-list {core_list} <command>")
+}
+
+/// macOS has no native affinity tool, so the command relies on
+/// hwloc's `hwloc-bind` being installed
+#[cfg(target_os = "macos")]
+fn format_pinning_script(core_list: &str) -> String {
+    format!("hwloc-bind --cpubind pu:{core_list} <command>")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::BuildError;
+    use crate::types::{AcquireRequest, Assignment, WorkType};
+    use crate::Map;
+    use ccp_shared::types::CUID;
+    use cpu_utils::{LogicalCoreId, PhysicalCoreId};
+    use fxhash::FxBuildHasher;
+    use hex::FromHex;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeSet;
+
+    fn assignment_with_logical_cores(core_ids: &[u32]) -> Assignment {
+        Assignment {
+            physical_core_ids: BTreeSet::new(),
+            logical_core_ids: core_ids.iter().map(|id| LogicalCoreId::new(*id)).collect(),
+            cuid_cores: Map::with_hasher(FxBuildHasher::default()),
+            preempted_cuids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_cpuset_string_contiguous() {
+        let assignment = assignment_with_logical_cores(&[2, 3, 4, 5]);
+        assert_eq!(assignment.to_cpuset_string(), "2-5");
+    }
+
+    #[test]
+    fn test_cpuset_string_disjoint() {
+        let assignment = assignment_with_logical_cores(&[2, 3, 4, 5, 8]);
+        assert_eq!(assignment.to_cpuset_string(), "2-5,8");
+    }
+
+    #[test]
+    fn test_cpuset_string_single_core() {
+        let assignment = assignment_with_logical_cores(&[7]);
+        assert_eq!(assignment.to_cpuset_string(), "7");
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+    struct WorkTypeWrapper {
+        work_type: WorkType,
+    }
+
+    #[test]
+    fn test_work_type_toml_roundtrip() {
+        for (work_type, expected) in [
+            (WorkType::Deal, "work_type = \"Deal\"\n"),
+            (
+                WorkType::CapacityCommitment,
+                "work_type = \"CapacityCommitment\"\n",
+            ),
+            (
+                WorkType::Custom("Proof".to_string()),
+                "work_type = \"Proof\"\n",
+            ),
+        ] {
+            let wrapper = WorkTypeWrapper { work_type };
+            let serialized = toml::to_string(&wrapper).unwrap();
+            assert_eq!(serialized, expected);
+            let deserialized: WorkTypeWrapper = toml::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, wrapper);
+        }
+    }
+
+    #[test]
+    fn test_cpuset_roundtrip() {
+        let assignment = assignment_with_logical_cores(&[0, 2, 3, 4, 9]);
+        let parsed =
+            Assignment::logical_cores_from_cpuset(&assignment.to_cpuset_string()).unwrap();
+        assert_eq!(parsed, assignment.logical_core_ids);
+    }
+
+    fn unit_id() -> CUID {
+        <CUID>::from_hex("54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_builder_missing_unit_ids() {
+        let result = AcquireRequest::builder().worker_type(WorkType::Deal).build();
+        assert!(
+            matches!(result, Err(BuildError::MissingUnitIds)),
+            "expected MissingUnitIds, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn test_builder_missing_worker_type() {
+        let result = AcquireRequest::builder().unit_ids(vec![unit_id()]).build();
+        assert!(
+            matches!(result, Err(BuildError::MissingWorkerType)),
+            "expected MissingWorkerType, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn test_builder_optional_fields() {
+        let request = AcquireRequest::builder()
+            .unit_ids(vec![unit_id()])
+            .worker_type(WorkType::Deal)
+            .priority(3)
+            .preferred_cores(vec![PhysicalCoreId::new(2)])
+            .build()
+            .unwrap();
+        assert_eq!(request.unit_ids, vec![unit_id()]);
+        assert_eq!(request.worker_type, WorkType::Deal);
+        assert!(!request.preempt);
+        assert_eq!(request.priority(), 3);
+        assert_eq!(request.preferred_cores, vec![PhysicalCoreId::new(2)]);
+    }
+
+    #[test]
+    fn test_builder_defaults_match_new() {
+        let request = AcquireRequest::new(vec![unit_id()], WorkType::Deal);
+        assert!(!request.preempt);
+        assert_eq!(request.priority(), 0);
+        assert!(request.preferred_cores.is_empty());
+    }
 }
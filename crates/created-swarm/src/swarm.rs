@@ -39,7 +39,7 @@ use fluence_libp2p::random_multiaddr::{create_memory_maddr, create_tcp_maddr};
 use fluence_libp2p::Transport;
 use fs_utils::to_abs_path;
 use futures::stream::iter;
-use nox::{Connectivity, Node};
+use nox::{Connectivity, Node, ParticleTimelineStore};
 use particle_protocol::ProtocolConfig;
 use rand::RngCore;
 use server_config::{
@@ -83,6 +83,56 @@ pub struct CreatedSwarm {
     pub aquamarine_api: AquamarineApi,
     http_listen_addr: SocketAddr,
     pub network_key: NetworkKey,
+    // input config the swarm was created from, used by `restart`
+    #[derivative(Debug = "ignore")]
+    pub swarm_config: SwarmConfig,
+}
+
+impl CreatedSwarm {
+    /// Gracefully shuts the node down and starts a new one from the same data directory,
+    /// preserving all on-disk state (keys, services, modules, spells).
+    /// `cfg_modifier` allows to adjust the configuration the node is restarted with.
+    pub async fn restart(
+        self,
+        cfg_modifier: impl FnOnce(SwarmConfig) -> SwarmConfig,
+    ) -> CreatedSwarm {
+        // ask the node to stop and wait until all its tasks are drained
+        self.exit_outlet.send(()).ok();
+        self.cancellation_token.cancelled().await;
+
+        let config = cfg_modifier(self.swarm_config);
+        let (peer_id, node, management_keypair, input_config, resolved_config, span) =
+            create_swarm(config).await;
+        let connectivity = node.connectivity.clone();
+        let aquamarine_api = node.aquamarine_api.clone();
+        let started_node = node
+            .start(peer_id)
+            .instrument(span)
+            .await
+            .expect("node restart");
+        let http_listen_addr = started_node
+            .http_listen_addr
+            .expect("could not take http listen addr");
+
+        let swarm = CreatedSwarm {
+            config: resolved_config,
+            peer_id,
+            multiaddr: input_config.listen_on.clone(),
+            tmp_dir: input_config.tmp_dir.clone(),
+            management_keypair,
+            exit_outlet: started_node.exit_outlet,
+            cancellation_token: started_node.cancellation_token,
+            connectivity,
+            aquamarine_api,
+            http_listen_addr,
+            network_key: input_config.network_key.clone(),
+            swarm_config: input_config,
+        };
+
+        wait_connected_on_addrs(vec![swarm.http_listen_addr]).await;
+
+        swarm
+    }
 }
 
 pub async fn make_swarms(n: usize) -> Vec<CreatedSwarm> {
@@ -210,7 +260,7 @@ where
                 CreatedSwarm {
                     config: resolved_config,
                     peer_id,
-                    multiaddr: input_config.listen_on,
+                    multiaddr: input_config.listen_on.clone(),
                     tmp_dir: input_config.tmp_dir.clone(),
                     management_keypair,
                     exit_outlet: started_node.exit_outlet,
@@ -219,6 +269,7 @@ where
                     aquamarine_api,
                     http_listen_addr,
                     network_key: input_config.network_key.clone(),
+                    swarm_config: input_config,
                 }
             }
             .boxed_local()
@@ -311,6 +362,12 @@ pub struct SwarmConfig {
     pub chain_config: Option<ChainConfig>,
     pub cc_events_dir: Option<PathBuf>,
     pub network_key: NetworkKey,
+    /// When set, the node records per-particle event timelines into this store
+    /// and serves them through the `("debug", "particle_timeline")` builtin.
+    /// The store is shared: to actually fill it, tests must also register
+    /// a `ParticleTimelineLayer` on the global tracing subscriber
+    #[derivative(Debug = "ignore")]
+    pub particle_timeline: Option<ParticleTimelineStore>,
 }
 
 impl SwarmConfig {
@@ -342,6 +399,7 @@ impl SwarmConfig {
             chain_config: None,
             cc_events_dir: None,
             network_key,
+            particle_timeline: None,
         }
     }
 }
@@ -494,6 +552,8 @@ pub async fn create_swarm_with_runtime<RT: AquaRuntime>(
             "some version",
             "some version",
             system_service_distros,
+            config.particle_timeline.clone(),
+            vec![],
         );
         (node, config.management_keypair.clone(), resolved)
     });
@@ -673,7 +673,7 @@ mod tests {
     use tokio::sync::oneshot;
 
     use fluence_libp2p::random_multiaddr::create_memory_maddr;
-    use fluence_libp2p::{build_memory_transport, RandomPeerId};
+    use fluence_libp2p::{build_memory_transport, RandomPeerId, TransportConfig};
     use log_utils::enable_logs;
 
     use crate::{KademliaConfig, KademliaError};
@@ -707,12 +707,15 @@ mod tests {
         let _guard = span.enter();
         let config = kad_config(peer_id, network_id);
         let (kad, _) = Kademlia::new(config, None, span.clone());
-        let timeout = Duration::from_secs(20);
+        let transport_config = TransportConfig {
+            socket_timeout: Duration::from_secs(20),
+            ..<_>::default()
+        };
 
         let kp: Keypair = kp.into();
         let mut swarm = SwarmBuilder::with_existing_identity(kp.clone())
             .with_tokio()
-            .with_other_transport(|_| build_memory_transport(&kp, timeout))
+            .with_other_transport(|_| build_memory_transport(&kp, &transport_config))
             .unwrap()
             .with_behaviour(|_| kad)
             .unwrap()
@@ -0,0 +1,157 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::io::IoSlice;
+use futures::{ready, AsyncRead, AsyncWrite};
+
+/// Node-wide transport-level traffic counters shared by all connections.
+/// Cloning yields a handle to the same counters
+#[derive(Clone, Debug, Default)]
+pub struct BandwidthCounters {
+    inbound_bytes: Arc<AtomicU64>,
+    outbound_bytes: Arc<AtomicU64>,
+}
+
+impl BandwidthCounters {
+    /// Total bytes received over the transport, encryption and framing included
+    pub fn inbound_bytes(&self) -> u64 {
+        self.inbound_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Total bytes sent over the transport, encryption and framing included
+    pub fn outbound_bytes(&self) -> u64 {
+        self.outbound_bytes.load(Ordering::Relaxed)
+    }
+
+    fn add_inbound(&self, bytes: u64) {
+        self.inbound_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn add_outbound(&self, bytes: u64) {
+        self.outbound_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Counts the bytes going through the wrapped stream into [`BandwidthCounters`]
+pub(crate) struct MeteredStream<S> {
+    inner: S,
+    counters: BandwidthCounters,
+}
+
+impl<S> MeteredStream<S> {
+    pub(crate) fn new(inner: S, counters: BandwidthCounters) -> Self {
+        Self { inner, counters }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for MeteredStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let read = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.counters.add_inbound(read as u64);
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for MeteredStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.counters.add_outbound(written as u64);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = ready!(Pin::new(&mut this.inner).poll_write_vectored(cx, bufs))?;
+        this.counters.add_outbound(written as u64);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use libp2p::core::multiaddr::multiaddr;
+    use libp2p::core::transport::{ListenerId, TransportEvent};
+    use libp2p::identity::Keypair;
+    use libp2p::Transport as NetworkTransport;
+    use rand::{thread_rng, Rng};
+
+    use super::BandwidthCounters;
+    use crate::{build_memory_transport, TransportConfig};
+
+    #[tokio::test]
+    async fn counters_advance_over_a_memory_transport() {
+        let counters = BandwidthCounters::default();
+        let config = TransportConfig {
+            bandwidth: Some(counters.clone()),
+            ..<_>::default()
+        };
+
+        let mut listener = build_memory_transport(&Keypair::generate_ed25519(), &config);
+        let mut dialer = build_memory_transport(&Keypair::generate_ed25519(), &config);
+
+        let mem_addr = multiaddr![Memory(thread_rng().gen::<u64>())];
+        listener.listen_on(ListenerId::next(), mem_addr).unwrap();
+        let listen_addr = match listener.select_next_some().await {
+            TransportEvent::NewAddress { listen_addr, .. } => listen_addr,
+            e => panic!("expected NewAddress, got {e:?}"),
+        };
+
+        let inbound = async {
+            let (upgrade, _) = listener.select_next_some().await.into_incoming().unwrap();
+            upgrade.await.unwrap()
+        };
+        let outbound = dialer.dial(listen_addr).unwrap();
+        let (_listener_conn, dialer_conn) = futures::join!(inbound, outbound);
+        dialer_conn.unwrap();
+
+        // the secure channel handshake alone moves bytes both ways,
+        // and both ends share the same counters
+        assert!(counters.inbound_bytes() > 0, "inbound bytes must be counted");
+        assert!(
+            counters.outbound_bytes() > 0,
+            "outbound bytes must be counted"
+        );
+    }
+}
@@ -26,7 +26,10 @@
     unreachable_patterns
 )]
 
+#[cfg(feature = "tokio")]
+mod bandwidth;
 mod connected_point;
+mod log_limiter;
 mod macros;
 pub mod random_multiaddr;
 mod random_peer_id;
@@ -35,10 +38,15 @@ mod serde;
 mod transport;
 
 pub use self::serde::*;
+#[cfg(feature = "tokio")]
+pub use bandwidth::BandwidthCounters;
 pub use connected_point::*;
+pub use log_limiter::{LogRateLimit, LogRateLimiter};
 pub use random_peer_id::RandomPeerId;
 #[cfg(feature = "tokio")]
-pub use transport::{build_memory_transport, build_transport, Transport};
+pub use transport::{
+    build_memory_transport, build_transport, CompressionConfig, Transport, TransportConfig,
+};
 
 // libp2p reexports
 pub use libp2p::PeerId;
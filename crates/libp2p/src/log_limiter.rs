@@ -0,0 +1,225 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long repeats of one message are suppressed before a summary is logged
+pub const DEFAULT_SUMMARY_WINDOW: Duration = Duration::from_secs(60);
+
+/// At most this many (site, key) pairs are tracked; beyond the cap messages
+/// for untracked keys are logged in full, so the limiter never grows unbounded
+const MAX_TRACKED_KEYS: usize = 1024;
+
+/// What the caller should do with a message, decided by [LogRateLimiter::observe]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRateLimit {
+    /// First occurrence in a while: log the full message
+    Emit,
+    /// The message kept repeating for a whole window: log it once with
+    /// a "repeated N times" note
+    Summary { repeats: u64, window: Duration },
+    /// A recent repeat: don't log
+    Suppress,
+}
+
+/// Deduplicates repeating log messages, keyed by a (site, peer/address) pair.
+/// The first occurrence is logged immediately; repeats within a window are
+/// suppressed and surface as a single summary once the window passes. Meant
+/// for warn paths a flapping peer can hit thousands of times per minute
+pub struct LogRateLimiter {
+    window: Duration,
+    entries: Mutex<HashMap<(&'static str, String), RepeatState>>,
+}
+
+struct RepeatState {
+    window_start: Instant,
+    /// Occurrences suppressed since `window_start`
+    repeats: u64,
+}
+
+impl Default for LogRateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_SUMMARY_WINDOW)
+    }
+}
+
+impl LogRateLimiter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records an occurrence of the message identified by (site, key) and
+    /// says whether to log it in full, as a summary, or not at all
+    pub fn observe(&self, site: &'static str, key: &str) -> LogRateLimit {
+        self.observe_at(site, key, Instant::now())
+    }
+
+    fn observe_at(&self, site: &'static str, key: &str, now: Instant) -> LogRateLimit {
+        let mut entries = self.entries.lock().expect("log limiter lock poisoned");
+
+        let key = (site, key.to_string());
+        if entries.len() >= MAX_TRACKED_KEYS && !entries.contains_key(&key) {
+            entries.retain(|_, state| now.duration_since(state.window_start) < self.window);
+            if entries.len() >= MAX_TRACKED_KEYS {
+                // every tracked key is active; degrade to plain logging
+                // rather than growing the map
+                return LogRateLimit::Emit;
+            }
+        }
+
+        match entries.entry(key) {
+            Entry::Occupied(mut occupied) => {
+                let state = occupied.get_mut();
+                if now.duration_since(state.window_start) >= self.window {
+                    let repeats = std::mem::take(&mut state.repeats);
+                    state.window_start = now;
+                    if repeats == 0 {
+                        // the message went quiet for a whole window:
+                        // log it in full again
+                        LogRateLimit::Emit
+                    } else {
+                        LogRateLimit::Summary {
+                            repeats,
+                            window: self.window,
+                        }
+                    }
+                } else {
+                    state.repeats += 1;
+                    LogRateLimit::Suppress
+                }
+            }
+            Entry::Vacant(vacant) => {
+                vacant.insert(RepeatState {
+                    window_start: now,
+                    repeats: 0,
+                });
+                LogRateLimit::Emit
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{LogRateLimit, LogRateLimiter};
+
+    #[test]
+    fn repeats_are_suppressed_and_summarized() {
+        let window = Duration::from_secs(60);
+        let limiter = LogRateLimiter::new(window);
+        let start = Instant::now();
+
+        // a tight loop of dial failures for one address: only the first
+        // occurrence is logged in full
+        assert_eq!(
+            limiter.observe_at("dial_failure", "/ip4/1.2.3.4/tcp/7777", start),
+            LogRateLimit::Emit
+        );
+        for i in 1..=100 {
+            assert_eq!(
+                limiter.observe_at(
+                    "dial_failure",
+                    "/ip4/1.2.3.4/tcp/7777",
+                    start + Duration::from_millis(i)
+                ),
+                LogRateLimit::Suppress
+            );
+        }
+
+        // a failure for a different address still logs immediately
+        assert_eq!(
+            limiter.observe_at("dial_failure", "/ip4/5.6.7.8/tcp/7777", start),
+            LogRateLimit::Emit
+        );
+        // and so does the same address at a different site
+        assert_eq!(
+            limiter.observe_at("connect_failure", "/ip4/1.2.3.4/tcp/7777", start),
+            LogRateLimit::Emit
+        );
+
+        // once the window passes, the repeats collapse into one summary
+        assert_eq!(
+            limiter.observe_at("dial_failure", "/ip4/1.2.3.4/tcp/7777", start + window),
+            LogRateLimit::Summary { repeats: 100, window }
+        );
+        // which starts a new window of suppression
+        assert_eq!(
+            limiter.observe_at(
+                "dial_failure",
+                "/ip4/1.2.3.4/tcp/7777",
+                start + window + Duration::from_millis(1)
+            ),
+            LogRateLimit::Suppress
+        );
+    }
+
+    #[test]
+    fn quiet_message_is_logged_in_full_again() {
+        let window = Duration::from_secs(60);
+        let limiter = LogRateLimiter::new(window);
+        let start = Instant::now();
+
+        assert_eq!(
+            limiter.observe_at("dial_failure", "peer", start),
+            LogRateLimit::Emit
+        );
+        // no repeats for a whole window: the next occurrence is not a summary
+        assert_eq!(
+            limiter.observe_at("dial_failure", "peer", start + window * 2),
+            LogRateLimit::Emit
+        );
+    }
+
+    #[test]
+    fn tracked_keys_are_bounded() {
+        let window = Duration::from_secs(60);
+        let limiter = LogRateLimiter::new(window);
+        let start = Instant::now();
+
+        for i in 0..super::MAX_TRACKED_KEYS {
+            limiter.observe_at("dial_failure", &format!("peer-{i}"), start);
+        }
+        // the map is full of active keys: new ones are not tracked, only logged
+        assert_eq!(
+            limiter.observe_at("dial_failure", "one-too-many", start),
+            LogRateLimit::Emit
+        );
+        assert_eq!(
+            limiter.observe_at("dial_failure", "one-too-many", start),
+            LogRateLimit::Emit
+        );
+        assert_eq!(
+            limiter.entries.lock().unwrap().len(),
+            super::MAX_TRACKED_KEYS
+        );
+
+        // expired keys are evicted to make room
+        let later = start + window * 2;
+        assert_eq!(
+            limiter.observe_at("dial_failure", "one-too-many", later),
+            LogRateLimit::Emit
+        );
+        assert_eq!(limiter.entries.lock().unwrap().len(), 1);
+    }
+}
@@ -26,14 +26,58 @@ use libp2p::tcp::{tokio::Tcp as TokioTcp, Config as GenTcpConfig};
 use libp2p::{core, identity::Keypair, PeerId, Transport as NetworkTransport};
 use serde::{Deserialize, Serialize};
 
+use crate::bandwidth::{BandwidthCounters, MeteredStream};
+
+/// Compression applied to transport connections. No algorithm is wired in yet:
+/// the type reserves a place in [`TransportConfig`] so that enabling
+/// compression later doesn't change the transport building API
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CompressionConfig {}
+
+/// All the knobs of transport construction in one place, so that adding
+/// a knob doesn't ripple through the `build_*_transport` signatures.
+/// `Default` replicates the values transports were previously built with
+#[derive(Clone, Debug)]
+pub struct TransportConfig {
+    /// Timeout of transport-level operations on a connection
+    pub socket_timeout: Duration,
+    /// Socket send buffer size; OS default when unset. Not applied yet
+    pub send_buf_size: Option<usize>,
+    /// Socket receive buffer size; OS default when unset. Not applied yet
+    pub recv_buf_size: Option<usize>,
+    /// Compression of transport connections; none is implemented yet
+    pub compression: Option<CompressionConfig>,
+    /// Maximum number of multiplexed substreams per connection
+    pub max_num_streams: usize,
+    /// Prefer IPv6 addresses when a DNS name resolves to both. Not applied yet
+    pub prefer_ipv6: bool,
+    /// Count transport-level traffic into these shared counters;
+    /// connections aren't wrapped at all when unset
+    pub bandwidth: Option<BandwidthCounters>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            socket_timeout: Duration::from_secs(20),
+            send_buf_size: None,
+            recv_buf_size: None,
+            compression: None,
+            max_num_streams: 1024 * 1024,
+            prefer_ipv6: false,
+            bandwidth: None,
+        }
+    }
+}
+
 pub fn build_transport(
     transport: Transport,
     key_pair: &Keypair,
-    timeout: Duration,
+    config: &TransportConfig,
 ) -> Boxed<(PeerId, StreamMuxerBox)> {
     match transport {
-        Transport::Network => build_network_transport(key_pair, timeout),
-        Transport::Memory => build_memory_transport(key_pair, timeout),
+        Transport::Network => build_network_transport(key_pair, config),
+        Transport::Memory => build_memory_transport(key_pair, config),
     }
 }
 
@@ -43,7 +87,7 @@ pub fn build_transport(
 /// the multiplexing layer.
 pub fn build_network_transport(
     key_pair: &Keypair,
-    socket_timeout: Duration,
+    config: &TransportConfig,
 ) -> Boxed<(PeerId, StreamMuxerBox)> {
     let tcp = || {
         let tcp = TcpTransport::<TokioTcp>::new(GenTcpConfig::default().nodelay(true));
@@ -57,13 +101,36 @@ pub fn build_network_transport(
         websocket.or_transport(tcp())
     };
 
-    configure_transport(transport, key_pair, socket_timeout)
+    configure_transport(transport, key_pair, config)
 }
 
 pub fn configure_transport<T, C>(
     transport: T,
     key_pair: &Keypair,
-    transport_timeout: Duration,
+    config: &TransportConfig,
+) -> Boxed<(PeerId, StreamMuxerBox)>
+where
+    T: NetworkTransport<Output = C> + Send + Sync + Unpin + 'static,
+    C: AsyncRead + AsyncWrite + Unpin + Send + Unpin + 'static,
+    T::Dial: Send + Unpin + 'static,
+    T::ListenerUpgrade: Send + Unpin + 'static,
+    T::Error: Send + Unpin + Sync + 'static,
+{
+    match &config.bandwidth {
+        Some(counters) => {
+            let counters = counters.clone();
+            let transport =
+                transport.map(move |output, _| MeteredStream::new(output, counters.clone()));
+            upgrade_transport(transport, key_pair, config)
+        }
+        None => upgrade_transport(transport, key_pair, config),
+    }
+}
+
+fn upgrade_transport<T, C>(
+    transport: T,
+    key_pair: &Keypair,
+    config: &TransportConfig,
 ) -> Boxed<(PeerId, StreamMuxerBox)>
 where
     T: NetworkTransport<Output = C> + Send + Sync + Unpin + 'static,
@@ -74,10 +141,10 @@ where
 {
     let multiplex = {
         let mut mplex = libp2p_mplex::MplexConfig::default();
-        mplex.set_max_num_streams(1024 * 1024);
+        mplex.set_max_num_streams(config.max_num_streams);
 
         let mut yamux = libp2p::yamux::Config::default();
-        yamux.set_max_num_streams(1024 * 1024);
+        yamux.set_max_num_streams(config.max_num_streams);
 
         core::upgrade::SelectUpgrade::new(yamux, mplex)
     };
@@ -88,17 +155,17 @@ where
         .upgrade(core::upgrade::Version::V1)
         .authenticate(auth_config)
         .multiplex(multiplex)
-        .timeout(transport_timeout)
+        .timeout(config.socket_timeout)
         .boxed()
 }
 
 pub fn build_memory_transport(
     key_pair: &Keypair,
-    transport_timeout: Duration,
+    config: &TransportConfig,
 ) -> Boxed<(PeerId, StreamMuxerBox)> {
     let transport = MemoryTransport::default();
 
-    configure_transport(transport, key_pair, transport_timeout)
+    configure_transport(transport, key_pair, config)
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Copy)]
@@ -269,6 +269,8 @@ pub async fn make_particle(
         script: script.clone(),
         signature: vec![],
         data: vec![],
+        hop_count: 0,
+        path_trace: vec![],
     };
     // We can sign at this point since the `data` which is evaluated below isn't part of the signature
     particle.sign(key_pair).expect("sign particle");
@@ -668,6 +668,54 @@ async fn timestamp_sec() {
     let _: u64 = serde_json::from_value(result).unwrap();
 }
 
+#[tokio::test]
+async fn monotonic_interval_and_timestamps() {
+    let swarms = make_swarms(1).await;
+
+    let mut client = ConnectedClient::connect_to(swarms[0].multiaddr.clone())
+        .await
+        .wrap_err("connect client")
+        .unwrap();
+
+    client
+        .send_particle(
+            r#"
+        (seq
+            (seq
+                (call relay ("op" "monotonic_ms") [] start)
+                (seq
+                    (call relay ("op" "interval_ms") [start] interval)
+                    (call relay ("op" "timestamps") [] timestamps)
+                )
+            )
+            (call client ("op" "return") [start interval timestamps])
+        )
+        "#,
+            hashmap! {
+                "relay" => json!(client.node.to_string()),
+                "client" => json!(client.peer_id.to_string()),
+            },
+        )
+        .await;
+
+    let mut result = client
+        .receive_args()
+        .await
+        .wrap_err("receive args")
+        .unwrap();
+    let timestamps = result.pop().unwrap();
+    let interval: u64 = serde_json::from_value(result.pop().unwrap()).unwrap();
+    let start: u64 = serde_json::from_value(result.pop().unwrap()).unwrap();
+
+    let monotonic = timestamps["monotonic_ms"].as_u64().unwrap();
+    assert!(
+        monotonic >= start + interval,
+        "monotonic readings must not decrease: {start} + {interval} > {monotonic}"
+    );
+    assert!(timestamps["timestamp_ms"].as_u64().is_some());
+    assert!(timestamps["uptime_ms"].as_u64().is_some());
+}
+
 #[tokio::test]
 async fn base58_string_builtins() {
     let script = r#"
@@ -817,6 +865,67 @@ async fn neighborhood() {
     assert!(error.contains("Invalid multihash"));
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn network_graph_heavy() {
+    let swarms = make_swarms(3).await;
+    let mut client = ConnectedClient::connect_to(swarms[0].multiaddr.clone())
+        .await
+        .wrap_err("connect client")
+        .unwrap();
+
+    let response = client
+        .execute_particle(
+            r#"
+            (seq
+                (call node ("net" "network_graph") [] graph)
+                (call client ("return" "") [graph] void)
+            )
+        "#,
+            hashmap! {
+                "node" => json!(client.node.to_string()),
+                "client" => json!(client.peer_id.to_string())
+            },
+        )
+        .await
+        .unwrap();
+
+    if let JValue::Object(graph) = response[0].clone() {
+        // both other swarms must show up as neighbors with their addresses
+        for swarm in &swarms[1..] {
+            let addresses = graph
+                .get(&swarm.peer_id.to_base58())
+                .unwrap_or_else(|| panic!("peer {} must be in the graph", swarm.peer_id));
+            let addresses = addresses.as_array().expect("addresses is an array");
+            assert!(
+                !addresses.is_empty(),
+                "neighbor {} must have at least one address",
+                swarm.peer_id
+            );
+        }
+    } else {
+        panic!("response[0] must be an object, response was {:#?}", response);
+    }
+
+    // a depth beyond the supported maximum must be rejected
+    let result = client
+        .execute_particle(
+            r#"
+            (xor
+                (call node ("net" "network_graph") [3] graph)
+                (call client ("return" "") [%last_error%.$.message] void)
+            )
+        "#,
+            hashmap! {
+                "node" => json!(client.node.to_string()),
+                "client" => json!(client.peer_id.to_string())
+            },
+        )
+        .await
+        .unwrap();
+    let error = result[0].as_str().expect("error is string");
+    assert!(error.contains("invalid depth"));
+}
+
 #[tokio::test]
 async fn kad_merge() {
     let target = RandomPeerId::random();
@@ -970,13 +1079,47 @@ async fn array_length() {
     .await
     .unwrap();
 
-    assert_eq!(result, vec![
-        json!(0),
-        json!(5),
-        json!("Local service error, ret_code is 1, error message is '\"op array_length accepts exactly 1 argument: 0 found\"'"),
-        json!("Local service error, ret_code is 1, error message is '\"op array_length accepts exactly 1 argument: 2 found\"'"),
-        json!("Local service error, ret_code is 1, error message is '\"op array_length's argument must be an array\"'"),
-    ])
+    assert_eq!(result[0], json!(0));
+    assert_eq!(result[1], json!(5));
+    // errors carry a stable code envelope next to the message text
+    let expected = [
+        "op array_length accepts exactly 1 argument: 0 found",
+        "op array_length accepts exactly 1 argument: 2 found",
+        "op array_length's argument must be an array",
+    ];
+    for (error, expected) in result[2..].iter().zip(expected) {
+        let error = error.as_str().expect("error is string");
+        assert!(
+            error.contains(r#""code":"op.invalid_args""#),
+            "expected op.invalid_args code in:\n{error}"
+        );
+        assert!(
+            error.contains(expected),
+            "expected `{expected}` in:\n{error}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn list_builtins() {
+    let result = exec_script(
+        r#"(call relay ("op" "list_builtins") [] builtins)"#,
+        <_>::default(),
+        "builtins",
+        1,
+    )
+    .await
+    .unwrap();
+
+    let builtins = result[0].as_array().expect("builtins is an array");
+    for (namespace, name) in [("json", "parse"), ("op", "noop")] {
+        assert!(
+            builtins
+                .iter()
+                .any(|b| b["namespace"] == namespace && b["name"] == name),
+            "expected ({namespace}, {name}) in the catalog"
+        );
+    }
 }
 
 #[tokio::test]
@@ -1063,7 +1206,7 @@ async fn array_slice_wrong_second_arg() {
         r#"(call relay ("array" "slice") [ data bad_idx eidx ] result)"#,
         hashmap! {
             "data"      => json!(vec![1,2,3,4]),
-            "bad_idx"   => json!(-1),
+            "bad_idx"   => json!("zero"),
             "eidx"      => json!(2),
         },
         "result",
@@ -1073,7 +1216,7 @@ async fn array_slice_wrong_second_arg() {
     assert!(result.is_err());
     assert!(
         format!("{result:?}")
-            .contains("second argument (start index) must be an unsigned integer, was -1"),
+            .contains("second argument (start index) must be an integer, was"),
         "{}",
         "{result:?}"
     );
@@ -1085,7 +1228,7 @@ async fn array_slice_wrong_third_arg() {
         r#"(call relay ("array" "slice") [ data sidx bad_idx] result)"#,
         hashmap! {
             "data"      => json!(vec![1,2,3,4]),
-            "bad_idx"   => json!(-1),
+            "bad_idx"   => json!("two"),
             "sidx"      => json!(2),
         },
         "result",
@@ -1095,12 +1238,31 @@ async fn array_slice_wrong_third_arg() {
     assert!(result.is_err());
     assert!(
         format!("{result:?}")
-            .contains("third argument (end index) must be an unsigned integer, was -1"),
+            .contains("third argument (end index) must be an integer, was"),
         "{}",
         "{result:?}"
     );
 }
 
+#[tokio::test]
+async fn array_slice_negative_indexes() {
+    let result = exec_script(
+        r#"(call relay ("array" "slice") [ data sidx eidx ] result)"#,
+        hashmap! {
+            "data"      => json!(vec![1,2,3,4]),
+            "sidx"      => json!(-3),
+            "eidx"      => json!(-1),
+        },
+        "result",
+        1,
+    )
+    .await
+    .unwrap();
+
+    let expected = vec![json!(vec![2, 3])];
+    assert_eq!(result, expected);
+}
+
 #[tokio::test]
 async fn array_slice() {
     let result = exec_script(
@@ -1232,6 +1394,42 @@ async fn debug_stringify() {
     assert_eq!(result[0].as_str().unwrap().to_string(), r#"["a","b"]"#);
 }
 
+#[tokio::test]
+async fn debug_particle() {
+    let swarms = make_swarms(1).await;
+    let mut client = ConnectedClient::connect_to(swarms[0].multiaddr.clone())
+        .await
+        .wrap_err("connect client")
+        .unwrap();
+
+    let particle_id = client
+        .send_particle(
+            r#"
+        (seq
+            (call relay ("debug" "particle") [] result)
+            (call %init_peer_id% ("op" "return") [result])
+        )
+        "#,
+            hashmap! {
+                "relay" => json!(client.node.to_string()),
+            },
+        )
+        .await;
+
+    let mut result = client
+        .wait_particle_args(particle_id.clone())
+        .await
+        .unwrap();
+    let meta = result[0].take();
+    assert_eq!(meta["id"].as_str().unwrap(), particle_id);
+    assert_eq!(
+        meta["init_peer_id"].as_str().unwrap(),
+        client.peer_id.to_base58()
+    );
+    assert!(meta["timestamp"].as_u64().is_some());
+    assert!(meta["ttl"].as_u64().is_some());
+}
+
 #[tokio::test]
 // checks that type errors are caught by XOR
 async fn xor_type_error() {
@@ -1498,6 +1696,40 @@ async fn array_dedup() {
     };
 }
 
+#[tokio::test]
+async fn array_builtins_chain() {
+    let result = exec_script(
+        r#"
+        (seq
+            (seq
+                (call relay ("array" "concat") [ xs ys ] concatenated)
+                (call relay ("array" "flatten") [ concatenated ] flattened)
+            )
+            (seq
+                (call relay ("array" "dedup") [ flattened ] deduped)
+                (seq
+                    (call relay ("array" "sort") [ deduped ] sorted)
+                    (call relay ("array" "slice") [ sorted sidx eidx ] result)
+                )
+            )
+        )
+    "#,
+        hashmap! {
+            "xs"        => json!([[3, 1], [2]]),
+            "ys"        => json!([[3, 2]]),
+            "sidx"      => json!(0),
+            "eidx"      => json!(-1),
+        },
+        "result",
+        1,
+    )
+    .await
+    .unwrap();
+
+    let expected = vec![json!(vec![1, 2])];
+    assert_eq!(result, expected);
+}
+
 #[tokio::test]
 async fn array_intersect() {
     match binary(
@@ -92,6 +92,8 @@ async fn call_custom_service() {
         script,
         signature: vec![],
         data: vec![],
+        hop_count: 0,
+        path_trace: vec![],
     };
 
     let exec_f = swarms[1]
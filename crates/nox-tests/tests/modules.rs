@@ -122,8 +122,16 @@ async fn test_add_module_effectors_forbidden() {
 
     let response = client.execute_particle(script, data).await.unwrap();
     if let Some(result) = response[0].as_str() {
-        let expected = "Local service error, ret_code is 1, error message is '\"Error: Config error: requested module effector tetraplets with CID bafkreiepzclggkt57vu7yrhxylfhaafmuogtqly7wel7ozl5k2ehkd44oe is forbidden on this host\\nForbiddenEffector { module_name: \\\"tetraplets\\\", forbidden_cid: \\\"bafkreiepzclggkt57vu7yrhxylfhaafmuogtqly7wel7ozl5k2ehkd44oe\\\" }\"'";
-        assert_eq!(expected, result);
+        // match on the stable machine-readable code, not the message text
+        assert!(
+            result.contains(r#""code":"modules.forbidden_effector""#),
+            "expected modules.forbidden_effector code, got {result:?}"
+        );
+        // the legacy human-readable text is still carried in the message
+        assert!(
+            result.contains("is forbidden on this host"),
+            "expected legacy message text, got {result:?}"
+        );
     } else {
         panic!("can't receive response from node");
     }
@@ -166,12 +174,49 @@ async fn test_add_module_by_other_forbidden() {
         "module_config" => config,
     };
     let response = client.execute_particle(script, data).await.unwrap();
+    // match on the stable machine-readable code, not the message text
     assert!(
         response[0]
             .as_str()
             .unwrap()
-            .contains("function is only available to the host or worker spells"),
+            .contains(r#""code":"auth.permission_denied""#),
         "got {:?}",
         response[0]
     );
 }
+
+#[tokio::test]
+async fn test_lint_module_config_suggests_key() {
+    let swarms = make_swarms(1).await;
+
+    let mut client = ConnectedClient::connect_to(swarms[0].multiaddr.clone())
+        .await
+        .expect("connect client");
+
+    let config = json!({
+        "name": "effector",
+        "mem_pages_cout": 100,
+    });
+
+    let script = r#"
+    (seq
+        (call node ("dist" "lint_module_config") [config] lints)
+        (call client ("return" "") [lints])
+    )
+   "#;
+
+    let data = hashmap! {
+        "client" => json!(client.peer_id.to_string()),
+        "node" => json!(client.node.to_string()),
+        "config" => config,
+    };
+
+    let response = client.execute_particle(script, data).await.unwrap();
+    let lints = response[0].as_array().expect("lint list");
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0]["level"], "error");
+    assert_eq!(
+        lints[0]["message"],
+        "unknown config key 'mem_pages_cout'; did you mean 'mem_pages_count'?"
+    );
+}
@@ -0,0 +1,144 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use eyre::WrapErr;
+use maplit::hashmap;
+use serde_json::json;
+use serde_json::Value as JValue;
+use tracing_subscriber::layer::SubscriberExt;
+
+use connected_client::ConnectedClient;
+use created_swarm::make_swarms_with_cfg;
+use nox::{ParticleTimelineLayer, ParticleTimelineStore};
+
+const RETENTION: Duration = Duration::from_secs(3);
+
+async fn query_timeline(client: &mut ConnectedClient, particle_id: &str) -> Vec<JValue> {
+    client
+        .send_particle(
+            r#"
+        (seq
+            (call relay ("debug" "particle_timeline") [particle_id] timeline)
+            (call %init_peer_id% ("op" "return") [timeline])
+        )"#,
+            hashmap! {
+                "relay" => json!(client.node.to_string()),
+                "particle_id" => json!(particle_id),
+            },
+        )
+        .await;
+
+    let mut args = client.receive_args().await.expect("receive timeline");
+    match args.remove(0) {
+        JValue::Array(records) => records,
+        other => panic!("expected an array of timeline records, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn particle_timeline_is_recorded_and_expires() {
+    // particle timelines are filled from tracing events, so the layer has to be
+    // registered on the subscriber of the process running the nodes; the store
+    // is shared by both swarms, which only makes their timelines richer
+    let store = ParticleTimelineStore::new(RETENTION, 100);
+    let subscriber =
+        tracing_subscriber::registry().with(ParticleTimelineLayer::new(store.clone()));
+    tracing::subscriber::set_global_default(subscriber).expect("set global subscriber");
+
+    let swarm_store = store.clone();
+    let swarms = make_swarms_with_cfg(2, move |mut cfg| {
+        cfg.particle_timeline = Some(swarm_store.clone());
+        cfg
+    })
+    .await;
+
+    let mut client = ConnectedClient::connect_with_keypair(
+        swarms[0].multiaddr.clone(),
+        Some(swarms[0].management_keypair.clone()),
+    )
+    .await
+    .wrap_err("connect client")
+    .unwrap();
+
+    // run a particle across both nodes of the swarm
+    let particle_id = client
+        .send_particle(
+            r#"
+        (seq
+            (seq
+                (call relay ("op" "noop") [])
+                (call second ("op" "noop") [])
+            )
+            (seq
+                (call relay ("op" "noop") [])
+                (call %init_peer_id% ("op" "return") ["done"])
+            )
+        )"#,
+            hashmap! {
+                "relay" => json!(client.node.to_string()),
+                "second" => json!(swarms[1].peer_id.to_string()),
+            },
+        )
+        .await;
+    client.receive_args().await.expect("particle is executed");
+
+    // the timeline is available on both nodes while it is retained
+    let records = query_timeline(&mut client, &particle_id).await;
+    assert!(!records.is_empty(), "timeline must not be empty");
+    let timestamps: Vec<u64> = records
+        .iter()
+        .map(|record| record["timestamp_ms"].as_u64().expect("timestamp_ms"))
+        .collect();
+    let mut sorted = timestamps.clone();
+    sorted.sort_unstable();
+    assert_eq!(timestamps, sorted, "records must be ordered in time");
+
+    let messages: Vec<&str> = records
+        .iter()
+        .map(|record| record["message"].as_str().expect("message"))
+        .collect();
+    assert!(
+        messages.iter().any(|message| message.contains("received particle")),
+        "expected a 'received particle' milestone in {messages:?}"
+    );
+    assert!(
+        messages.iter().any(|message| message.contains("Sent particle")),
+        "expected a 'Sent particle' milestone in {messages:?}"
+    );
+
+    let mut second_client = ConnectedClient::connect_with_keypair(
+        swarms[1].multiaddr.clone(),
+        Some(swarms[1].management_keypair.clone()),
+    )
+    .await
+    .wrap_err("connect second client")
+    .unwrap();
+    let records = query_timeline(&mut second_client, &particle_id).await;
+    assert!(
+        !records.is_empty(),
+        "timeline must be available on the second node"
+    );
+
+    // after the retention window the timeline is gone
+    tokio::time::sleep(RETENTION + Duration::from_secs(1)).await;
+    let records = query_timeline(&mut client, &particle_id).await;
+    assert!(
+        records.is_empty(),
+        "timeline must expire after the retention window, got {records:?}"
+    );
+}
@@ -21,6 +21,7 @@ use maplit::hashmap;
 use serde_json::Value as JValue;
 use serde_json::{json, Value};
 use std::assert_matches::assert_matches;
+use std::convert::identity;
 
 use base64::{engine::general_purpose::STANDARD as base64, Engine};
 use connected_client::ConnectedClient;
@@ -28,6 +29,7 @@ use created_swarm::{make_swarms, make_swarms_with_cfg};
 use jsonrpsee::core::async_trait;
 use service_modules::{load_module, Hash};
 use system_services::{CallService, Deployment, InitService, PackageDistro, ServiceDistro};
+use test_utils::create_service;
 
 #[tokio::test]
 async fn test_system_service_override() {
@@ -540,3 +542,54 @@ async fn test_create_service_by_other_forbidden() {
         response[0]
     );
 }
+
+#[tokio::test]
+async fn test_service_callable_after_restart() {
+    let swarms = make_swarms(1).await;
+    let swarm = swarms.into_iter().next().expect("single swarm");
+
+    let mut client = ConnectedClient::connect_with_keypair(
+        swarm.multiaddr.clone(),
+        Some(swarm.management_keypair.clone()),
+    )
+    .await
+    .wrap_err("connect client")
+    .unwrap();
+
+    let tetraplets_service = create_service(
+        &mut client,
+        "tetraplets",
+        load_module("tests/tetraplets/artifacts", "tetraplets").expect("load module"),
+    )
+    .await;
+    drop(client);
+
+    let swarm = swarm.restart(identity).await;
+
+    let mut client = ConnectedClient::connect_with_keypair(
+        swarm.multiaddr.clone(),
+        Some(swarm.management_keypair.clone()),
+    )
+    .await
+    .wrap_err("connect client after restart")
+    .unwrap();
+
+    client
+        .send_particle(
+            r#"
+        (seq
+            (call relay (service "not") [arg] result)
+            (call %init_peer_id% ("op" "return") [result])
+        )
+        "#,
+            hashmap! {
+                "relay" => json!(client.node.to_string()),
+                "service" => json!(tetraplets_service.id),
+                "arg" => json!(false),
+            },
+        )
+        .await;
+
+    let result = client.receive_args().await.expect("service call succeeds");
+    assert_eq!(result[0], json!(true));
+}
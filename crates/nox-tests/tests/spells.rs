@@ -571,8 +571,8 @@ async fn spell_install_fail_large_period() {
         .unwrap();
 
     if let [JValue::String(error_msg)] = result.as_slice() {
-        let msg = "Local service error, ret_code is 1, error message is '\"Error: invalid config: period is too big.";
-        assert!(error_msg.starts_with(msg));
+        let msg = "invalid config: period is too big.";
+        assert!(error_msg.contains(msg), "expected `{msg}` in:\n{error_msg}");
     }
 }
 
@@ -620,9 +620,9 @@ async fn spell_install_fail_end_sec_past() {
         .await
         .unwrap();
     if let [JValue::String(error_msg)] = result.as_slice() {
-        let expected = "Local service error, ret_code is 1, error message is '\"Error: invalid config: end_sec is less than start_sec or in the past";
+        let expected = "invalid config: end_sec is less than start_sec or in the past";
         assert!(
-            error_msg.starts_with(expected),
+            error_msg.contains(expected),
             "expected:\n{expected}\ngot:\n{error_msg}"
         );
     }
@@ -677,9 +677,9 @@ async fn spell_install_fail_end_sec_before_start() {
         .unwrap();
 
     if let [JValue::String(error_msg)] = result.as_slice() {
-        let expected = "Local service error, ret_code is 1, error message is '\"Error: invalid config: end_sec is less than start_sec or in the past";
+        let expected = "invalid config: end_sec is less than start_sec or in the past";
         assert!(
-            error_msg.starts_with(expected),
+            error_msg.contains(expected),
             "expected:\n{expected}\ngot:\n{error_msg}"
         );
     }
@@ -1491,7 +1491,12 @@ async fn resolve_alias_wrong_worker() {
         .unwrap()
         .as_slice()
     {
-        assert!(error.starts_with("Local service error, ret_code is 1, error message is '\"Error: Service with alias 'alias' is not found on worker"));
+        // the error envelope carries a stable code next to the message text
+        assert!(
+            error.contains(r#""code":"srv.not_found""#),
+            "expected srv.not_found code in:\n{error}"
+        );
+        assert!(error.contains("Service with alias 'alias' is not found on worker"));
     }
 }
 
@@ -19,9 +19,46 @@ use json_utils::err_as_value;
 use eyre::Report;
 use serde_json::{json, Value as JValue};
 use std::borrow::Cow;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use thiserror::Error;
 
+/// Stable machine-readable codes carried in the `code` field of [`JError`]
+/// envelopes, namespaced by builtin area (`modules.*`, `srv.*`, `json.*`, ...).
+/// Clients should match on these instead of the human-readable `message`,
+/// which may change between releases. Codes are part of the protocol:
+/// never change or reuse one, only add new ones
+pub mod error_codes {
+    /// Module upload rejected: the module is an effector not allowed on this host
+    pub const MODULES_FORBIDDEN_EFFECTOR: &str = "modules.forbidden_effector";
+    /// An effector module requested a binary missing from the configured list
+    pub const MODULES_INVALID_EFFECTOR_BINARY: &str = "modules.invalid_effector_mounted_binary";
+    /// Module bytes download denied for the requesting peer
+    pub const MODULES_DOWNLOAD_FORBIDDEN: &str = "modules.download_forbidden";
+    /// Any other module store failure
+    pub const MODULES_ERROR: &str = "modules.error";
+    /// Malformed module config passed to a `dist` builtin
+    pub const DIST_INVALID_CONFIG: &str = "dist.invalid_config";
+    /// Malformed or unknown blueprint passed to a `dist` builtin
+    pub const DIST_INVALID_BLUEPRINT: &str = "dist.invalid_blueprint";
+    /// Service, alias or worker not found
+    pub const SRV_NOT_FOUND: &str = "srv.not_found";
+    /// Any other service failure
+    pub const SRV_ERROR: &str = "srv.error";
+    /// A `json` builtin did not find a requested key or placeholder
+    pub const JSON_KEY_MISSING: &str = "json.key_missing";
+    /// Malformed arguments passed to a `json` builtin
+    pub const JSON_INVALID_ARGS: &str = "json.invalid_args";
+    /// Malformed arguments passed to an `op` builtin
+    pub const OP_INVALID_ARGS: &str = "op.invalid_args";
+    /// The init peer exhausted its builtin execution budget for the current
+    /// usage window; retry after the window rolls over
+    pub const OP_BUDGET_EXCEEDED: &str = "op.budget_exceeded";
+    /// The caller is not allowed to call this function
+    pub const AUTH_PERMISSION_DENIED: &str = "auth.permission_denied";
+    /// Fallback for errors not migrated to a specific code yet
+    pub const UNCLASSIFIED: &str = "error.unclassified";
+}
+
 #[derive(Debug, Error)]
 pub enum ArgsError {
     #[error("Field '{0}' is missing from args to call_service")]
@@ -65,11 +102,37 @@ impl Display for JError {
 
 impl JError {
     pub fn new(msg: impl AsRef<str>) -> Self {
-        Self(json!(msg.as_ref()))
+        Self::coded(error_codes::UNCLASSIFIED, msg)
+    }
+
+    /// Builds the structured envelope all builtin failures share: a stable
+    /// `code` from [`error_codes`] plus the human-readable `message`
+    pub fn coded(code: &'static str, msg: impl AsRef<str>) -> Self {
+        Self(json!({ "code": code, "message": msg.as_ref() }))
+    }
+
+    /// Envelope for a Rust error with an explicit code. The message keeps
+    /// the legacy `Error: {display}\n{debug}` text clients matched on
+    /// before codes existed
+    pub fn coded_error(code: &'static str, err: impl Debug + Display) -> Self {
+        Self(json!({ "code": code, "message": err_as_value(err) }))
+    }
+
+    /// Attaches a machine-readable details object to the envelope
+    pub fn with_details(mut self, details: JValue) -> Self {
+        if let JValue::Object(envelope) = &mut self.0 {
+            envelope.insert("details".to_string(), details);
+        }
+        self
+    }
+
+    /// The stable code of this error, when it carries the envelope
+    pub fn code(&self) -> Option<&str> {
+        self.0.get("code")?.as_str()
     }
 
     pub fn from_eyre(err: Report) -> Self {
-        JError(err_as_value(err))
+        Self::coded_error(error_codes::UNCLASSIFIED, err)
     }
 }
 
@@ -81,7 +144,9 @@ impl From<JError> for JValue {
 
 impl<E: std::error::Error> From<E> for JError {
     fn from(err: E) -> Self {
-        JError(err_as_value(err))
+        // the blanket conversion can't know a specific code; errors that have
+        // one are converted explicitly (e.g. `ModuleError::into_jerror`)
+        Self::coded_error(error_codes::UNCLASSIFIED, err)
     }
 }
 
@@ -30,7 +30,7 @@ mod args_error;
 mod base58;
 
 pub use args::Args;
-pub use args_error::{ArgsError, JError};
+pub use args_error::{error_codes, ArgsError, JError};
 
 pub use avm_server::AVMError;
 pub use base58::from_base58;
@@ -50,6 +50,8 @@ pub struct ChainListenerMetrics {
     // How many block we manage to process while processing the block
     blocks_processed: Counter,
     last_process_block: Gauge,
+    // How many reorgs replaced blocks the listener had already acted on
+    deep_reorgs: Counter,
 }
 
 impl ChainListenerMetrics {
@@ -132,6 +134,13 @@ impl ChainListenerMetrics {
             "Last processed block from the newHead subscription",
         );
 
+        let deep_reorgs = register(
+            sub_registry,
+            Counter::default(),
+            "deep_reorgs",
+            "Total number of chain reorgs that replaced blocks already past the confirmation depth",
+        );
+
         Self {
             ccp_requests_total,
             ccp_replies_total,
@@ -144,6 +153,7 @@ impl ChainListenerMetrics {
             last_seen_block,
             blocks_processed,
             last_process_block,
+            deep_reorgs,
         }
     }
 
@@ -182,4 +192,8 @@ impl ChainListenerMetrics {
         self.blocks_processed.inc();
         self.last_process_block.set(block_number as i64);
     }
+
+    pub fn observe_deep_reorg(&self) {
+        self.deep_reorgs.inc();
+    }
 }
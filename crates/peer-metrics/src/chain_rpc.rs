@@ -0,0 +1,126 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use crate::{execution_time_buckets, register};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::Histogram;
+use prometheus_client::registry::Registry;
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct EndpointLabel {
+    pub endpoint: String,
+}
+
+/// Per-endpoint accounting of chain RPC requests, keyed by the endpoint URL
+#[derive(Clone)]
+pub struct ChainRpcMetrics {
+    // how many requests were sent to the endpoint, successful or not
+    requests: Family<EndpointLabel, Counter>,
+    // how many requests failed on the transport level (no proper RPC reply)
+    request_errors: Family<EndpointLabel, Counter>,
+    // how long requests to the endpoint take
+    request_duration_sec: Family<EndpointLabel, Histogram>,
+    // 1 for the endpoint requests are currently routed to, 0 for the rest
+    endpoint_active: Family<EndpointLabel, Gauge>,
+    // how many times requests were switched to another endpoint
+    failovers: Counter,
+}
+
+impl ChainRpcMetrics {
+    pub fn new(registry: &mut Registry) -> Self {
+        let sub_registry = registry.sub_registry_with_prefix("chain_rpc");
+
+        let requests = register(
+            sub_registry,
+            Family::default(),
+            "requests",
+            "Total number of requests sent to a chain RPC endpoint",
+        );
+
+        let request_errors = register(
+            sub_registry,
+            Family::default(),
+            "request_errors",
+            "Total number of chain RPC requests that failed on the transport level",
+        );
+
+        let request_duration_sec: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(execution_time_buckets()));
+        let request_duration_sec = register(
+            sub_registry,
+            request_duration_sec,
+            "request_duration_sec",
+            "Duration of chain RPC requests",
+        );
+
+        let endpoint_active = register(
+            sub_registry,
+            Family::default(),
+            "endpoint_active",
+            "Whether requests are currently routed to the chain RPC endpoint",
+        );
+
+        let failovers = register(
+            sub_registry,
+            Counter::default(),
+            "failovers",
+            "Total number of switches to another chain RPC endpoint",
+        );
+
+        Self {
+            requests,
+            request_errors,
+            request_duration_sec,
+            endpoint_active,
+            failovers,
+        }
+    }
+
+    fn label(endpoint: &str) -> EndpointLabel {
+        EndpointLabel {
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    pub fn observe_request(&self, endpoint: &str, duration: Duration) {
+        let label = Self::label(endpoint);
+        self.requests.get_or_create(&label).inc();
+        self.request_duration_sec
+            .get_or_create(&label)
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn observe_request_error(&self, endpoint: &str) {
+        let label = Self::label(endpoint);
+        self.requests.get_or_create(&label).inc();
+        self.request_errors.get_or_create(&label).inc();
+    }
+
+    pub fn set_active_endpoint(&self, endpoint: &str, active: bool) {
+        self.endpoint_active
+            .get_or_create(&Self::label(endpoint))
+            .set(active as i64);
+    }
+
+    pub fn observe_failover(&self) {
+        self.failovers.inc();
+    }
+}
@@ -14,19 +14,59 @@
  * limitations under the License.
  */
 
-use crate::{ParticleLabel, ParticleType};
+use std::time::Duration;
+
+use crate::{execution_time_buckets, ParticleLabel, ParticleType};
+use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
 use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::family::Family;
 use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
 use prometheus_client::registry::Registry;
 
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct CommandLabel {
+    pub kind: String,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct LinkLabel {
+    pub remote_peer: String,
+}
+
+/// Whether a rejected particle was received from the peer or about to be forwarded to it
+#[derive(Copy, Clone, Debug, EncodeLabelValue, Hash, Eq, PartialEq)]
+pub enum ParticleDirection {
+    Inbound,
+    Outbound,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct VersionMismatchLabel {
+    direction: ParticleDirection,
+}
+
 #[derive(Clone)]
 pub struct ConnectionPoolMetrics {
     pub received_particles: Family<ParticleLabel, Counter>,
     pub particle_sizes: Family<ParticleLabel, Histogram>,
+    pub sent_particles: Family<ParticleLabel, Counter>,
+    pub message_sizes_sent: Family<ParticleLabel, Histogram>,
     pub connected_peers: Gauge,
     pub particle_queue_size: Gauge,
+    pub particles_dropped_outlet_closed: Counter,
+    pub command_queue_delay: Family<CommandLabel, Histogram>,
+    pub slow_links: Family<LinkLabel, Gauge>,
+    pub queue_wait_sec: Histogram,
+    pub dial_latency_sec: Histogram,
+    pub version_mismatches: Family<VersionMismatchLabel, Counter>,
+    pub client_sessions_idle_closed: Counter,
+    pub client_sessions_evicted: Counter,
+    pub spilled_particles_dropped: Counter,
+    pub spilled_particles_expired: Counter,
+    pub discovered_addresses_per_peer: Histogram,
+    pub hop_limit_exceeded: Counter,
+    pub upgrade_retries: Counter,
 }
 
 impl ConnectionPoolMetrics {
@@ -49,6 +89,22 @@ impl ConnectionPoolMetrics {
             particle_sizes.clone(),
         );
 
+        let sent_particles = Family::default();
+        sub_registry.register(
+            "sent_particles",
+            "Number of particles sent to the network (not unique)",
+            sent_particles.clone(),
+        );
+
+        // from 100 bytes to 100 MB
+        let message_sizes_sent: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(100.0, 10.0, 7)));
+        sub_registry.register(
+            "message_sizes_sent",
+            "Distribution of outbound particle data sizes",
+            message_sizes_sent.clone(),
+        );
+
         let connected_peers = Gauge::default();
         sub_registry.register(
             "connected_peers",
@@ -63,14 +119,137 @@ impl ConnectionPoolMetrics {
             particle_queue_size.clone(),
         );
 
+        let particles_dropped_outlet_closed = Counter::default();
+        sub_registry.register(
+            "particles_dropped_outlet_closed",
+            "Number of particles dropped because the particle outlet was closed",
+            particles_dropped_outlet_closed.clone(),
+        );
+
+        let command_queue_delay: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(execution_time_buckets()));
+        sub_registry.register(
+            "command_queue_delay",
+            "Time between enqueueing a command to the connection pool and its execution",
+            command_queue_delay.clone(),
+        );
+
+        let slow_links = Family::default();
+        sub_registry.register(
+            "slow_links",
+            "p95 of particle send time in milliseconds, per link whose p95 exceeds the slow link threshold",
+            slow_links.clone(),
+        );
+
+        let queue_wait_sec = Histogram::new(execution_time_buckets());
+        sub_registry.register(
+            "queue_wait_sec",
+            "Time particles spend in the connection pool queue before being forwarded to execution",
+            queue_wait_sec.clone(),
+        );
+
+        let dial_latency_sec = Histogram::new(execution_time_buckets());
+        sub_registry.register(
+            "dial_latency_sec",
+            "Time from dial initiation to connection established",
+            dial_latency_sec.clone(),
+        );
+
+        let version_mismatches = Family::default();
+        sub_registry.register(
+            "version_mismatches",
+            "Number of particles refused because the remote peer advertised an AIR interpreter version outside the supported range",
+            version_mismatches.clone(),
+        );
+
+        let client_sessions_idle_closed = Counter::default();
+        sub_registry.register(
+            "client_sessions_idle_closed",
+            "Number of client connections closed because they had no particle activity for the configured idle timeout",
+            client_sessions_idle_closed.clone(),
+        );
+
+        let client_sessions_evicted = Counter::default();
+        sub_registry.register(
+            "client_sessions_evicted",
+            "Number of client connections evicted to make room for a new client when the session cap was reached",
+            client_sessions_evicted.clone(),
+        );
+
+        let spilled_particles_dropped = Counter::default();
+        sub_registry.register(
+            "spilled_particles_dropped",
+            "Number of local particles not persisted on shutdown because the spill file size cap was reached",
+            spilled_particles_dropped.clone(),
+        );
+
+        let spilled_particles_expired = Counter::default();
+        sub_registry.register(
+            "spilled_particles_expired",
+            "Number of spilled local particles dropped because their TTL ran out",
+            spilled_particles_expired.clone(),
+        );
+
+        // from 1 to 32 addresses
+        let discovered_addresses_per_peer = Histogram::new(exponential_buckets(1.0, 2.0, 6));
+        sub_registry.register(
+            "discovered_addresses_per_peer",
+            "Distribution of the number of Identify-discovered addresses retained per peer",
+            discovered_addresses_per_peer.clone(),
+        );
+
+        let hop_limit_exceeded = Counter::default();
+        sub_registry.register(
+            "hop_limit_exceeded",
+            "Number of particles dropped because they were forwarded more times than the hop limit allows",
+            hop_limit_exceeded.clone(),
+        );
+
+        let upgrade_retries = Counter::default();
+        sub_registry.register(
+            "upgrade_retries",
+            "Number of times a particle send was retried after a failed substream upgrade",
+            upgrade_retries.clone(),
+        );
+
         Self {
             received_particles,
             particle_sizes,
+            sent_particles,
+            message_sizes_sent,
             connected_peers,
             particle_queue_size,
+            particles_dropped_outlet_closed,
+            command_queue_delay,
+            slow_links,
+            queue_wait_sec,
+            dial_latency_sec,
+            version_mismatches,
+            client_sessions_idle_closed,
+            client_sessions_evicted,
+            spilled_particles_dropped,
+            spilled_particles_expired,
+            discovered_addresses_per_peer,
+            hop_limit_exceeded,
+            upgrade_retries,
         }
     }
 
+    pub fn command_executed(&self, kind: &'static str, delay: Duration) {
+        let label = CommandLabel {
+            kind: kind.to_string(),
+        };
+        self.command_queue_delay
+            .get_or_create(&label)
+            .observe(delay.as_secs_f64());
+    }
+
+    pub fn count_version_mismatch(&self, direction: ParticleDirection) {
+        self.version_mismatches
+            .get_or_create(&VersionMismatchLabel { direction })
+            .inc();
+    }
+
     pub fn incoming_particle(&self, particle_id: &str, queue_len: i64, particle_len: f64) {
         self.particle_queue_size.set(queue_len);
         let label = ParticleLabel {
@@ -81,4 +260,14 @@ impl ConnectionPoolMetrics {
             .get_or_create(&label)
             .observe(particle_len);
     }
+
+    pub fn outgoing_particle(&self, particle_id: &str, particle_len: f64) {
+        let label = ParticleLabel {
+            particle_type: ParticleType::from_particle(particle_id),
+        };
+        self.sent_particles.get_or_create(&label).inc();
+        self.message_sizes_sent
+            .get_or_create(&label)
+            .observe(particle_len);
+    }
 }
@@ -14,14 +14,21 @@
  * limitations under the License.
  */
 
+use std::time::Duration;
+
 use crate::{ParticleLabel, ParticleType};
 use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
 use prometheus_client::registry::Registry;
 
 #[derive(Clone)]
 pub struct DispatcherMetrics {
     pub expired_particles: Family<ParticleLabel, Counter>,
+    pub particle_processing_panics: Counter,
+    pub reorder_buffer_depth: Gauge,
+    pub remaining_ttl_sec: Histogram,
 }
 
 impl DispatcherMetrics {
@@ -48,7 +55,34 @@ impl DispatcherMetrics {
             expired_particles.clone(),
         );
 
-        DispatcherMetrics { expired_particles }
+        let particle_processing_panics = Counter::default();
+        sub_registry.register(
+            "particle_processing_panics",
+            "Number of panics caught while processing particles",
+            particle_processing_panics.clone(),
+        );
+
+        let reorder_buffer_depth = Gauge::default();
+        sub_registry.register(
+            "reorder_buffer_depth",
+            "Number of particles waiting in the deadline-ordered buffer",
+            reorder_buffer_depth.clone(),
+        );
+
+        // from 100 milliseconds to ~3.5 minutes of remaining TTL
+        let remaining_ttl_sec = Histogram::new(exponential_buckets(0.1, 2.0, 12));
+        sub_registry.register(
+            "remaining_ttl_sec",
+            "Remaining TTL of particles at execution start, in seconds",
+            remaining_ttl_sec.clone(),
+        );
+
+        DispatcherMetrics {
+            expired_particles,
+            particle_processing_panics,
+            reorder_buffer_depth,
+            remaining_ttl_sec,
+        }
     }
 
     pub fn particle_expired(&self, particle_id: &str) {
@@ -58,4 +92,16 @@ impl DispatcherMetrics {
             })
             .inc();
     }
+
+    pub fn particle_processing_panicked(&self) {
+        self.particle_processing_panics.inc();
+    }
+
+    pub fn reorder_buffer_size(&self, depth: usize) {
+        self.reorder_buffer_depth.set(depth as i64);
+    }
+
+    pub fn observe_remaining_ttl(&self, remaining: Duration) {
+        self.remaining_ttl_sec.observe(remaining.as_secs_f64());
+    }
 }
@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::metrics::info::Info;
 use prometheus_client::registry::Registry;
 
@@ -31,3 +32,14 @@ pub fn add_info_metrics(
     ]);
     sub_registry.register("build", "Nox Info", info);
 }
+
+pub fn add_unclean_restart_metric(registry: &mut Registry, unclean: bool) {
+    let sub_registry = registry.sub_registry_with_prefix("node");
+    let unclean_restart = Gauge::default();
+    unclean_restart.set(i64::from(unclean));
+    sub_registry.register(
+        "unclean_restart",
+        "1 when the previous run of the node ended without a clean shutdown",
+        unclean_restart,
+    );
+}
@@ -20,28 +20,41 @@ use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue, EncodeMetric
 use prometheus_client::registry::Registry;
 
 pub use chain_listener::ChainListenerMetrics;
+pub use chain_rpc::{ChainRpcMetrics, EndpointLabel};
 pub use connection_pool::ConnectionPoolMetrics;
+pub use connection_pool::LinkLabel;
+pub use connection_pool::ParticleDirection;
 pub use connectivity::ConnectivityMetrics;
 pub use connectivity::Resolution;
 pub use dispatcher::DispatcherMetrics;
-pub use info::add_info_metrics;
+pub use info::{add_info_metrics, add_unclean_restart_metric};
+pub use particle_data_store::{ParticleDataStoreMetrics, WorkerScopeLabel};
 use particle_execution::ParticleParams;
-pub use particle_executor::{FunctionKind, ParticleExecutorMetrics, WorkerLabel, WorkerType};
+pub use particle_executor::{
+    FunctionKind, InterpretationFailureLabel, InterpretationFailureReason, ParticleExecutorMetrics,
+    WorkerLabel, WorkerType,
+};
 pub use services_metrics::{
-    ServiceCallStats, ServiceMemoryStat, ServiceType, ServicesMetrics, ServicesMetricsBackend,
-    ServicesMetricsBuiltin, ServicesMetricsExternal,
+    MemoryAlert, ServiceCallStats, ServiceMemoryStat, ServiceType, ServicesMetrics,
+    ServicesMetricsBackend, ServicesMetricsBuiltin, ServicesMetricsExternal, TimingPercentiles,
 };
+pub use spell_event_bus::{SpellEventBusMetrics, SpellLabel};
 pub use spell_metrics::SpellMetrics;
+pub use toggles::{MetricsToggles, Togglable};
 pub use vm_pool::VmPoolMetrics;
 
 mod chain_listener;
+mod chain_rpc;
 mod connection_pool;
 mod connectivity;
 mod dispatcher;
 mod info;
+mod particle_data_store;
 mod particle_executor;
 mod services_metrics;
+mod spell_event_bus;
 mod spell_metrics;
+mod toggles;
 mod vm_pool;
 
 // TODO:
@@ -58,7 +71,7 @@ pub enum ParticleType {
 }
 
 impl ParticleType {
-    fn from_particle(particle_id: &str) -> Self {
+    pub fn from_particle(particle_id: &str) -> Self {
         if ParticleParams::is_spell_particle(particle_id) {
             ParticleType::Spell
         } else {
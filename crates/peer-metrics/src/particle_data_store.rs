@@ -0,0 +1,76 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::register;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::registry::Registry;
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct WorkerScopeLabel {
+    pub worker: String,
+}
+
+/// Disk usage of the per-particle interpreter data files,
+/// kept in sync with every write and delete
+#[derive(Debug, Clone)]
+pub struct ParticleDataStoreMetrics {
+    pub total_bytes: Gauge,
+    pub worker_bytes: Family<WorkerScopeLabel, Gauge>,
+}
+
+impl ParticleDataStoreMetrics {
+    pub fn new(registry: &mut Registry) -> Self {
+        let sub_registry = registry.sub_registry_with_prefix("particle_data_store");
+
+        let total_bytes = register(
+            sub_registry,
+            Gauge::default(),
+            "total_bytes",
+            "Total size of particle data files on disk",
+        );
+
+        let worker_bytes = register(
+            sub_registry,
+            Family::default(),
+            "worker_bytes",
+            "Size of particle data files on disk per worker scope",
+        );
+
+        Self {
+            total_bytes,
+            worker_bytes,
+        }
+    }
+
+    /// Records the current usage of one worker scope along with the new total.
+    /// A worker that no longer stores anything has its gauge removed instead
+    /// of being kept at zero forever
+    pub fn observe_usage(&self, worker: &str, worker_bytes: u64, total_bytes: u64) {
+        self.total_bytes.set(total_bytes as i64);
+        let label = WorkerScopeLabel {
+            worker: worker.to_string(),
+        };
+        if worker_bytes == 0 {
+            self.worker_bytes.remove(&label);
+        } else {
+            self.worker_bytes
+                .get_or_create(&label)
+                .set(worker_bytes as i64);
+        }
+    }
+}
@@ -24,6 +24,7 @@ use prometheus_client::metrics::histogram::Histogram;
 use prometheus_client::registry::Registry;
 
 use crate::execution_time_buckets;
+use crate::{MetricsToggles, Togglable};
 
 #[derive(Copy, Clone, Debug, EncodeLabelValue, Hash, Eq, PartialEq)]
 pub enum FunctionKind {
@@ -40,12 +41,12 @@ pub struct FunctionKindLabel {
 
 #[derive(Clone)]
 pub struct ParticleExecutorMetrics {
-    pub interpretation_time_sec: Family<WorkerLabel, Histogram>,
+    pub interpretation_time_sec: Togglable<Family<WorkerLabel, Histogram>>,
     pub interpretation_successes: Family<WorkerLabel, Counter>,
-    pub interpretation_failures: Family<WorkerLabel, Counter>,
+    pub interpretation_failures: Family<InterpretationFailureLabel, Counter>,
     pub total_actors_mailbox: Family<WorkerLabel, Gauge>,
     pub alive_actors: Family<WorkerLabel, Gauge>,
-    service_call_time_sec: Family<FunctionKindLabel, Histogram>,
+    service_call_time_sec: Togglable<Family<FunctionKindLabel, Histogram>>,
     service_call_success: Family<FunctionKindLabel, Counter>,
     service_call_failure: Family<FunctionKindLabel, Counter>,
 }
@@ -71,8 +72,36 @@ pub enum WorkerType {
     Host,
 }
 
+/// Why a particle interpretation failed
+#[derive(Copy, Clone, Debug, EncodeLabelValue, Hash, Eq, PartialEq)]
+pub enum InterpretationFailureReason {
+    ScriptError,
+    ResourceExhausted,
+    WasmTrap,
+    Timeout,
+    DataQuotaExceeded,
+    Other,
+}
+
+#[derive(EncodeLabelSet, Debug, Clone, Hash, Eq, PartialEq)]
+pub struct InterpretationFailureLabel {
+    worker_type: WorkerType,
+    peer_id: String,
+    reason: InterpretationFailureReason,
+}
+
+impl InterpretationFailureLabel {
+    pub fn new(label: &WorkerLabel, reason: InterpretationFailureReason) -> Self {
+        Self {
+            worker_type: label.worker_type.clone(),
+            peer_id: label.peer_id.clone(),
+            reason,
+        }
+    }
+}
+
 impl ParticleExecutorMetrics {
-    pub fn new(registry: &mut Registry) -> Self {
+    pub fn new(registry: &mut Registry, toggles: &MetricsToggles) -> Self {
         let sub_registry = registry.sub_registry_with_prefix("particle_executor");
 
         let interpretation_time_sec: Family<WorkerLabel, Histogram> =
@@ -82,6 +111,12 @@ impl ParticleExecutorMetrics {
             "Distribution of time it took to run the interpreter once",
             interpretation_time_sec.clone(),
         );
+        // the per-worker histogram is expensive in cardinality,
+        // so it can be switched off at runtime
+        let interpretation_time_sec = toggles.wrap(
+            "particle_executor_interpretation_time_sec",
+            interpretation_time_sec,
+        );
 
         let call_time_sec = Histogram::new(execution_time_buckets());
         sub_registry.register(
@@ -97,10 +132,11 @@ impl ParticleExecutorMetrics {
             interpretation_successes.clone(),
         );
 
-        let interpretation_failures = Family::default();
+        let interpretation_failures: Family<InterpretationFailureLabel, Counter> =
+            Family::default();
         sub_registry.register(
             "interpretation_failures",
-            "Number of failed particle interpretations",
+            "Number of failed particle interpretations, split by failure reason",
             interpretation_failures.clone(),
         );
 
@@ -125,6 +161,10 @@ impl ParticleExecutorMetrics {
             "Distribution of time it took to execute a single service or builtin call",
             service_call_time_sec.clone(),
         );
+        let service_call_time_sec = toggles.wrap(
+            "particle_executor_service_call_time_sec",
+            service_call_time_sec,
+        );
         let service_call_success = Family::default();
         sub_registry.register(
             "service_call_success",
@@ -162,8 +202,7 @@ impl ParticleExecutorMetrics {
         }
         if let Some(run_time) = run_time {
             self.service_call_time_sec
-                .get_or_create(&label)
-                .observe(run_time.as_secs_f64())
+                .observe(|family| family.get_or_create(&label).observe(run_time.as_secs_f64()))
         }
     }
 }
@@ -18,6 +18,7 @@ use std::collections::HashMap;
 use std::time;
 
 use futures::stream::StreamExt;
+use parking_lot::Mutex;
 use tokio::select;
 use tokio::sync::mpsc;
 use tokio::task::{Builder, JoinHandle};
@@ -31,6 +32,51 @@ use crate::ServiceType;
 
 type ServiceId = String;
 
+/// Sent to a memory-alert subscriber when a service's observed memory
+/// goes above the subscriber's threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAlert {
+    pub service_id: String,
+    pub service_type: ServiceType,
+    /// Memory the service was observed to use, in bytes
+    pub bytes: u64,
+    /// The threshold of the subscriber this alert is sent to, in bytes
+    pub threshold: u64,
+}
+
+struct MemoryAlertSubscriber {
+    threshold: u64,
+    outlet: mpsc::UnboundedSender<MemoryAlert>,
+}
+
+/// The two channel flavors the backend can read from. The bounded one is used
+/// when the node prefers dropping metrics over queueing them without limit
+pub enum MetricsInlet {
+    Unbounded(mpsc::UnboundedReceiver<ServiceMetricsMsg>),
+    Bounded(mpsc::Receiver<ServiceMetricsMsg>),
+}
+
+impl MetricsInlet {
+    async fn recv(&mut self) -> Option<ServiceMetricsMsg> {
+        match self {
+            MetricsInlet::Unbounded(inlet) => inlet.recv().await,
+            MetricsInlet::Bounded(inlet) => inlet.recv().await,
+        }
+    }
+}
+
+impl From<mpsc::UnboundedReceiver<ServiceMetricsMsg>> for MetricsInlet {
+    fn from(inlet: mpsc::UnboundedReceiver<ServiceMetricsMsg>) -> Self {
+        MetricsInlet::Unbounded(inlet)
+    }
+}
+
+impl From<mpsc::Receiver<ServiceMetricsMsg>> for MetricsInlet {
+    fn from(inlet: mpsc::Receiver<ServiceMetricsMsg>) -> Self {
+        MetricsInlet::Bounded(inlet)
+    }
+}
+
 /// Metrics that are meant to be written to an external metrics storage like Prometheus
 struct ExternalMetricsBackend {
     /// How often to send memory data to prometheus
@@ -45,9 +91,10 @@ struct ExternalMetricsBackend {
 /// requests from critical sections of code (where we can't afford to wait on locks)
 /// to store some metrics.
 pub struct ServicesMetricsBackend {
-    inlet: mpsc::UnboundedReceiver<ServiceMetricsMsg>,
+    inlet: MetricsInlet,
     external_metrics: Option<ExternalMetricsBackend>,
     builtin_metrics: ServicesMetricsBuiltin,
+    memory_alert_subscribers: Mutex<Vec<MemoryAlertSubscriber>>,
 }
 
 impl ServicesMetricsBackend {
@@ -56,7 +103,7 @@ impl ServicesMetricsBackend {
         timer_resolution: time::Duration,
         memory_metrics: ServicesMemoryMetrics,
         builtin_metrics: ServicesMetricsBuiltin,
-        inlet: mpsc::UnboundedReceiver<ServiceMetricsMsg>,
+        inlet: impl Into<MetricsInlet>,
     ) -> Self {
         let external_metrics = ExternalMetricsBackend {
             timer_resolution,
@@ -64,36 +111,58 @@ impl ServicesMetricsBackend {
             services_memory_stats: HashMap::new(),
         };
         Self {
-            inlet,
+            inlet: inlet.into(),
             external_metrics: Some(external_metrics),
             builtin_metrics,
+            memory_alert_subscribers: <_>::default(),
         }
     }
 
     /// Create a backend with only builtin metrics gathering enabled.
-    pub fn new(
-        builtin_metrics: ServicesMetricsBuiltin,
-        inlet: mpsc::UnboundedReceiver<ServiceMetricsMsg>,
-    ) -> Self {
+    pub fn new(builtin_metrics: ServicesMetricsBuiltin, inlet: impl Into<MetricsInlet>) -> Self {
         Self {
-            inlet,
+            inlet: inlet.into(),
             external_metrics: None,
             builtin_metrics,
+            memory_alert_subscribers: <_>::default(),
         }
     }
 
+    /// Registers a subscriber that is sent a [`MemoryAlert`] whenever a service
+    /// is observed to use more than `threshold_bytes` of memory. Subscribers
+    /// with different thresholds are notified independently; a subscriber
+    /// whose receiver is dropped is unregistered
+    pub fn subscribe_memory_alerts(
+        &self,
+        threshold_bytes: u64,
+    ) -> mpsc::UnboundedReceiver<MemoryAlert> {
+        let (outlet, inlet) = mpsc::unbounded_channel();
+        self.memory_alert_subscribers.lock().push(MemoryAlertSubscriber {
+            threshold: threshold_bytes,
+            outlet,
+        });
+        inlet
+    }
+
     pub fn start(self) -> JoinHandle<()> {
+        let subscribers = self.memory_alert_subscribers.into_inner();
         if let Some(external_metrics) = self.external_metrics {
-            Self::start_with_external(self.inlet, self.builtin_metrics, external_metrics)
+            Self::start_with_external(
+                self.inlet,
+                self.builtin_metrics,
+                external_metrics,
+                subscribers,
+            )
         } else {
-            Self::start_builtin_only(self.inlet, self.builtin_metrics)
+            Self::start_builtin_only(self.inlet, self.builtin_metrics, subscribers)
         }
     }
 
     fn start_with_external(
-        mut inlet: mpsc::UnboundedReceiver<ServiceMetricsMsg>,
+        mut inlet: MetricsInlet,
         builtin_metrics: ServicesMetricsBuiltin,
         external_metrics: ExternalMetricsBackend,
+        mut memory_alert_subscribers: Vec<MemoryAlertSubscriber>,
     ) -> JoinHandle<()> {
         Builder::new().name("Metrics").spawn(async move {
             let mut timer = IntervalStream::new(interval(external_metrics.timer_resolution));
@@ -105,11 +174,15 @@ impl ServicesMetricsBackend {
                         match msg {
                             // save data to the map
                             ServiceMetricsMsg::Memory { service_id, service_type, memory_stat } => {
+                                Self::check_memory_alerts(&mut memory_alert_subscribers, &service_id, &service_type, &memory_stat);
                                 Self::observe_service_mem(&mut services_memory_stats, service_id, service_type, memory_stat);
                             },
                             ServiceMetricsMsg::CallStats { service_id, function_name, stats } => {
                                 builtin_metrics.update(service_id, function_name, stats);
                             },
+                            ServiceMetricsMsg::Panic { service_id, service_type } => {
+                                log::warn!("Service {} ({:?}) panicked", service_id, service_type);
+                            },
                         }
                     },
                     _ = timer.next() => {
@@ -122,18 +195,24 @@ impl ServicesMetricsBackend {
     }
 
     fn start_builtin_only(
-        mut inlet: mpsc::UnboundedReceiver<ServiceMetricsMsg>,
+        mut inlet: MetricsInlet,
         builtin_metrics: ServicesMetricsBuiltin,
+        mut memory_alert_subscribers: Vec<MemoryAlertSubscriber>,
     ) -> JoinHandle<()> {
         Builder::new().name("Metrics").spawn(async move {
             loop {
                 select! {
                     Some(msg) = inlet.recv() => {
                         match msg {
-                            ServiceMetricsMsg::Memory{..} => {},
+                            ServiceMetricsMsg::Memory { service_id, service_type, memory_stat } => {
+                                Self::check_memory_alerts(&mut memory_alert_subscribers, &service_id, &service_type, &memory_stat);
+                            },
                             ServiceMetricsMsg::CallStats { service_id, function_name, stats } => {
                                 builtin_metrics.update(service_id, function_name, stats);
                             },
+                            ServiceMetricsMsg::Panic { service_id, service_type } => {
+                                log::warn!("Service {} ({:?}) panicked", service_id, service_type);
+                            },
                         }
                     },
                 }
@@ -141,6 +220,30 @@ impl ServicesMetricsBackend {
         }).expect("Could not spawn task")
     }
 
+    /// Notify every subscriber whose threshold the observed memory exceeds;
+    /// subscribers whose receivers are gone are unregistered on the way
+    fn check_memory_alerts(
+        subscribers: &mut Vec<MemoryAlertSubscriber>,
+        service_id: &str,
+        service_type: &ServiceType,
+        memory_stat: &ServiceMemoryStat,
+    ) {
+        subscribers.retain(|subscriber| {
+            if memory_stat.used_mem <= subscriber.threshold {
+                return true;
+            }
+            subscriber
+                .outlet
+                .send(MemoryAlert {
+                    service_id: service_id.to_string(),
+                    service_type: service_type.clone(),
+                    bytes: memory_stat.used_mem,
+                    threshold: subscriber.threshold,
+                })
+                .is_ok()
+        });
+    }
+
     /// Collect the current service memory metrics including memory metrics of the modules
     /// that belongs to the service.
     fn observe_service_mem(
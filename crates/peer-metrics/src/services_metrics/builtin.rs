@@ -16,6 +16,7 @@
 
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use parking_lot::RwLock;
 use serde::{
@@ -138,9 +139,62 @@ where
     seq.end()
 }
 
+/// Width of the sliding window over which per-service call rates are estimated
+pub const CALL_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Sliding-window estimate of how often a service is called.
+/// Keeps only the call counts of two adjacent [`CALL_RATE_WINDOW`]-wide buckets
+/// and weighs the previous one by how much of it the sliding window still
+/// covers, so the estimate takes constant memory no matter how hot the service is.
+#[derive(Debug, Clone)]
+struct RollingRate {
+    window_start: Instant,
+    current: u64,
+    previous: u64,
+}
+
+impl RollingRate {
+    fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            current: 0,
+            previous: 0,
+        }
+    }
+
+    /// Rotate the buckets so that `now` falls into the current one
+    fn rotate(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= 2 * CALL_RATE_WINDOW {
+            self.previous = 0;
+            self.current = 0;
+            self.window_start = now;
+        } else if elapsed >= CALL_RATE_WINDOW {
+            self.previous = self.current;
+            self.current = 0;
+            self.window_start += CALL_RATE_WINDOW;
+        }
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.rotate(now);
+        self.current += 1;
+    }
+
+    /// Calls per second over the sliding window ending at `now`
+    fn rate(&mut self, now: Instant) -> f64 {
+        self.rotate(now);
+        let into_window = now.duration_since(self.window_start).as_secs_f64();
+        let window = CALL_RATE_WINDOW.as_secs_f64();
+        let previous_weight = (window - into_window) / window;
+        (self.previous as f64 * previous_weight + self.current as f64) / window
+    }
+}
+
 #[derive(Clone)]
 pub struct ServicesMetricsBuiltin {
     content: Arc<RwLock<HashMap<ServiceId, ServiceStat>>>,
+    call_rates: Arc<RwLock<HashMap<ServiceId, RollingRate>>>,
     max_metrics_storage_size: usize,
 }
 
@@ -148,10 +202,37 @@ impl ServicesMetricsBuiltin {
     pub fn new(max_metrics_storage_size: usize) -> Self {
         ServicesMetricsBuiltin {
             content: Arc::new(RwLock::new(HashMap::new())),
+            call_rates: Arc::new(RwLock::new(HashMap::new())),
             max_metrics_storage_size,
         }
     }
 
+    /// Count a call towards the service's rolling call rate
+    pub fn record_call(&self, service_id: &str) {
+        self.record_call_at(service_id, Instant::now())
+    }
+
+    fn record_call_at(&self, service_id: &str, now: Instant) {
+        let mut call_rates = self.call_rates.write();
+        call_rates
+            .entry(service_id.to_string())
+            .or_insert_with(|| RollingRate::new(now))
+            .record(now);
+    }
+
+    /// Estimated rate of calls to the service in calls per second
+    /// over the last [`CALL_RATE_WINDOW`]. 0 for services that were never called
+    pub fn call_rate(&self, service_id: &str) -> f64 {
+        self.call_rate_at(service_id, Instant::now())
+    }
+
+    fn call_rate_at(&self, service_id: &str, now: Instant) -> f64 {
+        let mut call_rates = self.call_rates.write();
+        call_rates
+            .get_mut(service_id)
+            .map_or(0.0, |rate| rate.rate(now))
+    }
+
     pub fn update(&self, service_id: ServiceId, function_name: Name, stats: ServiceCallStats) {
         let mut content = self.content.write();
         let service_stat = content.entry(service_id).or_default();
@@ -178,3 +259,31 @@ impl ServicesMetricsBuiltin {
             .fold(0, |acc, x| acc + x.memory_size as u64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_rate_decays_as_the_window_slides() {
+        let builtin = ServicesMetricsBuiltin::new(10);
+        let start = Instant::now();
+
+        // 600 calls in the first window: 10 calls per second
+        for _ in 0..600 {
+            builtin.record_call_at("service", start);
+        }
+        let full = builtin.call_rate_at("service", start + CALL_RATE_WINDOW);
+        assert_eq!(full, 10.0);
+
+        // half a window later only half of the burst is still covered
+        let half = builtin.call_rate_at("service", start + CALL_RATE_WINDOW + CALL_RATE_WINDOW / 2);
+        assert_eq!(half, 5.0);
+
+        // two quiet windows later the burst is forgotten entirely
+        let gone = builtin.call_rate_at("service", start + 3 * CALL_RATE_WINDOW);
+        assert_eq!(gone, 0.0);
+
+        assert_eq!(builtin.call_rate_at("other", start), 0.0);
+    }
+}
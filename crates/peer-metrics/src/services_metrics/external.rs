@@ -21,13 +21,72 @@ use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::metrics::histogram::{linear_buckets, Histogram};
 use prometheus_client::registry::Registry;
+use std::collections::VecDeque;
 use std::fmt::Write;
+use std::sync::Arc;
 
+use parking_lot::Mutex;
 use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue, LabelValueEncoder};
 use prometheus_client::metrics::family::Family;
 
 use crate::{execution_time_buckets, mem_buckets_4gib, mem_buckets_8gib, register};
 
+/// How many recent observations the rolling percentile estimators keep
+const ROLLING_TIMINGS_CAPACITY: usize = 1024;
+
+/// Approximate percentiles of recent timing observations, in the same unit
+/// the timings were observed in (milliseconds for creation/removal times)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingPercentiles {
+    pub p50: f64,
+    pub p95: f64,
+}
+
+/// A rolling window of recent timing observations. The prometheus [`Histogram`]
+/// cannot be read back, so this estimator is maintained alongside it to serve
+/// approximate percentiles to the node snapshot
+#[derive(Clone)]
+struct RollingTimings {
+    samples: Arc<Mutex<VecDeque<f64>>>,
+}
+
+impl RollingTimings {
+    fn new() -> Self {
+        Self {
+            samples: Arc::new(Mutex::new(VecDeque::with_capacity(
+                ROLLING_TIMINGS_CAPACITY,
+            ))),
+        }
+    }
+
+    fn observe(&self, value: f64) {
+        let mut samples = self.samples.lock();
+        if samples.len() == ROLLING_TIMINGS_CAPACITY {
+            samples.pop_front();
+        }
+        samples.push_back(value);
+    }
+
+    /// Nearest-rank percentiles over the kept window; `None` until
+    /// at least one observation is made
+    fn percentiles(&self) -> Option<TimingPercentiles> {
+        let samples = self.samples.lock();
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = samples.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let rank = |q: f64| {
+            let index = (q * sorted.len() as f64).ceil() as usize;
+            sorted[index.saturating_sub(1).min(sorted.len() - 1)]
+        };
+        Some(TimingPercentiles {
+            p50: rank(0.50),
+            p95: rank(0.95),
+        })
+    }
+}
+
 #[derive(Hash, Clone, Eq, PartialEq, Debug)]
 pub enum ServiceType {
     Builtin,
@@ -85,14 +144,26 @@ pub struct ServicesMetricsExternal {
     /// How many modules a service includes.
     pub modules_in_services_count: Histogram,
 
+    /// Number of modules served from the compiled module cache on service creation
+    pub module_cache_hit_count: Counter,
+    /// Number of modules compiled from scratch on service creation
+    pub module_cache_miss_count: Counter,
+
     /// Service call time
     pub call_time_sec: Family<ServiceTypeLabel, Histogram>,
     pub lock_wait_time_sec: Family<ServiceTypeLabel, Histogram>,
     pub call_success_count: Family<ServiceTypeLabel, Counter>,
     pub call_failed_count: Family<ServiceTypeLabel, Counter>,
+    /// Number of WASM panics (traps) during call execution
+    pub service_panics: Family<ServiceTypeLabel, Counter>,
 
     /// Memory metrics
     pub memory_metrics: ServicesMemoryMetrics,
+
+    /// Rolling estimators mirroring `creation_time_msec`/`removal_time_msec`,
+    /// since the prometheus histograms cannot be read back
+    creation_timings: RollingTimings,
+    removal_timings: RollingTimings,
 }
 
 impl ServicesMetricsExternal {
@@ -176,6 +247,20 @@ impl ServicesMetricsExternal {
             "number of modules per services",
         );
 
+        let module_cache_hit_count = register(
+            sub_registry,
+            Counter::default(),
+            "module_cache_hit_count",
+            "number of modules served from the compiled module cache on service creation",
+        );
+
+        let module_cache_miss_count = register(
+            sub_registry,
+            Counter::default(),
+            "module_cache_miss_count",
+            "number of modules compiled from scratch on service creation",
+        );
+
         let call_time_sec: Family<_, _> = register(
             sub_registry,
             Family::new_with_constructor(|| Histogram::new(execution_time_buckets())),
@@ -209,6 +294,13 @@ impl ServicesMetricsExternal {
             "call_failed_count",
             "count of fails of calls execution",
         );
+
+        let service_panics = register(
+            sub_registry,
+            Family::default(),
+            "service_panics",
+            "count of WASM panics (traps) during calls execution",
+        );
         Self {
             services_count,
             creation_time_msec,
@@ -217,14 +309,31 @@ impl ServicesMetricsExternal {
             removal_count,
             creation_failure_count,
             modules_in_services_count,
+            module_cache_hit_count,
+            module_cache_miss_count,
             call_time_sec,
             lock_wait_time_sec,
             call_success_count,
             call_failed_count,
+            service_panics,
             memory_metrics,
+            creation_timings: RollingTimings::new(),
+            removal_timings: RollingTimings::new(),
         }
     }
 
+    /// Approximate p50/p95 of recent service creation times in milliseconds;
+    /// `None` until a service creation was observed
+    pub fn creation_time_percentiles(&self) -> Option<TimingPercentiles> {
+        self.creation_timings.percentiles()
+    }
+
+    /// Approximate p50/p95 of recent service removal times in milliseconds;
+    /// `None` until a service removal was observed
+    pub fn removal_time_percentiles(&self) -> Option<TimingPercentiles> {
+        self.removal_timings.percentiles()
+    }
+
     /// Collect all metrics that are relevant on service removal.
     pub fn observe_removed(&self, service_type: ServiceType, removal_time: f64) {
         let label = ServiceTypeLabel { service_type };
@@ -233,6 +342,7 @@ impl ServicesMetricsExternal {
         self.removal_time_msec
             .get_or_create(&label)
             .observe(removal_time);
+        self.removal_timings.observe(removal_time);
     }
 
     pub fn observe_created(&self, service_type: ServiceType, modules_num: f64, creation_time: f64) {
@@ -243,5 +353,63 @@ impl ServicesMetricsExternal {
         self.creation_time_msec
             .get_or_create(&label)
             .observe(creation_time);
+        self.creation_timings.observe(creation_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timing_percentiles_are_estimated_from_observations() {
+        let mut registry = Registry::default();
+        let metrics = ServicesMetricsExternal::new(&mut registry);
+
+        assert_eq!(metrics.creation_time_percentiles(), None);
+        assert_eq!(metrics.removal_time_percentiles(), None);
+
+        for time in 1..=100 {
+            metrics.observe_created(ServiceType::Service(None), 1.0, time as f64);
+            metrics.observe_removed(ServiceType::Service(None), (time * 2) as f64);
+        }
+
+        let creation = metrics.creation_time_percentiles().unwrap();
+        assert!(
+            (45.0..=55.0).contains(&creation.p50),
+            "unexpected creation p50: {}",
+            creation.p50
+        );
+        assert!(
+            (90.0..=100.0).contains(&creation.p95),
+            "unexpected creation p95: {}",
+            creation.p95
+        );
+
+        let removal = metrics.removal_time_percentiles().unwrap();
+        assert!(
+            (90.0..=110.0).contains(&removal.p50),
+            "unexpected removal p50: {}",
+            removal.p50
+        );
+        assert!(
+            (180.0..=200.0).contains(&removal.p95),
+            "unexpected removal p95: {}",
+            removal.p95
+        );
+    }
+
+    #[test]
+    fn rolling_timings_keep_a_bounded_window() {
+        let timings = RollingTimings::new();
+        // the first observation falls out of the window
+        // after `ROLLING_TIMINGS_CAPACITY` newer ones
+        timings.observe(1_000_000.0);
+        for _ in 0..ROLLING_TIMINGS_CAPACITY {
+            timings.observe(1.0);
+        }
+        let percentiles = timings.percentiles().unwrap();
+        assert_eq!(percentiles.p50, 1.0);
+        assert_eq!(percentiles.p95, 1.0);
     }
 }
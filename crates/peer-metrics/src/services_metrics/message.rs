@@ -48,6 +48,10 @@ pub enum ServiceMetricsMsg {
         function_name: String,
         stats: ServiceCallStats,
     },
+    Panic {
+        service_id: String,
+        service_type: ServiceType,
+    },
 }
 
 #[derive(Default, Debug)]
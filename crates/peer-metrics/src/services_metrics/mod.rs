@@ -19,13 +19,16 @@ pub mod builtin;
 pub mod external;
 pub mod message;
 
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::{fmt, time::Duration};
 
-pub use crate::services_metrics::backend::ServicesMetricsBackend;
+pub use crate::services_metrics::backend::{MemoryAlert, ServicesMetricsBackend};
 pub use crate::services_metrics::builtin::ServicesMetricsBuiltin;
 pub use crate::services_metrics::external::ServiceType;
 use crate::services_metrics::external::ServiceTypeLabel;
 pub use crate::services_metrics::external::ServicesMetricsExternal;
+pub use crate::services_metrics::external::TimingPercentiles;
 pub use crate::services_metrics::message::{ServiceCallStats, ServiceMemoryStat};
 use crate::ServiceCallStats::Success;
 use prometheus_client::registry::Registry;
@@ -34,11 +37,24 @@ use tokio::sync::mpsc::unbounded_channel;
 
 use crate::services_metrics::message::ServiceMetricsMsg;
 
+/// The sending side of the backend channel. The bounded flavor never blocks
+/// the call path: when the backend stalls and the buffer fills up, metrics
+/// are dropped and counted instead of queueing without limit
+#[derive(Clone)]
+enum MetricsBackendOutlet {
+    Unbounded(mpsc::UnboundedSender<ServiceMetricsMsg>),
+    Bounded {
+        outlet: mpsc::Sender<ServiceMetricsMsg>,
+        dropped_metrics: Arc<AtomicU64>,
+    },
+}
+
 #[derive(Clone)]
 pub struct ServicesMetrics {
     pub external: Option<ServicesMetricsExternal>,
     pub builtin: ServicesMetricsBuiltin,
-    metrics_backend_outlet: mpsc::UnboundedSender<ServiceMetricsMsg>,
+    metrics_backend_outlet: MetricsBackendOutlet,
+    call_rate_warn_threshold: Option<f64>,
 }
 
 impl fmt::Debug for ServicesMetrics {
@@ -56,10 +72,19 @@ impl ServicesMetrics {
         Self {
             external,
             builtin: ServicesMetricsBuiltin::new(max_builtin_storage_size),
-            metrics_backend_outlet,
+            metrics_backend_outlet: MetricsBackendOutlet::Unbounded(metrics_backend_outlet),
+            call_rate_warn_threshold: None,
         }
     }
 
+    /// Log a warning from the call path when a service's estimated call rate
+    /// exceeds `threshold` calls per second. Observability only: the calls
+    /// themselves are never blocked
+    pub fn with_call_rate_warn_threshold(mut self, threshold: Option<f64>) -> Self {
+        self.call_rate_warn_threshold = threshold;
+        self
+    }
+
     pub fn with_external_backend(
         timer_resolution: Duration,
         max_builtin_storage_size: usize,
@@ -87,6 +112,39 @@ impl ServicesMetrics {
         (backend, metrics)
     }
 
+    /// Like [`ServicesMetrics::with_simple_backend`], but with a bounded channel
+    /// of `buffer_size` messages towards the backend. When the backend stalls
+    /// and the buffer fills up, metrics are dropped (and counted) instead of
+    /// letting the queue grow until the process runs out of memory
+    pub fn with_bounded_backend(
+        buffer_size: usize,
+        max_builtin_storage_size: usize,
+    ) -> (ServicesMetricsBackend, Self) {
+        let (outlet, inlet) = mpsc::channel(buffer_size);
+        let metrics = Self {
+            external: None,
+            builtin: ServicesMetricsBuiltin::new(max_builtin_storage_size),
+            metrics_backend_outlet: MetricsBackendOutlet::Bounded {
+                outlet,
+                dropped_metrics: <_>::default(),
+            },
+            call_rate_warn_threshold: None,
+        };
+        let backend = ServicesMetricsBackend::new(metrics.builtin.clone(), inlet);
+        (backend, metrics)
+    }
+
+    /// How many metrics were dropped because the bounded backend channel was
+    /// full; always 0 for the unbounded flavor
+    pub fn dropped_metrics(&self) -> u64 {
+        match &self.metrics_backend_outlet {
+            MetricsBackendOutlet::Unbounded(_) => 0,
+            MetricsBackendOutlet::Bounded {
+                dropped_metrics, ..
+            } => dropped_metrics.load(Ordering::Relaxed),
+        }
+    }
+
     pub fn observe_builtins(&self, is_ok: bool, call_time: f64) {
         self.observe_external(|external| {
             let label = ServiceTypeLabel {
@@ -148,12 +206,46 @@ impl ServicesMetrics {
         });
     }
 
+    /// Record a WASM panic (trap) during a call execution.
+    /// Panics are counted on top of the regular failure accounting
+    /// done by [`ServicesMetrics::observe_service_state_failed`].
+    pub fn observe_service_panic(&self, service_id: String, service_type: ServiceType) {
+        self.observe_external(|external| {
+            external
+                .service_panics
+                .get_or_create(&ServiceTypeLabel {
+                    service_type: service_type.clone(),
+                })
+                .inc();
+        });
+        self.send(ServiceMetricsMsg::Panic {
+            service_id,
+            service_type,
+        });
+    }
+
+    /// Estimated rate of calls to the service in calls per second,
+    /// over a rolling one-minute window
+    pub fn call_rate(&self, service_id: &str) -> f64 {
+        self.builtin.call_rate(service_id)
+    }
+
     fn observe_service_call(
         &self,
         service_id: String,
         function_name: Option<String>,
         stats: ServiceCallStats,
     ) {
+        self.builtin.record_call(&service_id);
+        if let Some(threshold) = self.call_rate_warn_threshold {
+            let rate = self.builtin.call_rate(&service_id);
+            if rate > threshold {
+                log::warn!(
+                    "service {service_id} is called {rate:.2} times per second, \
+                     above the configured threshold of {threshold} calls/s"
+                );
+            }
+        }
         let function_name = function_name.unwrap_or("<unknown>".to_string());
         self.send(ServiceMetricsMsg::CallStats {
             service_id,
@@ -186,6 +278,13 @@ impl ServicesMetrics {
         });
     }
 
+    pub fn observe_module_cache(&self, hits: u64, misses: u64) {
+        self.observe_external(|external| {
+            external.module_cache_hit_count.inc_by(hits);
+            external.module_cache_miss_count.inc_by(misses);
+        });
+    }
+
     pub fn observe_removed(&self, service_type: ServiceType, removal_time: f64) {
         self.observe_external(|external| {
             external.observe_removed(service_type, removal_time);
@@ -216,9 +315,164 @@ impl ServicesMetrics {
     }
 
     fn send(&self, msg: ServiceMetricsMsg) {
-        let result = self.metrics_backend_outlet.send(msg);
-        if let Err(e) = result {
-            log::warn!("Can't save services' metrics: {:?}", e);
+        match &self.metrics_backend_outlet {
+            MetricsBackendOutlet::Unbounded(outlet) => {
+                if let Err(e) = outlet.send(msg) {
+                    log::warn!("Can't save services' metrics: {:?}", e);
+                }
+            }
+            MetricsBackendOutlet::Bounded {
+                outlet,
+                dropped_metrics,
+            } => match outlet.try_send(msg) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    // the backend is stalled; dropping the metric is cheaper
+                    // than blocking the call path
+                    dropped_metrics.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e @ mpsc::error::TrySendError::Closed(_)) => {
+                    log::warn!("Can't save services' metrics: {:?}", e);
+                }
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_are_counted_on_top_of_failures() {
+        let mut registry = Registry::default();
+        let (outlet, mut inlet) = unbounded_channel();
+        let external = ServicesMetricsExternal::new(&mut registry);
+        let metrics = ServicesMetrics::new(Some(external), outlet, 10);
+
+        // this is what the service call path does for a panicking call
+        metrics.observe_service_panic("service".to_string(), ServiceType::Service(None));
+        metrics.observe_service_state_failed(
+            "service".to_string(),
+            Some("func".to_string()),
+            ServiceType::Service(None),
+            ServiceCallStats::Fail { timestamp: 0 },
+        );
+
+        let mut encoded = String::new();
+        prometheus_client::encoding::text::encode(&mut encoded, &registry).unwrap();
+        assert!(
+            encoded
+                .contains("services_service_panics_total{service_type=\"non-aliased-services\"} 1"),
+            "no panic observation in {encoded}"
+        );
+        assert!(
+            encoded.contains(
+                "services_call_failed_count_total{service_type=\"non-aliased-services\"} 1"
+            ),
+            "no failed call observation in {encoded}"
+        );
+
+        // the backend is notified about the panic as well
+        let msg = inlet.try_recv().unwrap();
+        assert!(
+            matches!(msg, ServiceMetricsMsg::Panic { ref service_id, .. } if service_id == "service"),
+            "expected Panic message, got {msg:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_alerts_are_sent_to_subscribers_over_threshold() {
+        let mut registry = Registry::default();
+        let (outlet, inlet) = unbounded_channel();
+        let external = ServicesMetricsExternal::new(&mut registry);
+        let memory_metrics = external.memory_metrics.clone();
+        let metrics = ServicesMetrics::new(Some(external), outlet, 10);
+        let backend = ServicesMetricsBackend::with_external_metrics(
+            Duration::from_secs(60),
+            memory_metrics,
+            metrics.builtin.clone(),
+            inlet,
+        );
+
+        let mut low = backend.subscribe_memory_alerts(1024);
+        let mut high = backend.subscribe_memory_alerts(10 * 1024 * 1024);
+        backend.start();
+
+        metrics.observe_service_state(
+            "service".to_string(),
+            "func".to_string(),
+            ServiceType::Service(None),
+            ServiceMemoryStat {
+                used_mem: 2048,
+                modules_stats: <_>::default(),
+            },
+            ServiceCallStats::Success {
+                memory_delta_bytes: 0.0,
+                call_time_sec: 0.0,
+                lock_wait_time_sec: 0.0,
+                timestamp: 0,
+            },
+        );
+
+        let alert = low.recv().await.expect("receive an alert");
+        assert_eq!(
+            alert,
+            MemoryAlert {
+                service_id: "service".to_string(),
+                service_type: ServiceType::Service(None),
+                bytes: 2048,
+                threshold: 1024,
+            }
+        );
+        assert!(
+            high.try_recv().is_err(),
+            "the high-threshold subscriber must not be notified"
+        );
+    }
+
+    #[test]
+    fn bounded_backend_counts_drops_instead_of_blocking() {
+        let buffer_size = 2;
+        let (_backend, metrics) = ServicesMetrics::with_bounded_backend(buffer_size, 10);
+
+        // the backend is not started, so the channel fills after `buffer_size`
+        // messages; the sends past that point must return immediately
+        for _ in 0..5 {
+            metrics.observe_service_state_failed(
+                "service".to_string(),
+                Some("func".to_string()),
+                ServiceType::Service(None),
+                ServiceCallStats::Fail { timestamp: 0 },
+            );
+        }
+
+        assert_eq!(metrics.dropped_metrics(), (5 - buffer_size) as u64);
+    }
+
+    #[test]
+    fn burst_of_calls_drives_the_rate_above_the_threshold() {
+        let threshold = 10.0;
+        let (_backend, metrics) = ServicesMetrics::with_simple_backend(10);
+        let metrics = metrics.with_call_rate_warn_threshold(Some(threshold));
+
+        assert_eq!(metrics.call_rate("service"), 0.0);
+
+        // 1000 calls within the one-minute window: well above 10 calls/s
+        for _ in 0..1000 {
+            metrics.observe_service_state_failed(
+                "service".to_string(),
+                Some("func".to_string()),
+                ServiceType::Service(None),
+                ServiceCallStats::Fail { timestamp: 0 },
+            );
+        }
+
+        let rate = metrics.call_rate("service");
+        assert!(
+            rate > threshold,
+            "estimated rate {rate} must exceed the threshold {threshold}"
+        );
+        assert_eq!(metrics.call_rate("other-service"), 0.0);
+    }
+}
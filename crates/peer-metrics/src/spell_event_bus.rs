@@ -0,0 +1,90 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::Histogram;
+use prometheus_client::registry::Registry;
+
+use crate::{execution_time_buckets, register};
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct SpellLabel {
+    pub spell_id: String,
+}
+
+#[derive(Clone)]
+pub struct SpellEventBusMetrics {
+    /// Number of triggers delivered to each spell
+    pub triggers_fired: Family<SpellLabel, Counter>,
+    /// Time between the moment a timer trigger was scheduled to fire and
+    /// the moment it actually fired
+    pub trigger_delay_sec: Family<SpellLabel, Histogram>,
+    /// Number of subscribed spells whose triggers are on hold
+    /// because scheduling hasn't been started yet
+    pub paused_spells: Gauge,
+}
+
+impl SpellEventBusMetrics {
+    pub fn new(registry: &mut Registry) -> Self {
+        let sub_registry = registry.sub_registry_with_prefix("spell_event_bus");
+
+        let triggers_fired = register(
+            sub_registry,
+            Family::default(),
+            "triggers_fired",
+            "Number of triggers delivered to each spell",
+        );
+
+        let trigger_delay_sec: Family<_, _> = register(
+            sub_registry,
+            Family::new_with_constructor(|| Histogram::new(execution_time_buckets())),
+            "trigger_delay_sec",
+            "Delay between the scheduled and the actual fire time of spell timer triggers",
+        );
+
+        let paused_spells = register(
+            sub_registry,
+            Gauge::default(),
+            "paused_spells",
+            "Number of subscribed spells whose triggers are on hold until scheduling starts",
+        );
+
+        Self {
+            triggers_fired,
+            trigger_delay_sec,
+            paused_spells,
+        }
+    }
+
+    /// Records a trigger delivered to the spell. `delay` is set for timer
+    /// triggers only: event triggers have no scheduled fire time.
+    pub fn observe_trigger(&self, spell_id: &str, delay: Option<Duration>) {
+        let label = SpellLabel {
+            spell_id: spell_id.to_string(),
+        };
+        self.triggers_fired.get_or_create(&label).inc();
+        if let Some(delay) = delay {
+            self.trigger_delay_sec
+                .get_or_create(&label)
+                .observe(delay.as_secs_f64());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+/// Runtime switches for metric families that are expensive in cardinality.
+///
+/// Families registered through [`MetricsToggles::wrap`] can be turned on and
+/// off without restarting the node: while a family is disabled its
+/// observations are no-ops, but the family stays in the registry and keeps
+/// encoding whatever series it has already created.
+#[derive(Clone, Default)]
+pub struct MetricsToggles {
+    /// Families that start disabled, usually taken from node config
+    disabled_at_startup: Arc<Vec<String>>,
+    switches: Arc<RwLock<BTreeMap<String, Arc<AtomicBool>>>>,
+}
+
+impl MetricsToggles {
+    pub fn new(disabled_families: Vec<String>) -> Self {
+        Self {
+            disabled_at_startup: Arc::new(disabled_families),
+            switches: <_>::default(),
+        }
+    }
+
+    /// Puts a metric family behind a runtime switch registered as `family_name`.
+    /// The family starts enabled unless it is listed in the initial disabled set
+    pub fn wrap<M>(&self, family_name: &str, metric: M) -> Togglable<M> {
+        let enabled = !self.disabled_at_startup.iter().any(|f| f == family_name);
+        let switch = Arc::new(AtomicBool::new(enabled));
+        self.switches
+            .write()
+            .insert(family_name.to_string(), switch.clone());
+        Togglable { metric, switch }
+    }
+
+    /// All togglable family names with their current state, in a stable order
+    pub fn list(&self) -> Vec<(String, bool)> {
+        self.switches
+            .read()
+            .iter()
+            .map(|(name, switch)| (name.clone(), switch.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Flips a family on or off; returns `false` if no such family was registered
+    pub fn set_enabled(&self, family_name: &str, enabled: bool) -> bool {
+        match self.switches.read().get(family_name) {
+            Some(switch) => {
+                switch.store(enabled, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A metric behind a runtime switch from [`MetricsToggles`]. The enabled check
+/// is a single relaxed atomic load, so the wrapper costs next to nothing on
+/// the hot path
+#[derive(Clone)]
+pub struct Togglable<M> {
+    metric: M,
+    switch: Arc<AtomicBool>,
+}
+
+impl<M> Togglable<M> {
+    #[inline]
+    pub fn observe(&self, observation: impl FnOnce(&M)) {
+        if self.switch.load(Ordering::Relaxed) {
+            observation(&self.metric)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus_client::encoding::text::encode;
+    use prometheus_client::metrics::family::Family;
+    use prometheus_client::metrics::histogram::Histogram;
+    use prometheus_client::registry::Registry;
+
+    use crate::execution_time_buckets;
+    use crate::{WorkerLabel, WorkerType};
+
+    use super::*;
+
+    fn encoded(registry: &Registry) -> String {
+        let mut text = String::new();
+        encode(&mut text, registry).unwrap();
+        text
+    }
+
+    #[test]
+    fn disabled_family_stops_and_resumes_observations() {
+        let mut registry = Registry::default();
+        let toggles = MetricsToggles::new(vec!["test_time_sec".to_string()]);
+
+        let family: Family<WorkerLabel, Histogram> =
+            Family::new_with_constructor(|| Histogram::new(execution_time_buckets()));
+        registry.register("test_time_sec", "Togglable test histogram", family.clone());
+        let togglable = toggles.wrap("test_time_sec", family);
+
+        let label = WorkerLabel::new(WorkerType::Host, "host_peer_id".to_string());
+        togglable.observe(|family| family.get_or_create(&label).observe(0.5));
+        assert!(
+            !encoded(&registry).contains("host_peer_id"),
+            "no series should appear while the family is disabled"
+        );
+
+        assert!(toggles.set_enabled("test_time_sec", true));
+        togglable.observe(|family| family.get_or_create(&label).observe(0.5));
+        assert!(
+            encoded(&registry).contains("host_peer_id"),
+            "observations should resume once the family is enabled"
+        );
+
+        assert!(toggles.set_enabled("test_time_sec", false));
+        let before = encoded(&registry);
+        let other_label = WorkerLabel::new(WorkerType::Host, "other_peer_id".to_string());
+        togglable.observe(|family| family.get_or_create(&other_label).observe(0.5));
+        assert_eq!(
+            before,
+            encoded(&registry),
+            "a disabled family should keep encoding existing series and gain no new ones"
+        );
+    }
+
+    #[test]
+    fn toggles_are_listed_and_unknown_families_are_rejected() {
+        let toggles = MetricsToggles::new(vec!["b".to_string()]);
+        let _a = toggles.wrap("a", ());
+        let _b = toggles.wrap("b", ());
+
+        assert_eq!(
+            toggles.list(),
+            vec![("a".to_string(), true), ("b".to_string(), false)]
+        );
+        assert!(!toggles.set_enabled("unknown", true));
+        assert!(toggles.set_enabled("b", true));
+        assert_eq!(
+            toggles.list(),
+            vec![("a".to_string(), true), ("b".to_string(), true)]
+        );
+    }
+}
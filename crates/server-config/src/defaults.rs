@@ -152,6 +152,18 @@ pub fn default_particle_queue_buffer_size() -> usize {
     128
 }
 
+pub fn default_command_queue_buffer_size() -> usize {
+    128
+}
+
+pub fn default_slow_link_latency_threshold() -> Duration {
+    Duration::from_secs(1)
+}
+
+pub fn default_particle_queue_spill_max_size() -> usize {
+    10 * 1024 * 1024
+}
+
 pub fn default_effects_queue_buffer_size() -> usize {
     128
 }
@@ -168,6 +180,10 @@ pub fn default_max_spell_particle_ttl() -> Duration {
     Duration::from_secs(120)
 }
 
+pub fn default_spell_peer_events_enabled() -> bool {
+    true
+}
+
 pub fn default_bootstrap_frequency() -> usize {
     3
 }
@@ -176,6 +192,22 @@ pub fn default_execution_timeout() -> Duration {
     Duration::from_secs(20)
 }
 
+pub fn default_builtin_usage_window() -> Duration {
+    Duration::from_secs(60)
+}
+
+pub fn default_particle_timeline_max_particles() -> usize {
+    1000
+}
+
+pub fn default_ipfs_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+pub fn default_effect_retries() -> usize {
+    3
+}
+
 pub fn default_processing_timeout() -> Duration {
     Duration::from_secs(120)
 }
@@ -298,3 +330,7 @@ pub fn default_binaries_mapping() -> BTreeMap<String, String> {
 pub fn default_proof_poll_period() -> Duration {
     Duration::from_secs(60)
 }
+
+pub fn default_confirmation_depth() -> u64 {
+    0
+}
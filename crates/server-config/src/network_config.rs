@@ -17,11 +17,12 @@
 use libp2p::{core::Multiaddr, identity::Keypair, PeerId};
 use libp2p_connection_limits::ConnectionLimits;
 use libp2p_metrics::Metrics;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
 use config_utils::to_peer_id;
-use particle_protocol::ProtocolConfig;
+use particle_protocol::{AirVersionPolicy, ProtocolConfig};
 use peer_metrics::{ConnectionPoolMetrics, ConnectivityMetrics};
 
 use crate::kademlia_config::KademliaConfig;
@@ -31,20 +32,34 @@ pub struct NetworkConfig {
     pub key_pair: Keypair,
     pub local_peer_id: PeerId,
     pub node_version: &'static str,
+    pub air_version: &'static str,
     pub bootstrap_nodes: Vec<Multiaddr>,
     pub bootstrap: BootstrapConfig,
+    /// Peer authorized to manage the node; its connections are classified
+    /// as worker-class for the connection keep-alive policy
+    pub management_peer_id: PeerId,
     pub libp2p_metrics: Option<Arc<Metrics>>,
     pub protocol_config: ProtocolConfig,
     pub kademlia_config: KademliaConfig,
     pub particle_queue_buffer: usize,
+    pub command_queue_buffer: usize,
+    pub slow_link_threshold: Duration,
+    pub client_idle_timeout: Option<Duration>,
+    pub max_client_sessions: Option<usize>,
+    /// File queued local particles are spilled to on graceful shutdown;
+    /// `None` disables the spill
+    pub particle_queue_spill: Option<PathBuf>,
+    pub particle_queue_spill_max_size: usize,
     pub bootstrap_frequency: usize,
     pub connectivity_metrics: Option<ConnectivityMetrics>,
     pub connection_pool_metrics: Option<ConnectionPoolMetrics>,
     pub connection_limits: ConnectionLimits,
     pub connection_idle_timeout: Duration,
+    pub air_version_policy: AirVersionPolicy,
 }
 
 impl NetworkConfig {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         libp2p_metrics: Option<Arc<Metrics>>,
         connectivity_metrics: Option<ConnectivityMetrics>,
@@ -52,23 +67,35 @@ impl NetworkConfig {
         key_pair: Keypair,
         config: &ResolvedConfig,
         node_version: &'static str,
+        air_version: &'static str,
         connection_limits: ConnectionLimits,
     ) -> Self {
         Self {
             node_version,
+            air_version,
             libp2p_metrics,
             local_peer_id: to_peer_id(&key_pair),
             key_pair,
             bootstrap_nodes: config.bootstrap_nodes.clone(),
             bootstrap: config.bootstrap_config.clone(),
+            management_peer_id: config.management_peer_id,
             protocol_config: config.protocol_config.clone(),
             kademlia_config: config.kademlia.clone(),
             particle_queue_buffer: config.particle_queue_buffer,
+            command_queue_buffer: config.command_queue_buffer,
+            slow_link_threshold: config.slow_link_latency_threshold,
+            client_idle_timeout: config.client_idle_timeout,
+            max_client_sessions: config.max_client_sessions,
+            particle_queue_spill: config
+                .particle_queue_spill_enabled
+                .then(|| config.dir_config.base_dir.join("particles_spill.json")),
+            particle_queue_spill_max_size: config.particle_queue_spill_max_size,
             bootstrap_frequency: config.bootstrap_frequency,
             connectivity_metrics,
             connection_pool_metrics,
             connection_limits,
             connection_idle_timeout: config.node_config.transport_config.connection_idle_timeout,
+            air_version_policy: config.air_version_policy.clone(),
         }
     }
 }
@@ -38,7 +38,7 @@ use fluence_libp2p::PeerId;
 use fluence_libp2p::Transport;
 use fs_utils::to_abs_path;
 use hex_utils::serde_as::Hex;
-use particle_protocol::ProtocolConfig;
+use particle_protocol::{AirVersionPolicy, ProtocolConfig};
 use types::peer_id;
 
 use crate::avm_config::AVMConfig;
@@ -107,6 +107,11 @@ pub struct UnresolvedNodeConfig {
     #[serde(default)]
     pub protocol_config: ProtocolConfig,
 
+    /// Range of remote AIR interpreter versions this node exchanges particles with;
+    /// particles from and to peers outside the range are refused
+    #[serde(default)]
+    pub air_version_policy: AirVersionPolicy,
+
     /// These are the AquaVM limits that are used by the AquaVM limit check.
     #[derivative(Debug = "ignore")]
     pub avm_config: Option<AVMConfig>,
@@ -126,6 +131,36 @@ pub struct UnresolvedNodeConfig {
     #[serde(default = "default_particle_queue_buffer_size")]
     pub particle_queue_buffer: usize,
 
+    #[serde(default = "default_command_queue_buffer_size")]
+    pub command_queue_buffer: usize,
+
+    /// Links whose p95 particle send latency exceeds this threshold
+    /// are reported through the `slow_links` metric
+    #[serde(default = "default_slow_link_latency_threshold")]
+    #[serde(with = "humantime_serde")]
+    pub slow_link_latency_threshold: Duration,
+
+    /// Client (non-relay) connections with no particle activity for this long
+    /// are closed. No timeout means idle clients are never disconnected
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub client_idle_timeout: Option<Duration>,
+
+    /// Maximum number of concurrently connected client (non-relay) peers;
+    /// when exceeded, the most idle client is disconnected to make room.
+    /// No limit means client connections are never evicted
+    #[serde(default)]
+    pub max_client_sessions: Option<usize>,
+
+    /// Persist locally originated particles left in the connection pool queue
+    /// on graceful shutdown and replay them on the next start
+    #[serde(default)]
+    pub particle_queue_spill_enabled: bool,
+
+    /// Maximum size of the particle spill file in bytes
+    #[serde(default = "default_particle_queue_spill_max_size")]
+    pub particle_queue_spill_max_size: usize,
+
     #[serde(default = "default_effects_queue_buffer_size")]
     pub effects_queue_buffer: usize,
 
@@ -139,6 +174,18 @@ pub struct UnresolvedNodeConfig {
     #[serde(with = "humantime_serde")]
     pub max_spell_particle_ttl: Duration,
 
+    /// Node-level switch for peer-event spell triggers (connect/disconnect).
+    /// When disabled, spells are never subscribed to connection pool events,
+    /// no matter what their trigger configs request.
+    #[serde(default = "default_spell_peer_events_enabled")]
+    pub spell_peer_events_enabled: bool,
+
+    /// What to do with delayed spell triggers whose fire time passed while
+    /// the node was down: fire them immediately on startup when enabled,
+    /// drop them otherwise
+    #[serde(default)]
+    pub spell_fire_missed_delayed_triggers: bool,
+
     #[serde(default = "default_bootstrap_frequency")]
     pub bootstrap_frequency: usize,
 
@@ -149,6 +196,50 @@ pub struct UnresolvedNodeConfig {
     #[serde(with = "humantime_serde")]
     pub particle_execution_timeout: Duration,
 
+    /// Window over which builtin execution time is accounted per init peer
+    #[serde(default = "default_builtin_usage_window")]
+    #[serde(with = "humantime_serde")]
+    pub builtin_usage_window: Duration,
+
+    /// Builtin execution time budget per init peer within a window; a peer
+    /// that exhausts it gets its further builtin calls refused until the
+    /// window rolls over. No budget means builtin calls are never throttled
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub builtin_usage_budget: Option<Duration>,
+
+    /// How long per-particle event timelines are kept for the
+    /// `("debug", "particle_timeline")` builtin; no value disables
+    /// timeline collection entirely
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub particle_timeline_retention: Option<Duration>,
+
+    /// At most this many particles keep a timeline at once;
+    /// the oldest ones are evicted first
+    #[serde(default = "default_particle_timeline_max_particles")]
+    pub particle_timeline_max_particles: usize,
+
+    /// Base URL of an IPFS gateway the `ipfs.cat` builtin fetches content
+    /// from; no value disables the builtin
+    #[serde(default)]
+    pub ipfs_gateway: Option<String>,
+
+    /// Base URL of an IPFS HTTP API the `ipfs.add` builtin adds content
+    /// through; no value disables the builtin
+    #[serde(default)]
+    pub ipfs_api: Option<String>,
+
+    /// Timeout of a single HTTP request made by the `ipfs` builtins
+    #[serde(default = "default_ipfs_request_timeout")]
+    #[serde(with = "humantime_serde")]
+    pub ipfs_request_timeout: Duration,
+
+    /// How many times failed particle routing effects are retried
+    /// before the particle is given up on
+    #[serde(default = "default_effect_retries")]
+    pub effect_retries: usize,
+
     #[serde(
         serialize_with = "peer_id::serde::serialize",
         deserialize_with = "peer_id::serde::deserialize"
@@ -240,6 +331,10 @@ impl UnresolvedNodeConfig {
 
         let kademlia = self.kademlia.resolve(&self.network)?;
 
+        self.protocol_config
+            .validate()
+            .map_err(|err| eyre!("`protocol_config`: {err}"))?;
+
         let result = NodeConfig {
             system_cpu_count: self.system_cpu_count,
             cpus_range,
@@ -254,18 +349,35 @@ impl UnresolvedNodeConfig {
             root_weights: self.root_weights,
             services_envs: self.services_envs,
             protocol_config: self.protocol_config,
+            air_version_policy: self.air_version_policy,
             aquavm_pool_size: self.aquavm_pool_size,
             default_service_memory_limit: self.default_service_memory_limit,
             avm_config: self.avm_config.unwrap_or_default(),
             kademlia,
             particle_queue_buffer: self.particle_queue_buffer,
+            command_queue_buffer: self.command_queue_buffer,
+            slow_link_latency_threshold: self.slow_link_latency_threshold,
+            client_idle_timeout: self.client_idle_timeout,
+            max_client_sessions: self.max_client_sessions,
+            particle_queue_spill_enabled: self.particle_queue_spill_enabled,
+            particle_queue_spill_max_size: self.particle_queue_spill_max_size,
             effects_queue_buffer: self.effects_queue_buffer,
             workers_queue_buffer: self.workers_queue_buffer,
             particle_processor_parallelism: self.particle_processor_parallelism,
             max_spell_particle_ttl: self.max_spell_particle_ttl,
+            spell_peer_events_enabled: self.spell_peer_events_enabled,
+            spell_fire_missed_delayed_triggers: self.spell_fire_missed_delayed_triggers,
             bootstrap_frequency: self.bootstrap_frequency,
             allow_local_addresses: self.allow_local_addresses,
             particle_execution_timeout: self.particle_execution_timeout,
+            builtin_usage_window: self.builtin_usage_window,
+            builtin_usage_budget: self.builtin_usage_budget,
+            particle_timeline_retention: self.particle_timeline_retention,
+            particle_timeline_max_particles: self.particle_timeline_max_particles,
+            ipfs_gateway: self.ipfs_gateway,
+            ipfs_api: self.ipfs_api,
+            effect_retries: self.effect_retries,
+            ipfs_request_timeout: self.ipfs_request_timeout,
             management_peer_id: self.management_peer_id,
             transport_config: self.transport_config,
             listen_config: self.listen_config,
@@ -416,6 +528,10 @@ pub struct NodeConfig {
 
     pub protocol_config: ProtocolConfig,
 
+    /// Range of remote AIR interpreter versions this node exchanges particles with;
+    /// particles from and to peers outside the range are refused
+    pub air_version_policy: AirVersionPolicy,
+
     /// Number of AVMs to create. By default, `num_cpus::get() * 2` is used
     pub aquavm_pool_size: usize,
 
@@ -429,6 +545,27 @@ pub struct NodeConfig {
 
     pub particle_queue_buffer: usize,
 
+    pub command_queue_buffer: usize,
+
+    /// Links whose p95 particle send latency exceeds this threshold
+    /// are reported through the `slow_links` metric
+    pub slow_link_latency_threshold: Duration,
+
+    /// Client (non-relay) connections with no particle activity for this long
+    /// are closed; no timeout means idle clients are never disconnected
+    pub client_idle_timeout: Option<Duration>,
+
+    /// Maximum number of concurrently connected client (non-relay) peers;
+    /// no limit means client connections are never evicted
+    pub max_client_sessions: Option<usize>,
+
+    /// Persist locally originated particles left in the connection pool queue
+    /// on graceful shutdown and replay them on the next start
+    pub particle_queue_spill_enabled: bool,
+
+    /// Maximum size of the particle spill file in bytes
+    pub particle_queue_spill_max_size: usize,
+
     pub effects_queue_buffer: usize,
 
     pub workers_queue_buffer: usize,
@@ -437,12 +574,50 @@ pub struct NodeConfig {
 
     pub max_spell_particle_ttl: Duration,
 
+    pub spell_peer_events_enabled: bool,
+
+    /// What to do with delayed spell triggers whose fire time passed while
+    /// the node was down: fire them immediately on startup when enabled,
+    /// drop them otherwise
+    pub spell_fire_missed_delayed_triggers: bool,
+
     pub bootstrap_frequency: usize,
 
     pub allow_local_addresses: bool,
 
     pub particle_execution_timeout: Duration,
 
+    /// Window over which builtin execution time is accounted per init peer
+    pub builtin_usage_window: Duration,
+
+    /// Builtin execution time budget per init peer within a window;
+    /// no budget means builtin calls are never throttled
+    pub builtin_usage_budget: Option<Duration>,
+
+    /// How long per-particle event timelines are kept for the
+    /// `("debug", "particle_timeline")` builtin;
+    /// no value disables timeline collection entirely
+    pub particle_timeline_retention: Option<Duration>,
+
+    /// At most this many particles keep a timeline at once;
+    /// the oldest ones are evicted first
+    pub particle_timeline_max_particles: usize,
+
+    /// Base URL of an IPFS gateway the `ipfs.cat` builtin fetches content
+    /// from; no value disables the builtin
+    pub ipfs_gateway: Option<String>,
+
+    /// Base URL of an IPFS HTTP API the `ipfs.add` builtin adds content
+    /// through; no value disables the builtin
+    pub ipfs_api: Option<String>,
+
+    /// Timeout of a single HTTP request made by the `ipfs` builtins
+    pub ipfs_request_timeout: Duration,
+
+    /// How many times failed particle routing effects are retried
+    /// before the particle is given up on
+    pub effect_retries: usize,
+
     #[serde(serialize_with = "peer_id::serde::serialize")]
     pub management_peer_id: PeerId,
 
@@ -517,6 +692,17 @@ pub struct MetricsConfig {
 
     #[serde(default = "default_tokio_metrics_poll_histogram_enabled")]
     pub tokio_metrics_poll_histogram_enabled: bool,
+
+    /// Togglable metric families that start disabled; they can be switched
+    /// back on at runtime via the `stat.metrics_config` builtin
+    #[serde(default)]
+    pub disabled_metric_families: Vec<String>,
+
+    /// When set, a service whose estimated call rate exceeds this many calls
+    /// per second is reported in a log warning. Observability only,
+    /// the calls themselves are never blocked
+    #[serde(default)]
+    pub service_call_rate_warn_threshold: Option<f64>,
 }
 
 #[derive(Clone, Deserialize, Serialize, Derivative)]
@@ -625,6 +811,9 @@ impl KeypairConfig {
 #[derivative(Debug)]
 pub struct ChainConfig {
     pub http_endpoint: String,
+    /// Backup RPC endpoints to fail over to when `http_endpoint` is unhealthy
+    #[serde(default)]
+    pub http_backup_endpoints: Vec<String>,
     // TODO get all addresses from Core contract
     pub core_contract_address: String,
     pub cc_contract_address: String,
@@ -641,11 +830,20 @@ pub struct ChainConfig {
 #[derivative(Debug)]
 pub struct ChainListenerConfig {
     pub ws_endpoint: String,
+    /// Backup websocket endpoints to fail over to when `ws_endpoint` is unhealthy
+    #[serde(default)]
+    pub ws_backup_endpoints: Vec<String>,
     pub ccp_endpoint: Option<String>,
     /// How often to poll proofs
     #[serde(default = "default_proof_poll_period")]
     #[serde(with = "humantime_serde")]
     pub proof_poll_period: Duration,
+    /// How many blocks the chain head must be past an event before the node
+    /// acts on it, so a reorg within that window can't leave the node with
+    /// state for blocks that are no longer canonical; 0 means events are
+    /// processed as soon as they are seen
+    #[serde(default = "default_confirmation_depth")]
+    pub confirmation_depth: u64,
 }
 
 /// Name of the effector module
@@ -688,11 +886,16 @@ pub struct DevModeConfig {
     /// Mounted binaries mapping: binary name (used in the effector modules) to binary path
     #[serde(default = "default_binaries_mapping")]
     pub binaries: BTreeMap<String, String>,
+    /// Fail on startup when the persisted core state doesn't match the config
+    /// instead of silently rebuilding the core mapping from scratch
+    #[serde(default)]
+    pub strict_core_state: bool,
 }
 
 fn default_dev_mode_config() -> DevModeConfig {
     DevModeConfig {
         enable: false,
         binaries: default_binaries_mapping(),
+        strict_core_state: false,
     }
 }
@@ -20,4 +20,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ServicesConfig {
     pub wasm_backend: WasmBackendConfig,
+    /// Compat: let worker-scoped service resolution fall back to host services
+    /// the way it did before scopes became strict; goes away in the next release
+    #[serde(default)]
+    pub permissive_scope_resolution: bool,
 }
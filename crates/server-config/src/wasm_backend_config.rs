@@ -30,6 +30,12 @@ pub struct WasmBackendConfig {
     /// Enables the epoch interruption mechanism
     #[serde(with = "humantime_serde")]
     pub epoch_interruption_duration: Option<Duration>,
+    /// Keep compiled modules on disk and reuse them between service creations.
+    #[serde(default = "default_module_cache_enabled")]
+    pub module_cache_enabled: bool,
+    /// Soft cap on the total size of compiled modules kept on disk.
+    #[serde(default = "default_module_cache_size_limit")]
+    pub module_cache_size_limit: bytesize::ByteSize,
 }
 
 impl Default for WasmBackendConfig {
@@ -40,6 +46,16 @@ impl Default for WasmBackendConfig {
             async_wasm_stack: bytesize::ByteSize::mb(4),
             max_wasm_stack: bytesize::ByteSize::mb(2),
             epoch_interruption_duration: Some(Duration::from_secs(1)),
+            module_cache_enabled: default_module_cache_enabled(),
+            module_cache_size_limit: default_module_cache_size_limit(),
         }
     }
 }
+
+fn default_module_cache_enabled() -> bool {
+    true
+}
+
+fn default_module_cache_size_limit() -> bytesize::ByteSize {
+    bytesize::ByteSize::mb(512)
+}
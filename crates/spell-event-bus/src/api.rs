@@ -16,8 +16,10 @@
 
 use connection_pool::LifecycleEvent;
 use fluence_libp2p::PeerId;
+use particle_services::ServiceLifecycleEvent;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 use types::peer_id;
@@ -38,6 +40,10 @@ pub enum TriggerInfo {
     Timer(TimerEvent),
     /// Event is triggered by a peer event.
     Peer(PeerEvent),
+    /// Event is triggered by a local service lifecycle event.
+    Service(ServiceEvent),
+    /// Event is triggered by an ad-hoc delayed trigger scheduled via `schedule_delayed`.
+    Delayed(DelayedEvent),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -45,6 +51,15 @@ pub struct TimerEvent {
     pub timestamp: u64,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Event is triggered by a one-shot delayed trigger
+pub struct DelayedEvent {
+    pub timestamp: u64,
+    /// Token the trigger was scheduled with; lets the spell match the event
+    /// to the schedule request
+    pub token: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 /// Event is triggered by connection pool event
 pub struct PeerEvent {
@@ -88,12 +103,64 @@ pub enum PeerEventType {
     Disconnected,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Event is triggered by a service being created or removed on the node
+pub struct ServiceEvent {
+    pub service_id: String,
+    pub created: bool,
+}
+
+impl From<ServiceLifecycleEvent> for ServiceEvent {
+    fn from(e: ServiceLifecycleEvent) -> Self {
+        match e {
+            ServiceLifecycleEvent::Created { service_id } => Self {
+                service_id,
+                created: true,
+            },
+            ServiceLifecycleEvent::Removed { service_id } => Self {
+                service_id,
+                created: false,
+            },
+        }
+    }
+}
+
+impl ServiceEvent {
+    pub(crate) fn get_type(&self) -> ServiceEventType {
+        if self.created {
+            ServiceEventType::Created
+        } else {
+            ServiceEventType::Removed
+        }
+    }
+}
+
+/// Types of service events that are available for subscription.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub enum ServiceEventType {
+    Created,
+    Removed,
+}
+
+/// Events the bus consumes from its sources.
+#[derive(Clone, Debug)]
+pub enum SourceEvent {
+    Peer(PeerEvent),
+    Service(ServiceEvent),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TriggerInfoAqua {
     // Vec is a representation for Aqua optional values. This Vec always holds at most 1 element.
     timer: Vec<TimerEvent>,
     // Vec is a representation for Aqua optional values. This Vec always holds at most 1 element.
     peer: Vec<PeerEvent>,
+    // Vec is a representation for Aqua optional values. This Vec always holds at most 1 element.
+    #[serde(default)]
+    service: Vec<ServiceEvent>,
+    // Vec is a representation for Aqua optional values. This Vec always holds at most 1 element.
+    #[serde(default)]
+    delayed: Vec<DelayedEvent>,
 }
 
 impl From<TriggerInfo> for TriggerInfoAqua {
@@ -101,11 +168,28 @@ impl From<TriggerInfo> for TriggerInfoAqua {
         match i {
             TriggerInfo::Timer(t) => Self {
                 timer: vec![t],
-                peer: vec![], // Empty Vec corresponds to Aqua nil
+                // Empty Vec corresponds to Aqua nil
+                peer: vec![],
+                service: vec![],
+                delayed: vec![],
             },
             TriggerInfo::Peer(p) => Self {
-                timer: vec![], // Empty Vec corresponds to Aqua nil
+                timer: vec![],
                 peer: vec![p],
+                service: vec![],
+                delayed: vec![],
+            },
+            TriggerInfo::Service(s) => Self {
+                timer: vec![],
+                peer: vec![],
+                service: vec![s],
+                delayed: vec![],
+            },
+            TriggerInfo::Delayed(d) => Self {
+                timer: vec![],
+                peer: vec![],
+                service: vec![],
+                delayed: vec![d],
             },
         }
     }
@@ -113,10 +197,17 @@ impl From<TriggerInfo> for TriggerInfoAqua {
 
 impl From<TriggerInfoAqua> for TriggerInfo {
     fn from(i: TriggerInfoAqua) -> Self {
-        match (i.timer.first(), i.peer.first()) {
-            (Some(t), None) => Self::Timer(t.clone()),
-            (None, Some(p)) => Self::Peer(p.clone()),
-            _ => unreachable!("TriggerInfoAqua should always have either timer or peer event"),
+        match (
+            i.timer.first(),
+            i.peer.first(),
+            i.service.first(),
+            i.delayed.first(),
+        ) {
+            (Some(t), None, None, None) => Self::Timer(t.clone()),
+            (None, Some(p), None, None) => Self::Peer(p.clone()),
+            (None, None, Some(s), None) => Self::Service(s.clone()),
+            (None, None, None, Some(d)) => Self::Delayed(d.clone()),
+            _ => unreachable!("TriggerInfoAqua should always have exactly one event kind"),
         }
     }
 }
@@ -133,10 +224,23 @@ pub enum Action {
     Subscribe(SpellId, SpellTriggerConfigs),
     /// Remove all subscriptions of a spell
     Unsubscribe(SpellId),
+    /// Schedule a one-shot delayed trigger for a spell
+    ScheduleDelayed(DelayedTriggerConfig),
+    /// Cancel a pending delayed trigger of a spell by its token
+    CancelDelayed(SpellId, String),
     /// Actually start the scheduling
     Start,
 }
 
+/// A one-shot trigger that fires once after a delay
+#[derive(Debug, Clone)]
+pub struct DelayedTriggerConfig {
+    pub spell_id: SpellId,
+    /// Cancellation token; also delivered in the trigger payload
+    pub token: String,
+    pub delay: Duration,
+}
+
 #[derive(Error, Debug)]
 pub enum EventBusError {
     #[error("can't send a command `{action:?}` to spell-event-bus: {reason}")]
@@ -189,10 +293,39 @@ impl SpellEventBusApi {
     }
 
     /// Unsubscribe a spell from all events.
+    /// Pending delayed triggers of the spell are cancelled as well.
     pub async fn unsubscribe(&self, spell_id: SpellId) -> Result<(), EventBusError> {
         self.send(Action::Unsubscribe(spell_id)).await
     }
 
+    /// Schedule a one-shot trigger that fires for the spell once after `delay`.
+    /// Returns a token the trigger can be cancelled with via [`Self::cancel_delayed`];
+    /// the same token is delivered in the trigger payload.
+    pub async fn schedule_delayed(
+        &self,
+        spell_id: SpellId,
+        delay: Duration,
+    ) -> Result<String, EventBusError> {
+        let token = uuid_utils::uuid();
+        self.send(Action::ScheduleDelayed(DelayedTriggerConfig {
+            spell_id,
+            token: token.clone(),
+            delay,
+        }))
+        .await?;
+        Ok(token)
+    }
+
+    /// Cancel a pending delayed trigger by the token returned from [`Self::schedule_delayed`].
+    /// Cancelling an already fired or unknown token is a no-op.
+    pub async fn cancel_delayed(
+        &self,
+        spell_id: SpellId,
+        token: String,
+    ) -> Result<(), EventBusError> {
+        self.send(Action::CancelDelayed(spell_id, token)).await
+    }
+
     pub async fn start_scheduling(&self) -> Result<(), EventBusError> {
         self.send(Action::Start).await
     }
@@ -15,13 +15,16 @@
  */
 
 use crate::api::*;
-use crate::config::{SpellTriggerConfigs, TriggerConfig};
+use crate::config::{PeerEventConfig, ServiceEventConfig, SpellTriggerConfigs, TriggerConfig};
+use fluence_libp2p::PeerId;
 use futures::stream::BoxStream;
 use futures::StreamExt;
 use futures::{future, FutureExt};
-use peer_metrics::SpellMetrics;
+use peer_metrics::{SpellEventBusMetrics, SpellMetrics};
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
@@ -32,30 +35,54 @@ use tokio::task;
 use tracing::Instrument;
 
 struct PeerEventSubscribers {
+    /// Subscribers without a peer filter receive every event of the type
     subscribers: HashMap<PeerEventType, Vec<Arc<SpellId>>>,
+    /// Subscribers filtered by peer id, indexed by (event type, peer id)
+    /// so dispatch doesn't scan subscriptions of unrelated peers
+    filtered_subscribers: HashMap<(PeerEventType, PeerId), Vec<Arc<SpellId>>>,
 }
 
 impl PeerEventSubscribers {
     fn new() -> Self {
         Self {
             subscribers: HashMap::new(),
+            filtered_subscribers: HashMap::new(),
         }
     }
 
-    fn add(&mut self, spell_id: Arc<SpellId>, event_types: Vec<PeerEventType>) {
-        for event_type in event_types {
-            self.subscribers
-                .entry(event_type)
-                .or_default()
-                .push(spell_id.clone());
+    fn add(&mut self, spell_id: Arc<SpellId>, config: &PeerEventConfig) {
+        for event_type in &config.events {
+            match &config.peer_ids {
+                None => {
+                    self.subscribers
+                        .entry(event_type.clone())
+                        .or_default()
+                        .push(spell_id.clone());
+                }
+                Some(peer_ids) => {
+                    for peer_id in peer_ids {
+                        self.filtered_subscribers
+                            .entry((event_type.clone(), *peer_id))
+                            .or_default()
+                            .push(spell_id.clone());
+                    }
+                }
+            }
         }
     }
 
-    fn get(&self, event_type: &PeerEventType) -> impl Iterator<Item = &Arc<SpellId>> {
+    fn get(&self, event: &PeerEvent) -> impl Iterator<Item = &Arc<SpellId>> {
+        let event_type = event.get_type();
+        let filtered = self
+            .filtered_subscribers
+            .get(&(event_type.clone(), event.peer_id))
+            .map(|x| x.iter())
+            .unwrap_or_else(|| [].iter());
         self.subscribers
-            .get(event_type)
+            .get(&event_type)
             .map(|x| x.iter())
             .unwrap_or_else(|| [].iter())
+            .chain(filtered)
     }
 
     /// Returns true if spell_id was removed from subscribers
@@ -63,6 +90,61 @@ impl PeerEventSubscribers {
         for subscribers in self.subscribers.values_mut() {
             subscribers.retain(|sub_id| **sub_id != *spell_id);
         }
+        for subscribers in self.filtered_subscribers.values_mut() {
+            subscribers.retain(|sub_id| **sub_id != *spell_id);
+        }
+    }
+}
+
+/// A spell subscription to service events, optionally filtered by a service id
+struct ServiceEventSubscriber {
+    spell_id: Arc<SpellId>,
+    service_id: Option<String>,
+}
+
+struct ServiceEventSubscribers {
+    subscribers: HashMap<ServiceEventType, Vec<ServiceEventSubscriber>>,
+}
+
+impl ServiceEventSubscribers {
+    fn new() -> Self {
+        Self {
+            subscribers: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, spell_id: Arc<SpellId>, config: &ServiceEventConfig) {
+        for event_type in &config.events {
+            self.subscribers
+                .entry(event_type.clone())
+                .or_default()
+                .push(ServiceEventSubscriber {
+                    spell_id: spell_id.clone(),
+                    service_id: config.service_id.clone(),
+                });
+        }
+    }
+
+    /// Returns subscribers of the event's type whose service id filter
+    /// (if any) matches the event's service id
+    fn get<'a>(&'a self, event: &'a ServiceEvent) -> impl Iterator<Item = &'a Arc<SpellId>> {
+        self.subscribers
+            .get(&event.get_type())
+            .map(|x| x.iter())
+            .unwrap_or_else(|| [].iter())
+            .filter(move |subscriber| {
+                subscriber
+                    .service_id
+                    .as_ref()
+                    .map_or(true, |service_id| *service_id == event.service_id)
+            })
+            .map(|subscriber| &subscriber.spell_id)
+    }
+
+    fn remove(&mut self, spell_id: &SpellId) {
+        for subscribers in self.subscribers.values_mut() {
+            subscribers.retain(|subscriber| *subscriber.spell_id != *spell_id);
+        }
     }
 }
 
@@ -73,15 +155,40 @@ struct Periodic {
     end_at: Option<Instant>,
 }
 
+/// An ad-hoc one-shot trigger scheduled via `schedule_delayed`
+#[derive(Debug, PartialEq, Eq)]
+struct Delayed {
+    id: Arc<SpellId>,
+    /// Token the trigger can be cancelled by; delivered in the trigger payload
+    token: String,
+    /// Unix timestamp in ms at which the trigger fires; kept for persistence
+    fire_at_ms: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ScheduledTask {
+    Periodic(Periodic),
+    Delayed(Delayed),
+}
+
+impl ScheduledTask {
+    fn spell_id(&self) -> &Arc<SpellId> {
+        match self {
+            ScheduledTask::Periodic(periodic) => &periodic.id,
+            ScheduledTask::Delayed(delayed) => &delayed.id,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct Scheduled {
-    data: Periodic,
+    data: ScheduledTask,
     /// the time after which we need to notify the subscriber
     run_at: Instant,
 }
 
 impl Scheduled {
-    fn new(data: Periodic, run_at: Instant) -> Self {
+    fn new(data: ScheduledTask, run_at: Instant) -> Self {
         Self { data, run_at }
     }
 
@@ -94,7 +201,10 @@ impl Scheduled {
             return None;
         }
 
-        Some(Scheduled { data, run_at })
+        Some(Scheduled {
+            data: ScheduledTask::Periodic(data),
+            run_at,
+        })
     }
 }
 
@@ -113,6 +223,7 @@ impl PartialOrd for Scheduled {
 
 struct SubscribersState {
     subscribers: PeerEventSubscribers,
+    service_subscribers: ServiceEventSubscribers,
     scheduled: BinaryHeap<Scheduled>,
     active: HashSet<Arc<SpellId>>,
 }
@@ -121,6 +232,7 @@ impl SubscribersState {
     fn new() -> Self {
         Self {
             subscribers: PeerEventSubscribers::new(),
+            service_subscribers: ServiceEventSubscribers::new(),
             scheduled: BinaryHeap::new(),
             active: HashSet::new(),
         }
@@ -136,28 +248,76 @@ impl SubscribersState {
                         period: config.period,
                         end_at: config.end_at,
                     };
-                    let scheduled = Scheduled::new(periodic, config.start_at);
+                    let scheduled =
+                        Scheduled::new(ScheduledTask::Periodic(periodic), config.start_at);
                     self.scheduled.push(scheduled);
                 }
                 TriggerConfig::PeerEvent(config) => {
-                    self.subscribers
-                        .add(spell_id.clone(), config.events.clone());
+                    self.subscribers.add(spell_id.clone(), config);
+                }
+                TriggerConfig::ServiceEvent(config) => {
+                    self.service_subscribers.add(spell_id.clone(), config);
                 }
             }
         }
         self.active.insert(spell_id);
     }
 
+    /// Schedule a one-shot delayed trigger of a spell
+    fn schedule_delayed(
+        &mut self,
+        spell_id: SpellId,
+        token: String,
+        run_at: Instant,
+        fire_at_ms: u64,
+    ) {
+        let delayed = Delayed {
+            id: Arc::new(spell_id),
+            token,
+            fire_at_ms,
+        };
+        self.scheduled
+            .push(Scheduled::new(ScheduledTask::Delayed(delayed), run_at));
+    }
+
+    /// Remove a pending delayed trigger of a spell by its token; no-op if there is none
+    fn cancel_delayed(&mut self, spell_id: &SpellId, token: &str) {
+        self.scheduled.retain(|scheduled| match &scheduled.data {
+            ScheduledTask::Delayed(delayed) => {
+                *delayed.id != *spell_id || delayed.token != token
+            }
+            ScheduledTask::Periodic(_) => true,
+        });
+    }
+
+    /// Pending delayed triggers, in no particular order
+    fn pending_delayed(&self) -> impl Iterator<Item = &Delayed> {
+        self.scheduled
+            .iter()
+            .filter_map(|scheduled| match &scheduled.data {
+                ScheduledTask::Delayed(delayed) => Some(delayed),
+                ScheduledTask::Periodic(_) => None,
+            })
+    }
+
     /// Returns true if spell_id was removed from subscribers
     fn unsubscribe(&mut self, spell_id: &SpellId) {
         self.active.remove(spell_id);
         self.scheduled
-            .retain(|scheduled| *scheduled.data.id != *spell_id);
+            .retain(|scheduled| **scheduled.data.spell_id() != *spell_id);
         self.subscribers.remove(spell_id);
+        self.service_subscribers.remove(spell_id);
     }
 
-    fn subscribers(&self, event_type: &PeerEventType) -> impl Iterator<Item = &Arc<SpellId>> {
-        self.subscribers.get(event_type)
+    fn subscribers(&self, event: &PeerEvent) -> impl Iterator<Item = &Arc<SpellId>> {
+        self.subscribers.get(event)
+    }
+
+    fn service_subscribers<'a>(
+        &'a self,
+        event: &'a ServiceEvent,
+    ) -> impl Iterator<Item = &'a Arc<SpellId>> {
+        self.service_subscribers.get(event)
     }
 
     fn next_scheduled_in(&self, now: Instant) -> Option<Duration> {
@@ -178,21 +338,44 @@ enum BusInternalError {
     SendEvent(SpellId, TriggerInfo, Pin<Box<dyn std::error::Error>>),
 }
 
+/// Controls persistence of pending delayed triggers across restarts
+#[derive(Debug, Clone)]
+pub struct DelayedTriggersPersistenceConfig {
+    /// File pending delayed triggers are saved to
+    pub path: PathBuf,
+    /// What to do with triggers whose fire time passed while the bus was down:
+    /// fire them immediately on reload when true, drop them otherwise
+    pub fire_missed_on_reload: bool,
+}
+
+/// On-disk representation of a pending delayed trigger
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedDelayedTrigger {
+    spell_id: SpellId,
+    token: String,
+    fire_at_ms: u64,
+}
+
 pub struct SpellEventBus {
     /// List of events producers.
-    sources: Vec<BoxStream<'static, PeerEvent>>,
+    sources: Vec<BoxStream<'static, SourceEvent>>,
     /// API connections
     recv_cmd_channel: mpsc::UnboundedReceiver<Command>,
     /// Notify when trigger happened
     send_events: mpsc::UnboundedSender<TriggerEvent>,
     /// Spell metrics
     spell_metrics: Option<SpellMetrics>,
+    /// Trigger counts and delays per spell
+    bus_metrics: Option<SpellEventBusMetrics>,
+    /// Pending delayed triggers storage; when unset, delayed triggers don't survive restarts
+    persistence: Option<DelayedTriggersPersistenceConfig>,
 }
 
 impl SpellEventBus {
     pub fn new(
         spell_metrics: Option<SpellMetrics>,
-        sources: Vec<BoxStream<'static, PeerEvent>>,
+        bus_metrics: Option<SpellEventBusMetrics>,
+        sources: Vec<BoxStream<'static, SourceEvent>>,
     ) -> (
         Self,
         SpellEventBusApi,
@@ -208,10 +391,21 @@ impl SpellEventBus {
             recv_cmd_channel,
             send_events,
             spell_metrics,
+            bus_metrics,
+            persistence: None,
         };
         (this, api, recv_events)
     }
 
+    /// Persist pending delayed triggers to the given file so they survive restarts
+    pub fn with_delayed_triggers_persistence(
+        mut self,
+        config: DelayedTriggersPersistenceConfig,
+    ) -> Self {
+        self.persistence = Some(config);
+        self
+    }
+
     pub fn start(self) -> task::JoinHandle<()> {
         task::Builder::new()
             .name("spell-bus")
@@ -230,6 +424,9 @@ impl SpellEventBus {
         let mut sources_channel = futures::stream::select_all(sources);
 
         let mut state = SubscribersState::new();
+        Self::load_persisted_delayed(&self.persistence, &mut state);
+        // drop the triggers skipped on reload from the file
+        Self::persist_delayed(&self.persistence, &state);
         let mut is_started = false;
         loop {
             let now = Instant::now();
@@ -273,6 +470,32 @@ impl SpellEventBus {
                             Action::Unsubscribe(spell_id) => {
                                 log::trace!("Unsubscribe {spell_id}");
                                 state.unsubscribe(spell_id);
+                                Self::persist_delayed(&self.persistence, &state);
+                            },
+                            Action::ScheduleDelayed(config) => {
+                                log::trace!("Schedule a delayed trigger {} for {}", config.token, config.spell_id);
+                                match Instant::now().checked_add(config.delay) {
+                                    Some(run_at) => {
+                                        let fire_at_ms = (now_millis::now_ms() as u64)
+                                            .saturating_add(config.delay.as_millis() as u64);
+                                        state.schedule_delayed(
+                                            config.spell_id.clone(),
+                                            config.token.clone(),
+                                            run_at,
+                                            fire_at_ms,
+                                        );
+                                        Self::persist_delayed(&self.persistence, &state);
+                                    }
+                                    None => log::warn!(
+                                        "ignoring delayed trigger {} for {}: the delay is too big",
+                                        config.token, config.spell_id
+                                    ),
+                                }
+                            },
+                            Action::CancelDelayed(spell_id, token) => {
+                                log::trace!("Cancel the delayed trigger {token} of {spell_id}");
+                                state.cancel_delayed(spell_id, token);
+                                Self::persist_delayed(&self.persistence, &state);
                             },
                             Action::Start => {
                                 log::trace!("Start the bus");
@@ -282,11 +505,32 @@ impl SpellEventBus {
                         reply.send(()).map_err(|_| {
                             BusInternalError::Reply(action)
                         })?;
+                        if let Some(m) = &self.bus_metrics {
+                            // until scheduling is started, every subscribed spell is on hold
+                            let paused = if is_started { 0 } else { state.active.len() as i64 };
+                            m.paused_spells.set(paused);
+                        }
                     },
                     Some(event) = sources_channel.next(), if is_started => {
-                        for spell_id in state.subscribers(&event.get_type()) {
-                            let event = TriggerInfo::Peer(event.clone());
-                            Self::trigger_spell(&send_events, spell_id, event)?;
+                        match event {
+                            SourceEvent::Peer(event) => {
+                                for spell_id in state.subscribers(&event) {
+                                    let event = TriggerInfo::Peer(event.clone());
+                                    if let Some(m) = &self.bus_metrics {
+                                        m.observe_trigger(spell_id, None);
+                                    }
+                                    Self::trigger_spell(&send_events, spell_id, event)?;
+                                }
+                            },
+                            SourceEvent::Service(event) => {
+                                for spell_id in state.service_subscribers(&event) {
+                                    let event = TriggerInfo::Service(event.clone());
+                                    if let Some(m) = &self.bus_metrics {
+                                        m.observe_trigger(spell_id, None);
+                                    }
+                                    Self::trigger_spell(&send_events, spell_id, event)?;
+                                }
+                            },
                         }
                     },
                     _ = timer_task, if is_started => {
@@ -294,16 +538,30 @@ impl SpellEventBus {
                         if let Some(scheduled_spell) = state.scheduled.pop() {
                             log::trace!("Execute: {:?}", scheduled_spell);
                             let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
-                            let spell_id = scheduled_spell.data.id.clone();
-                            Self::trigger_spell(&send_events, &scheduled_spell.data.id, TriggerInfo::Timer(TimerEvent{ timestamp }))?;
-                            // Do not reschedule the spell otherwise.
-                            if let Some(rescheduled) = Scheduled::at(scheduled_spell.data, Instant::now()) {
-                                log::trace!("Reschedule: {:?}", rescheduled);
-                                state.scheduled.push(rescheduled);
-                            } else {
-                                state.active.remove(&spell_id);
-                                if let Some(m) = &self.spell_metrics {
-                                    m.observe_finished_spell();
+                            let spell_id = scheduled_spell.data.spell_id().clone();
+                            if let Some(m) = &self.bus_metrics {
+                                // how late the trigger fired relative to its schedule
+                                m.observe_trigger(&spell_id, Some(scheduled_spell.run_at.elapsed()));
+                            }
+                            match scheduled_spell.data {
+                                ScheduledTask::Periodic(data) => {
+                                    Self::trigger_spell(&send_events, &spell_id, TriggerInfo::Timer(TimerEvent{ timestamp }))?;
+                                    // Do not reschedule the spell otherwise.
+                                    if let Some(rescheduled) = Scheduled::at(data, Instant::now()) {
+                                        log::trace!("Reschedule: {:?}", rescheduled);
+                                        state.scheduled.push(rescheduled);
+                                    } else {
+                                        state.active.remove(&spell_id);
+                                        if let Some(m) = &self.spell_metrics {
+                                            m.observe_finished_spell();
+                                        }
+                                    }
+                                }
+                                ScheduledTask::Delayed(delayed) => {
+                                    // Delayed triggers are one-shot and never rescheduled
+                                    let event = DelayedEvent { timestamp, token: delayed.token };
+                                    Self::trigger_spell(&send_events, &spell_id, TriggerInfo::Delayed(event))?;
+                                    Self::persist_delayed(&self.persistence, &state);
                                 }
                             }
                         }
@@ -330,6 +588,87 @@ impl SpellEventBus {
             .map_err(|e| BusInternalError::SendEvent((**id).clone(), event, Box::pin(e)))?;
         Ok(())
     }
+
+    /// Reload pending delayed triggers persisted by a previous run.
+    /// Triggers whose fire time has already passed either fire immediately
+    /// or are dropped, depending on the configured policy.
+    fn load_persisted_delayed(
+        persistence: &Option<DelayedTriggersPersistenceConfig>,
+        state: &mut SubscribersState,
+    ) {
+        let persistence = match persistence {
+            Some(persistence) => persistence,
+            None => return,
+        };
+        let content = match std::fs::read(&persistence.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+            Err(err) => {
+                log::warn!(
+                    "failed to read persisted delayed triggers from {:?}: {}",
+                    persistence.path,
+                    err
+                );
+                return;
+            }
+        };
+        let triggers: Vec<PersistedDelayedTrigger> = match serde_json::from_slice(&content) {
+            Ok(triggers) => triggers,
+            Err(err) => {
+                log::warn!(
+                    "failed to parse persisted delayed triggers from {:?}: {}",
+                    persistence.path,
+                    err
+                );
+                return;
+            }
+        };
+        let now = Instant::now();
+        let now_ms = now_millis::now_ms() as u64;
+        for trigger in triggers {
+            if trigger.fire_at_ms <= now_ms && !persistence.fire_missed_on_reload {
+                log::info!(
+                    "dropping delayed trigger {} of spell {}: its fire time passed while the bus was down",
+                    trigger.token,
+                    trigger.spell_id
+                );
+                continue;
+            }
+            let run_at = now + Duration::from_millis(trigger.fire_at_ms.saturating_sub(now_ms));
+            state.schedule_delayed(trigger.spell_id, trigger.token, run_at, trigger.fire_at_ms);
+        }
+    }
+
+    /// Save pending delayed triggers so they can be rescheduled after a restart
+    fn persist_delayed(
+        persistence: &Option<DelayedTriggersPersistenceConfig>,
+        state: &SubscribersState,
+    ) {
+        let persistence = match persistence {
+            Some(persistence) => persistence,
+            None => return,
+        };
+        let triggers = state
+            .pending_delayed()
+            .map(|delayed| PersistedDelayedTrigger {
+                spell_id: (*delayed.id).clone(),
+                token: delayed.token.clone(),
+                fire_at_ms: delayed.fire_at_ms,
+            })
+            .collect::<Vec<_>>();
+        let result = serde_json::to_vec(&triggers)
+            .map_err(|err| err.to_string())
+            .and_then(|content| {
+                std::fs::write(&persistence.path, content).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            log::warn!(
+                "failed to persist pending delayed triggers to {:?}: {}",
+                persistence.path,
+                err
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -338,7 +677,7 @@ mod tests {
     use connection_pool::LifecycleEvent;
     use futures::StreamExt;
     use libp2p::PeerId;
-    use maplit::hashmap;
+    use maplit::{hashmap, hashset};
     use particle_protocol::Contact;
     use std::assert_matches::assert_matches;
     use std::time::Duration;
@@ -359,16 +698,28 @@ mod tests {
         }
     }
 
-    fn send_connect_event(sender: &mpsc::UnboundedSender<PeerEvent>, peer_id: PeerId) {
+    fn send_connect_event(sender: &mpsc::UnboundedSender<SourceEvent>, peer_id: PeerId) {
+        sender
+            .send(SourceEvent::Peer(PeerEvent::from(
+                LifecycleEvent::Connected(Contact::new(peer_id, Vec::new())),
+            )))
+            .unwrap();
+    }
+
+    fn send_service_event(
+        sender: &mpsc::UnboundedSender<SourceEvent>,
+        service_id: &str,
+        created: bool,
+    ) {
         sender
-            .send(PeerEvent::from(LifecycleEvent::Connected(Contact::new(
-                peer_id,
-                Vec::new(),
-            ))))
+            .send(SourceEvent::Service(ServiceEvent {
+                service_id: service_id.to_string(),
+                created,
+            }))
             .unwrap();
     }
 
-    fn emulate_connect(period: Duration) -> (mpsc::UnboundedReceiver<PeerEvent>, JoinHandle<()>) {
+    fn emulate_connect(period: Duration) -> (mpsc::UnboundedReceiver<SourceEvent>, JoinHandle<()>) {
         let (send, recv) = mpsc::unbounded_channel();
         let hdl = task::Builder::new()
             .name("emulate_connect")
@@ -391,17 +742,43 @@ mod tests {
         api: &SpellEventBusApi,
         spell_id: SpellId,
         events: Vec<PeerEventType>,
+    ) {
+        subscribe_filtered_peer_event(api, spell_id, events, None).await;
+    }
+
+    async fn subscribe_filtered_peer_event(
+        api: &SpellEventBusApi,
+        spell_id: SpellId,
+        events: Vec<PeerEventType>,
+        peer_ids: Option<HashSet<PeerId>>,
     ) {
         api.subscribe(
             spell_id,
             SpellTriggerConfigs {
-                triggers: vec![TriggerConfig::PeerEvent(PeerEventConfig { events })],
+                triggers: vec![TriggerConfig::PeerEvent(PeerEventConfig {
+                    events,
+                    peer_ids,
+                })],
             },
         )
         .await
         .expect("Could not subscribe peer event");
     }
 
+    async fn subscribe_service_event(
+        api: &SpellEventBusApi,
+        spell_id: SpellId,
+        events: Vec<ServiceEventType>,
+        service_id: Option<String>,
+    ) {
+        api.subscribe(
+            spell_id,
+            SpellTriggerConfigs::for_service_events(events, service_id),
+        )
+        .await
+        .expect("Could not subscribe service event");
+    }
+
     async fn subscribe_timer(api: &SpellEventBusApi, spell_id: SpellId, config: TimerConfig) {
         api.subscribe(
             spell_id,
@@ -432,7 +809,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_subscribe_one() {
-        let (bus, api, event_receiver) = SpellEventBus::new(None, vec![]);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![]);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
         let event_stream = UnboundedReceiverStream::new(event_receiver);
@@ -455,9 +832,40 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_triggers_fired_metric() {
+        use peer_metrics::SpellLabel;
+        use prometheus_client::registry::Registry;
+
+        let mut registry = Registry::default();
+        let metrics = SpellEventBusMetrics::new(&mut registry);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, Some(metrics.clone()), vec![]);
+        let bus = bus.start();
+        let _ = api.start_scheduling().await;
+        let event_stream = UnboundedReceiverStream::new(event_receiver);
+
+        let spell1_id = "spell1".to_string();
+        subscribe_periodic_endless(&api, spell1_id.clone(), Duration::from_millis(10)).await;
+
+        // wait for 5 triggers to be delivered
+        let events = event_stream.take(5).collect::<Vec<TriggerEvent>>().await;
+        try_catch(
+            || {
+                assert_eq!(events.len(), 5);
+                let label = SpellLabel {
+                    spell_id: spell1_id.clone(),
+                };
+                assert_eq!(metrics.triggers_fired.get_or_create(&label).get(), 5);
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
     #[tokio::test]
     async fn test_subscribe_many() {
-        let (bus, api, event_receiver) = SpellEventBus::new(None, vec![]);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![]);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
         let event_stream = UnboundedReceiverStream::new(event_receiver);
@@ -491,7 +899,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_subscribe_oneshot() {
-        let (bus, api, event_receiver) = SpellEventBus::new(None, vec![]);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![]);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
         let event_stream = UnboundedReceiverStream::new(event_receiver);
@@ -523,7 +931,7 @@ mod tests {
     async fn test_subscribe_connect() {
         let (send, recv) = mpsc::unbounded_channel();
         let recv = UnboundedReceiverStream::new(recv).boxed();
-        let (bus, api, event_receiver) = SpellEventBus::new(None, vec![recv]);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![recv]);
         let mut event_stream = UnboundedReceiverStream::new(event_receiver);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
@@ -550,11 +958,126 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_peer_event_filtered_by_peer_id() {
+        let (send, recv) = mpsc::unbounded_channel();
+        let recv = UnboundedReceiverStream::new(recv).boxed();
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![recv]);
+        let bus = bus.start();
+        let _ = api.start_scheduling().await;
+
+        let watched_peer = PeerId::random();
+        let other_peer = PeerId::random();
+
+        let spell1_id = "spell1".to_string();
+        subscribe_filtered_peer_event(
+            &api,
+            spell1_id.clone(),
+            vec![PeerEventType::Connected],
+            Some(hashset! {watched_peer}),
+        )
+        .await;
+
+        // events of peers outside the filter must not trigger the spell
+        send_connect_event(&send, other_peer);
+        send_connect_event(&send, watched_peer);
+
+        let event = event_receiver.recv().await.unwrap();
+        let no_more_events = event_receiver.try_recv().is_err();
+        try_catch(
+            || {
+                assert_eq!(event.spell_id, spell1_id.clone());
+                assert_matches!(
+                    event.info,
+                    TriggerInfo::Peer(p) if p.peer_id == watched_peer
+                );
+                assert!(
+                    no_more_events,
+                    "events of peers outside the filter must not be delivered"
+                );
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_service_event() {
+        let (send, recv) = mpsc::unbounded_channel();
+        let recv = UnboundedReceiverStream::new(recv).boxed();
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![recv]);
+        let mut event_stream = UnboundedReceiverStream::new(event_receiver);
+        let bus = bus.start();
+        let _ = api.start_scheduling().await;
+
+        let spell1_id = "spell1".to_string();
+        subscribe_service_event(
+            &api,
+            spell1_id.clone(),
+            vec![ServiceEventType::Created, ServiceEventType::Removed],
+            None,
+        )
+        .await;
+
+        send_service_event(&send, "service1", true);
+        let event = event_stream.next().await.unwrap();
+        try_catch(
+            || {
+                assert_eq!(event.spell_id, spell1_id.clone());
+                assert_matches!(
+                    &event.info,
+                    TriggerInfo::Service(s) if s.service_id == "service1" && s.created
+                );
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_service_event_filtered_by_service_id() {
+        let (send, recv) = mpsc::unbounded_channel();
+        let recv = UnboundedReceiverStream::new(recv).boxed();
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![recv]);
+        let bus = bus.start();
+        let _ = api.start_scheduling().await;
+
+        let spell1_id = "spell1".to_string();
+        subscribe_service_event(
+            &api,
+            spell1_id.clone(),
+            vec![ServiceEventType::Removed],
+            Some("watched".to_string()),
+        )
+        .await;
+
+        // neither the creation nor events of other services must trigger the spell
+        send_service_event(&send, "watched", true);
+        send_service_event(&send, "other", false);
+        send_service_event(&send, "watched", false);
+
+        let event = event_receiver.recv().await.unwrap();
+        try_catch(
+            || {
+                assert_eq!(event.spell_id, spell1_id.clone());
+                assert_matches!(
+                    &event.info,
+                    TriggerInfo::Service(s) if s.service_id == "watched" && !s.created
+                );
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
     #[tokio::test]
     async fn test_unsubscribe() {
         let (send, recv) = mpsc::unbounded_channel();
         let recv = UnboundedReceiverStream::new(recv).boxed();
-        let (bus, api, mut event_receiver) = SpellEventBus::new(None, vec![recv]);
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![recv]);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
 
@@ -584,7 +1107,7 @@ mod tests {
     async fn test_subscribe_many_spells_with_diff_event_types() {
         let (recv, hdl) = emulate_connect(Duration::from_millis(10));
         let recv = UnboundedReceiverStream::new(recv).boxed();
-        let (bus, api, event_receiver) = SpellEventBus::new(None, vec![recv]);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![recv]);
         let event_stream = UnboundedReceiverStream::new(event_receiver);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
@@ -620,7 +1143,7 @@ mod tests {
     #[tokio::test]
     async fn test_double_subscribe_before_run() {
         //log_utils::enable_logs();
-        let (bus, api, event_receiver) = SpellEventBus::new(None, vec![]);
+        let (bus, api, event_receiver) = SpellEventBus::new(None, None, vec![]);
         let bus = bus.start();
         let mut event_stream = UnboundedReceiverStream::new(event_receiver).fuse();
         let spell1_id = "spell1".to_string();
@@ -658,7 +1181,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_resubscribing_same_spell() {
-        let (bus, api, mut event_receiver) = SpellEventBus::new(None, vec![]);
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![]);
         let bus = bus.start();
         let _ = api.start_scheduling().await;
         let spell1_id = "spell1".to_string();
@@ -690,4 +1213,189 @@ mod tests {
             },
         );
     }
+
+    #[tokio::test]
+    async fn test_delayed_trigger_fires_once() {
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus.start();
+        let _ = api.start_scheduling().await;
+
+        let spell1_id = "spell1".to_string();
+        let scheduled_at = Instant::now();
+        let token = api
+            .schedule_delayed(spell1_id.clone(), Duration::from_millis(50))
+            .await
+            .expect("Could not schedule a delayed trigger");
+
+        let event = event_receiver.recv().await.unwrap();
+        let elapsed = scheduled_at.elapsed();
+        let second = tokio::time::timeout(Duration::from_millis(100), event_receiver.recv()).await;
+        try_catch(
+            || {
+                assert_eq!(event.spell_id, spell1_id.clone());
+                assert!(
+                    elapsed >= Duration::from_millis(50),
+                    "the trigger mustn't fire before the delay passes"
+                );
+                assert_matches!(
+                    &event.info,
+                    TriggerInfo::Delayed(d) if d.token == token
+                );
+                assert!(second.is_err(), "a delayed trigger must fire exactly once");
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delayed_trigger_cancelled() {
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus.start();
+        let _ = api.start_scheduling().await;
+
+        let spell1_id = "spell1".to_string();
+        let token = api
+            .schedule_delayed(spell1_id.clone(), Duration::from_millis(100))
+            .await
+            .expect("Could not schedule a delayed trigger");
+        api.cancel_delayed(spell1_id, token)
+            .await
+            .expect("Could not cancel the delayed trigger");
+
+        let event = tokio::time::timeout(Duration::from_millis(200), event_receiver.recv()).await;
+        try_catch(
+            || {
+                assert!(event.is_err(), "a cancelled delayed trigger must never fire");
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delayed_trigger_survives_restart() {
+        let dir = tempfile::tempdir().expect("Could not create temp dir");
+        let persistence = DelayedTriggersPersistenceConfig {
+            path: dir.path().join("delayed_triggers.json"),
+            fire_missed_on_reload: false,
+        };
+
+        // the first bus instance is never started, so the trigger can't fire before the restart
+        let (bus, api, _event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus
+            .with_delayed_triggers_persistence(persistence.clone())
+            .start();
+        let spell1_id = "spell1".to_string();
+        let token = api
+            .schedule_delayed(spell1_id.clone(), Duration::from_millis(300))
+            .await
+            .expect("Could not schedule a delayed trigger");
+        bus.abort();
+
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus.with_delayed_triggers_persistence(persistence).start();
+        let _ = api.start_scheduling().await;
+
+        let event = tokio::time::timeout(Duration::from_secs(1), event_receiver.recv()).await;
+        try_catch(
+            || {
+                let event = event
+                    .ok()
+                    .flatten()
+                    .expect("the reloaded delayed trigger must fire");
+                assert_eq!(event.spell_id, spell1_id.clone());
+                assert_matches!(
+                    &event.info,
+                    TriggerInfo::Delayed(d) if d.token == token
+                );
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missed_delayed_trigger_dropped_on_reload() {
+        let dir = tempfile::tempdir().expect("Could not create temp dir");
+        let persistence = DelayedTriggersPersistenceConfig {
+            path: dir.path().join("delayed_triggers.json"),
+            fire_missed_on_reload: false,
+        };
+
+        let (bus, api, _event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus
+            .with_delayed_triggers_persistence(persistence.clone())
+            .start();
+        api.schedule_delayed("spell1".to_string(), Duration::from_millis(50))
+            .await
+            .expect("Could not schedule a delayed trigger");
+        bus.abort();
+        // let the fire time pass while the bus is down
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus.with_delayed_triggers_persistence(persistence).start();
+        let _ = api.start_scheduling().await;
+
+        let event = tokio::time::timeout(Duration::from_millis(200), event_receiver.recv()).await;
+        try_catch(
+            || {
+                assert!(
+                    event.is_err(),
+                    "a trigger missed while the bus was down must be dropped on reload"
+                );
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missed_delayed_trigger_fired_on_reload() {
+        let dir = tempfile::tempdir().expect("Could not create temp dir");
+        let persistence = DelayedTriggersPersistenceConfig {
+            path: dir.path().join("delayed_triggers.json"),
+            fire_missed_on_reload: true,
+        };
+
+        let (bus, api, _event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus
+            .with_delayed_triggers_persistence(persistence.clone())
+            .start();
+        let spell1_id = "spell1".to_string();
+        let token = api
+            .schedule_delayed(spell1_id.clone(), Duration::from_millis(50))
+            .await
+            .expect("Could not schedule a delayed trigger");
+        bus.abort();
+        // let the fire time pass while the bus is down
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let (bus, api, mut event_receiver) = SpellEventBus::new(None, None, vec![]);
+        let bus = bus.with_delayed_triggers_persistence(persistence).start();
+        let _ = api.start_scheduling().await;
+
+        let event = tokio::time::timeout(Duration::from_millis(500), event_receiver.recv()).await;
+        try_catch(
+            || {
+                let event = event
+                    .ok()
+                    .flatten()
+                    .expect("a trigger missed while the bus was down must fire on reload");
+                assert_eq!(event.spell_id, spell1_id.clone());
+                assert_matches!(
+                    &event.info,
+                    TriggerInfo::Delayed(d) if d.token == token
+                );
+            },
+            || {
+                bus.abort();
+            },
+        );
+    }
 }
@@ -14,10 +14,13 @@
  * limitations under the License.
  */
 
-use crate::api::PeerEventType;
+use crate::api::{PeerEventType, ServiceEventType};
+use fluence_libp2p::PeerId;
 use fluence_spell_dtos::trigger_config::{
     ClockConfig, ConnectionPoolConfig, TriggerConfig as UserTriggerConfig,
 };
+use std::collections::HashSet;
+use std::str::FromStr;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
@@ -36,6 +39,8 @@ pub enum ConfigError {
     InvalidPeriod,
     #[error("invalid config: end_sec is less than start_sec or in the past")]
     InvalidEndSec,
+    #[error("invalid config: `{peer_id}` in the peer filter is not a valid base58 peer id: {err}")]
+    InvalidPeerId { peer_id: String, err: String },
 }
 
 /// Convert timestamp to std::time::Instant.
@@ -47,8 +52,13 @@ fn to_instant(timestamp: u64) -> Option<Instant> {
 }
 
 /// Convert user-friendly config to event-bus-friendly config, validating it in the process.
+///
+/// `peer_events_enabled` is the node-level switch for peer-event triggers. It takes precedence
+/// over per-spell settings: when it is off, connection pool events are never subscribed to,
+/// no matter what the user requested in `connections`.
 pub fn from_user_config(
     user_config: &UserTriggerConfig,
+    peer_events_enabled: bool,
 ) -> Result<Option<SpellTriggerConfigs>, ConfigError> {
     let mut triggers = Vec::new();
 
@@ -58,7 +68,9 @@ pub fn from_user_config(
         triggers.push(TriggerConfig::Timer(timer_config));
     }
 
-    if let Some(peer_event_config) = from_connection_config(&user_config.connections) {
+    if let Some(peer_event_config) =
+        from_connection_config(&user_config.connections, peer_events_enabled, &[])?
+    {
         triggers.push(TriggerConfig::PeerEvent(peer_event_config));
     }
 
@@ -70,7 +82,21 @@ pub fn from_user_config(
     Ok(cfg)
 }
 
-fn from_connection_config(connection_config: &ConnectionPoolConfig) -> Option<PeerEventConfig> {
+/// Convert the user-facing connection pool config, validating it in the process.
+///
+/// `peer_ids` is an optional peer filter: when non-empty, only events of the listed
+/// peers trigger the spell. The filter can't yet be expressed in the user-facing
+/// trigger config DTO, so `from_user_config` always passes an empty list; filtered
+/// subscriptions are constructed programmatically.
+pub fn from_connection_config(
+    connection_config: &ConnectionPoolConfig,
+    peer_events_enabled: bool,
+    peer_ids: &[String],
+) -> Result<Option<PeerEventConfig>, ConfigError> {
+    // The node-level switch wins over the user's config
+    if !peer_events_enabled {
+        return Ok(None);
+    }
     let mut pool_events = Vec::with_capacity(2);
     if connection_config.connect {
         pool_events.push(PeerEventType::Connected);
@@ -79,12 +105,26 @@ fn from_connection_config(connection_config: &ConnectionPoolConfig) -> Option<Pe
         pool_events.push(PeerEventType::Disconnected);
     }
     if pool_events.is_empty() {
+        return Ok(None);
+    }
+    let peer_ids = if peer_ids.is_empty() {
         None
     } else {
-        Some(PeerEventConfig {
-            events: pool_events,
-        })
-    }
+        let peer_ids = peer_ids
+            .iter()
+            .map(|raw| {
+                PeerId::from_str(raw).map_err(|err| ConfigError::InvalidPeerId {
+                    peer_id: raw.clone(),
+                    err: err.to_string(),
+                })
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+        Some(peer_ids)
+    };
+    Ok(Some(PeerEventConfig {
+        events: pool_events,
+        peer_ids,
+    }))
 }
 
 fn from_clock_config(clock: &ClockConfig) -> Result<TimerConfig, ConfigError> {
@@ -131,6 +171,26 @@ pub struct SpellTriggerConfigs {
 }
 
 impl SpellTriggerConfigs {
+    /// Build a config subscribed to service lifecycle events.
+    /// Such triggers can't yet be expressed in the user-facing trigger config DTO,
+    /// hence a separate constructor instead of a branch in `from_user_config`.
+    pub fn for_service_events(events: Vec<ServiceEventType>, service_id: Option<String>) -> Self {
+        Self {
+            triggers: vec![TriggerConfig::ServiceEvent(ServiceEventConfig {
+                events,
+                service_id,
+            })],
+        }
+    }
+
+    /// Build a config subscribed to connection pool events, possibly filtered by peer id.
+    /// Used for filtered subscriptions that `from_user_config` can't express.
+    pub fn for_peer_events(config: PeerEventConfig) -> Self {
+        Self {
+            triggers: vec![TriggerConfig::PeerEvent(config)],
+        }
+    }
+
     pub fn into_rescheduled(self) -> Option<Self> {
         let new_triggers: Vec<TriggerConfig> = self
             .triggers
@@ -151,6 +211,9 @@ impl SpellTriggerConfigs {
 pub(crate) enum TriggerConfig {
     Timer(TimerConfig),
     PeerEvent(PeerEventConfig),
+    /// Note: can't yet be expressed in the user-facing trigger config DTO,
+    /// so it is only constructed programmatically
+    ServiceEvent(ServiceEventConfig),
 }
 
 impl TriggerConfig {
@@ -158,7 +221,7 @@ impl TriggerConfig {
         if let TriggerConfig::Timer(c) = self {
             c.into_rescheduled().map(TriggerConfig::Timer)
         } else {
-            // Peer events can't stop being relevant
+            // Peer and service events can't stop being relevant
             Some(self)
         }
     }
@@ -205,15 +268,32 @@ impl TimerConfig {
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct PeerEventConfig {
+pub struct PeerEventConfig {
     pub(crate) events: Vec<PeerEventType>,
+    /// When set, only events of these particular peers trigger the spell
+    pub(crate) peer_ids: Option<HashSet<PeerId>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ServiceEventConfig {
+    pub(crate) events: Vec<ServiceEventType>,
+    /// When set, only events of this particular service trigger the spell
+    pub(crate) service_id: Option<String>,
 }
 
 #[cfg(test)]
 mod trigger_config_tests {
-    use crate::api::PeerEventType;
-    use crate::config::{PeerEventConfig, SpellTriggerConfigs, TimerConfig, TriggerConfig};
+    use crate::api::{PeerEventType, ServiceEventType};
+    use crate::config::{
+        from_connection_config, from_user_config, ConfigError, PeerEventConfig, ServiceEventConfig,
+        SpellTriggerConfigs, TimerConfig, TriggerConfig,
+    };
+    use fluence_libp2p::PeerId;
+    use fluence_spell_dtos::trigger_config::{
+        ConnectionPoolConfig, TriggerConfig as UserTriggerConfig,
+    };
     use std::assert_matches::assert_matches;
+    use std::str::FromStr;
     use std::time::{Duration, Instant};
 
     #[test]
@@ -289,11 +369,37 @@ mod trigger_config_tests {
         );
     }
 
+    #[test]
+    fn test_peer_events_enabled_globally() {
+        let mut user_config = UserTriggerConfig::default();
+        user_config.connections.connect = true;
+        user_config.connections.disconnect = true;
+
+        let config = from_user_config(&user_config, true)
+            .expect("config must be valid")
+            .expect("config mustn't be empty");
+        assert_matches!(config.triggers[..], [TriggerConfig::PeerEvent(_)]);
+    }
+
+    #[test]
+    fn test_peer_events_disabled_globally() {
+        let mut user_config = UserTriggerConfig::default();
+        user_config.connections.connect = true;
+        user_config.connections.disconnect = true;
+
+        let config = from_user_config(&user_config, false).expect("config must be valid");
+        assert!(
+            config.is_none(),
+            "peer events must be ignored when disabled on the node level"
+        );
+    }
+
     #[test]
     fn test_peer_events() {
         let peer_events = vec![PeerEventType::Connected, PeerEventType::Disconnected];
         let peer_event_config = PeerEventConfig {
             events: peer_events,
+            peer_ids: None,
         };
         let trigger_config = TriggerConfig::PeerEvent(peer_event_config);
         let rescheduled = trigger_config.into_rescheduled();
@@ -303,12 +409,64 @@ mod trigger_config_tests {
         );
     }
 
+    #[test]
+    fn test_peer_filter_parsed() {
+        let connection_config = ConnectionPoolConfig {
+            connect: true,
+            disconnect: false,
+        };
+        let peer_ids = vec![PeerId::random().to_base58(), PeerId::random().to_base58()];
+
+        let config = from_connection_config(&connection_config, true, &peer_ids)
+            .expect("config must be valid")
+            .expect("config mustn't be empty");
+        let filter = config.peer_ids.expect("peer filter must be set");
+        assert_eq!(filter.len(), 2);
+        for peer_id in &peer_ids {
+            assert!(filter.contains(&PeerId::from_str(peer_id).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_peer_filter_invalid_peer_id() {
+        let connection_config = ConnectionPoolConfig {
+            connect: true,
+            disconnect: true,
+        };
+        let peer_ids = vec![
+            PeerId::random().to_base58(),
+            "definitely-not-base58".to_string(),
+        ];
+
+        let result = from_connection_config(&connection_config, true, &peer_ids);
+        assert_matches!(
+            result,
+            Err(ConfigError::InvalidPeerId { peer_id, .. }) if peer_id == "definitely-not-base58"
+        );
+    }
+
+    #[test]
+    fn test_service_events_rescheduled() {
+        let service_event_config = ServiceEventConfig {
+            events: vec![ServiceEventType::Created, ServiceEventType::Removed],
+            service_id: Some("service1".to_string()),
+        };
+        let trigger_config = TriggerConfig::ServiceEvent(service_event_config);
+        let rescheduled = trigger_config.into_rescheduled();
+        assert!(
+            rescheduled.is_some(),
+            "service events never expire, so they must survive rescheduling"
+        );
+        assert_matches!(rescheduled, Some(TriggerConfig::ServiceEvent(_)));
+    }
+
     // Test that ended configs are filtered out after rescheduling
     #[test]
     fn test_both_types_ended() {
         let peer_events = vec![PeerEventType::Connected, PeerEventType::Disconnected];
         let peer_event_config = PeerEventConfig {
             events: peer_events,
+            peer_ids: None,
         };
         let peer_trigger_config = TriggerConfig::PeerEvent(peer_event_config);
         let timer_config = TriggerConfig::Timer(TimerConfig::oneshot(
@@ -333,6 +491,7 @@ mod trigger_config_tests {
         let peer_events = vec![PeerEventType::Connected, PeerEventType::Disconnected];
         let peer_event_config = PeerEventConfig {
             events: peer_events,
+            peer_ids: None,
         };
         let peer_trigger_config = TriggerConfig::PeerEvent(peer_event_config);
         let timer_config = TriggerConfig::Timer(TimerConfig::periodic(
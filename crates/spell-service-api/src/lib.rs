@@ -377,6 +377,7 @@ mod tests {
             Default::default(),
             true,
             wasm_backend_config,
+            false,
         )
         .unwrap();
 
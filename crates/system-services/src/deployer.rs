@@ -70,6 +70,8 @@ pub struct Deployer {
     management_id: PeerId,
 
     system_service_distros: SystemServiceDistros,
+    // Node-level switch for peer-event spell triggers
+    spell_peer_events_enabled: bool,
 }
 
 impl Deployer {
@@ -83,6 +85,7 @@ impl Deployer {
         root_worker_id: PeerId,
         management_id: PeerId,
         system_service_distros: SystemServiceDistros,
+        spell_peer_events_enabled: bool,
     ) -> Self {
         Self {
             services,
@@ -94,6 +97,7 @@ impl Deployer {
             management_id,
 
             system_service_distros,
+            spell_peer_events_enabled,
         }
     }
     pub fn versions(&self) -> Versions {
@@ -205,7 +209,10 @@ impl Deployer {
             );
         }
 
-        let trigger_config = spell_event_bus::api::from_user_config(&spell_distro.trigger_config)?;
+        let trigger_config = spell_event_bus::api::from_user_config(
+            &spell_distro.trigger_config,
+            self.spell_peer_events_enabled,
+        )?;
         let params = CallParams::new(
             self.host_peer_id,
             PeerScope::Host,
@@ -269,6 +276,7 @@ impl Deployer {
             spell_distro.air.to_string(),
             json!(spell_distro.kv),
             self.host_peer_id,
+            self.spell_peer_events_enabled,
         )
         .await
         .map_err(|e| eyre!(e))?;
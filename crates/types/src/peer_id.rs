@@ -37,6 +37,67 @@ pub mod serde {
     }
 }
 
+/// Like [serde], but for optional peer id fields: missing and `null` values
+/// deserialize to `None`. Meant for `#[serde(default)]` fields absent in
+/// messages from older peers
+pub mod serde_optional {
+    use libp2p_identity::PeerId;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S>(value: &Option<PeerId>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        value
+            .as_ref()
+            .map(|peer_id| peer_id.to_base58())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<PeerId>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = Option::<String>::deserialize(deserializer)?;
+        str.map(|str| {
+            PeerId::from_str(&str).map_err(|e| {
+                serde::de::Error::custom(format!("peer id deserialization failed for {e:?}"))
+            })
+        })
+        .transpose()
+    }
+}
+
+/// Like [serde], but for lists of peer ids serialized as base58 strings
+pub mod serde_vec {
+    use libp2p_identity::PeerId;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S>(value: &[PeerId], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let strs: Vec<String> = value.iter().map(|peer_id| peer_id.to_base58()).collect();
+        strs.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<PeerId>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let strs = Vec::<String>::deserialize(deserializer)?;
+        strs.iter()
+            .map(|str| {
+                PeerId::from_str(str).map_err(|e| {
+                    serde::de::Error::custom(format!("peer id deserialization failed for {e:?}"))
+                })
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::peer_id;
@@ -83,4 +144,35 @@ mod tests {
         );
         assert_eq!(deserialized_test.unwrap(), test);
     }
+
+    #[test]
+    fn optional_peerid() {
+        #[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+        struct Test {
+            #[serde(
+                default,
+                serialize_with = "peer_id::serde_optional::serialize",
+                deserialize_with = "peer_id::serde_optional::deserialize"
+            )]
+            peer_id: Option<PeerId>,
+        }
+
+        // an absent field deserializes to None
+        let deserialized = serde_json::from_value::<Test>(serde_json::json!({})).unwrap();
+        assert_eq!(deserialized.peer_id, None);
+
+        // so does an explicit null
+        let deserialized =
+            serde_json::from_value::<Test>(serde_json::json!({ "peer_id": null })).unwrap();
+        assert_eq!(deserialized.peer_id, None);
+
+        // a present field round-trips through serialization
+        let peer_id = PeerId::from_str("QmY28NSCefB532XbERtnKHadexGuNzAfYnh5fJk6qhLsSi").unwrap();
+        let test = Test {
+            peer_id: Some(peer_id),
+        };
+        let serialized = serde_json::to_value(test.clone()).unwrap();
+        let deserialized = serde_json::from_value::<Test>(serialized).unwrap();
+        assert_eq!(deserialized, test);
+    }
 }
@@ -14,7 +14,8 @@
  * limitations under the License.
  */
 
-use core_manager::errors::AcquireError;
+use core_manager::errors::{AcquireError, PersistError};
+use fluence_keypair::error::{DecodingError, SigningError, VerificationError};
 use libp2p::PeerId;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -155,3 +156,106 @@ pub enum WorkersError {
     #[error("Failed to notify subsystem {worker_id}")]
     FailedToNotifySubsystem { worker_id: WorkerId },
 }
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Error loading persisted workers for the archive: {err}")]
+    LoadWorkers { err: eyre::Report },
+    #[error("Error loading persisted keypairs for the archive: {err}")]
+    LoadKeypairs { err: eyre::Report },
+    #[error("Invalid key format in persisted keypair {path:?}: {err}")]
+    InvalidKeypairFormat {
+        path: PathBuf,
+        #[source]
+        err: fluence_keypair::error::Error,
+    },
+    #[error("Failed to decode persisted keypair {path:?}: {err}")]
+    DecodeKeypair {
+        path: PathBuf,
+        #[source]
+        err: DecodingError,
+    },
+    #[error("No keypair for worker {worker_id}; refusing a partial archive")]
+    MissingKeypair { worker_id: WorkerId },
+    #[error("Error reading core state from {path:?}: {err}")]
+    ReadCoreState {
+        path: PathBuf,
+        #[source]
+        err: std::io::Error,
+    },
+    #[error("Error deserializing core state from {path:?}: {err}")]
+    DeserializeCoreState {
+        path: PathBuf,
+        #[source]
+        err: toml::de::Error,
+    },
+    #[error("Error reading archived directory {path:?}: {err}")]
+    ReadArchivedDir {
+        path: PathBuf,
+        #[source]
+        err: std::io::Error,
+    },
+    #[error("Error serializing the archive: {err}")]
+    SerializeArchive {
+        #[source]
+        err: toml_edit::ser::Error,
+    },
+    #[error("Error deserializing the archive: {err}")]
+    DeserializeArchive {
+        #[source]
+        err: toml_edit::de::Error,
+    },
+    #[error("Failed to sign the archive: {err}")]
+    SignArchive {
+        #[source]
+        err: SigningError,
+    },
+    #[error("Invalid host peer id {peer_id} in the archive: {err}")]
+    InvalidHostPeerId { peer_id: String, err: String },
+    #[error("The archive signature doesn't match host peer id {peer_id}: {err}")]
+    BadSignature {
+        peer_id: String,
+        #[source]
+        err: VerificationError,
+    },
+    #[error("Unsupported archive version {version}; this node supports version {supported}")]
+    UnsupportedVersion { version: u32, supported: u32 },
+    #[error(
+        "Failed to decrypt keypair of worker {worker_id}: wrong passphrase or corrupted archive"
+    )]
+    WrongPassphrase { worker_id: WorkerId },
+    #[error("Decrypted keypair derives peer id {actual}, but the archive records worker {expected}")]
+    KeypairIdentityMismatch { expected: WorkerId, actual: PeerId },
+    #[error("Invalid archived keypair of worker {worker_id}: {err}")]
+    InvalidArchivedKeypair { worker_id: WorkerId, err: String },
+    #[error("Archived file name {name:?} is not a plain file name")]
+    InvalidFileName { name: String },
+    #[error(
+        "The new topology has {available} cores, which is not enough for {system} system cores \
+         and at least one worker core"
+    )]
+    NotEnoughCores { available: usize, system: usize },
+    #[error("Import target {path:?} is not empty; placement import requires a fresh node")]
+    TargetNotEmpty { path: PathBuf },
+    #[error("Error persisting imported keypair: {err}")]
+    PersistKeypair {
+        #[source]
+        err: KeyStorageError,
+    },
+    #[error("Error persisting imported worker: {err}")]
+    PersistWorker {
+        #[source]
+        err: WorkersError,
+    },
+    #[error("Error persisting imported core state: {err}")]
+    PersistCoreState {
+        #[source]
+        err: PersistError,
+    },
+    #[error("Error writing imported file {path:?}: {err}")]
+    WriteFile {
+        path: PathBuf,
+        #[source]
+        err: std::io::Error,
+    },
+}
@@ -17,6 +17,7 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use parking_lot::RwLock;
 
@@ -26,8 +27,9 @@ use fluence_keypair::{KeyFormat, KeyPair};
 use types::peer_scope::{PeerScope, WorkerId};
 
 pub struct KeyStorage {
-    /// worker_id -> worker_keypair
-    worker_key_pairs: RwLock<HashMap<WorkerId, KeyPair>>,
+    /// worker_id -> worker_keypair; the map is the source of truth, the disk
+    /// copy converges to it even if a caller is cancelled mid-operation
+    worker_key_pairs: Arc<RwLock<HashMap<WorkerId, KeyPair>>>,
     key_pairs_dir: PathBuf,
     pub root_key_pair: KeyPair,
 }
@@ -46,7 +48,7 @@ impl KeyStorage {
             worker_key_pairs.insert(worker_id, keypair);
         }
         Ok(Self {
-            worker_key_pairs: RwLock::new(worker_key_pairs),
+            worker_key_pairs: Arc::new(RwLock::new(worker_key_pairs)),
             key_pairs_dir,
             root_key_pair,
         })
@@ -65,16 +67,45 @@ impl KeyStorage {
     pub async fn create_key_pair(&self) -> Result<KeyPair, KeyStorageError> {
         let keypair = KeyPair::generate_ed25519();
         let worker_id: WorkerId = keypair.get_peer_id().into();
-        persist_keypair(&self.key_pairs_dir, worker_id, (&keypair).try_into()?).await?;
-        let mut guard = self.worker_key_pairs.write();
-        guard.insert(worker_id, keypair.clone());
+        let persisted_keypair = (&keypair).try_into()?;
+
+        // The map is updated before the first await point, so a cancelled
+        // caller can't leave the new keypair half-registered
+        self.worker_key_pairs
+            .write()
+            .insert(worker_id, keypair.clone());
+
+        // The disk write runs in a spawned task that outlives a cancelled
+        // caller, rolling the map back if the write fails; this way disk and
+        // memory converge no matter where this future is dropped
+        let key_pairs_dir = self.key_pairs_dir.clone();
+        let worker_key_pairs = self.worker_key_pairs.clone();
+        let persist = tokio::task::spawn(async move {
+            let result = persist_keypair(&key_pairs_dir, worker_id, persisted_keypair).await;
+            if result.is_err() {
+                worker_key_pairs.write().remove(&worker_id);
+            }
+            result
+        });
+        persist.await.expect("keypair persist task panicked")?;
         Ok(keypair)
     }
 
     pub async fn remove_key_pair(&self, worker_id: WorkerId) -> Result<(), KeyStorageError> {
-        remove_keypair(&self.key_pairs_dir, worker_id).await?;
-        let mut guard = self.worker_key_pairs.write();
-        guard.remove(&worker_id);
+        let removed = self.worker_key_pairs.write().remove(&worker_id);
+
+        // as in `create_key_pair`, the disk update survives cancellation of
+        // this future and restores the map entry if the removal fails
+        let key_pairs_dir = self.key_pairs_dir.clone();
+        let worker_key_pairs = self.worker_key_pairs.clone();
+        let remove = tokio::task::spawn(async move {
+            let result = remove_keypair(&key_pairs_dir, worker_id).await;
+            if let (Err(_), Some(keypair)) = (&result, removed) {
+                worker_key_pairs.write().insert(worker_id, keypair);
+            }
+            result
+        });
+        remove.await.expect("keypair remove task panicked")?;
         Ok(())
     }
 }
@@ -169,6 +200,50 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_cancelled_create_leaves_consistent_state() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let key_pairs_dir = temp_dir.path().to_path_buf();
+
+        let root_key_pair = fluence_keypair::KeyPair::generate_ed25519();
+
+        let key_storage = KeyStorage::from_path(key_pairs_dir.clone(), root_key_pair.clone())
+            .await
+            .expect("Failed to create KeyStorage from path");
+
+        // cancel the create at its earliest await point: the zero timeout
+        // polls the future exactly once and then drops it
+        let _ = tokio::time::timeout(
+            std::time::Duration::ZERO,
+            key_storage.create_key_pair(),
+        )
+        .await;
+
+        // the in-memory map is the source of truth; the disk copy must
+        // converge to it despite the cancellation
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        loop {
+            let in_memory: Vec<_> = key_storage
+                .worker_key_pairs
+                .read()
+                .keys()
+                .cloned()
+                .collect();
+            let reloaded = KeyStorage::from_path(key_pairs_dir.clone(), root_key_pair.clone())
+                .await
+                .expect("Failed to reload KeyStorage from path");
+            let on_disk: Vec<_> = reloaded.worker_key_pairs.read().keys().cloned().collect();
+            if in_memory == on_disk {
+                break;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "disk and memory never converged: memory {in_memory:?}, disk {on_disk:?}"
+            );
+            tokio::task::yield_now().await;
+        }
+    }
+
     #[tokio::test]
     async fn test_persistence() {
         // Create a temporary directory for key storage
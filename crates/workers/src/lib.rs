@@ -18,6 +18,7 @@
 
 mod error;
 mod key_storage;
+pub mod migration;
 mod persistence;
 mod scope;
 mod workers;
@@ -27,6 +28,7 @@ pub use core_manager::DummyCoreManager;
 pub use core_manager::StrictCoreManager;
 pub use core_manager::CUID;
 pub use error::KeyStorageError;
+pub use error::MigrationError;
 pub use error::WorkersError;
 pub use key_storage::KeyStorage;
 pub use scope::PeerScopes;
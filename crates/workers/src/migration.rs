@@ -0,0 +1,897 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Export and import of the node placement state for migration to new hardware.
+//!
+//! The export side collects everything that binds this node to its deals —
+//! worker keypairs, worker metadata, the core-manager state and the
+//! module/blueprint indexes — into a single archive signed with the root key.
+//! Worker secret keys are encrypted with an operator-supplied passphrase, so
+//! the archive can be moved over untrusted channels.
+//!
+//! The import side runs on a fresh node before it is started: it verifies the
+//! signature and the archive version, decrypts and validates every keypair, and
+//! fits the core state onto the new machine's topology, producing a
+//! [`CoreRemapReport`]. All validation happens before anything is written, and
+//! a target that already holds placement state is refused, so an import either
+//! restores the whole archive or restores nothing.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use core_manager::{LogicalCoreId, PersistentCoreManagerState, PhysicalCoreId, CUID};
+use fluence_keypair::{KeyFormat, KeyPair, PublicKey, Signature};
+use hex_utils::serde_as::Hex;
+use libp2p::PeerId;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use types::peer_scope::WorkerId;
+
+use crate::error::MigrationError;
+use crate::persistence::{
+    is_keypair, is_worker, load_persisted_key_pairs, load_persisted_workers, persist_keypair,
+    persist_worker, PersistedKeypair, PersistedWorker,
+};
+
+/// Version of the archive format; bump on incompatible changes
+pub const ARCHIVE_VERSION: u32 = 1;
+
+// domain separation contexts for the keys derived from the passphrase
+const ENCRYPTION_KEY_CONTEXT: &str = "nox placement archive v1 keypair encryption key";
+const MAC_KEY_CONTEXT: &str = "nox placement archive v1 keypair mac key";
+const NONCE_LEN: usize = 32;
+
+/// Locations of the per-subsystem state covered by a placement archive.
+/// On export these are the source node's directories, on import the target's
+#[derive(Clone, Debug)]
+pub struct PlacementPaths {
+    pub key_pairs_dir: PathBuf,
+    pub workers_dir: PathBuf,
+    pub core_state_path: PathBuf,
+    pub modules_dir: PathBuf,
+    pub blueprints_dir: PathBuf,
+}
+
+/// The outer, signed envelope: `payload` holds the exact TOML bytes of a
+/// [`PlacementArchive`] the signature was computed over
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+pub struct SignedPlacementArchive {
+    /// base58 peer id of the exporting node's root key
+    pub host_peer_id: String,
+    #[serde_as(as = "Hex")]
+    pub payload: Vec<u8>,
+    #[serde_as(as = "Hex")]
+    pub signature: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlacementArchive {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workers: Vec<PersistedWorker>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keypairs: Vec<EncryptedKeypair>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<ArchivedFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blueprints: Vec<ArchivedFile>,
+    pub core_state: PersistentCoreManagerState,
+}
+
+/// A worker secret key encrypted with the operator passphrase. The keystream
+/// and the MAC are keyed BLAKE3 with separate derivation contexts
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedKeypair {
+    pub worker_id: WorkerId,
+    pub key_format: String,
+    #[serde_as(as = "Hex")]
+    pub nonce: Vec<u8>,
+    #[serde_as(as = "Hex")]
+    pub ciphertext: Vec<u8>,
+    #[serde_as(as = "Hex")]
+    pub mac: Vec<u8>,
+}
+
+/// A file carried verbatim, used for the module and blueprint indexes
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub name: String,
+    #[serde_as(as = "Hex")]
+    pub bytes: Vec<u8>,
+}
+
+/// How the imported units were fitted onto the new machine's cores
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct CoreRemapReport {
+    /// units that stayed on their previous physical core
+    #[serde_as(as = "Vec<(Hex, _)>")]
+    pub kept: Vec<(CUID, PhysicalCoreId)>,
+    /// units whose previous core doesn't exist on the new machine (or became
+    /// a system core there) and had to be reassigned
+    pub moved: Vec<UnitMove>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct UnitMove {
+    #[serde_as(as = "Hex")]
+    pub unit_id: CUID,
+    pub old_core: PhysicalCoreId,
+    pub new_core: PhysicalCoreId,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub workers: usize,
+    pub keypairs: usize,
+    pub modules: usize,
+    pub blueprints: usize,
+    pub core_remap: CoreRemapReport,
+}
+
+/// Collects the placement state from `paths` into a signed archive and returns
+/// it as a TOML string. Worker secret keys are encrypted with `passphrase`;
+/// wasm blobs are skipped unless `include_wasm` is set, since modules are
+/// usually re-fetched from IPFS by their hashes in the archived index
+pub async fn export_placement_state(
+    root_key_pair: &KeyPair,
+    passphrase: &str,
+    paths: &PlacementPaths,
+    include_wasm: bool,
+) -> Result<String, MigrationError> {
+    let workers = load_persisted_workers(&paths.workers_dir)
+        .await
+        .map_err(|err| MigrationError::LoadWorkers { err })?;
+    let workers: Vec<PersistedWorker> = workers.into_iter().map(|(worker, _)| worker).collect();
+
+    let key_pairs = load_persisted_key_pairs(&paths.key_pairs_dir)
+        .await
+        .map_err(|err| MigrationError::LoadKeypairs { err })?;
+    let mut keypairs = Vec::with_capacity(key_pairs.len());
+    let mut keypair_ids: HashSet<WorkerId> = HashSet::with_capacity(key_pairs.len());
+    for (persisted, path) in key_pairs {
+        // the worker id is not stored next to the key, it is derived from it
+        let format = KeyFormat::from_str(&persisted.key_format)
+            .map_err(|err| MigrationError::InvalidKeypairFormat {
+                path: path.clone(),
+                err,
+            })?;
+        let keypair = KeyPair::from_secret_key(persisted.private_key_bytes.clone(), format)
+            .map_err(|err| MigrationError::DecodeKeypair { path, err })?;
+        let worker_id: WorkerId = keypair.get_peer_id().into();
+        keypair_ids.insert(worker_id);
+        keypairs.push(encrypt_keypair(worker_id, &persisted, passphrase));
+    }
+    // a worker without its key would be unusable on the new machine; better to
+    // find that out on the old one, while the key might still be recoverable
+    for worker in &workers {
+        if !keypair_ids.contains(&worker.worker_id) {
+            return Err(MigrationError::MissingKeypair {
+                worker_id: worker.worker_id,
+            });
+        }
+    }
+
+    let core_state_bytes = tokio::fs::read(&paths.core_state_path).await.map_err(|err| {
+        MigrationError::ReadCoreState {
+            path: paths.core_state_path.clone(),
+            err,
+        }
+    })?;
+    let core_state: PersistentCoreManagerState =
+        toml::from_str(String::from_utf8_lossy(&core_state_bytes).as_ref()).map_err(|err| {
+            MigrationError::DeserializeCoreState {
+                path: paths.core_state_path.clone(),
+                err,
+            }
+        })?;
+
+    let modules = archive_dir_files(&paths.modules_dir, include_wasm).await?;
+    let blueprints = archive_dir_files(&paths.blueprints_dir, true).await?;
+
+    let archive = PlacementArchive {
+        version: ARCHIVE_VERSION,
+        workers,
+        keypairs,
+        modules,
+        blueprints,
+        core_state,
+    };
+    let payload =
+        toml_edit::ser::to_vec(&archive).map_err(|err| MigrationError::SerializeArchive { err })?;
+    let signature = root_key_pair
+        .sign(&payload)
+        .map_err(|err| MigrationError::SignArchive { err })?
+        .to_vec();
+    let signed = SignedPlacementArchive {
+        host_peer_id: root_key_pair.get_peer_id().to_base58(),
+        payload,
+        signature,
+    };
+    toml_edit::ser::to_string(&signed).map_err(|err| MigrationError::SerializeArchive { err })
+}
+
+/// Verifies and restores `archive` into `paths` on a fresh node.
+/// `new_cores_mapping` describes the new machine's topology; the archived core
+/// state is fitted onto it and the reassignments are returned in the report.
+/// Nothing is written until the whole archive has been validated, and targets
+/// that already hold placement state are refused, so the import is all-or-nothing
+pub async fn import_placement_state(
+    archive: &str,
+    passphrase: &str,
+    paths: &PlacementPaths,
+    new_cores_mapping: &[(PhysicalCoreId, LogicalCoreId)],
+    system_cpu_count: usize,
+) -> Result<ImportReport, MigrationError> {
+    let signed: SignedPlacementArchive =
+        toml_edit::de::from_str(archive).map_err(|err| MigrationError::DeserializeArchive { err })?;
+    let peer_id = PeerId::from_str(&signed.host_peer_id).map_err(|err| {
+        MigrationError::InvalidHostPeerId {
+            peer_id: signed.host_peer_id.clone(),
+            err: err.to_string(),
+        }
+    })?;
+    let public_key: PublicKey =
+        peer_id
+            .try_into()
+            .map_err(|err: fluence_keypair::error::DecodingError| {
+                MigrationError::InvalidHostPeerId {
+                    peer_id: signed.host_peer_id.clone(),
+                    err: err.to_string(),
+                }
+            })?;
+    let signature = Signature::from_bytes(public_key.get_key_format(), signed.signature.clone());
+    public_key
+        .verify(&signed.payload, &signature)
+        .map_err(|err| MigrationError::BadSignature {
+            peer_id: signed.host_peer_id.clone(),
+            err,
+        })?;
+
+    let archive: PlacementArchive = toml_edit::de::from_slice(&signed.payload)
+        .map_err(|err| MigrationError::DeserializeArchive { err })?;
+    if archive.version != ARCHIVE_VERSION {
+        return Err(MigrationError::UnsupportedVersion {
+            version: archive.version,
+            supported: ARCHIVE_VERSION,
+        });
+    }
+
+    // decrypt and validate every keypair before anything is written
+    let mut keypairs = Vec::with_capacity(archive.keypairs.len());
+    let mut keypair_ids: HashSet<WorkerId> = HashSet::with_capacity(archive.keypairs.len());
+    for entry in &archive.keypairs {
+        keypairs.push((entry.worker_id, decrypt_keypair(entry, passphrase)?));
+        keypair_ids.insert(entry.worker_id);
+    }
+    for worker in &archive.workers {
+        if !keypair_ids.contains(&worker.worker_id) {
+            return Err(MigrationError::MissingKeypair {
+                worker_id: worker.worker_id,
+            });
+        }
+    }
+    for file in archive.modules.iter().chain(archive.blueprints.iter()) {
+        // the names come from a signed archive, but a crafted name must not be
+        // able to escape the target directory anyway
+        if Path::new(&file.name).file_name() != Some(file.name.as_ref()) {
+            return Err(MigrationError::InvalidFileName {
+                name: file.name.clone(),
+            });
+        }
+    }
+
+    let (core_state, core_remap) =
+        remap_core_state(&archive.core_state, new_cores_mapping, system_cpu_count)?;
+
+    // refuse to touch a node that already holds placement state
+    ensure_no_entries(&paths.key_pairs_dir, is_keypair)?;
+    ensure_no_entries(&paths.workers_dir, is_worker)?;
+    if paths.core_state_path.exists() {
+        return Err(MigrationError::TargetNotEmpty {
+            path: paths.core_state_path.clone(),
+        });
+    }
+    ensure_no_entries(&paths.modules_dir, |_| true)?;
+    ensure_no_entries(&paths.blueprints_dir, |_| true)?;
+
+    // all checks passed, write the state out
+    for dir in [
+        &paths.key_pairs_dir,
+        &paths.workers_dir,
+        &paths.modules_dir,
+        &paths.blueprints_dir,
+    ] {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|err| MigrationError::WriteFile {
+                path: dir.to_path_buf(),
+                err,
+            })?;
+    }
+    for (worker_id, persisted) in keypairs {
+        persist_keypair(&paths.key_pairs_dir, worker_id, persisted)
+            .await
+            .map_err(|err| MigrationError::PersistKeypair { err })?;
+    }
+    let workers_count = archive.workers.len();
+    for worker in archive.workers {
+        let worker_id = worker.worker_id;
+        persist_worker(&paths.workers_dir, worker_id, worker)
+            .await
+            .map_err(|err| MigrationError::PersistWorker { err })?;
+    }
+    for (dir, files) in [
+        (&paths.modules_dir, &archive.modules),
+        (&paths.blueprints_dir, &archive.blueprints),
+    ] {
+        for file in files {
+            let path = dir.join(&file.name);
+            tokio::fs::write(&path, &file.bytes)
+                .await
+                .map_err(|err| MigrationError::WriteFile { path, err })?;
+        }
+    }
+    core_state
+        .persist(&paths.core_state_path)
+        .map_err(|err| MigrationError::PersistCoreState { err })?;
+
+    Ok(ImportReport {
+        workers: workers_count,
+        keypairs: keypair_ids.len(),
+        modules: archive.modules.len(),
+        blueprints: archive.blueprints.len(),
+        core_remap,
+    })
+}
+
+/// Fits a core state exported on one machine onto the topology of another.
+/// System cores are taken from the lowest core ids, matching how the core
+/// managers pick them; a unit keeps its physical core when it still exists and
+/// is not a system core, otherwise it goes to the least loaded worker core
+pub fn remap_core_state(
+    old: &PersistentCoreManagerState,
+    new_cores_mapping: &[(PhysicalCoreId, LogicalCoreId)],
+    system_cpu_count: usize,
+) -> Result<(PersistentCoreManagerState, CoreRemapReport), MigrationError> {
+    let new_cores: BTreeSet<PhysicalCoreId> =
+        new_cores_mapping.iter().map(|(core_id, _)| *core_id).collect();
+    if system_cpu_count == 0 || system_cpu_count >= new_cores.len() {
+        return Err(MigrationError::NotEnoughCores {
+            available: new_cores.len(),
+            system: system_cpu_count,
+        });
+    }
+    let system_cores: Vec<PhysicalCoreId> =
+        new_cores.iter().take(system_cpu_count).copied().collect();
+    let worker_cores: Vec<PhysicalCoreId> =
+        new_cores.iter().skip(system_cpu_count).copied().collect();
+    let worker_core_set: BTreeSet<PhysicalCoreId> = worker_cores.iter().copied().collect();
+
+    let mut occupancy: BTreeMap<PhysicalCoreId, usize> =
+        worker_cores.iter().map(|core_id| (*core_id, 0)).collect();
+    let mut kept = Vec::new();
+    let mut moved = Vec::new();
+    let mut unit_id_mapping = Vec::with_capacity(old.unit_id_mapping.len());
+    for (old_core, unit_id) in &old.unit_id_mapping {
+        let new_core = if worker_core_set.contains(old_core) {
+            kept.push((*unit_id, *old_core));
+            *old_core
+        } else {
+            // the least loaded worker core, lowest id on a tie
+            let target = occupancy
+                .iter()
+                .min_by_key(|(core_id, count)| (**count, **core_id))
+                .map(|(core_id, _)| *core_id)
+                .expect("worker cores can't be empty here");
+            moved.push(UnitMove {
+                unit_id: *unit_id,
+                old_core: *old_core,
+                new_core: target,
+            });
+            target
+        };
+        *occupancy
+            .get_mut(&new_core)
+            .expect("every assigned core is a worker core") += 1;
+        unit_id_mapping.push((new_core, *unit_id));
+    }
+    let available_cores: Vec<PhysicalCoreId> = worker_cores
+        .iter()
+        .filter(|core_id| occupancy[core_id] == 0)
+        .copied()
+        .collect();
+
+    let state = PersistentCoreManagerState {
+        cores_mapping: new_cores_mapping.to_vec(),
+        system_cores,
+        available_cores,
+        unit_id_mapping,
+        work_type_mapping: old.work_type_mapping.clone(),
+        acquire_order: old.acquire_order.clone(),
+    };
+    Ok((state, CoreRemapReport { kept, moved }))
+}
+
+fn encrypt_keypair(
+    worker_id: WorkerId,
+    persisted: &PersistedKeypair,
+    passphrase: &str,
+) -> EncryptedKeypair {
+    let mut nonce = vec![0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = keystream_xor(passphrase, &nonce, &persisted.private_key_bytes);
+    let mac = mac(passphrase, &nonce, &ciphertext);
+    EncryptedKeypair {
+        worker_id,
+        key_format: persisted.key_format.clone(),
+        nonce,
+        ciphertext,
+        mac: mac.to_vec(),
+    }
+}
+
+fn decrypt_keypair(
+    entry: &EncryptedKeypair,
+    passphrase: &str,
+) -> Result<PersistedKeypair, MigrationError> {
+    let wrong_passphrase = || MigrationError::WrongPassphrase {
+        worker_id: entry.worker_id,
+    };
+    let stored_mac: [u8; 32] = entry
+        .mac
+        .as_slice()
+        .try_into()
+        .map_err(|_| wrong_passphrase())?;
+    let expected_mac = mac(passphrase, &entry.nonce, &entry.ciphertext);
+    // blake3::Hash comparison is constant-time
+    if blake3::Hash::from(stored_mac) != blake3::Hash::from(expected_mac) {
+        return Err(wrong_passphrase());
+    }
+    let persisted = PersistedKeypair {
+        private_key_bytes: keystream_xor(passphrase, &entry.nonce, &entry.ciphertext),
+        key_format: entry.key_format.clone(),
+    };
+
+    // the decrypted key must derive exactly the worker id it is recorded under
+    let invalid_keypair = |err: String| MigrationError::InvalidArchivedKeypair {
+        worker_id: entry.worker_id,
+        err,
+    };
+    let format = KeyFormat::from_str(&persisted.key_format)
+        .map_err(|err| invalid_keypair(err.to_string()))?;
+    let keypair = KeyPair::from_secret_key(persisted.private_key_bytes.clone(), format)
+        .map_err(|err| invalid_keypair(err.to_string()))?;
+    let actual = keypair.get_peer_id();
+    if WorkerId::from(actual) != entry.worker_id {
+        return Err(MigrationError::KeypairIdentityMismatch {
+            expected: entry.worker_id,
+            actual,
+        });
+    }
+    Ok(persisted)
+}
+
+fn keystream_xor(passphrase: &str, nonce: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = blake3::derive_key(ENCRYPTION_KEY_CONTEXT, passphrase.as_bytes());
+    let mut hasher = blake3::Hasher::new_keyed(&key);
+    hasher.update(nonce);
+    let mut stream = vec![0u8; data.len()];
+    hasher.finalize_xof().fill(&mut stream);
+    stream
+        .iter()
+        .zip(data)
+        .map(|(stream_byte, data_byte)| stream_byte ^ data_byte)
+        .collect()
+}
+
+fn mac(passphrase: &str, nonce: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let key = blake3::derive_key(MAC_KEY_CONTEXT, passphrase.as_bytes());
+    let mut hasher = blake3::Hasher::new_keyed(&key);
+    hasher.update(nonce);
+    hasher.update(ciphertext);
+    *hasher.finalize().as_bytes()
+}
+
+async fn archive_dir_files(
+    dir: &Path,
+    include_wasm: bool,
+) -> Result<Vec<ArchivedFile>, MigrationError> {
+    let read_err = |err| MigrationError::ReadArchivedDir {
+        path: dir.to_path_buf(),
+        err,
+    };
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(read_err)?;
+    while let Some(entry) = entries.next_entry().await.map_err(read_err)? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !include_wasm && name.ends_with(".wasm") {
+            continue;
+        }
+        let bytes = tokio::fs::read(&path).await.map_err(read_err)?;
+        files.push(ArchivedFile {
+            name: name.to_string(),
+            bytes,
+        });
+    }
+    // deterministic payload regardless of the directory iteration order
+    files.sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(files)
+}
+
+fn ensure_no_entries(dir: &Path, matches: impl Fn(&Path) -> bool) -> Result<(), MigrationError> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    let entries = std::fs::read_dir(dir).map_err(|err| MigrationError::ReadArchivedDir {
+        path: dir.to_path_buf(),
+        err,
+    })?;
+    for entry in entries {
+        let entry = entry.map_err(|err| MigrationError::ReadArchivedDir {
+            path: dir.to_path_buf(),
+            err,
+        })?;
+        if matches(&entry.path()) {
+            return Err(MigrationError::TargetNotEmpty { path: entry.path() });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use core_manager::types::WorkType;
+    use core_manager::{LogicalCoreId, PersistentCoreManagerState, PhysicalCoreId, CUID};
+    use hex::FromHex;
+    use libp2p::PeerId;
+    use tempfile::TempDir;
+
+    use crate::error::MigrationError;
+    use crate::migration::{
+        export_placement_state, import_placement_state, remap_core_state, PlacementArchive,
+        PlacementPaths, SignedPlacementArchive, ARCHIVE_VERSION,
+    };
+    use crate::persistence::{load_persisted_workers, persist_worker, PersistedWorker};
+    use crate::KeyStorage;
+
+    fn paths_in(dir: &Path) -> PlacementPaths {
+        PlacementPaths {
+            key_pairs_dir: dir.join("keypairs"),
+            workers_dir: dir.join("workers"),
+            core_state_path: dir.join("core_state.toml"),
+            modules_dir: dir.join("modules"),
+            blueprints_dir: dir.join("blueprints"),
+        }
+    }
+
+    fn cuid(hex: &str) -> CUID {
+        <CUID>::from_hex(hex).unwrap()
+    }
+
+    fn cuid_1() -> CUID {
+        cuid("54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea")
+    }
+
+    fn cuid_2() -> CUID {
+        cuid("1cce3d08f784b11d636f2fb55adf291d43c2e9cbe7ae7eeb2d0301a96be0a3a0")
+    }
+
+    fn mapping(cores: &[u32]) -> Vec<(PhysicalCoreId, LogicalCoreId)> {
+        cores
+            .iter()
+            .map(|core_id| (PhysicalCoreId::new(*core_id), LogicalCoreId::new(*core_id)))
+            .collect()
+    }
+
+    /// A node with two workers pinned to cores 2 and 3 of a 0-3 topology
+    async fn populate_source(
+        paths: &PlacementPaths,
+        root_key_pair: &fluence_keypair::KeyPair,
+    ) -> eyre::Result<Vec<crate::WorkerId>> {
+        tokio::fs::create_dir_all(&paths.key_pairs_dir).await?;
+        tokio::fs::create_dir_all(&paths.workers_dir).await?;
+        tokio::fs::create_dir_all(&paths.modules_dir).await?;
+        tokio::fs::create_dir_all(&paths.blueprints_dir).await?;
+
+        let key_storage =
+            KeyStorage::from_path(paths.key_pairs_dir.clone(), root_key_pair.clone()).await?;
+        let mut worker_ids = Vec::new();
+        for (deal_id, unit_id) in [("0x1111", cuid_1()), ("0x2222", cuid_2())] {
+            let keypair = key_storage.create_key_pair().await?;
+            let worker_id: crate::WorkerId = keypair.get_peer_id().into();
+            persist_worker(
+                &paths.workers_dir,
+                worker_id,
+                PersistedWorker {
+                    worker_id,
+                    creator: PeerId::random(),
+                    deal_id: deal_id.to_string(),
+                    active: true,
+                    cu_ids: vec![unit_id],
+                },
+            )
+            .await?;
+            worker_ids.push(worker_id);
+        }
+
+        let core_state = PersistentCoreManagerState {
+            cores_mapping: mapping(&[0, 1, 2, 3]),
+            system_cores: vec![PhysicalCoreId::new(0)],
+            available_cores: vec![PhysicalCoreId::new(1)],
+            unit_id_mapping: vec![
+                (PhysicalCoreId::new(2), cuid_1()),
+                (PhysicalCoreId::new(3), cuid_2()),
+            ],
+            work_type_mapping: vec![(cuid_1(), WorkType::Deal), (cuid_2(), WorkType::Deal)],
+            acquire_order: vec![cuid_1(), cuid_2()],
+        };
+        core_state.persist(&paths.core_state_path)?;
+
+        tokio::fs::write(
+            paths.modules_dir.join("module_config.toml"),
+            b"name = \"curl_adapter\"",
+        )
+        .await?;
+        tokio::fs::write(paths.modules_dir.join("blob.wasm"), b"\0asm").await?;
+        tokio::fs::write(paths.blueprints_dir.join("bp.toml"), b"name = \"bp\"").await?;
+        Ok(worker_ids)
+    }
+
+    #[tokio::test]
+    async fn export_import_round_trip_with_remapping() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let source = paths_in(source_dir.path());
+        let target = paths_in(target_dir.path());
+        let root_key_pair = fluence_keypair::KeyPair::generate_ed25519();
+        let worker_ids = populate_source(&source, &root_key_pair).await.unwrap();
+
+        let archive = export_placement_state(&root_key_pair, "correct horse", &source, false)
+            .await
+            .unwrap();
+
+        // the new machine has cores 10-12 instead of 0-3: both units must move
+        let report = import_placement_state(
+            &archive,
+            "correct horse",
+            &target,
+            &mapping(&[10, 11, 12]),
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.workers, 2);
+        assert_eq!(report.keypairs, 2);
+        assert_eq!(report.modules, 1, "the wasm blob must not be archived");
+        assert_eq!(report.blueprints, 1);
+        assert_eq!(report.core_remap.kept.len(), 0);
+        assert_eq!(report.core_remap.moved.len(), 2);
+
+        // the keys must load and derive the same worker ids
+        let key_storage = KeyStorage::from_path(target.key_pairs_dir.clone(), root_key_pair)
+            .await
+            .unwrap();
+        for worker_id in &worker_ids {
+            assert!(
+                key_storage.get_worker_key_pair(*worker_id).is_some(),
+                "imported keypair of worker {worker_id} must be loadable"
+            );
+        }
+
+        let workers = load_persisted_workers(&target.workers_dir).await.unwrap();
+        assert_eq!(workers.len(), 2);
+
+        let core_state: PersistentCoreManagerState = toml::from_str(
+            &std::fs::read_to_string(&target.core_state_path).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(core_state.system_cores, vec![PhysicalCoreId::new(10)]);
+        let assigned: Vec<PhysicalCoreId> = core_state
+            .unit_id_mapping
+            .iter()
+            .map(|(core_id, _)| *core_id)
+            .collect();
+        assert_eq!(
+            assigned,
+            vec![PhysicalCoreId::new(11), PhysicalCoreId::new(12)],
+            "each unit must land on its own worker core"
+        );
+        assert!(
+            core_state.available_cores.is_empty(),
+            "both worker cores are occupied"
+        );
+        assert_eq!(core_state.acquire_order, vec![cuid_1(), cuid_2()]);
+
+        assert!(target.modules_dir.join("module_config.toml").exists());
+        assert!(!target.modules_dir.join("blob.wasm").exists());
+        assert!(target.blueprints_dir.join("bp.toml").exists());
+    }
+
+    #[tokio::test]
+    async fn import_refuses_wrong_passphrase_without_writing() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let source = paths_in(source_dir.path());
+        let target = paths_in(target_dir.path());
+        let root_key_pair = fluence_keypair::KeyPair::generate_ed25519();
+        populate_source(&source, &root_key_pair).await.unwrap();
+
+        let archive = export_placement_state(&root_key_pair, "correct horse", &source, false)
+            .await
+            .unwrap();
+        let result =
+            import_placement_state(&archive, "battery staple", &target, &mapping(&[10, 11, 12]), 1)
+                .await;
+        assert!(
+            matches!(result, Err(MigrationError::WrongPassphrase { .. })),
+            "expected WrongPassphrase, got {result:?}"
+        );
+        assert!(
+            !target.key_pairs_dir.exists() && !target.core_state_path.exists(),
+            "a refused import must not write anything"
+        );
+    }
+
+    #[tokio::test]
+    async fn import_refuses_tampered_archive() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let source = paths_in(source_dir.path());
+        let target = paths_in(target_dir.path());
+        let root_key_pair = fluence_keypair::KeyPair::generate_ed25519();
+        populate_source(&source, &root_key_pair).await.unwrap();
+
+        let archive = export_placement_state(&root_key_pair, "correct horse", &source, false)
+            .await
+            .unwrap();
+        let mut signed: SignedPlacementArchive = toml_edit::de::from_str(&archive).unwrap();
+        let last = signed.payload.len() - 1;
+        signed.payload[last] ^= 1;
+        let tampered = toml_edit::ser::to_string(&signed).unwrap();
+
+        let result =
+            import_placement_state(&tampered, "correct horse", &target, &mapping(&[10, 11, 12]), 1)
+                .await;
+        assert!(
+            matches!(result, Err(MigrationError::BadSignature { .. })),
+            "expected BadSignature, got {result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn import_refuses_populated_target() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let source = paths_in(source_dir.path());
+        let target = paths_in(target_dir.path());
+        let root_key_pair = fluence_keypair::KeyPair::generate_ed25519();
+        populate_source(&source, &root_key_pair).await.unwrap();
+
+        let archive = export_placement_state(&root_key_pair, "correct horse", &source, false)
+            .await
+            .unwrap();
+
+        // the target node already has a worker keypair of its own
+        tokio::fs::create_dir_all(&target.key_pairs_dir).await.unwrap();
+        let resident = target.key_pairs_dir.join("someworker_keypair.toml");
+        tokio::fs::write(&resident, b"").await.unwrap();
+
+        let result =
+            import_placement_state(&archive, "correct horse", &target, &mapping(&[10, 11, 12]), 1)
+                .await;
+        assert!(
+            matches!(result, Err(MigrationError::TargetNotEmpty { ref path }) if path == &resident),
+            "expected TargetNotEmpty, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn remap_keeps_cores_that_still_exist() {
+        let old = PersistentCoreManagerState {
+            cores_mapping: mapping(&[0, 1, 2, 3]),
+            system_cores: vec![PhysicalCoreId::new(0)],
+            available_cores: vec![],
+            unit_id_mapping: vec![
+                (PhysicalCoreId::new(2), cuid_1()),
+                (PhysicalCoreId::new(0), cuid_2()),
+            ],
+            work_type_mapping: vec![(cuid_1(), WorkType::Deal), (cuid_2(), WorkType::Deal)],
+            acquire_order: vec![cuid_1(), cuid_2()],
+        };
+        // core 2 survives the move, core 0 doesn't exist on the new machine
+        let (state, report) = remap_core_state(&old, &mapping(&[1, 2, 3]), 1).unwrap();
+
+        assert_eq!(report.kept, vec![(cuid_1(), PhysicalCoreId::new(2))]);
+        assert_eq!(report.moved.len(), 1);
+        assert_eq!(report.moved[0].unit_id, cuid_2());
+        assert_eq!(report.moved[0].old_core, PhysicalCoreId::new(0));
+        assert_eq!(report.moved[0].new_core, PhysicalCoreId::new(3));
+        assert_eq!(state.system_cores, vec![PhysicalCoreId::new(1)]);
+        assert!(state.available_cores.is_empty());
+    }
+
+    #[test]
+    fn remap_refuses_topology_without_worker_cores() {
+        let old = PersistentCoreManagerState {
+            cores_mapping: mapping(&[0, 1]),
+            system_cores: vec![PhysicalCoreId::new(0)],
+            available_cores: vec![],
+            unit_id_mapping: vec![(PhysicalCoreId::new(1), cuid_1())],
+            work_type_mapping: vec![(cuid_1(), WorkType::Deal)],
+            acquire_order: vec![cuid_1()],
+        };
+        let result = remap_core_state(&old, &mapping(&[5]), 1).map(|_| ());
+        assert!(
+            matches!(result, Err(MigrationError::NotEnoughCores { available: 1, system: 1 })),
+            "expected NotEnoughCores, got {result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn import_refuses_unsupported_version() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let source = paths_in(source_dir.path());
+        let target = paths_in(target_dir.path());
+        let root_key_pair = fluence_keypair::KeyPair::generate_ed25519();
+        populate_source(&source, &root_key_pair).await.unwrap();
+
+        let archive = export_placement_state(&root_key_pair, "correct horse", &source, false)
+            .await
+            .unwrap();
+        // re-sign the payload with a bumped version: the signature is valid,
+        // but the format is from the future
+        let signed: SignedPlacementArchive = toml_edit::de::from_str(&archive).unwrap();
+        let mut payload: PlacementArchive =
+            toml_edit::de::from_slice(&signed.payload).unwrap();
+        payload.version = ARCHIVE_VERSION + 1;
+        let payload = toml_edit::ser::to_vec(&payload).unwrap();
+        let signature = root_key_pair.sign(&payload).unwrap().to_vec();
+        let future = toml_edit::ser::to_string(&SignedPlacementArchive {
+            host_peer_id: root_key_pair.get_peer_id().to_base58(),
+            payload,
+            signature,
+        })
+        .unwrap();
+
+        let result =
+            import_placement_state(&future, "correct horse", &target, &mapping(&[10, 11, 12]), 1)
+                .await;
+        assert!(
+            matches!(result, Err(MigrationError::UnsupportedVersion { version, supported })
+                if version == ARCHIVE_VERSION + 1 && supported == ARCHIVE_VERSION),
+            "expected UnsupportedVersion, got {result:?}"
+        );
+    }
+}
@@ -84,7 +84,7 @@ pub(crate) fn worker_file_name(worker_id: WorkerId) -> String {
     format!("{}_info.toml", worker_id)
 }
 
-fn is_keypair(path: &Path) -> bool {
+pub(crate) fn is_keypair(path: &Path) -> bool {
     path.file_name()
         .and_then(|n| n.to_str())
         .map_or(false, |n| n.ends_with("_keypair.toml"))
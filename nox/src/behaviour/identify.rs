@@ -19,7 +19,7 @@ use libp2p::{
     core::{multiaddr::Protocol, Multiaddr},
     identify::Event as IdentifyEvent,
 };
-use particle_protocol::PROTOCOL_NAME;
+use particle_protocol::{parse_air_version, PROTOCOL_NAME};
 use tokio::sync::oneshot;
 
 use super::FluenceNetworkBehaviour;
@@ -57,15 +57,19 @@ impl FluenceNetworkBehaviour {
 
                 if supports_fluence {
                     let protocols: Vec<_> = info.protocols.iter().map(|p| p.to_string()).collect();
+                    let air_version = parse_air_version(&info.agent_version);
                     log::debug!(
                         target: "network",
-                        "Found fluence peer {}: protocols: {:?} version: {} listen addrs {:?}",
-                        peer_id, protocols, info.protocol_version, addresses
+                        "Found fluence peer {}: protocols: {:?} version: {} air version: {:?} listen addrs {:?}",
+                        peer_id, protocols, info.protocol_version, air_version, addresses
                     );
                     // Add addresses to connection pool disregarding whether it supports kademlia or not
                     // we want to have full info on non-kademlia peers as well
                     self.connection_pool
                         .add_discovered_addresses(peer_id, addresses.clone());
+                    // Remember the peer's AIR interpreter version, so particles
+                    // from and to incompatible peers are refused
+                    self.connection_pool.set_air_version(peer_id, air_version);
                     if supports_kademlia {
                         self.kademlia.add_kad_node(peer_id, addresses);
                     }
@@ -23,10 +23,12 @@ use libp2p::{
 };
 use tokio::sync::mpsc;
 
-use connection_pool::ConnectionPoolBehaviour;
+use connection_pool::{
+    ClientSessionConfig, ConnectionPoolBehaviour, KeepAliveConfig, QueueSpillConfig,
+};
 use health::HealthCheckRegistry;
 use kademlia::{Kademlia, KademliaConfig};
-use particle_protocol::{ExtendedParticle, PROTOCOL_NAME};
+use particle_protocol::{agent_version, ExtendedParticle, PROTOCOL_NAME};
 use server_config::NetworkConfig;
 
 use crate::connectivity::Connectivity;
@@ -69,7 +71,7 @@ impl FluenceNetworkBehaviour {
         let local_public_key = cfg.key_pair.public();
         let identify = Identify::new(
             IdentifyConfig::new(PROTOCOL_NAME.into(), local_public_key)
-                .with_agent_version(cfg.node_version.into()),
+                .with_agent_version(agent_version(cfg.node_version, cfg.air_version)),
         );
         let ping = Ping::new(PingConfig::new());
 
@@ -79,12 +81,33 @@ impl FluenceNetworkBehaviour {
         };
 
         let (kademlia, kademlia_api) = Kademlia::new(kad_config.into(), cfg.libp2p_metrics);
+        let client_sessions = ClientSessionConfig {
+            relay_addresses: cfg.bootstrap_nodes.iter().cloned().collect(),
+            idle_timeout: cfg.client_idle_timeout,
+            max_sessions: cfg.max_client_sessions,
+        };
+        let queue_spill = cfg.particle_queue_spill.map(|path| QueueSpillConfig {
+            path,
+            max_size: cfg.particle_queue_spill_max_size,
+        });
         let (connection_pool, particle_stream, connection_pool_api) = ConnectionPoolBehaviour::new(
             cfg.particle_queue_buffer,
+            cfg.command_queue_buffer,
             cfg.protocol_config,
             cfg.local_peer_id,
             cfg.connection_pool_metrics,
+            cfg.slow_link_threshold,
+            cfg.air_version_policy,
+            client_sessions,
+            queue_spill,
         );
+        // relay links are re-dialed on unexpected closes (on top of the
+        // periodic bootstrap reconnects), while client connections stay on
+        // the idle-timeout path
+        let connection_pool = connection_pool.with_keep_alive(KeepAliveConfig {
+            worker_peer_ids: std::iter::once(cfg.management_peer_id).collect(),
+            redial_relays: true,
+        });
 
         let connection_limits = ConnectionLimits::new(cfg.connection_limits);
 
@@ -14,10 +14,22 @@
  * limitations under the License.
  */
 
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use aquamarine::ParticleDataStore;
+use fluence_keypair::KeyPair;
 use futures::FutureExt;
-use particle_builtins::{ok, CustomService, NodeInfo};
+use particle_args::{Args, JError};
+use particle_builtins::{ok, wrap, CustomService, NodeInfo};
 use particle_execution::ServiceFunction;
+use peer_metrics::MetricsToggles;
 use serde_json::json;
+use workers::migration::{export_placement_state, PlacementPaths};
+use workers::PeerScopes;
+
+use crate::crash_marker::Disposition;
+use crate::particle_timeline::ParticleTimelineStore;
 
 pub fn make_peer_builtin(node_info: NodeInfo) -> (String, CustomService) {
     (
@@ -34,3 +46,311 @@ fn make_peer_identify_closure(node_info: NodeInfo) -> ServiceFunction {
         async move { ok(json!(node_info)) }.boxed()
     }))
 }
+
+/// `node.last_shutdowns` reports how the last few runs of the node ended
+/// (clean shutdown, crash with a panic report, crash without one), as
+/// classified by the crash marker on startup
+pub fn make_node_builtin(last_shutdowns: Vec<Disposition>) -> (String, CustomService) {
+    (
+        "node".to_string(),
+        CustomService::new(
+            vec![("last_shutdowns", make_last_shutdowns_closure(last_shutdowns))],
+            None,
+        ),
+    )
+}
+
+fn make_last_shutdowns_closure(last_shutdowns: Vec<Disposition>) -> ServiceFunction {
+    ServiceFunction::Immut(Box::new(move |_args, _params| {
+        let last_shutdowns = last_shutdowns.clone();
+        async move { ok(json!(last_shutdowns)) }.boxed()
+    }))
+}
+
+pub fn make_particle_timeline_builtin(
+    timeline: ParticleTimelineStore,
+    scopes: PeerScopes,
+) -> (String, CustomService) {
+    (
+        "debug".to_string(),
+        CustomService::new(
+            vec![(
+                "particle_timeline",
+                make_particle_timeline_closure(timeline, scopes),
+            )],
+            None,
+        ),
+    )
+}
+
+fn make_particle_timeline_closure(
+    timeline: ParticleTimelineStore,
+    scopes: PeerScopes,
+) -> ServiceFunction {
+    ServiceFunction::Immut(Box::new(move |args, params| {
+        let timeline = timeline.clone();
+        let scopes = scopes.clone();
+        async move {
+            let result = if scopes.is_management(params.init_peer_id) {
+                get_particle_timeline(args, timeline)
+            } else {
+                Err(JError::new(
+                    "debug.particle_timeline is available only to the management peer",
+                ))
+            };
+            wrap(result)
+        }
+        .boxed()
+    }))
+}
+
+fn get_particle_timeline(
+    args: Args,
+    timeline: ParticleTimelineStore,
+) -> Result<serde_json::Value, JError> {
+    let mut args = args.function_args.into_iter();
+    let particle_id: String = Args::next("particle_id", &mut args)?;
+    Ok(json!(timeline.get(&particle_id).unwrap_or_default()))
+}
+
+pub fn make_stat_builtin(
+    toggles: MetricsToggles,
+    scopes: PeerScopes,
+    data_store: Arc<ParticleDataStore>,
+) -> (String, CustomService) {
+    (
+        "stat".to_string(),
+        CustomService::new(
+            vec![
+                (
+                    "metrics_config",
+                    make_metrics_config_closure(toggles, scopes.clone()),
+                ),
+                (
+                    "particle_data_usage",
+                    make_particle_data_usage_closure(data_store, scopes),
+                ),
+            ],
+            None,
+        ),
+    )
+}
+
+fn make_particle_data_usage_closure(
+    data_store: Arc<ParticleDataStore>,
+    scopes: PeerScopes,
+) -> ServiceFunction {
+    ServiceFunction::Immut(Box::new(move |_args, params| {
+        let data_store = data_store.clone();
+        let scopes = scopes.clone();
+        async move {
+            let result = if scopes.is_management(params.init_peer_id) {
+                let (workers, total_bytes) = data_store.usage();
+                Ok(json!({ "total_bytes": total_bytes, "workers": workers }))
+            } else {
+                Err(JError::new(
+                    "stat.particle_data_usage is available only to the management peer",
+                ))
+            };
+            wrap(result)
+        }
+        .boxed()
+    }))
+}
+
+fn make_metrics_config_closure(toggles: MetricsToggles, scopes: PeerScopes) -> ServiceFunction {
+    ServiceFunction::Immut(Box::new(move |args, params| {
+        let toggles = toggles.clone();
+        let scopes = scopes.clone();
+        async move {
+            let result = if scopes.is_management(params.init_peer_id) {
+                metrics_config(args, toggles)
+            } else {
+                Err(JError::new(
+                    "stat.metrics_config is available only to the management peer",
+                ))
+            };
+            wrap(result)
+        }
+        .boxed()
+    }))
+}
+
+/// Without arguments lists togglable metric families; with `family` and
+/// `enabled` flips the named family first, then returns the updated list
+fn metrics_config(args: Args, toggles: MetricsToggles) -> Result<serde_json::Value, JError> {
+    let mut args = args.function_args.into_iter();
+    let family: Option<String> = Args::next_opt("family", &mut args)?;
+    let enabled: Option<bool> = Args::next_opt("enabled", &mut args)?;
+    if let Some(family) = family {
+        let enabled = enabled
+            .ok_or_else(|| JError::new("missing field 'enabled': expected a boolean value"))?;
+        if !toggles.set_enabled(&family, enabled) {
+            return Err(JError::new(format!(
+                "there is no togglable metric family '{family}'"
+            )));
+        }
+    }
+    let families: Vec<_> = toggles
+        .list()
+        .into_iter()
+        .map(|(name, enabled)| json!({ "name": name, "enabled": enabled }))
+        .collect();
+    Ok(json!(families))
+}
+
+/// Exports the node placement state (worker keys, deal bindings, core mapping,
+/// module/blueprint indexes) into a signed archive for migration to new
+/// hardware; available only to the management peer. The import counterpart is
+/// `workers::migration::import_placement_state`, run on a stopped fresh node
+pub fn make_migration_builtin(
+    root_key_pair: KeyPair,
+    paths: PlacementPaths,
+    archive_dir: PathBuf,
+    scopes: PeerScopes,
+) -> (String, CustomService) {
+    (
+        "migration".to_string(),
+        CustomService::new(
+            vec![(
+                "export",
+                make_migration_export_closure(root_key_pair, paths, archive_dir, scopes),
+            )],
+            None,
+        ),
+    )
+}
+
+fn make_migration_export_closure(
+    root_key_pair: KeyPair,
+    paths: PlacementPaths,
+    archive_dir: PathBuf,
+    scopes: PeerScopes,
+) -> ServiceFunction {
+    ServiceFunction::Immut(Box::new(move |args, params| {
+        let root_key_pair = root_key_pair.clone();
+        let paths = paths.clone();
+        let archive_dir = archive_dir.clone();
+        let scopes = scopes.clone();
+        async move {
+            let result = if scopes.is_management(params.init_peer_id) {
+                export_placement_archive(args, root_key_pair, paths, archive_dir).await
+            } else {
+                Err(JError::new(
+                    "migration.export is available only to the management peer",
+                ))
+            };
+            wrap(result)
+        }
+        .boxed()
+    }))
+}
+
+/// Writes the archive next to the node state and returns its path: the secret
+/// keys inside are passphrase-encrypted, so the archive itself never travels
+/// through the particle
+async fn export_placement_archive(
+    args: Args,
+    root_key_pair: KeyPair,
+    paths: PlacementPaths,
+    archive_dir: PathBuf,
+) -> Result<serde_json::Value, JError> {
+    let mut args = args.function_args.into_iter();
+    let passphrase: String = Args::next("passphrase", &mut args)?;
+    let include_wasm: Option<bool> = Args::next_opt("include_wasm", &mut args)?;
+    let archive = export_placement_state(
+        &root_key_pair,
+        &passphrase,
+        &paths,
+        include_wasm.unwrap_or(false),
+    )
+    .await
+    .map_err(|err| JError::new(format!("failed to export placement state: {err}")))?;
+    let path = archive_dir.join("placement_archive.toml");
+    tokio::fs::write(&path, archive)
+        .await
+        .map_err(|err| JError::new(format!("failed to write the archive to {path:?}: {err}")))?;
+    Ok(json!({ "path": path }))
+}
+
+/// Failure injection controls for integration tests; compiled only under the
+/// `chaos` feature and available only to the management peer
+#[cfg(feature = "chaos")]
+pub fn make_chaos_builtin(scopes: PeerScopes) -> (String, CustomService) {
+    (
+        "chaos".to_string(),
+        CustomService::new(
+            vec![
+                ("install", make_chaos_closure(scopes.clone(), chaos_install)),
+                ("clear", make_chaos_closure(scopes.clone(), chaos_clear)),
+                ("list", make_chaos_closure(scopes, chaos_list)),
+            ],
+            None,
+        ),
+    )
+}
+
+#[cfg(feature = "chaos")]
+fn make_chaos_closure(
+    scopes: PeerScopes,
+    function: fn(Args) -> Result<serde_json::Value, JError>,
+) -> ServiceFunction {
+    ServiceFunction::Immut(Box::new(move |args, params| {
+        let scopes = scopes.clone();
+        async move {
+            let result = if scopes.is_management(params.init_peer_id) {
+                function(args)
+            } else {
+                Err(JError::new(
+                    "chaos builtins are available only to the management peer",
+                ))
+            };
+            wrap(result)
+        }
+        .boxed()
+    }))
+}
+
+#[cfg(feature = "chaos")]
+fn chaos_install(args: Args) -> Result<serde_json::Value, JError> {
+    use std::time::Duration;
+
+    let mut args = args.function_args.into_iter();
+    let point: String = Args::next("point", &mut args)?;
+    let kind: String = Args::next("kind", &mut args)?;
+    let amount: u64 = Args::next("amount", &mut args)?;
+    let rule = match kind.as_str() {
+        "fail_next" => chaos::FaultRule::FailNext(amount as usize),
+        "fail_for_ms" => chaos::FaultRule::FailFor(Duration::from_millis(amount)),
+        "delay_ms" => chaos::FaultRule::Delay(Duration::from_millis(amount)),
+        other => {
+            return Err(JError::new(format!(
+                "unknown fault rule kind '{other}'; expected 'fail_next', 'fail_for_ms' or 'delay_ms'"
+            )))
+        }
+    };
+    chaos::install(&point, rule);
+    Ok(json!(null))
+}
+
+#[cfg(feature = "chaos")]
+fn chaos_clear(args: Args) -> Result<serde_json::Value, JError> {
+    let mut args = args.function_args.into_iter();
+    let point: Option<String> = Args::next_opt("point", &mut args)?;
+    match point {
+        Some(point) => Ok(json!(chaos::clear(&point))),
+        None => {
+            chaos::clear_all();
+            Ok(json!(true))
+        }
+    }
+}
+
+#[cfg(feature = "chaos")]
+fn chaos_list(_args: Args) -> Result<serde_json::Value, JError> {
+    let rules: Vec<_> = chaos::list()
+        .into_iter()
+        .map(|(point, rule)| json!({ "point": point, "rule": rule }))
+        .collect();
+    Ok(json!(rules))
+}
@@ -16,11 +16,12 @@
 
 use std::cmp::min;
 use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::health::ConnectivityHealth;
 use connection_pool::{ConnectionPoolApi, ConnectionPoolT, LifecycleEvent};
-use fluence_libp2p::PeerId;
+use fluence_libp2p::{LogRateLimit, LogRateLimiter, PeerId};
 use futures::{stream::iter, StreamExt};
 use humantime_serde::re::humantime::format_duration as pretty;
 use kademlia::{KademliaApi, KademliaApiT, KademliaError};
@@ -260,10 +261,15 @@ impl Connectivity {
         // TODO: exponential backoff + random?
         let delta = Duration::from_secs(5);
 
+        // a silent bootstrap produces a failed dial every few seconds;
+        // log the first one in full and then only periodic summaries
+        let log_limiter = Arc::new(LogRateLimiter::default());
+
         let reconnect = move |kademlia: KademliaApi,
                               pool: ConnectionPoolApi,
                               addr: Multiaddr,
                               parent_span: Span| {
+            let log_limiter = log_limiter.clone();
             (async move {
                 let mut delay = Duration::from_secs(0);
                 loop {
@@ -280,7 +286,19 @@ impl Connectivity {
                     }
 
                     delay = min(delay + delta, max);
-                    log::warn!("can't connect bootstrap {} (pause {})", addr, pretty(delay));
+                    match log_limiter.observe("bootstrap_reconnect", &addr.to_string()) {
+                        LogRateLimit::Emit => {
+                            log::warn!("can't connect bootstrap {} (pause {})", addr, pretty(delay))
+                        }
+                        LogRateLimit::Summary { repeats, window } => log::warn!(
+                            "can't connect bootstrap {} (pause {}, repeated {} times in the last {})",
+                            addr,
+                            pretty(delay),
+                            repeats,
+                            pretty(window)
+                        ),
+                        LogRateLimit::Suppress => {}
+                    }
                     sleep(delay).await;
                 }
             })
@@ -0,0 +1,289 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Crash markers: a small state file that lets the node tell apart a clean
+//! shutdown from a crash on the next start, without spelunking journald.
+//!
+//! On startup the previous marker is classified into a [`Disposition`] and a
+//! fresh "running" marker is written; the orchestrated shutdown path replaces
+//! it with a "clean" record. A marker still saying "running" on the next start
+//! means the previous run died; if the panic hook managed to write a panic
+//! report, its summary is attached to the disposition.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use now_millis::now_sec;
+use serde::{Deserialize, Serialize};
+
+const MARKER_FILE: &str = "shutdown_marker.json";
+const PANIC_REPORT_FILE: &str = "panic_report.txt";
+/// How many past dispositions are kept in the marker
+const HISTORY_LEN: usize = 3;
+
+/// What we know about how a past run of the node ended; most recent first
+/// in [`CrashMarker::last_dispositions`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "disposition", rename_all = "snake_case")]
+pub enum Disposition {
+    /// The run went through the orchestrated shutdown path
+    Clean {
+        started_at: u64,
+        uptime_sec: u64,
+        shutdown_duration_sec: f64,
+    },
+    /// The run never reached the shutdown path: a panic, an OOM kill
+    /// or a power loss. `panic` holds the panic report summary if the
+    /// panic hook managed to write one
+    Unclean {
+        started_at: Option<u64>,
+        panic: Option<String>,
+    },
+    /// No marker found: the first start in this data dir
+    Missing,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum MarkerState {
+    Running {
+        started_at: u64,
+    },
+    Clean {
+        started_at: u64,
+        uptime_sec: u64,
+        shutdown_duration_sec: f64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Marker {
+    #[serde(flatten)]
+    state: MarkerState,
+    /// Dispositions of past runs, most recent first
+    #[serde(default)]
+    history: Vec<Disposition>,
+}
+
+pub struct CrashMarker {
+    marker_path: PathBuf,
+    started: Instant,
+    started_at: u64,
+    /// Disposition of the previous run first, then older ones
+    history: Vec<Disposition>,
+}
+
+impl CrashMarker {
+    /// Classify how the previous run ended and write a fresh "running" marker.
+    /// The panic report, if any, is consumed so it can't be attributed twice
+    pub fn start(data_dir: &Path) -> io::Result<Self> {
+        let marker_path = data_dir.join(MARKER_FILE);
+        let panic_report_path = data_dir.join(PANIC_REPORT_FILE);
+
+        let previous = match std::fs::read(&marker_path) {
+            Ok(bytes) => match serde_json::from_slice::<Marker>(&bytes) {
+                Ok(marker) => Some(marker),
+                // an unreadable marker is as suspicious as a "running" one
+                Err(_) => Some(Marker {
+                    state: MarkerState::Running { started_at: 0 },
+                    history: vec![],
+                }),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+
+        let panic = match std::fs::read_to_string(&panic_report_path) {
+            Ok(report) => {
+                std::fs::remove_file(&panic_report_path).ok();
+                report.lines().next().map(|line| line.to_string())
+            }
+            Err(_) => None,
+        };
+
+        let (disposition, older) = match previous {
+            None => (Disposition::Missing, vec![]),
+            Some(marker) => {
+                let disposition = match marker.state {
+                    MarkerState::Clean {
+                        started_at,
+                        uptime_sec,
+                        shutdown_duration_sec,
+                    } => Disposition::Clean {
+                        started_at,
+                        uptime_sec,
+                        shutdown_duration_sec,
+                    },
+                    MarkerState::Running { started_at } => Disposition::Unclean {
+                        started_at: (started_at > 0).then_some(started_at),
+                        panic,
+                    },
+                };
+                (disposition, marker.history)
+            }
+        };
+
+        let mut history = vec![disposition];
+        history.extend(older);
+        history.truncate(HISTORY_LEN);
+
+        let marker = Self {
+            marker_path,
+            started: Instant::now(),
+            started_at: now_sec(),
+            history,
+        };
+        marker.write(MarkerState::Running {
+            started_at: marker.started_at,
+        })?;
+        Ok(marker)
+    }
+
+    /// Dispositions of up to [`HISTORY_LEN`] past runs, most recent first
+    pub fn last_dispositions(&self) -> &[Disposition] {
+        &self.history
+    }
+
+    /// Whether the most recent past run ended without a clean shutdown
+    pub fn unclean_restart(&self) -> bool {
+        matches!(self.history.first(), Some(Disposition::Unclean { .. }))
+    }
+
+    /// Replace the marker with a "clean" record; call at the very end
+    /// of the orchestrated shutdown path
+    pub fn clean_shutdown(&self, shutdown_started: Instant) -> io::Result<()> {
+        self.write(MarkerState::Clean {
+            started_at: self.started_at,
+            uptime_sec: self.started.elapsed().as_secs(),
+            shutdown_duration_sec: shutdown_started.elapsed().as_secs_f64(),
+        })
+    }
+
+    fn write(&self, state: MarkerState) -> io::Result<()> {
+        let marker = Marker {
+            state,
+            history: self.history.clone(),
+        };
+        let bytes = serde_json::to_vec_pretty(&marker).expect("serialize shutdown marker");
+        std::fs::write(&self.marker_path, bytes)
+    }
+}
+
+/// Install a panic hook that writes the panic message and backtrace into
+/// `data_dir`, so the next start can attribute the unclean shutdown.
+/// Chains to the previously installed hook
+pub fn install_panic_report_hook(data_dir: &Path) {
+    let path = data_dir.join(PANIC_REPORT_FILE);
+    let prev_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        let report = format!("{panic_info}\n\nbacktrace:\n{backtrace}");
+        // if the report can't be written there is nothing better to do:
+        // the run is still recorded as unclean, just without the reason
+        std::fs::write(&path, report).ok();
+        prev_hook(panic_info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_start_is_recorded_as_missing() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+
+        let marker = CrashMarker::start(dir.path()).expect("start marker");
+
+        assert_eq!(marker.last_dispositions(), [Disposition::Missing]);
+        assert!(!marker.unclean_restart());
+        // the marker left on disk says "running" now
+        let on_disk = std::fs::read_to_string(dir.path().join(MARKER_FILE)).unwrap();
+        assert!(on_disk.contains("\"state\": \"running\""), "{on_disk}");
+    }
+
+    #[test]
+    fn clean_shutdown_is_recognized_on_the_next_start() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+
+        let marker = CrashMarker::start(dir.path()).expect("start marker");
+        marker
+            .clean_shutdown(Instant::now())
+            .expect("write clean marker");
+        let started_at = marker.started_at;
+        drop(marker);
+
+        let marker = CrashMarker::start(dir.path()).expect("restart marker");
+        match marker.last_dispositions() {
+            [Disposition::Clean {
+                started_at: recorded,
+                ..
+            }, Disposition::Missing] => assert_eq!(*recorded, started_at),
+            other => panic!("expected [Clean, Missing], got {other:?}"),
+        }
+        assert!(!marker.unclean_restart());
+    }
+
+    #[test]
+    fn running_marker_with_a_panic_report_means_unclean_restart() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+
+        // the previous run died right after this point
+        let marker = CrashMarker::start(dir.path()).expect("start marker");
+        let started_at = marker.started_at;
+        drop(marker);
+        std::fs::write(
+            dir.path().join(PANIC_REPORT_FILE),
+            "panicked at 'boom', nox/src/main.rs:1:1\n\nbacktrace:\n...",
+        )
+        .unwrap();
+
+        let marker = CrashMarker::start(dir.path()).expect("restart marker");
+        assert!(marker.unclean_restart());
+        match marker.last_dispositions().first() {
+            Some(Disposition::Unclean {
+                started_at: Some(recorded),
+                panic: Some(panic),
+            }) => {
+                assert_eq!(*recorded, started_at);
+                assert_eq!(panic, "panicked at 'boom', nox/src/main.rs:1:1");
+            }
+            other => panic!("expected an Unclean disposition with a panic, got {other:?}"),
+        }
+        // the report is consumed: a second restart must not see it again
+        assert!(!dir.path().join(PANIC_REPORT_FILE).exists());
+    }
+
+    #[test]
+    fn history_is_capped_at_three_dispositions() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+
+        for _ in 0..5 {
+            let marker = CrashMarker::start(dir.path()).expect("start marker");
+            marker
+                .clean_shutdown(Instant::now())
+                .expect("write clean marker");
+        }
+
+        let marker = CrashMarker::start(dir.path()).expect("restart marker");
+        assert_eq!(marker.last_dispositions().len(), HISTORY_LEN);
+        assert!(marker
+            .last_dispositions()
+            .iter()
+            .all(|d| matches!(d, Disposition::Clean { .. })));
+    }
+}
@@ -14,7 +14,15 @@
  * limitations under the License.
  */
 
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
 use futures::{FutureExt, StreamExt};
+use health::HealthCheckRegistry;
 use prometheus_client::registry::Registry;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
@@ -26,10 +34,148 @@ use particle_protocol::{ExtendedParticle, Particle};
 use peer_metrics::DispatcherMetrics;
 
 use crate::effectors::Effectors;
-use crate::tasks::Tasks;
+use crate::tasks::{Tasks, TasksHealth};
 
 type Effects = Result<RemoteRoutingEffects, AquamarineApiError>;
 
+/// How many particles the deadline-ordering stage may hold at once
+const REORDER_BUFFER_DEPTH: usize = 128;
+/// Every Nth yielded slot goes to the oldest waiting particle instead of the
+/// most urgent one, so a steady stream of short-TTL particles can't starve
+/// long-TTL ones entirely
+const FAIRNESS_EVERY: u64 = 8;
+/// Base delay before re-executing failed routing effects; doubles per attempt
+const EFFECT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Observes the dispatcher from the outside: implementations are called on
+/// every particle taken off the particle stream and on every batch of routing
+/// effects that was fully delivered. Register custom hooks (span processors,
+/// counters, audit trails) with [`Dispatcher::with_observability_hooks`] —
+/// dispatch logic itself stays untouched
+pub trait ObservabilityHook {
+    /// Called exactly once for every particle received from the stream,
+    /// before the expiry check, in the particle's tracing span
+    fn on_particle_received(&self, particle: &ExtendedParticle);
+    /// Called once the routing effects of a particle have been executed and
+    /// every next peer got the particle; not called for deliveries that
+    /// exhausted their retries or panicked
+    fn on_effects_applied(&self, effects: &RemoteRoutingEffects);
+}
+
+/// The stock observability behavior, installed on every dispatcher: emits
+/// tracing events for received particles and applied effects. Hooks added
+/// with [`Dispatcher::with_observability_hooks`] run in addition to it
+#[derive(Default)]
+pub struct DefaultObservabilityHook;
+
+impl ObservabilityHook for DefaultObservabilityHook {
+    fn on_particle_received(&self, particle: &ExtendedParticle) {
+        tracing::trace!(
+            target: "dispatcher",
+            particle_id = particle.particle.id,
+            "particle received"
+        );
+    }
+
+    fn on_effects_applied(&self, effects: &RemoteRoutingEffects) {
+        tracing::trace!(
+            target: "dispatcher",
+            particle_id = effects.particle.particle.id,
+            next_peers = effects.next_peers.len(),
+            "routing effects applied"
+        );
+    }
+}
+
+type Hooks = Vec<Arc<dyn ObservabilityHook + Send + Sync>>;
+
+/// Buffers up to `depth` particles from the source and yields the one closest
+/// to its TTL deadline first. Ordering is best-effort: the stage only reorders
+/// what has already arrived, it never waits for more urgent particles to show up
+struct DeadlineOrdered<Src> {
+    source: Src,
+    source_done: bool,
+    /// (arrival order, particle); arrival order drives the fairness slots
+    buffer: Vec<(u64, ExtendedParticle)>,
+    depth: usize,
+    next_arrival: u64,
+    yielded: u64,
+    metrics: Option<DispatcherMetrics>,
+}
+
+impl<Src> DeadlineOrdered<Src> {
+    fn new(source: Src, depth: usize, metrics: Option<DispatcherMetrics>) -> Self {
+        Self {
+            source,
+            source_done: false,
+            buffer: Vec::with_capacity(depth),
+            depth,
+            next_arrival: 0,
+            yielded: 0,
+            metrics,
+        }
+    }
+}
+
+fn index_of_min<K: Ord>(
+    buffer: &[(u64, ExtendedParticle)],
+    key: impl Fn(&(u64, ExtendedParticle)) -> K,
+) -> usize {
+    buffer
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| key(entry))
+        .map(|(idx, _)| idx)
+        .expect("buffer is not empty")
+}
+
+impl<Src> futures::Stream for DeadlineOrdered<Src>
+where
+    Src: futures::Stream<Item = ExtendedParticle> + Unpin,
+{
+    type Item = ExtendedParticle;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        // top the buffer up with everything the source has ready
+        while !this.source_done && this.buffer.len() < this.depth {
+            match Pin::new(&mut this.source).poll_next(cx) {
+                Poll::Ready(Some(particle)) => {
+                    this.buffer.push((this.next_arrival, particle));
+                    this.next_arrival += 1;
+                }
+                Poll::Ready(None) => this.source_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.buffer.is_empty() {
+            return if this.source_done {
+                Poll::Ready(None)
+            } else {
+                // the source poll above has registered the waker
+                Poll::Pending
+            };
+        }
+
+        this.yielded += 1;
+        let idx = if this.yielded % FAIRNESS_EVERY == 0 {
+            // fairness slot: take the particle that has waited the longest
+            index_of_min(&this.buffer, |(arrival, _)| *arrival)
+        } else {
+            index_of_min(&this.buffer, |(_, particle)| {
+                particle.particle.time_to_live()
+            })
+        };
+        let (_, particle) = this.buffer.swap_remove(idx);
+        if let Some(m) = this.metrics.as_ref() {
+            m.reorder_buffer_size(this.buffer.len());
+            m.observe_remaining_ttl(particle.particle.time_to_live());
+        }
+        Poll::Ready(Some(particle))
+    }
+}
+
 #[derive(Clone)]
 pub struct Dispatcher {
     #[allow(unused)]
@@ -38,7 +184,16 @@ pub struct Dispatcher {
     particle_parallelism: Option<usize>,
     aquamarine: AquamarineApi,
     effectors: Effectors,
+    /// How many times failed routing effects are re-executed before the
+    /// particle is given up on
+    effect_retries: usize,
+    /// Effects that exhausted their retries go here instead of being dropped
+    dead_letter: Option<mpsc::Sender<RemoteRoutingEffects>>,
     metrics: Option<DispatcherMetrics>,
+    /// Observers of received particles and applied effects; always contains
+    /// [`DefaultObservabilityHook`], custom hooks are appended after it
+    hooks: Hooks,
+    tasks_health: TasksHealth,
 }
 
 impl Dispatcher {
@@ -47,16 +202,34 @@ impl Dispatcher {
         aquamarine: AquamarineApi,
         effectors: Effectors,
         particle_parallelism: Option<usize>,
+        effect_retries: usize,
+        dead_letter: Option<mpsc::Sender<RemoteRoutingEffects>>,
         registry: Option<&mut Registry>,
+        health_registry: Option<&mut HealthCheckRegistry>,
     ) -> Self {
+        let tasks_health = TasksHealth::default();
+        if let Some(health) = health_registry {
+            health.register("dispatcher_tasks", tasks_health.clone());
+        }
         Self {
             peer_id,
             effectors,
             aquamarine,
             particle_parallelism,
+            effect_retries,
+            dead_letter,
             metrics: registry.map(|r| DispatcherMetrics::new(r, particle_parallelism)),
+            hooks: vec![Arc::new(DefaultObservabilityHook)],
+            tasks_health,
         }
     }
+
+    /// Appends custom observability hooks to the stock ones. Hooks are called
+    /// in registration order
+    pub fn with_observability_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks.extend(hooks);
+        self
+    }
 }
 
 impl Dispatcher {
@@ -66,6 +239,7 @@ impl Dispatcher {
         effects_stream: mpsc::Receiver<Effects>,
     ) -> Tasks {
         log::info!("starting dispatcher");
+        let tasks_health = self.tasks_health.clone();
         let particle_stream = ReceiverStream::new(particle_stream);
         let effects_stream = ReceiverStream::new(effects_stream);
         let particles = tokio::task::Builder::new()
@@ -81,7 +255,7 @@ impl Dispatcher {
             .spawn(self.process_effects(effects_stream).in_current_span())
             .expect("Could not spawn task");
 
-        Tasks::new("Dispatcher", vec![particles, effects])
+        Tasks::with_health("Dispatcher", vec![particles, effects], tasks_health)
     }
 
     pub async fn process_particles<Src>(self, particle_stream: Src)
@@ -91,13 +265,48 @@ impl Dispatcher {
         let parallelism = self.particle_parallelism;
         let aquamarine = self.aquamarine;
         let metrics = self.metrics;
+        // schedule particles closest to expiry first so they don't die
+        // in the queue behind long-TTL ones
+        let particle_stream =
+            DeadlineOrdered::new(particle_stream, REORDER_BUFFER_DEPTH, metrics.clone());
+        Self::process_particles_with(
+            particle_stream,
+            move |ext_particle| {
+                aquamarine
+                    .clone()
+                    .execute(ext_particle, None)
+                    // do not log errors: Aquamarine will log them fine
+                    .map(|_| ())
+            },
+            parallelism,
+            metrics,
+            self.hooks,
+        )
+        .await
+    }
+
+    /// Processes particles from the stream with `execute`, isolating panics:
+    /// a panic in one particle is logged and counted, the stream goes on
+    async fn process_particles_with<Src, F, Fut>(
+        particle_stream: Src,
+        execute: F,
+        parallelism: Option<usize>,
+        metrics: Option<DispatcherMetrics>,
+        hooks: Hooks,
+    ) where
+        Src: futures::Stream<Item = ExtendedParticle> + Unpin + Send + Sync + 'static,
+        F: Fn(ExtendedParticle) -> Fut + Send + Sync,
+        Fut: Future<Output = ()> + Send,
+    {
         particle_stream
             .for_each_concurrent(parallelism, move |ext_particle| {
                 let current_span = tracing::info_span!(parent: ext_particle.span.as_ref(), "Dispatcher::process_particles::for_each");
                 let _ = current_span.enter();
                 let async_span = tracing::info_span!("Dispatcher::process_particles::async");
-                let aquamarine = aquamarine.clone();
                 let metrics = metrics.clone();
+                for hook in hooks.iter() {
+                    hook.on_particle_received(&ext_particle);
+                }
                 let particle: &Particle = ext_particle.as_ref();
 
                 if particle.is_expired() {
@@ -109,12 +318,20 @@ impl Dispatcher {
                     return async {}.boxed();
                 }
 
+                let particle_id = particle.id.clone();
+                let init_peer_id = particle.init_peer_id;
+                let fut = execute(ext_particle);
                 async move {
-                    aquamarine
-                        .execute(ext_particle, None)
-                        // do not log errors: Aquamarine will log them fine
-                        .map(|_| ())
-                        .await
+                    // a panicking particle must not bring down the whole `particles` task
+                    if let Err(panic) = AssertUnwindSafe(fut).catch_unwind().await {
+                        log::error!(
+                            "Panic while processing particle {particle_id} from {init_peer_id}: {}",
+                            panic_message(panic.as_ref())
+                        );
+                        if let Some(m) = metrics {
+                            m.particle_processing_panicked();
+                        }
+                    }
                 }
                     .instrument(async_span)
                 .boxed()
@@ -131,23 +348,106 @@ impl Dispatcher {
     {
         let parallelism = self.particle_parallelism;
         let effectors = self.effectors;
+        let execute = move |effects: RemoteRoutingEffects| {
+            let async_span = tracing::info_span!(parent: effects.particle.span.as_ref(), "Dispatcher::effectors::execute");
+            // perform effects as instructed by aquamarine
+            effectors.clone().execute(effects).instrument(async_span)
+        };
+        Self::process_effects_with(
+            effects_stream,
+            execute,
+            parallelism,
+            self.effect_retries,
+            self.dead_letter,
+            self.metrics,
+            self.hooks,
+        )
+        .await
+    }
+
+    /// Executes effects from the stream with `execute`, re-executing failed
+    /// deliveries with exponential backoff up to `retries` times per particle,
+    /// and only towards the peers that have not got the particle yet. Effects
+    /// that exhaust the budget go to the `dead_letter` channel when one is
+    /// configured, otherwise they are dropped. Panics are isolated the same
+    /// way as in particle processing and are not retried
+    async fn process_effects_with<Src, F, Fut>(
+        effects_stream: Src,
+        execute: F,
+        parallelism: Option<usize>,
+        retries: usize,
+        dead_letter: Option<mpsc::Sender<RemoteRoutingEffects>>,
+        metrics: Option<DispatcherMetrics>,
+        hooks: Hooks,
+    ) where
+        Src: futures::Stream<Item = Effects> + Unpin + Send + Sync + 'static,
+        F: Fn(RemoteRoutingEffects) -> Fut + Send + Sync,
+        Fut: Future<Output = Vec<PeerId>> + Send,
+    {
+        let execute = Arc::new(execute);
         effects_stream
             .for_each_concurrent(parallelism, move |effects| {
-                let effectors = effectors.clone();
+                let execute = execute.clone();
+                let dead_letter = dead_letter.clone();
+                let metrics = metrics.clone();
+                let hooks = hooks.clone();
 
                 async move {
-                    match effects {
-                        Ok(effects) => {
-                            let async_span = tracing::info_span!(parent: effects.particle.span.as_ref(), "Dispatcher::effectors::execute");
-                            // perform effects as instructed by aquamarine
-                            effectors.execute(effects).instrument(async_span).await;
-                        }
+                    let mut effects = match effects {
+                        Ok(effects) => effects,
                         Err(err) => {
                             // particles are sent in fire and forget fashion, so
                             // there's nothing to do here but log
                             log::warn!("Error executing particle: {}", err);
+                            return;
                         }
                     };
+                    let particle_id = effects.particle.particle.id.clone();
+                    let init_peer_id = effects.particle.particle.init_peer_id;
+
+                    for attempt in 0..=retries {
+                        let fut = execute(effects.clone());
+                        let failed = match AssertUnwindSafe(fut).catch_unwind().await {
+                            Ok(failed) => failed,
+                            Err(panic) => {
+                                log::error!(
+                                    "Panic while executing effects of particle {particle_id} from {init_peer_id}: {}",
+                                    panic_message(panic.as_ref())
+                                );
+                                if let Some(m) = metrics.as_ref() {
+                                    m.particle_processing_panicked();
+                                }
+                                return;
+                            }
+                        };
+                        if failed.is_empty() {
+                            for hook in hooks.iter() {
+                                hook.on_effects_applied(&effects);
+                            }
+                            return;
+                        }
+                        // retry only towards the peers that are still undelivered
+                        effects.next_peers = failed;
+                        if attempt < retries {
+                            let backoff = EFFECT_RETRY_BACKOFF * 2u32.pow(attempt as u32);
+                            log::warn!(
+                                "Failed to deliver particle {particle_id} to {:?}, retry {} of {retries} in {backoff:?}",
+                                effects.next_peers,
+                                attempt + 1,
+                            );
+                            tokio::time::sleep(backoff).await;
+                        }
+                    }
+
+                    log::error!(
+                        "Giving up on particle {particle_id} from {init_peer_id} after {retries} retries, undelivered to {:?}",
+                        effects.next_peers
+                    );
+                    if let Some(dead_letter) = dead_letter {
+                        if dead_letter.send(effects).await.is_err() {
+                            log::warn!("Dead-letter channel is closed, particle {particle_id} is dropped");
+                        }
+                    }
                 }
             })
             .await;
@@ -155,3 +455,295 @@ impl Dispatcher {
         log::error!("Effects stream has ended");
     }
 }
+
+/// Extracts a printable message from a `catch_unwind` payload
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&'static str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<opaque panic payload>")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use futures::stream;
+    use parking_lot::Mutex;
+    use prometheus_client::registry::Registry;
+
+    use super::*;
+
+    fn particle_with_ttl(id: &str, ttl: u32) -> ExtendedParticle {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_millis() as u64;
+        let particle = Particle {
+            id: id.to_string(),
+            timestamp: now,
+            ttl,
+            ..<_>::default()
+        };
+        ExtendedParticle::new(particle, tracing::Span::none())
+    }
+
+    fn particle(id: &str) -> ExtendedParticle {
+        particle_with_ttl(id, u32::MAX)
+    }
+
+    #[tokio::test]
+    async fn panic_in_one_particle_does_not_stop_processing() {
+        let mut registry = Registry::default();
+        let metrics = DispatcherMetrics::new(&mut registry, None);
+        let processed: Arc<Mutex<Vec<String>>> = <_>::default();
+
+        let particles = stream::iter(vec![
+            particle("before"),
+            particle("boom"),
+            particle("after"),
+        ]);
+        let execute = {
+            let processed = processed.clone();
+            move |ext_particle: ExtendedParticle| {
+                let processed = processed.clone();
+                async move {
+                    let id = ext_particle.particle.id;
+                    if id == "boom" {
+                        panic!("bug in particle processing");
+                    }
+                    processed.lock().push(id);
+                }
+            }
+        };
+
+        Dispatcher::process_particles_with(particles, execute, None, Some(metrics.clone()), vec![])
+            .await;
+
+        assert_eq!(*processed.lock(), vec!["before", "after"]);
+        assert_eq!(metrics.particle_processing_panics.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn fairness_slot_prevents_starvation() {
+        // one long-TTL particle arrives first, then a flood of short-TTL ones
+        let mut particles = vec![particle_with_ttl("old", 100_000)];
+        for i in 0..10 {
+            particles.push(particle_with_ttl(&format!("short-{i}"), 1_000));
+        }
+        let ordered = DeadlineOrdered::new(stream::iter(particles), 128, None);
+        let order: Vec<String> = ordered.map(|p| p.particle.id.clone()).collect().await;
+
+        // every FAIRNESS_EVERY-th slot goes to the oldest waiting particle,
+        // so the long one runs in that slot instead of dead last
+        assert_eq!(order[FAIRNESS_EVERY as usize - 1], "old");
+        assert_eq!(order.len(), 11);
+    }
+
+    /// Four long-TTL particles followed by two particles that expire in 200ms.
+    /// With one execution slot taking 100ms per particle, FIFO lets the short
+    /// ones die in the queue while deadline ordering runs them first
+    fn mixed_ttl_particles() -> Vec<ExtendedParticle> {
+        let mut particles: Vec<_> = (0..4)
+            .map(|i| particle_with_ttl(&format!("long-{i}"), 10_000))
+            .collect();
+        particles.push(particle_with_ttl("short-0", 200));
+        particles.push(particle_with_ttl("short-1", 200));
+        particles
+    }
+
+    fn slow_executor(
+        processed: Arc<Mutex<Vec<String>>>,
+    ) -> impl Fn(ExtendedParticle) -> futures::future::BoxFuture<'static, ()> + Send + Sync {
+        move |ext_particle: ExtendedParticle| {
+            let processed = processed.clone();
+            async move {
+                processed.lock().push(ext_particle.particle.id);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            .boxed()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn deadline_ordering_saves_short_ttl_particles() {
+        let mut registry = Registry::default();
+
+        // FIFO baseline: the short-TTL particles expire in the queue
+        let fifo_metrics = DispatcherMetrics::new(&mut registry, None);
+        let fifo_processed: Arc<Mutex<Vec<String>>> = <_>::default();
+        Dispatcher::process_particles_with(
+            stream::iter(mixed_ttl_particles()),
+            slow_executor(fifo_processed.clone()),
+            Some(1),
+            Some(fifo_metrics),
+            vec![],
+        )
+        .await;
+        let fifo_processed = fifo_processed.lock().clone();
+        assert!(
+            !fifo_processed.iter().any(|id| id.starts_with("short")),
+            "FIFO must lose the short-TTL particles, processed {fifo_processed:?}"
+        );
+
+        // deadline ordering: the short-TTL particles run first and survive
+        let mut registry = Registry::default();
+        let ordered_metrics = DispatcherMetrics::new(&mut registry, None);
+        let ordered_processed: Arc<Mutex<Vec<String>>> = <_>::default();
+        let ordered = DeadlineOrdered::new(
+            stream::iter(mixed_ttl_particles()),
+            REORDER_BUFFER_DEPTH,
+            Some(ordered_metrics.clone()),
+        );
+        Dispatcher::process_particles_with(
+            ordered,
+            slow_executor(ordered_processed.clone()),
+            Some(1),
+            Some(ordered_metrics),
+            vec![],
+        )
+        .await;
+        let ordered_processed = ordered_processed.lock().clone();
+        assert_eq!(
+            &ordered_processed[..2],
+            ["short-0".to_string(), "short-1".to_string()],
+            "the most urgent particles must be scheduled first"
+        );
+        assert_eq!(
+            ordered_processed.len(),
+            6,
+            "no particle expired with deadline ordering"
+        );
+    }
+
+    fn routing_effects(id: &str, next_peers: Vec<PeerId>) -> RemoteRoutingEffects {
+        RemoteRoutingEffects {
+            particle: particle(id),
+            next_peers,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flaky_effector_eventually_delivers_within_retry_budget() {
+        let target = PeerId::random();
+        let effects: Vec<Effects> = vec![Ok(routing_effects("flaky", vec![target]))];
+
+        let attempts: Arc<Mutex<usize>> = <_>::default();
+        let execute = {
+            let attempts = attempts.clone();
+            move |effects: RemoteRoutingEffects| {
+                let attempts = attempts.clone();
+                async move {
+                    let mut attempts = attempts.lock();
+                    *attempts += 1;
+                    if *attempts < 3 {
+                        // delivery fails for every target
+                        effects.next_peers
+                    } else {
+                        vec![]
+                    }
+                }
+            }
+        };
+        let (dead_letter, mut dead_letters) = mpsc::channel(1);
+        Dispatcher::process_effects_with(
+            stream::iter(effects),
+            execute,
+            None,
+            3,
+            Some(dead_letter),
+            None,
+            vec![],
+        )
+        .await;
+
+        assert_eq!(*attempts.lock(), 3, "third delivery attempt must succeed");
+        assert!(
+            dead_letters.recv().await.is_none(),
+            "a delivered particle must not go to the dead-letter channel"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exhausted_retries_go_to_dead_letter() {
+        let target = PeerId::random();
+        let effects: Vec<Effects> = vec![Ok(routing_effects("doomed", vec![target]))];
+
+        let execute = move |effects: RemoteRoutingEffects| async move { effects.next_peers };
+        let (dead_letter, mut dead_letters) = mpsc::channel(1);
+        Dispatcher::process_effects_with(
+            stream::iter(effects),
+            execute,
+            None,
+            2,
+            Some(dead_letter),
+            None,
+            vec![],
+        )
+        .await;
+
+        let dead = dead_letters.recv().await.expect("dead letter expected");
+        assert_eq!(dead.particle.particle.id, "doomed");
+        assert_eq!(dead.next_peers, vec![target]);
+    }
+
+    #[derive(Default)]
+    struct CountingHook {
+        received: AtomicUsize,
+        applied: AtomicUsize,
+    }
+
+    impl ObservabilityHook for CountingHook {
+        fn on_particle_received(&self, _particle: &ExtendedParticle) {
+            self.received.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_effects_applied(&self, _effects: &RemoteRoutingEffects) {
+            self.applied.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn observability_hook_sees_every_particle_and_applied_effects() {
+        let hook: Arc<CountingHook> = <_>::default();
+
+        // every received particle hits the hook exactly once, even an expired
+        // one that never reaches the executor
+        let particles = stream::iter(vec![
+            particle("first"),
+            particle_with_ttl("expired", 0),
+            particle("second"),
+        ]);
+        let execute = |_: ExtendedParticle| async {};
+        Dispatcher::process_particles_with(particles, execute, None, None, vec![hook.clone()])
+            .await;
+        assert_eq!(hook.received.load(Ordering::SeqCst), 3);
+
+        // applied effects are reported only for fully delivered particles
+        let effects: Vec<Effects> = vec![
+            Ok(routing_effects("delivered", vec![PeerId::random()])),
+            Ok(routing_effects("undelivered", vec![PeerId::random()])),
+        ];
+        let execute = move |effects: RemoteRoutingEffects| async move {
+            if effects.particle.particle.id == "delivered" {
+                vec![]
+            } else {
+                effects.next_peers
+            }
+        };
+        Dispatcher::process_effects_with(
+            stream::iter(effects),
+            execute,
+            None,
+            0,
+            None,
+            None,
+            vec![hook.clone()],
+        )
+        .await;
+        assert_eq!(hook.applied.load(Ordering::SeqCst), 1);
+    }
+}
@@ -15,9 +15,11 @@
  */
 
 use futures::{stream::iter, StreamExt};
+use parking_lot::Mutex;
 use tracing::instrument;
 
 use aquamarine::RemoteRoutingEffects;
+use fluence_libp2p::PeerId;
 use particle_protocol::Particle;
 
 use crate::connectivity::Connectivity;
@@ -32,19 +34,26 @@ impl Effectors {
         Self { connectivity }
     }
 
-    /// Perform effects that Aquamarine instructed us to
+    /// Perform effects that Aquamarine instructed us to.
+    /// Returns the peers the particle could not be delivered to
     #[instrument(level = tracing::Level::INFO, skip_all)]
-    pub async fn execute(self, effects: RemoteRoutingEffects) {
+    pub async fn execute(self, effects: RemoteRoutingEffects) -> Vec<PeerId> {
         let particle: &Particle = effects.particle.as_ref();
         if particle.is_expired() {
             tracing::info!(target: "expired", particle_id = particle.id, "Particle is expired");
-            return;
+            return vec![];
         }
 
+        // don't bounce the particle straight back to the relay that just
+        // forwarded it here, unless that relay is the only next peer
+        let next_peers = skip_ping_pong(effects.next_peers, particle.prev_relay());
+
+        let failed: Mutex<Vec<PeerId>> = <_>::default();
         // take every next peers, and try to send particle there concurrently
-        let nps = iter(effects.next_peers);
+        let nps = iter(next_peers);
         let particle = &effects.particle;
         let connectivity = self.connectivity.clone();
+        let failed = &failed;
         nps.for_each_concurrent(None, move |target| {
             let connectivity = connectivity.clone();
             let particle = particle.clone();
@@ -56,12 +65,62 @@ impl Effectors {
                 {
                     // forward particle
                     let sent = connectivity.send(contact, particle).await;
-                    if sent {
-                        // resolved and sent, exit
+                    if !sent {
+                        failed.lock().push(target);
                     }
+                } else {
+                    failed.lock().push(target);
                 }
             }
         })
         .await;
+        failed.lock().drain(..).collect()
+    }
+}
+
+/// Removes the relay the particle just came from out of `next_peers`:
+/// forwarding straight back is redundant ping-pong routing. The relay is
+/// kept when it is the only next peer, so delivery through it still works
+fn skip_ping_pong(mut next_peers: Vec<PeerId>, prev_relay: Option<PeerId>) -> Vec<PeerId> {
+    if next_peers.len() > 1 {
+        if let Some(prev) = prev_relay {
+            if next_peers.contains(&prev) {
+                tracing::debug!(
+                    target: "network",
+                    "Skipping redundant forward back to relay {prev}"
+                );
+                next_peers.retain(|peer| *peer != prev);
+            }
+        }
+    }
+    next_peers
+}
+
+#[cfg(test)]
+mod tests {
+    use fluence_libp2p::PeerId;
+
+    use super::skip_ping_pong;
+
+    #[test]
+    fn ping_pong_back_forward_is_skipped() {
+        let prev = PeerId::random();
+        let other = PeerId::random();
+        let filtered = skip_ping_pong(vec![prev, other], Some(prev));
+        assert_eq!(filtered, vec![other]);
+    }
+
+    #[test]
+    fn sole_next_peer_is_kept_even_if_it_is_the_prev_relay() {
+        let prev = PeerId::random();
+        let filtered = skip_ping_pong(vec![prev], Some(prev));
+        assert_eq!(filtered, vec![prev]);
+    }
+
+    #[test]
+    fn particles_without_a_trace_are_untouched() {
+        let peers = vec![PeerId::random(), PeerId::random()];
+        let filtered = skip_ping_pong(peers.clone(), None);
+        assert_eq!(filtered, peers);
     }
 }
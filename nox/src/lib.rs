@@ -31,6 +31,7 @@
 
 mod builtins;
 mod connectivity;
+mod crash_marker;
 mod dispatcher;
 mod effectors;
 mod health;
@@ -38,6 +39,7 @@ mod http;
 mod layers;
 mod metrics;
 mod node;
+mod particle_timeline;
 mod tasks;
 mod behaviour {
     mod identify;
@@ -47,6 +49,8 @@ mod behaviour {
 }
 
 pub use behaviour::{FluenceNetworkBehaviour, FluenceNetworkBehaviourEvent};
+pub use crash_marker::{install_panic_report_hook, CrashMarker, Disposition};
+pub use dispatcher::{DefaultObservabilityHook, ObservabilityHook};
 pub use http::StartedHttp;
 pub use node::Node;
 
@@ -57,6 +61,7 @@ pub use kademlia::Command as KademliaCommand;
 pub use layers::env_filter;
 pub use layers::log_layer;
 pub use layers::tracing_layer;
+pub use particle_timeline::{ParticleTimelineLayer, ParticleTimelineStore, TimelineRecord};
 
 #[derive(Debug, Clone)]
 pub struct Versions {
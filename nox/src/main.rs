@@ -31,6 +31,7 @@ use base64::{engine::general_purpose::STANDARD as base64, Engine};
 use eyre::WrapErr;
 use libp2p::PeerId;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::signal;
 use tokio::sync::oneshot;
 use tokio_util::sync::CancellationToken;
@@ -40,9 +41,14 @@ use tracing_subscriber::util::SubscriberInitExt;
 use air_interpreter_fs::write_default_air_interpreter;
 use aquamarine::{AVMRunner, DataStoreConfig, VmConfig};
 use config_utils::to_peer_id;
-use core_manager::{CoreManager, CoreManagerFunctions, DevCoreManager, StrictCoreManager};
+use core_manager::{
+    CPUTopology, CoreManager, CoreManagerFunctions, DevCoreManager, StrictCoreManager,
+};
 use fs_utils::to_abs_path;
-use nox::{env_filter, log_layer, tracing_layer, Node};
+use nox::{
+    env_filter, install_panic_report_hook, log_layer, tracing_layer, CrashMarker, Disposition,
+    Node, ParticleTimelineLayer, ParticleTimelineStore,
+};
 use server_config::{load_config, ConfigData, ResolvedConfig};
 use tracing_panic::panic_hook;
 use tracing_subscriber::reload;
@@ -73,6 +79,7 @@ fn main() -> eyre::Result<()> {
     }));
 
     let (reloadable_tracing_layer, reload_handle) = reload::Layer::new(None);
+    let (reloadable_timeline_layer, timeline_reload_handle) = reload::Layer::new(None);
 
     let (log_layer, _worker_guard) = log_layer();
 
@@ -80,6 +87,7 @@ fn main() -> eyre::Result<()> {
         .with(env_filter())
         .with(log_layer)
         .with(reloadable_tracing_layer)
+        .with(reloadable_timeline_layer)
         .init();
 
     let version = format!("{}; AIR version {}", VERSION, air_interpreter_wasm::VERSION);
@@ -119,16 +127,50 @@ fn main() -> eyre::Result<()> {
 
     let resolved_config = config.clone().resolve()?;
 
+    // from here on panics leave a report next to the node state, and the
+    // crash marker lets the next start tell a crash from a clean shutdown
+    install_panic_report_hook(&resolved_config.dir_config.base_dir);
+    let crash_marker = CrashMarker::start(&resolved_config.dir_config.base_dir)?;
+
+    // Validate the configured CPU range against the machine topology before
+    // constructing the core manager: this names the specific missing core id
+    // instead of the opaque error the manager construction fails with
+    let topology = CPUTopology::new()
+        .map_err(|err| eyre::eyre!("Failed to create CPU topology {err}"))?;
+    resolved_config
+        .node_config
+        .cpus_range
+        .validate_against_topology(&topology)?;
+
+    // The timeline layer is installed into the already running subscriber
+    // through a reload handle, since the store dimensions come from the config
+    let particle_timeline = resolved_config
+        .node_config
+        .particle_timeline_retention
+        .map(|retention| {
+            ParticleTimelineStore::new(
+                retention,
+                resolved_config.node_config.particle_timeline_max_particles,
+            )
+        });
+    if let Some(store) = particle_timeline.clone() {
+        timeline_reload_handle
+            .modify(move |layer| *layer = Some(ParticleTimelineLayer::new(store)))?;
+    }
+
     let (core_manager, core_manager_task) = if resolved_config.dev_mode_config.enable {
         let (core_manager, core_manager_task) = DevCoreManager::from_path(
+            "host".to_string(),
             resolved_config.dir_config.core_state_path.clone(),
             resolved_config.node_config.system_cpu_count,
             resolved_config.node_config.cpus_range.clone(),
+            resolved_config.dev_mode_config.strict_core_state,
         )?;
         let core_manager: Arc<CoreManager> = Arc::new(core_manager.into());
         (core_manager, core_manager_task)
     } else {
         let (core_manager, core_manager_task) = StrictCoreManager::from_path(
+            "host".to_string(),
             resolved_config.dir_config.core_state_path.clone(),
             resolved_config.node_config.system_cpu_count,
             resolved_config.node_config.cpus_range.clone(),
@@ -180,13 +222,32 @@ fn main() -> eyre::Result<()> {
             write_default_air_interpreter(&interpreter_path)?;
             log::info!("AIR interpreter: {:?}", interpreter_path);
 
-            let fluence = start_fluence(resolved_config, core_manager, peer_id).await?;
+            let fluence = start_fluence(
+                resolved_config,
+                core_manager.clone(),
+                peer_id,
+                particle_timeline,
+                crash_marker.last_dispositions().to_vec(),
+            )
+            .await?;
             log::info!("Fluence has been successfully started.");
 
             signal::ctrl_c().await.expect("Failed to listen for event");
             log::info!("Shutting down...");
+            let shutdown_started = Instant::now();
 
             fluence.stop().await;
+
+            // stop the persistence task (it drains pending events and writes
+            // one last time) and make sure the final core state reaches the
+            // disk before exiting
+            core_manager_task.stop();
+            core_manager_task.force_flush(core_manager).await;
+
+            // everything is down: record the shutdown as clean
+            if let Err(err) = crash_marker.clean_shutdown(shutdown_started) {
+                log::warn!("Failed to write the clean shutdown marker: {err}");
+            }
             Ok(())
         })
 }
@@ -196,6 +257,8 @@ async fn start_fluence(
     config: ResolvedConfig,
     core_manager: Arc<CoreManager>,
     peer_id: PeerId,
+    particle_timeline: Option<ParticleTimelineStore>,
+    last_shutdowns: Vec<Disposition>,
 ) -> eyre::Result<impl Stoppable> {
     log::trace!("starting Fluence");
 
@@ -217,6 +280,8 @@ async fn start_fluence(
         VERSION,
         air_interpreter_wasm::VERSION,
         system_service_distros,
+        particle_timeline,
+        last_shutdowns,
     )
     .await
     .wrap_err("error create node instance")?;
@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::process::exit;
 use std::sync::Arc;
 use std::{io, net::SocketAddr};
@@ -47,25 +48,33 @@ use chain_listener::ChainListener;
 use config_utils::to_peer_id;
 use connection_pool::ConnectionPoolT;
 use core_manager::CoreManager;
-use fluence_libp2p::build_transport;
+use fluence_libp2p::{build_transport, TransportConfig};
 use health::HealthCheckRegistry;
-use particle_builtins::{Builtins, CustomService, NodeInfo, ParticleAppServicesConfig};
+use particle_builtins::{
+    BuiltinUsageConfig, Builtins, CustomService, IpfsConfig, NodeInfo, ParticleAppServicesConfig,
+};
 use particle_execution::ParticleFunctionStatic;
 use particle_protocol::ExtendedParticle;
 use peer_metrics::{
-    ChainListenerMetrics, ConnectionPoolMetrics, ConnectivityMetrics, ParticleExecutorMetrics,
-    ServicesMetrics, ServicesMetricsBackend, SpellMetrics, VmPoolMetrics,
+    ChainListenerMetrics, ChainRpcMetrics, ConnectionPoolMetrics, ConnectivityMetrics,
+    MetricsToggles, ParticleDataStoreMetrics, ParticleExecutorMetrics, ServicesMetrics,
+    ServicesMetricsBackend, SpellEventBusMetrics, SpellMetrics, VmPoolMetrics,
 };
 use server_config::system_services_config::ServiceKey;
 use server_config::{NetworkConfig, ResolvedConfig};
 use sorcerer::Sorcerer;
-use spell_event_bus::api::{PeerEvent, SpellEventBusApi, TriggerEvent};
-use spell_event_bus::bus::SpellEventBus;
+use spell_event_bus::api::{PeerEvent, SourceEvent, SpellEventBusApi, TriggerEvent};
+use spell_event_bus::bus::{DelayedTriggersPersistenceConfig, SpellEventBus};
 use system_services::{Deployer, SystemServiceDistros};
 use workers::{KeyStorage, PeerScopes, Workers};
 
 use crate::behaviour::FluenceNetworkBehaviourEvent;
-use crate::builtins::make_peer_builtin;
+use crate::builtins::{
+    make_migration_builtin, make_node_builtin, make_particle_timeline_builtin, make_peer_builtin,
+    make_stat_builtin,
+};
+use crate::crash_marker::Disposition;
+use crate::particle_timeline::ParticleTimelineStore;
 use crate::dispatcher::Dispatcher;
 use crate::effectors::Effectors;
 use crate::http::{start_http_endpoint, HttpEndpointData};
@@ -166,11 +175,19 @@ impl<RT: AquaRuntime> Node<RT> {
         node_version: &'static str,
         air_version: &'static str,
         system_service_distros: SystemServiceDistros,
+        particle_timeline: Option<ParticleTimelineStore>,
+        last_shutdowns: Vec<Disposition>,
     ) -> eyre::Result<Box<Self>> {
         let key_pair: Keypair = config.node_config.root_key_pair.clone().into();
         let transport = config.transport_config.transport;
-        let transport =
-            build_transport(transport, &key_pair, config.transport_config.socket_timeout);
+        let transport = build_transport(
+            transport,
+            &key_pair,
+            &TransportConfig {
+                socket_timeout: config.transport_config.socket_timeout,
+                ..<_>::default()
+            },
+        );
 
         let builtins_peer_id = to_peer_id(&config.builtins_key_pair.clone().into());
 
@@ -222,6 +239,7 @@ impl<RT: AquaRuntime> Node<RT> {
                 .collect(),
             config.node_config.dev_mode_config.enable,
             wasm_backend_config,
+            config.node_config.services.permissive_scope_resolution,
         )
         .expect("create services config");
 
@@ -237,13 +255,22 @@ impl<RT: AquaRuntime> Node<RT> {
             None
         };
 
+        let metrics_toggles =
+            MetricsToggles::new(config.metrics_config.disabled_metric_families.clone());
+
         let libp2p_metrics = metrics_registry.as_mut().map(|r| Arc::new(Metrics::new(r)));
         let connectivity_metrics = metrics_registry.as_mut().map(ConnectivityMetrics::new);
         let connection_pool_metrics = metrics_registry.as_mut().map(ConnectionPoolMetrics::new);
-        let plumber_metrics = metrics_registry.as_mut().map(ParticleExecutorMetrics::new);
+        let plumber_metrics = metrics_registry
+            .as_mut()
+            .map(|r| ParticleExecutorMetrics::new(r, &metrics_toggles));
         let vm_pool_metrics = metrics_registry.as_mut().map(VmPoolMetrics::new);
+        let particle_data_store_metrics =
+            metrics_registry.as_mut().map(ParticleDataStoreMetrics::new);
         let spell_metrics = metrics_registry.as_mut().map(SpellMetrics::new);
+        let spell_event_bus_metrics = metrics_registry.as_mut().map(SpellEventBusMetrics::new);
         let chain_listener_metrics = metrics_registry.as_mut().map(ChainListenerMetrics::new);
+        let chain_rpc_metrics = metrics_registry.as_mut().map(ChainRpcMetrics::new);
 
         if config.metrics_config.tokio_metrics_enabled {
             if let Some(r) = metrics_registry.as_mut() {
@@ -274,6 +301,7 @@ impl<RT: AquaRuntime> Node<RT> {
             key_pair,
             &config,
             node_version,
+            air_version,
             connection_limits,
         );
 
@@ -300,6 +328,9 @@ impl<RT: AquaRuntime> Node<RT> {
                     config.metrics_config.max_builtin_metrics_storage_size,
                 )
             };
+        let services_metrics = services_metrics.with_call_rate_warn_threshold(
+            config.metrics_config.service_call_rate_warn_threshold,
+        );
 
         let mut builtins = Self::builtins(
             connectivity.clone(),
@@ -310,6 +341,19 @@ impl<RT: AquaRuntime> Node<RT> {
             scopes.clone(),
             health_registry.as_mut(),
             config.system_services.decider.network_api_endpoint.clone(),
+            BuiltinUsageConfig {
+                window: config.node_config.builtin_usage_window,
+                budget: config.node_config.builtin_usage_budget,
+                ..<_>::default()
+            },
+            IpfsConfig {
+                gateway: config.node_config.ipfs_gateway.clone(),
+                api: config.node_config.ipfs_api.clone(),
+                request_timeout: config.node_config.ipfs_request_timeout,
+            },
+            // injected into the WASI config of every added module,
+            // unless a module sets the same env itself
+            HashMap::from([("NODE_VERSION".to_string(), node_version.to_string())]),
         );
 
         builtins.services.create_persisted_services().await?;
@@ -330,6 +374,7 @@ impl<RT: AquaRuntime> Node<RT> {
             effects_out,
             plumber_metrics,
             vm_pool_metrics,
+            particle_data_store_metrics,
             health_registry.as_mut(),
             workers.clone(),
             key_storage.clone(),
@@ -344,15 +389,35 @@ impl<RT: AquaRuntime> Node<RT> {
                 aquamarine_api.clone(),
                 effectors,
                 parallelism,
+                config.node_config.effect_retries,
+                // no dead-letter consumer is wired up yet
+                None,
                 metrics_registry.as_mut(),
+                health_registry.as_mut(),
             )
         };
 
         let recv_connection_pool_events = connectivity.connection_pool.lifecycle_events();
-        let sources = vec![recv_connection_pool_events.map(PeerEvent::from).boxed()];
+        let recv_service_events = builtins.services.lifecycle_events();
+        let sources = vec![
+            recv_connection_pool_events
+                .map(|e| SourceEvent::Peer(PeerEvent::from(e)))
+                .boxed(),
+            recv_service_events
+                .map(|e| SourceEvent::Service(e.into()))
+                .boxed(),
+        ];
 
         let (spell_event_bus, spell_event_bus_api, spell_events_receiver) =
-            SpellEventBus::new(spell_metrics.clone(), sources);
+            SpellEventBus::new(spell_metrics.clone(), spell_event_bus_metrics, sources);
+        let spell_event_bus =
+            spell_event_bus.with_delayed_triggers_persistence(DelayedTriggersPersistenceConfig {
+                path: config
+                    .dir_config
+                    .spell_base_dir
+                    .join("delayed_triggers.json"),
+                fire_missed_on_reload: config.spell_fire_missed_delayed_triggers,
+            });
 
         let spell_service_api = spell_service_api::SpellServiceApi::new(builtins.services.clone());
         let (sorcerer, mut custom_service_functions, spell_version) = Sorcerer::new(
@@ -383,6 +448,7 @@ impl<RT: AquaRuntime> Node<RT> {
             spell_version: spell_version.clone(),
             // TODO: remove
             allowed_binaries,
+            capabilities: builtins.capabilities.list(),
         };
         if let Some(m) = metrics_registry.as_mut() {
             peer_metrics::add_info_metrics(
@@ -392,7 +458,37 @@ impl<RT: AquaRuntime> Node<RT> {
                 node_info.spell_version.clone(),
             );
         }
+        if let Some(m) = metrics_registry.as_mut() {
+            let unclean = matches!(last_shutdowns.first(), Some(Disposition::Unclean { .. }));
+            peer_metrics::add_unclean_restart_metric(m, unclean);
+        }
         custom_service_functions.extend_one(make_peer_builtin(node_info));
+        custom_service_functions.extend_one(make_node_builtin(last_shutdowns));
+        if let Some(timeline) = particle_timeline {
+            custom_service_functions
+                .extend_one(make_particle_timeline_builtin(timeline, scopes.clone()));
+        }
+        custom_service_functions.extend_one(make_stat_builtin(
+            metrics_toggles,
+            scopes.clone(),
+            aquamarine_backend.data_store(),
+        ));
+        custom_service_functions.extend_one(make_migration_builtin(
+            root_key_pair.clone(),
+            workers::migration::PlacementPaths {
+                key_pairs_dir: config.dir_config.keypairs_base_dir.clone(),
+                workers_dir: config.dir_config.workers_base_dir.clone(),
+                core_state_path: config.dir_config.core_state_path.clone(),
+                modules_dir: config_utils::modules_dir(&config.dir_config.services_persistent_dir),
+                blueprints_dir: config_utils::blueprint_dir(
+                    &config.dir_config.services_persistent_dir,
+                ),
+            },
+            config.dir_config.base_dir.clone(),
+            scopes.clone(),
+        ));
+        #[cfg(feature = "chaos")]
+        custom_service_functions.extend_one(crate::builtins::make_chaos_builtin(scopes.clone()));
 
         let services = builtins.services.clone();
         let modules = builtins.modules.clone();
@@ -400,13 +496,14 @@ impl<RT: AquaRuntime> Node<RT> {
         let connector = if let Some(chain_config) = config.chain_config.clone() {
             let host_id = scopes.get_host_peer_id();
             let (chain_connector, chain_builtins) =
-                HttpChainConnector::new(chain_config.clone(), host_id).map_err(|err| {
-                    log::error!(
-                        "Error connecting to http endpoint {}, error: {err}",
-                        chain_config.http_endpoint
-                    );
-                    err
-                })?;
+                HttpChainConnector::new(chain_config.clone(), host_id, chain_rpc_metrics.clone())
+                    .map_err(|err| {
+                        log::error!(
+                            "Error connecting to http endpoint {}, error: {err}",
+                            chain_config.http_endpoint
+                        );
+                        err
+                    })?;
             custom_service_functions.extend(chain_builtins.into_iter());
             Some(chain_connector)
         } else {
@@ -446,6 +543,7 @@ impl<RT: AquaRuntime> Node<RT> {
             scopes.get_host_peer_id(),
             builtins_peer_id,
             system_service_distros,
+            config.spell_peer_events_enabled,
         );
 
         let versions = Versions::new(
@@ -534,6 +632,9 @@ impl<RT: AquaRuntime> Node<RT> {
         scopes: PeerScopes,
         health_registry: Option<&mut HealthCheckRegistry>,
         connector_api_endpoint: String,
+        builtin_usage_config: BuiltinUsageConfig,
+        ipfs_config: IpfsConfig,
+        default_wasi_envs: HashMap<String, String>,
     ) -> Builtins<Connectivity> {
         Builtins::new(
             connectivity,
@@ -544,6 +645,9 @@ impl<RT: AquaRuntime> Node<RT> {
             scopes,
             health_registry,
             connector_api_endpoint,
+            builtin_usage_config,
+            ipfs_config,
+            default_wasi_envs,
         )
     }
 }
@@ -690,6 +794,14 @@ impl<RT: AquaRuntime> Node<RT> {
             }
 
             log::info!("Stopping node");
+            // spill queued local particles (host and workers) to disk
+            // so the next start can replay them
+            let local_peers: std::collections::HashSet<PeerId> = workers
+                .list_workers()
+                .into_iter()
+                .map(PeerId::from)
+                .collect();
+            swarm.behaviour().connection_pool.spill_queue(&local_peers);
             if let Some(c) = chain_listener { c.abort() }
             services_metrics_backend.abort();
             spell_event_bus.abort();
@@ -764,6 +876,9 @@ fn avm_wasm_backend_config(config: &ResolvedConfig) -> WasmBackendConfig {
             .avm_config
             .wasm_backend
             .epoch_interruption_duration,
+        // the AVM module is compiled once at startup, a persistent cache doesn't pay off there
+        module_cache_enabled: false,
+        module_cache_size_limit: 0,
     }
 }
 
@@ -788,6 +903,13 @@ fn services_wasm_backend_config(config: &ResolvedConfig) -> WasmBackendConfig {
             .services
             .wasm_backend
             .epoch_interruption_duration,
+        module_cache_enabled: config.node_config.services.wasm_backend.module_cache_enabled,
+        module_cache_size_limit: config
+            .node_config
+            .services
+            .wasm_backend
+            .module_cache_size_limit
+            .as_u64(),
     }
 }
 
@@ -857,6 +979,8 @@ mod tests {
             "some version",
             "some version",
             system_service_distros,
+            None,
+            vec![],
         )
         .await
         .expect("create node");
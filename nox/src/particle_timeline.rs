@@ -0,0 +1,312 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// A single recorded point of a particle's life on this node
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineRecord {
+    /// Unix timestamp in milliseconds of when the event was observed
+    pub timestamp_ms: u64,
+    /// Target of the tracing event the record was extracted from
+    pub target: String,
+    /// The event message with its non-id fields appended as `key=value`
+    pub message: String,
+}
+
+struct Timeline {
+    updated: Instant,
+    records: Vec<TimelineRecord>,
+}
+
+struct Inner {
+    timelines: HashMap<String, Timeline>,
+    /// Particle ids in insertion order, for evicting the oldest timelines
+    /// when `max_particles` is exceeded
+    order: VecDeque<String>,
+}
+
+/// In-memory per-particle event timelines with a bounded lifetime and size.
+/// Filled by [`ParticleTimelineLayer`] from tracing events that carry
+/// a `particle_id` field; queried through the `("debug", "particle_timeline")`
+/// builtin by the management peer
+#[derive(Clone)]
+pub struct ParticleTimelineStore {
+    inner: Arc<Mutex<Inner>>,
+    retention: Duration,
+    max_particles: usize,
+}
+
+impl ParticleTimelineStore {
+    pub fn new(retention: Duration, max_particles: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                timelines: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+            retention,
+            max_particles,
+        }
+    }
+
+    pub fn record(&self, particle_id: &str, target: &str, message: String) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or_default();
+        let record = TimelineRecord {
+            timestamp_ms,
+            target: target.to_string(),
+            message,
+        };
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+        if let Some(timeline) = inner.timelines.get_mut(particle_id) {
+            timeline.updated = now;
+            timeline.records.push(record);
+            return;
+        }
+
+        // eviction runs only when a new particle arrives, so the cost
+        // is bounded per particle rather than per event
+        self.evict(&mut inner, now);
+        inner.order.push_back(particle_id.to_string());
+        inner.timelines.insert(
+            particle_id.to_string(),
+            Timeline {
+                updated: now,
+                records: vec![record],
+            },
+        );
+        while inner.timelines.len() > self.max_particles {
+            match inner.order.pop_front() {
+                Some(oldest) => {
+                    inner.timelines.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the recorded timeline of a particle if it is still retained
+    pub fn get(&self, particle_id: &str) -> Option<Vec<TimelineRecord>> {
+        let mut inner = self.inner.lock();
+        self.evict(&mut inner, Instant::now());
+        inner
+            .timelines
+            .get(particle_id)
+            .map(|timeline| timeline.records.clone())
+    }
+
+    fn evict(&self, inner: &mut Inner, now: Instant) {
+        inner
+            .timelines
+            .retain(|_, timeline| now.duration_since(timeline.updated) < self.retention);
+        let timelines = &inner.timelines;
+        inner.order.retain(|id| timelines.contains_key(id));
+    }
+}
+
+/// Recorded into span extensions when a span carries a `particle_id` field,
+/// so events inside the span can be attributed to the particle
+struct SpanParticleId(String);
+
+/// Mirrors `particle_id`-carrying tracing events into a [`ParticleTimelineStore`].
+/// The particle id is taken from the event's own fields or, failing that, from
+/// the closest enclosing span that has one (e.g. the `Particle` root span
+/// created by the connection pool)
+pub struct ParticleTimelineLayer {
+    store: ParticleTimelineStore,
+}
+
+impl ParticleTimelineLayer {
+    pub fn new(store: ParticleTimelineStore) -> Self {
+        Self { store }
+    }
+}
+
+impl<S> Layer<S> for ParticleTimelineLayer
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = ParticleIdVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(particle_id) = visitor.particle_id {
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(SpanParticleId(particle_id));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let particle_id = visitor.particle_id.or_else(|| {
+            ctx.event_scope(event)?.find_map(|span| {
+                let extensions = span.extensions();
+                extensions.get::<SpanParticleId>().map(|id| id.0.clone())
+            })
+        });
+        if let Some(particle_id) = particle_id {
+            self.store
+                .record(&particle_id, event.metadata().target(), visitor.message);
+        }
+    }
+}
+
+/// Extracts only the `particle_id` field; used for span attributes
+#[derive(Default)]
+struct ParticleIdVisitor {
+    particle_id: Option<String>,
+}
+
+impl Visit for ParticleIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "particle_id" {
+            self.particle_id = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "particle_id" {
+            let value = format!("{value:?}");
+            self.particle_id = Some(value.trim_matches('"').to_string());
+        }
+    }
+}
+
+/// Extracts the `particle_id` field and renders the rest of the event
+/// into a compact one-line message
+#[derive(Default)]
+struct EventVisitor {
+    particle_id: Option<String>,
+    message: String,
+}
+
+impl EventVisitor {
+    fn push_field(&mut self, name: &str, value: &str) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+        self.message.push_str(name);
+        self.message.push('=');
+        self.message.push_str(value);
+    }
+
+    fn push_message(&mut self, rendered: String) {
+        if self.message.is_empty() {
+            self.message = rendered;
+        } else {
+            self.message = format!("{rendered} {}", self.message);
+        }
+    }
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "particle_id" => self.particle_id = Some(value.to_string()),
+            "message" => self.push_message(value.to_string()),
+            name => self.push_field(name, value),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "particle_id" => {
+                let value = format!("{value:?}");
+                self.particle_id = Some(value.trim_matches('"').to_string());
+            }
+            "message" => self.push_message(format!("{value:?}")),
+            name => self.push_field(name, &format!("{value:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::{ParticleTimelineLayer, ParticleTimelineStore};
+
+    #[test]
+    fn records_are_ordered_and_expire() {
+        let store = ParticleTimelineStore::new(Duration::from_millis(50), 10);
+        store.record("particle", "network", "received".to_string());
+        store.record("particle", "execution", "executed".to_string());
+
+        let records = store.get("particle").expect("timeline is retained");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].message, "received");
+        assert_eq!(records[1].message, "executed");
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(
+            store.get("particle").is_none(),
+            "timeline must expire after the retention window"
+        );
+    }
+
+    #[test]
+    fn oldest_timelines_are_evicted_on_overflow() {
+        let store = ParticleTimelineStore::new(Duration::from_secs(60), 2);
+        store.record("first", "network", "received".to_string());
+        store.record("second", "network", "received".to_string());
+        store.record("third", "network", "received".to_string());
+
+        assert!(store.get("first").is_none());
+        assert!(store.get("second").is_some());
+        assert!(store.get("third").is_some());
+    }
+
+    #[test]
+    fn layer_records_events_with_particle_id() {
+        let store = ParticleTimelineStore::new(Duration::from_secs(60), 10);
+        let subscriber =
+            tracing_subscriber::registry().with(ParticleTimelineLayer::new(store.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(particle_id = "particle", "received from {}", "peer");
+            // events without their own particle_id field are attributed
+            // through the enclosing span, like the `Particle` root span
+            let span = tracing::info_span!("Particle", particle_id = "particle");
+            let _guard = span.enter();
+            tracing::info!(rounds = 3, "interpretation finished");
+            drop(_guard);
+            tracing::info!("no particle id here");
+        });
+
+        let records = store.get("particle").expect("timeline is retained");
+        assert_eq!(records.len(), 2, "unexpected records: {records:?}");
+        assert_eq!(records[0].message, "received from peer");
+        assert_eq!(records[1].message, "interpretation finished rounds=3");
+    }
+}
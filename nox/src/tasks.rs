@@ -16,22 +16,71 @@
 
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::future::FusedFuture;
 use futures::FutureExt;
+use health::HealthCheck;
+use parking_lot::RwLock;
 use tokio::task::JoinHandle;
 
+/// Reports task groups whose tasks terminated while the node kept running.
+/// Spawned tasks are infinite loops, so any termination observed by
+/// [Tasks::poll] means the node has lost a part of its processing pipeline.
+#[derive(Clone, Default)]
+pub struct TasksHealth {
+    terminated: Arc<RwLock<Vec<&'static str>>>,
+}
+
+impl TasksHealth {
+    pub fn on_task_terminated(&self, name: &'static str) {
+        self.terminated.write().push(name);
+    }
+}
+
+impl HealthCheck for TasksHealth {
+    fn status(&self) -> eyre::Result<()> {
+        let terminated = self.terminated.read();
+        if terminated.is_empty() {
+            Ok(())
+        } else {
+            Err(eyre::eyre!(
+                "tasks terminated unexpectedly: {}",
+                terminated.join(", ")
+            ))
+        }
+    }
+}
+
 /// Holds handles to spawned tasks
 pub struct Tasks {
     name: &'static str,
     /// Task that processes particles from particle stream
     pub tasks: Vec<JoinHandle<()>>,
+    /// Health check that is marked failed when a task terminates on its own
+    health: Option<TasksHealth>,
 }
 
 impl Tasks {
     pub fn new(name: &'static str, tasks: Vec<JoinHandle<()>>) -> Self {
-        Self { name, tasks }
+        Self {
+            name,
+            tasks,
+            health: None,
+        }
+    }
+
+    pub fn with_health(
+        name: &'static str,
+        tasks: Vec<JoinHandle<()>>,
+        health: TasksHealth,
+    ) -> Self {
+        Self {
+            name,
+            tasks,
+            health: Some(health),
+        }
     }
 
     pub async fn cancel(self) {
@@ -45,7 +94,23 @@ impl Future for Tasks {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let _ = self.tasks.extract_if(|task| task.poll_unpin(cx).is_ready());
+        let name = self.name;
+        let mut terminated = false;
+        self.tasks.retain_mut(|task| match task.poll_unpin(cx) {
+            Poll::Ready(res) => {
+                terminated = true;
+                if let Err(err) = res {
+                    log::error!("{name} task failed: {err}");
+                }
+                false
+            }
+            Poll::Pending => true,
+        });
+        if terminated {
+            if let Some(health) = self.health.as_ref() {
+                health.on_task_terminated(name);
+            }
+        }
 
         if self.is_terminated() {
             log::warn!("{} tasks terminated", self.name);
@@ -61,3 +126,29 @@ impl FusedFuture for Tasks {
         self.tasks.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn terminated_task_is_reported_unhealthy() {
+        let health = TasksHealth::default();
+        let task = tokio::spawn(async { panic!("task died") });
+        let tasks = Tasks::with_health("Test", vec![task], health.clone());
+
+        tasks.await;
+
+        assert!(health.status().is_err());
+    }
+
+    #[tokio::test]
+    async fn running_tasks_are_healthy() {
+        let health = TasksHealth::default();
+        let task = tokio::spawn(futures::future::pending());
+        let tasks = Tasks::with_health("Test", vec![task], health.clone());
+
+        assert!(health.status().is_ok());
+        tasks.cancel().await;
+    }
+}
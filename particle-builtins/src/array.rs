@@ -0,0 +1,427 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cmp::Ordering;
+
+use particle_args::{Args, JError};
+use serde_json::Value as JValue;
+
+use crate::json::total_order;
+
+/// The `array` builtins refuse to process more elements than this,
+/// to bound per-call work; tests exercise the cap with smaller values
+pub(crate) const ARRAY_ELEMENTS_CAP: usize = 1_048_576;
+
+fn check_cap(len: usize, cap: usize) -> Result<(), JError> {
+    if len > cap {
+        return Err(JError::new(format!(
+            "array of {len} elements exceeds the allowed maximum of {cap}"
+        )));
+    }
+    Ok(())
+}
+
+/// Resolves a dotted key path (e.g. `a.b`) against a value;
+/// an empty path resolves to the value itself
+fn resolve_key_path<'v>(value: &'v JValue, key_path: &str) -> Option<&'v JValue> {
+    if key_path.is_empty() {
+        return Some(value);
+    }
+    key_path
+        .split('.')
+        .try_fold(value, |value, key| value.as_object()?.get(key))
+}
+
+/// Sorts a JSON array: scalars by value, arrays of objects by a dotted key
+/// path (optional second argument), ascending unless the optional third
+/// argument is "desc".
+///
+/// The sort is stable. Elements without a value at the key path go last
+/// regardless of the direction. Mixed types follow [total_order]
+pub fn array_sort(args: Args) -> Result<JValue, JError> {
+    sort_with_cap(args, ARRAY_ELEMENTS_CAP)
+}
+
+fn sort_with_cap(args: Args, cap: usize) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let mut array: Vec<JValue> = Args::next("array", &mut args)?;
+    let key_path: Option<String> = Args::next_opt("key_path", &mut args)?;
+    let direction: Option<String> = Args::next_opt("direction", &mut args)?;
+    let descending = match direction.as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => {
+            return Err(JError::new(format!(
+                "expected direction 'asc' or 'desc', got '{other}'"
+            )))
+        }
+    };
+
+    check_cap(array.len(), cap)?;
+
+    let key_path = key_path.unwrap_or_default();
+    array.sort_by(|a, b| {
+        let order = match (
+            resolve_key_path(a, &key_path),
+            resolve_key_path(b, &key_path),
+        ) {
+            (None, None) => Ordering::Equal,
+            // elements without the key go last regardless of the direction
+            (None, Some(_)) => return Ordering::Greater,
+            (Some(_), None) => return Ordering::Less,
+            (Some(a), Some(b)) => total_order(a, b),
+        };
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+
+    Ok(JValue::Array(array))
+}
+
+/// Removes duplicate elements from a JSON array, keeping the first occurrence
+/// in its original position. With the optional dotted key path argument,
+/// elements are compared by the value at that path instead of the whole
+/// element; elements missing the path count as equal to each other
+pub fn array_dedup(args: Args) -> Result<JValue, JError> {
+    dedup_with_cap(args, ARRAY_ELEMENTS_CAP)
+}
+
+fn dedup_with_cap(args: Args, cap: usize) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let array: Vec<JValue> = Args::next("array", &mut args)?;
+    let key_path: Option<String> = Args::next_opt("key_path", &mut args)?;
+
+    check_cap(array.len(), cap)?;
+
+    match key_path {
+        None => {
+            let mut unique: Vec<JValue> = Vec::with_capacity(array.len());
+            for value in array {
+                if !unique.contains(&value) {
+                    unique.push(value);
+                }
+            }
+            Ok(JValue::Array(unique))
+        }
+        Some(key_path) => {
+            let mut seen: Vec<Option<JValue>> = Vec::new();
+            let mut unique: Vec<JValue> = Vec::new();
+            for (index, value) in array.into_iter().enumerate() {
+                if !value.is_object() {
+                    return Err(JError::new(format!(
+                        "element at index {index} is not an object, cannot dedup by key path '{key_path}'"
+                    )));
+                }
+                let key = resolve_key_path(&value, &key_path).cloned();
+                if !seen.contains(&key) {
+                    seen.push(key);
+                    unique.push(value);
+                }
+            }
+            Ok(JValue::Array(unique))
+        }
+    }
+}
+
+/// Flattens nested arrays by the given depth (optional, one level by
+/// default); non-array elements are kept as is
+pub fn array_flatten(args: Args) -> Result<JValue, JError> {
+    flatten_with_cap(args, ARRAY_ELEMENTS_CAP)
+}
+
+fn flatten_with_cap(args: Args, cap: usize) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let array: Vec<JValue> = Args::next("array", &mut args)?;
+    let depth: Option<u64> = Args::next_opt("depth", &mut args)?;
+    let depth = depth.unwrap_or(1);
+
+    let mut flattened = array;
+    for _ in 0..depth {
+        check_cap(flattened.len(), cap)?;
+        if !flattened.iter().any(JValue::is_array) {
+            break;
+        }
+        let mut next = Vec::with_capacity(flattened.len());
+        for value in flattened {
+            match value {
+                JValue::Array(inner) => {
+                    check_cap(next.len() + inner.len(), cap)?;
+                    next.extend(inner);
+                }
+                value => next.push(value),
+            }
+        }
+        flattened = next;
+    }
+
+    check_cap(flattened.len(), cap)?;
+    Ok(JValue::Array(flattened))
+}
+
+/// Concatenates any number of JSON arrays in argument order
+pub fn array_concat(args: Args) -> Result<JValue, JError> {
+    concat_with_cap(args, ARRAY_ELEMENTS_CAP)
+}
+
+fn concat_with_cap(args: Args, cap: usize) -> Result<JValue, JError> {
+    let mut concatenated: Vec<JValue> = Vec::new();
+    for (index, value) in args.function_args.into_iter().enumerate() {
+        match value {
+            JValue::Array(array) => {
+                check_cap(concatenated.len() + array.len(), cap)?;
+                concatenated.extend(array);
+            }
+            value => {
+                return Err(JError::new(format!(
+                    "argument at index {index} must be an array, was {value}"
+                )))
+            }
+        }
+    }
+    Ok(JValue::Array(concatenated))
+}
+
+/// Removes duplicate elements from a JSON array, keeping the first occurrence
+/// of each element in its original position. Unlike `array.dedup`, elements
+/// can be of any JSON type and are compared by JSON value equality
+pub fn array_unique(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let array: Vec<JValue> = Args::next("array", &mut args)?;
+
+    let mut unique: Vec<JValue> = Vec::with_capacity(array.len());
+    for value in array {
+        if !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+
+    Ok(JValue::Array(unique))
+}
+
+/// Returns the elements of the first JSON array that are also present in the
+/// second one, deduplicated, in the order of the first array. Unlike
+/// `array.intersect`, elements can be of any JSON type and are compared by
+/// JSON value equality
+pub fn array_intersection(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let xs: Vec<JValue> = Args::next("xs", &mut args)?;
+    let ys: Vec<JValue> = Args::next("ys", &mut args)?;
+
+    let mut intersection: Vec<JValue> = Vec::new();
+    for value in xs {
+        if ys.contains(&value) && !intersection.contains(&value) {
+            intersection.push(value);
+        }
+    }
+
+    Ok(JValue::Array(intersection))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::array::{
+        array_concat, array_dedup, array_flatten, array_intersection, array_sort, array_unique,
+        concat_with_cap, dedup_with_cap, flatten_with_cap, sort_with_cap,
+    };
+    use particle_args::Args;
+    use serde_json::json;
+    use serde_json::Value as JValue;
+
+    fn args(function_args: Vec<JValue>) -> Args {
+        Args {
+            service_id: "".to_string(),
+            function_name: "".to_string(),
+            function_args,
+            tetraplets: vec![],
+        }
+    }
+
+    #[test]
+    fn array_unique_dedups_mixed_types() {
+        let array = json!([1, "1", 1, { "a": 1 }, null, { "a": 1 }, null]);
+        let unique = array_unique(args(vec![array])).unwrap();
+        assert_eq!(unique, json!([1, "1", { "a": 1 }, null]));
+    }
+
+    #[test]
+    fn array_unique_preserves_first_occurrence_order() {
+        let array = json!(["c", "a", "b", "a", "c"]);
+        let unique = array_unique(args(vec![array])).unwrap();
+        assert_eq!(unique, json!(["c", "a", "b"]));
+    }
+
+    #[test]
+    fn array_intersection_keeps_common_elements() {
+        let xs = json!([1, { "a": 1 }, "x", 2, 1]);
+        let ys = json!([2, 1, { "a": 1 }]);
+        let intersection = array_intersection(args(vec![xs, ys])).unwrap();
+        assert_eq!(intersection, json!([1, { "a": 1 }, 2]));
+    }
+
+    #[test]
+    fn array_intersection_with_empty_array_is_empty() {
+        let xs = json!([1, 2, 3]);
+        let ys = json!([]);
+        let intersection = array_intersection(args(vec![xs, ys])).unwrap();
+        assert_eq!(intersection, json!([]));
+    }
+
+    #[test]
+    fn array_sort_scalars_by_value() {
+        let array = json!([3, 1, 2]);
+        let sorted = array_sort(args(vec![array.clone()])).unwrap();
+        assert_eq!(sorted, json!([1, 2, 3]));
+
+        let sorted = array_sort(args(vec![array, json!(""), json!("desc")])).unwrap();
+        assert_eq!(sorted, json!([3, 2, 1]));
+    }
+
+    #[test]
+    fn array_sort_empty_array() {
+        let sorted = array_sort(args(vec![json!([])])).unwrap();
+        assert_eq!(sorted, json!([]));
+    }
+
+    #[test]
+    fn array_sort_objects_by_dotted_key_path() {
+        let array = json!([
+            { "a": { "n": 3 } },
+            { "a": { "n": 1 } },
+            { "a": { "n": 2 } }
+        ]);
+        let sorted = array_sort(args(vec![array, json!("a.n")])).unwrap();
+        assert_eq!(
+            sorted,
+            json!([{ "a": { "n": 1 } }, { "a": { "n": 2 } }, { "a": { "n": 3 } }])
+        );
+    }
+
+    #[test]
+    fn array_sort_missing_key_path_goes_last() {
+        let array = json!([{ "x": 1 }, { "n": 2 }, { "n": 1 }]);
+        let sorted = array_sort(args(vec![array.clone(), json!("n")])).unwrap();
+        assert_eq!(sorted, json!([{ "n": 1 }, { "n": 2 }, { "x": 1 }]));
+
+        // missing keys stay last in descending order too
+        let sorted = array_sort(args(vec![array, json!("n"), json!("desc")])).unwrap();
+        assert_eq!(sorted, json!([{ "n": 2 }, { "n": 1 }, { "x": 1 }]));
+    }
+
+    #[test]
+    fn array_sort_rejects_unknown_direction() {
+        let result = array_sort(args(vec![json!([1, 2]), json!(""), json!("sideways")]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn array_sort_rejects_arrays_over_the_cap() {
+        let result = sort_with_cap(args(vec![json!([1, 2, 3])]), 2);
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("array of 3 elements exceeds the allowed maximum of 2"));
+    }
+
+    #[test]
+    fn array_dedup_preserves_first_occurrence() {
+        let array = json!(["c", "a", "c", "b", "a"]);
+        let unique = array_dedup(args(vec![array])).unwrap();
+        assert_eq!(unique, json!(["c", "a", "b"]));
+    }
+
+    #[test]
+    fn array_dedup_empty_array() {
+        let unique = array_dedup(args(vec![json!([])])).unwrap();
+        assert_eq!(unique, json!([]));
+    }
+
+    #[test]
+    fn array_dedup_by_key_path() {
+        let array = json!([
+            { "a": { "id": 1 }, "v": "first" },
+            { "a": { "id": 2 } },
+            { "a": { "id": 1 }, "v": "second" }
+        ]);
+        let unique = array_dedup(args(vec![array, json!("a.id")])).unwrap();
+        assert_eq!(
+            unique,
+            json!([{ "a": { "id": 1 }, "v": "first" }, { "a": { "id": 2 } }])
+        );
+    }
+
+    #[test]
+    fn array_dedup_by_key_path_rejects_non_objects_with_index() {
+        let array = json!([{ "id": 1 }, 42, { "id": 2 }]);
+        let err = array_dedup(args(vec![array, json!("id")]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("element at index 1 is not an object"));
+    }
+
+    #[test]
+    fn array_dedup_rejects_arrays_over_the_cap() {
+        let result = dedup_with_cap(args(vec![json!([1, 1, 2])]), 2);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn array_flatten_one_level_by_default() {
+        let array = json!([[1, [2]], 3, [4]]);
+        let flattened = array_flatten(args(vec![array])).unwrap();
+        assert_eq!(flattened, json!([1, [2], 3, 4]));
+    }
+
+    #[test]
+    fn array_flatten_with_depth() {
+        let array = json!([[1, [2, [3]]], [4]]);
+        let flattened = array_flatten(args(vec![array, json!(2)])).unwrap();
+        assert_eq!(flattened, json!([1, 2, [3], 4]));
+    }
+
+    #[test]
+    fn array_flatten_empty_array() {
+        let flattened = array_flatten(args(vec![json!([])])).unwrap();
+        assert_eq!(flattened, json!([]));
+    }
+
+    #[test]
+    fn array_flatten_rejects_results_over_the_cap() {
+        let result = flatten_with_cap(args(vec![json!([[1, 2], [3, 4]])]), 3);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn array_concat_joins_arrays_in_order() {
+        let concatenated =
+            array_concat(args(vec![json!([1, 2]), json!([]), json!([3])])).unwrap();
+        assert_eq!(concatenated, json!([1, 2, 3]));
+    }
+
+    #[test]
+    fn array_concat_rejects_non_arrays_with_index() {
+        let err = array_concat(args(vec![json!([1]), json!(2)]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("argument at index 1 must be an array"));
+    }
+
+    #[test]
+    fn array_concat_rejects_results_over_the_cap() {
+        let result = concat_with_cap(args(vec![json!([1, 2]), json!([3, 4])]), 3);
+        assert!(result.is_err());
+    }
+}
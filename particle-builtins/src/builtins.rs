@@ -36,26 +36,32 @@ use connection_pool::{ConnectionPoolApi, ConnectionPoolT};
 use health::HealthCheckRegistry;
 use kademlia::{KademliaApi, KademliaApiT};
 use now_millis::{now_ms, now_sec};
-use particle_args::{from_base58, Args, ArgsError, JError};
+use particle_args::{error_codes, from_base58, Args, ArgsError, JError};
 use particle_execution::{FunctionOutcome, ParticleParams, ServiceFunction};
 use particle_modules::{
-    AddBlueprint, EffectorsMode, ModuleConfig, ModuleRepository, NamedModuleConfig, WASIConfig,
+    lint_module_config, AddBlueprint, EffectorsMode, LintLevel, ModuleConfig, ModuleError,
+    ModuleRepository, NamedModuleConfig, WASIConfig,
 };
 use particle_protocol::Contact;
 use particle_services::{
-    ParticleAppServices, ParticleAppServicesConfig, PeerScope, ServiceInfo, ServiceType,
+    ParticleAppServices, ParticleAppServicesConfig, PeerScope, ServiceError, ServiceInfo,
+    ServiceType,
 };
 use peer_metrics::ServicesMetrics;
 use types::peer_id;
 use uuid_utils::uuid;
 use workers::{KeyStorage, PeerScopes, Workers};
 
+use crate::capabilities::CapabilityRegistry;
 use crate::debug::fmt_custom_services;
 use crate::error::HostClosureCallError;
 use crate::error::HostClosureCallError::{DecodeBase58, DecodeUTF8};
 use crate::func::{binary, unary};
+use crate::catalog::{self, BuiltinMeta};
+use crate::ipfs::{IpfsClient, IpfsConfig};
 use crate::outcome::{ok, wrap, wrap_unit};
-use crate::{json, math};
+use crate::usage::{BuiltinUsageConfig, BuiltinUsageTracker};
+use crate::{array, encoding, json, math};
 
 pub struct CustomService {
     /// (function_name -> service function)
@@ -86,11 +92,27 @@ pub struct Builtins<C> {
     #[derivative(Debug(format_with = "fmt_custom_services"))]
     pub custom_services: RwLock<HashMap<String, CustomService>>,
 
+    /// What this peer advertises to others: builtins and installed modules
+    #[derivative(Debug = "ignore")]
+    pub capabilities: Arc<CapabilityRegistry>,
+
+    /// Metadata of every registered builtin, queried by `op list_builtins`
+    #[derivative(Debug = "ignore")]
+    catalog: HashMap<(String, String), BuiltinMeta>,
+
     #[derivative(Debug = "ignore")]
     key_storage: Arc<KeyStorage>,
     #[derivative(Debug = "ignore")]
     scopes: PeerScopes,
     connector_api_endpoint: String,
+    builtin_usage: BuiltinUsageTracker,
+    #[derivative(Debug = "ignore")]
+    ipfs: IpfsClient,
+    /// When the node's builtins were created, to report uptime
+    started: Instant,
+    /// Per-particle service call deadlines set by `srv.call_timeout`;
+    /// deadlines of long-gone particles are pruned whenever a new one is set
+    call_deadlines: parking_lot::Mutex<HashMap<String, Instant>>,
 }
 
 impl<C> Builtins<C>
@@ -106,6 +128,9 @@ where
         scope: PeerScopes,
         health_registry: Option<&mut HealthCheckRegistry>,
         connector_api_endpoint: String,
+        usage_config: BuiltinUsageConfig,
+        ipfs_config: IpfsConfig,
+        default_wasi_envs: HashMap<String, String>,
     ) -> Self {
         let modules_dir = &config.modules_dir;
         let blueprint_dir = &config.blueprint_dir;
@@ -118,7 +143,8 @@ where
                 effectors: config.allowed_effectors.clone(),
             }
         };
-        let modules = ModuleRepository::new(modules_dir, blueprint_dir, effectors_mode);
+        let modules = ModuleRepository::new(modules_dir, blueprint_dir, effectors_mode)
+            .with_default_wasi_envs(default_wasi_envs);
         let services = ParticleAppServices::new(
             config,
             modules.clone(),
@@ -129,18 +155,33 @@ where
         )
         .expect("TODO async-marine: handle error from ParticleAppServices");
 
+        let builtins_version = semver::Version::parse(env!("CARGO_PKG_VERSION"))
+            .expect("particle-builtins version is a valid semver");
+        let capabilities = Arc::new(CapabilityRegistry::with_builtins(builtins_version));
+
         Self {
             connectivity,
             modules,
             services,
             custom_services: <_>::default(),
+            capabilities,
+            catalog: catalog::builtin_catalog(),
             key_storage,
             scopes: scope,
             connector_api_endpoint,
+            builtin_usage: BuiltinUsageTracker::new(usage_config),
+            ipfs: IpfsClient::new(ipfs_config),
+            started: Instant::now(),
+            call_deadlines: <_>::default(),
         }
     }
 
     pub async fn call(&self, args: Args, particle: ParticleParams) -> FunctionOutcome {
+        if let Err(err) = self.guard_builtin_budget(&args, &particle) {
+            return FunctionOutcome::Err(err);
+        }
+
+        let init_peer_id = particle.init_peer_id;
         let mut start = Instant::now();
         let result = self.builtins_call(args, particle).await;
         let result = match result {
@@ -150,14 +191,15 @@ where
             }
             result => result,
         };
-        let end = start.elapsed().as_secs();
+        let elapsed = start.elapsed();
 
         match result {
             FunctionOutcome::NotDefined { args, params } => self.call_service(args, params).await,
             result => {
                 if let Some(metrics) = self.services.metrics.as_ref() {
-                    metrics.observe_builtins(result.not_err(), end as f64);
+                    metrics.observe_builtins(result.not_err(), elapsed.as_secs() as f64);
                 }
+                self.builtin_usage.observe(init_peer_id, elapsed);
                 result
             }
         }
@@ -196,10 +238,15 @@ where
         match (args.service_id.as_str(), args.function_name.as_str()) {
             ("peer", "timestamp_ms") => ok(json!(now_ms() as u64)),
             ("peer", "timestamp_sec") => ok(json!(now_sec())),
+            ("peer", "timestamp") => wrap(self.timestamp(args)),
             ("peer", "is_connected") => wrap(self.is_connected(args).await),
             ("peer", "connect") => wrap(self.connect(args, particle).await),
             ("peer", "get_contact") => self.get_contact(args).await,
             ("peer", "timeout") => self.timeout(args).await,
+            ("peer", "capabilities") => ok(self.peer_capabilities()),
+
+            ("net", "slow_links") => wrap(self.slow_links(args)),
+            ("net", "network_graph") => wrap(self.network_graph(args).await),
 
             ("kad", "neighborhood") => wrap(self.neighborhood(args).await),
             ("kad", "neigh_with_addrs") => wrap(self.neighborhood_with_addresses(args).await),
@@ -213,6 +260,11 @@ where
             ("srv", "add_alias") => wrap_unit(self.add_alias(args, particle).await),
             ("srv", "remove") => wrap_unit(self.remove_service(args, particle).await),
             ("srv", "info") => wrap(self.get_service_info(args, particle).await),
+            ("srv", "transfer_ownership") => wrap_unit(self.transfer_service_ownership(args, particle).await),
+            ("srv", "grant_delegate") => wrap_unit(self.grant_service_delegate(args, particle).await),
+            ("srv", "revoke_delegate") => wrap_unit(self.revoke_service_delegate(args, particle).await),
+            ("srv", "list_delegates") => wrap(self.list_service_delegates(args, particle).await),
+            ("srv", "call_timeout") => wrap_unit(self.call_timeout(args, &particle)),
 
             ("dist", "add_module_from_vault") => wrap(self.add_module_from_vault(args, particle).await),
             ("dist", "add_module") => wrap(self.add_module(args, particle).await),
@@ -221,10 +273,12 @@ where
             ("dist", "make_module_config") => wrap(make_module_config(args)),
             ("dist", "load_module_config") => wrap(self.load_module_config_from_vault(args, particle)),
             ("dist", "default_module_config") => wrap(self.default_module_config(args)),
+            ("dist", "lint_module_config") => wrap(self.lint_module_config(args)),
             ("dist", "make_blueprint") => wrap(self.make_blueprint(args)),
             ("dist", "load_blueprint") => wrap(self.load_blueprint_from_vault(args, particle)),
             ("dist", "list_modules") => wrap(self.list_modules()),
             ("dist", "get_module_interface") => wrap(self.get_module_interface(args)),
+            ("dist", "get_module") => wrap(self.get_module(args, particle)),
             ("dist", "list_blueprints") => wrap(self.get_blueprints()),
             ("dist", "get_blueprint") => wrap(self.get_blueprint(args)),
 
@@ -236,14 +290,22 @@ where
             ("op", "string_from_b58") => wrap(self.string_from_b58(args.function_args)),
             ("op", "bytes_from_b58") => wrap(self.bytes_from_b58(args.function_args)),
             ("op", "bytes_to_b58") => wrap(self.bytes_to_b58(args.function_args)),
+            ("op", "b64_encode") => wrap(encoding::b64_encode(args)),
+            ("op", "b64_decode") => wrap(encoding::b64_decode(args)),
             ("op", "sha256_string") => wrap(self.sha256_string(args.function_args)),
             ("op", "concat_strings") => wrap(self.concat_strings(args.function_args)),
             ("op", "identity") => self.identity(args.function_args),
+            ("op", "monotonic_ms") => ok(json!(monotonic_ms())),
+            ("op", "interval_ms") => wrap(self.interval_ms(args.function_args)),
+            ("op", "timestamps") => ok(self.timestamps()),
+            ("op", "list_builtins") => wrap(catalog::list_builtins(&self.catalog, args)),
 
             ("debug", "stringify") => self.stringify(args.function_args),
+            ("debug", "particle") => wrap(self.debug_particle(particle)),
 
             ("stat", "service_memory") => wrap(self.service_mem_stats(args, particle).await),
             ("stat", "service_stat") => wrap(self.service_stat(args, particle).await),
+            ("stat", "builtin_usage") => wrap(self.builtin_usage_stats(args, particle).await),
 
             ("math", "add") => binary(args, |x: i64, y: i64| -> R<i64, _> { math::add(x, y) }),
             ("math", "sub") => binary(args, |x: i64, y: i64| -> R<i64, _> { math::sub(x, y) }),
@@ -261,10 +323,15 @@ where
             ("cmp", "cmp") => binary(args, |x: i64, y: i64| -> R<i8, _> { math::cmp(x, y) }),
 
             ("array", "sum") => unary(args, |xs: Vec<i64>| -> R<i64, _> { math::array_sum(xs) }),
-            ("array", "dedup") => unary(args, |xs: Vec<String>| -> R<Vec<String>, _> { math::dedup(xs) }),
+            ("array", "sort") => wrap(array::array_sort(args)),
+            ("array", "dedup") => wrap(array::array_dedup(args)),
+            ("array", "flatten") => wrap(array::array_flatten(args)),
+            ("array", "concat") => wrap(array::array_concat(args)),
             ("array", "intersect") => binary(args, |xs: HashSet<String>, ys: HashSet<String>| -> R<Vec<String>, _> { math::intersect(xs, ys) }),
             ("array", "diff") => binary(args, |xs: HashSet<String>, ys: HashSet<String>| -> R<Vec<String>, _> { math::diff(xs, ys) }),
             ("array", "sdiff") => binary(args, |xs: HashSet<String>, ys: HashSet<String>| -> R<Vec<String>, _> { math::sdiff(xs, ys) }),
+            ("array", "unique") => wrap(array::array_unique(args)),
+            ("array", "intersection") => wrap(array::array_intersection(args)),
             ("array", "slice") => wrap(self.array_slice(args.function_args)),
             ("array", "length") => wrap(self.array_length(args.function_args)),
 
@@ -274,15 +341,26 @@ where
 
             ("json", "obj") => wrap(json::obj(args)),
             ("json", "put") => wrap(json::put(args)),
+            ("json", "put_if_absent") => wrap(json::put_if_absent(args)),
+            ("json", "put_or_replace_null") => wrap(json::put_or_replace_null(args)),
             ("json", "puts") => wrap(json::puts(args)),
             ("json", "parse") => unary(args, |s: String| -> R<JValue, _> { json::parse(&s) }),
             ("json", "stringify") => unary(args, |v: JValue| -> R<String, _> { Ok(json::stringify(v)) }),
             ("json", "obj_pairs") => unary(args, |vs: Vec<(String, JValue)>| -> R<JValue, _> { json::obj_from_pairs(vs) }),
             ("json", "puts_pairs") => binary(args, |obj: JValue, vs: Vec<(String, JValue)>| -> R<JValue, _> { json::puts_from_pairs(obj, vs) }),
+            ("json", "merge_patch") => wrap(json::merge_patch(args)),
+            ("json", "sort_by") => wrap(json::sort_by(args)),
+            ("json", "select") => wrap(json::select(args)),
+            ("json", "template") => wrap(json::template(args)),
+            ("json", "jsonpath") => wrap(json::jsonpath(args)),
+            ("json", "validate") => wrap(json::json_validate(args)),
 
             ("vault", "put") => wrap(self.vault_put(args, particle)),
             ("vault", "cat") => wrap(self.vault_cat(args, particle)),
 
+            ("ipfs", "cat") => wrap(self.ipfs.cat(args).await),
+            ("ipfs", "add") => wrap(self.ipfs.add(args).await),
+
             ("subnet", "resolve") => wrap(self.subnet_resolve(args).await),
             ("run-console", "print") => {
                 self.guard_protected(&particle).await?;
@@ -372,6 +450,67 @@ where
         Ok(json!(ok))
     }
 
+    /// A local view of the p2p graph around this node: a JSON object mapping
+    /// peer ids to their known addresses. `depth` is how many hops out to
+    /// include (default 1, max 2). Direct neighbors come from the connection
+    /// pool; the second hop is approximated by a kademlia lookup around each
+    /// neighbor, with addresses resolved from the local routing table
+    async fn network_graph(&self, args: Args) -> Result<JValue, JError> {
+        use futures::stream::FuturesUnordered;
+        use futures::StreamExt;
+
+        let mut args = args.function_args.into_iter();
+        let depth: Option<usize> = Args::next_opt("depth", &mut args)?;
+        let depth = depth.unwrap_or(1);
+        if !(1..=2).contains(&depth) {
+            return Err(JError::new(format!(
+                "invalid depth {depth}: must be 1 or 2"
+            )));
+        }
+
+        let contacts = self.connection_pool().get_all_contacts().await;
+        let mut graph = serde_json::Map::new();
+        for contact in &contacts {
+            graph.insert(contact.peer_id.to_base58(), json!(contact.addresses));
+        }
+
+        if depth == 2 {
+            let second_hop: Vec<Vec<PeerId>> = contacts
+                .iter()
+                .map(|contact| async move {
+                    let key = Multihash::from_bytes(&contact.peer_id.to_bytes()).ok()?;
+                    self.kademlia().neighborhood(key, K_VALUE.get()).await.ok()
+                })
+                .collect::<FuturesUnordered<_>>()
+                .filter_map(|peers| async move { peers })
+                .collect()
+                .await;
+
+            for peer_id in second_hop.into_iter().flatten() {
+                let key = peer_id.to_base58();
+                if graph.contains_key(&key) || self.scopes.is_host(peer_id) {
+                    continue;
+                }
+                let addresses = self
+                    .kademlia()
+                    .local_lookup(peer_id)
+                    .await
+                    .unwrap_or_default();
+                graph.insert(key, json!(addresses));
+            }
+        }
+
+        Ok(JValue::Object(graph))
+    }
+
+    /// Returns the top `count` (10 by default) links by p95 particle send latency
+    fn slow_links(&self, args: Args) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let count: Option<usize> = Args::next_opt("count", &mut args)?;
+        let report = self.connection_pool().slow_links(count.unwrap_or(10));
+        Ok(json!(report))
+    }
+
     async fn connect(&self, args: Args, params: ParticleParams) -> Result<JValue, JError> {
         let mut args = args.function_args.into_iter();
 
@@ -533,6 +672,62 @@ where
         FunctionOutcome::Ok(JValue::String(debug))
     }
 
+    /// Returns metadata of the particle this call was made from,
+    /// so scripts can inspect their own `id`, `init_peer_id`, `timestamp`
+    /// and `ttl` without passing them around as arguments
+    fn debug_particle(&self, params: ParticleParams) -> Result<JValue, JError> {
+        Ok(json!({
+            "id": params.id,
+            "init_peer_id": params.init_peer_id.to_base58(),
+            "timestamp": params.timestamp,
+            "ttl": params.ttl,
+        }))
+    }
+
+    /// Returns the node's current time in the format requested by the argument:
+    /// `sec` (unix seconds), `ms` (unix millis) or `rfc3339`
+    fn timestamp(&self, args: Args) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let format: String = Args::next("format", &mut args)?;
+        format_timestamp(now_ms(), &format)
+    }
+
+    /// Returns milliseconds elapsed since a reading previously obtained
+    /// from `op.monotonic_ms`. Readings taken before the node restarted
+    /// refer to another epoch, so the result is clamped to zero instead
+    /// of yielding a bogus huge interval
+    fn interval_ms(&self, args: Vec<serde_json::Value>) -> Result<JValue, JError> {
+        match &args[..] {
+            [JValue::Number(start)] => {
+                let start = start.as_u64().ok_or_else(|| {
+                    JError::coded(
+                        error_codes::OP_INVALID_ARGS,
+                        "op interval_ms's argument must be a reading returned by op monotonic_ms",
+                    )
+                })?;
+                Ok(json!(monotonic_ms().saturating_sub(start)))
+            }
+            [_] => Err(JError::coded(
+                error_codes::OP_INVALID_ARGS,
+                "op interval_ms's argument must be a reading returned by op monotonic_ms",
+            )),
+            args => Err(JError::coded(
+                error_codes::OP_INVALID_ARGS,
+                format!("op interval_ms accepts exactly 1 argument: {} found", args.len()),
+            )),
+        }
+    }
+
+    /// Returns wall clock time, the monotonic reading and the node's uptime
+    /// in one call, so hot spell loops don't pay for three separate calls
+    fn timestamps(&self) -> JValue {
+        json!({
+            "timestamp_ms": now_ms() as u64,
+            "monotonic_ms": monotonic_ms(),
+            "uptime_ms": self.started.elapsed().as_millis() as u64,
+        })
+    }
+
     /// Flattens an array of arrays
     fn concat(&self, args: Vec<serde_json::Value>) -> Result<JValue, JError> {
         let flattened: Vec<JValue> =
@@ -543,9 +738,10 @@ where
                         acc.append(&mut array);
                         Ok(acc)
                     }
-                    _ => Err(JError::new(format!(
-                        "all arguments of 'concat' must be arrays: argument #{i} is not"
-                    ))),
+                    _ => Err(JError::coded(
+                        error_codes::OP_INVALID_ARGS,
+                        format!("all arguments of 'concat' must be arrays: argument #{i} is not"),
+                    )),
                 })?;
 
         Ok(JValue::Array(flattened))
@@ -561,9 +757,12 @@ where
                         acc.push_str(&s);
                         Ok(acc)
                     }
-                    _ => Err(JError::new(format!(
-                        "all arguments of 'concat_strings' must be strings: argument #{i} is not"
-                    ))),
+                    _ => Err(JError::coded(
+                        error_codes::OP_INVALID_ARGS,
+                        format!(
+                            "all arguments of 'concat_strings' must be strings: argument #{i} is not"
+                        ),
+                    )),
                 })?;
 
         Ok(JValue::String(string))
@@ -572,21 +771,26 @@ where
     fn array_length(&self, args: Vec<serde_json::Value>) -> Result<JValue, JError> {
         match &args[..] {
             [JValue::Array(array)] => Ok(json!(array.len())),
-            [_] => Err(JError::new("op array_length's argument must be an array")),
-            arr => Err(JError::new(format!(
-                "op array_length accepts exactly 1 argument: {} found",
-                arr.len()
-            ))),
+            [_] => Err(JError::coded(
+                error_codes::OP_INVALID_ARGS,
+                "op array_length's argument must be an array",
+            )),
+            arr => Err(JError::coded(
+                error_codes::OP_INVALID_ARGS,
+                format!("op array_length accepts exactly 1 argument: {} found", arr.len()),
+            )),
         }
     }
 
     /// takes a range of values from an array
-    /// slice(array: []JValue, start: usize, end: usize) -> []JValue
+    /// slice(array: []JValue, start: i64, end: i64) -> []JValue
+    /// negative indexes count from the end of the array: -1 is the last element
     fn array_slice(&self, args: Vec<serde_json::Value>) -> Result<JValue, JError> {
         let (array, start, end) = if let [array, start, end] = &args[..] {
             (array, start, end)
         } else {
-            return Err(JError::new(
+            return Err(JError::coded(
+                error_codes::OP_INVALID_ARGS,
                 "invalid number of parameters. need array, start index and end index",
             ));
         };
@@ -595,48 +799,95 @@ where
             JValue::Array(arr) if arr.is_empty() => return Ok(json!([])),
             JValue::Array(arr) => arr,
             e => {
-                return Err(JError::new(format!(
-                    "first argument must be an array, was {e}"
-                )));
+                return Err(JError::coded(
+                    error_codes::OP_INVALID_ARGS,
+                    format!("first argument must be an array, was {e}"),
+                ));
             }
         };
 
-        let start = match start.as_u64() {
-            Some(n) => n as usize,
+        let len = array.len() as i64;
+        let resolve = |index: i64| if index < 0 { len + index } else { index };
+
+        let start = match start.as_i64() {
+            Some(n) => resolve(n),
             _ => {
-                return Err(JError::new(format!(
-                    "second argument (start index) must be an unsigned integer, was {start}"
-                )));
+                return Err(JError::coded(
+                    error_codes::OP_INVALID_ARGS,
+                    format!("second argument (start index) must be an integer, was {start}"),
+                ));
             }
         };
 
-        let end = match end.as_u64() {
-            Some(n) => n as usize,
+        let end = match end.as_i64() {
+            Some(n) => resolve(n),
             _ => {
-                return Err(JError::new(format!(
-                    "third argument (end index) must be an unsigned integer, was {end}"
-                )));
+                return Err(JError::coded(
+                    error_codes::OP_INVALID_ARGS,
+                    format!("third argument (end index) must be an integer, was {end}"),
+                ));
             }
         };
 
-        if start > end || end > array.len() {
-            return Err(JError::new(format!(
-                "slice indexes out of bounds. start index: {:?}, end index: {:?}, array length: {:?}",
-                start, end, array.len())
+        if start < 0 || start > end || end > len {
+            return Err(JError::coded(
+                error_codes::OP_INVALID_ARGS,
+                format!(
+                    "slice indexes out of bounds. start index: {start:?}, end index: {end:?}, array length: {len:?}"
+                ),
             ));
         }
 
-        let slice: Vec<JValue> = array[start..end].to_vec();
+        let slice: Vec<JValue> = array[start as usize..end as usize].to_vec();
         Ok(JValue::Array(slice))
     }
 
+    /// Capabilities of the local peer: builtin namespaces and installed
+    /// modules, each with a version
+    fn peer_capabilities(&self) -> JValue {
+        json!(self.capabilities.list())
+    }
+
     async fn add_module(&self, args: Args, params: ParticleParams) -> Result<JValue, JError> {
         let mut args = args.function_args.into_iter();
         let module_bytes: String = Args::next("module_bytes", &mut args)?;
-        let config = Args::next("config", &mut args)?;
+        let config: JValue = Args::next("config", &mut args)?;
+        let public: Option<bool> = Args::next_opt("public", &mut args)?;
+
+        // lint on the raw JSON: deserialization below drops unknown keys silently
+        let lints = lint_module_config(&config);
+        let errors: Vec<&str> = lints
+            .iter()
+            .filter(|lint| lint.level == LintLevel::Error)
+            .map(|lint| lint.message.as_str())
+            .collect();
+        if !errors.is_empty() {
+            return Err(JError::coded(
+                error_codes::DIST_INVALID_CONFIG,
+                format!("invalid module config: {}", errors.join("; ")),
+            ));
+        }
+        for lint in &lints {
+            log::warn!("module config lint: {}", lint.message);
+        }
+
+        let config: TomlMarineNamedModuleConfig = serde_json::from_value(config).map_err(|err| {
+            JError::coded(
+                error_codes::DIST_INVALID_CONFIG,
+                format!("Error deserializing module config: {err}"),
+            )
+        })?;
 
         self.guard_protected(&params).await?;
-        let hash = self.modules.add_module_base64(module_bytes, config)?;
+        let module_name = config.name.clone();
+        let hash = self
+            .modules
+            .add_module_base64(module_bytes, config)
+            .map_err(ModuleError::into_jerror)?;
+        self.modules
+            .set_module_access(&hash, params.init_peer_id, public.unwrap_or(false))
+            .map_err(ModuleError::into_jerror)?;
+        self.capabilities.add_module(&module_name);
 
         Ok(json!(hash))
     }
@@ -649,16 +900,23 @@ where
         let mut args = args.function_args.into_iter();
         let module_path: String = Args::next("module_path", &mut args)?;
         let config: TomlMarineNamedModuleConfig = Args::next("config", &mut args)?;
+        let public: Option<bool> = Args::next_opt("public", &mut args)?;
 
         self.guard_protected(&params).await?;
 
+        let init_peer_id = params.init_peer_id;
         let module_hash = self.modules.add_module_from_vault(
             &self.services.vault,
             self.scopes.to_peer_id(params.peer_scope),
-            config.name,
+            config.name.clone(),
             module_path,
             params,
-        )?;
+        )
+        .map_err(ModuleError::into_jerror)?;
+        self.modules
+            .set_module_access(&module_hash, init_peer_id, public.unwrap_or(false))
+            .map_err(ModuleError::into_jerror)?;
+        self.capabilities.add_module(&config.name);
 
         Ok(json!(module_hash))
     }
@@ -671,16 +929,23 @@ where
         let mut args = args.function_args.into_iter();
         let module_name: String = Args::next("module_name", &mut args)?;
         let module_path: String = Args::next("module_path", &mut args)?;
+        let public: Option<bool> = Args::next_opt("public", &mut args)?;
 
         self.guard_protected(&params).await?;
 
+        let init_peer_id = params.init_peer_id;
         let module_hash = self.modules.add_module_from_vault(
             &self.services.vault,
             self.scopes.to_peer_id(params.peer_scope),
-            module_name,
+            module_name.clone(),
             module_path,
             params,
-        )?;
+        )
+        .map_err(ModuleError::into_jerror)?;
+        self.modules
+            .set_module_access(&module_hash, init_peer_id, public.unwrap_or(false))
+            .map_err(ModuleError::into_jerror)?;
+        self.capabilities.add_module(&module_name);
 
         Ok(json!(module_hash))
     }
@@ -692,9 +957,15 @@ where
         self.guard_protected(&params).await?;
 
         let blueprint = AddBlueprint::decode(blueprint.as_bytes()).map_err(|err| {
-            JError::new(format!("Error deserializing blueprint from IPLD: {err}"))
+            JError::coded(
+                error_codes::DIST_INVALID_BLUEPRINT,
+                format!("Error deserializing blueprint from IPLD: {err}"),
+            )
         })?;
-        let blueprint_id = self.modules.add_blueprint(blueprint)?;
+        let blueprint_id = self
+            .modules
+            .add_blueprint(blueprint)
+            .map_err(ModuleError::into_jerror)?;
         Ok(JValue::String(blueprint_id))
     }
 
@@ -711,7 +982,8 @@ where
             self.scopes.to_peer_id(params.peer_scope),
             config_path,
             params,
-        )?;
+        )
+        .map_err(ModuleError::into_jerror)?;
         let config = serde_json::to_value(config)
             .map_err(|err| JError::new(format!("Error serializing config to JSON: {err}")))?;
 
@@ -733,6 +1005,15 @@ where
         Ok(config)
     }
 
+    /// Runs the `add_module` config lint on a config JSON without uploading
+    /// anything; returns all findings, errors and warnings alike
+    fn lint_module_config(&self, args: Args) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let config: JValue = Args::next("config", &mut args)?;
+
+        Ok(json!(lint_module_config(&config)))
+    }
+
     fn make_blueprint(&self, args: Args) -> Result<JValue, JError> {
         let mut args = args.function_args.into_iter();
         let name = Args::next("name", &mut args)?;
@@ -759,9 +1040,10 @@ where
                 .vault
                 .cat_slice(current_peer_id, &params, Path::new(&blueprint_path))?;
         let blueprint = AddBlueprint::decode(&data).map_err(|err| {
-            JError::new(format!(
-                "Error parsing blueprint from vault {blueprint_path:?}: {err}"
-            ))
+            JError::coded(
+                error_codes::DIST_INVALID_BLUEPRINT,
+                format!("Error parsing blueprint from vault {blueprint_path:?}: {err}"),
+            )
         })?;
 
         let blueprint = blueprint
@@ -780,6 +1062,25 @@ where
         self.modules.get_interface(&hash)
     }
 
+    fn get_module(&self, args: Args, params: ParticleParams) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let module_hash: String = Args::next("module_hash", &mut args)?;
+        let offset: Option<usize> = Args::next_opt("offset", &mut args)?;
+        let length: Option<usize> = Args::next_opt("length", &mut args)?;
+
+        // the management key may download any module; other peers go through
+        // the per-module access rules recorded at upload time
+        if !self.scopes.is_management(params.init_peer_id) {
+            self.modules
+                .check_module_access(&module_hash, params.init_peer_id)?;
+        }
+        let chunk = self
+            .modules
+            .get_module_chunk(&module_hash, offset.unwrap_or(0), length)?;
+
+        Ok(json!(chunk))
+    }
+
     fn get_blueprints(&self) -> Result<JValue, JError> {
         self.modules
             .get_blueprints()
@@ -796,7 +1097,10 @@ where
         let mut args = args.function_args.into_iter();
         let blueprint_id: String = Args::next("blueprint_id", &mut args)?;
 
-        let blueprint = self.modules.get_blueprint_from_cache(&blueprint_id)?;
+        let blueprint = self
+            .modules
+            .get_blueprint_from_cache(&blueprint_id)
+            .map_err(ModuleError::into_jerror)?;
 
         Ok(json!(blueprint))
     }
@@ -815,7 +1119,8 @@ where
                 blueprint_id,
                 params.init_peer_id,
             )
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
 
         Ok(JValue::String(service_id))
     }
@@ -824,7 +1129,20 @@ where
         let mut args = args.function_args.into_iter();
         let service_id_or_alias: String = Args::next("service_id_or_alias", &mut args)?;
 
-        self.guard_protected(&params).await?;
+        // delegates hold management rights granted by the service owner;
+        // the services layer re-checks the grant before removing
+        let is_delegate = self
+            .services
+            .is_delegate(
+                params.peer_scope,
+                &service_id_or_alias,
+                params.init_peer_id,
+                &params.id,
+            )
+            .await;
+        if !is_delegate {
+            self.guard_protected(&params).await?;
+        }
 
         self.services
             .remove_service(
@@ -834,7 +1152,8 @@ where
                 params.init_peer_id,
                 false,
             )
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
 
         Ok(())
     }
@@ -851,9 +1170,40 @@ where
     }
 
     async fn call_service(&self, function_args: Args, particle: ParticleParams) -> FunctionOutcome {
-        self.services
-            .call_service(function_args, particle, true)
-            .await
+        let deadline = self.call_deadlines.lock().get(&particle.id).copied();
+        match deadline {
+            Some(deadline) => {
+                let service_id = function_args.service_id.clone();
+                enforce_call_deadline(
+                    &service_id,
+                    deadline,
+                    self.services.call_service(function_args, particle, true),
+                )
+                .await
+            }
+            None => {
+                self.services
+                    .call_service(function_args, particle, true)
+                    .await
+            }
+        }
+    }
+
+    /// Sets a deadline for the particle's subsequent service calls: a call
+    /// still running when `timeout_ms` elapses is cut off with a "timeout"
+    /// error, and a call dispatched past the deadline fails without waiting
+    fn call_timeout(&self, args: Args, params: &ParticleParams) -> Result<(), JError> {
+        let mut args = args.function_args.into_iter();
+
+        let ms_field = "timeout_ms";
+        let timeout_ms: u64 =
+            parse_from_str(ms_field, &mut args)?.ok_or(ArgsError::MissingField(ms_field))?;
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+        let mut deadlines = self.call_deadlines.lock();
+        deadlines.retain(|_, deadline| *deadline > Instant::now());
+        deadlines.insert(params.id.clone(), deadline);
+        Ok(())
     }
 
     async fn get_interface(&self, args: Args, params: ParticleParams) -> Result<JValue, JError> {
@@ -862,7 +1212,8 @@ where
         Ok(self
             .services
             .get_interface(params.peer_scope, service_id, &params.id)
-            .await?)
+            .await
+            .map_err(ServiceError::into_jerror)?)
     }
 
     async fn add_alias(&self, args: Args, params: ParticleParams) -> Result<(), JError> {
@@ -871,7 +1222,15 @@ where
         let alias: String = Args::next("alias", &mut args)?;
         let service_id: String = Args::next("service_id", &mut args)?;
 
-        self.guard_protected(&params).await?;
+        // delegates hold management rights granted by the service owner;
+        // the services layer re-checks the grant before aliasing
+        let is_delegate = self
+            .services
+            .is_delegate(params.peer_scope, &service_id, params.init_peer_id, &params.id)
+            .await;
+        if !is_delegate {
+            self.guard_protected(&params).await?;
+        }
 
         self.services
             .add_alias(
@@ -880,7 +1239,8 @@ where
                 service_id.clone(),
                 params.init_peer_id,
             )
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
 
         log::debug!(
             "Added alias {} for service {:?} {}",
@@ -898,7 +1258,8 @@ where
         let service_id = self
             .services
             .resolve_alias(params.peer_scope, alias.clone(), &params.id)
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
 
         log::debug!(
             "Resolved alias {} to service {:?} {}",
@@ -933,11 +1294,112 @@ where
         let info = self
             .services
             .get_service_info(params.peer_scope, service_id_or_alias, &params.id)
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
 
         Ok(json!(Service::from(&info, self.scopes.clone())))
     }
 
+    /// Reassigns the service owner. The services layer allows this only for
+    /// the current owner or the management peer, so no `guard_protected` here
+    async fn transfer_service_ownership(
+        &self,
+        args: Args,
+        params: ParticleParams,
+    ) -> Result<(), JError> {
+        let mut args = args.function_args.into_iter();
+        let service_id_or_alias: String = Args::next("service_id_or_alias", &mut args)?;
+        let new_owner: String = Args::next("new_owner", &mut args)?;
+        let new_owner = PeerId::from_str(new_owner.as_str())?;
+
+        self.services
+            .transfer_ownership(
+                params.peer_scope,
+                service_id_or_alias,
+                new_owner,
+                params.init_peer_id,
+                &params.id,
+            )
+            .await
+            .map_err(ServiceError::into_jerror)?;
+
+        Ok(())
+    }
+
+    /// Grants a peer management rights (remove, alias, info) on a service.
+    /// The services layer allows this only for the owner or the management peer
+    async fn grant_service_delegate(
+        &self,
+        args: Args,
+        params: ParticleParams,
+    ) -> Result<(), JError> {
+        let mut args = args.function_args.into_iter();
+        let service_id_or_alias: String = Args::next("service_id_or_alias", &mut args)?;
+        let delegate: String = Args::next("delegate", &mut args)?;
+        let delegate = PeerId::from_str(delegate.as_str())?;
+
+        self.services
+            .grant_delegate(
+                params.peer_scope,
+                service_id_or_alias,
+                delegate,
+                params.init_peer_id,
+                &params.id,
+            )
+            .await
+            .map_err(ServiceError::into_jerror)?;
+
+        Ok(())
+    }
+
+    /// Revokes a previously granted delegation, effective immediately
+    async fn revoke_service_delegate(
+        &self,
+        args: Args,
+        params: ParticleParams,
+    ) -> Result<(), JError> {
+        let mut args = args.function_args.into_iter();
+        let service_id_or_alias: String = Args::next("service_id_or_alias", &mut args)?;
+        let delegate: String = Args::next("delegate", &mut args)?;
+        let delegate = PeerId::from_str(delegate.as_str())?;
+
+        self.services
+            .revoke_delegate(
+                params.peer_scope,
+                service_id_or_alias,
+                delegate,
+                params.init_peer_id,
+                &params.id,
+            )
+            .await
+            .map_err(ServiceError::into_jerror)?;
+
+        Ok(())
+    }
+
+    /// Lists peers granted management rights on the service, as base58 strings
+    async fn list_service_delegates(
+        &self,
+        args: Args,
+        params: ParticleParams,
+    ) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let service_id_or_alias: String = Args::next("service_id_or_alias", &mut args)?;
+
+        let delegates = self
+            .services
+            .list_delegates(params.peer_scope, service_id_or_alias, &params.id)
+            .await
+            .map_err(ServiceError::into_jerror)?;
+
+        Ok(Array(
+            delegates
+                .into_iter()
+                .map(|peer_id| JValue::String(peer_id.to_base58()))
+                .collect(),
+        ))
+    }
+
     fn kademlia(&self) -> &KademliaApi {
         self.connectivity.as_ref()
     }
@@ -967,7 +1429,8 @@ where
         let service_id = self
             .services
             .to_service_id(params.peer_scope, service_id_or_alias, &params.id)
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
         let metrics = self
             .services
             .metrics
@@ -988,6 +1451,17 @@ where
         }
     }
 
+    async fn builtin_usage_stats(
+        &self,
+        args: Args,
+        params: ParticleParams,
+    ) -> Result<JValue, JError> {
+        self.guard_protected(&params).await?;
+        let mut args = args.function_args.into_iter();
+        let count: Option<usize> = Args::next_opt("count", &mut args)?;
+        Ok(self.builtin_usage.report(count.unwrap_or(10)))
+    }
+
     fn sign(&self, args: Args, params: ParticleParams) -> Result<JValue, JError> {
         let tetraplets = args.tetraplets;
         let mut args = args.function_args.into_iter();
@@ -1112,6 +1586,35 @@ where
         Ok(json!(result))
     }
 
+    /// Refuses a builtin call when its init peer has spent more than the
+    /// configured builtin execution budget in the current usage window.
+    /// Management and the node's own (host/worker) peers are never throttled;
+    /// service and custom service calls are not budgeted at all.
+    fn guard_builtin_budget(&self, args: &Args, particle: &ParticleParams) -> Result<(), JError> {
+        if !is_builtin_namespace(&args.service_id) {
+            return Ok(());
+        }
+        let init_peer_id = particle.init_peer_id;
+        if self.scopes.is_management(init_peer_id) || self.scopes.scope(init_peer_id).is_ok() {
+            return Ok(());
+        }
+        if let Some(exceeded) = self.builtin_usage.exceeded_budget(init_peer_id) {
+            return Err(JError::coded(
+                error_codes::OP_BUDGET_EXCEEDED,
+                format!(
+                    "Builtin execution budget exceeded: peer {} spent {:?} of the {:?} budget in the current {:?} window; retry after the window rolls over",
+                    init_peer_id, exceeded.used, exceeded.budget, exceeded.window
+                ),
+            )
+            .with_details(json!({
+                "used_ms": exceeded.used.as_millis() as u64,
+                "budget_ms": exceeded.budget.as_millis() as u64,
+                "window_ms": exceeded.window.as_millis() as u64,
+            })));
+        }
+        Ok(())
+    }
+
     async fn guard_protected(&self, particle: &ParticleParams) -> Result<(), JError> {
         if self.is_worker_spell(particle).await
             || self.scopes.is_host(particle.init_peer_id)
@@ -1119,7 +1622,8 @@ where
         {
             Ok(())
         } else {
-            Err(JError::new(
+            Err(JError::coded(
+                error_codes::AUTH_PERMISSION_DENIED,
                 "This function is only available to the host or worker spells",
             ))
         }
@@ -1146,6 +1650,33 @@ where
     }
 }
 
+/// Builtin namespaces dispatched by [`Builtins::builtins_call`]; keep in sync
+/// with the match there. Used to apply the builtin execution budget only to
+/// builtin calls, leaving service calls alone.
+fn is_builtin_namespace(service_id: &str) -> bool {
+    matches!(
+        service_id,
+        "peer"
+            | "net"
+            | "kad"
+            | "srv"
+            | "dist"
+            | "op"
+            | "debug"
+            | "stat"
+            | "math"
+            | "cmp"
+            | "array"
+            | "sig"
+            | "json"
+            | "vault"
+            | "ipfs"
+            | "subnet"
+            | "run-console"
+            | "aqua-ipfs"
+    )
+}
+
 fn make_module_config(args: Args) -> Result<JValue, JError> {
     use toml_utils::table;
 
@@ -1182,6 +1713,64 @@ fn make_module_config(args: Args) -> Result<JValue, JError> {
     Ok(config)
 }
 
+/// An arbitrary but stable per-process epoch for `op.monotonic_ms`.
+/// Initialized on the first reading, so readings are comparable only
+/// within one node run
+fn monotonic_epoch() -> Instant {
+    static EPOCH: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// Milliseconds elapsed since [monotonic_epoch]. Unlike wall clock time,
+/// never goes backwards when NTP steps the clock. The value fits the JSON
+/// safe integer range (2^53 ms is ~285k years), so no wrapping occurs
+fn monotonic_ms() -> u64 {
+    monotonic_epoch().elapsed().as_millis() as u64
+}
+
+/// Renders a unix timestamp in milliseconds in one of the supported formats.
+/// The timestamp is anchored to `UNIX_EPOCH` the same way `now_millis` produces it,
+/// so all formats describe the same instant of the node's wall clock
+fn format_timestamp(now_ms: u128, format: &str) -> Result<JValue, JError> {
+    match format {
+        "sec" => Ok(json!((now_ms / 1000) as u64)),
+        "ms" => Ok(json!(now_ms as u64)),
+        "rfc3339" => {
+            let time = std::time::UNIX_EPOCH + Duration::from_millis(now_ms as u64);
+            Ok(json!(humantime::format_rfc3339_millis(time).to_string()))
+        }
+        unknown => Err(JError::new(format!(
+            "unsupported timestamp format `{unknown}`; supported formats: sec, ms, rfc3339"
+        ))),
+    }
+}
+
+/// Races `call` against `deadline`: an already-elapsed deadline fails without
+/// polling `call` at all, and a call still running at the deadline is cut off
+async fn enforce_call_deadline(
+    service_id: &str,
+    deadline: Instant,
+    call: impl std::future::Future<Output = FunctionOutcome>,
+) -> FunctionOutcome {
+    let now = Instant::now();
+    if now >= deadline {
+        return call_deadline_exceeded(service_id);
+    }
+    match tokio::time::timeout(deadline - now, call).await {
+        Ok(outcome) => outcome,
+        Err(_) => call_deadline_exceeded(service_id),
+    }
+}
+
+fn call_deadline_exceeded(service_id: &str) -> FunctionOutcome {
+    FunctionOutcome::Err(JError(json!({
+        "code": "timeout",
+        "message": format!(
+            "call to service '{service_id}' exceeded the deadline set by srv.call_timeout"
+        ),
+    })))
+}
+
 fn parse_from_str<T>(
     field: &'static str,
     mut args: &mut impl Iterator<Item = JValue>,
@@ -1256,6 +1845,113 @@ impl Service {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::builtins::{enforce_call_deadline, format_timestamp, monotonic_ms};
+
+    #[tokio::test]
+    async fn call_deadline_cuts_off_a_slow_service() {
+        use particle_execution::FunctionOutcome;
+        use std::time::{Duration, Instant};
+
+        // a mock service that sleeps for 200 ms, called with a 50 ms deadline
+        let started = Instant::now();
+        let outcome = enforce_call_deadline(
+            "slow",
+            Instant::now() + Duration::from_millis(50),
+            async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                FunctionOutcome::Ok(json!("done"))
+            },
+        )
+        .await;
+        assert!(
+            started.elapsed() < Duration::from_millis(100),
+            "the call must be cut off at the deadline, took {:?}",
+            started.elapsed()
+        );
+        match outcome {
+            FunctionOutcome::Err(err) => assert_eq!(err.0["code"], json!("timeout")),
+            other => panic!("expected a timeout error, got {other:?}"),
+        }
+
+        // an already-elapsed deadline fails without polling the call
+        let outcome = enforce_call_deadline("slow", Instant::now(), async {
+            panic!("the call must not be polled past the deadline")
+        })
+        .await;
+        match outcome {
+            FunctionOutcome::Err(err) => assert_eq!(err.0["code"], json!("timeout")),
+            other => panic!("expected a timeout error, got {other:?}"),
+        }
+
+        // a call that fits in the deadline flows through untouched
+        let outcome = enforce_call_deadline(
+            "fast",
+            Instant::now() + Duration::from_millis(200),
+            async { FunctionOutcome::Ok(json!("done")) },
+        )
+        .await;
+        match outcome {
+            FunctionOutcome::Ok(value) => assert_eq!(value, json!("done")),
+            other => panic!("expected Ok, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn timestamp_formats_are_consistent() {
+        // 2023-11-14T22:13:20.123Z
+        let now_ms: u128 = 1_700_000_000_123;
+
+        let sec = format_timestamp(now_ms, "sec").expect("sec must be supported");
+        let ms = format_timestamp(now_ms, "ms").expect("ms must be supported");
+        let rfc3339 = format_timestamp(now_ms, "rfc3339").expect("rfc3339 must be supported");
+
+        assert_eq!(sec, json!(1_700_000_000_u64));
+        assert_eq!(ms, json!(now_ms as u64));
+
+        let parsed = humantime::parse_rfc3339(rfc3339.as_str().expect("rfc3339 is a string"))
+            .expect("rfc3339 must be parseable back");
+        let parsed_ms = parsed
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("timestamp is past the epoch")
+            .as_millis();
+        assert_eq!(parsed_ms, now_ms);
+    }
+
+    #[test]
+    fn unknown_timestamp_format_is_rejected() {
+        let result = format_timestamp(1_700_000_000_123, "iso8601");
+        assert!(result.is_err(), "unknown formats must be rejected");
+    }
+
+    #[test]
+    fn monotonic_readings_never_decrease() {
+        let mut previous = monotonic_ms();
+        for _ in 0..100 {
+            let current = monotonic_ms();
+            assert!(
+                current >= previous,
+                "monotonic reading went backwards: {previous} -> {current}"
+            );
+            previous = current;
+        }
+    }
+
+    #[test]
+    fn monotonic_readings_advance_with_time() {
+        let before = monotonic_ms();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let after = monotonic_ms();
+        assert!(
+            after > before,
+            "reading must advance after a sleep: {before} -> {after}"
+        );
+    }
+}
+
 #[cfg(test)]
 mod prop_tests {
     use prop::collection::vec;
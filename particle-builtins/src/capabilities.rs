@@ -0,0 +1,110 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+use semver::Version;
+
+/// Builtin namespaces the node advertises as capabilities; must be kept in
+/// sync with the dispatch table in [crate::builtins]
+pub(crate) const BUILTIN_NAMESPACES: [&str; 16] = [
+    "peer", "net", "kad", "srv", "dist", "op", "debug", "stat", "math", "cmp", "array", "sig",
+    "json", "vault", "ipfs", "subnet",
+];
+
+/// What the local peer can do: builtin namespaces and installed modules,
+/// each with a version. Queried by the `peer.capabilities` builtin so
+/// scripts can branch on whether a peer supports a specific capability
+pub struct CapabilityRegistry {
+    capabilities: RwLock<HashMap<String, Version>>,
+}
+
+impl CapabilityRegistry {
+    /// A registry pre-populated with the node's builtin namespaces,
+    /// all advertised at the given version
+    pub fn with_builtins(version: Version) -> Self {
+        let capabilities = BUILTIN_NAMESPACES
+            .iter()
+            .map(|name| (name.to_string(), version.clone()))
+            .collect();
+        Self {
+            capabilities: RwLock::new(capabilities),
+        }
+    }
+
+    pub fn add(&self, name: impl Into<String>, version: Version) {
+        self.capabilities.write().insert(name.into(), version);
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Version> {
+        self.capabilities.write().remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Version> {
+        self.capabilities.read().get(name).cloned()
+    }
+
+    /// Registers an installed module. Modules don't carry a version,
+    /// so they are advertised with a zero one
+    pub fn add_module(&self, module_name: &str) {
+        self.add(module_capability(module_name), Version::new(0, 0, 0));
+    }
+
+    /// Removes capabilities of a module that is no longer available
+    pub fn remove_module(&self, module_name: &str) {
+        self.remove(&module_capability(module_name));
+    }
+
+    /// Capability name -> version, for JSON responses
+    pub fn list(&self) -> HashMap<String, String> {
+        self.capabilities
+            .read()
+            .iter()
+            .map(|(name, version)| (name.clone(), version.to_string()))
+            .collect()
+    }
+}
+
+fn module_capability(module_name: &str) -> String {
+    format!("module:{module_name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_namespaces_are_advertised() {
+        let registry = CapabilityRegistry::with_builtins(Version::new(1, 2, 3));
+        let capabilities = registry.list();
+        for namespace in BUILTIN_NAMESPACES {
+            assert_eq!(capabilities.get(namespace).map(String::as_str), Some("1.2.3"));
+        }
+    }
+
+    #[test]
+    fn removing_a_module_removes_its_capability() {
+        let registry = CapabilityRegistry::with_builtins(Version::new(1, 0, 0));
+        registry.add_module("curl_adapter");
+        assert!(registry.get("module:curl_adapter").is_some());
+
+        registry.remove_module("curl_adapter");
+        assert!(registry.get("module:curl_adapter").is_none());
+        // builtins are untouched
+        assert!(registry.get("peer").is_some());
+    }
+}
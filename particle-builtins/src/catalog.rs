@@ -0,0 +1,280 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value as JValue};
+
+use particle_args::{Args, JError};
+
+/// Metadata of a single registered builtin, as reported by `op list_builtins`
+#[derive(Debug, Clone)]
+pub struct BuiltinMeta {
+    pub name: &'static str,
+    pub namespace: &'static str,
+    pub description: &'static str,
+    /// Number of required arguments; `None` when the builtin is variadic
+    /// or takes optional arguments
+    pub arg_count: Option<usize>,
+}
+
+/// Every entry of the dispatch match in [`crate::Builtins::builtins_call`]:
+/// (namespace, name, required arg count, description). Keep in sync when
+/// adding or removing a builtin
+#[rustfmt::skip]
+const BUILTINS: &[(&str, &str, Option<usize>, &str)] = &[
+    ("peer", "timestamp_ms", Some(0), "Current node time as a unix timestamp in milliseconds"),
+    ("peer", "timestamp_sec", Some(0), "Current node time as a unix timestamp in seconds"),
+    ("peer", "timestamp", None, "Current node time in the requested format: sec, ms or rfc3339"),
+    ("peer", "is_connected", Some(1), "Checks whether the node is connected to the given peer"),
+    ("peer", "connect", None, "Connects the node to a peer by id and multiaddresses"),
+    ("peer", "get_contact", Some(1), "Returns a connected peer's id and addresses"),
+    ("peer", "timeout", Some(2), "Returns the given message after a timeout in milliseconds"),
+    ("peer", "capabilities", Some(0), "Lists capabilities supported by this node"),
+    ("net", "slow_links", None, "Lists connected peers with the slowest links"),
+    ("net", "network_graph", None, "Renders the connection graph around this node up to a depth"),
+    ("kad", "neighborhood", None, "Returns the Kademlia neighborhood of a key"),
+    ("kad", "neigh_with_addrs", None, "Returns the Kademlia neighborhood of a key with addresses"),
+    ("kad", "merge", None, "Merges peer lists by Kademlia distance to a target"),
+    ("srv", "list", Some(0), "Lists services deployed to the current scope"),
+    ("srv", "create", Some(1), "Creates a service from a blueprint"),
+    ("srv", "get_interface", Some(1), "Returns the interface of a service"),
+    ("srv", "resolve_alias", Some(1), "Resolves a service alias to a service id"),
+    ("srv", "resolve_alias_opt", Some(1), "Resolves a service alias to an optional service id"),
+    ("srv", "add_alias", Some(2), "Adds an alias for a service"),
+    ("srv", "remove", Some(1), "Removes a service by id or alias"),
+    ("srv", "info", Some(1), "Returns information about a service"),
+    ("srv", "transfer_ownership", Some(2), "Transfers service ownership to another peer"),
+    ("srv", "grant_delegate", Some(2), "Grants a peer management rights on a service"),
+    ("srv", "revoke_delegate", Some(2), "Revokes previously granted management rights"),
+    ("srv", "list_delegates", Some(1), "Lists peers granted management rights on a service"),
+    ("srv", "call_timeout", Some(1), "Sets a call deadline for the current particle"),
+    ("dist", "add_module_from_vault", Some(2), "Adds a module from a vault file with a config"),
+    ("dist", "add_module", Some(2), "Adds a module from base64 bytes with a config"),
+    ("dist", "add_module_bytes_from_vault", Some(2), "Adds a module from vault bytes"),
+    ("dist", "add_blueprint", Some(1), "Adds a blueprint"),
+    ("dist", "make_module_config", None, "Builds a module config from individual fields"),
+    ("dist", "load_module_config", Some(1), "Loads a module config from a vault file"),
+    ("dist", "default_module_config", Some(1), "Returns the default config for a module name"),
+    ("dist", "lint_module_config", Some(1), "Checks a module config for misspelled keys"),
+    ("dist", "make_blueprint", Some(2), "Builds a blueprint from a name and dependencies"),
+    ("dist", "load_blueprint", Some(1), "Loads a blueprint from a vault file"),
+    ("dist", "list_modules", Some(0), "Lists modules available on the node"),
+    ("dist", "get_module_interface", Some(1), "Returns the interface of a module"),
+    ("dist", "get_module", None, "Returns module bytes, optionally by chunks"),
+    ("dist", "list_blueprints", Some(0), "Lists blueprints available on the node"),
+    ("dist", "get_blueprint", Some(1), "Returns a blueprint by id"),
+    ("op", "noop", Some(0), "Does nothing"),
+    ("op", "array", None, "Wraps arguments into an array"),
+    ("op", "array_length", Some(1), "Returns the length of an array"),
+    ("op", "concat", None, "Concatenates arrays"),
+    ("op", "string_to_b58", Some(1), "Encodes a string to base58"),
+    ("op", "string_from_b58", Some(1), "Decodes a base58 string to a string"),
+    ("op", "bytes_from_b58", Some(1), "Decodes a base58 string to bytes"),
+    ("op", "bytes_to_b58", Some(1), "Encodes bytes to base58"),
+    ("op", "b64_encode", Some(1), "Encodes bytes or a string to base64"),
+    ("op", "b64_decode", Some(1), "Decodes a base64 string to bytes"),
+    ("op", "sha256_string", None, "Hashes a string with SHA-256"),
+    ("op", "concat_strings", None, "Concatenates strings"),
+    ("op", "identity", None, "Returns its argument unchanged"),
+    ("op", "monotonic_ms", Some(0), "A monotonic clock reading in milliseconds"),
+    ("op", "interval_ms", Some(1), "Milliseconds elapsed since a monotonic clock reading"),
+    ("op", "timestamps", Some(0), "Wall clock, monotonic clock and uptime in one call"),
+    ("op", "list_builtins", None, "Lists all builtins, optionally filtered by namespace"),
+    ("debug", "stringify", None, "Renders arguments as a JSON string"),
+    ("debug", "particle", Some(0), "Returns the parameters of the current particle"),
+    ("stat", "service_memory", Some(1), "Memory statistics of a service, per module"),
+    ("stat", "service_stat", Some(1), "Call statistics of a service"),
+    ("stat", "builtin_usage", None, "Builtin execution time accounted per init peer"),
+    ("math", "add", Some(2), "Adds two integers, checking for overflow"),
+    ("math", "sub", Some(2), "Subtracts two integers, checking for overflow"),
+    ("math", "mul", Some(2), "Multiplies two integers, checking for overflow"),
+    ("math", "fmul", Some(2), "Multiplies two floats and floors the result to an integer"),
+    ("math", "div", Some(2), "Divides two integers"),
+    ("math", "rem", Some(2), "Remainder of integer division"),
+    ("math", "pow", Some(2), "Raises an integer to a power"),
+    ("math", "log", Some(2), "Integer logarithm"),
+    ("cmp", "gt", Some(2), "Checks that the first integer is greater than the second"),
+    ("cmp", "gte", Some(2), "Checks that the first integer is greater than or equal to the second"),
+    ("cmp", "lt", Some(2), "Checks that the first integer is less than the second"),
+    ("cmp", "lte", Some(2), "Checks that the first integer is less than or equal to the second"),
+    ("cmp", "cmp", Some(2), "Compares two integers, returning -1, 0 or 1"),
+    ("array", "sum", Some(1), "Sums an array of integers"),
+    ("array", "sort", None, "Sorts an array ascending or descending"),
+    ("array", "dedup", None, "Removes all duplicates, keeping the first occurrence"),
+    ("array", "flatten", None, "Flattens nested arrays by an optional depth, one level by default"),
+    ("array", "concat", None, "Concatenates arrays"),
+    ("array", "intersect", Some(2), "Intersection of two string sets"),
+    ("array", "diff", Some(2), "Difference of two string sets"),
+    ("array", "sdiff", Some(2), "Symmetric difference of two string sets"),
+    ("array", "unique", Some(1), "Removes all duplicates from an array"),
+    ("array", "intersection", Some(2), "Intersection of arrays"),
+    ("array", "slice", Some(3), "Slices an array by start and end indexes"),
+    ("array", "length", Some(1), "Returns the length of an array"),
+    ("sig", "sign", Some(1), "Signs data with the peer's keypair"),
+    ("sig", "verify", Some(2), "Verifies a signature against the peer's public key"),
+    ("sig", "get_peer_id", Some(0), "Returns the peer id of the current scope"),
+    ("json", "obj", None, "Builds a JSON object from key value arguments"),
+    ("json", "put", Some(3), "Inserts a value into a JSON object"),
+    ("json", "put_if_absent", Some(3), "Inserts a value unless the key is already present"),
+    ("json", "put_or_replace_null", Some(3), "Inserts a value when the key is absent or null"),
+    ("json", "puts", None, "Inserts key value arguments into a JSON object"),
+    ("json", "parse", Some(1), "Parses a string as JSON"),
+    ("json", "stringify", Some(1), "Renders a JSON value as a string"),
+    ("json", "obj_pairs", Some(1), "Builds a JSON object from an array of pairs"),
+    ("json", "puts_pairs", Some(2), "Inserts an array of pairs into a JSON object"),
+    ("json", "merge_patch", Some(2), "Applies an RFC 7386 JSON merge-patch"),
+    ("json", "sort_by", Some(3), "Sorts an array of values by a JSON Pointer path"),
+    ("json", "select", None, "Projects and filters an array of JSON objects"),
+    ("json", "template", None, "Substitutes placeholders in a template string"),
+    ("json", "jsonpath", Some(2), "Extracts values by a JSONPath expression"),
+    ("json", "validate", Some(2), "Validates a value against a JSON Schema"),
+    ("vault", "put", Some(1), "Writes a payload into the particle vault"),
+    ("vault", "cat", Some(1), "Reads a file from the particle vault"),
+    ("ipfs", "cat", None, "Downloads content from IPFS by CID"),
+    ("ipfs", "add", None, "Uploads content to IPFS"),
+    ("subnet", "resolve", Some(1), "Resolves the workers of a deal subnet"),
+    ("run-console", "print", None, "Prints arguments to the node log"),
+];
+
+/// Builds the dispatch catalog queried by `op list_builtins`
+pub fn builtin_catalog() -> HashMap<(String, String), BuiltinMeta> {
+    BUILTINS
+        .iter()
+        .map(|&(namespace, name, arg_count, description)| {
+            let meta = BuiltinMeta {
+                name,
+                namespace,
+                description,
+                arg_count,
+            };
+            ((namespace.to_string(), name.to_string()), meta)
+        })
+        .collect()
+}
+
+/// Returns the catalog as a JSON array sorted by namespace and name;
+/// an optional first argument filters by namespace
+pub fn list_builtins(
+    catalog: &HashMap<(String, String), BuiltinMeta>,
+    args: Args,
+) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let namespace: Option<String> = Args::next_opt("namespace", &mut args)?;
+
+    let mut metas: Vec<_> = catalog
+        .values()
+        .filter(|meta| namespace.as_deref().map_or(true, |ns| ns == meta.namespace))
+        .collect();
+    metas.sort_by_key(|meta| (meta.namespace, meta.name));
+
+    let builtins = metas
+        .into_iter()
+        .map(|meta| {
+            json!({
+                "namespace": meta.namespace,
+                "name": meta.name,
+                "description": meta.description,
+                "arg_count": meta.arg_count,
+            })
+        })
+        .collect();
+
+    Ok(JValue::Array(builtins))
+}
+
+#[cfg(test)]
+mod tests {
+    use particle_args::Args;
+    use serde_json::json;
+
+    use super::{builtin_catalog, list_builtins};
+
+    fn args(function_args: Vec<serde_json::Value>) -> Args {
+        Args {
+            service_id: "".to_string(),
+            function_name: "".to_string(),
+            function_args,
+            tetraplets: vec![],
+        }
+    }
+
+    #[test]
+    fn catalog_lists_well_known_builtins() {
+        let catalog = builtin_catalog();
+        let listed = list_builtins(&catalog, args(vec![])).unwrap();
+        let listed = listed.as_array().unwrap();
+
+        assert_eq!(listed.len(), catalog.len());
+        for (namespace, name) in [("json", "parse"), ("op", "noop"), ("op", "list_builtins")] {
+            assert!(
+                listed
+                    .iter()
+                    .any(|b| b["namespace"] == namespace && b["name"] == name),
+                "expected ({namespace}, {name}) in the catalog"
+            );
+        }
+    }
+
+    #[test]
+    fn catalog_is_sorted_and_filters_by_namespace() {
+        let catalog = builtin_catalog();
+        let listed = list_builtins(&catalog, args(vec![json!("json")])).unwrap();
+        let listed = listed.as_array().unwrap();
+
+        assert!(!listed.is_empty());
+        assert!(listed.iter().all(|b| b["namespace"] == "json"));
+        let names: Vec<_> = listed.iter().map(|b| b["name"].as_str().unwrap()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+    }
+
+    /// Cross-checks the table against the actual `array` builtin signatures,
+    /// so the catalog can't silently drift from the dispatch it mirrors
+    #[test]
+    fn arg_counts_match_the_array_builtin_signatures() {
+        use crate::array::{
+            array_dedup, array_flatten, array_intersection, array_sort, array_unique,
+        };
+
+        let catalog = builtin_catalog();
+        let arg_count = |name: &str| catalog[&("array".to_string(), name.to_string())].arg_count;
+
+        // builtins with optional trailing arguments are listed as variadic:
+        // both the minimal and the extended call shapes are valid
+        assert_eq!(arg_count("sort"), None);
+        array_sort(args(vec![json!([2, 1])])).unwrap();
+        array_sort(args(vec![json!([2, 1]), json!(""), json!("desc")])).unwrap();
+
+        assert_eq!(arg_count("dedup"), None);
+        array_dedup(args(vec![json!([1, 1])])).unwrap();
+        array_dedup(args(vec![json!([{ "id": 1 }]), json!("id")])).unwrap();
+
+        assert_eq!(arg_count("flatten"), None);
+        array_flatten(args(vec![json!([[1]])])).unwrap();
+        array_flatten(args(vec![json!([[1]]), json!(2)])).unwrap();
+
+        // fixed-arity builtins accept exactly the listed number of arguments
+        assert_eq!(arg_count("unique"), Some(1));
+        array_unique(args(vec![json!([1, 1])])).unwrap();
+        array_unique(args(vec![])).unwrap_err();
+
+        assert_eq!(arg_count("intersection"), Some(2));
+        array_intersection(args(vec![json!([1]), json!([1])])).unwrap();
+        array_intersection(args(vec![json!([1])])).unwrap_err();
+    }
+}
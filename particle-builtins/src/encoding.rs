@@ -0,0 +1,163 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD, URL_SAFE};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use particle_args::{Args, JError};
+use serde_json::json;
+use serde_json::Value as JValue;
+
+/// Decoding accepts both padded and non-padded input, since scripts that
+/// concatenate or truncate base64 chunks often lose the padding
+const STANDARD_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+enum Alphabet {
+    Standard,
+    UrlSafe,
+}
+
+fn alphabet_variant(variant: Option<String>) -> Result<Alphabet, JError> {
+    match variant.as_deref() {
+        None | Some("standard") => Ok(Alphabet::Standard),
+        Some("url_safe") => Ok(Alphabet::UrlSafe),
+        Some(other) => Err(JError::new(format!(
+            "unknown base64 variant '{other}'; expected 'standard' or 'url_safe'"
+        ))),
+    }
+}
+
+/// Encodes bytes to a base64 string. `data` is either a byte array or a hex
+/// string (an optional `0x` prefix is ignored); the optional `variant`
+/// argument selects the alphabet: "standard" (default) or "url_safe"
+pub fn b64_encode(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let data: JValue = Args::next("data", &mut args)?;
+    let variant: Option<String> = Args::next_opt("variant", &mut args)?;
+
+    let bytes: Vec<u8> = match data {
+        JValue::String(hex) => hex::decode(hex.trim_start_matches("0x"))
+            .map_err(|err| JError::new(format!("error parsing 'data' as a hex string: {err}")))?,
+        array @ JValue::Array(_) => serde_json::from_value(array)
+            .map_err(|err| JError::new(format!("error parsing 'data' as a byte array: {err}")))?,
+        other => {
+            return Err(JError::new(format!(
+                "expected 'data' to be a byte array or a hex string, got {other}"
+            )))
+        }
+    };
+
+    let encoded = match alphabet_variant(variant)? {
+        Alphabet::Standard => STANDARD.encode(bytes),
+        Alphabet::UrlSafe => URL_SAFE.encode(bytes),
+    };
+
+    Ok(json!(encoded))
+}
+
+/// Decodes a base64 string (padded or not) to a hex string; the optional
+/// `variant` argument selects the alphabet: "standard" (default) or "url_safe"
+pub fn b64_decode(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let data: String = Args::next("data", &mut args)?;
+    let variant: Option<String> = Args::next_opt("variant", &mut args)?;
+
+    let engine = match alphabet_variant(variant)? {
+        Alphabet::Standard => &STANDARD_LENIENT,
+        Alphabet::UrlSafe => &URL_SAFE_LENIENT,
+    };
+    let bytes = engine
+        .decode(data)
+        .map_err(|err| JError::new(format!("error decoding 'data' from base64: {err}")))?;
+
+    Ok(json!(hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use particle_args::Args;
+    use serde_json::{json, Value as JValue};
+
+    use crate::encoding::{b64_decode, b64_encode};
+
+    fn args(function_args: Vec<JValue>) -> Args {
+        Args {
+            service_id: "".to_string(),
+            function_name: "".to_string(),
+            function_args,
+            tetraplets: vec![],
+        }
+    }
+
+    #[test]
+    fn b64_roundtrip_byte_array_to_hex() {
+        let encoded = b64_encode(args(vec![json!([0, 1, 254, 255])])).unwrap();
+        assert_eq!(encoded, json!("AAH+/w=="));
+
+        let decoded = b64_decode(args(vec![encoded])).unwrap();
+        assert_eq!(decoded, json!("0001feff"));
+    }
+
+    #[test]
+    fn b64_encode_accepts_hex_with_and_without_prefix() {
+        let from_prefixed = b64_encode(args(vec![json!("0xdeadbeef")])).unwrap();
+        let from_bare = b64_encode(args(vec![json!("deadbeef")])).unwrap();
+        assert_eq!(from_prefixed, from_bare);
+        assert_eq!(from_bare, json!("3q2+7w=="));
+    }
+
+    #[test]
+    fn b64_url_safe_variant_roundtrip() {
+        let encoded = b64_encode(args(vec![json!([251, 255]), json!("url_safe")])).unwrap();
+        assert_eq!(encoded, json!("-_8="));
+
+        let decoded = b64_decode(args(vec![encoded, json!("url_safe")])).unwrap();
+        assert_eq!(decoded, json!("fbff"));
+
+        let unknown = b64_encode(args(vec![json!([1]), json!("mime")]));
+        assert!(unknown.is_err(), "unknown variants must be rejected");
+    }
+
+    #[test]
+    fn b64_decode_accepts_non_padded_input() {
+        let padded = b64_decode(args(vec![json!("AAH+/w==")])).unwrap();
+        let non_padded = b64_decode(args(vec![json!("AAH+/w")])).unwrap();
+        assert_eq!(padded, non_padded);
+    }
+
+    #[test]
+    fn b64_empty_input_roundtrip() {
+        let encoded = b64_encode(args(vec![json!([])])).unwrap();
+        assert_eq!(encoded, json!(""));
+
+        let decoded = b64_decode(args(vec![json!("")])).unwrap();
+        assert_eq!(decoded, json!(""));
+    }
+
+    #[test]
+    fn b64_encode_rejects_out_of_range_bytes() {
+        let result = b64_encode(args(vec![json!([0, 256])]));
+        assert!(result.is_err(), "byte values must fit into u8");
+    }
+}
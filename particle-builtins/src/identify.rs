@@ -14,6 +14,8 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+
 use libp2p::core::Multiaddr;
 use serde::Serialize;
 
@@ -24,4 +26,7 @@ pub struct NodeInfo {
     pub air_version: &'static str,
     pub spell_version: String,
     pub allowed_binaries: Vec<String>,
+    /// Capability name -> version, as advertised at node startup; the full
+    /// up-to-date set is available via the `peer.capabilities` builtin
+    pub capabilities: HashMap<String, String>,
 }
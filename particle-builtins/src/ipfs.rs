@@ -0,0 +1,233 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+use serde_json::{json, Value as JValue};
+
+use particle_args::{Args, JError};
+
+/// Where the `ipfs` builtins send their HTTP requests. Both endpoints are
+/// optional; a builtin whose endpoint is not configured refuses the call
+/// with a descriptive error
+#[derive(Debug, Clone)]
+pub struct IpfsConfig {
+    /// Base URL of an IPFS gateway content is fetched from,
+    /// e.g. `http://127.0.0.1:8080`
+    pub gateway: Option<String>,
+    /// Base URL of an IPFS HTTP API content is added through,
+    /// e.g. `http://127.0.0.1:5001`
+    pub api: Option<String>,
+    /// Timeout of a single HTTP request to either endpoint
+    pub request_timeout: Duration,
+}
+
+impl Default for IpfsConfig {
+    fn default() -> Self {
+        Self {
+            gateway: None,
+            api: None,
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Serves the `("ipfs", "cat")` and `("ipfs", "add")` builtins so scripts
+/// can reach content-addressed data without deploying a custom service
+#[derive(Debug, Clone)]
+pub struct IpfsClient {
+    gateway: Option<String>,
+    api: Option<String>,
+    client: reqwest::Client,
+}
+
+impl IpfsClient {
+    pub fn new(config: IpfsConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .build()
+            .expect("Could not build an HTTP client for the ipfs builtins");
+        Self {
+            gateway: config.gateway.map(|url| trim_base_url(&url)),
+            api: config.api.map(|url| trim_base_url(&url)),
+            client,
+        }
+    }
+
+    /// Fetches content by CID from the configured gateway and returns it
+    /// base64-encoded
+    pub async fn cat(&self, args: Args) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let cid: String = Args::next("cid", &mut args)?;
+
+        let gateway = self.gateway.as_ref().ok_or_else(|| {
+            JError::new("ipfs.cat is disabled: no IPFS gateway is configured on this node")
+        })?;
+
+        let url = format!("{gateway}/ipfs/{cid}");
+        let response = self.client.get(&url).send().await.map_err(|err| {
+            JError::new(format!("error fetching '{cid}' from the IPFS gateway: {err}"))
+        })?;
+        if !response.status().is_success() {
+            return Err(JError::new(format!(
+                "IPFS gateway responded to cat of '{cid}' with {}",
+                response.status()
+            )));
+        }
+        let content = response.bytes().await.map_err(|err| {
+            JError::new(format!("error reading '{cid}' from the IPFS gateway: {err}"))
+        })?;
+
+        Ok(json!(base64.encode(content)))
+    }
+
+    /// Adds base64-encoded bytes through the configured IPFS API and returns
+    /// the CID of the stored content
+    pub async fn add(&self, args: Args) -> Result<JValue, JError> {
+        let mut args = args.function_args.into_iter();
+        let content: String = Args::next("content", &mut args)?;
+        let content = base64
+            .decode(&content)
+            .map_err(|err| JError::new(format!("error decoding content from base64: {err}")))?;
+
+        let api = self.api.as_ref().ok_or_else(|| {
+            JError::new("ipfs.add is disabled: no IPFS API is configured on this node")
+        })?;
+
+        let url = format!("{api}/api/v0/add");
+        let form = reqwest::multipart::Form::new()
+            .part("file", reqwest::multipart::Part::bytes(content));
+        let response = self
+            .client
+            .post(&url)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|err| JError::new(format!("error adding content to IPFS: {err}")))?;
+        if !response.status().is_success() {
+            return Err(JError::new(format!(
+                "IPFS API responded to add with {}",
+                response.status()
+            )));
+        }
+        let reply: JValue = response.json().await.map_err(|err| {
+            JError::new(format!("error reading the IPFS API response to add: {err}"))
+        })?;
+        let cid = reply
+            .get("Hash")
+            .and_then(JValue::as_str)
+            .ok_or_else(|| {
+                JError::new(format!("IPFS API responded to add without a Hash field: {reply}"))
+            })?;
+
+        Ok(json!(cid))
+    }
+}
+
+fn trim_base_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use base64::{engine::general_purpose::STANDARD as base64, Engine};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use particle_args::Args;
+
+    use crate::ipfs::{IpfsClient, IpfsConfig};
+
+    const CID: &str = "QmTgsuGW4h6cPAVeEligf7WHNWFzQGGqkmjIw5deF5gdj4";
+
+    fn args(function_args: Vec<serde_json::Value>) -> Args {
+        Args {
+            service_id: "ipfs".to_string(),
+            function_name: "".to_string(),
+            function_args,
+            tetraplets: vec![],
+        }
+    }
+
+    fn client(server_uri: String) -> IpfsClient {
+        IpfsClient::new(IpfsConfig {
+            gateway: Some(server_uri.clone()),
+            api: Some(server_uri),
+            request_timeout: Duration::from_secs(1),
+        })
+    }
+
+    #[tokio::test]
+    async fn ipfs_add_cat_roundtrip() {
+        let content = b"content-addressed data".to_vec();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v0/add"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "Hash": CID })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/ipfs/{CID}")))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.clone()))
+            .mount(&server)
+            .await;
+
+        let client = client(server.uri());
+        let cid = client
+            .add(args(vec![json!(base64.encode(&content))]))
+            .await
+            .unwrap();
+        assert_eq!(cid, json!(CID));
+
+        let fetched = client
+            .cat(args(vec![cid.clone()]))
+            .await
+            .unwrap();
+        assert_eq!(fetched, json!(base64.encode(&content)));
+    }
+
+    #[tokio::test]
+    async fn ipfs_cat_reports_gateway_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/ipfs/{CID}")))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let client = client(server.uri());
+        let result = client.cat(args(vec![json!(CID)])).await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("404"), "expected a 404 in the error, got {err}");
+    }
+
+    #[tokio::test]
+    async fn ipfs_builtins_refuse_without_endpoints() {
+        let client = IpfsClient::new(IpfsConfig::default());
+
+        let result = client.cat(args(vec![json!(CID)])).await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("no IPFS gateway is configured"), "got {err}");
+
+        let result = client.add(args(vec![json!(base64.encode(b"data"))])).await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("no IPFS API is configured"), "got {err}");
+    }
+}
@@ -14,8 +14,10 @@
  * limitations under the License.
  */
 
+use std::cmp::Ordering;
+
 use eyre::{eyre, Context};
-use particle_args::{Args, JError};
+use particle_args::{error_codes, Args, JError};
 use serde_json::Value as JValue;
 
 fn obj_from_iter(
@@ -25,15 +27,27 @@ fn obj_from_iter(
     loop {
         match (args.next(), args.next()) {
             (Some(JValue::String(name)), Some(value)) => { object.insert(name, value); },
-            (Some(key), None) => return Err(JError::new(eyre!(
-                "Expected odd number of arguments, got even. No value for key '{}'",
-                key
-            ).to_string())),
-            (Some(key), Some(value)) => return Err(JError::new(eyre!(
-                "All keys must be of type string. Key of the following pair is of invalid type: ({}, {})",
-                key,
-                value
-            ).to_string())),
+            (Some(key), None) => {
+                return Err(JError::coded(
+                    error_codes::JSON_INVALID_ARGS,
+                    eyre!(
+                        "Expected odd number of arguments, got even. No value for key '{}'",
+                        key
+                    )
+                    .to_string(),
+                ))
+            }
+            (Some(key), Some(value)) => {
+                return Err(JError::coded(
+                    error_codes::JSON_INVALID_ARGS,
+                    eyre!(
+                        "All keys must be of type string. Key of the following pair is of invalid type: ({}, {})",
+                        key,
+                        value
+                    )
+                    .to_string(),
+                ))
+            }
             (None, _) => break,
         }
     }
@@ -77,6 +91,34 @@ pub fn put(args: Args) -> Result<JValue, JError> {
     Ok(JValue::Object(object))
 }
 
+/// Inserts a value into a JSON object only if the key is not already present.
+/// A key mapping to `null` counts as present, so the `null` is kept
+pub fn put_if_absent(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let mut object: serde_json::Map<String, JValue> = Args::next("object", &mut args)?;
+    let key: String = Args::next("key", &mut args)?;
+    let value = Args::next("value", &mut args)?;
+
+    object.entry(key).or_insert(value);
+
+    Ok(JValue::Object(object))
+}
+
+/// Inserts a value into a JSON object if the key is absent or maps to `null`
+pub fn put_or_replace_null(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let mut object: serde_json::Map<String, JValue> = Args::next("object", &mut args)?;
+    let key: String = Args::next("key", &mut args)?;
+    let value = Args::next("value", &mut args)?;
+
+    let slot = object.entry(key).or_insert(JValue::Null);
+    if slot.is_null() {
+        *slot = value;
+    }
+
+    Ok(JValue::Object(object))
+}
+
 /// Inserts list of key value pairs into an object.
 pub fn puts(args: Args) -> Result<JValue, JError> {
     let mut args = args.function_args.into_iter();
@@ -99,10 +141,291 @@ pub fn puts_from_pairs(
         });
         Ok(JValue::Object(map))
     } else {
-        Err(JError::new(format!("expected json object, got {object}")))
+        Err(JError::coded(
+            error_codes::JSON_INVALID_ARGS,
+            format!("expected json object, got {object}"),
+        ))
+    }
+}
+
+/// Applies an RFC 7386 JSON merge-patch to a target value.
+///
+/// Unlike a plain deep merge, a `null` in the patch is not a value to store:
+/// it deletes the corresponding key from the target. A non-object patch
+/// replaces the target wholesale, as the RFC prescribes.
+pub fn merge_patch(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let target = Args::next("target", &mut args)?;
+    let patch = Args::next("patch", &mut args)?;
+
+    Ok(apply_merge_patch(target, patch))
+}
+
+fn apply_merge_patch(target: JValue, patch: JValue) -> JValue {
+    match patch {
+        JValue::Object(patch) => {
+            let mut target = match target {
+                JValue::Object(map) => map,
+                // a non-object target is replaced by the patched-up object
+                _ => serde_json::Map::new(),
+            };
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else {
+                    let merged =
+                        apply_merge_patch(target.remove(&key).unwrap_or(JValue::Null), value);
+                    target.insert(key, merged);
+                }
+            }
+            JValue::Object(target)
+        }
+        patch => patch,
+    }
+}
+
+/// Sorts an array by the value at a JSON Pointer path (RFC 6901), e.g. `/a/b`;
+/// an empty path sorts by the elements themselves.
+///
+/// The sort is stable. Elements without a value at the path go last regardless
+/// of the order. Numbers and strings compare naturally; across types the total
+/// order is null < booleans < numbers < strings < arrays < objects, with
+/// arrays and objects compared by their JSON representation.
+pub fn sort_by(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let mut array: Vec<JValue> = Args::next("array", &mut args)?;
+    let key_path: String = Args::next("key_path", &mut args)?;
+    let order: String = Args::next("order", &mut args)?;
+    let descending = match order.as_str() {
+        "asc" => false,
+        "desc" => true,
+        other => {
+            return Err(JError::coded(
+                error_codes::JSON_INVALID_ARGS,
+                format!("expected order 'asc' or 'desc', got '{other}'"),
+            ))
+        }
+    };
+
+    array.sort_by(|a, b| compare_keys(a.pointer(&key_path), b.pointer(&key_path), descending));
+
+    Ok(JValue::Array(array))
+}
+
+fn compare_keys(a: Option<&JValue>, b: Option<&JValue>, descending: bool) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        // missing keys go last regardless of the requested order
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            let order = total_order(a, b);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        }
+    }
+}
+
+/// A total order over JSON values: null < booleans < numbers < strings <
+/// arrays < objects; same-type scalars compare naturally, composites by
+/// their JSON representation
+pub(crate) fn total_order(a: &JValue, b: &JValue) -> Ordering {
+    match (a, b) {
+        (JValue::Bool(a), JValue::Bool(b)) => a.cmp(b),
+        (JValue::Number(a), JValue::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (JValue::String(a), JValue::String(b)) => a.cmp(b),
+        _ => type_rank(a)
+            .cmp(&type_rank(b))
+            // same-rank composites (and nulls) compare by their JSON representation
+            .then_with(|| a.to_string().cmp(&b.to_string())),
+    }
+}
+
+fn type_rank(value: &JValue) -> u8 {
+    match value {
+        JValue::Null => 0,
+        JValue::Bool(_) => 1,
+        JValue::Number(_) => 2,
+        JValue::String(_) => 3,
+        JValue::Array(_) => 4,
+        JValue::Object(_) => 5,
     }
 }
 
+/// SQL-style `SELECT fields FROM array WHERE where_key == where_value` over
+/// an array of objects.
+///
+/// Every matching element is projected to only the requested `fields`, with
+/// `null` standing in for fields the element does not have. Without
+/// `where_key` and `where_value` all elements are kept
+pub fn select(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let array: Vec<JValue> = Args::next("array", &mut args)?;
+    let fields: Vec<String> = Args::next("fields", &mut args)?;
+    let where_key: Option<String> = Args::next_opt("where_key", &mut args)?;
+    let where_value: Option<JValue> = Args::next_opt("where_value", &mut args)?;
+
+    let mut selected = Vec::new();
+    for element in array {
+        let object = match element {
+            JValue::Object(object) => object,
+            other => {
+                return Err(JError::coded(
+                    error_codes::JSON_INVALID_ARGS,
+                    format!("expected an array of objects, got {other}"),
+                ))
+            }
+        };
+        if let Some(key) = &where_key {
+            // an absent field matches a `null` (or omitted) where_value
+            let value = object.get(key).unwrap_or(&JValue::Null);
+            if value != where_value.as_ref().unwrap_or(&JValue::Null) {
+                continue;
+            }
+        }
+        let projection = fields
+            .iter()
+            .map(|field| {
+                let value = object.get(field).cloned().unwrap_or(JValue::Null);
+                (field.clone(), value)
+            })
+            .collect();
+        selected.push(JValue::Object(projection));
+    }
+
+    Ok(JValue::Array(selected))
+}
+
+/// Substitutes `{key}` placeholders in a template string with values from a
+/// JSON object.
+///
+/// String values are inserted verbatim, everything else by its JSON
+/// representation. Literal braces are escaped by doubling: `{{` and `}}`.
+/// A placeholder without a matching key is an error unless `keep_missing`
+/// is `true`, in which case the placeholder is left as is
+pub fn template(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let template: String = Args::next("template", &mut args)?;
+    let values: serde_json::Map<String, JValue> = Args::next("values", &mut args)?;
+    let keep_missing: Option<bool> = Args::next_opt("keep_missing", &mut args)?;
+    let keep_missing = keep_missing.unwrap_or(false);
+
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut key = String::new();
+                let mut terminated = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        terminated = true;
+                        break;
+                    }
+                    key.push(c);
+                }
+                if !terminated {
+                    return Err(JError::coded(
+                        error_codes::JSON_INVALID_ARGS,
+                        format!(
+                            "unterminated placeholder '{{{key}' in template; escape a literal '{{' as '{{{{'"
+                        ),
+                    ));
+                }
+                match values.get(&key) {
+                    Some(JValue::String(value)) => result.push_str(value),
+                    Some(value) => result.push_str(&value.to_string()),
+                    None if keep_missing => {
+                        result.push('{');
+                        result.push_str(&key);
+                        result.push('}');
+                    }
+                    None => {
+                        return Err(JError::coded(
+                            error_codes::JSON_KEY_MISSING,
+                            format!("no value for placeholder '{{{key}}}' in template"),
+                        ))
+                    }
+                }
+            }
+            '}' => {
+                return Err(JError::coded(
+                    error_codes::JSON_INVALID_ARGS,
+                    "unmatched '}' in template; escape a literal '}' as '}}'",
+                ))
+            }
+            c => result.push(c),
+        }
+    }
+
+    Ok(JValue::String(result))
+}
+
+/// Extracts values from a JSON value by a JSONPath expression (RFC 9535),
+/// e.g. `$.items[*].name`, returning all matches as a JSON array.
+///
+/// The full RFC 9535 feature set is supported: child and descendant (`..`)
+/// segments; name, index, wildcard and slice selectors; and filter selectors
+/// such as `$.items[?@.price < 10]`, including the standard `length`, `count`,
+/// `match`, `search` and `value` functions. A path that matches nothing yields
+/// an empty array.
+///
+/// A more powerful complement to JSON Pointer based lookups: a pointer
+/// addresses at most one value, while a JSONPath may fan out over arrays
+pub fn jsonpath(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let value: JValue = Args::next("value", &mut args)?;
+    let path: String = Args::next("path", &mut args)?;
+
+    let path = serde_json_path::JsonPath::parse(&path)
+        .map_err(|err| {
+            JError::coded(
+                error_codes::JSON_INVALID_ARGS,
+                format!("invalid JSONPath expression '{path}': {err}"),
+            )
+        })?;
+    let matched = path.query(&value).all().into_iter().cloned().collect();
+
+    Ok(JValue::Array(matched))
+}
+
+/// Validates a JSON value against a JSON Schema (Draft 7), returning a boolean.
+///
+/// A value that doesn't conform to the schema yields `false` rather than an
+/// error, so scripts can branch on the result; only a malformed schema is an
+/// error. Remote `$ref` resolution is disabled: schemas must be self-contained
+pub fn json_validate(args: Args) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let value: JValue = Args::next("value", &mut args)?;
+    let schema: JValue = Args::next("schema", &mut args)?;
+
+    let schema = jsonschema::JSONSchema::options()
+        .with_draft(jsonschema::Draft::Draft7)
+        .compile(&schema)
+        .map_err(|err| {
+            JError::coded(
+                error_codes::JSON_INVALID_ARGS,
+                format!("invalid JSON Schema: {err}"),
+            )
+        })?;
+
+    Ok(JValue::Bool(schema.is_valid(&value)))
+}
+
 pub fn parse(json: &str) -> Result<JValue, JError> {
     serde_json::from_str(json)
         .context(format!("error parsing json {json}"))
@@ -115,7 +438,330 @@ pub fn stringify(value: JValue) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::json::parse;
+    use particle_args::{error_codes, Args};
+    use serde_json::Value as JValue;
+
+    use crate::json::{
+        apply_merge_patch, json_validate, jsonpath, parse, put_if_absent, put_or_replace_null,
+        select, sort_by, template,
+    };
+
+    fn args(function_args: Vec<JValue>) -> Args {
+        Args {
+            service_id: "".to_string(),
+            function_name: "".to_string(),
+            function_args,
+            tetraplets: vec![],
+        }
+    }
+
+    #[test]
+    fn json_sort_by_numeric_key() {
+        use serde_json::json;
+
+        let array = json!([{ "n": 3 }, { "n": 1.5 }, { "n": 2 }]);
+        let sorted = sort_by(args(vec![array.clone(), json!("/n"), json!("asc")])).unwrap();
+        assert_eq!(sorted, json!([{ "n": 1.5 }, { "n": 2 }, { "n": 3 }]));
+
+        let sorted = sort_by(args(vec![array, json!("/n"), json!("desc")])).unwrap();
+        assert_eq!(sorted, json!([{ "n": 3 }, { "n": 2 }, { "n": 1.5 }]));
+    }
+
+    #[test]
+    fn json_sort_by_nested_string_key() {
+        use serde_json::json;
+
+        let array = json!([
+            { "a": { "name": "carol" } },
+            { "a": { "name": "alice" } },
+            { "a": { "name": "bob" } }
+        ]);
+        let sorted = sort_by(args(vec![array, json!("/a/name"), json!("asc")])).unwrap();
+        assert_eq!(
+            sorted,
+            json!([
+                { "a": { "name": "alice" } },
+                { "a": { "name": "bob" } },
+                { "a": { "name": "carol" } }
+            ])
+        );
+    }
+
+    #[test]
+    fn json_sort_by_missing_keys_go_last() {
+        use serde_json::json;
+
+        let array = json!([{ "x": 1 }, { "n": 2 }, { "n": 1 }]);
+        let sorted = sort_by(args(vec![array.clone(), json!("/n"), json!("asc")])).unwrap();
+        assert_eq!(sorted, json!([{ "n": 1 }, { "n": 2 }, { "x": 1 }]));
+
+        // missing keys stay last in descending order too
+        let sorted = sort_by(args(vec![array, json!("/n"), json!("desc")])).unwrap();
+        assert_eq!(sorted, json!([{ "n": 2 }, { "n": 1 }, { "x": 1 }]));
+    }
+
+    #[test]
+    fn json_sort_by_rejects_unknown_order() {
+        use serde_json::json;
+
+        let result = sort_by(args(vec![json!([1, 2]), json!(""), json!("sideways")]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_put_if_absent_inserts_absent_key() {
+        use serde_json::json;
+
+        let object = json!({ "a": 1 });
+        let args = args(vec![object, json!("b"), json!(2)]);
+        assert_eq!(put_if_absent(args).unwrap(), json!({ "a": 1, "b": 2 }));
+    }
+
+    #[test]
+    fn json_put_if_absent_keeps_present_key() {
+        use serde_json::json;
+
+        let object = json!({ "a": 1 });
+        let args = args(vec![object, json!("a"), json!(2)]);
+        assert_eq!(put_if_absent(args).unwrap(), json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn json_put_if_absent_keeps_null() {
+        use serde_json::json;
+
+        let object = json!({ "a": null });
+        let args = args(vec![object, json!("a"), json!(2)]);
+        assert_eq!(put_if_absent(args).unwrap(), json!({ "a": null }));
+    }
+
+    #[test]
+    fn json_put_or_replace_null_inserts_absent_key() {
+        use serde_json::json;
+
+        let object = json!({ "a": 1 });
+        let args = args(vec![object, json!("b"), json!(2)]);
+        assert_eq!(
+            put_or_replace_null(args).unwrap(),
+            json!({ "a": 1, "b": 2 })
+        );
+    }
+
+    #[test]
+    fn json_put_or_replace_null_keeps_present_value() {
+        use serde_json::json;
+
+        let object = json!({ "a": 1 });
+        let args = args(vec![object, json!("a"), json!(2)]);
+        assert_eq!(put_or_replace_null(args).unwrap(), json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn json_put_or_replace_null_replaces_null() {
+        use serde_json::json;
+
+        let object = json!({ "a": null });
+        let args = args(vec![object, json!("a"), json!(2)]);
+        assert_eq!(put_or_replace_null(args).unwrap(), json!({ "a": 2 }));
+    }
+
+    #[test]
+    fn json_select_projects_and_filters() {
+        use serde_json::json;
+
+        let array = json!([
+            { "name": "alice", "age": 30, "city": "london", "active": true },
+            { "name": "bob", "age": 25, "city": "paris", "active": false },
+            { "name": "carol", "age": 35, "city": "berlin", "active": true },
+            { "name": "dave", "age": 28, "active": true },
+            { "name": "eve", "age": 22, "city": "oslo", "active": false }
+        ]);
+        let selected = select(args(vec![
+            array,
+            json!(["name", "city"]),
+            json!("active"),
+            json!(true),
+        ]))
+        .unwrap();
+        assert_eq!(
+            selected,
+            json!([
+                { "name": "alice", "city": "london" },
+                { "name": "carol", "city": "berlin" },
+                // a field missing from the element projects to null
+                { "name": "dave", "city": null }
+            ])
+        );
+    }
+
+    #[test]
+    fn json_select_without_filter_keeps_all_elements() {
+        use serde_json::json;
+
+        let array = json!([{ "n": 1, "x": "a" }, { "n": 2, "x": "b" }]);
+        let selected = select(args(vec![array, json!(["n"])])).unwrap();
+        assert_eq!(selected, json!([{ "n": 1 }, { "n": 2 }]));
+    }
+
+    #[test]
+    fn json_select_rejects_non_object_elements() {
+        use serde_json::json;
+
+        let result = select(args(vec![json!([{ "n": 1 }, 42]), json!(["n"])]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_template_substitutes_values() {
+        use serde_json::json;
+
+        let result = template(args(vec![
+            json!("{name} is {age} years old, active: {active}"),
+            json!({ "name": "alice", "age": 30, "active": true }),
+        ]))
+        .unwrap();
+        assert_eq!(result, json!("alice is 30 years old, active: true"));
+    }
+
+    #[test]
+    fn json_template_rejects_missing_key() {
+        use serde_json::json;
+
+        let result = template(args(vec![json!("hello {name}"), json!({ "age": 30 })]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_errors_carry_machine_readable_codes() {
+        use serde_json::json;
+
+        // a missing placeholder value is a distinct, matchable condition
+        let err = template(args(vec![json!("hello {name}"), json!({ "age": 30 })])).unwrap_err();
+        assert_eq!(err.code(), Some(error_codes::JSON_KEY_MISSING));
+
+        // malformed input is reported under the generic invalid-args code
+        let err = sort_by(args(vec![json!([1, 2]), json!(""), json!("sideways")])).unwrap_err();
+        assert_eq!(err.code(), Some(error_codes::JSON_INVALID_ARGS));
+    }
+
+    #[test]
+    fn json_template_keeps_missing_placeholder_when_asked() {
+        use serde_json::json;
+
+        let result = template(args(vec![
+            json!("hello {name}, you are {age}"),
+            json!({ "age": 30 }),
+            json!(true),
+        ]))
+        .unwrap();
+        assert_eq!(result, json!("hello {name}, you are 30"));
+    }
+
+    #[test]
+    fn json_template_escaped_braces() {
+        use serde_json::json;
+
+        let result = template(args(vec![
+            json!("{{\"n\": {n}}}"),
+            json!({ "n": 1 }),
+        ]))
+        .unwrap();
+        assert_eq!(result, json!("{\"n\": 1}"));
+    }
+
+    #[test]
+    fn json_template_rejects_unterminated_placeholder() {
+        use serde_json::json;
+
+        let result = template(args(vec![json!("hello {name"), json!({ "name": "x" })]));
+        assert!(result.is_err());
+
+        let result = template(args(vec![json!("hello name}"), json!({ "name": "x" })]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_jsonpath_wildcard_array_extraction() {
+        use serde_json::json;
+
+        let value = json!({ "items": [ { "name": "a" }, { "name": "b" }, { "name": "c" } ] });
+        let matched = jsonpath(args(vec![value, json!("$.items[*].name")])).unwrap();
+        assert_eq!(matched, json!(["a", "b", "c"]));
+    }
+
+    #[test]
+    fn json_jsonpath_filtering() {
+        use serde_json::json;
+
+        let value = json!({ "items": [
+            { "name": "a", "price": 5 },
+            { "name": "b", "price": 15 },
+            { "name": "c", "price": 8 }
+        ] });
+        let matched = jsonpath(args(vec![value, json!("$.items[?@.price < 10].name")])).unwrap();
+        assert_eq!(matched, json!(["a", "c"]));
+    }
+
+    #[test]
+    fn json_jsonpath_no_match_returns_empty_array() {
+        use serde_json::json;
+
+        let value = json!({ "items": [] });
+        let matched = jsonpath(args(vec![value, json!("$.items[*].name")])).unwrap();
+        assert_eq!(matched, json!([]));
+    }
+
+    #[test]
+    fn json_jsonpath_rejects_invalid_expression() {
+        use serde_json::json;
+
+        let result = jsonpath(args(vec![json!({}), json!("items[")]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_validate_required_fields_present() {
+        use serde_json::json;
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "number" }
+            },
+            "required": ["name", "age"]
+        });
+        let value = json!({ "name": "alice", "age": 30 });
+        let result = json_validate(args(vec![value, schema])).unwrap();
+        assert_eq!(result, json!(true));
+    }
+
+    #[test]
+    fn json_validate_missing_required_field() {
+        use serde_json::json;
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "number" }
+            },
+            "required": ["name", "age"]
+        });
+        let value = json!({ "name": "alice" });
+        // a non-conforming value is `false`, not an error
+        let result = json_validate(args(vec![value, schema])).unwrap();
+        assert_eq!(result, json!(false));
+    }
+
+    #[test]
+    fn json_validate_rejects_invalid_schema() {
+        use serde_json::json;
+
+        let schema = json!({ "type": "not-a-type" });
+        let result = json_validate(args(vec![json!({}), schema]));
+        assert!(result.is_err());
+    }
 
     #[test]
     fn json_parse_string() {
@@ -125,4 +771,34 @@ mod tests {
         let parsed = parse(&str.to_string());
         assert_eq!(parsed.ok(), Some(str));
     }
+
+    #[test]
+    fn json_merge_patch_adds_keys() {
+        use serde_json::json;
+
+        let target = json!({ "a": 1 });
+        let patch = json!({ "b": 2 });
+        let patched = apply_merge_patch(target, patch);
+        assert_eq!(patched, json!({ "a": 1, "b": 2 }));
+    }
+
+    #[test]
+    fn json_merge_patch_merges_nested_objects() {
+        use serde_json::json;
+
+        let target = json!({ "a": { "b": 1, "c": 2 }, "d": 3 });
+        let patch = json!({ "a": { "b": 42 } });
+        let patched = apply_merge_patch(target, patch);
+        assert_eq!(patched, json!({ "a": { "b": 42, "c": 2 }, "d": 3 }));
+    }
+
+    #[test]
+    fn json_merge_patch_null_deletes_keys() {
+        use serde_json::json;
+
+        let target = json!({ "a": { "b": 1, "c": 2 }, "d": 3 });
+        let patch = json!({ "a": { "b": null }, "d": null });
+        let patched = apply_merge_patch(target, patch);
+        assert_eq!(patched, json!({ "a": { "c": 2 } }));
+    }
 }
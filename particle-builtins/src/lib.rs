@@ -30,15 +30,24 @@
 )]
 
 pub use builtins::{Builtins, CustomService};
+pub use capabilities::CapabilityRegistry;
 pub use identify::NodeInfo;
+pub use ipfs::IpfsConfig;
 pub use outcome::{ok, wrap, wrap_unit};
 pub use particle_services::ParticleAppServicesConfig;
+pub use usage::BuiltinUsageConfig;
+mod array;
 mod builtins;
+mod capabilities;
+mod catalog;
 mod debug;
+mod encoding;
 mod error;
 mod func;
 mod identify;
+mod ipfs;
 mod json;
 mod math;
 mod outcome;
 mod particle_function;
+mod usage;
@@ -17,8 +17,6 @@
 use std::collections::HashSet;
 use std::ops::Mul;
 
-use itertools::Itertools;
-
 use particle_args::JError;
 
 /// x + y
@@ -107,11 +105,6 @@ pub fn array_sum(xs: Vec<i64>) -> Result<i64, JError> {
         .ok_or_else(|| JError::new("i64 add overflow"))
 }
 
-/// remove duplicates, not stable
-pub fn dedup(xs: Vec<String>) -> Result<Vec<String>, JError> {
-    Ok(xs.into_iter().unique().collect())
-}
-
 /// set-intersection of two arrays, not stable, deduplicates
 pub fn intersect(xs: HashSet<String>, ys: HashSet<String>) -> Result<Vec<String>, JError> {
     Ok(xs.intersection(&ys).cloned().collect())
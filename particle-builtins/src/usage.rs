@@ -0,0 +1,247 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+use parking_lot::{Mutex, MutexGuard};
+use serde_json::{json, Value as JValue};
+
+/// Per-init-peer budgeting of builtin execution time
+#[derive(Clone, Debug)]
+pub struct BuiltinUsageConfig {
+    /// Length of a usage accounting window; usage resets when it elapses
+    pub window: Duration,
+    /// Builtin execution time budget per init peer within a window.
+    /// `None` disables throttling; usage is still accounted
+    pub budget: Option<Duration>,
+    /// How many peers are tracked individually within a window; usage of
+    /// the rest is accumulated in the `other` bucket and never throttled
+    pub max_tracked_peers: usize,
+}
+
+impl Default for BuiltinUsageConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            budget: None,
+            max_tracked_peers: 1024,
+        }
+    }
+}
+
+/// Details of a peer going over the builtin execution budget,
+/// reported back to the caller in the "budget exceeded" error
+#[derive(Clone, Debug)]
+pub struct ExceededBudget {
+    pub used: Duration,
+    pub budget: Duration,
+    pub window: Duration,
+}
+
+#[derive(Debug)]
+struct UsageWindow {
+    started: Instant,
+    peers: HashMap<PeerId, Duration>,
+    /// Usage of peers beyond `max_tracked_peers`, accumulated together
+    other: Duration,
+}
+
+/// Accounts builtin execution time by the particle's init peer over
+/// fixed time windows. Used to report the heaviest builtin consumers
+/// through `("stat" "builtin_usage")` and, when a budget is configured,
+/// to refuse further builtin calls from peers that have exhausted it.
+#[derive(Debug)]
+pub struct BuiltinUsageTracker {
+    config: BuiltinUsageConfig,
+    window: Mutex<UsageWindow>,
+}
+
+impl BuiltinUsageTracker {
+    pub fn new(config: BuiltinUsageConfig) -> Self {
+        let window = UsageWindow {
+            started: Instant::now(),
+            peers: HashMap::new(),
+            other: Duration::ZERO,
+        };
+        Self {
+            config,
+            window: Mutex::new(window),
+        }
+    }
+
+    /// Records `elapsed` of builtin execution time against `peer`
+    /// in the current window
+    pub fn observe(&self, peer: PeerId, elapsed: Duration) {
+        let mut window = self.current();
+        if window.peers.contains_key(&peer) || window.peers.len() < self.config.max_tracked_peers {
+            *window.peers.entry(peer).or_default() += elapsed;
+        } else {
+            window.other += elapsed;
+        }
+    }
+
+    /// Returns the budget violation details if `peer` has spent more than
+    /// the configured budget in the current window; `None` when no budget
+    /// is configured or the peer is within it
+    pub fn exceeded_budget(&self, peer: PeerId) -> Option<ExceededBudget> {
+        let budget = self.config.budget?;
+        let current = self.current();
+        let used = *current.peers.get(&peer)?;
+        (used > budget).then_some(ExceededBudget {
+            used,
+            budget,
+            window: self.config.window,
+        })
+    }
+
+    /// Reports the top `count` consumers of the current window, heaviest first
+    pub fn report(&self, count: usize) -> JValue {
+        let current = self.current();
+        let mut peers: Vec<_> = current.peers.iter().collect();
+        peers.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let peers: Vec<_> = peers
+            .into_iter()
+            .take(count)
+            .map(|(peer_id, used)| {
+                json!({
+                    "peer_id": peer_id.to_base58(),
+                    "used_ms": used.as_millis() as u64,
+                })
+            })
+            .collect();
+
+        json!({
+            "window_ms": self.config.window.as_millis() as u64,
+            "budget_ms": self.config.budget.map(|b| b.as_millis() as u64),
+            "window_elapsed_ms": current.started.elapsed().as_millis() as u64,
+            "peers": peers,
+            "other_ms": current.other.as_millis() as u64,
+        })
+    }
+
+    /// Locks the window, rolling it over first if it has elapsed
+    fn current(&self) -> MutexGuard<'_, UsageWindow> {
+        let mut window = self.window.lock();
+        if window.started.elapsed() >= self.config.window {
+            window.started = Instant::now();
+            window.peers.clear();
+            window.other = Duration::ZERO;
+        }
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(budget: Option<Duration>) -> BuiltinUsageTracker {
+        BuiltinUsageTracker::new(BuiltinUsageConfig {
+            budget,
+            ..<_>::default()
+        })
+    }
+
+    #[test]
+    fn report_orders_peers_by_usage() {
+        let tracker = tracker(None);
+        let heavy = PeerId::random();
+        let light = PeerId::random();
+
+        for _ in 0..5 {
+            tracker.observe(heavy, Duration::from_millis(10));
+        }
+        tracker.observe(light, Duration::from_millis(1));
+
+        let report = tracker.report(10);
+        let peers = report["peers"].as_array().unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0]["peer_id"], heavy.to_base58());
+        assert_eq!(peers[0]["used_ms"], 50);
+        assert_eq!(peers[1]["peer_id"], light.to_base58());
+        assert_eq!(peers[1]["used_ms"], 1);
+    }
+
+    #[test]
+    fn only_the_heavy_peer_is_throttled_once_the_budget_is_crossed() {
+        let tracker = tracker(Some(Duration::from_millis(10)));
+        let heavy = PeerId::random();
+        let light = PeerId::random();
+
+        tracker.observe(light, Duration::from_millis(5));
+        for _ in 0..2 {
+            tracker.observe(heavy, Duration::from_millis(5));
+            assert!(
+                tracker.exceeded_budget(heavy).is_none(),
+                "still within budget"
+            );
+        }
+        tracker.observe(heavy, Duration::from_millis(5));
+
+        let exceeded = tracker
+            .exceeded_budget(heavy)
+            .expect("heavy peer must be throttled");
+        assert_eq!(exceeded.used, Duration::from_millis(15));
+        assert_eq!(exceeded.budget, Duration::from_millis(10));
+        assert!(
+            tracker.exceeded_budget(light).is_none(),
+            "light peer must not be throttled"
+        );
+    }
+
+    #[test]
+    fn usage_resets_when_the_window_elapses() {
+        let tracker = BuiltinUsageTracker::new(BuiltinUsageConfig {
+            window: Duration::from_millis(10),
+            budget: Some(Duration::from_millis(1)),
+            ..<_>::default()
+        });
+        let peer = PeerId::random();
+
+        tracker.observe(peer, Duration::from_millis(5));
+        assert!(tracker.exceeded_budget(peer).is_some());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(
+            tracker.exceeded_budget(peer).is_none(),
+            "budget must reset with the window"
+        );
+        let report = tracker.report(10);
+        assert!(report["peers"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn untracked_peers_fold_into_the_other_bucket() {
+        let tracker = BuiltinUsageTracker::new(BuiltinUsageConfig {
+            budget: Some(Duration::from_millis(1)),
+            max_tracked_peers: 1,
+            ..<_>::default()
+        });
+        let tracked = PeerId::random();
+        let untracked = PeerId::random();
+
+        tracker.observe(tracked, Duration::from_millis(2));
+        tracker.observe(untracked, Duration::from_millis(7));
+
+        // untracked usage is accounted, but can't be attributed, so no throttling
+        assert!(tracker.exceeded_budget(untracked).is_none());
+        let report = tracker.report(10);
+        assert_eq!(report["peers"].as_array().unwrap().len(), 1);
+        assert_eq!(report["other_ms"], 7);
+    }
+}
@@ -19,7 +19,6 @@ use std::ops::{ControlFlow, FromResidual, Try};
 
 use serde_json::Value as JValue;
 
-use json_utils::err_as_value;
 use particle_args::{Args, JError};
 
 use crate::ParticleParams;
@@ -55,7 +54,9 @@ impl FunctionOutcome {
 
 impl<E: std::error::Error> From<E> for FunctionOutcome {
     fn from(err: E) -> Self {
-        FunctionOutcome::Err(JError(err_as_value(err)))
+        // goes through `From<E> for JError` so the error carries
+        // the structured code/message envelope
+        FunctionOutcome::Err(err.into())
     }
 }
 
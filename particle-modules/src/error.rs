@@ -24,7 +24,9 @@ use marine_module_info_parser::ModuleInfoError;
 use serde_json::Value as JValue;
 use thiserror::Error;
 
+use fluence_libp2p::PeerId;
 use json_utils::err_as_value;
+use particle_args::{error_codes, JError};
 use particle_execution::VaultError;
 use service_modules::Blueprint;
 
@@ -99,6 +101,12 @@ pub enum ModuleError {
         #[source]
         err: MarineError,
     },
+    #[error("Error reading metadata of {path:?}: {err}")]
+    ReadMetadata {
+        path: PathBuf,
+        #[source]
+        err: std::io::Error,
+    },
     #[error("Module wasn't found on path {path:?}: {err}")]
     ModuleNotFound {
         path: PathBuf,
@@ -157,6 +165,14 @@ pub enum ModuleError {
         module_cid: String,
         binary_name: String,
     },
+    #[error("Downloading module {module_cid} is forbidden for peer {peer_id}")]
+    ModuleDownloadForbidden { module_cid: String, peer_id: PeerId },
+    #[error("Invalid offset {offset} into module {module_cid} of size {module_size}")]
+    InvalidModuleOffset {
+        module_cid: String,
+        offset: usize,
+        module_size: usize,
+    },
     #[error(transparent)]
     Vault(#[from] VaultError),
     #[error(transparent)]
@@ -165,6 +181,35 @@ pub enum ModuleError {
     WrongModuleHash(#[from] eyre::ErrReport),
 }
 
+impl ModuleError {
+    /// The stable machine-readable code of this error;
+    /// see [`particle_args::error_codes`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModuleError::ForbiddenEffector { .. } => error_codes::MODULES_FORBIDDEN_EFFECTOR,
+            ModuleError::InvalidEffectorMountedBinary { .. } => {
+                error_codes::MODULES_INVALID_EFFECTOR_BINARY
+            }
+            ModuleError::ModuleDownloadForbidden { .. } => error_codes::MODULES_DOWNLOAD_FORBIDDEN,
+            ModuleError::NoSuchBlueprint { .. }
+            | ModuleError::BlueprintNotFound { .. }
+            | ModuleError::EmptyDependenciesList { .. }
+            | ModuleError::FacadeShouldBeHash { .. }
+            | ModuleError::IncorrectBlueprint { .. } => error_codes::DIST_INVALID_BLUEPRINT,
+            ModuleError::IncorrectModuleConfig { .. }
+            | ModuleError::IncorrectVaultModuleConfig { .. }
+            | ModuleError::MaxHeapSizeOverflow { .. } => error_codes::DIST_INVALID_CONFIG,
+            _ => error_codes::MODULES_ERROR,
+        }
+    }
+
+    /// Converts into the coded [`JError`] envelope. Used instead of the
+    /// blanket `From<E: Error>` conversion, which can't know the code
+    pub fn into_jerror(self) -> JError {
+        JError::coded_error(self.code(), self)
+    }
+}
+
 impl From<ModuleError> for JValue {
     fn from(err: ModuleError) -> Self {
         err_as_value(err)
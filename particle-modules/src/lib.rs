@@ -32,12 +32,16 @@ extern crate fstrings;
 
 mod error;
 mod files;
+mod lint;
 mod modules;
 
 pub use error::ModuleError;
+pub use lint::{lint_module_config, ConfigLint, LintLevel};
 pub use files::{load_blueprint, load_module_by_path, load_module_descriptor};
 pub use modules::EffectorsMode;
 pub use modules::ModuleRepository;
+pub use modules::{DiskUsage, ModuleDiskUsage};
+pub use modules::{ModuleAccess, ModuleChunk, MAX_MODULE_CHUNK_SIZE};
 
 // reexport
 pub use fluence_app_service::{
@@ -0,0 +1,364 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use serde::Serialize;
+use serde_json::Value as JValue;
+
+/// Top-level config keys the current deserializer understands
+const KNOWN_KEYS: &[&str] = &[
+    "name",
+    "file_name",
+    "load_from",
+    "logger_enabled",
+    "wasi",
+    "mounted_binaries",
+    "logging_mask",
+];
+
+/// Keys older clients still send; accepted for compatibility but ignored
+const LEGACY_KEYS: &[&str] = &["mem_pages_count", "max_heap_size"];
+
+const KNOWN_WASI_KEYS: &[&str] = &["envs", "mapped_dirs"];
+const LEGACY_WASI_KEYS: &[&str] = &["preopened_files"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// The config is wrong in a way that will break the module; blocks `add_module`
+    Error,
+    /// The config is suspicious but may work; reported, never blocks
+    Warning,
+}
+
+/// A single finding of [lint_module_config]
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigLint {
+    pub level: LintLevel,
+    pub message: String,
+}
+
+impl ConfigLint {
+    fn error(message: String) -> Self {
+        Self {
+            level: LintLevel::Error,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            level: LintLevel::Warning,
+            message,
+        }
+    }
+}
+
+/// Checks a module config (the JSON passed to `add_module`) for mistakes the
+/// deserializer accepts silently: typos in key names, ignored legacy keys,
+/// values of the wrong type, undialable mounted binary paths. Returns all
+/// findings; callers decide whether [LintLevel::Error] ones are fatal
+pub fn lint_module_config(config: &JValue) -> Vec<ConfigLint> {
+    let mut lints = Vec::new();
+
+    let Some(object) = config.as_object() else {
+        lints.push(ConfigLint::error(
+            "module config must be a JSON object".to_string(),
+        ));
+        return lints;
+    };
+
+    for (key, value) in object {
+        match key.as_str() {
+            "name" | "file_name" | "load_from" => {
+                if !value.is_string() {
+                    lints.push(ConfigLint::error(format!(
+                        "'{key}' must be a string, was {value}"
+                    )));
+                }
+            }
+            "logger_enabled" => {
+                if !value.is_boolean() {
+                    lints.push(ConfigLint::error(format!(
+                        "'logger_enabled' must be a boolean, was {value}"
+                    )));
+                }
+            }
+            "logging_mask" => {
+                if !value.is_i64() && !value.is_u64() {
+                    lints.push(ConfigLint::error(format!(
+                        "'logging_mask' must be an integer, was {value}"
+                    )));
+                }
+            }
+            "wasi" => lint_wasi(value, &mut lints),
+            "mounted_binaries" => lint_mounted_binaries(value, &mut lints),
+            key if LEGACY_KEYS.contains(&key) => {
+                lints.push(ConfigLint::warning(format!(
+                    "'{key}' is not used anymore and is ignored"
+                )));
+            }
+            key => lints.push(unknown_key(key, "config", KNOWN_KEYS, LEGACY_KEYS)),
+        }
+    }
+
+    match object.get("name") {
+        None => lints.push(ConfigLint::error(
+            "missing required key 'name'".to_string(),
+        )),
+        Some(JValue::String(name)) if name.is_empty() => {
+            lints.push(ConfigLint::error("'name' must not be empty".to_string()))
+        }
+        _ => {}
+    }
+
+    lints
+}
+
+fn lint_wasi(wasi: &JValue, lints: &mut Vec<ConfigLint>) {
+    let Some(wasi) = wasi.as_object() else {
+        lints.push(ConfigLint::error(format!(
+            "'wasi' must be an object, was {wasi}"
+        )));
+        return;
+    };
+
+    for (key, value) in wasi {
+        match key.as_str() {
+            "envs" => {
+                let Some(envs) = value.as_object() else {
+                    lints.push(ConfigLint::error(format!(
+                        "'wasi.envs' must be an object, was {value}"
+                    )));
+                    continue;
+                };
+                for (name, value) in envs {
+                    if !value.is_string() {
+                        lints.push(ConfigLint::warning(format!(
+                            "env '{name}' has a non-string value {value}; it will be coerced to a string"
+                        )));
+                    }
+                }
+            }
+            "mapped_dirs" => {
+                let Some(dirs) = value.as_object() else {
+                    lints.push(ConfigLint::error(format!(
+                        "'wasi.mapped_dirs' must be an object, was {value}"
+                    )));
+                    continue;
+                };
+                for (alias, path) in dirs {
+                    let Some(path) = path.as_str() else {
+                        lints.push(ConfigLint::error(format!(
+                            "mapped dir '{alias}' must map to a string path, was {path}"
+                        )));
+                        continue;
+                    };
+                    if alias.is_empty() || path.is_empty() {
+                        lints.push(ConfigLint::error(format!(
+                            "mapped dir '{alias}' -> '{path}' must have a non-empty alias and path"
+                        )));
+                    } else if path.split('/').any(|segment| segment == "..") {
+                        lints.push(ConfigLint::warning(format!(
+                            "mapped dir '{alias}' -> '{path}' contains '..' and may escape the service directory"
+                        )));
+                    }
+                }
+            }
+            key if LEGACY_WASI_KEYS.contains(&key) => {
+                lints.push(ConfigLint::warning(format!(
+                    "'wasi.{key}' is not used anymore and is ignored; use 'wasi.mapped_dirs'"
+                )));
+            }
+            key => lints.push(unknown_key(key, "wasi", KNOWN_WASI_KEYS, LEGACY_WASI_KEYS)),
+        }
+    }
+}
+
+fn lint_mounted_binaries(binaries: &JValue, lints: &mut Vec<ConfigLint>) {
+    let Some(binaries) = binaries.as_object() else {
+        lints.push(ConfigLint::error(format!(
+            "'mounted_binaries' must be an object, was {binaries}"
+        )));
+        return;
+    };
+
+    for (name, path) in binaries {
+        let Some(path) = path.as_str() else {
+            lints.push(ConfigLint::error(format!(
+                "mounted binary '{name}' must map to a string path, was {path}"
+            )));
+            continue;
+        };
+        if !path.starts_with('/') {
+            lints.push(ConfigLint::warning(format!(
+                "mounted binary '{name}' path '{path}' is not absolute"
+            )));
+            continue;
+        }
+        // host-side checks are advisory: the config may be prepared
+        // on a different machine than the one it is deployed to
+        match std::fs::metadata(path) {
+            Err(_) => lints.push(ConfigLint::warning(format!(
+                "mounted binary '{name}' path '{path}' does not exist on this host"
+            ))),
+            Ok(metadata) => {
+                use std::os::unix::fs::PermissionsExt;
+                if metadata.permissions().mode() & 0o111 == 0 {
+                    lints.push(ConfigLint::warning(format!(
+                        "mounted binary '{name}' path '{path}' is not executable"
+                    )));
+                }
+            }
+        }
+    }
+}
+
+fn unknown_key(key: &str, section: &str, known: &[&str], legacy: &[&str]) -> ConfigLint {
+    let suggestion = known
+        .iter()
+        .chain(legacy)
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .min()
+        .filter(|(distance, _)| *distance <= 2)
+        .map(|(_, candidate)| format!("; did you mean '{candidate}'?"))
+        .unwrap_or_default();
+    ConfigLint::error(format!("unknown {section} key '{key}'{suggestion}"))
+}
+
+/// Levenshtein distance, used for did-you-mean suggestions on unknown keys
+fn edit_distance(left: &str, right: &str) -> usize {
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+    for (i, l) in left.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, r) in right.iter().enumerate() {
+            let substitute = previous[j] + usize::from(l != r);
+            let insert_or_delete = current[j].min(previous[j + 1]) + 1;
+            current.push(substitute.min(insert_or_delete));
+        }
+        previous = current;
+    }
+    previous[right.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{lint_module_config, LintLevel};
+
+    fn messages(config: &serde_json::Value) -> Vec<String> {
+        lint_module_config(config)
+            .into_iter()
+            .map(|lint| lint.message)
+            .collect()
+    }
+
+    #[test]
+    fn clean_config_produces_no_lints() {
+        let config = json!({
+            "name": "effector",
+            "logger_enabled": true,
+            "wasi": { "envs": { "KEY": "value" }, "mapped_dirs": { "data": "/data" } },
+        });
+        assert!(lint_module_config(&config).is_empty());
+    }
+
+    #[test]
+    fn typoed_key_gets_a_suggestion() {
+        let config = json!({ "name": "m", "mem_pages_cout": 100 });
+        let lints = lint_module_config(&config);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].level, LintLevel::Error);
+        assert_eq!(
+            lints[0].message,
+            "unknown config key 'mem_pages_cout'; did you mean 'mem_pages_count'?"
+        );
+    }
+
+    #[test]
+    fn legacy_keys_are_warnings() {
+        let config = json!({
+            "name": "m",
+            "mem_pages_count": 100,
+            "wasi": { "preopened_files": ["/tmp"] },
+        });
+        let lints = lint_module_config(&config);
+        assert_eq!(lints.len(), 2);
+        assert!(lints.iter().all(|lint| lint.level == LintLevel::Warning));
+    }
+
+    #[test]
+    fn missing_name_is_an_error() {
+        let lints = lint_module_config(&json!({ "logger_enabled": true }));
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].message, "missing required key 'name'");
+    }
+
+    #[test]
+    fn wrong_value_types_are_errors() {
+        let config = json!({
+            "name": "m",
+            "logger_enabled": "true",
+            "logging_mask": "all",
+            "wasi": [],
+        });
+        let lints = lint_module_config(&config);
+        assert_eq!(lints.len(), 3);
+        assert!(lints.iter().all(|lint| lint.level == LintLevel::Error));
+    }
+
+    #[test]
+    fn non_string_env_is_a_coercion_warning() {
+        let config = json!({ "name": "m", "wasi": { "envs": { "PORT": 8080 } } });
+        let lints = lint_module_config(&config);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].level, LintLevel::Warning);
+        assert!(lints[0].message.contains("coerced"), "{}", lints[0].message);
+    }
+
+    #[test]
+    fn mapped_dir_escaping_the_service_dir_is_a_warning() {
+        let config = json!({
+            "name": "m",
+            "wasi": { "mapped_dirs": { "up": "../outside", "empty": "" } },
+        });
+        let lints = lint_module_config(&config);
+        assert_eq!(lints.len(), 2);
+        assert!(messages(&config).iter().any(|m| m.contains("'..'")));
+        assert!(messages(&config).iter().any(|m| m.contains("non-empty")));
+    }
+
+    #[test]
+    fn mounted_binary_paths_are_checked_on_the_host() {
+        let config = json!({
+            "name": "m",
+            "mounted_binaries": {
+                "ok": "/bin/sh",
+                "missing": "/definitely/not/a/binary",
+                "relative": "bin/ls",
+            },
+        });
+        let lints = lint_module_config(&config);
+        assert_eq!(lints.len(), 2);
+        assert!(lints.iter().all(|lint| lint.level == LintLevel::Warning));
+        let messages = messages(&config);
+        assert!(messages.iter().any(|m| m.contains("does not exist")));
+        assert!(messages.iter().any(|m| m.contains("not absolute")));
+    }
+}
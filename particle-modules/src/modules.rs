@@ -20,7 +20,10 @@ use std::{collections::HashMap, path::Path, path::PathBuf, sync::Arc};
 
 use base64::{engine::general_purpose::STANDARD as base64, Engine};
 use eyre::WrapErr;
-use fluence_app_service::{ModuleDescriptor, TomlMarineModuleConfig, TomlMarineNamedModuleConfig};
+use fluence_app_service::{
+    ModuleDescriptor, TomlMarineModuleConfig, TomlMarineNamedModuleConfig, TomlValue,
+    TomlWASIConfig,
+};
 use fstrings::f;
 use marine_it_parser::module_interface;
 use marine_module_info_parser::effects;
@@ -43,7 +46,7 @@ use crate::error::Result;
 use crate::files::{self, load_config_by_path, load_module_descriptor};
 use crate::ModuleError::{
     ForbiddenEffector, IncorrectVaultModuleConfig, InvalidEffectorMountedBinary,
-    SerializeBlueprintJson,
+    InvalidModuleOffset, ModuleDownloadForbidden, ReadMetadata, SerializeBlueprintJson,
 };
 
 #[derive(Debug, Clone)]
@@ -64,13 +67,62 @@ impl Default for EffectorsMode {
     }
 }
 
+/// Biggest piece of module bytes returned by [`ModuleRepository::get_module_chunk`]
+/// in one call: raw size before base64 encoding, chosen to keep the response
+/// well below the particle size limit
+pub const MAX_MODULE_CHUNK_SIZE: usize = 512 * 1024;
+
+/// Who may download a module's bytes back over the particle protocol
+#[derive(Debug, Clone)]
+pub struct ModuleAccess {
+    /// Peer that uploaded the module; always allowed to download it back
+    pub uploader: PeerId,
+    /// When set, any peer may download the module bytes
+    pub public: bool,
+}
+
+/// A piece of module bytes plus enough metadata to request the next piece
+/// or detect that the download is complete
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModuleChunk {
+    pub hash: String,
+    pub total_size: usize,
+    pub offset: usize,
+    /// base64-encoded bytes `[offset, offset + chunk_size)` of the module
+    pub chunk: String,
+    /// True when this chunk ends at the last byte of the module
+    pub last: bool,
+}
+
+/// How much disk space a single module takes: the wasm file itself
+/// plus its config descriptor
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModuleDiskUsage {
+    pub hash: String,
+    pub module_bytes: u64,
+    pub config_bytes: u64,
+}
+
+/// Total disk usage of the module store with a per-module breakdown
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    pub modules: Vec<ModuleDiskUsage>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ModuleRepository {
     modules_dir: PathBuf,
     blueprints_dir: PathBuf,
     module_interface_cache: Arc<RwLock<HashMap<Hash, JValue>>>,
     blueprints: Arc<RwLock<HashMap<String, Blueprint>>>,
+    // download permissions recorded at upload time; modules without an entry
+    // (e.g. system modules) are not downloadable by anyone but the management key
+    module_access: Arc<RwLock<HashMap<Hash, ModuleAccess>>>,
     effectors: EffectorsMode,
+    // node-level environment variables (e.g. NODE_VERSION) merged into each
+    // module's WASI config at add time; a module's own env always wins
+    default_wasi_envs: HashMap<String, String>,
 }
 
 impl ModuleRepository {
@@ -83,10 +135,19 @@ impl ModuleRepository {
             blueprints_dir: blueprints_dir.to_path_buf(),
             module_interface_cache: <_>::default(),
             blueprints: blueprints_cache,
+            module_access: <_>::default(),
             effectors,
+            default_wasi_envs: <_>::default(),
         }
     }
 
+    /// Sets environment variables injected into the WASI config of every module
+    /// added afterwards. Envs set explicitly in a module's own config are kept
+    pub fn with_default_wasi_envs(mut self, envs: HashMap<String, String>) -> Self {
+        self.default_wasi_envs = envs;
+        self
+    }
+
     fn make_effectors_config(
         &self,
         module_name: &str,
@@ -120,6 +181,24 @@ impl ModuleRepository {
         Ok(binaries)
     }
 
+    /// Merges [`Self::default_wasi_envs`] into the module's WASI config.
+    /// Envs already present in the config are left untouched, so a module
+    /// overrides a node-level default simply by setting the same name
+    fn inject_default_wasi_envs(&self, config: &mut TomlMarineModuleConfig) {
+        if self.default_wasi_envs.is_empty() {
+            return;
+        }
+        let wasi = config.wasi.get_or_insert(TomlWASIConfig {
+            envs: None,
+            mapped_dirs: None,
+        });
+        let envs = wasi.envs.get_or_insert_with(Default::default);
+        for (name, value) in &self.default_wasi_envs {
+            envs.entry(name.clone())
+                .or_insert_with(|| TomlValue::String(value.clone()));
+        }
+    }
+
     pub fn add_module(&self, name: String, module: Vec<u8>) -> Result<Hash> {
         let hash = Hash::new(&module)?;
         let (logger_enabled, mounted) = Self::get_module_effects(&module)?;
@@ -128,7 +207,8 @@ impl ModuleRepository {
             .not()
             .then(|| self.make_effectors_config(&name, &hash, mounted))
             .transpose()?;
-        let config = Self::make_config(name, logger_enabled, effector_settings);
+        let mut config = Self::make_config(name, logger_enabled, effector_settings);
+        self.inject_default_wasi_envs(&mut config.config);
         let _config = files::add_module(&self.modules_dir, &hash, &module, config)?;
 
         Ok(hash)
@@ -138,9 +218,10 @@ impl ModuleRepository {
     pub fn add_system_module(
         &self,
         module: Vec<u8>,
-        config: TomlMarineNamedModuleConfig,
+        mut config: TomlMarineNamedModuleConfig,
     ) -> Result<Hash> {
         let hash = Hash::new(&module)?;
+        self.inject_default_wasi_envs(&mut config.config);
         let _config = files::add_module(&self.modules_dir, &hash, &module, config)?;
         Ok(hash)
     }
@@ -185,6 +266,84 @@ impl ModuleRepository {
         Ok(hash.to_string())
     }
 
+    /// Parses a module CID from its string form, converting the error
+    /// into [`crate::ModuleError::WrongModuleHash`]
+    fn parse_module_hash(module_hash: &str) -> Result<Hash> {
+        Ok(Hash::from_string(module_hash)
+            .map_err(|err| eyre::eyre!("invalid module hash {module_hash}: {err}"))?)
+    }
+
+    /// Records who uploaded a module and whether its bytes may be downloaded
+    /// by anyone. Called by the builtins layer after a successful add
+    pub fn set_module_access(
+        &self,
+        module_hash: &str,
+        uploader: PeerId,
+        public: bool,
+    ) -> Result<()> {
+        let hash = Self::parse_module_hash(module_hash)?;
+        self.module_access
+            .write()
+            .insert(hash, ModuleAccess { uploader, public });
+        Ok(())
+    }
+
+    /// Checks whether `peer_id` may download bytes of the module: the uploader
+    /// always may, other peers only when the module was marked public.
+    /// The management key is allowed upstream, before this check
+    pub fn check_module_access(&self, module_hash: &str, peer_id: PeerId) -> Result<()> {
+        let hash = Self::parse_module_hash(module_hash)?;
+        let allowed = {
+            let lock = self.module_access.read();
+            lock.get(&hash)
+                .map_or(false, |access| access.public || access.uploader == peer_id)
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(ModuleDownloadForbidden {
+                module_cid: module_hash.to_string(),
+                peer_id,
+            })
+        }
+    }
+
+    /// Reads bytes `[offset, offset + length)` of the stored module, base64-encodes
+    /// them and returns them along with the module size. `length` is capped
+    /// by [`MAX_MODULE_CHUNK_SIZE`] so a single response fits into a particle;
+    /// callers download big modules by advancing `offset` until `last` is set
+    pub fn get_module_chunk(
+        &self,
+        module_hash: &str,
+        offset: usize,
+        length: Option<usize>,
+    ) -> Result<ModuleChunk> {
+        let hash = Self::parse_module_hash(module_hash)?;
+        let path = self.modules_dir.join(module_file_name_hash(&hash));
+        let module = files::load_module_by_path(&path)?;
+
+        let total_size = module.len();
+        if offset > total_size {
+            return Err(InvalidModuleOffset {
+                module_cid: module_hash.to_string(),
+                offset,
+                module_size: total_size,
+            });
+        }
+        let length = length
+            .unwrap_or(MAX_MODULE_CHUNK_SIZE)
+            .min(MAX_MODULE_CHUNK_SIZE);
+        let end = (offset + length).min(total_size);
+
+        Ok(ModuleChunk {
+            hash: hash.to_string(),
+            total_size,
+            offset,
+            chunk: base64.encode(&module[offset..end]),
+            last: end == total_size,
+        })
+    }
+
     /// Saves new blueprint to disk
     pub fn add_blueprint(&self, blueprint: AddBlueprint) -> Result<String> {
         let blueprint_name = blueprint.name.clone();
@@ -240,6 +399,46 @@ impl ModuleRepository {
         Ok(modules)
     }
 
+    /// Computes how much disk the module store takes, without loading
+    /// module contents into memory: only file metadata is inspected
+    pub fn disk_usage(&self) -> Result<DiskUsage> {
+        fn file_size(path: &Path) -> Result<u64> {
+            std::fs::metadata(path)
+                .map(|meta| meta.len())
+                .map_err(|err| ReadMetadata {
+                    path: path.to_path_buf(),
+                    err,
+                })
+        }
+
+        let mut modules = vec![];
+        for path in fs_utils::list_files(&self.modules_dir).into_iter().flatten() {
+            let hash = match extract_module_file_name(&path).map(Hash::from_string) {
+                Some(Ok(hash)) => hash,
+                // not a module file or an invalid name; list_modules reports those
+                _ => continue,
+            };
+            let module_bytes = file_size(&path)?;
+            let config_path = self.modules_dir.join(module_config_name_hash(&hash));
+            let config_bytes = file_size(&config_path)?;
+            modules.push(ModuleDiskUsage {
+                hash: hash.to_string(),
+                module_bytes,
+                config_bytes,
+            });
+        }
+
+        let total_bytes = modules
+            .iter()
+            .map(|module| module.module_bytes + module.config_bytes)
+            .sum();
+
+        Ok(DiskUsage {
+            total_bytes,
+            modules,
+        })
+    }
+
     pub fn get_facade_interface(&self, id: &str) -> Result<JValue> {
         let blueprints = self.blueprints.clone();
 
@@ -427,17 +626,24 @@ fn get_interface_by_hash(
 #[cfg(test)]
 mod tests {
     use base64::{engine::general_purpose::STANDARD as base64, Engine};
-    use fluence_app_service::{TomlMarineModuleConfig, TomlMarineNamedModuleConfig};
+    use fluence_app_service::{
+        TomlMarineModuleConfig, TomlMarineNamedModuleConfig, TomlValue, TomlWASIConfig,
+    };
     use maplit::hashmap;
     use std::assert_matches::assert_matches;
     use std::default::Default;
     use std::path::PathBuf;
     use tempdir::TempDir;
 
+    use fluence_libp2p::RandomPeerId;
     use service_modules::load_module;
+    use service_modules::module_config_name_hash;
     use service_modules::Hash;
 
-    use crate::ModuleError::{ForbiddenEffector, InvalidEffectorMountedBinary};
+    use crate::files::load_config_by_path;
+    use crate::ModuleError::{
+        ForbiddenEffector, InvalidEffectorMountedBinary, ModuleDownloadForbidden,
+    };
     use crate::{AddBlueprint, EffectorsMode, ModuleRepository};
 
     #[test]
@@ -586,6 +792,187 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_disk_usage() {
+        let module_dir = TempDir::new("test").unwrap();
+        let bp_dir = TempDir::new("test2").unwrap();
+        let allowed_effectors = EffectorsMode::AllEffectors {
+            binaries: hashmap! {
+                "ls".to_string() => PathBuf::from("/bin/ls"),
+            },
+        };
+        let repo = ModuleRepository::new(module_dir.path(), bp_dir.path(), allowed_effectors);
+
+        let tetraplets = load_module(
+            "../crates/nox-tests/tests/tetraplets/artifacts",
+            "tetraplets",
+        )
+        .expect("load module");
+        let effector = load_module("../crates/nox-tests/tests/effector/artifacts", "effector")
+            .expect("load module");
+
+        repo.add_module("tetraplets".to_string(), tetraplets).unwrap();
+        repo.add_module("effector".to_string(), effector).unwrap();
+
+        let usage = repo.disk_usage().unwrap();
+        assert_eq!(usage.modules.len(), 2);
+        for module in &usage.modules {
+            assert!(module.module_bytes > 0);
+            assert!(module.config_bytes > 0);
+        }
+
+        // every file in the store is either a module or its config,
+        // so the reported total must match the on-disk size of the directory
+        let on_disk: u64 = fs_utils::list_files(module_dir.path())
+            .into_iter()
+            .flatten()
+            .map(|path| std::fs::metadata(path).unwrap().len())
+            .sum();
+        assert_eq!(usage.total_bytes, on_disk);
+    }
+
+    #[test]
+    fn test_get_module_chunked() {
+        let module_dir = TempDir::new("test").unwrap();
+        let bp_dir = TempDir::new("test2").unwrap();
+        let repo = ModuleRepository::new(module_dir.path(), bp_dir.path(), Default::default());
+
+        let module = load_module(
+            "../crates/nox-tests/tests/tetraplets/artifacts",
+            "tetraplets",
+        )
+        .expect("load module");
+
+        let m_hash = repo
+            .add_module("tetraplets".to_string(), module.clone())
+            .unwrap();
+        let hash_str = m_hash.to_string();
+
+        // download the module back in small chunks and reassemble it
+        let mut bytes = vec![];
+        loop {
+            let chunk = repo
+                .get_module_chunk(&hash_str, bytes.len(), Some(1024))
+                .unwrap();
+            assert_eq!(chunk.total_size, module.len());
+            assert_eq!(chunk.offset, bytes.len());
+            bytes.extend(base64.decode(chunk.chunk).unwrap());
+            if chunk.last {
+                break;
+            }
+        }
+
+        assert_eq!(bytes, module);
+        // the reassembled bytes must hash back to the module's CID
+        assert_eq!(Hash::new(&bytes).unwrap(), m_hash);
+    }
+
+    #[test]
+    fn test_module_download_access() {
+        let module_dir = TempDir::new("test").unwrap();
+        let bp_dir = TempDir::new("test2").unwrap();
+        let repo = ModuleRepository::new(module_dir.path(), bp_dir.path(), Default::default());
+
+        let module = load_module(
+            "../crates/nox-tests/tests/tetraplets/artifacts",
+            "tetraplets",
+        )
+        .expect("load module");
+
+        let hash = repo
+            .add_module("tetraplets".to_string(), module)
+            .unwrap()
+            .to_string();
+        let uploader = RandomPeerId::random();
+        let other = RandomPeerId::random();
+
+        // before access is recorded no one can download the module
+        assert_matches!(
+            repo.check_module_access(&hash, uploader),
+            Err(ModuleDownloadForbidden { .. })
+        );
+
+        repo.set_module_access(&hash, uploader, false).unwrap();
+        assert_matches!(repo.check_module_access(&hash, uploader), Ok(()));
+        assert_matches!(
+            repo.check_module_access(&hash, other),
+            Err(ModuleDownloadForbidden { .. })
+        );
+
+        // the public flag opens the module to everyone
+        repo.set_module_access(&hash, uploader, true).unwrap();
+        assert_matches!(repo.check_module_access(&hash, other), Ok(()));
+    }
+
+    #[test]
+    fn test_default_wasi_envs_injected() {
+        let module_dir = TempDir::new("test").unwrap();
+        let bp_dir = TempDir::new("test2").unwrap();
+        let repo = ModuleRepository::new(module_dir.path(), bp_dir.path(), Default::default())
+            .with_default_wasi_envs(hashmap! {
+                "NODE_VERSION".to_string() => "0.1.0".to_string(),
+                "NODE_ENV".to_string() => "production".to_string(),
+            });
+
+        let module = load_module(
+            "../crates/nox-tests/tests/tetraplets/artifacts",
+            "tetraplets",
+        )
+        .expect("load module");
+
+        let load_envs = |hash: &Hash| {
+            let config_path = module_dir.path().join(module_config_name_hash(hash));
+            let config = load_config_by_path(&config_path).unwrap();
+            config.config.wasi.unwrap().envs.unwrap()
+        };
+
+        // a module without its own WASI config gets all node-level defaults
+        let hash = repo.add_module("tetra".to_string(), module.clone()).unwrap();
+        let envs = load_envs(&hash);
+        assert_eq!(
+            envs.get("NODE_VERSION"),
+            Some(&TomlValue::String("0.1.0".to_string()))
+        );
+        assert_eq!(
+            envs.get("NODE_ENV"),
+            Some(&TomlValue::String("production".to_string()))
+        );
+
+        // an env set in the module's own config wins over the node default,
+        // while the other defaults are still merged in
+        let config = TomlMarineNamedModuleConfig {
+            name: "tetra2".to_string(),
+            file_name: None,
+            load_from: None,
+            config: TomlMarineModuleConfig {
+                logger_enabled: None,
+                wasi: Some(TomlWASIConfig {
+                    envs: Some(
+                        vec![(
+                            "NODE_ENV".to_string(),
+                            TomlValue::String("test".to_string()),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    mapped_dirs: None,
+                }),
+                mounted_binaries: None,
+                logging_mask: None,
+            },
+        };
+        let hash = repo.add_system_module(module, config).unwrap();
+        let envs = load_envs(&hash);
+        assert_eq!(
+            envs.get("NODE_ENV"),
+            Some(&TomlValue::String("test".to_string()))
+        );
+        assert_eq!(
+            envs.get("NODE_VERSION"),
+            Some(&TomlValue::String("0.1.0".to_string()))
+        );
+    }
+
     #[test]
     fn test_add_module_pure() {
         let module_dir = TempDir::new("test").unwrap();
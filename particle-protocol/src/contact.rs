@@ -37,6 +37,62 @@ impl Contact {
     }
 }
 
+/// Serde helpers for [`Contact`] fields, selectable with field attributes
+pub mod serde {
+    /// Encodes a list of addresses as a single buffer of length-prefixed
+    /// `Multiaddr::to_vec` byte representations, which is considerably more
+    /// compact than the default encoding in binary formats like bincode.
+    /// Select with `#[serde(with = "contact::serde::compact")]`; the default
+    /// stays human-readable for JSON
+    pub mod compact {
+        use libp2p::core::Multiaddr;
+        use serde::de::Error;
+        use serde::{Deserialize, Deserializer, Serializer};
+        use unsigned_varint::{decode, encode};
+
+        pub fn serialize<S>(addresses: &[Multiaddr], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut buffer = Vec::new();
+            let mut length_buffer = encode::usize_buffer();
+            for address in addresses {
+                let bytes = address.to_vec();
+                buffer.extend_from_slice(encode::usize(bytes.len(), &mut length_buffer));
+                buffer.extend_from_slice(&bytes);
+            }
+            serializer.serialize_bytes(&buffer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Multiaddr>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let buffer: Vec<u8> = Deserialize::deserialize(deserializer)?;
+            let mut addresses = Vec::new();
+            let mut rest = buffer.as_slice();
+            while !rest.is_empty() {
+                let (length, tail) = decode::usize(rest).map_err(|e| {
+                    D::Error::custom(format!("malformed address length prefix: {e}"))
+                })?;
+                if tail.len() < length {
+                    return Err(D::Error::custom(format!(
+                        "truncated address: {} bytes left of {length}",
+                        tail.len()
+                    )));
+                }
+                let (bytes, tail) = tail.split_at(length);
+                let address = Multiaddr::try_from(bytes.to_vec()).map_err(|e| {
+                    D::Error::custom(format!("address deserialization failed: {e}"))
+                })?;
+                addresses.push(address);
+                rest = tail;
+            }
+            Ok(addresses)
+        }
+    }
+}
+
 impl Display for Contact {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if self.addresses.is_empty() {
@@ -52,3 +108,61 @@ impl Display for Contact {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use libp2p::core::Multiaddr;
+    use libp2p::PeerId;
+    use serde::{Deserialize, Serialize};
+
+    use types::peer_id;
+
+    use crate::contact::Contact;
+
+    /// Same shape as [`Contact`], with the compact address encoding chosen
+    #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+    struct CompactContact {
+        #[serde(
+            serialize_with = "peer_id::serde::serialize",
+            deserialize_with = "peer_id::serde::deserialize"
+        )]
+        peer_id: PeerId,
+        #[serde(with = "crate::contact::serde::compact")]
+        addresses: Vec<Multiaddr>,
+    }
+
+    #[test]
+    fn compact_addresses_are_smaller_and_lossless_over_bincode() {
+        let addresses: Vec<Multiaddr> = vec![
+            "/ip4/127.0.0.1/tcp/7777".parse().unwrap(),
+            "/ip4/192.168.0.1/tcp/9999/ws".parse().unwrap(),
+            "/dns4/node.fluence.dev/tcp/9000".parse().unwrap(),
+        ];
+        let peer_id = PeerId::random();
+        let default_form = Contact::new(peer_id, addresses.clone());
+        let compact_form = CompactContact { peer_id, addresses };
+
+        let default_bytes = bincode::serialize(&default_form).unwrap();
+        let compact_bytes = bincode::serialize(&compact_form).unwrap();
+        assert!(
+            compact_bytes.len() < default_bytes.len(),
+            "expected the compact form ({} bytes) to be smaller than the default one ({} bytes)",
+            compact_bytes.len(),
+            default_bytes.len()
+        );
+
+        let restored: CompactContact = bincode::deserialize(&compact_bytes).unwrap();
+        assert_eq!(restored, compact_form);
+    }
+
+    #[test]
+    fn compact_addresses_roundtrip_empty() {
+        let compact_form = CompactContact {
+            peer_id: PeerId::random(),
+            addresses: vec![],
+        };
+        let bytes = bincode::serialize(&compact_form).unwrap();
+        let restored: CompactContact = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(restored, compact_form);
+    }
+}
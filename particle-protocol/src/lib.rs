@@ -32,17 +32,22 @@ mod libp2p_protocol {
     pub(super) mod upgrade;
 }
 
-mod contact;
+pub mod contact;
 mod error;
 mod particle;
+mod versions;
 
 pub use contact::Contact;
 pub use error::ParticleError;
 pub use libp2p_protocol::message::CompletionChannel;
 pub use libp2p_protocol::message::SendStatus;
 pub use libp2p_protocol::message::{HandlerMessage, ProtocolMessage};
-pub use libp2p_protocol::upgrade::ProtocolConfig;
+pub use libp2p_protocol::upgrade::{
+    InvalidProtocolConfig, ProtocolConfig, ProtocolConfigBuilder, ProtocolConfigProblem,
+};
 pub use particle::ExtendedParticle;
 pub use particle::Particle;
+pub use particle::PATH_TRACE_LIMIT;
+pub use versions::{agent_version, parse_air_version, AirVersionPolicy};
 
 pub const PROTOCOL_NAME: &str = "/fluence/particle/2.0.0";
@@ -144,6 +144,8 @@ mod tests {
             script: "script".to_string(),
             signature: vec![0, 0, 128],
             data: vec![0, 0, 255],
+            hop_count: 3,
+            path_trace: vec![PeerId::random()],
         });
         let mut bytes = BytesMut::new();
         codec
@@ -185,6 +187,9 @@ mod tests {
                 253, 156, 242, 141, 129, 217, 205, 181, 156, 231, 10,
             ],
             data: vec![],
+            // the blob predates the fields: they must default to empty
+            hop_count: 0,
+            path_trace: vec![],
         });
 
         assert_eq!(result, Some(expected))
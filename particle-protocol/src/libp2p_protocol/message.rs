@@ -30,6 +30,13 @@ pub enum SendStatus {
     },
     ProtocolError(String),
     NotConnected,
+    /// The remote peer advertised an AIR interpreter version outside the supported range
+    VersionMismatch,
+    /// The particle was forwarded more times than the configured hop limit allows,
+    /// which points at a routing loop
+    HopLimitExceeded,
+    /// Command wasn't enqueued to the connection pool in time: the command queue was full
+    CommandQueueTimedOut,
     #[default]
     ConnectionPoolDied,
 }
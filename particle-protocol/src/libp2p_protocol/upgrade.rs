@@ -15,7 +15,7 @@
  */
 
 use asynchronous_codec::{FramedRead, FramedWrite};
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::{io, iter, time::Duration};
 
 use futures::{
@@ -28,6 +28,7 @@ use libp2p::{
 };
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::libp2p_protocol::codec::FluenceCodec;
 use crate::{HandlerMessage, SendStatus, PROTOCOL_NAME};
@@ -43,6 +44,14 @@ pub struct ProtocolConfig {
         default = "default_outbound_substream_timeout"
     )]
     pub outbound_substream_timeout: Duration,
+    /// A particle forwarded more than this many times is dropped
+    /// as a probable routing loop
+    #[serde(default = "default_max_hops")]
+    pub max_hops: u32,
+    /// How many times a send whose substream upgrade failed is retried
+    /// before the failure is reported to the caller. 0 disables retries
+    #[serde(default)]
+    pub max_upgrade_retries: u32,
 }
 
 impl Default for ProtocolConfig {
@@ -50,6 +59,8 @@ impl Default for ProtocolConfig {
         Self {
             upgrade_timeout: default_upgrade_timeout(),
             outbound_substream_timeout: default_outbound_substream_timeout(),
+            max_hops: default_max_hops(),
+            max_upgrade_retries: 0,
         }
     }
 }
@@ -60,12 +71,147 @@ fn default_outbound_substream_timeout() -> Duration {
 fn default_upgrade_timeout() -> Duration {
     Duration::from_secs(10)
 }
+fn default_max_hops() -> u32 {
+    64
+}
+
+/// Upper bound on protocol timeouts: anything longer than this is almost
+/// certainly a units mistake in the config (e.g. millis passed as secs)
+const MAX_TIMEOUT: Duration = Duration::from_secs(600);
+/// Upper bound on the hop limit; a particle that survived this many forwards
+/// is looping, a higher limit only postpones the drop
+const MAX_MAX_HOPS: u32 = 1024;
+
+/// A single out-of-range [`ProtocolConfig`] field
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProtocolConfigProblem {
+    #[error("`{field}` must be greater than zero")]
+    ZeroTimeout { field: &'static str },
+    #[error("`{field}` ({value:?}) must not exceed {max:?}")]
+    TimeoutTooLarge {
+        field: &'static str,
+        value: Duration,
+        max: Duration,
+    },
+    #[error(
+        "`outbound_substream_timeout` ({outbound:?}) must not be shorter than \
+         `upgrade_timeout` ({upgrade:?}): the substream timeout covers the upgrade"
+    )]
+    SubstreamShorterThanUpgrade { outbound: Duration, upgrade: Duration },
+    #[error("`max_hops` must be greater than zero")]
+    ZeroMaxHops,
+    #[error("`max_hops` ({value}) must not exceed {max}")]
+    MaxHopsTooLarge { value: u32, max: u32 },
+}
+
+/// Validation error of a [`ProtocolConfig`]: lists every out-of-range field,
+/// not just the first one
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub struct InvalidProtocolConfig {
+    pub problems: Vec<ProtocolConfigProblem>,
+}
+
+impl fmt::Display for InvalidProtocolConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid protocol config: ")?;
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{problem}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`ProtocolConfig`] that validates the result.
+/// Fields start at their defaults, so setting only what differs is fine
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolConfigBuilder {
+    config: ProtocolConfig,
+}
+
+impl ProtocolConfigBuilder {
+    pub fn upgrade_timeout(mut self, timeout: Duration) -> Self {
+        self.config.upgrade_timeout = timeout;
+        self
+    }
+
+    pub fn outbound_substream_timeout(mut self, timeout: Duration) -> Self {
+        self.config.outbound_substream_timeout = timeout;
+        self
+    }
+
+    pub fn max_hops(mut self, max_hops: u32) -> Self {
+        self.config.max_hops = max_hops;
+        self
+    }
+
+    pub fn max_upgrade_retries(mut self, retries: u32) -> Self {
+        self.config.max_upgrade_retries = retries;
+        self
+    }
+
+    pub fn build(self) -> Result<ProtocolConfig, InvalidProtocolConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
 
 impl ProtocolConfig {
+    /// Panics if the arguments are out of range;
+    /// use [`ProtocolConfig::builder`] for fallible construction
     pub fn new(upgrade_timeout: Duration, outbound_substream_timeout: Duration) -> Self {
-        Self {
-            upgrade_timeout,
-            outbound_substream_timeout,
+        Self::builder()
+            .upgrade_timeout(upgrade_timeout)
+            .outbound_substream_timeout(outbound_substream_timeout)
+            .build()
+            .expect("valid protocol config")
+    }
+
+    pub fn builder() -> ProtocolConfigBuilder {
+        ProtocolConfigBuilder::default()
+    }
+
+    /// Check every field against its sanity bounds, collecting all problems
+    pub fn validate(&self) -> Result<(), InvalidProtocolConfig> {
+        let mut problems = vec![];
+
+        for (field, value) in [
+            ("upgrade_timeout", self.upgrade_timeout),
+            ("outbound_substream_timeout", self.outbound_substream_timeout),
+        ] {
+            if value.is_zero() {
+                problems.push(ProtocolConfigProblem::ZeroTimeout { field });
+            } else if value > MAX_TIMEOUT {
+                problems.push(ProtocolConfigProblem::TimeoutTooLarge {
+                    field,
+                    value,
+                    max: MAX_TIMEOUT,
+                });
+            }
+        }
+
+        if self.outbound_substream_timeout < self.upgrade_timeout {
+            problems.push(ProtocolConfigProblem::SubstreamShorterThanUpgrade {
+                outbound: self.outbound_substream_timeout,
+                upgrade: self.upgrade_timeout,
+            });
+        }
+
+        if self.max_hops == 0 {
+            problems.push(ProtocolConfigProblem::ZeroMaxHops);
+        } else if self.max_hops > MAX_MAX_HOPS {
+            problems.push(ProtocolConfigProblem::MaxHopsTooLarge {
+                value: self.max_hops,
+                max: MAX_MAX_HOPS,
+            });
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidProtocolConfig { problems })
         }
     }
 }
@@ -200,9 +346,13 @@ mod tests {
     use libp2p::{InboundUpgrade, OutboundUpgrade};
     use rand::{thread_rng, Rng};
 
+    use std::time::Duration;
+
     use crate::libp2p_protocol::message::ProtocolMessage;
     use crate::{HandlerMessage, ProtocolConfig};
 
+    use super::{ProtocolConfigProblem, MAX_MAX_HOPS, MAX_TIMEOUT};
+
     const BYTES: [u8; 175] = [
         123, 34, 97, 99, 116, 105, 111, 110, 34, 58, 34, 80, 97, 114, 116, 105, 99, 108, 101, 34,
         44, 34, 105, 100, 34, 58, 34, 49, 34, 44, 34, 105, 110, 105, 116, 95, 112, 101, 101, 114,
@@ -256,6 +406,104 @@ mod tests {
         }
     }
 
+    #[test]
+    fn default_protocol_config_is_valid() {
+        ProtocolConfig::default()
+            .validate()
+            .expect("default config must be valid");
+    }
+
+    #[test]
+    fn zero_upgrade_timeout_is_rejected() {
+        let err = ProtocolConfig::builder()
+            .upgrade_timeout(Duration::ZERO)
+            .outbound_substream_timeout(Duration::ZERO)
+            .build()
+            .expect_err("zero timeouts must be rejected");
+        assert_eq!(
+            err.problems,
+            vec![
+                ProtocolConfigProblem::ZeroTimeout {
+                    field: "upgrade_timeout"
+                },
+                ProtocolConfigProblem::ZeroTimeout {
+                    field: "outbound_substream_timeout"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_timeout_is_rejected() {
+        let value = MAX_TIMEOUT + Duration::from_secs(1);
+        let err = ProtocolConfig::builder()
+            .upgrade_timeout(value)
+            .outbound_substream_timeout(value)
+            .build()
+            .expect_err("oversized timeouts must be rejected");
+        assert!(err.problems.contains(&ProtocolConfigProblem::TimeoutTooLarge {
+            field: "upgrade_timeout",
+            value,
+            max: MAX_TIMEOUT,
+        }));
+    }
+
+    #[test]
+    fn substream_timeout_shorter_than_upgrade_is_rejected() {
+        let err = ProtocolConfig::builder()
+            .upgrade_timeout(Duration::from_secs(10))
+            .outbound_substream_timeout(Duration::from_secs(5))
+            .build()
+            .expect_err("inconsistent timeouts must be rejected");
+        assert_eq!(
+            err.problems,
+            vec![ProtocolConfigProblem::SubstreamShorterThanUpgrade {
+                outbound: Duration::from_secs(5),
+                upgrade: Duration::from_secs(10),
+            }]
+        );
+    }
+
+    #[test]
+    fn zero_max_hops_is_rejected() {
+        let err = ProtocolConfig::builder()
+            .max_hops(0)
+            .build()
+            .expect_err("zero max_hops must be rejected");
+        assert_eq!(err.problems, vec![ProtocolConfigProblem::ZeroMaxHops]);
+    }
+
+    #[test]
+    fn oversized_max_hops_is_rejected() {
+        let err = ProtocolConfig::builder()
+            .max_hops(MAX_MAX_HOPS + 1)
+            .build()
+            .expect_err("oversized max_hops must be rejected");
+        assert_eq!(
+            err.problems,
+            vec![ProtocolConfigProblem::MaxHopsTooLarge {
+                value: MAX_MAX_HOPS + 1,
+                max: MAX_MAX_HOPS,
+            }]
+        );
+    }
+
+    #[test]
+    fn all_problems_are_reported_at_once() {
+        let err = ProtocolConfig::builder()
+            .upgrade_timeout(Duration::ZERO)
+            .outbound_substream_timeout(Duration::ZERO)
+            .max_hops(0)
+            .build()
+            .expect_err("invalid config must be rejected");
+        assert_eq!(err.problems.len(), 3);
+
+        let message = err.to_string();
+        assert!(message.contains("`upgrade_timeout` must be greater than zero"));
+        assert!(message.contains("`outbound_substream_timeout` must be greater than zero"));
+        assert!(message.contains("`max_hops` must be greater than zero"));
+    }
+
     #[test]
     fn deserialize() {
         let str = r#"{"action":"Particle","id":"2","init_peer_id":"12D3KooWAcn1f5iZ7wbo9QrYPFgq6o7DGkh7VwC8Zucn6DgWZQDo","timestamp":1617733422130,"ttl":65525,"script":"!","signature":[],"data":"MTJEM0tvb1dDM3dhcjhqcTJzaGFVQ2hSZWttYjNNN0RGRGl4ZkdVTm5ydGY0VlRGQVlVdywxMkQzS29vV0o2bVZLYXpKQzdyd2dtd0JpZm5LZ0JoR2NSTWtaOXdRTjY4dmJ1UGdIUjlO"}"#;
@@ -86,8 +86,22 @@ pub struct Particle {
     #[serde(with = "serde_bytes")]
     #[derivative(Debug(format_with = "fmt_data"))]
     pub data: Vec<u8>,
+    /// How many nodes have forwarded this particle; incremented on every
+    /// forward to detect routing loops. Defaults to 0 for particles from
+    /// older peers that don't send the field; excluded from the signature
+    #[serde(default)]
+    pub hop_count: u32,
+    /// The last few relay peer ids the particle went through, newest last;
+    /// capped at [PATH_TRACE_LIMIT] entries. Used to skip immediate ping-pong
+    /// forwards. Empty for particles from older peers that don't send
+    /// the field; excluded from the signature
+    #[serde(default, with = "peer_id::serde_vec")]
+    pub path_trace: Vec<PeerId>,
 }
 
+/// How many relay peer ids are kept in [Particle::path_trace]
+pub const PATH_TRACE_LIMIT: usize = 4;
+
 impl Default for Particle {
     fn default() -> Self {
         Self {
@@ -99,11 +113,27 @@ impl Default for Particle {
             script: "".to_string(),
             signature: vec![],
             data: vec![],
+            hop_count: 0,
+            path_trace: vec![],
         }
     }
 }
 
 impl Particle {
+    /// Appends a relay to the path trace, dropping the oldest entry
+    /// once [PATH_TRACE_LIMIT] is reached
+    pub fn push_path_trace(&mut self, peer_id: PeerId) {
+        if self.path_trace.len() >= PATH_TRACE_LIMIT {
+            self.path_trace.remove(0);
+        }
+        self.path_trace.push(peer_id);
+    }
+
+    /// The peer that forwarded this particle here, if it reported itself
+    pub fn prev_relay(&self) -> Option<PeerId> {
+        self.path_trace.last().copied()
+    }
+
     pub fn is_expired(&self) -> bool {
         if let Some(deadline) = self.deadline() {
             return now_ms() > deadline as u128;
@@ -201,9 +231,29 @@ impl std::fmt::Display for Particle {
 
 #[cfg(test)]
 mod tests {
+    use crate::particle::PATH_TRACE_LIMIT;
     use crate::Particle;
     use base64::{engine::general_purpose::STANDARD as base64, Engine};
     use fluence_keypair::{KeyFormat, KeyPair};
+    use fluence_libp2p::RandomPeerId;
+
+    #[test]
+    fn path_trace_keeps_only_the_last_relays() {
+        let mut p = Particle::default();
+        assert_eq!(p.prev_relay(), None);
+
+        let relays: Vec<_> = (0..PATH_TRACE_LIMIT + 2)
+            .map(|_| RandomPeerId::random())
+            .collect();
+        for relay in &relays {
+            p.push_path_trace(*relay);
+        }
+
+        assert_eq!(p.path_trace.len(), PATH_TRACE_LIMIT);
+        // the oldest relays are dropped, the newest one is the prev relay
+        assert_eq!(p.path_trace, relays[2..]);
+        assert_eq!(p.prev_relay(), relays.last().copied());
+    }
 
     #[test]
     fn test_signature() {
@@ -236,6 +286,8 @@ mod tests {
             script: "abc".to_string(),
             signature: vec![],
             data: vec![],
+            hop_count: 0,
+            path_trace: vec![],
         };
 
         let particle_bytes = p.as_bytes();
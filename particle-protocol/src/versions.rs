@@ -0,0 +1,103 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Marks the AIR interpreter version inside the Identify `agent_version` string
+const AIR_VERSION_PREFIX: &str = "air_version=";
+
+/// Builds the `agent_version` string advertised via the Identify protocol.
+/// It carries the node version and the AIR interpreter version,
+/// e.g. `0.16.0; air_version=0.55.0`
+pub fn agent_version(node_version: &str, air_version: &str) -> String {
+    format!("{node_version}; {AIR_VERSION_PREFIX}{air_version}")
+}
+
+/// Extracts the AIR interpreter version from an `agent_version` string.
+/// `None` means the peer doesn't advertise it (an older node) or
+/// the advertised version isn't valid semver.
+pub fn parse_air_version(agent_version: &str) -> Option<Version> {
+    let version = agent_version
+        .split(';')
+        .find_map(|part| part.trim().strip_prefix(AIR_VERSION_PREFIX))?;
+    Version::parse(version).ok()
+}
+
+/// Range of remote AIR interpreter versions this node agrees to exchange particles with.
+/// Particles from and to peers outside the range are refused.
+/// Peers that don't advertise a version at all are allowed for backward compatibility.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AirVersionPolicy {
+    #[serde(default)]
+    pub min_air_version: Option<Version>,
+    #[serde(default)]
+    pub max_air_version: Option<Version>,
+}
+
+impl AirVersionPolicy {
+    pub fn allows(&self, version: &Version) -> bool {
+        let min_ok = self
+            .min_air_version
+            .as_ref()
+            .map_or(true, |min| version >= min);
+        let max_ok = self
+            .max_air_version
+            .as_ref()
+            .map_or(true, |max| version <= max);
+        min_ok && max_ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn air_version_survives_agent_version_roundtrip() {
+        let agent_version = agent_version("0.16.0", "0.55.0");
+        assert_eq!(
+            parse_air_version(&agent_version),
+            Some("0.55.0".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn agent_version_without_air_version_yields_none() {
+        // older nodes advertise the bare node version
+        assert_eq!(parse_air_version("0.16.0"), None);
+        assert_eq!(parse_air_version(""), None);
+        // garbage after the marker isn't a version
+        assert_eq!(parse_air_version("0.16.0; air_version=latest"), None);
+    }
+
+    #[test]
+    fn policy_checks_both_range_ends() {
+        let policy = AirVersionPolicy {
+            min_air_version: Some("0.50.0".parse().unwrap()),
+            max_air_version: Some("0.60.0".parse().unwrap()),
+        };
+        assert!(policy.allows(&"0.50.0".parse().unwrap()));
+        assert!(policy.allows(&"0.55.3".parse().unwrap()));
+        assert!(policy.allows(&"0.60.0".parse().unwrap()));
+        assert!(!policy.allows(&"0.33.0".parse().unwrap()));
+        assert!(!policy.allows(&"0.61.0".parse().unwrap()));
+
+        // the default policy accepts everything
+        let default = AirVersionPolicy::default();
+        assert!(default.allows(&"0.0.1".parse().unwrap()));
+    }
+}
@@ -24,12 +24,14 @@ use fluence_app_service::{
     MarineConfig, MarineError, MarineWASIConfig, ModuleDescriptor, SecurityTetraplet,
     ServiceInterface, WasmtimeConfig,
 };
+use futures::stream::BoxStream;
 use futures::{stream, StreamExt};
 use humantime_serde::re::humantime::format_duration as pretty;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JValue};
 use tokio::runtime::Handle;
-use tokio_stream::wrappers::IntervalStream;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{IntervalStream, UnboundedReceiverStream};
 use tokio_util::context::TokioContext;
 
 use fluence_libp2p::PeerId;
@@ -50,6 +52,7 @@ use crate::error::ServiceError;
 use crate::error::ServiceError::{AliasAsServiceId, Forbidden, NoSuchAlias};
 use crate::health::PersistedServiceHealth;
 use crate::persistence::{load_persisted_services, remove_persisted_service, PersistedService};
+use crate::wasm_cache::{self, ModuleCacheTracker};
 use crate::ParticleAppServicesConfig;
 use crate::ServiceError::{
     FailedToCreateDirectory, ForbiddenAlias, ForbiddenAliasRoot, ForbiddenAliasWorker,
@@ -59,6 +62,11 @@ use crate::ServiceError::{
 type ServiceId = String;
 type ServiceAlias = String;
 
+/// A service id or alias prefixed with this is resolved among host services
+/// regardless of the scope the calling particle executes in. Aliases starting
+/// with the prefix cannot be registered
+pub const HOST_SCOPE_PREFIX: &str = "host:";
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ServiceType {
@@ -90,12 +98,15 @@ pub struct Service {
     pub service_id: String,
     pub blueprint_id: String,
     pub service_type: ServiceType,
-    pub owner_id: PeerId,
+    pub owner_id: tokio::sync::RwLock<PeerId>,
     pub aliases: tokio::sync::RwLock<Vec<ServiceAlias>>,
+    // peers granted management rights (remove, alias, info) on the service by its owner
+    pub delegates: tokio::sync::RwLock<Vec<PeerId>>,
     pub peer_scope: PeerScope,
 }
 
 impl Service {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         service: tokio::sync::Mutex<AppService>,
         service_id: String,
@@ -103,6 +114,7 @@ impl Service {
         service_type: ServiceType,
         owner_id: PeerId,
         aliases: Vec<ServiceAlias>,
+        delegates: Vec<PeerId>,
         peer_scope: PeerScope,
     ) -> Self {
         Self {
@@ -110,8 +122,9 @@ impl Service {
             service_id,
             blueprint_id,
             service_type,
-            owner_id,
+            owner_id: tokio::sync::RwLock::new(owner_id),
             aliases: tokio::sync::RwLock::new(aliases),
+            delegates: tokio::sync::RwLock::new(delegates),
             peer_scope,
         }
     }
@@ -127,12 +140,35 @@ impl Service {
         self.aliases.write().await.push(alias);
     }
 
+    pub async fn owner_id(&self) -> PeerId {
+        *self.owner_id.read().await
+    }
+
+    pub async fn set_owner_id(&self, owner_id: PeerId) {
+        *self.owner_id.write().await = owner_id;
+    }
+
+    pub async fn is_delegate(&self, peer_id: PeerId) -> bool {
+        self.delegates.read().await.contains(&peer_id)
+    }
+
+    pub async fn add_delegate(&self, delegate: PeerId) {
+        let mut delegates = self.delegates.write().await;
+        if !delegates.contains(&delegate) {
+            delegates.push(delegate);
+        }
+    }
+
+    pub async fn remove_delegate(&self, delegate: PeerId) {
+        self.delegates.write().await.retain(|d| *d != delegate);
+    }
+
     pub async fn get_info(&self, id: &str) -> ServiceInfo {
         ServiceInfo {
             id: id.to_string(),
             blueprint_id: self.blueprint_id.clone(),
             service_type: self.service_type.clone(),
-            owner_id: self.owner_id,
+            owner_id: self.owner_id().await,
             aliases: self.aliases.read().await.clone(),
             peer_scope: self.peer_scope,
         }
@@ -169,6 +205,13 @@ struct Services {
     aliases: Arc<tokio::sync::RwLock<HashMap<ServiceAlias, ServiceId>>>,
 }
 
+/// Service lifecycle events delivered to subscribers, e.g. the spell event bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceLifecycleEvent {
+    Created { service_id: ServiceId },
+    Removed { service_id: ServiceId },
+}
+
 #[derive(Derivative)]
 #[derivative(Debug, Clone)]
 pub struct ParticleAppServices {
@@ -190,6 +233,10 @@ pub struct ParticleAppServices {
     app_service_factory: AppServiceFactory,
     #[derivative(Debug = "ignore")]
     app_service_epoch_ticker: EpochTicker,
+    #[derivative(Debug = "ignore")]
+    module_cache_tracker: Option<ModuleCacheTracker>,
+    #[derivative(Debug = "ignore")]
+    event_listeners: Arc<parking_lot::RwLock<Vec<mpsc::UnboundedSender<ServiceLifecycleEvent>>>>,
 }
 
 async fn resolve_alias(
@@ -236,7 +283,18 @@ impl ParticleAppServices {
             persisted_services
         });
 
-        let wasmtime_config: WasmtimeConfig = config.wasm_backend_config.clone().into();
+        let wasmtime_config: WasmtimeConfig = if config.wasm_backend_config.module_cache_enabled {
+            wasm_cache::wasmtime_config_with_cache(
+                &config.wasm_backend_config,
+                &config.wasm_cache_dir,
+            )
+        } else {
+            config.wasm_backend_config.clone().into()
+        };
+        let module_cache_tracker = config
+            .wasm_backend_config
+            .module_cache_enabled
+            .then(ModuleCacheTracker::default);
 
         let (app_service_factory, epoch_ticker) =
             AppServiceFactory::new(wasmtime_config).map_err(ServiceError::Engine)?;
@@ -265,9 +323,25 @@ impl ParticleAppServices {
             health,
             app_service_factory,
             app_service_epoch_ticker: epoch_ticker,
+            module_cache_tracker,
+            event_listeners: <_>::default(),
         })
     }
 
+    /// Subscribes to service creation/removal events.
+    /// Only events emitted after this call are delivered to the returned stream.
+    pub fn lifecycle_events(&self) -> BoxStream<'static, ServiceLifecycleEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.event_listeners.write().push(sender);
+        UnboundedReceiverStream::new(receiver).boxed()
+    }
+
+    fn notify_lifecycle_event(&self, event: ServiceLifecycleEvent) {
+        self.event_listeners
+            .write()
+            .retain(|listener| listener.send(event.clone()).is_ok());
+    }
+
     pub async fn create_service(
         &self,
         peer_scope: PeerScope,
@@ -293,6 +367,7 @@ impl ParticleAppServices {
                 peer_scope,
                 service_id.clone(),
                 vec![],
+                vec![],
             )
             .await
         };
@@ -384,12 +459,14 @@ impl ParticleAppServices {
             //  service.owner_id has created the service, so can remove. that's OK.
             //  management_peer_id is the node admin, can remove any service. that's OK.
             //  service.worker_id is the worker itself, so can remove. that's OK.
+            //  delegates were granted management rights by the owner, so can remove. that's OK.
 
             let service_worker_id: PeerId = self.scopes.to_peer_id(peer_scope);
 
             if service_worker_id != init_peer_id
-                && service.owner_id != init_peer_id
+                && service.owner_id().await != init_peer_id
                 && !self.scopes.is_management(init_peer_id)
+                && !service.is_delegate(init_peer_id).await
             {
                 return Err(Forbidden {
                     user: init_peer_id,
@@ -424,6 +501,7 @@ impl ParticleAppServices {
         if let Some(metrics) = self.metrics.as_ref() {
             metrics.observe_removed(service_type, removal_end_time as f64);
         }
+        self.notify_lifecycle_event(ServiceLifecycleEvent::Removed { service_id });
 
         Ok(())
     }
@@ -478,7 +556,7 @@ impl ParticleAppServices {
         let params = CallParameters {
             particle: particle.to_particle_parameters(),
             service_id: service_id.clone(),
-            service_creator_peer_id: service.owner_id.to_string(),
+            service_creator_peer_id: service.owner_id().await.to_string(),
             host_id: self.scopes.get_host_peer_id().to_string(),
             worker_id: call_parameters_worker_id.to_string(),
             tetraplets: function_args
@@ -523,6 +601,9 @@ impl ParticleAppServices {
                 } else {
                     Some(function_name.clone())
                 };
+                if is_wasm_panic(&e) {
+                    metrics.observe_service_panic(service_id.clone(), service_type.clone());
+                }
                 metrics.observe_service_state_failed(
                     service_id.clone(),
                     function_name,
@@ -636,11 +717,64 @@ impl ParticleAppServices {
         }
     }
 
+    /// Resolves a service by id or alias strictly within `peer_scope`:
+    /// worker-scoped particles see only their worker's services, host-scoped
+    /// particles see only host services. Prefixing `id_or_alias` with
+    /// [`HOST_SCOPE_PREFIX`] explicitly resolves it among host services from
+    /// any scope.
+    ///
+    /// With `permissive_scope_resolution` enabled, a worker-scoped lookup that
+    /// finds nothing in its own scope still falls back to host services;
+    /// every such resolution is logged since the fallback is going away
+    /// in the next release.
     pub async fn get_service(
         &self,
         peer_scope: PeerScope,
         id_or_alias: String,
         particle_id: &str,
+    ) -> Result<(Arc<Service>, String), ServiceError> {
+        if let Some(target) = id_or_alias.strip_prefix(HOST_SCOPE_PREFIX) {
+            return self
+                .get_service_inner(PeerScope::Host, target.to_string(), particle_id)
+                .await;
+        }
+
+        let resolved = self
+            .get_service_inner(peer_scope, id_or_alias.clone(), particle_id)
+            .await;
+        match resolved {
+            Err(err)
+                if self.config.permissive_scope_resolution
+                    && matches!(peer_scope, PeerScope::WorkerId(_)) =>
+            {
+                let fallback = self
+                    .get_service_inner(PeerScope::Host, id_or_alias.clone(), particle_id)
+                    .await;
+                match fallback {
+                    Ok(found) => {
+                        tracing::warn!(
+                            "service '{}' was resolved on the host from {:?} (particle {}); \
+                             this fallback is deprecated, qualify the target as '{}{}' instead",
+                            id_or_alias,
+                            peer_scope,
+                            particle_id,
+                            HOST_SCOPE_PREFIX,
+                            id_or_alias
+                        );
+                        Ok(found)
+                    }
+                    Err(_) => Err(err),
+                }
+            }
+            resolved => resolved,
+        }
+    }
+
+    async fn get_service_inner(
+        &self,
+        peer_scope: PeerScope,
+        id_or_alias: String,
+        particle_id: &str,
     ) -> Result<(Arc<Service>, String), ServiceError> {
         let services = self.get_services(&peer_scope).await?;
         let services_id_mapping = services.services.read().await;
@@ -695,8 +829,13 @@ impl ParticleAppServices {
         init_peer_id: PeerId,
     ) -> Result<(), ServiceError> {
         let is_management = self.scopes.is_management(init_peer_id);
+        // delegates were granted management rights on the service by its owner,
+        // so they may manage its aliases too
+        let is_delegate = self
+            .is_delegate(peer_scope, &service_id, init_peer_id, "")
+            .await;
 
-        if !is_management {
+        if !is_management && !is_delegate {
             match peer_scope {
                 PeerScope::WorkerId(worker_id) => {
                     let worker_creator = self
@@ -725,6 +864,11 @@ impl ParticleAppServices {
             return Err(ForbiddenAlias(alias));
         }
 
+        // the prefix is reserved for explicit host-scope resolution
+        if alias.starts_with(HOST_SCOPE_PREFIX) {
+            return Err(ForbiddenAlias(alias));
+        }
+
         // Allow only HOST add alias "worker-spell"
         if alias == "worker-spell" && !self.scopes.is_host(init_peer_id) && !is_management {
             return Err(ForbiddenAlias(alias));
@@ -780,7 +924,141 @@ impl ParticleAppServices {
             .get_service(peer_scope, id_or_alias, particle_id)
             .await?;
 
-        Ok(service.owner_id)
+        Ok(service.owner_id().await)
+    }
+
+    /// Errors unless `init_peer_id` is the service owner or the management peer
+    async fn guard_owner(
+        &self,
+        service: &Service,
+        init_peer_id: PeerId,
+        function: &'static str,
+        reason: &'static str,
+    ) -> Result<(), ServiceError> {
+        if service.owner_id().await != init_peer_id && !self.scopes.is_management(init_peer_id) {
+            return Err(Forbidden {
+                user: init_peer_id,
+                function,
+                reason,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reassigns the owner of a service to `new_owner`, persisting the change.
+    /// Only the current owner or the management peer can transfer ownership
+    pub async fn transfer_ownership(
+        &self,
+        peer_scope: PeerScope,
+        service_id_or_alias: String,
+        new_owner: PeerId,
+        init_peer_id: PeerId,
+        particle_id: &str,
+    ) -> Result<(), ServiceError> {
+        let (service, _) = self
+            .get_service(peer_scope, service_id_or_alias, particle_id)
+            .await?;
+
+        self.guard_owner(
+            &service,
+            init_peer_id,
+            "transfer_ownership",
+            "only the owner or the management peer can transfer ownership",
+        )
+        .await?;
+
+        service.set_owner_id(new_owner).await;
+
+        let persisted = PersistedService::from_service(service.as_ref()).await;
+        persisted.persist(&self.config.services_dir).await
+    }
+
+    /// Grants `delegate` management rights (remove, alias, info) on the service,
+    /// persisting the grant. Only the owner or the management peer can grant
+    pub async fn grant_delegate(
+        &self,
+        peer_scope: PeerScope,
+        service_id_or_alias: String,
+        delegate: PeerId,
+        init_peer_id: PeerId,
+        particle_id: &str,
+    ) -> Result<(), ServiceError> {
+        let (service, _) = self
+            .get_service(peer_scope, service_id_or_alias, particle_id)
+            .await?;
+
+        self.guard_owner(
+            &service,
+            init_peer_id,
+            "grant_delegate",
+            "only the owner or the management peer can grant delegates",
+        )
+        .await?;
+
+        service.add_delegate(delegate).await;
+
+        let persisted = PersistedService::from_service(service.as_ref()).await;
+        persisted.persist(&self.config.services_dir).await
+    }
+
+    /// Revokes a previously granted delegation, persisting the change.
+    /// The revocation takes effect immediately
+    pub async fn revoke_delegate(
+        &self,
+        peer_scope: PeerScope,
+        service_id_or_alias: String,
+        delegate: PeerId,
+        init_peer_id: PeerId,
+        particle_id: &str,
+    ) -> Result<(), ServiceError> {
+        let (service, _) = self
+            .get_service(peer_scope, service_id_or_alias, particle_id)
+            .await?;
+
+        self.guard_owner(
+            &service,
+            init_peer_id,
+            "revoke_delegate",
+            "only the owner or the management peer can revoke delegates",
+        )
+        .await?;
+
+        service.remove_delegate(delegate).await;
+
+        let persisted = PersistedService::from_service(service.as_ref()).await;
+        persisted.persist(&self.config.services_dir).await
+    }
+
+    /// Lists the peers granted management rights on the service
+    pub async fn list_delegates(
+        &self,
+        peer_scope: PeerScope,
+        service_id_or_alias: String,
+        particle_id: &str,
+    ) -> Result<Vec<PeerId>, ServiceError> {
+        let (service, _) = self
+            .get_service(peer_scope, service_id_or_alias, particle_id)
+            .await?;
+
+        Ok(service.delegates.read().await.clone())
+    }
+
+    /// Whether `peer_id` was granted management rights on the service.
+    /// `false` if the service doesn't exist
+    pub async fn is_delegate(
+        &self,
+        peer_scope: PeerScope,
+        service_id_or_alias: &str,
+        peer_id: PeerId,
+        particle_id: &str,
+    ) -> bool {
+        match self
+            .get_service(peer_scope, service_id_or_alias.to_string(), particle_id)
+            .await
+        {
+            Ok((service, _)) => service.is_delegate(peer_id).await,
+            Err(_) => false,
+        }
     }
 
     pub async fn check_service_worker_id(
@@ -880,7 +1158,8 @@ impl ParticleAppServices {
     ) -> Result<Vec<JValue>, JError> {
         let (service, _) = self
             .get_service(peer_scope, service_id, particle_id)
-            .await?;
+            .await
+            .map_err(ServiceError::into_jerror)?;
 
         let lock = service.service.lock().await;
         let stats = lock.module_memory_stats();
@@ -935,6 +1214,7 @@ impl ParticleAppServices {
                     service.peer_scope,
                     service.service_id.clone(),
                     service.aliases.clone(),
+                    service.delegates.clone(),
                 )
                 .await;
             let replaced = match result {
@@ -998,6 +1278,7 @@ impl ParticleAppServices {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn create_service_inner(
         &self,
         service_type: ServiceType,
@@ -1006,6 +1287,7 @@ impl ParticleAppServices {
         peer_scope: PeerScope,
         service_id: String,
         aliases: Vec<String>,
+        delegates: Vec<PeerId>,
     ) -> Result<Option<Arc<Service>>, ServiceError> {
         let creation_start_time = Instant::now();
         let service = self
@@ -1030,6 +1312,7 @@ impl ParticleAppServices {
             service_type,
             owner_id,
             aliases,
+            delegates,
             peer_scope,
         );
         let service = Arc::new(service);
@@ -1044,6 +1327,9 @@ impl ParticleAppServices {
             .await
             .insert(service_id.clone(), service);
 
+        self.notify_lifecycle_event(ServiceLifecycleEvent::Created {
+            service_id: service_id.clone(),
+        });
         if let Some(m) = self.metrics.as_ref() {
             let creation_end_time = creation_start_time.elapsed().as_secs();
             m.observe_created(service_id, service_type, stats, creation_end_time as f64);
@@ -1151,6 +1437,14 @@ impl ParticleAppServices {
 
         let mut modules_config = self.modules.resolve_blueprint(&blueprint_id)?;
 
+        if let Some(tracker) = self.module_cache_tracker.as_ref() {
+            let blueprint = self.modules.get_blueprint_from_cache(&blueprint_id)?;
+            let observation = tracker.observe(&blueprint.dependencies);
+            if let Some(metrics) = self.metrics.as_ref() {
+                metrics.observe_module_cache(observation.hits, observation.misses);
+            }
+        }
+
         // Create Particle File Vault for Worker
         self.vault.initialize_worker(current_peer_id)?;
 
@@ -1228,6 +1522,13 @@ fn is_unknown_function(err: &AppServiceError) -> bool {
     )
 }
 
+/// WASM panics surface as engine errors mentioning a wasm trap
+/// (e.g. hitting the `unreachable` instruction), as opposed to
+/// soft errors returned by the service logic
+fn is_wasm_panic(err: &AppServiceError) -> bool {
+    err.to_string().contains("wasm trap")
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -1237,6 +1538,7 @@ mod tests {
     use base64::{engine::general_purpose::STANDARD as base64, Engine};
     use fluence_app_service::{TomlMarineModuleConfig, TomlMarineNamedModuleConfig};
     use fluence_keypair::KeyPair;
+    use hex::FromHex;
     use libp2p_identity::{Keypair, PeerId};
     use tempdir::TempDir;
 
@@ -1246,9 +1548,9 @@ mod tests {
     use service_modules::load_module;
     use service_modules::Hash;
     use types::peer_scope::PeerScope;
-    use workers::{DummyCoreManager, KeyStorage, PeerScopes, Workers};
+    use workers::{DummyCoreManager, KeyStorage, PeerScopes, WorkerId, WorkerParams, Workers, CUID};
 
-    use crate::app_services::{ServiceAlias, ServiceType};
+    use crate::app_services::{ServiceAlias, ServiceType, HOST_SCOPE_PREFIX};
     use crate::persistence::load_persisted_services;
     use crate::{ParticleAppServices, ParticleAppServicesConfig, ServiceError, WasmBackendConfig};
 
@@ -1263,6 +1565,17 @@ mod tests {
         management_pid: PeerId,
         base_dir: PathBuf,
     ) -> ParticleAppServices {
+        let (pas, _workers) =
+            create_pas_with_workers(root_keypair, management_pid, base_dir, false).await;
+        pas
+    }
+
+    async fn create_pas_with_workers(
+        root_keypair: Keypair,
+        management_pid: PeerId,
+        base_dir: PathBuf,
+        permissive_scope_resolution: bool,
+    ) -> (ParticleAppServices, Arc<Workers>) {
         let persistent_dir = base_dir.join("persistent");
         let ephemeral_dir = base_dir.join("ephemeral");
         let vault_dir = ephemeral_dir.join("vault");
@@ -1307,6 +1620,7 @@ mod tests {
             Default::default(),
             true,
             wasm_backend_config,
+            permissive_scope_resolution,
         )
         .unwrap();
 
@@ -1316,8 +1630,19 @@ mod tests {
             Default::default(),
         );
 
-        ParticleAppServices::new(config, repo, None, None, workers, scope)
-            .expect("Could not create ParticleAppServices")
+        let pas = ParticleAppServices::new(config, repo, None, None, workers.clone(), scope)
+            .expect("Could not create ParticleAppServices");
+        (pas, workers)
+    }
+
+    async fn create_worker(workers: &Workers) -> WorkerId {
+        let cu_id =
+            <CUID>::from_hex("54ae1b506c260367a054f80800a545f23e32c6bc4a8908c9a794cb8dad23e5ea")
+                .unwrap();
+        workers
+            .create_worker(WorkerParams::new("deal_id".into(), create_pid(), vec![cu_id]))
+            .await
+            .expect("Could not create worker")
     }
 
     async fn call_add_alias_raw(
@@ -1678,7 +2003,276 @@ mod tests {
         assert_eq!(service_aliases_1, persisted_service_1.aliases);
         assert_eq!(service_1.blueprint_id, persisted_service_1.blueprint_id);
         assert_eq!(service_id1, persisted_service_1.service_id);
-        assert_eq!(service_1.owner_id, persisted_service_1.owner_id);
+        assert_eq!(service_1.owner_id().await, persisted_service_1.owner_id);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_ownership() {
+        let base_dir = TempDir::new("test4").unwrap();
+        let root_keypair = Keypair::generate_ed25519();
+        let management_pid = create_pid();
+        let pas = create_pas(root_keypair, management_pid, base_dir.into_path()).await;
+
+        let module_name = "tetra".to_string();
+        let m_hash = upload_tetra_service(&pas, module_name.clone());
+        let dep = Hash::from_string(&m_hash).unwrap();
+        let bp = pas
+            .modules
+            .add_blueprint(AddBlueprint::new(module_name, vec![dep]))
+            .unwrap();
+
+        let old_owner = create_pid();
+        let new_owner = create_pid();
+        let stranger = create_pid();
+        let service_id = pas
+            .create_service(PeerScope::Host, ServiceType::Service, bp, old_owner)
+            .await
+            .unwrap();
+
+        // a random peer must not be able to transfer ownership
+        let result = pas
+            .transfer_ownership(PeerScope::Host, service_id.clone(), stranger, stranger, "")
+            .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden { .. })));
+
+        // the owner transfers the service away
+        pas.transfer_ownership(PeerScope::Host, service_id.clone(), new_owner, old_owner, "")
+            .await
+            .unwrap();
+
+        // the new owner must be persisted
+        let (persisted_service, _) = load_persisted_services(&pas.config.services_dir)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(s, _)| s.service_id == service_id)
+            .unwrap();
+        assert_eq!(persisted_service.owner_id, new_owner);
+
+        // the old owner must lose management rights
+        let result = pas
+            .remove_service(PeerScope::Host, "", &service_id, old_owner, false)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden { .. })));
+
+        // the new owner must gain them
+        pas.remove_service(PeerScope::Host, "", &service_id, new_owner, false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delegation() {
+        let base_dir = TempDir::new("test4").unwrap();
+        let root_keypair = Keypair::generate_ed25519();
+        let management_pid = create_pid();
+        let pas = create_pas(root_keypair, management_pid, base_dir.into_path()).await;
+
+        let module_name = "tetra".to_string();
+        let m_hash = upload_tetra_service(&pas, module_name.clone());
+        let dep = Hash::from_string(&m_hash).unwrap();
+        let bp = pas
+            .modules
+            .add_blueprint(AddBlueprint::new(module_name, vec![dep]))
+            .unwrap();
+
+        let owner = create_pid();
+        let delegate = create_pid();
+        let service_id = pas
+            .create_service(PeerScope::Host, ServiceType::Service, bp, owner)
+            .await
+            .unwrap();
+
+        // before the grant the delegate has no rights on the service
+        let result = pas
+            .remove_service(PeerScope::Host, "", &service_id, delegate, false)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden { .. })));
+        // and can't grant rights to themselves
+        let result = pas
+            .grant_delegate(PeerScope::Host, service_id.clone(), delegate, delegate, "")
+            .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden { .. })));
+
+        // the owner grants management rights to the delegate
+        pas.grant_delegate(PeerScope::Host, service_id.clone(), delegate, owner, "")
+            .await
+            .unwrap();
+        let delegates = pas
+            .list_delegates(PeerScope::Host, service_id.clone(), "")
+            .await
+            .unwrap();
+        assert_eq!(delegates, vec![delegate]);
+
+        // the grant must be persisted
+        let (persisted_service, _) = load_persisted_services(&pas.config.services_dir)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(s, _)| s.service_id == service_id)
+            .unwrap();
+        assert_eq!(persisted_service.delegates, vec![delegate]);
+
+        // the delegate can manage aliases now
+        pas.add_alias(
+            PeerScope::Host,
+            "alias".to_string(),
+            service_id.clone(),
+            delegate,
+        )
+        .await
+        .unwrap();
+
+        // but delegation is not ownership: no transfer for the delegate
+        let result = pas
+            .transfer_ownership(PeerScope::Host, service_id.clone(), delegate, delegate, "")
+            .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden { .. })));
+
+        // the revocation takes effect immediately
+        pas.revoke_delegate(PeerScope::Host, service_id.clone(), delegate, owner, "")
+            .await
+            .unwrap();
+        let result = pas
+            .remove_service(PeerScope::Host, "", &service_id, delegate, false)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden { .. })));
+
+        // after a fresh grant the delegate can remove the service
+        pas.grant_delegate(PeerScope::Host, service_id.clone(), delegate, owner, "")
+            .await
+            .unwrap();
+        pas.remove_service(PeerScope::Host, "", &service_id, delegate, false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scoped_service_resolution() {
+        let base_dir = TempDir::new("test5").unwrap();
+        let root_keypair = Keypair::generate_ed25519();
+        let management_pid = create_pid();
+        let (pas, workers) =
+            create_pas_with_workers(root_keypair, management_pid, base_dir.into_path(), false)
+                .await;
+        let worker_id = create_worker(&workers).await;
+        let worker_scope = PeerScope::WorkerId(worker_id);
+
+        let module_name = "tetra".to_string();
+        let m_hash = upload_tetra_service(&pas, module_name.clone());
+        let host_service = create_service(&pas, module_name.clone(), &m_hash, PeerScope::Host)
+            .await
+            .unwrap();
+        let worker_service = create_service(&pas, module_name, &m_hash, worker_scope)
+            .await
+            .unwrap();
+
+        // the same alias points to different services in different scopes
+        let alias = "alias".to_string();
+        pas.add_alias(
+            PeerScope::Host,
+            alias.clone(),
+            host_service.clone(),
+            management_pid,
+        )
+        .await
+        .unwrap();
+        pas.add_alias(
+            worker_scope,
+            alias.clone(),
+            worker_service.clone(),
+            management_pid,
+        )
+        .await
+        .unwrap();
+
+        // each scope resolves the alias to its own service
+        let resolved = pas
+            .to_service_id(PeerScope::Host, alias.clone(), "")
+            .await
+            .unwrap();
+        assert_eq!(resolved, host_service);
+        let resolved = pas
+            .to_service_id(worker_scope, alias.clone(), "")
+            .await
+            .unwrap();
+        assert_eq!(resolved, worker_service);
+
+        // a host service is invisible from the worker scope without the qualifier
+        let result = pas
+            .to_service_id(worker_scope, host_service.clone(), "")
+            .await;
+        assert!(matches!(result, Err(ServiceError::NoSuchService(..))));
+
+        // and reachable with it, both by id and by alias
+        let resolved = pas
+            .to_service_id(worker_scope, format!("{HOST_SCOPE_PREFIX}{host_service}"), "")
+            .await
+            .unwrap();
+        assert_eq!(resolved, host_service);
+        let resolved = pas
+            .to_service_id(worker_scope, format!("{HOST_SCOPE_PREFIX}{alias}"), "")
+            .await
+            .unwrap();
+        assert_eq!(resolved, host_service);
+
+        // worker services are never visible from the host scope
+        let result = pas
+            .to_service_id(PeerScope::Host, worker_service, "")
+            .await;
+        assert!(matches!(result, Err(ServiceError::NoSuchService(..))));
+
+        // tokio doesn't allow to drop runtimes in async context,
+        // so shifting workers drop to the blocking thread
+        tokio::task::spawn_blocking(move || drop((pas, workers)))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_permissive_scope_resolution_fallback() {
+        let base_dir = TempDir::new("test5").unwrap();
+        let root_keypair = Keypair::generate_ed25519();
+        let management_pid = create_pid();
+        let (pas, workers) =
+            create_pas_with_workers(root_keypair, management_pid, base_dir.into_path(), true).await;
+        let worker_id = create_worker(&workers).await;
+        let worker_scope = PeerScope::WorkerId(worker_id);
+
+        let module_name = "tetra".to_string();
+        let m_hash = upload_tetra_service(&pas, module_name.clone());
+        let host_service = create_service(&pas, module_name.clone(), &m_hash, PeerScope::Host)
+            .await
+            .unwrap();
+        let worker_service = create_service(&pas, module_name, &m_hash, worker_scope)
+            .await
+            .unwrap();
+
+        // the worker's own services still win under the compat flag
+        let resolved = pas
+            .to_service_id(worker_scope, worker_service.clone(), "")
+            .await
+            .unwrap();
+        assert_eq!(resolved, worker_service);
+
+        // an unqualified host service resolves from the worker scope the old way
+        let resolved = pas
+            .to_service_id(worker_scope, host_service.clone(), "")
+            .await
+            .unwrap();
+        assert_eq!(resolved, host_service);
+
+        // the host scope gets no fallback into workers even under the flag
+        let result = pas
+            .to_service_id(PeerScope::Host, worker_service, "")
+            .await;
+        assert!(matches!(result, Err(ServiceError::NoSuchService(..))));
+
+        // tokio doesn't allow to drop runtimes in async context,
+        // so shifting workers drop to the blocking thread
+        tokio::task::spawn_blocking(move || drop((pas, workers)))
+            .await
+            .unwrap();
     }
 
     // TODO: add more tests
@@ -39,6 +39,8 @@ pub struct ParticleAppServicesConfig {
     pub ephemeral_work_dir: PathBuf,
     /// Dir to store .wasm modules and their configs
     pub modules_dir: PathBuf,
+    /// Dir to keep modules compiled by the wasm backend between service creations
+    pub wasm_cache_dir: PathBuf,
     /// Dir to persist info about running services
     pub services_dir: PathBuf,
     /// Dir to store directories shared between services
@@ -58,6 +60,9 @@ pub struct ParticleAppServicesConfig {
     pub is_dev_mode: bool,
     /// config for the wasmtime backend
     pub wasm_backend_config: WasmBackendConfig,
+    /// Compat: let worker-scoped service resolution fall back to host services
+    /// the way it did before scopes became strict; goes away in the next release
+    pub permissive_scope_resolution: bool,
 }
 
 impl ParticleAppServicesConfig {
@@ -75,6 +80,7 @@ impl ParticleAppServicesConfig {
         mounted_binaries_mapping: HashMap<String, String>,
         is_dev_mode: bool,
         wasm_backend_config: WasmBackendConfig,
+        permissive_scope_resolution: bool,
     ) -> Result<Self, std::io::Error> {
         let persistent_dir = to_abs_path(persistent_dir);
         let ephemeral_dir = to_abs_path(ephemeral_dir);
@@ -125,6 +131,7 @@ impl ParticleAppServicesConfig {
             persistent_work_dir: config_utils::workdir(&persistent_dir),
             ephemeral_work_dir: config_utils::workdir(&ephemeral_dir),
             modules_dir: config_utils::modules_dir(&persistent_dir),
+            wasm_cache_dir: config_utils::wasm_cache_dir(&persistent_dir),
             services_dir: config_utils::services_dir(&persistent_dir),
             particles_vault_dir,
             envs,
@@ -135,6 +142,7 @@ impl ParticleAppServicesConfig {
             mounted_binaries_mapping,
             is_dev_mode,
             wasm_backend_config,
+            permissive_scope_resolution,
         };
 
         create_dirs(&[
@@ -142,6 +150,7 @@ impl ParticleAppServicesConfig {
             &this.persistent_work_dir,
             &this.ephemeral_work_dir,
             &this.modules_dir,
+            &this.wasm_cache_dir,
             &this.services_dir,
             &this.particles_vault_dir,
         ])?;
@@ -164,17 +173,27 @@ pub struct WasmBackendConfig {
     pub max_wasm_stack: usize,
     /// Enables the epoch interruption mechanism.
     pub epoch_interruption_duration: Option<Duration>,
+    /// Keep compiled modules on disk and reuse them between service creations.
+    pub module_cache_enabled: bool,
+    /// Soft cap on the total size of compiled modules kept on disk.
+    pub module_cache_size_limit: u64,
+}
+
+impl WasmBackendConfig {
+    pub(crate) fn apply(&self, config: &mut WasmtimeConfig) {
+        config
+            .debug_info(self.debug_info)
+            .wasm_backtrace(self.wasm_backtrace)
+            .epoch_interruption(true)
+            .async_wasm_stack(self.async_wasm_stack)
+            .max_wasm_stack(self.max_wasm_stack);
+    }
 }
 
 impl From<WasmBackendConfig> for WasmtimeConfig {
     fn from(value: WasmBackendConfig) -> Self {
         let mut config = WasmtimeConfig::default();
-        config
-            .debug_info(value.debug_info)
-            .wasm_backtrace(value.wasm_backtrace)
-            .epoch_interruption(true)
-            .async_wasm_stack(value.async_wasm_stack)
-            .max_wasm_stack(value.max_wasm_stack);
+        value.apply(&mut config);
         config
     }
 }
@@ -187,6 +206,8 @@ impl Default for WasmBackendConfig {
             async_wasm_stack: 4 * 1024 * 1024,
             max_wasm_stack: 2 * 1024 * 1024,
             epoch_interruption_duration: Some(Duration::from_secs(1)),
+            module_cache_enabled: true,
+            module_cache_size_limit: 512 * 1024 * 1024,
         }
     }
 }
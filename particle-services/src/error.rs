@@ -23,7 +23,7 @@ use thiserror::Error;
 
 use fluence_libp2p::PeerId;
 use json_utils::err_as_value;
-use particle_args::ArgsError;
+use particle_args::{error_codes, ArgsError, JError};
 use particle_execution::VaultError;
 use particle_modules::ModuleError;
 use types::peer_scope::{PeerScope, WorkerId};
@@ -132,6 +132,30 @@ impl From<ModuleError> for ServiceError {
     }
 }
 
+impl ServiceError {
+    /// The stable machine-readable code of this error;
+    /// see [`particle_args::error_codes`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::NoSuchService(..)
+            | ServiceError::NoSuchServiceWithFunction { .. }
+            | ServiceError::NoSuchAlias(..)
+            | ServiceError::WorkerNotFound { .. } => error_codes::SRV_NOT_FOUND,
+            ServiceError::Forbidden { .. }
+            | ServiceError::ForbiddenAliasRoot(..)
+            | ServiceError::ForbiddenAliasWorker(..) => error_codes::AUTH_PERMISSION_DENIED,
+            ServiceError::ModuleError(err) => err.code(),
+            _ => error_codes::SRV_ERROR,
+        }
+    }
+
+    /// Converts into the coded [`JError`] envelope. Used instead of the
+    /// blanket `From<E: Error>` conversion, which can't know the code
+    pub fn into_jerror(self) -> JError {
+        JError::coded_error(self.code(), self)
+    }
+}
+
 impl From<ServiceError> for JValue {
     fn from(err: ServiceError) -> Self {
         err_as_value(err)
@@ -30,7 +30,9 @@
 pub use fluence_app_service::{IType, IValue};
 
 pub use app_services::ParticleAppServices;
+pub use app_services::ServiceLifecycleEvent;
 pub use app_services::ServiceType;
+pub use app_services::HOST_SCOPE_PREFIX;
 
 pub use crate::error::ServiceError;
 
@@ -38,6 +40,7 @@ mod app_services;
 mod error;
 mod health;
 mod persistence;
+mod wasm_cache;
 
 mod config;
 
@@ -42,6 +42,14 @@ pub struct PersistedService {
         deserialize_with = "peer_id::serde::deserialize"
     )]
     pub owner_id: PeerId,
+    // Peers granted management rights on the service by its owner.
+    // Old versions of PersistedService may omit `delegates` field, tolerate that
+    #[serde(default)]
+    #[serde(
+        serialize_with = "peer_id::serde_vec::serialize",
+        deserialize_with = "peer_id::serde_vec::deserialize"
+    )]
+    pub delegates: Vec<PeerId>,
     pub peer_scope: PeerScope,
 }
 
@@ -52,7 +60,8 @@ impl PersistedService {
             service_type: Some(service.service_type.clone()),
             blueprint_id: service.blueprint_id.clone(),
             aliases: service.aliases.read().await.clone(),
-            owner_id: service.owner_id,
+            owner_id: service.owner_id().await,
+            delegates: service.delegates.read().await.clone(),
             peer_scope: service.peer_scope,
         }
     }
@@ -105,6 +114,7 @@ mod tests {
             blueprint_id: "blueprint_id_1".to_string(),
             aliases: vec!["alias_1".to_string()],
             owner_id,
+            delegates: vec![],
             peer_scope: PeerScope::WorkerId(owner_id.into()),
         };
         service_1
@@ -118,6 +128,7 @@ mod tests {
             blueprint_id: "blueprint_id_2".to_string(),
             aliases: vec!["alias_2".to_string()],
             owner_id,
+            delegates: vec![],
             peer_scope: PeerScope::Host,
         };
         service_2
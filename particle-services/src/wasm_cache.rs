@@ -0,0 +1,208 @@
+/*
+ * Copyright 2024 Fluence DAO
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cid_utils::Hash;
+use fluence_app_service::WasmtimeConfig;
+
+use crate::config::WasmBackendConfig;
+
+const CACHE_CONFIG_FILE: &str = "cache-config.toml";
+
+/// How many distinct module hashes [`ModuleCacheTracker`] remembers
+/// before it starts to evict the least recently seen ones.
+const MAX_TRACKED_MODULES: usize = 4096;
+
+/// Builds a wasmtime config with the on-disk compiled module cache enabled.
+///
+/// The cache is content-addressed, so a module is recompiled only when its
+/// bytes change; all services created from the same modules reuse the compiled
+/// artifacts. If the cache can't be set up, falls back to a config without
+/// the cache: services are still created, just slower.
+pub(crate) fn wasmtime_config_with_cache(
+    backend: &WasmBackendConfig,
+    cache_dir: &Path,
+) -> WasmtimeConfig {
+    let raw_config: Result<wasmtime::Config, std::io::Error> = try {
+        let cache_config_path = write_cache_config(cache_dir, backend.module_cache_size_limit)?;
+        let mut raw_config = wasmtime::Config::new();
+        raw_config
+            .cache_config_load(&cache_config_path)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+        raw_config
+    };
+    match raw_config {
+        Ok(raw_config) => {
+            let mut config = WasmtimeConfig::from_raw(raw_config);
+            backend.apply(&mut config);
+            config
+        }
+        Err(err) => {
+            tracing::warn!(
+                "Failed to enable the wasm module cache at {:?}: {err}. \
+                 Modules will be recompiled on every service creation",
+                cache_dir
+            );
+            backend.clone().into()
+        }
+    }
+}
+
+/// Writes a wasmtime cache config next to the cache directory and returns its path.
+pub(crate) fn write_cache_config(
+    cache_dir: &Path,
+    size_limit: u64,
+) -> Result<PathBuf, std::io::Error> {
+    std::fs::create_dir_all(cache_dir)?;
+    let path = cache_dir.join(CACHE_CONFIG_FILE);
+    let config = format!(
+        "[cache]\nenabled = true\ndirectory = {:?}\nfiles-total-size-soft-limit = \"{}\"\n",
+        cache_dir, size_limit
+    );
+    std::fs::write(&path, config)?;
+    Ok(path)
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub(crate) struct CacheObservation {
+    /// Number of modules that have already been compiled before
+    pub hits: u64,
+    /// Number of modules seen for the first time
+    pub misses: u64,
+}
+
+/// Remembers which modules have already gone through compilation to tell cache
+/// hits from misses on service creation. The wasm backend doesn't report them
+/// itself, so this is an approximation: a module seen before is expected to be
+/// served from the compiled module cache.
+#[derive(Clone)]
+pub(crate) struct ModuleCacheTracker {
+    capacity: usize,
+    inner: Arc<parking_lot::Mutex<TrackedModules>>,
+}
+
+#[derive(Default)]
+struct TrackedModules {
+    /// Module hashes in the order of the last sighting, oldest first
+    order: VecDeque<Hash>,
+    seen: HashSet<Hash>,
+}
+
+impl ModuleCacheTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(parking_lot::Mutex::new(TrackedModules::default())),
+        }
+    }
+
+    pub fn observe(&self, modules: &[Hash]) -> CacheObservation {
+        let mut tracked = self.inner.lock();
+        let mut observation = CacheObservation::default();
+        for module in modules {
+            if tracked.seen.contains(module) {
+                observation.hits += 1;
+                tracked.order.retain(|m| m != module);
+            } else {
+                observation.misses += 1;
+                tracked.seen.insert(module.clone());
+                if tracked.seen.len() > self.capacity {
+                    if let Some(oldest) = tracked.order.pop_front() {
+                        tracked.seen.remove(&oldest);
+                    }
+                }
+            }
+            tracked.order.push_back(module.clone());
+        }
+        observation
+    }
+}
+
+impl Default for ModuleCacheTracker {
+    fn default() -> Self {
+        Self::new(MAX_TRACKED_MODULES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn hash(data: &[u8]) -> Hash {
+        Hash::new(data).unwrap()
+    }
+
+    #[test]
+    fn second_sighting_of_a_module_is_a_hit() {
+        let tracker = ModuleCacheTracker::new(10);
+        let modules = vec![hash(b"a"), hash(b"b")];
+
+        let first = tracker.observe(&modules);
+        assert_eq!(first.hits, 0);
+        assert_eq!(first.misses, 2);
+
+        let second = tracker.observe(&modules);
+        assert_eq!(second.hits, 2);
+        assert_eq!(second.misses, 0);
+    }
+
+    #[test]
+    fn eviction_beyond_the_cap_turns_hits_back_into_misses() {
+        let tracker = ModuleCacheTracker::new(2);
+        let a = vec![hash(b"a")];
+
+        tracker.observe(&a);
+        // b and c push a beyond the cap
+        tracker.observe(&[hash(b"b"), hash(b"c")]);
+
+        let again = tracker.observe(&a);
+        assert_eq!(again.hits, 0);
+        assert_eq!(again.misses, 1);
+    }
+
+    #[test]
+    fn recently_seen_modules_survive_eviction() {
+        let tracker = ModuleCacheTracker::new(2);
+        let a = vec![hash(b"a")];
+
+        tracker.observe(&a);
+        // a is seen again, so b should be the one evicted by c
+        tracker.observe(&[hash(b"b")]);
+        tracker.observe(&a);
+        tracker.observe(&[hash(b"c")]);
+
+        let again = tracker.observe(&a);
+        assert_eq!(again.hits, 1);
+        assert_eq!(again.misses, 0);
+    }
+
+    #[test]
+    fn generated_cache_config_is_accepted_by_wasmtime() {
+        let cache_dir = TempDir::new("wasm_cache").unwrap();
+
+        let path = write_cache_config(cache_dir.path(), 512 * 1024 * 1024).unwrap();
+
+        let mut config = wasmtime::Config::new();
+        config
+            .cache_config_load(path)
+            .expect("wasmtime must load the generated cache config");
+    }
+}
@@ -24,8 +24,8 @@ use tokio::task::JoinHandle;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::spell_builtins::{
-    get_spell_arg, get_spell_id, spell_install, spell_list, spell_remove, spell_update_config,
-    store_error, store_response,
+    get_spell_arg, get_spell_id, spell_cancel_delayed, spell_install, spell_list, spell_remove,
+    spell_schedule_delayed, spell_update_config, store_error, store_response,
 };
 use crate::worker_builins::{
     activate_deal, create_worker, deactivate_deal, get_worker_peer_id, is_deal_active,
@@ -59,6 +59,7 @@ pub struct Sorcerer {
     pub spell_service_api: SpellServiceApi,
     pub spell_metrics: Option<SpellMetrics>,
     pub worker_period_sec: u32,
+    pub spell_peer_events_enabled: bool,
 }
 
 impl Sorcerer {
@@ -92,6 +93,7 @@ impl Sorcerer {
             spell_service_api,
             spell_metrics,
             worker_period_sec: config.system_services.decider.worker_period_sec,
+            spell_peer_events_enabled: config.spell_peer_events_enabled,
         };
 
         let mut builtin_functions = sorcerer.make_spell_builtins();
@@ -117,7 +119,7 @@ impl Sorcerer {
                     );
                     let config = self.spell_service_api.get_trigger_config(params).await?;
                     let period = config.clock.period_sec;
-                    let config = from_user_config(&config)?;
+                    let config = from_user_config(&config, self.spell_peer_events_enabled)?;
                     if let Some(config) = config.and_then(|c| c.into_rescheduled()) {
                         self.spell_event_bus_api
                             .subscribe(spell_id.clone(), config)
@@ -187,6 +189,11 @@ impl Sorcerer {
                         "update_trigger_config",
                         self.make_spell_update_config_closure(),
                     ),
+                    (
+                        "schedule_delayed",
+                        self.make_spell_schedule_delayed_closure(),
+                    ),
+                    ("cancel_delayed", self.make_spell_cancel_delayed_closure()),
                 ],
                 None,
             ),
@@ -244,6 +251,7 @@ impl Sorcerer {
         let workers = self.workers.clone();
         let spell_service_api = self.spell_service_api.clone();
         let scope = self.scopes.clone();
+        let peer_events_enabled = self.spell_peer_events_enabled;
         ServiceFunction::Immut(Box::new(move |args, params| {
             let storage = storage.clone();
             let services = services.clone();
@@ -262,6 +270,7 @@ impl Sorcerer {
                         spell_service_api,
                         workers,
                         scope,
+                        peer_events_enabled,
                     )
                     .await,
                 )
@@ -306,6 +315,7 @@ impl Sorcerer {
         let workers = self.workers.clone();
         let scope = self.scopes.clone();
         let spell_service_api = self.spell_service_api.clone();
+        let peer_events_enabled = self.spell_peer_events_enabled;
         ServiceFunction::Immut(Box::new(move |args, params| {
             let spell_event_bus_api = spell_event_bus_api.clone();
             let services = services.clone();
@@ -322,6 +332,7 @@ impl Sorcerer {
                         spell_service_api,
                         workers,
                         scopes,
+                        peer_events_enabled,
                     )
                     .await,
                 )
@@ -330,6 +341,41 @@ impl Sorcerer {
         }))
     }
 
+    fn make_spell_schedule_delayed_closure(&self) -> ServiceFunction {
+        let services = self.services.clone();
+        let spell_event_bus_api = self.spell_event_bus_api.clone();
+        let scopes = self.scopes.clone();
+        ServiceFunction::Immut(Box::new(move |args, params| {
+            let services = services.clone();
+            let spell_event_bus_api = spell_event_bus_api.clone();
+            let scopes = scopes.clone();
+            async move {
+                wrap(
+                    spell_schedule_delayed(args, params, services, spell_event_bus_api, scopes)
+                        .await,
+                )
+            }
+            .boxed()
+        }))
+    }
+
+    fn make_spell_cancel_delayed_closure(&self) -> ServiceFunction {
+        let services = self.services.clone();
+        let spell_event_bus_api = self.spell_event_bus_api.clone();
+        let scopes = self.scopes.clone();
+        ServiceFunction::Immut(Box::new(move |args, params| {
+            let services = services.clone();
+            let spell_event_bus_api = spell_event_bus_api.clone();
+            let scopes = scopes.clone();
+            async move {
+                wrap_unit(
+                    spell_cancel_delayed(args, params, services, spell_event_bus_api, scopes).await,
+                )
+            }
+            .boxed()
+        }))
+    }
+
     fn make_get_spell_id_closure(&self) -> ServiceFunction {
         ServiceFunction::Immut(Box::new(move |_, params| {
             async move { wrap(get_spell_id(params)) }.boxed()
@@ -427,6 +473,7 @@ impl Sorcerer {
         let spell_event_bus_api = self.spell_event_bus_api.clone();
         let spells_api = self.spell_service_api.clone();
         let worker_period_sec = self.worker_period_sec;
+        let peer_events_enabled = self.spell_peer_events_enabled;
         ServiceFunction::Immut(Box::new(move |args, params| {
             let services = services.clone();
             let spell_event_bus_api = spell_event_bus_api.clone();
@@ -444,6 +491,7 @@ impl Sorcerer {
                     spell_event_bus_api,
                     spells_api,
                     worker_period_sec,
+                    peer_events_enabled,
                 )
                 .await;
                 wrap_unit(res)
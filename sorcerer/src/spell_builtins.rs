@@ -67,8 +67,9 @@ pub async fn install_spell(
     script: String,
     init_data: Value,
     owner_id: PeerId,
+    peer_events_enabled: bool,
 ) -> Result<String, JError> {
-    let config = api::from_user_config(&user_config)?;
+    let config = api::from_user_config(&user_config, peer_events_enabled)?;
 
     let spell_id = services
         .create_service(
@@ -166,6 +167,7 @@ pub(crate) async fn spell_install(
     spell_service_api: SpellServiceApi,
     workers: Arc<Workers>,
     scopes: PeerScopes,
+    peer_events_enabled: bool,
 ) -> Result<JValue, JError> {
     let mut args = sargs.function_args.clone().into_iter();
     let script: String = Args::next("script", &mut args)?;
@@ -207,6 +209,7 @@ pub(crate) async fn spell_install(
         script,
         init_data,
         owner_id,
+        peer_events_enabled,
     )
     .await?;
 
@@ -320,6 +323,7 @@ pub(crate) async fn spell_update_config(
     spell_service_api: SpellServiceApi,
     workers: Arc<Workers>,
     scopes: PeerScopes,
+    peer_events_enabled: bool,
 ) -> Result<(), JError> {
     let mut args = args.function_args.into_iter();
     let spell_id_or_alias: String = Args::next("spell_id", &mut args)?;
@@ -356,7 +360,7 @@ pub(crate) async fn spell_update_config(
         .await?;
 
     let user_config: TriggerConfig = Args::next("config", &mut args)?;
-    let config = api::from_user_config(&user_config)?;
+    let config = api::from_user_config(&user_config, peer_events_enabled)?;
     let init_peer_id = scopes.to_peer_id(peer_scope);
     let params = CallParams::local(
         peer_scope,
@@ -391,6 +395,90 @@ pub(crate) async fn spell_update_config(
     Ok(())
 }
 
+/// Allow an operation on a spell's delayed triggers to the spell itself,
+/// its owner or the management peer
+async fn check_delayed_trigger_permission(
+    params: &ParticleParams,
+    services: &ParticleAppServices,
+    scopes: &PeerScopes,
+    spell_id: &str,
+) -> Result<(), JError> {
+    let is_self = ParticleParams::get_spell_id(&params.id).is_some_and(|id| id == spell_id);
+    if is_self {
+        return Ok(());
+    }
+    let owner = services
+        .get_service_owner(params.peer_scope, spell_id.to_string(), &params.id)
+        .await?;
+    let init_peer_id = params.init_peer_id;
+    if init_peer_id != owner && !scopes.is_management(init_peer_id) {
+        return Err(JError::new(format!(
+            "Failed to manage delayed triggers of spell {spell_id}: only the spell itself, its owner {owner} or peer manager is allowed; init_peer_id={init_peer_id}"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) async fn spell_schedule_delayed(
+    args: Args,
+    params: ParticleParams,
+    services: ParticleAppServices,
+    spell_event_bus_api: SpellEventBusApi,
+    scopes: PeerScopes,
+) -> Result<JValue, JError> {
+    let mut args = args.function_args.into_iter();
+    let spell_id_or_alias: String = Args::next("spell_id", &mut args)?;
+    let delay_sec: u32 = Args::next("delay_sec", &mut args)?;
+
+    if delay_sec > api::MAX_PERIOD_SEC {
+        return Err(JError::new(format!(
+            "Failed to schedule a delayed trigger for spell {spell_id_or_alias}: delay {delay_sec}s exceeds the maximum of {}s",
+            api::MAX_PERIOD_SEC
+        )));
+    }
+
+    let spell_id = services
+        .to_service_id(params.peer_scope, spell_id_or_alias.clone(), &params.id)
+        .await?;
+    check_delayed_trigger_permission(&params, &services, &scopes, &spell_id).await?;
+
+    let token = spell_event_bus_api
+        .schedule_delayed(spell_id, Duration::from_secs(delay_sec as u64))
+        .await
+        .map_err(|err| {
+            JError::new(format!(
+                "can't schedule a delayed trigger for spell {spell_id_or_alias} due to an internal error: {err}"
+            ))
+        })?;
+    Ok(JValue::String(token))
+}
+
+pub(crate) async fn spell_cancel_delayed(
+    args: Args,
+    params: ParticleParams,
+    services: ParticleAppServices,
+    spell_event_bus_api: SpellEventBusApi,
+    scopes: PeerScopes,
+) -> Result<(), JError> {
+    let mut args = args.function_args.into_iter();
+    let spell_id_or_alias: String = Args::next("spell_id", &mut args)?;
+    let token: String = Args::next("token", &mut args)?;
+
+    let spell_id = services
+        .to_service_id(params.peer_scope, spell_id_or_alias.clone(), &params.id)
+        .await?;
+    check_delayed_trigger_permission(&params, &services, &scopes, &spell_id).await?;
+
+    spell_event_bus_api
+        .cancel_delayed(spell_id, token)
+        .await
+        .map_err(|err| {
+            JError::new(format!(
+                "can't cancel a delayed trigger of spell {spell_id_or_alias} due to an internal error: {err}"
+            ))
+        })
+}
+
 pub(crate) fn get_spell_id(params: ParticleParams) -> Result<JValue, JError> {
     Ok(json!(parse_spell_id_from(&params)?))
 }
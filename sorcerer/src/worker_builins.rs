@@ -201,6 +201,7 @@ pub(crate) async fn activate_deal(
     spell_event_bus_api: SpellEventBusApi,
     spell_service_api: SpellServiceApi,
     worker_period_sec: u32,
+    peer_events_enabled: bool,
 ) -> Result<(), JError> {
     let mut args = args.function_args.into_iter();
     let deal_id: String = Args::next("deal_id", &mut args)?;
@@ -242,7 +243,7 @@ pub(crate) async fn activate_deal(
         )
         .await?;
 
-    let trigger_config = from_user_config(&worker_config)?.ok_or(JError::new(
+    let trigger_config = from_user_config(&worker_config, peer_events_enabled)?.ok_or(JError::new(
         "Deal activation failed due to failure to parse trigger config",
     ))?;
 